@@ -0,0 +1,1280 @@
+//! Reading of the assembly language into [`Class`]
+
+use super::lexer::{tokenize, Line, Token, Tokens};
+use super::pool::{MemberKind, Pool};
+use super::{flag_names, reference_kind_name};
+use crate::raw::class::Class;
+use crate::raw::*;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Parses assembly `source` into a class, ready to be written with [`Class::to_bytes`]
+pub fn assemble(source: &str) -> std::io::Result<Class> {
+    let lines = tokenize(source)?;
+    let mut assembler = Assembler {
+        lines: &lines,
+        position: 0,
+        pool: Pool::new(),
+    };
+    assembler
+        .class()
+        .map_err(|error| invalid_data(format!("line {}: {}", error.line, error.message)))
+}
+
+struct Error {
+    line: usize,
+    message: String,
+}
+
+/// Attaches line number to errors of operand parsers
+trait At<T> {
+    fn at(self, line: &Line) -> Result<T, Error>;
+}
+
+impl<T> At<T> for Result<T, String> {
+    fn at(self, line: &Line) -> Result<T, Error> {
+        self.map_err(|message| Error {
+            line: line.number,
+            message,
+        })
+    }
+}
+
+/// Attributes of a class, field, method or code in the order of their directives
+#[derive(Default)]
+struct Attributes {
+    list: Vec<Attribute>,
+    /// Attributes that collect entries of all directives of one kind, like `.nestmember`
+    shared: HashMap<&'static str, usize>,
+}
+
+impl Attributes {
+    fn add(&mut self, pool: &mut Pool, name: &str, info: AttributeInfo) -> Result<(), String> {
+        let name_index = pool.utf8(name)?;
+        self.list.push(Attribute { name_index, info });
+        Ok(())
+    }
+
+    /// Attribute called `name` shared by the directives of one kind, created from `empty`
+    /// by the first one
+    fn shared(
+        &mut self,
+        pool: &mut Pool,
+        name: &'static str,
+        empty: AttributeInfo,
+    ) -> Result<&mut AttributeInfo, String> {
+        let index = match self.shared.get(name) {
+            Some(index) => *index,
+            None => {
+                self.add(pool, name, empty)?;
+                self.shared.insert(name, self.list.len() - 1);
+                self.list.len() - 1
+            }
+        };
+        Ok(&mut self.list[index].info)
+    }
+
+    /// Handles directives allowed on classes, fields and methods, returns whether it was
+    /// one of them
+    fn common(&mut self, pool: &mut Pool, tokens: &mut Tokens) -> Result<bool, String> {
+        let Some(directive) = tokens.peek().and_then(Token::word) else {
+            return Ok(false);
+        };
+        match directive {
+            ".signature" => {
+                tokens.next("directive")?;
+                let signature = tokens.next("signature")?.text.clone();
+                pool.utf8(&signature)?;
+                self.add(pool, "Signature", AttributeInfo::Signature(signature))?;
+            }
+            ".deprecated" => {
+                tokens.next("directive")?;
+                self.add(pool, "Deprecated", AttributeInfo::Deprecated)?;
+            }
+            ".synthetic" => {
+                tokens.next("directive")?;
+                self.add(pool, "Synthetic", AttributeInfo::Synthetic)?;
+            }
+            ".attribute" => {
+                tokens.next("directive")?;
+                let name_index = utf8(tokens, pool, "attribute name")?;
+                let mut bytes = vec![];
+                while let Some(token) = tokens.peek() {
+                    tokens.next("hex")?;
+                    bytes.extend(hex(&token.text)?);
+                }
+                self.list.push(Attribute {
+                    name_index,
+                    info: AttributeInfo::Uncrecognized(bytes),
+                });
+            }
+            _ => return Ok(false),
+        }
+        tokens.end()?;
+        Ok(true)
+    }
+}
+
+struct Assembler<'a> {
+    lines: &'a [Line],
+    /// Next line to read
+    position: usize,
+    pool: Pool,
+}
+
+impl<'a> Assembler<'a> {
+    fn next_line(&mut self) -> Option<&'a Line> {
+        let line = self.lines.get(self.position)?;
+        self.position += 1;
+        Some(line)
+    }
+
+    /// First token of the next line, if it is a directive
+    fn peek_directive(&self) -> Option<&'a str> {
+        self.lines
+            .get(self.position)
+            .and_then(|line| line.tokens[0].word())
+            .filter(|word| word.starts_with('.'))
+    }
+
+    fn class(&mut self) -> Result<Class, Error> {
+        // Explicit constants go first, so that symbolic references find them
+        for line in self.lines {
+            if line.tokens[0].word() == Some(".const") {
+                let mut tokens = Tokens::new(&line.tokens[1..]);
+                define_constant(&mut tokens, &mut self.pool).at(line)?;
+            }
+        }
+
+        let mut class = Class {
+            major: 49,
+            minor: 0,
+            ..Class::default()
+        };
+        let mut name = None;
+        let mut super_class = None;
+        let mut attributes = Attributes::default();
+        let mut bootstrap_count = 0;
+
+        while let Some(line) = self.next_line() {
+            let mut tokens = Tokens::new(&line.tokens);
+            let pool = &mut self.pool;
+            if attributes.common(pool, &mut tokens).at(line)? {
+                continue;
+            }
+            let directive = tokens.next("directive").at(line)?;
+            match directive.word().unwrap_or_default() {
+                ".const" => continue,
+                ".version" => {
+                    class.major = integer(tokens.next("major version").at(line)?).at(line)?;
+                    class.minor = integer(tokens.next("minor version").at(line)?).at(line)?;
+                }
+                ".class" => {
+                    let (flags, last) = line.tokens[1..]
+                        .split_last_chunk::<1>()
+                        .ok_or("expected class name")
+                        .map_err(str::to_string)
+                        .at(line)?;
+                    class.access_info = access_flags(flags, FlagsOwner::Class).at(line)?;
+                    let mut tokens = Tokens::new(last);
+                    name = Some(self::class(&mut tokens, pool).at(line)?);
+                    continue;
+                }
+                ".super" => super_class = Some(self::class(&mut tokens, pool).at(line)?),
+                ".implements" => class
+                    .interfaces
+                    .push(self::class(&mut tokens, pool).at(line)?),
+                ".source" => {
+                    let source = tokens.next("source file name").at(line)?.text.clone();
+                    pool.utf8(&source).at(line)?;
+                    attributes
+                        .add(pool, "SourceFile", AttributeInfo::SourceFile(source))
+                        .at(line)?;
+                }
+                ".nesthost" => {
+                    let host = self::class(&mut tokens, pool).at(line)?;
+                    attributes
+                        .add(pool, "NestHost", AttributeInfo::NestHost(host))
+                        .at(line)?;
+                }
+                ".nestmember" => {
+                    let member = self::class(&mut tokens, pool).at(line)?;
+                    let empty = AttributeInfo::NestMembers(vec![]);
+                    if let AttributeInfo::NestMembers(members) =
+                        attributes.shared(pool, "NestMembers", empty).at(line)?
+                    {
+                        members.push(member);
+                    }
+                }
+                ".permittedsubclass" => {
+                    let subclass = self::class(&mut tokens, pool).at(line)?;
+                    let empty = AttributeInfo::PermittedSubclasses(vec![]);
+                    if let AttributeInfo::PermittedSubclasses(subclasses) = attributes
+                        .shared(pool, "PermittedSubclasses", empty)
+                        .at(line)?
+                    {
+                        subclasses.push(subclass);
+                    }
+                }
+                ".enclosingmethod" => {
+                    let class_index = self::class(&mut tokens, pool).at(line)?;
+                    let method_index = name_and_type(&mut tokens, pool).at(line)?;
+                    let info = AttributeInfo::EnclosingMethod {
+                        class_index,
+                        method_index,
+                    };
+                    attributes.add(pool, "EnclosingMethod", info).at(line)?;
+                }
+                ".innerclass" => {
+                    let inner_class = inner_class(&line.tokens[1..], pool).at(line)?;
+                    let empty = AttributeInfo::InnerClasses(vec![]);
+                    if let AttributeInfo::InnerClasses(classes) =
+                        attributes.shared(pool, "InnerClasses", empty).at(line)?
+                    {
+                        classes.push(inner_class);
+                    }
+                    continue;
+                }
+                ".bootstrap" => {
+                    let index: u16 =
+                        integer(tokens.next("bootstrap method index").at(line)?).at(line)?;
+                    if index != bootstrap_count {
+                        return Err(format!(
+                            "bootstrap methods must be numbered in order, expected {bootstrap_count}"
+                        ))
+                        .at(line);
+                    }
+                    bootstrap_count += 1;
+                    let method = bootstrap_method(&mut tokens, pool).at(line)?;
+                    let empty = AttributeInfo::BootstrapMethods(vec![]);
+                    if let AttributeInfo::BootstrapMethods(methods) = attributes
+                        .shared(pool, "BootstrapMethods", empty)
+                        .at(line)?
+                    {
+                        methods.push(method);
+                    }
+                }
+                ".field" => {
+                    let field = self.field(line)?;
+                    class.fields.push(field);
+                    continue;
+                }
+                ".method" => {
+                    let method = self.method(line)?;
+                    class.methods.push(method);
+                    continue;
+                }
+                _ => return Err(format!("unexpected `{}`", directive.text)).at(line),
+            }
+            tokens.end().at(line)?;
+        }
+
+        let end = Line {
+            number: self.lines.last().map_or(1, |line| line.number),
+            tokens: vec![],
+        };
+        class.this_class = name.ok_or("missing .class".to_string()).at(&end)?;
+        class.super_class = match super_class {
+            Some(index) => index,
+            None => self.pool.class("java/lang/Object").at(&end)?,
+        };
+        class.attributes = attributes.list;
+        class.constant_pool = std::mem::replace(&mut self.pool, Pool::new())
+            .finish()
+            .at(&end)?;
+        Ok(class)
+    }
+
+    fn field(&mut self, line: &Line) -> Result<FieldInfo, Error> {
+        let tokens = &line.tokens[1..];
+        let equals = tokens
+            .iter()
+            .position(|token| token.word() == Some("="))
+            .unwrap_or(tokens.len());
+        let Some((flags, [name, descriptor])) = tokens[..equals].split_last_chunk::<2>() else {
+            return Err("expected field name and descriptor".to_string()).at(line);
+        };
+
+        let pool = &mut self.pool;
+        let mut attributes = Attributes::default();
+        let access_flags = access_flags(flags, FlagsOwner::Field).at(line)?;
+        let name_index =
+            utf8(&mut Tokens::new(std::slice::from_ref(name)), pool, "name").at(line)?;
+        let descriptor_index = utf8(
+            &mut Tokens::new(std::slice::from_ref(descriptor)),
+            pool,
+            "descriptor",
+        )
+        .at(line)?;
+        if equals < tokens.len() {
+            let mut value = Tokens::new(&tokens[equals + 1..]);
+            let index = constant(&mut value, pool).at(line)?;
+            value.end().at(line)?;
+            attributes
+                .add(pool, "ConstantValue", AttributeInfo::ConstantValue(index))
+                .at(line)?;
+        }
+
+        // Attribute directives right after the field belong to it
+        let mut has_attributes = false;
+        while let Some(".signature" | ".deprecated" | ".synthetic" | ".attribute") =
+            self.peek_directive()
+        {
+            let line = self.next_line().expect("peeked line exists");
+            let mut tokens = Tokens::new(&line.tokens);
+            attributes.common(&mut self.pool, &mut tokens).at(line)?;
+            has_attributes = true;
+        }
+        if self.peek_directive() == Some(".end") {
+            let line = self.next_line().expect("peeked line exists");
+            let mut tokens = Tokens::new(&line.tokens[1..]);
+            tokens.expect("field").at(line)?;
+            tokens.end().at(line)?;
+        } else if has_attributes {
+            return Err("expected `.end field` after field attributes".to_string()).at(line);
+        }
+
+        Ok(FieldInfo {
+            access_flags,
+            name_index,
+            descriptor_index,
+            attributes: attributes.list,
+        })
+    }
+
+    fn method(&mut self, header: &Line) -> Result<MethodInfo, Error> {
+        let tokens = &header.tokens[1..];
+        let pool = &mut self.pool;
+        let (flags, name_index, descriptor_index) = match tokens.split_last() {
+            Some((last, flags)) if last.word().is_some_and(|word| word.contains('(')) => {
+                let (name, descriptor) = split_name_and_descriptor(&last.text).at(header)?;
+                (
+                    flags,
+                    pool.utf8(name).at(header)?,
+                    pool.utf8(descriptor).at(header)?,
+                )
+            }
+            _ => {
+                let Some((flags, [name, descriptor])) = tokens.split_last_chunk::<2>() else {
+                    return Err("expected method name and descriptor".to_string()).at(header);
+                };
+                let name = utf8(&mut Tokens::new(std::slice::from_ref(name)), pool, "name");
+                let descriptor = utf8(
+                    &mut Tokens::new(std::slice::from_ref(descriptor)),
+                    pool,
+                    "descriptor",
+                );
+                (flags, name.at(header)?, descriptor.at(header)?)
+            }
+        };
+        let access_flags = access_flags(flags, FlagsOwner::Method).at(header)?;
+
+        let mut attributes = Attributes::default();
+        loop {
+            let Some(line) = self.next_line() else {
+                return Err("missing `.end method`".to_string()).at(header);
+            };
+            let mut tokens = Tokens::new(&line.tokens);
+            if attributes.common(&mut self.pool, &mut tokens).at(line)? {
+                continue;
+            }
+            match tokens
+                .next("directive")
+                .at(line)?
+                .word()
+                .unwrap_or_default()
+            {
+                ".end" => {
+                    tokens.expect("method").at(line)?;
+                    tokens.end().at(line)?;
+                    break;
+                }
+                ".throws" => {
+                    let exception = class(&mut tokens, &mut self.pool).at(line)?;
+                    tokens.end().at(line)?;
+                    let empty = AttributeInfo::Exceptions(vec![]);
+                    if let AttributeInfo::Exceptions(exceptions) = attributes
+                        .shared(&mut self.pool, "Exceptions", empty)
+                        .at(line)?
+                    {
+                        exceptions.push(exception);
+                    }
+                }
+                ".code" => {
+                    let code = self.code(line)?;
+                    attributes.add(&mut self.pool, "Code", code).at(line)?;
+                }
+                _ => {
+                    return Err(format!(
+                        "unexpected `{}` in method, instructions belong inside `.code`",
+                        line.tokens[0].text
+                    ))
+                    .at(line)
+                }
+            }
+        }
+
+        Ok(MethodInfo {
+            access_flags,
+            name_index,
+            descriptor_index,
+            attributes: attributes.list,
+        })
+    }
+
+    fn code(&mut self, header: &Line) -> Result<AttributeInfo, Error> {
+        let mut tokens = Tokens::new(&header.tokens[1..]);
+        tokens.expect("stack").at(header)?;
+        let max_stack = integer(tokens.next("max stack").at(header)?).at(header)?;
+        tokens.expect("locals").at(header)?;
+        let max_locals = integer(tokens.next("max locals").at(header)?).at(header)?;
+        tokens.end().at(header)?;
+
+        let start = self.position;
+        loop {
+            let Some(line) = self.next_line() else {
+                return Err("missing `.end code`".to_string()).at(header);
+            };
+            if line.tokens[0].word() == Some(".end") {
+                let mut tokens = Tokens::new(&line.tokens[1..]);
+                tokens.expect("code").at(line)?;
+                tokens.end().at(line)?;
+                break;
+            }
+        }
+        let body = &self.lines[start..self.position - 1];
+
+        // First pass finds offsets of labels, instructions do not change length with targets
+        let mut labels = HashMap::new();
+        let mut offset = 0u32;
+        for line in body {
+            let tokens = label(line, offset, Some(&mut labels))?;
+            if tokens.first().is_some_and(|token| !is_directive(token)) {
+                let instruction =
+                    instruction(&mut Tokens::new(tokens), &mut self.pool, offset, None).at(line)?;
+                offset += instruction.length(offset);
+            }
+        }
+
+        let mut code = vec![];
+        let mut exception_table = vec![];
+        let mut line_numbers = vec![];
+        let mut local_variables = vec![];
+        let mut local_variable_types = vec![];
+        let mut frames: Vec<(u32, StackMapFrame)> = vec![];
+        let mut raw_attributes = Attributes::default();
+        let mut offset = 0u32;
+        for line in body {
+            let pool = &mut self.pool;
+            let tokens = label(line, offset, None)?;
+            let mut tokens = Tokens::new(tokens);
+            let Some(first) = tokens.peek() else {
+                continue;
+            };
+            if !is_directive(first) {
+                let instruction = instruction(&mut tokens, pool, offset, Some(&labels)).at(line)?;
+                let length = instruction.length(offset);
+                code.push((offset, instruction));
+                offset += length;
+                continue;
+            }
+            if raw_attributes.common(pool, &mut tokens).at(line)? {
+                continue;
+            }
+            let labels = &labels;
+            match tokens.next("directive").at(line)?.text.as_str() {
+                ".line" => line_numbers.push(LineNumber {
+                    start_pc: pc(offset).at(line)?,
+                    line_number: integer(tokens.next("line number").at(line)?).at(line)?,
+                }),
+                ".stack" => frames.push((offset, frame(&mut tokens, pool, labels).at(line)?)),
+                ".catch" => {
+                    let catch_type = match tokens.eat("any") {
+                        true => 0,
+                        false => class(&mut tokens, pool).at(line)?,
+                    };
+                    tokens.expect("from").at(line)?;
+                    let start_pc = position(&mut tokens, labels).at(line)?;
+                    tokens.expect("to").at(line)?;
+                    let end_pc = position(&mut tokens, labels).at(line)?;
+                    tokens.expect("using").at(line)?;
+                    let handler_pc = position(&mut tokens, labels).at(line)?;
+                    exception_table.push(Exception {
+                        start_pc,
+                        end_pc,
+                        handler_pc,
+                        catch_type,
+                    });
+                }
+                directive @ (".var" | ".vartype") => {
+                    let index = integer(tokens.next("local variable index").at(line)?).at(line)?;
+                    tokens.expect("is").at(line)?;
+                    let name_index = utf8(&mut tokens, pool, "name").at(line)?;
+                    let descriptor_index = utf8(&mut tokens, pool, "descriptor").at(line)?;
+                    tokens.expect("from").at(line)?;
+                    let start_pc = position(&mut tokens, labels).at(line)?;
+                    tokens.expect("to").at(line)?;
+                    let end_pc = position(&mut tokens, labels).at(line)?;
+                    let length = end_pc
+                        .checked_sub(start_pc)
+                        .ok_or("local variable ends before it starts".to_string())
+                        .at(line)?;
+                    let variable = LocalVariable {
+                        start_pc,
+                        length,
+                        name_index,
+                        descriptor_index,
+                        index,
+                    };
+                    if directive == ".var" {
+                        local_variables.push(variable);
+                    } else {
+                        local_variable_types.push(variable);
+                    }
+                }
+                other => return Err(format!("unexpected `{other}` in code")).at(line),
+            }
+            tokens.end().at(line)?;
+        }
+
+        let pool = &mut self.pool;
+        let mut attributes = Attributes::default();
+        if !line_numbers.is_empty() {
+            let info = AttributeInfo::LineNumberTable(line_numbers);
+            attributes.add(pool, "LineNumberTable", info).at(header)?;
+        }
+        if !local_variables.is_empty() {
+            let info = AttributeInfo::LocalVariableTable(local_variables);
+            attributes
+                .add(pool, "LocalVariableTable", info)
+                .at(header)?;
+        }
+        if !local_variable_types.is_empty() {
+            let info = AttributeInfo::LocalVariableTypeTable(local_variable_types);
+            attributes
+                .add(pool, "LocalVariableTypeTable", info)
+                .at(header)?;
+        }
+        if !frames.is_empty() {
+            let info = AttributeInfo::StackMapTable(stack_map_table(frames).at(header)?);
+            attributes.add(pool, "StackMapTable", info).at(header)?;
+        }
+        attributes.list.extend(raw_attributes.list);
+
+        Ok(AttributeInfo::Code {
+            max_stack,
+            max_locals,
+            code,
+            exception_table,
+            attributes: attributes.list,
+        })
+    }
+}
+
+fn is_directive(token: &Token) -> bool {
+    token.word().is_some_and(|word| word.starts_with('.'))
+}
+
+/// Strips label definition from the start of the line, recording it when `labels` are given
+fn label<'a>(
+    line: &'a Line,
+    offset: u32,
+    labels: Option<&mut HashMap<String, u32>>,
+) -> Result<&'a [Token], Error> {
+    let Some(name) = line.tokens[0]
+        .word()
+        .and_then(|word| word.strip_suffix(':'))
+    else {
+        return Ok(&line.tokens);
+    };
+    if let Some(labels) = labels {
+        if name.is_empty() || name.parse::<i64>().is_ok() {
+            return Err(format!("invalid label name `{name}`")).at(line);
+        }
+        if labels.insert(name.to_string(), offset).is_some() {
+            return Err(format!("label `{name}` is defined twice")).at(line);
+        }
+    }
+    Ok(&line.tokens[1..])
+}
+
+fn pc(offset: u32) -> Result<u16, String> {
+    u16::try_from(offset).map_err(|_| format!("offset {offset} does not fit in u2"))
+}
+
+/// Code offset given by label or number
+fn position(tokens: &mut Tokens, labels: &HashMap<String, u32>) -> Result<u16, String> {
+    let token = tokens.next("label")?;
+    match labels.get(&token.text) {
+        Some(offset) => pc(*offset),
+        None => integer(token).map_err(|_| format!("undefined label `{}`", token.text)),
+    }
+}
+
+/// Relative branch offset to the label or number. Labels are not known yet in the first pass.
+fn target(
+    tokens: &mut Tokens,
+    offset: u32,
+    labels: Option<&HashMap<String, u32>>,
+) -> Result<i64, String> {
+    let token = tokens.next("branch target")?;
+    if let Ok(relative) = integer::<i64>(token) {
+        return Ok(relative);
+    }
+    let Some(labels) = labels else {
+        return Ok(0);
+    };
+    match labels.get(&token.text) {
+        Some(target) => Ok(*target as i64 - offset as i64),
+        None => Err(format!("undefined label `{}`", token.text)),
+    }
+}
+
+fn access_flags(tokens: &[Token], owner: FlagsOwner) -> Result<AccessFlags, String> {
+    let mut bits = 0;
+    for token in tokens {
+        let word = token.word().unwrap_or_default();
+        let bit = match flag_names(owner).iter().find(|(_, name)| *name == word) {
+            Some((bit, _)) => *bit,
+            None if word.starts_with("0x") => integer(token)?,
+            None => return Err(format!("unknown flag `{}`", token.text)),
+        };
+        bits |= bit;
+    }
+    Ok(AccessFlags::from(bits))
+}
+
+/// Parses decimal or `0x` hexadecimal integer that fits in `T`
+fn integer<T: TryFrom<i128>>(token: &Token) -> Result<T, String> {
+    let text = token
+        .word()
+        .ok_or_else(|| format!("expected number, found {:?}", token.text))?;
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = match digits.strip_prefix("0x") {
+        _ if digits.starts_with(['-', '+']) => None,
+        Some(hex) => i128::from_str_radix(hex, 16).ok(),
+        None => digits.parse::<i128>().ok(),
+    }
+    .ok_or_else(|| format!("expected number, found `{text}`"))?;
+    let value = if negative { -value } else { value };
+    T::try_from(value).map_err(|_| format!("{text} is out of range"))
+}
+
+fn hex(text: &str) -> Result<Vec<u8>, String> {
+    if !text.len().is_multiple_of(2) {
+        return Err(format!("odd number of hex digits in `{text}`"));
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&text[i..i + 2], 16).map_err(|_| format!("invalid hex `{text}`"))
+        })
+        .collect()
+}
+
+/// `#index` written instead of a symbolic reference
+fn raw_index(token: &Token) -> Result<Option<u16>, String> {
+    match token.word().and_then(|word| word.strip_prefix('#')) {
+        Some(index) => index
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("invalid constant index `{}`", token.text)),
+        None => Ok(None),
+    }
+}
+
+fn required_index(tokens: &mut Tokens) -> Result<u16, String> {
+    raw_index(tokens.next("#index")?)?.ok_or_else(|| "expected #index".to_string())
+}
+
+pub(super) fn utf8(tokens: &mut Tokens, pool: &mut Pool, expected: &str) -> Result<u16, String> {
+    let token = tokens.next(expected)?;
+    match raw_index(token)? {
+        Some(index) => Ok(index),
+        None => pool.utf8(&token.text),
+    }
+}
+
+pub(super) fn class(tokens: &mut Tokens, pool: &mut Pool) -> Result<u16, String> {
+    let token = tokens.next("class name")?;
+    match raw_index(token)? {
+        Some(index) => Ok(index),
+        None => pool.class(&token.text),
+    }
+}
+
+/// Splits `name(descriptor)` into the name and the descriptor
+fn split_name_and_descriptor(text: &str) -> Result<(&str, &str), String> {
+    match text.find('(') {
+        Some(open) => Ok(text.split_at(open)),
+        None => Err(format!("expected name(descriptor), found `{text}`")),
+    }
+}
+
+/// NameAndType written as `name(descriptor)`
+pub(super) fn name_and_type(tokens: &mut Tokens, pool: &mut Pool) -> Result<u16, String> {
+    let token = tokens.next("name(descriptor)")?;
+    if let Some(index) = raw_index(token)? {
+        return Ok(index);
+    }
+    let text = token.word().ok_or("member reference cannot be quoted")?;
+    let (name, descriptor) = split_name_and_descriptor(text)?;
+    pool.name_and_type(name, descriptor)
+}
+
+/// Field reference written as `Class.name descriptor`
+pub(super) fn field(tokens: &mut Tokens, pool: &mut Pool) -> Result<u16, String> {
+    let token = tokens.next("field")?;
+    if let Some(index) = raw_index(token)? {
+        return Ok(index);
+    }
+    let text = token.word().ok_or("member reference cannot be quoted")?;
+    let (class, name) = text
+        .rsplit_once('.')
+        .ok_or_else(|| format!("expected Class.name, found `{text}`"))?;
+    let descriptor = &tokens.next("field descriptor")?.text;
+    let class_index = pool.class(class)?;
+    let name_and_type_index = pool.name_and_type(name, descriptor)?;
+    pool.member(MemberKind::Field, class_index, name_and_type_index)
+}
+
+/// Method reference written as `Class.name(descriptor)`, optionally prefixed with `interface`
+pub(super) fn method(
+    tokens: &mut Tokens,
+    pool: &mut Pool,
+    kind: MemberKind,
+) -> Result<u16, String> {
+    let kind = match tokens.eat("interface") {
+        true => MemberKind::InterfaceMethod,
+        false => kind,
+    };
+    let token = tokens.next("method")?;
+    if let Some(index) = raw_index(token)? {
+        return Ok(index);
+    }
+    let text = token.word().ok_or("member reference cannot be quoted")?;
+    let (owner, descriptor) = split_name_and_descriptor(text)?;
+    let (class, name) = owner
+        .rsplit_once('.')
+        .ok_or_else(|| format!("expected Class.name(descriptor), found `{text}`"))?;
+    let class_index = pool.class(class)?;
+    let name_and_type_index = pool.name_and_type(name, descriptor)?;
+    pool.member(kind, class_index, name_and_type_index)
+}
+
+/// Method handle written as `<kind> <member>`, returns the kind and the member reference
+pub(super) fn handle(tokens: &mut Tokens, pool: &mut Pool) -> Result<(u8, u16), String> {
+    let token = tokens.next("reference kind")?;
+    let kind = match (1..=9).find(|kind| reference_kind_name(*kind) == token.word()) {
+        Some(kind) => kind,
+        None => integer(token)?,
+    };
+    let reference = match kind {
+        1..=4 => field(tokens, pool)?,
+        9 => method(tokens, pool, MemberKind::InterfaceMethod)?,
+        _ => method(tokens, pool, MemberKind::Method)?,
+    };
+    Ok((kind, reference))
+}
+
+/// Loadable constant, as used by `ldc`, ConstantValue and bootstrap arguments
+pub(super) fn constant(tokens: &mut Tokens, pool: &mut Pool) -> Result<u16, String> {
+    let token = tokens.next("constant")?;
+    if token.quoted {
+        return pool.string(token.utf8());
+    }
+    if let Some(index) = raw_index(token)? {
+        return Ok(index);
+    }
+    match token.text.as_str() {
+        "class" => class(tokens, pool),
+        "methodtype" => {
+            let descriptor_index = utf8(tokens, pool, "method descriptor")?;
+            pool.add(ConstantPoolInfo::MethodType { descriptor_index })
+        }
+        "methodhandle" => {
+            let (reference_kind, reference_index) = handle(tokens, pool)?;
+            pool.add(ConstantPoolInfo::MethodHandle {
+                reference_kind,
+                reference_index,
+            })
+        }
+        "dynamic" => {
+            let bootstrap_method_attr_index = integer(tokens.next("bootstrap method index")?)?;
+            let name = tokens.next("name")?.text.clone();
+            let descriptor = &tokens.next("descriptor")?.text;
+            let name_and_type_index = pool.name_and_type(&name, descriptor)?;
+            pool.add(ConstantPoolInfo::Dynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            })
+        }
+        text => pool.add(number(text)?),
+    }
+}
+
+/// Numeric constant: `5`, `0x1f`, `5L`, `1.5f`, `1.5d`, `1.5`, `NaNf`, `-Infinityd`
+fn number(text: &str) -> Result<ConstantPoolInfo, String> {
+    let invalid = || format!("invalid constant `{text}`");
+    let token = |text: &str| Token {
+        text: text.to_string(),
+        quoted: false,
+        units: None,
+    };
+    let unsigned = text.trim_start_matches(['-', '+']);
+    if unsigned.starts_with("0x") || unsigned.starts_with(|c: char| c.is_ascii_digit()) {
+        if let Some(long) = text.strip_suffix(['L', 'l']) {
+            return Ok(ConstantPoolInfo::Long(integer(&token(long))?));
+        }
+        if let Ok(value) = integer::<i64>(&token(text)) {
+            // Hexadecimal ints may be written as their unsigned bits
+            return match i32::try_from(value) {
+                Ok(value) => Ok(ConstantPoolInfo::Integer(value)),
+                Err(_) if unsigned.starts_with("0x") && value <= u32::MAX as i64 => {
+                    Ok(ConstantPoolInfo::Integer(value as u32 as i32))
+                }
+                Err(_) => Err(format!("{text} is out of range of int, use {text}L")),
+            };
+        }
+    }
+    if let Some(float) = text.strip_suffix(['f', 'F']) {
+        // Parsed as f32 directly, rounding through f64 could differ in the last bit
+        return float_value(float)
+            .and_then(|_| float.parse::<f32>().ok())
+            .map(ConstantPoolInfo::Float)
+            .ok_or_else(invalid);
+    }
+    let double = text.strip_suffix(['d', 'D']).unwrap_or(text);
+    float_value(double)
+        .map(ConstantPoolInfo::Double)
+        .ok_or_else(invalid)
+}
+
+/// Decimal floating point number, including `NaN` and `Infinity`
+fn float_value(text: &str) -> Option<f64> {
+    match text {
+        "NaN" => Some(f64::NAN),
+        "Infinity" | "+Infinity" => Some(f64::INFINITY),
+        "-Infinity" => Some(f64::NEG_INFINITY),
+        _ if text.contains(|c: char| c.is_ascii_alphabetic() && !matches!(c, 'e' | 'E')) => None,
+        _ => text.parse().ok(),
+    }
+}
+
+/// `.const #<index> = <tag> <operands>`
+fn define_constant(tokens: &mut Tokens, pool: &mut Pool) -> Result<(), String> {
+    let index = required_index(tokens)?;
+    tokens.expect("=")?;
+    let tag = tokens.next("constant tag")?;
+    let literal = |tokens: &mut Tokens, expected: &str| {
+        let text = &tokens.next("value")?.text;
+        // NaN with a non-standard payload is written as its bits
+        let bits = text.strip_prefix("0x").map(|bits| {
+            u64::from_str_radix(bits, 16).map_err(|_| format!("invalid bits `{text}`"))
+        });
+        let constant = match (expected, bits) {
+            ("Float", Some(bits)) => ConstantPoolInfo::Float(f32::from_bits(bits? as u32)),
+            ("Double", Some(bits)) => ConstantPoolInfo::Double(f64::from_bits(bits?)),
+            _ => number(text)?,
+        };
+        match constant.tag_name() == expected {
+            true => Ok(constant),
+            false => Err(format!("expected {expected} value")),
+        }
+    };
+    let constant = match tag.word().unwrap_or_default() {
+        "Utf8" => tokens.next("string")?.utf8(),
+        tag @ ("Integer" | "Float" | "Long" | "Double") => literal(tokens, tag)?,
+        "Class" => ConstantPoolInfo::Class {
+            name_index: required_index(tokens)?,
+        },
+        "String" => ConstantPoolInfo::String {
+            string_index: required_index(tokens)?,
+        },
+        "MethodType" => ConstantPoolInfo::MethodType {
+            descriptor_index: required_index(tokens)?,
+        },
+        "Module" => ConstantPoolInfo::Module {
+            name_index: required_index(tokens)?,
+        },
+        "Package" => ConstantPoolInfo::Package {
+            name_index: required_index(tokens)?,
+        },
+        "Fieldref" => ConstantPoolInfo::FieldRef {
+            class_index: required_index(tokens)?,
+            name_and_type_index: required_index(tokens)?,
+        },
+        "Methodref" => ConstantPoolInfo::MethodRef {
+            class_index: required_index(tokens)?,
+            name_and_type_index: required_index(tokens)?,
+        },
+        "InterfaceMethodref" => ConstantPoolInfo::InterfaceMethodRef {
+            class_index: required_index(tokens)?,
+            name_and_type_index: required_index(tokens)?,
+        },
+        "NameAndType" => ConstantPoolInfo::NameAndType {
+            name_index: required_index(tokens)?,
+            descriptor_index: required_index(tokens)?,
+        },
+        "MethodHandle" => {
+            let token = tokens.next("reference kind")?;
+            ConstantPoolInfo::MethodHandle {
+                reference_kind: match (1..=9)
+                    .find(|kind| reference_kind_name(*kind) == token.word())
+                {
+                    Some(kind) => kind,
+                    None => integer(token)?,
+                },
+                reference_index: required_index(tokens)?,
+            }
+        }
+        "Dynamic" => ConstantPoolInfo::Dynamic {
+            bootstrap_method_attr_index: integer(tokens.next("bootstrap method index")?)?,
+            name_and_type_index: required_index(tokens)?,
+        },
+        "InvokeDynamic" => ConstantPoolInfo::InvokeDynamic {
+            bootstrap_method_attr_index: integer(tokens.next("bootstrap method index")?)?,
+            name_and_type_index: required_index(tokens)?,
+        },
+        _ => return Err(format!("unknown constant tag `{}`", tag.text)),
+    };
+    tokens.end()?;
+    pool.define(index, constant)
+}
+
+/// `.bootstrap` after the index: method handle followed by static arguments
+fn bootstrap_method(tokens: &mut Tokens, pool: &mut Pool) -> Result<BootstrapMethod, String> {
+    let bootstrap_method_ref = match tokens.peek().map(raw_index).transpose()?.flatten() {
+        Some(index) => {
+            tokens.next("method handle")?;
+            index
+        }
+        None => {
+            let (reference_kind, reference_index) = handle(tokens, pool)?;
+            pool.add(ConstantPoolInfo::MethodHandle {
+                reference_kind,
+                reference_index,
+            })?
+        }
+    };
+    let mut bootstrap_arguments = vec![];
+    while tokens.remaining() > 0 {
+        bootstrap_arguments.push(constant(tokens, pool)?);
+    }
+    Ok(BootstrapMethod {
+        bootstrap_method_ref,
+        bootstrap_arguments,
+    })
+}
+
+/// `.innerclass` operands: flags followed by inner class, outer class and simple name
+fn inner_class(tokens: &[Token], pool: &mut Pool) -> Result<InnerClass, String> {
+    let Some((flags, [inner, outer, name])) = tokens.split_last_chunk::<3>() else {
+        return Err("expected inner class, outer class and name".to_string());
+    };
+    Ok(InnerClass {
+        inner_class_access_flags: access_flags(flags, FlagsOwner::InnerClass)?,
+        inner_class_info_index: class(&mut Tokens::new(std::slice::from_ref(inner)), pool)?,
+        outer_class_info_index: class(&mut Tokens::new(std::slice::from_ref(outer)), pool)?,
+        inner_name_index: utf8(&mut Tokens::new(std::slice::from_ref(name)), pool, "name")?,
+    })
+}
+
+/// Opcodes by mnemonic, found by decoding every opcode followed by zeros
+fn opcodes() -> &'static HashMap<&'static str, u8> {
+    static OPCODES: OnceLock<HashMap<&'static str, u8>> = OnceLock::new();
+    OPCODES.get_or_init(|| {
+        (0..=u8::MAX)
+            .filter_map(|opcode| {
+                let mut bytes = [0u8; 32];
+                bytes[0] = opcode;
+                let instruction = Instruction::from(&mut bytes.as_slice(), 0).ok()?;
+                Some((instruction.mnemonic(), opcode))
+            })
+            .collect()
+    })
+}
+
+/// Parses instruction at `offset`. Without `labels` branches to labels get offset 0,
+/// which is enough to know the length of the instruction.
+pub(super) fn instruction(
+    tokens: &mut Tokens,
+    pool: &mut Pool,
+    offset: u32,
+    labels: Option<&HashMap<String, u32>>,
+) -> Result<Instruction, String> {
+    let mnemonic = tokens.next("instruction")?;
+    let name = mnemonic.word().unwrap_or_default();
+    let mut bytes = vec![];
+    if name == "wide" {
+        let modified = tokens.next("instruction")?;
+        let opcode = opcodes()
+            .get(modified.word().unwrap_or_default())
+            .filter(|opcode| matches!(opcode, 0x15..=0x19 | 0x36..=0x3a | 0x84 | 0xa9))
+            .ok_or_else(|| format!("`{}` cannot be wide", modified.text))?;
+        bytes.put_u1(0xc4);
+        bytes.put_u1(*opcode);
+        bytes.put_u2(integer(tokens.next("local variable index")?)?);
+        if *opcode == 0x84 {
+            bytes.put_u2(integer::<i16>(tokens.next("increment")?)? as u16);
+        }
+    } else {
+        let opcode = *opcodes()
+            .get(name)
+            .ok_or_else(|| format!("unknown instruction `{}`", mnemonic.text))?;
+        let mut template = [0u8; 32];
+        template[0] = opcode;
+        let template = Instruction::from(&mut template.as_slice(), offset)
+            .map_err(|error| error.to_string())?;
+
+        bytes.put_u1(opcode);
+        let padding = (3 - offset % 4) as usize;
+        match template {
+            Instruction::BiPush(_) => bytes.put_u1(integer::<i8>(tokens.next("value")?)? as u8),
+            Instruction::SiPush(_) => bytes.put_u2(integer::<i16>(tokens.next("value")?)? as u16),
+            Instruction::NewArray(_) => {
+                let token = tokens.next("array type")?;
+                let atype = (4..=11)
+                    .find(|atype| array_type_name(*atype) == token.word())
+                    .map_or_else(|| integer(token), Ok)?;
+                bytes.put_u1(atype);
+            }
+            Instruction::IInc(..) => {
+                bytes.put_u1(integer(tokens.next("local variable index")?)?);
+                bytes.put_u1(integer::<i8>(tokens.next("increment")?)? as u8);
+            }
+            Instruction::Ldc(_) => {
+                let index = constant(tokens, pool)?;
+                let index = u8::try_from(index)
+                    .map_err(|_| format!("constant #{index} does not fit in ldc, use ldc_w"))?;
+                bytes.put_u1(index);
+            }
+            Instruction::LdcW(_) | Instruction::Ldc2W(_) => bytes.put_u2(constant(tokens, pool)?),
+            Instruction::GetStatic(_)
+            | Instruction::PutStatic(_)
+            | Instruction::GetField(_)
+            | Instruction::PutField(_) => bytes.put_u2(field(tokens, pool)?),
+            Instruction::InvokeVirtual(_)
+            | Instruction::InvokeSpecial(_)
+            | Instruction::InvokeStatic(_) => {
+                bytes.put_u2(method(tokens, pool, MemberKind::Method)?)
+            }
+            Instruction::InvokeInterface(..) => {
+                bytes.put_u2(method(tokens, pool, MemberKind::InterfaceMethod)?);
+                bytes.put_u1(integer(tokens.next("argument count")?)?);
+                bytes.put_u1(0);
+            }
+            Instruction::InvokeDynamic(_) => {
+                let index = match tokens.peek().map(raw_index).transpose()?.flatten() {
+                    Some(index) => {
+                        tokens.next("constant")?;
+                        index
+                    }
+                    None => {
+                        let bootstrap_method_attr_index =
+                            integer(tokens.next("bootstrap method index")?)?;
+                        let name_and_type_index = name_and_type(tokens, pool)?;
+                        pool.add(ConstantPoolInfo::InvokeDynamic {
+                            bootstrap_method_attr_index,
+                            name_and_type_index,
+                        })?
+                    }
+                };
+                bytes.put_u2(index);
+                bytes.put_u2(0);
+            }
+            Instruction::New(_)
+            | Instruction::ANewArray(_)
+            | Instruction::CheckCast(_)
+            | Instruction::InstanceOf(_) => bytes.put_u2(class(tokens, pool)?),
+            Instruction::MultiANewArray(..) => {
+                bytes.put_u2(class(tokens, pool)?);
+                bytes.put_u1(integer(tokens.next("dimensions")?)?);
+            }
+            Instruction::TableSwitch { .. } => {
+                let low: i32 = integer(tokens.next("low")?)?;
+                let high: i32 = integer(tokens.next("high")?)?;
+                let mut targets = vec![];
+                while !tokens.eat("default") {
+                    targets.push(target(tokens, offset, labels)?);
+                }
+                let default = target(tokens, offset, labels)?;
+                if targets.len() as i64 != high as i64 - low as i64 + 1 {
+                    return Err(format!(
+                        "tableswitch from {low} to {high} needs {} targets, has {}",
+                        high as i64 - low as i64 + 1,
+                        targets.len()
+                    ));
+                }
+                bytes.extend(std::iter::repeat_n(0, padding));
+                bytes.put_u4(wide_target(default)?);
+                bytes.put_u4(low as u32);
+                bytes.put_u4(high as u32);
+                for target in targets {
+                    bytes.put_u4(wide_target(target)?);
+                }
+            }
+            Instruction::LookupSwitch { .. } => {
+                let mut pairs = vec![];
+                while !tokens.eat("default") {
+                    let token = tokens.next("key:target")?;
+                    let (key, label) = token
+                        .text
+                        .split_once(':')
+                        .ok_or_else(|| format!("expected key:target, found `{}`", token.text))?;
+                    let key: i32 = integer(&Token {
+                        text: key.to_string(),
+                        quoted: false,
+                        units: None,
+                    })?;
+                    let target_token = [Token {
+                        text: label.to_string(),
+                        quoted: false,
+                        units: None,
+                    }];
+                    pairs.push((
+                        key,
+                        target(&mut Tokens::new(&target_token), offset, labels)?,
+                    ));
+                }
+                let default = target(tokens, offset, labels)?;
+                bytes.extend(std::iter::repeat_n(0, padding));
+                bytes.put_u4(wide_target(default)?);
+                bytes.put_u4(pairs.len() as u32);
+                for (key, target) in pairs {
+                    bytes.put_u4(key as u32);
+                    bytes.put_u4(wide_target(target)?);
+                }
+            }
+            Instruction::GotoW(_) | Instruction::JsrW(_) => {
+                bytes.put_u4(wide_target(target(tokens, offset, labels)?)?)
+            }
+            _ if !template.branch_targets(0).is_empty() => {
+                let relative = target(tokens, offset, labels)?;
+                let relative = i16::try_from(relative)
+                    .map_err(|_| format!("branch of {relative} bytes does not fit in {name}"))?;
+                bytes.put_u2(relative as u16);
+            }
+            _ if template.length(0) == 2 => {
+                bytes.put_u1(integer(tokens.next("local variable index")?)?)
+            }
+            _ => {}
+        }
+    }
+    tokens.end()?;
+    Instruction::from(&mut bytes.as_slice(), offset).map_err(|error| error.to_string())
+}
+
+fn wide_target(relative: i64) -> Result<u32, String> {
+    i32::try_from(relative)
+        .map(|relative| relative as u32)
+        .map_err(|_| format!("branch of {relative} bytes is too far"))
+}
+
+fn verification_type(
+    tokens: &mut Tokens,
+    pool: &mut Pool,
+    labels: &HashMap<String, u32>,
+) -> Result<VerificationType, String> {
+    let token = tokens.next("verification type")?;
+    Ok(match token.word().unwrap_or_default() {
+        "Top" => VerificationType::Top,
+        "Integer" => VerificationType::Integer,
+        "Float" => VerificationType::Float,
+        "Long" => VerificationType::Long,
+        "Double" => VerificationType::Double,
+        "Null" => VerificationType::Null,
+        "UninitializedThis" => VerificationType::UninitializedThis,
+        "Object" => VerificationType::Object(class(tokens, pool)?),
+        "Uninitialized" => VerificationType::Uninitialized(position(tokens, labels)?),
+        _ => return Err(format!("unknown verification type `{}`", token.text)),
+    })
+}
+
+/// Stack map frame of `.stack`, `frame_type` is decided once the offset delta is known
+fn frame(
+    tokens: &mut Tokens,
+    pool: &mut Pool,
+    labels: &HashMap<String, u32>,
+) -> Result<StackMapFrame, String> {
+    let mut frame = StackMapFrame {
+        frame_type: 0,
+        offset_delta: 0,
+        locals: vec![],
+        stack: vec![],
+    };
+    let kind = tokens.next("frame kind")?;
+    match kind.word().unwrap_or_default() {
+        "same" => frame.frame_type = 0,
+        "same_extended" => frame.frame_type = 251,
+        "same_locals_1_stack_item" => {
+            frame.frame_type = 64;
+            frame.stack = vec![verification_type(tokens, pool, labels)?];
+        }
+        "same_locals_1_stack_item_extended" => {
+            frame.frame_type = 247;
+            frame.stack = vec![verification_type(tokens, pool, labels)?];
+        }
+        "chop" => {
+            let count: u8 = integer(tokens.next("number of locals")?)?;
+            if !(1..=3).contains(&count) {
+                return Err("chop frame removes 1 to 3 locals".to_string());
+            }
+            frame.frame_type = 251 - count;
+        }
+        "append" => {
+            while tokens.remaining() > 0 {
+                frame.locals.push(verification_type(tokens, pool, labels)?);
+            }
+            if !(1..=3).contains(&frame.locals.len()) {
+                return Err("append frame adds 1 to 3 locals".to_string());
+            }
+            frame.frame_type = 251 + frame.locals.len() as u8;
+        }
+        "full" => {
+            frame.frame_type = 255;
+            tokens.expect("locals")?;
+            while !tokens.eat("stack") {
+                frame.locals.push(verification_type(tokens, pool, labels)?);
+            }
+            while tokens.remaining() > 0 {
+                frame.stack.push(verification_type(tokens, pool, labels)?);
+            }
+        }
+        _ => return Err(format!("unknown frame kind `{}`", kind.text)),
+    }
+    Ok(frame)
+}
+
+/// Computes offset deltas of frames at the given offsets, choosing the short
+/// form of `same` and `same_locals_1_stack_item` frames when the delta fits
+fn stack_map_table(frames: Vec<(u32, StackMapFrame)>) -> Result<Vec<StackMapFrame>, String> {
+    let mut previous: Option<u32> = None;
+    frames
+        .into_iter()
+        .map(|(offset, mut frame)| {
+            let delta = match previous {
+                None => offset,
+                Some(previous) if offset > previous => offset - previous - 1,
+                Some(_) => {
+                    return Err(format!(
+                        "stack map frame at offset {offset} is not after the previous one"
+                    ))
+                }
+            };
+            previous = Some(offset);
+            frame.offset_delta = pc(delta)?;
+            frame.frame_type = match frame.frame_type {
+                0 if delta < 64 => delta as u8,
+                0 => 251,
+                64 if delta < 64 => 64 + delta as u8,
+                64 => 247,
+                frame_type => frame_type,
+            };
+            Ok(frame)
+        })
+        .collect()
+}
@@ -0,0 +1,847 @@
+//! Printing of classes in the assembly language
+
+use super::assembler;
+use super::lexer::{name, quote, quote_utf16, tokenize, Tokens};
+use super::pool::{MemberKind, Pool};
+use super::{double_literal, flag_names, float_literal, reference_kind_name};
+use crate::raw::class::Class;
+use crate::raw::*;
+use std::collections::HashMap;
+use std::io::{Result, Write};
+
+/// Prints `class` as assembly that [`super::assemble`] turns back into the same class
+pub fn disassemble<W: Write>(out: &mut W, class: &Class) -> Result<()> {
+    let mut disassembler = Disassembler {
+        pool: &class.constant_pool,
+        checker: Pool::frozen(&class.constant_pool),
+        lines: vec![],
+    };
+    disassembler.class(class)?;
+    for line in disassembler.lines {
+        writeln!(out, "{line}")?;
+    }
+    Ok(())
+}
+
+/// Structure whose attributes are printed, decides which directives may be used
+#[derive(Clone, Copy, PartialEq)]
+enum Owner {
+    Class,
+    Field,
+    Method,
+}
+
+type Parser = fn(&mut Tokens, &mut Pool) -> std::result::Result<u16, String>;
+
+struct Disassembler<'a> {
+    pool: &'a [ConstantPoolInfo],
+    /// Reads symbolic operands back against the class' own constant pool,
+    /// so that only those resolving to the same index are printed
+    checker: Pool,
+    lines: Vec<String>,
+}
+
+impl Disassembler<'_> {
+    /// `text` if the assembler reads it back as `index`, `#index` otherwise
+    fn checked(&mut self, text: Option<String>, index: u16, parse: Parser) -> String {
+        if let Some(text) = text {
+            if let Ok(lines) = tokenize(&text) {
+                if let [line] = lines.as_slice() {
+                    let mut tokens = Tokens::new(&line.tokens);
+                    if parse(&mut tokens, &mut self.checker) == Ok(index) && tokens.end().is_ok() {
+                        return text;
+                    }
+                }
+            }
+        }
+        format!("#{index}")
+    }
+
+    fn class_text(&self, index: u16) -> Option<String> {
+        class_name_at(self.pool, index).map(name)
+    }
+
+    fn name_and_type_text(&self, index: u16) -> Option<String> {
+        let (member, descriptor) = name_and_type_at(self.pool, index)?;
+        Some(name(&format!("{member}{descriptor}")))
+    }
+
+    fn field_text(&self, index: u16) -> Option<String> {
+        let ConstantPoolInfo::FieldRef { .. } = constant(self.pool, index)? else {
+            return None;
+        };
+        let (class, member, descriptor) = member_ref_at(self.pool, index)?;
+        Some(format!(
+            "{} {}",
+            name(&format!("{class}.{member}")),
+            name(descriptor)
+        ))
+    }
+
+    /// Method reference, prefixed with `interface` when it is not of the `default` kind
+    fn method_text(&self, index: u16, default: MemberKind) -> Option<String> {
+        let kind = match constant(self.pool, index)? {
+            ConstantPoolInfo::MethodRef { .. } => MemberKind::Method,
+            ConstantPoolInfo::InterfaceMethodRef { .. } => MemberKind::InterfaceMethod,
+            _ => return None,
+        };
+        if kind == MemberKind::Method && default == MemberKind::InterfaceMethod {
+            return None;
+        }
+        let prefix = match kind == default {
+            true => "",
+            false => "interface ",
+        };
+        let (class, member, descriptor) = member_ref_at(self.pool, index)?;
+        Some(format!(
+            "{prefix}{}",
+            name(&format!("{class}.{member}{descriptor}"))
+        ))
+    }
+
+    fn handle_text(&self, reference_kind: u8, reference_index: u16) -> Option<String> {
+        let kind = match reference_kind_name(reference_kind) {
+            Some(kind) => kind.to_string(),
+            None => reference_kind.to_string(),
+        };
+        let reference = match reference_kind {
+            1..=4 => self.field_text(reference_index)?,
+            9 => self.method_text(reference_index, MemberKind::InterfaceMethod)?,
+            _ => self.method_text(reference_index, MemberKind::Method)?,
+        };
+        Some(format!("{kind} {reference}"))
+    }
+
+    fn constant_text(&self, index: u16) -> Option<String> {
+        Some(match constant(self.pool, index)? {
+            ConstantPoolInfo::Integer(value) => value.to_string(),
+            ConstantPoolInfo::Float(value) => float_literal(*value),
+            ConstantPoolInfo::Long(value) => format!("{value}L"),
+            ConstantPoolInfo::Double(value) => double_literal(*value),
+            ConstantPoolInfo::String { string_index } => {
+                quote_utf16(&utf16_at(self.pool, *string_index)?)
+            }
+            ConstantPoolInfo::Class { .. } => format!("class {}", self.class_text(index)?),
+            ConstantPoolInfo::MethodType { descriptor_index } => {
+                format!(
+                    "methodtype {}",
+                    name(utf8_at(self.pool, *descriptor_index)?)
+                )
+            }
+            ConstantPoolInfo::MethodHandle {
+                reference_kind,
+                reference_index,
+            } => format!(
+                "methodhandle {}",
+                self.handle_text(*reference_kind, *reference_index)?
+            ),
+            ConstantPoolInfo::Dynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => {
+                let (member, descriptor) = name_and_type_at(self.pool, *name_and_type_index)?;
+                format!(
+                    "dynamic {bootstrap_method_attr_index} {} {}",
+                    name(member),
+                    name(descriptor)
+                )
+            }
+            _ => return None,
+        })
+    }
+
+    fn class_operand(&mut self, index: u16) -> String {
+        self.checked(self.class_text(index), index, assembler::class)
+    }
+
+    fn utf8_operand(&mut self, index: u16) -> String {
+        let text = utf8_at(self.pool, index).map(name);
+        self.checked(text, index, |tokens, pool| {
+            assembler::utf8(tokens, pool, "name")
+        })
+    }
+
+    fn name_and_type_operand(&mut self, index: u16) -> String {
+        let text = self.name_and_type_text(index);
+        self.checked(text, index, assembler::name_and_type)
+    }
+
+    fn constant_operand(&mut self, index: u16) -> String {
+        self.checked(self.constant_text(index), index, assembler::constant)
+    }
+
+    /// Whether the attribute is named by the first Utf8 equal to `expected`,
+    /// which is the one the assembler uses
+    fn named(&mut self, attribute: &Attribute, expected: &str) -> bool {
+        self.checker.utf8(expected) == Ok(attribute.name_index)
+    }
+
+    fn class(&mut self, class: &Class) -> Result<()> {
+        self.lines
+            .push(format!(".version {} {}", class.major, class.minor));
+        let this_class = self.class_operand(class.this_class);
+        self.lines.push(format!(
+            ".class {}{this_class}",
+            flags(class.access_info, FlagsOwner::Class)
+        ));
+        let super_class = self.class_operand(class.super_class);
+        self.lines.push(format!(".super {super_class}"));
+        for interface in &class.interfaces {
+            let interface = self.class_operand(*interface);
+            self.lines.push(format!(".implements {interface}"));
+        }
+
+        self.lines.push(String::new());
+        for (i, constant) in self.pool.iter().enumerate() {
+            if let Some(line) = self.constant_definition(i as u16 + 1, constant) {
+                self.lines.push(line);
+            }
+        }
+
+        if !class.attributes.is_empty() {
+            self.lines.push(String::new());
+        }
+        self.attributes(&class.attributes, Owner::Class, "")?;
+
+        for field in &class.fields {
+            self.lines.push(String::new());
+            self.field(field)?;
+        }
+        for method in &class.methods {
+            self.lines.push(String::new());
+            self.method(method)?;
+        }
+        Ok(())
+    }
+
+    /// `.const` line of the entry under `index`, with its symbolic form as comment
+    fn constant_definition(&self, index: u16, constant: &ConstantPoolInfo) -> Option<String> {
+        let operands = match constant {
+            ConstantPoolInfo::Unusable => return None,
+            ConstantPoolInfo::Utf8(value) => quote(value),
+            ConstantPoolInfo::Utf16(units) => quote_utf16(units),
+            ConstantPoolInfo::Integer(value) => value.to_string(),
+            ConstantPoolInfo::Float(value) => match value.to_bits() {
+                // NaN with a payload other than Java's can only be written as bits
+                bits if value.is_nan() && bits != f32::NAN.to_bits() => format!("{bits:#010x}"),
+                _ => float_literal(*value),
+            },
+            ConstantPoolInfo::Long(value) => format!("{value}L"),
+            ConstantPoolInfo::Double(value) => match value.to_bits() {
+                bits if value.is_nan() && bits != f64::NAN.to_bits() => format!("{bits:#018x}"),
+                _ => double_literal(*value),
+            },
+            ConstantPoolInfo::Class { name_index }
+            | ConstantPoolInfo::Module { name_index }
+            | ConstantPoolInfo::Package { name_index } => format!("#{name_index}"),
+            ConstantPoolInfo::String { string_index } => format!("#{string_index}"),
+            ConstantPoolInfo::MethodType { descriptor_index } => format!("#{descriptor_index}"),
+            ConstantPoolInfo::FieldRef {
+                class_index,
+                name_and_type_index,
+            }
+            | ConstantPoolInfo::MethodRef {
+                class_index,
+                name_and_type_index,
+            }
+            | ConstantPoolInfo::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            } => format!("#{class_index} #{name_and_type_index}"),
+            ConstantPoolInfo::NameAndType {
+                name_index,
+                descriptor_index,
+            } => format!("#{name_index} #{descriptor_index}"),
+            ConstantPoolInfo::MethodHandle {
+                reference_kind,
+                reference_index,
+            } => match reference_kind_name(*reference_kind) {
+                Some(kind) => format!("{kind} #{reference_index}"),
+                None => format!("{reference_kind} #{reference_index}"),
+            },
+            ConstantPoolInfo::Dynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            }
+            | ConstantPoolInfo::InvokeDynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => format!("{bootstrap_method_attr_index} #{name_and_type_index}"),
+        };
+        let comment = match constant {
+            ConstantPoolInfo::FieldRef { .. } => self.field_text(index),
+            ConstantPoolInfo::MethodRef { .. } => self.method_text(index, MemberKind::Method),
+            ConstantPoolInfo::InterfaceMethodRef { .. } => {
+                self.method_text(index, MemberKind::InterfaceMethod)
+            }
+            ConstantPoolInfo::NameAndType { .. } => {
+                name_and_type_at(self.pool, index).map(|(member, descriptor)| {
+                    match descriptor.starts_with('(') {
+                        true => format!("{member}{descriptor}"),
+                        false => format!("{member} {descriptor}"),
+                    }
+                })
+            }
+            ConstantPoolInfo::InvokeDynamic {
+                name_and_type_index,
+                ..
+            } => self.name_and_type_text(*name_and_type_index),
+            ConstantPoolInfo::Module { name_index } | ConstantPoolInfo::Package { name_index } => {
+                utf8_at(self.pool, *name_index).map(name)
+            }
+            ConstantPoolInfo::Class { .. }
+            | ConstantPoolInfo::String { .. }
+            | ConstantPoolInfo::MethodType { .. }
+            | ConstantPoolInfo::MethodHandle { .. }
+            | ConstantPoolInfo::Dynamic { .. } => self.constant_text(index),
+            _ => None,
+        };
+        let definition = format!(".const #{index} = {} {operands}", constant.tag_name());
+        Some(match comment {
+            Some(comment) => format!("{definition:<40} ; {comment}"),
+            None => definition,
+        })
+    }
+
+    fn field(&mut self, field: &FieldInfo) -> Result<()> {
+        let name = self.member_name(field.name_index);
+        let descriptor = self.member_name(field.descriptor_index);
+        let mut line = format!(
+            ".field {}{name} {descriptor}",
+            flags(field.access_flags, FlagsOwner::Field)
+        );
+
+        let mut attributes = field.attributes.as_slice();
+        if let Some((first, rest)) = attributes.split_first() {
+            if let AttributeInfo::ConstantValue(index) = first.info {
+                if self.named(first, "ConstantValue") {
+                    line += &format!(" = {}", self.constant_operand(index));
+                    attributes = rest;
+                }
+            }
+        }
+        self.lines.push(line);
+
+        if !attributes.is_empty() {
+            self.attributes(attributes, Owner::Field, "    ")?;
+            self.lines.push(".end field".to_string());
+        }
+        Ok(())
+    }
+
+    /// Field name or descriptor, which must not be read as the `=` of the constant value
+    fn member_name(&mut self, index: u16) -> String {
+        match self.utf8_operand(index) {
+            text if text == "=" => quote(&text),
+            text => text,
+        }
+    }
+
+    fn method(&mut self, method: &MethodInfo) -> Result<()> {
+        let name = utf8_at(self.pool, method.name_index);
+        let descriptor = utf8_at(self.pool, method.descriptor_index);
+        let combined = match (name, descriptor) {
+            (Some(name), Some(descriptor))
+                if !name.contains('(')
+                    && self.checker.utf8(name) == Ok(method.name_index)
+                    && self.checker.utf8(descriptor) == Ok(method.descriptor_index) =>
+            {
+                Some(self::name(&format!("{name}{descriptor}")))
+            }
+            _ => None,
+        };
+        let header = match combined {
+            Some(text) if !text.starts_with(['"', '#']) && text.contains('(') => text,
+            _ => format!(
+                "{} #{}",
+                self.utf8_operand(method.name_index),
+                method.descriptor_index
+            ),
+        };
+        self.lines.push(format!(
+            ".method {}{header}",
+            flags(method.access_flags, FlagsOwner::Method)
+        ));
+        self.attributes(&method.attributes, Owner::Method, "    ")?;
+        self.lines.push(".end method".to_string());
+        Ok(())
+    }
+
+    /// Prints attributes with directives where the assembler would recreate them exactly,
+    /// and as `.attribute` hex otherwise
+    fn attributes(&mut self, attributes: &[Attribute], owner: Owner, indent: &str) -> Result<()> {
+        // Kinds of attributes collecting entries of several directives
+        let mut shared: Vec<&str> = vec![];
+        for attribute in attributes {
+            let mut first = |kind: &'static str, empty: bool| {
+                let first = !empty && !shared.contains(&kind);
+                if first {
+                    shared.push(kind);
+                }
+                first
+            };
+            let directives: Vec<String> = match &attribute.info {
+                AttributeInfo::Signature(signature) if self.named(attribute, "Signature") => {
+                    vec![format!(".signature {}", name(signature))]
+                }
+                AttributeInfo::Deprecated if self.named(attribute, "Deprecated") => {
+                    vec![".deprecated".to_string()]
+                }
+                AttributeInfo::Synthetic if self.named(attribute, "Synthetic") => {
+                    vec![".synthetic".to_string()]
+                }
+                AttributeInfo::SourceFile(source)
+                    if owner == Owner::Class && self.named(attribute, "SourceFile") =>
+                {
+                    vec![format!(".source {}", name(source))]
+                }
+                AttributeInfo::NestHost(host)
+                    if owner == Owner::Class && self.named(attribute, "NestHost") =>
+                {
+                    vec![format!(".nesthost {}", self.class_operand(*host))]
+                }
+                AttributeInfo::EnclosingMethod {
+                    class_index,
+                    method_index,
+                } if owner == Owner::Class && self.named(attribute, "EnclosingMethod") => {
+                    vec![format!(
+                        ".enclosingmethod {} {}",
+                        self.class_operand(*class_index),
+                        self.name_and_type_operand(*method_index)
+                    )]
+                }
+                AttributeInfo::NestMembers(members)
+                    if owner == Owner::Class
+                        && self.named(attribute, "NestMembers")
+                        && first("NestMembers", members.is_empty()) =>
+                {
+                    members
+                        .iter()
+                        .map(|member| format!(".nestmember {}", self.class_operand(*member)))
+                        .collect()
+                }
+                AttributeInfo::PermittedSubclasses(subclasses)
+                    if owner == Owner::Class
+                        && self.named(attribute, "PermittedSubclasses")
+                        && first("PermittedSubclasses", subclasses.is_empty()) =>
+                {
+                    subclasses
+                        .iter()
+                        .map(|class| format!(".permittedsubclass {}", self.class_operand(*class)))
+                        .collect()
+                }
+                AttributeInfo::InnerClasses(classes)
+                    if owner == Owner::Class
+                        && self.named(attribute, "InnerClasses")
+                        && first("InnerClasses", classes.is_empty()) =>
+                {
+                    classes
+                        .iter()
+                        .map(|class| {
+                            format!(
+                                ".innerclass {}{} {} {}",
+                                flags(class.inner_class_access_flags, FlagsOwner::InnerClass),
+                                self.class_operand(class.inner_class_info_index),
+                                self.class_operand(class.outer_class_info_index),
+                                self.utf8_operand(class.inner_name_index)
+                            )
+                        })
+                        .collect()
+                }
+                AttributeInfo::BootstrapMethods(methods)
+                    if owner == Owner::Class
+                        && self.named(attribute, "BootstrapMethods")
+                        && first("BootstrapMethods", methods.is_empty()) =>
+                {
+                    methods
+                        .iter()
+                        .enumerate()
+                        .map(|(i, method)| {
+                            let mut line =
+                                format!(".bootstrap {i} {}", self.bootstrap_handle(method));
+                            for argument in &method.bootstrap_arguments {
+                                line += " ";
+                                line += &self.constant_operand(*argument);
+                            }
+                            line
+                        })
+                        .collect()
+                }
+                AttributeInfo::Exceptions(exceptions)
+                    if owner == Owner::Method
+                        && self.named(attribute, "Exceptions")
+                        && first("Exceptions", exceptions.is_empty()) =>
+                {
+                    exceptions
+                        .iter()
+                        .map(|exception| format!(".throws {}", self.class_operand(*exception)))
+                        .collect()
+                }
+                AttributeInfo::Code {
+                    max_stack,
+                    max_locals,
+                    code,
+                    exception_table,
+                    attributes,
+                } if owner == Owner::Method && self.named(attribute, "Code") => {
+                    self.lines.push(format!(
+                        "{indent}.code stack {max_stack} locals {max_locals}"
+                    ));
+                    self.code(code, exception_table, attributes)?;
+                    self.lines.push(format!("{indent}.end code"));
+                    continue;
+                }
+                _ => vec![self.raw_attribute(attribute)?],
+            };
+            self.lines
+                .extend(directives.into_iter().map(|line| format!("{indent}{line}")));
+        }
+        Ok(())
+    }
+
+    fn bootstrap_handle(&mut self, method: &BootstrapMethod) -> String {
+        let index = method.bootstrap_method_ref;
+        let text = match constant(self.pool, index) {
+            Some(ConstantPoolInfo::MethodHandle {
+                reference_kind,
+                reference_index,
+            }) => self.handle_text(*reference_kind, *reference_index),
+            _ => None,
+        };
+        self.checked(text, index, |tokens, pool| {
+            let (reference_kind, reference_index) = assembler::handle(tokens, pool)?;
+            pool.add(ConstantPoolInfo::MethodHandle {
+                reference_kind,
+                reference_index,
+            })
+        })
+    }
+
+    /// `.attribute` directive with the encoded content in hex
+    fn raw_attribute(&mut self, attribute: &Attribute) -> Result<String> {
+        let mut bytes = vec![];
+        attribute.info.write(self.pool, &mut bytes)?;
+        let mut line = format!(".attribute {}", self.utf8_operand(attribute.name_index));
+        for chunk in bytes.chunks(32) {
+            line.push(' ');
+            for byte in chunk {
+                line += &format!("{byte:02x}");
+            }
+        }
+        Ok(line)
+    }
+
+    fn code(
+        &mut self,
+        code: &[(u32, Instruction)],
+        exception_table: &[Exception],
+        attributes: &[Attribute],
+    ) -> Result<()> {
+        let end = code.last().map_or(0, |(offset, instruction)| {
+            offset + instruction.length(*offset)
+        });
+        let boundary = |offset: u32| {
+            offset == end
+                || code
+                    .binary_search_by_key(&offset, |(offset, _)| *offset)
+                    .is_ok()
+        };
+
+        // The assembler writes these tables in this order before any other attribute,
+        // the rest (and tables it could not recreate) is kept as hex
+        let mut line_numbers: Option<&[LineNumber]> = None;
+        let mut variables: Option<&[LocalVariable]> = None;
+        let mut variable_types: Option<&[LocalVariable]> = None;
+        let mut frames: Option<Vec<(u32, &StackMapFrame)>> = None;
+        let mut raw = vec![];
+        let mut rank = 0;
+        let in_range = |table: &[LocalVariable]| {
+            table.iter().all(|variable| {
+                variable.start_pc as u32 + variable.length as u32 <= u16::MAX as u32
+            })
+        };
+        for attribute in attributes {
+            match &attribute.info {
+                AttributeInfo::LineNumberTable(table)
+                    if rank == 0
+                        && line_numbers.is_none()
+                        && !table.is_empty()
+                        && table.windows(2).all(|w| w[0].start_pc <= w[1].start_pc)
+                        && table.iter().all(|line| boundary(line.start_pc as u32))
+                        && self.named(attribute, "LineNumberTable") =>
+                {
+                    line_numbers = Some(table);
+                }
+                AttributeInfo::LocalVariableTable(table)
+                    if rank <= 1
+                        && variables.is_none()
+                        && !table.is_empty()
+                        && in_range(table)
+                        && self.named(attribute, "LocalVariableTable") =>
+                {
+                    variables = Some(table);
+                    rank = 1;
+                }
+                AttributeInfo::LocalVariableTypeTable(table)
+                    if rank <= 2
+                        && variable_types.is_none()
+                        && !table.is_empty()
+                        && in_range(table)
+                        && self.named(attribute, "LocalVariableTypeTable") =>
+                {
+                    variable_types = Some(table);
+                    rank = 2;
+                }
+                AttributeInfo::StackMapTable(table)
+                    if rank <= 3
+                        && frames.is_none()
+                        && !table.is_empty()
+                        && self.named(attribute, "StackMapTable") =>
+                {
+                    let mut offsets: Vec<(u32, &StackMapFrame)> = vec![];
+                    for frame in table {
+                        let offset = match offsets.last() {
+                            Some((previous, _)) => previous + frame.offset_delta as u32 + 1,
+                            None => frame.offset_delta as u32,
+                        };
+                        offsets.push((offset, frame));
+                    }
+                    let expressible = offsets.iter().all(|(offset, frame)| {
+                        boundary(*offset) && !(128..=246).contains(&frame.frame_type)
+                    });
+                    if expressible {
+                        frames = Some(offsets);
+                        rank = 3;
+                    } else {
+                        raw.push(attribute);
+                        rank = 4;
+                    }
+                }
+                _ => {
+                    raw.push(attribute);
+                    rank = 4;
+                }
+            }
+        }
+
+        let ranges = [variables, variable_types]
+            .into_iter()
+            .flatten()
+            .flatten()
+            .flat_map(|variable| {
+                let start = variable.start_pc as u32;
+                [start, start + variable.length as u32]
+            });
+        let uninitialized = frames
+            .iter()
+            .flatten()
+            .flat_map(|(_, frame)| frame.locals.iter().chain(&frame.stack))
+            .filter_map(|verification_type| match verification_type {
+                VerificationType::Uninitialized(offset) => Some(*offset as u32),
+                _ => None,
+            });
+        let labels = Labels::with_offsets(
+            code,
+            exception_table,
+            ranges.chain(uninitialized).collect::<Vec<_>>(),
+        );
+        let names: HashMap<String, u32> = labels
+            .offsets()
+            .iter()
+            .enumerate()
+            .map(|(i, offset)| (format!("L{i}"), *offset))
+            .collect();
+        let position = |offset: u32| labels.at(offset).unwrap_or_else(|| offset.to_string());
+
+        let mut line_numbers = line_numbers.unwrap_or_default().iter().peekable();
+        let mut frames = frames.unwrap_or_default().into_iter().peekable();
+        let offsets = code
+            .iter()
+            .map(|(offset, instruction)| (*offset, Some(instruction)))
+            .chain([(end, None)]);
+        for (offset, instruction) in offsets {
+            if let Some(label) = labels.at(offset) {
+                self.lines.push(format!("    {label}:"));
+            }
+            while let Some(line) = line_numbers.next_if(|line| line.start_pc as u32 == offset) {
+                self.lines
+                    .push(format!("        .line {}", line.line_number));
+            }
+            while let Some((_, frame)) = frames.next_if(|(at, _)| *at == offset) {
+                let frame = self.frame(frame, &position);
+                self.lines.push(format!("        .stack {frame}"));
+            }
+            if let Some(instruction) = instruction {
+                let text = self.instruction(offset, instruction, &labels, &names);
+                self.lines.push(format!("        {text}"));
+            }
+        }
+
+        for exception in exception_table {
+            let catch_type = match exception.catch_type {
+                0 => "any".to_string(),
+                index => match self.class_operand(index) {
+                    text if text == "any" => format!("#{index}"),
+                    text => text,
+                },
+            };
+            self.lines.push(format!(
+                "        .catch {catch_type} from {} to {} using {}",
+                position(exception.start_pc as u32),
+                position(exception.end_pc as u32),
+                position(exception.handler_pc as u32)
+            ));
+        }
+        let tables = [(".var", variables), (".vartype", variable_types)];
+        for (directive, table) in tables {
+            for variable in table.unwrap_or_default() {
+                let start = variable.start_pc as u32;
+                let line = format!(
+                    "        {directive} {} is {} {} from {} to {}",
+                    variable.index,
+                    self.utf8_operand(variable.name_index),
+                    self.utf8_operand(variable.descriptor_index),
+                    position(start),
+                    position(start + variable.length as u32)
+                );
+                self.lines.push(line);
+            }
+        }
+        for attribute in raw {
+            let line = self.raw_attribute(attribute)?;
+            self.lines.push(format!("        {line}"));
+        }
+        Ok(())
+    }
+
+    fn frame(&mut self, frame: &StackMapFrame, position: &dyn Fn(u32) -> String) -> String {
+        let mut types = |types: &[VerificationType]| {
+            types
+                .iter()
+                .map(|verification_type| match verification_type {
+                    VerificationType::Top => "Top".to_string(),
+                    VerificationType::Integer => "Integer".to_string(),
+                    VerificationType::Float => "Float".to_string(),
+                    VerificationType::Long => "Long".to_string(),
+                    VerificationType::Double => "Double".to_string(),
+                    VerificationType::Null => "Null".to_string(),
+                    VerificationType::UninitializedThis => "UninitializedThis".to_string(),
+                    VerificationType::Object(index) => {
+                        format!("Object {}", self.class_operand(*index))
+                    }
+                    VerificationType::Uninitialized(offset) => {
+                        format!("Uninitialized {}", position(*offset as u32))
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        match frame.frame_type {
+            0..=63 => "same".to_string(),
+            64..=127 => format!("same_locals_1_stack_item {}", types(&frame.stack)),
+            247 => format!("same_locals_1_stack_item_extended {}", types(&frame.stack)),
+            248..=250 => format!("chop {}", 251 - frame.frame_type),
+            251 => "same_extended".to_string(),
+            252..=254 => format!("append {}", types(&frame.locals)),
+            _ => {
+                let locals = types(&frame.locals);
+                let stack = types(&frame.stack);
+                format!("full locals {locals} stack {stack}")
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }
+        }
+    }
+
+    /// Instruction with symbolic operands when they are read back as the same instruction,
+    /// with `#index` constants otherwise
+    fn instruction(
+        &mut self,
+        offset: u32,
+        instruction: &Instruction,
+        labels: &Labels,
+        names: &HashMap<String, u32>,
+    ) -> String {
+        let mnemonic = instruction.mnemonic();
+        let symbolic = match instruction {
+            Instruction::NewArray(atype) => {
+                array_type_name(*atype).map(|name| format!("{mnemonic} {name}"))
+            }
+            Instruction::Ldc(index) => self.constant_text(*index as u16),
+            Instruction::LdcW(index) | Instruction::Ldc2W(index) => self.constant_text(*index),
+            Instruction::GetStatic(index)
+            | Instruction::PutStatic(index)
+            | Instruction::GetField(index)
+            | Instruction::PutField(index) => self.field_text(*index),
+            Instruction::InvokeVirtual(index)
+            | Instruction::InvokeSpecial(index)
+            | Instruction::InvokeStatic(index) => self.method_text(*index, MemberKind::Method),
+            Instruction::InvokeInterface(index, count) => self
+                .method_text(*index, MemberKind::InterfaceMethod)
+                .map(|method| format!("{method} {count}")),
+            Instruction::InvokeDynamic(index) => match constant(self.pool, *index) {
+                Some(ConstantPoolInfo::InvokeDynamic {
+                    bootstrap_method_attr_index,
+                    name_and_type_index,
+                }) => self
+                    .name_and_type_text(*name_and_type_index)
+                    .map(|nat| format!("{bootstrap_method_attr_index} {nat}")),
+                _ => None,
+            },
+            Instruction::New(index)
+            | Instruction::ANewArray(index)
+            | Instruction::CheckCast(index)
+            | Instruction::InstanceOf(index) => self.class_text(*index),
+            Instruction::MultiANewArray(index, dimensions) => self
+                .class_text(*index)
+                .map(|class| format!("{class} {dimensions}")),
+            _ => Some(labels.instruction(offset, instruction).to_string()),
+        };
+        // All but the fallthrough case above lack the mnemonic
+        let symbolic = symbolic.map(|text| match instruction.constant_index() {
+            Some(_) => format!("{mnemonic} {text}"),
+            None => text,
+        });
+
+        if let Some(text) = symbolic {
+            if let Ok(lines) = tokenize(&text) {
+                if let [line] = lines.as_slice() {
+                    let mut tokens = Tokens::new(&line.tokens);
+                    let parsed =
+                        assembler::instruction(&mut tokens, &mut self.checker, offset, Some(names));
+                    if parsed.as_ref() == Ok(instruction) {
+                        return text;
+                    }
+                }
+            }
+        }
+        match (instruction, instruction.constant_index()) {
+            (Instruction::InvokeInterface(_, count), Some(index)) => {
+                format!("{mnemonic} #{index} {count}")
+            }
+            (Instruction::MultiANewArray(_, dimensions), Some(index)) => {
+                format!("{mnemonic} #{index} {dimensions}")
+            }
+            (_, Some(index)) => format!("{mnemonic} #{index}"),
+            (_, None) => labels.instruction(offset, instruction).to_string(),
+        }
+    }
+}
+
+/// Flag keywords followed by a space, unknown bits as a hex number
+fn flags(flags: AccessFlags, owner: FlagsOwner) -> String {
+    let mut bits = flags.bits();
+    let mut text = String::new();
+    for (bit, name) in flag_names(owner) {
+        if bits & bit != 0 {
+            text += name;
+            text.push(' ');
+            bits &= !bit;
+        }
+    }
+    if bits != 0 {
+        text += &format!("{bits:#06x} ");
+    }
+    text
+}
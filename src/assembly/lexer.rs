@@ -0,0 +1,231 @@
+//! Splitting of assembly source into lines of tokens
+
+use crate::raw::{invalid_data, ConstantPoolInfo};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub text: String,
+    /// Written in double quotes, so it is never a keyword, label or `#index`
+    pub quoted: bool,
+    /// UTF-16 code units of a quoted string with unpaired surrogates, which `text` shows
+    /// as U+FFFD
+    pub units: Option<Vec<u16>>,
+}
+
+impl Token {
+    /// Text of the token if it was written without quotes
+    pub fn word(&self) -> Option<&str> {
+        (!self.quoted).then_some(self.text.as_str())
+    }
+
+    /// Utf8 constant with the text of the token, unpaired surrogates included
+    pub fn utf8(&self) -> ConstantPoolInfo {
+        match &self.units {
+            Some(units) => ConstantPoolInfo::Utf16(units.clone()),
+            None => ConstantPoolInfo::Utf8(self.text.clone()),
+        }
+    }
+}
+
+/// Non-empty source line
+#[derive(Debug)]
+pub struct Line {
+    /// 1-based line number, for error messages
+    pub number: usize,
+    pub tokens: Vec<Token>,
+}
+
+/// Splits `source` into whitespace separated tokens. Comments start with `;`
+/// at the beginning of a token, so `;` inside descriptors does not start one.
+pub fn tokenize(source: &str) -> std::io::Result<Vec<Line>> {
+    let mut lines = vec![];
+    for (index, text) in source.lines().enumerate() {
+        let number = index + 1;
+        let mut tokens = vec![];
+        let mut chars = text.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+            } else if c == ';' {
+                break;
+            } else if c == '"' {
+                chars.next();
+                let units = unescape(&mut chars)
+                    .map_err(|message| invalid_data(format!("line {number}: {message}")))?;
+                let (text, units) = match String::from_utf16(&units) {
+                    Ok(text) => (text, None),
+                    Err(_) => (String::from_utf16_lossy(&units), Some(units)),
+                };
+                tokens.push(Token {
+                    text,
+                    quoted: true,
+                    units,
+                });
+            } else {
+                let mut text = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    text.push(c);
+                    chars.next();
+                }
+                tokens.push(Token {
+                    text,
+                    quoted: false,
+                    units: None,
+                });
+            }
+        }
+        if !tokens.is_empty() {
+            lines.push(Line { number, tokens });
+        }
+    }
+    Ok(lines)
+}
+
+/// Reads the UTF-16 code units of the rest of a quoted string, after the opening quote
+fn unescape(chars: &mut impl Iterator<Item = char>) -> Result<Vec<u16>, String> {
+    let mut units: Vec<u16> = vec![];
+    loop {
+        let c = match chars.next() {
+            None => return Err("unterminated string".to_string()),
+            Some('"') => return Ok(units),
+            Some('\\') => match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some('b') => '\u{8}',
+                Some('f') => '\u{c}',
+                Some('0') => '\0',
+                Some('"') => '"',
+                Some('\'') => '\'',
+                Some('\\') => '\\',
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let unit = u16::from_str_radix(&hex, 16)
+                        .ok()
+                        .filter(|_| hex.len() == 4)
+                        .ok_or_else(|| format!("invalid escape \\u{hex}"))?;
+                    units.push(unit);
+                    continue;
+                }
+                Some(other) => return Err(format!("invalid escape \\{other}")),
+                None => return Err("unterminated string".to_string()),
+            },
+            Some(c) => c,
+        };
+        let mut buffer = [0u16; 2];
+        units.extend_from_slice(c.encode_utf16(&mut buffer));
+    }
+}
+
+/// Quotes `text` as a string literal that [`tokenize`] reads back
+pub fn quote(text: &str) -> String {
+    quote_utf16(&text.encode_utf16().collect::<Vec<_>>())
+}
+
+/// Quotes UTF-16 code units as a string literal that [`tokenize`] reads back, unpaired
+/// surrogates as `\uXXXX` escapes
+pub fn quote_utf16(units: &[u16]) -> String {
+    let mut quoted = String::with_capacity(units.len() + 2);
+    quoted.push('"');
+    for c in char::decode_utf16(units.iter().copied()) {
+        let c = match c {
+            Ok(c) => c,
+            Err(error) => {
+                let unit = error.unpaired_surrogate();
+                quoted.push_str(&format!("\\u{unit:04x}"));
+                continue;
+            }
+        };
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() || c == '\u{feff}' => {
+                let mut buffer = [0u16; 2];
+                for unit in c.encode_utf16(&mut buffer) {
+                    quoted.push_str(&format!("\\u{unit:04x}"));
+                }
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Writes `name` as a single token, quoting it only when it would not be read back as is
+pub fn name(name: &str) -> String {
+    let plain = !name.is_empty()
+        && !name.starts_with([';', '#', '"'])
+        && !name.ends_with(':')
+        && !name.chars().any(|c| c.is_whitespace() || c.is_control());
+    if plain {
+        name.to_string()
+    } else {
+        quote(name)
+    }
+}
+
+/// Cursor over tokens of a single line
+pub struct Tokens<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl<'a> Tokens<'a> {
+    pub fn new(tokens: &'a [Token]) -> Self {
+        Tokens {
+            tokens,
+            position: 0,
+        }
+    }
+
+    pub fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
+
+    /// Next token, or error naming what was expected
+    pub fn next(&mut self, expected: &str) -> Result<&'a Token, String> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .ok_or_else(|| format!("expected {expected}"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    /// Consumes the next token if it is the unquoted `keyword`
+    pub fn eat(&mut self, keyword: &str) -> bool {
+        let found = self.peek().and_then(Token::word) == Some(keyword);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    pub fn expect(&mut self, keyword: &str) -> Result<(), String> {
+        if self.eat(keyword) {
+            Ok(())
+        } else {
+            Err(format!("expected `{keyword}`"))
+        }
+    }
+
+    /// Number of tokens left
+    pub fn remaining(&self) -> usize {
+        self.tokens.len() - self.position
+    }
+
+    /// Fails if some tokens were not consumed
+    pub fn end(&self) -> Result<(), String> {
+        match self.peek() {
+            None => Ok(()),
+            Some(token) => Err(format!("unexpected `{}`", token.text)),
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Textual assembly language for class files, in the spirit of Jasmin.
+//! [`disassemble`] prints a class in it and [`assemble`] reads it back, so output
+//! of `hava dump` can be edited and turned into a class file with `hava assemble`.
+//!
+//! ```text
+//! .version 49 0
+//! .class public super Hello
+//! .super java/lang/Object
+//! .source "Hello.java"
+//!
+//! .field private static final GREETING Ljava/lang/String; = "Hello"
+//!
+//! .method public static main([Ljava/lang/String;)V
+//!     .code stack 2 locals 1
+//!     L0:
+//!         .line 3
+//!         getstatic java/lang/System.out Ljava/io/PrintStream;
+//!         ldc "Hello"
+//!         invokevirtual java/io/PrintStream.println(Ljava/lang/String;)V
+//!         return
+//!     L1:
+//!         .var 0 is args [Ljava/lang/String; from L0 to L1
+//!     .end code
+//! .end method
+//! ```
+//!
+//! Every directive and instruction takes one line. Comments start with `;` at the beginning
+//! of a token. Strings are written in double quotes with Java escapes; quotes are also
+//! used for names that contain whitespace.
+//!
+//! Constant pool references are symbolic and entries are created as needed:
+//!
+//! - classes: `java/lang/String`, `[I`
+//! - fields: `java/lang/System.out Ljava/io/PrintStream;`
+//! - methods: `java/io/PrintStream.println(I)V`, prefixed with `interface` for
+//!   InterfaceMethodref outside of `invokeinterface`
+//! - loadable constants: `5`, `5L`, `1.5f`, `1.5d`, `"text"`, `class java/lang/String`,
+//!   `methodtype (I)V`, `methodhandle invokeStatic Foo.bar()V`, `dynamic 0 name Ljava/lang/Object;`
+//! - `#12` refers to the entry under that index directly.
+//!
+//! `.const #1 = Methodref #2 #3` places an entry under the given index. The disassembler prints
+//! the whole constant pool this way, so reassembled classes keep their indexes and attributes
+//! without a directive of their own can be kept as hex with `.attribute <name> <hex>`.
+//! Without `.const` lines the pool is built from scratch.
+//!
+//! Class directives: `.version <major> <minor>` (49.0 by default, which does not require
+//! StackMapTable), `.class <flags> <name>`, `.super <name>` (java/lang/Object by default),
+//! `.implements`, `.source`, `.signature`, `.deprecated`, `.synthetic`, `.nesthost`,
+//! `.nestmember`, `.permittedsubclass`, `.enclosingmethod <class> <name(descriptor)>`,
+//! `.innerclass <flags> <inner> <outer> <name>` and
+//! `.bootstrap <index> <kind> <method> <arguments>` referenced by
+//! `invokedynamic <index> <name(descriptor)>`.
+//!
+//! `.field <flags> <name> <descriptor> [= <constant>]` may be followed by `.signature`,
+//! `.deprecated`, `.synthetic` or `.attribute` lines closed by `.end field`.
+//! `.method <flags> <name(descriptor)>` contains `.throws`, the same attribute directives
+//! and `.code stack <n> locals <n>` ... `.end code` with labels (`L0:`), instructions and
+//! `.line <n>`, `.stack <frame>`, `.catch <class|any> from <label> to <label> using <label>`,
+//! `.var <index> is <name> <descriptor> from <label> to <label>` and `.vartype` in the same
+//! shape for generic signatures. Plain numbers may be used instead of labels, meaning
+//! offsets exactly as encoded (relative to the instruction for branches).
+//!
+//! Stack map frames are `same`, `same_extended`, `same_locals_1_stack_item <type>`,
+//! `same_locals_1_stack_item_extended <type>`, `chop <n>`, `append <types>` and
+//! `full locals <types> stack <types>`, where types are `Top`, `Integer`, `Float`, `Long`,
+//! `Double`, `Null`, `UninitializedThis`, `Object <class>` and `Uninitialized <label>`.
+
+mod assembler;
+mod disassembler;
+mod lexer;
+mod pool;
+
+#[cfg(test)]
+mod tests;
+
+pub use assembler::assemble;
+pub use disassembler::disassemble;
+
+use crate::raw::*;
+
+/// Keywords of access flags that may be used for the given structure
+fn flag_names(owner: FlagsOwner) -> &'static [(u16, &'static str)] {
+    match owner {
+        FlagsOwner::Class => &[
+            (0x0001, "public"),
+            (0x0010, "final"),
+            (0x0020, "super"),
+            (0x0200, "interface"),
+            (0x0400, "abstract"),
+            (0x1000, "synthetic"),
+            (0x2000, "annotation"),
+            (0x4000, "enum"),
+            (0x8000, "module"),
+        ],
+        FlagsOwner::Field => &[
+            (0x0001, "public"),
+            (0x0002, "private"),
+            (0x0004, "protected"),
+            (0x0008, "static"),
+            (0x0010, "final"),
+            (0x0040, "volatile"),
+            (0x0080, "transient"),
+            (0x1000, "synthetic"),
+            (0x4000, "enum"),
+        ],
+        FlagsOwner::Method => &[
+            (0x0001, "public"),
+            (0x0002, "private"),
+            (0x0004, "protected"),
+            (0x0008, "static"),
+            (0x0010, "final"),
+            (0x0020, "synchronized"),
+            (0x0040, "bridge"),
+            (0x0080, "varargs"),
+            (0x0100, "native"),
+            (0x0400, "abstract"),
+            (0x0800, "strict"),
+            (0x1000, "synthetic"),
+        ],
+        FlagsOwner::InnerClass => &[
+            (0x0001, "public"),
+            (0x0002, "private"),
+            (0x0004, "protected"),
+            (0x0008, "static"),
+            (0x0010, "final"),
+            (0x0200, "interface"),
+            (0x0400, "abstract"),
+            (0x1000, "synthetic"),
+            (0x2000, "annotation"),
+            (0x4000, "enum"),
+        ],
+    }
+}
+
+/// Name of `MethodHandle.reference_kind` without the `REF_` prefix, like `invokeStatic`
+fn reference_kind_name(kind: u8) -> Option<&'static str> {
+    REFERENCE_KINDS
+        .get(kind as usize)
+        .and_then(|name| name.strip_prefix("REF_"))
+}
+
+fn float_literal(value: f32) -> String {
+    match value {
+        _ if value.is_nan() => "NaNf".to_string(),
+        f32::INFINITY => "Infinityf".to_string(),
+        f32::NEG_INFINITY => "-Infinityf".to_string(),
+        _ => format!("{value:?}f"),
+    }
+}
+
+fn double_literal(value: f64) -> String {
+    match value {
+        _ if value.is_nan() => "NaNd".to_string(),
+        f64::INFINITY => "Infinityd".to_string(),
+        f64::NEG_INFINITY => "-Infinityd".to_string(),
+        _ => format!("{value:?}d"),
+    }
+}
@@ -0,0 +1,234 @@
+//! Constant pool under construction, reusing entries that are already present
+
+use crate::raw::*;
+use std::collections::HashMap;
+
+/// Identity of a constant pool entry, floats compared by their bits
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Utf8(String),
+    Utf16(Vec<u16>),
+    Integer(i32),
+    Float(u32),
+    Long(i64),
+    Double(u64),
+    /// Tag with up to two operands
+    Reference(u8, u16, u16),
+}
+
+impl Key {
+    fn of(constant: &ConstantPoolInfo) -> Option<Key> {
+        Some(match constant {
+            ConstantPoolInfo::Utf8(value) => Key::Utf8(value.clone()),
+            ConstantPoolInfo::Utf16(units) => Key::Utf16(units.clone()),
+            ConstantPoolInfo::Integer(value) => Key::Integer(*value),
+            ConstantPoolInfo::Float(value) => Key::Float(value.to_bits()),
+            ConstantPoolInfo::Long(value) => Key::Long(*value),
+            ConstantPoolInfo::Double(value) => Key::Double(value.to_bits()),
+            ConstantPoolInfo::Class { name_index } => Key::Reference(7, *name_index, 0),
+            ConstantPoolInfo::String { string_index } => Key::Reference(8, *string_index, 0),
+            ConstantPoolInfo::FieldRef {
+                class_index,
+                name_and_type_index,
+            } => Key::Reference(9, *class_index, *name_and_type_index),
+            ConstantPoolInfo::MethodRef {
+                class_index,
+                name_and_type_index,
+            } => Key::Reference(10, *class_index, *name_and_type_index),
+            ConstantPoolInfo::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            } => Key::Reference(11, *class_index, *name_and_type_index),
+            ConstantPoolInfo::NameAndType {
+                name_index,
+                descriptor_index,
+            } => Key::Reference(12, *name_index, *descriptor_index),
+            ConstantPoolInfo::MethodHandle {
+                reference_kind,
+                reference_index,
+            } => Key::Reference(15, *reference_kind as u16, *reference_index),
+            ConstantPoolInfo::MethodType { descriptor_index } => {
+                Key::Reference(16, *descriptor_index, 0)
+            }
+            ConstantPoolInfo::Dynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => Key::Reference(17, *bootstrap_method_attr_index, *name_and_type_index),
+            ConstantPoolInfo::InvokeDynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => Key::Reference(18, *bootstrap_method_attr_index, *name_and_type_index),
+            ConstantPoolInfo::Module { name_index } => Key::Reference(19, *name_index, 0),
+            ConstantPoolInfo::Package { name_index } => Key::Reference(20, *name_index, 0),
+            ConstantPoolInfo::Unusable => return None,
+        })
+    }
+}
+
+/// Kind of member reference
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemberKind {
+    Field,
+    Method,
+    InterfaceMethod,
+}
+
+pub struct Pool {
+    /// Entries by `index - 1`, `None` for slots that are not defined yet
+    entries: Vec<Option<ConstantPoolInfo>>,
+    /// First index of every entry
+    indexes: HashMap<Key, u16>,
+    /// Lookups fail instead of adding missing entries
+    frozen: bool,
+    /// No free slot exists below this position
+    first_free: usize,
+}
+
+impl Pool {
+    pub fn new() -> Pool {
+        Pool {
+            entries: vec![],
+            indexes: HashMap::new(),
+            frozen: false,
+            first_free: 0,
+        }
+    }
+
+    /// Pool that only finds entries of an existing constant pool
+    pub fn frozen(constant_pool: &[ConstantPoolInfo]) -> Pool {
+        let mut pool = Pool::new();
+        for (i, constant) in constant_pool.iter().enumerate() {
+            if let Some(key) = Key::of(constant) {
+                pool.indexes.entry(key).or_insert(i as u16 + 1);
+            }
+        }
+        pool.frozen = true;
+        pool
+    }
+
+    /// Places `constant` under `index`, as requested by `.const`
+    pub fn define(&mut self, index: u16, constant: ConstantPoolInfo) -> Result<(), String> {
+        let position = (index as usize)
+            .checked_sub(1)
+            .ok_or("constant pool starts at #1")?;
+        let slots = constant.slots();
+        if position + slots > u16::MAX as usize - 1 {
+            return Err(format!("#{index} does not fit in the constant pool"));
+        }
+        if self.entries.len() < position + slots {
+            self.entries.resize(position + slots, None);
+        }
+        if self.entries[position..position + slots]
+            .iter()
+            .any(Option::is_some)
+        {
+            return Err(format!("#{index} is already defined"));
+        }
+        if let Some(key) = Key::of(&constant) {
+            self.indexes.entry(key).or_insert(index);
+        }
+        self.entries[position] = Some(constant);
+        if slots == 2 {
+            self.entries[position + 1] = Some(ConstantPoolInfo::Unusable);
+        }
+        Ok(())
+    }
+
+    /// Index of `constant`, adding it to the first free slot if it is not present yet
+    pub fn add(&mut self, constant: ConstantPoolInfo) -> Result<u16, String> {
+        let key = Key::of(&constant).ok_or("unusable slot cannot be referenced")?;
+        if let Some(index) = self.indexes.get(&key) {
+            return Ok(*index);
+        }
+        if self.frozen {
+            return Err(format!(
+                "{} is not in the constant pool",
+                constant.tag_name()
+            ));
+        }
+
+        let slots = constant.slots();
+        let mut position = self.first_free;
+        while self.entries[position.min(self.entries.len())..]
+            .iter()
+            .take(slots)
+            .any(Option::is_some)
+        {
+            position += 1;
+        }
+        if position + slots > u16::MAX as usize - 1 {
+            return Err("constant pool is full".to_string());
+        }
+        if self.entries.len() < position + slots {
+            self.entries.resize(position + slots, None);
+        }
+        if position == self.first_free {
+            self.first_free = position + slots;
+        }
+
+        let index = position as u16 + 1;
+        self.entries[position] = Some(constant);
+        if slots == 2 {
+            self.entries[position + 1] = Some(ConstantPoolInfo::Unusable);
+        }
+        self.indexes.insert(key, index);
+        Ok(index)
+    }
+
+    pub fn utf8(&mut self, value: &str) -> Result<u16, String> {
+        self.add(ConstantPoolInfo::Utf8(value.to_string()))
+    }
+
+    pub fn class(&mut self, name: &str) -> Result<u16, String> {
+        let name_index = self.utf8(name)?;
+        self.add(ConstantPoolInfo::Class { name_index })
+    }
+
+    /// String entry of the Utf8 entry, which may be [`ConstantPoolInfo::Utf16`]
+    pub fn string(&mut self, utf8: ConstantPoolInfo) -> Result<u16, String> {
+        let string_index = self.add(utf8)?;
+        self.add(ConstantPoolInfo::String { string_index })
+    }
+
+    pub fn name_and_type(&mut self, name: &str, descriptor: &str) -> Result<u16, String> {
+        let name_index = self.utf8(name)?;
+        let descriptor_index = self.utf8(descriptor)?;
+        self.add(ConstantPoolInfo::NameAndType {
+            name_index,
+            descriptor_index,
+        })
+    }
+
+    pub fn member(
+        &mut self,
+        kind: MemberKind,
+        class_index: u16,
+        name_and_type_index: u16,
+    ) -> Result<u16, String> {
+        self.add(match kind {
+            MemberKind::Field => ConstantPoolInfo::FieldRef {
+                class_index,
+                name_and_type_index,
+            },
+            MemberKind::Method => ConstantPoolInfo::MethodRef {
+                class_index,
+                name_and_type_index,
+            },
+            MemberKind::InterfaceMethod => ConstantPoolInfo::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            },
+        })
+    }
+
+    /// Entries of the finished pool, failing if some slot was left undefined
+    pub fn finish(self) -> Result<Vec<ConstantPoolInfo>, String> {
+        self.entries
+            .into_iter()
+            .enumerate()
+            .map(|(i, constant)| {
+                constant.ok_or_else(|| format!("constant #{} is not defined", i + 1))
+            })
+            .collect()
+    }
+}
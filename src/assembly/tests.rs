@@ -0,0 +1,147 @@
+use crate::assembly::{assemble, disassemble};
+use crate::raw::class::Class;
+use std::path::Path;
+
+/// Class read from the bytes, as `hava dump` reads it
+fn parse(bytes: &[u8]) -> Class {
+    let mut class = Class::from(bytes).unwrap();
+    class.resolve_attributes().unwrap();
+    class
+}
+
+/// Bytes of the class after `hava dump X.class | hava assemble`
+fn reassemble(bytes: &[u8]) -> Vec<u8> {
+    let mut text = vec![];
+    disassemble(&mut text, &parse(bytes)).unwrap();
+    let text = String::from_utf8(text).unwrap();
+    assemble(&text)
+        .and_then(|class| class.to_bytes())
+        .unwrap_or_else(|error| panic!("{error}\n{text}"))
+}
+
+/// Asserts that the class assembled from the listing comes back byte for byte
+fn assert_round_trip(listing: &str) {
+    let bytes = assemble(listing).unwrap().to_bytes().unwrap();
+    assert_eq!(reassemble(&bytes), bytes);
+}
+
+#[test]
+fn examples_round_trip() {
+    let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let mut classes = 0;
+    for entry in std::fs::read_dir(examples).unwrap() {
+        let path = entry.unwrap().path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "class")
+        {
+            let bytes = std::fs::read(&path).unwrap();
+            assert_eq!(reassemble(&bytes), bytes, "{}", path.display());
+            classes += 1;
+        }
+    }
+    assert!(classes > 0, "no class files in examples");
+}
+
+#[test]
+fn labels_catch_and_stack_round_trip() {
+    assert_round_trip(
+        r#"
+.version 52 0
+.class public super Parse
+.super java/lang/Object
+
+.method public static parse(Ljava/lang/String;)I
+    .code stack 2 locals 3
+    L0:
+        .line 4
+        aload_0
+        invokestatic java/lang/Integer.parseInt(Ljava/lang/String;)I
+    L1:
+        ireturn
+    L2:
+        .stack same_locals_1_stack_item Object java/lang/NumberFormatException
+        astore_1
+        iconst_0
+        istore_2
+    L3:
+        .stack append Object java/lang/NumberFormatException Integer
+        iload_2
+        bipush 10
+        if_icmpge L4
+        iinc 2 1
+        goto L3
+    L4:
+        .stack same
+        iload_2
+        lookupswitch 1:L5 10:L6 default L5
+    L5:
+        .stack same
+        iconst_m1
+        ireturn
+    L6:
+        .stack full locals Object java/lang/String Top Integer stack
+        iload_2
+        ireturn
+        .catch java/lang/NumberFormatException from L0 to L1 using L2
+        .catch any from L0 to L1 using L2
+        .var 0 is text Ljava/lang/String; from L0 to L6
+    .end code
+.end method
+"#,
+    );
+}
+
+#[test]
+fn invokedynamic_round_trip() {
+    assert_round_trip(
+        r#"
+.version 52 0
+.class super Lambda
+.super java/lang/Object
+.bootstrap 0 invokeStatic java/lang/invoke/LambdaMetafactory.metafactory(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/CallSite; methodtype ()V methodhandle invokeStatic Lambda.lambda$run$0()V methodtype ()V
+.innerclass public static final java/lang/invoke/MethodHandles$Lookup java/lang/invoke/MethodHandles Lookup
+
+.field private static final GREETING Ljava/lang/String; = "hello"
+
+.method static run()V
+    .code stack 1 locals 0
+        invokedynamic 0 run()Ljava/lang/Runnable;
+        invokeinterface java/lang/Runnable.run()V 1
+        return
+    .end code
+.end method
+
+.method private static synthetic lambda$run$0()V
+    .code stack 2 locals 0
+        getstatic java/lang/System.out Ljava/io/PrintStream;
+        ldc "hello"
+        invokevirtual java/io/PrintStream.println(Ljava/lang/String;)V
+        return
+    .end code
+.end method
+"#,
+    );
+}
+
+#[test]
+fn unpaired_surrogates_round_trip() {
+    let listing = r#"
+.version 52 0
+.class super Surrogates
+.super java/lang/Object
+
+.field static final LOW Ljava/lang/String; = "\udc00"
+
+.method static high()Ljava/lang/String;
+    .code stack 1 locals 0
+        ldc "x\ud800y"
+        areturn
+    .end code
+.end method
+"#;
+    assert_round_trip(listing);
+    let bytes = assemble(listing).unwrap().to_bytes().unwrap();
+    let high = [b'x', 0xed, 0xa0, 0x80, b'y'];
+    assert!(bytes.windows(high.len()).any(|window| window == high));
+}
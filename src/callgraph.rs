@@ -0,0 +1,565 @@
+//! Call graph of the methods reachable from entry points. Calls are found in the
+//! invoke instructions of the code: static and special calls go to the resolved method,
+//! virtual and interface calls to the methods selected for every possible receiver class.
+//! Class hierarchy analysis (CHA) takes every subclass of the resolved class as possible
+//! receiver, rapid type analysis (RTA) only those instantiated by reachable code.
+//! invokedynamic calls go to the methods their bootstrap arguments refer to, like the
+//! bodies of lambdas, and class initializers are called where classes are first used.
+
+use crate::classpath::ClassPath;
+use crate::hierarchy::Hierarchy;
+use crate::raw::class::Class;
+use crate::raw::*;
+use crate::resolve::{Member, Reference, ResolutionError, Resolver};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MethodId {
+    pub class: String,
+    pub name: String,
+    pub descriptor: String,
+}
+
+impl MethodId {
+    pub fn new(class: &str, name: &str, descriptor: &str) -> MethodId {
+        MethodId {
+            class: class.to_string(),
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+        }
+    }
+
+    /// Method declared by `member`
+    pub fn of(member: &Member) -> MethodId {
+        let pool = &member.class.constant_pool;
+        let method = member.method();
+        MethodId::new(
+            member.class_name(),
+            utf8_at(pool, method.name_index).unwrap_or("?"),
+            utf8_at(pool, method.descriptor_index).unwrap_or("?"),
+        )
+    }
+}
+
+impl Display for MethodId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}{}", self.class, self.name, self.descriptor)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Analysis {
+    /// Class hierarchy analysis
+    Cha,
+    /// Rapid type analysis
+    Rta,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CallKind {
+    Static,
+    Special,
+    Virtual,
+    Interface,
+    Dynamic,
+    /// Class initializer run by the first use of its class
+    Initializer,
+}
+
+impl CallKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CallKind::Static => "static",
+            CallKind::Special => "special",
+            CallKind::Virtual => "virtual",
+            CallKind::Interface => "interface",
+            CallKind::Dynamic => "dynamic",
+            CallKind::Initializer => "initializer",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Edge {
+    pub caller: MethodId,
+    /// Offset of the instruction making the call
+    pub offset: u32,
+    pub kind: CallKind,
+    pub callee: MethodId,
+}
+
+/// Reference of reachable code that could not be resolved
+#[derive(Debug, Clone)]
+pub struct Unresolved {
+    pub method: MethodId,
+    pub offset: u32,
+    pub error: ResolutionError,
+}
+
+/// Virtual or interface call, dispatched again when RTA finds more instantiated classes
+#[derive(Clone)]
+struct VirtualCall {
+    caller: MethodId,
+    offset: u32,
+    kind: CallKind,
+    resolved: Member,
+}
+
+#[derive(Debug, Default)]
+pub struct CallGraph {
+    /// Methods reachable from the entry points, including the entry points
+    reachable: BTreeSet<MethodId>,
+    /// Edges by caller, sorted by offset
+    callees: BTreeMap<MethodId, BTreeSet<Edge>>,
+    /// Edges by callee
+    callers: BTreeMap<MethodId, BTreeSet<Edge>>,
+    /// Classes instantiated by reachable code, the only receivers RTA takes into account
+    instantiated: BTreeSet<String>,
+    unresolved: Vec<Unresolved>,
+    /// Methods that are reachable but were not analysed, as they are not in the application
+    library: BTreeSet<MethodId>,
+}
+
+impl CallGraph {
+    pub fn reachable(&self) -> &BTreeSet<MethodId> {
+        &self.reachable
+    }
+
+    pub fn is_reachable(&self, method: &MethodId) -> bool {
+        self.reachable.contains(method)
+    }
+
+    /// Calls made by `method`, in the order of its code
+    pub fn callees(&self, method: &MethodId) -> impl Iterator<Item = &Edge> {
+        self.callees.get(method).into_iter().flatten()
+    }
+
+    /// Calls of `method`, sorted by caller
+    pub fn callers(&self, method: &MethodId) -> impl Iterator<Item = &Edge> {
+        self.callers.get(method).into_iter().flatten()
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = &Edge> {
+        self.callees.values().flatten()
+    }
+
+    pub fn instantiated(&self) -> &BTreeSet<String> {
+        &self.instantiated
+    }
+
+    pub fn unresolved(&self) -> &[Unresolved] {
+        &self.unresolved
+    }
+
+    /// Whether the method was reached but its code not analysed
+    pub fn is_library(&self, method: &MethodId) -> bool {
+        self.library.contains(method)
+    }
+
+    /// Writes the graph in the DOT language of Graphviz, with library methods dashed
+    /// and edges labelled by their kind, except for virtual calls
+    pub fn write_dot<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        writeln!(out, "digraph calls {{")?;
+        writeln!(out, "    node [shape=box, fontname=monospace];")?;
+        for method in &self.reachable {
+            let style = match self.is_library(method) {
+                true => " [style=dashed]",
+                false => "",
+            };
+            writeln!(out, "    {}{style};", dot_string(&method.to_string()))?;
+        }
+        // Parallel edges of one kind from different instructions are drawn once
+        let mut drawn = HashSet::new();
+        for edge in self.edges() {
+            if !drawn.insert((&edge.caller, &edge.callee, edge.kind)) {
+                continue;
+            }
+            let label = match edge.kind {
+                CallKind::Virtual => String::new(),
+                kind => format!(" [label={}]", kind.name()),
+            };
+            writeln!(
+                out,
+                "    {} -> {}{label};",
+                dot_string(&edge.caller.to_string()),
+                dot_string(&edge.callee.to_string())
+            )?;
+        }
+        writeln!(out, "}}")
+    }
+
+    fn add_edge(&mut self, edge: Edge) {
+        self.callers
+            .entry(edge.callee.clone())
+            .or_default()
+            .insert(edge.clone());
+        self.callees
+            .entry(edge.caller.clone())
+            .or_default()
+            .insert(edge);
+    }
+}
+
+/// Quoted identifier of the DOT language
+pub fn dot_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Builds the call graph of the methods reachable from `entry_points`, following code of the
+/// classes for which `analyse` is true. Calls into other classes end at the called method.
+/// `hierarchy` gives the possible receivers of virtual calls, so it should contain
+/// the classes that are analysed.
+pub fn build(
+    class_path: &ClassPath,
+    hierarchy: &Hierarchy,
+    entry_points: &[MethodId],
+    analysis: Analysis,
+    analyse: &dyn Fn(&str) -> bool,
+) -> CallGraph {
+    let mut builder = Builder {
+        class_path,
+        resolver: Resolver::new(class_path),
+        hierarchy,
+        analysis,
+        analyse,
+        graph: CallGraph::default(),
+        pending: vec![],
+        virtual_calls: vec![],
+        calls_by_class: HashMap::new(),
+        new_instances: vec![],
+    };
+    for entry in entry_points {
+        builder.reach(entry.clone());
+    }
+    loop {
+        while let Some(method) = builder.pending.pop() {
+            builder.analyse_method(&method);
+        }
+        // Classes instantiated since calls were dispatched are receivers of calls
+        // resolved to one of their supertypes
+        let new_instances = std::mem::take(&mut builder.new_instances);
+        if new_instances.is_empty() {
+            break;
+        }
+        for receiver in &new_instances {
+            let supertypes =
+                std::iter::once(receiver.as_str()).chain(hierarchy.supertypes(receiver));
+            for supertype in supertypes {
+                let calls = builder
+                    .calls_by_class
+                    .get(supertype)
+                    .cloned()
+                    .unwrap_or_default();
+                for call in calls {
+                    builder.dispatch_to(call, receiver);
+                }
+            }
+        }
+    }
+    builder.graph
+}
+
+struct Builder<'a> {
+    class_path: &'a ClassPath,
+    resolver: Resolver<'a>,
+    hierarchy: &'a Hierarchy,
+    analysis: Analysis,
+    analyse: &'a dyn Fn(&str) -> bool,
+    graph: CallGraph,
+    /// Reachable methods whose code is still to be analysed
+    pending: Vec<MethodId>,
+    /// Virtual calls, kept by RTA to dispatch them to classes instantiated later
+    virtual_calls: Vec<VirtualCall>,
+    /// Indexes of the virtual calls by the class of their resolved method
+    calls_by_class: HashMap<String, Vec<usize>>,
+    /// Classes instantiated since the last dispatch
+    new_instances: Vec<String>,
+}
+
+impl Builder<'_> {
+    fn reach(&mut self, method: MethodId) {
+        if self.graph.reachable.insert(method.clone()) {
+            match (self.analyse)(&method.class) {
+                true => self.pending.push(method),
+                false => {
+                    self.graph.library.insert(method);
+                }
+            }
+        }
+    }
+
+    fn call(&mut self, caller: &MethodId, offset: u32, kind: CallKind, callee: MethodId) {
+        self.graph.add_edge(Edge {
+            caller: caller.clone(),
+            offset,
+            kind,
+            callee: callee.clone(),
+        });
+        self.reach(callee);
+    }
+
+    fn unresolved(&mut self, method: &MethodId, offset: u32, error: ResolutionError) {
+        self.graph.unresolved.push(Unresolved {
+            method: method.clone(),
+            offset,
+            error,
+        });
+    }
+
+    fn analyse_method(&mut self, method: &MethodId) {
+        let class = match self.class_path.class(&method.class) {
+            Ok(class) => class,
+            Err(error) => {
+                let error = ResolutionError {
+                    error: "java/lang/NoClassDefFoundError",
+                    message: error.to_string(),
+                };
+                return self.unresolved(method, 0, error);
+            }
+        };
+        let Some((_, info)) = class.method(&method.name, &method.descriptor) else {
+            let error = ResolutionError {
+                error: "java/lang/NoSuchMethodError",
+                message: method.to_string(),
+            };
+            return self.unresolved(method, 0, error);
+        };
+        let Some(code) = info.code() else {
+            return;
+        };
+        for (offset, instruction) in code.code {
+            let offset = *offset;
+            let result = match instruction {
+                Instruction::InvokeStatic(index) => {
+                    self.direct_call(method, offset, &class, *index, CallKind::Static)
+                }
+                Instruction::InvokeSpecial(index) => {
+                    self.special_call(method, offset, &class, *index)
+                }
+                Instruction::InvokeVirtual(index) => {
+                    self.virtual_call(method, offset, &class, *index, CallKind::Virtual)
+                }
+                Instruction::InvokeInterface(index, _) => {
+                    self.virtual_call(method, offset, &class, *index, CallKind::Interface)
+                }
+                Instruction::InvokeDynamic(index) => {
+                    self.dynamic_call(method, offset, &class, *index);
+                    Ok(())
+                }
+                Instruction::New(index) => {
+                    let name = class_name_at(&class.constant_pool, *index).unwrap_or("?");
+                    if self.graph.instantiated.insert(name.to_string())
+                        && self.analysis == Analysis::Rta
+                    {
+                        self.new_instances.push(name.to_string());
+                    }
+                    self.initialize(method, offset, name);
+                    Ok(())
+                }
+                Instruction::GetStatic(index) | Instruction::PutStatic(index) => {
+                    self.resolver.resolve(&class, *index).map(|field| {
+                        let owner = field.class_name().to_string();
+                        self.initialize(method, offset, &owner);
+                    })
+                }
+                _ => Ok(()),
+            };
+            if let Err(error) = result {
+                self.unresolved(method, offset, error);
+            }
+        }
+    }
+
+    fn direct_call(
+        &mut self,
+        caller: &MethodId,
+        offset: u32,
+        class: &Class,
+        index: u16,
+        kind: CallKind,
+    ) -> Result<(), ResolutionError> {
+        let member = self.resolver.resolve(class, index)?;
+        let owner = member.class_name().to_string();
+        if kind == CallKind::Static {
+            self.initialize(caller, offset, &owner);
+        }
+        self.call(caller, offset, kind, MethodId::of(&member));
+        Ok(())
+    }
+
+    /// invokespecial calls the resolved method, except for calls of superclass methods,
+    /// which are selected starting from the direct superclass of the calling class
+    fn special_call(
+        &mut self,
+        caller: &MethodId,
+        offset: u32,
+        class: &Rc<Class>,
+        index: u16,
+    ) -> Result<(), ResolutionError> {
+        let member = self.resolver.resolve(class, index)?;
+        let method = MethodId::of(&member);
+        let super_call = method.name != "<init>"
+            && member.class_name() != caller.class
+            && !member.class.access_info.contains(AccessFlags::Interface);
+        let super_name = class.super_name().map(str::to_string);
+        let callee = match (super_call, super_name) {
+            (true, Some(super_name)) => self
+                .resolver
+                .select(&super_name, &method.name, &method.descriptor)?
+                .map(|member| MethodId::of(&member))
+                .unwrap_or(method),
+            _ => method,
+        };
+        self.call(caller, offset, CallKind::Special, callee);
+        Ok(())
+    }
+
+    fn virtual_call(
+        &mut self,
+        caller: &MethodId,
+        offset: u32,
+        class: &Class,
+        index: u16,
+        kind: CallKind,
+    ) -> Result<(), ResolutionError> {
+        let resolved = self.resolver.resolve(class, index)?;
+        let flags = resolved.method().access_flags;
+        // Private methods are not overridden
+        if flags.contains(AccessFlags::Private) {
+            self.call(caller, offset, kind, MethodId::of(&resolved));
+            return Ok(());
+        }
+        let call = VirtualCall {
+            caller: caller.clone(),
+            offset,
+            kind,
+            resolved,
+        };
+        self.dispatch(&call);
+        if self.analysis == Analysis::Rta {
+            let owner = call.resolved.class_name().to_string();
+            self.calls_by_class
+                .entry(owner)
+                .or_default()
+                .push(self.virtual_calls.len());
+            self.virtual_calls.push(call);
+        }
+        Ok(())
+    }
+
+    /// Adds edges to the methods selected for the receivers of the call. Calls whose class
+    /// is not analysed also go to the resolved method, since receivers may be unknown classes.
+    fn dispatch(&mut self, call: &VirtualCall) {
+        let owner = call.resolved.class_name();
+        if !(self.analyse)(owner) {
+            let declared = MethodId::of(&call.resolved);
+            self.call(&call.caller, call.offset, call.kind, declared);
+        }
+        let receivers: Vec<&str> = std::iter::once(owner)
+            .chain(self.hierarchy.subtypes(owner))
+            .filter(|name| {
+                self.analysis == Analysis::Cha || self.graph.instantiated.contains(*name)
+            })
+            .collect();
+        for receiver in receivers {
+            self.select(call, receiver);
+        }
+    }
+
+    /// Dispatches the virtual call under `index` to a class instantiated after it was found
+    fn dispatch_to(&mut self, index: usize, receiver: &str) {
+        let call = self.virtual_calls[index].clone();
+        self.select(&call, receiver);
+    }
+
+    /// Adds the edge to the method selected for a receiver of the class, if it can be instantiated
+    fn select(&mut self, call: &VirtualCall, receiver: &str) {
+        let concrete = self.hierarchy.get(receiver).is_some_and(|node| {
+            !node
+                .access_flags
+                .intersects(AccessFlags::Interface | AccessFlags::Abstract)
+        });
+        if !concrete {
+            return;
+        }
+        let declared = MethodId::of(&call.resolved);
+        match self
+            .resolver
+            .select(receiver, &declared.name, &declared.descriptor)
+        {
+            Ok(Some(member)) => {
+                self.call(&call.caller, call.offset, call.kind, MethodId::of(&member))
+            }
+            Ok(None) => {}
+            Err(error) => self.unresolved(&call.caller, call.offset, error),
+        }
+    }
+
+    /// invokedynamic calls methods referred to by method handles among the bootstrap arguments
+    fn dynamic_call(&mut self, caller: &MethodId, offset: u32, class: &Class, index: u16) {
+        let pool = &class.constant_pool;
+        let Some(ConstantPoolInfo::InvokeDynamic {
+            bootstrap_method_attr_index,
+            ..
+        }) = constant(pool, index)
+        else {
+            return;
+        };
+        let Some(bootstrap) = class
+            .bootstrap_methods()
+            .get(*bootstrap_method_attr_index as usize)
+        else {
+            return;
+        };
+        for argument in &bootstrap.bootstrap_arguments {
+            let Some(ConstantPoolInfo::MethodHandle {
+                reference_kind,
+                reference_index,
+            }) = constant(pool, *argument)
+            else {
+                continue;
+            };
+            // Kinds 1 to 4 access fields
+            if *reference_kind < 5 {
+                continue;
+            }
+            let reference = match constant(pool, *reference_index) {
+                Some(ConstantPoolInfo::InterfaceMethodRef { .. }) => Reference::InterfaceMethod,
+                _ => Reference::Method,
+            };
+            let Some((owner, name, descriptor)) = member_ref_at(pool, *reference_index) else {
+                continue;
+            };
+            match self
+                .resolver
+                .resolve_reference(reference, owner, name, descriptor)
+            {
+                Ok(member) => self.call(caller, offset, CallKind::Dynamic, MethodId::of(&member)),
+                Err(error) => self.unresolved(caller, offset, error),
+            }
+        }
+    }
+
+    /// Calls the class initializers of the class and its superclasses that declare one.
+    /// Classes of the caller's superclass chain are initialized before the caller runs.
+    fn initialize(&mut self, caller: &MethodId, offset: u32, class: &str) {
+        let initialized = self.hierarchy.superclass_chain(&caller.class);
+        let mut current = self.class_path.class(class).ok();
+        while let Some(class) = current {
+            let name = class.name().unwrap_or("?");
+            if initialized.contains(&name) {
+                break;
+            }
+            if class.method("<clinit>", "()V").is_some() {
+                let initializer = MethodId::new(name, "<clinit>", "()V");
+                self.call(caller, offset, CallKind::Initializer, initializer);
+            }
+            current = class
+                .super_name()
+                .and_then(|name| self.class_path.class(name).ok());
+        }
+    }
+}
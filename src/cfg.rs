@@ -0,0 +1,556 @@
+//! Control-flow graph of a method body. Code is split into basic blocks at branch targets,
+//! after instructions that do not fall through and at the bounds of exception handler
+//! ranges, so every block lies either entirely inside or outside each protected range.
+//! Blocks are connected by normal edges and by exceptional edges to the handlers protecting
+//! them. Dominators and post-dominators are computed with the algorithm of Cooper, Harvey
+//! and Kennedy, post-dominators relative to a virtual exit that every return and athrow
+//! leads to. Loops are the natural loops of back edges to blocks dominating their source.
+
+use crate::callgraph::dot_string;
+use crate::raw::class::Class;
+use crate::raw::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    /// Offset of the first instruction
+    pub start: u32,
+    /// Offset after the last instruction
+    pub end: u32,
+    /// Indexes of the instructions in the code
+    pub instructions: Range<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EdgeKind {
+    /// To the next instruction, also after a conditional branch that is not taken
+    FallThrough,
+    /// Taken branch, goto, switch case, jsr, or ret back to an instruction following a jsr
+    Branch,
+    /// To an exception handler, with the constant pool index of the caught class,
+    /// 0 for handlers catching everything
+    Exception(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// Natural loop: the header and the blocks that reach a back edge to it without passing it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loop {
+    pub header: usize,
+    /// Sources of the back edges to the header
+    pub latches: Vec<usize>,
+    /// Blocks of the loop including the header, sorted
+    pub blocks: Vec<usize>,
+    /// Header of the innermost loop containing this one
+    pub parent: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ControlFlowGraph<'a> {
+    code: &'a [(u32, Instruction)],
+    blocks: Vec<Block>,
+    /// Sorted, normal edges before exceptional ones of the same blocks
+    edges: Vec<Edge>,
+    successors: Vec<Vec<usize>>,
+    predecessors: Vec<Vec<usize>>,
+    /// Immediate dominator of each block, `None` for the entry and unreachable blocks
+    dominators: Vec<Option<usize>>,
+    /// Immediate post-dominator of each block, `None` if it is the exit
+    post_dominators: Vec<Option<usize>>,
+    /// Blocks that are reachable from the entry
+    reachable: Vec<bool>,
+    /// Loops by header, outer loops first
+    loops: Vec<Loop>,
+}
+
+impl<'a> ControlFlowGraph<'a> {
+    pub fn new(code: &'a [(u32, Instruction)], exception_table: &[Exception]) -> Self {
+        let end = code
+            .last()
+            .map(|(offset, instruction)| offset + instruction.length(*offset))
+            .unwrap_or(0);
+        let index_of = |offset: u32| {
+            code.binary_search_by_key(&offset, |(offset, _)| *offset)
+                .ok()
+        };
+
+        // First instructions of blocks, by index
+        let mut leaders = BTreeSet::from([0]);
+        for (i, (offset, instruction)) in code.iter().enumerate() {
+            let targets = instruction.branch_targets(*offset);
+            if !targets.is_empty() || !falls_through(instruction) {
+                leaders.insert(i + 1);
+            }
+            for target in targets {
+                if let Some(index) = u32::try_from(target).ok().and_then(index_of) {
+                    leaders.insert(index);
+                }
+            }
+        }
+        for exception in exception_table {
+            for offset in [exception.start_pc, exception.end_pc, exception.handler_pc] {
+                if let Some(index) = index_of(offset.into()) {
+                    leaders.insert(index);
+                }
+            }
+        }
+        leaders.retain(|index| *index < code.len());
+
+        let starts: Vec<usize> = leaders.into_iter().collect();
+        let blocks: Vec<Block> = starts
+            .iter()
+            .enumerate()
+            .map(|(i, start)| {
+                let next = starts.get(i + 1).copied().unwrap_or(code.len());
+                Block {
+                    start: code[*start].0,
+                    end: code.get(next).map_or(end, |(offset, _)| *offset),
+                    instructions: *start..next,
+                }
+            })
+            .collect();
+        let block_of = |offset: i64| {
+            let offset = u32::try_from(offset).ok()?;
+            let index = index_of(offset)?;
+            Some(starts.partition_point(|start| *start <= index) - 1)
+        };
+
+        // Instructions following a jsr, where ret may go back to
+        let return_sites: Vec<usize> = code
+            .iter()
+            .filter(|(_, instruction)| {
+                matches!(instruction, Instruction::Jsr(_) | Instruction::JsrW(_))
+            })
+            .filter_map(|(offset, instruction)| {
+                block_of(i64::from(offset + instruction.length(*offset)))
+            })
+            .collect();
+
+        let mut edges = BTreeSet::new();
+        for (from, block) in blocks.iter().enumerate() {
+            let (offset, instruction) = &code[block.instructions.end - 1];
+            for target in instruction.branch_targets(*offset) {
+                if let Some(to) = block_of(target) {
+                    edges.insert(Edge {
+                        from,
+                        to,
+                        kind: EdgeKind::Branch,
+                    });
+                }
+            }
+            if matches!(
+                instruction,
+                Instruction::Ret(_) | Instruction::Wide(Wide::Ret(_))
+            ) {
+                for to in &return_sites {
+                    edges.insert(Edge {
+                        from,
+                        to: *to,
+                        kind: EdgeKind::Branch,
+                    });
+                }
+            }
+            // Returning from a jsr is a ret edge, not a fall-through
+            let jsr = matches!(instruction, Instruction::Jsr(_) | Instruction::JsrW(_));
+            if falls_through(instruction) && !jsr && from + 1 < blocks.len() {
+                edges.insert(Edge {
+                    from,
+                    to: from + 1,
+                    kind: EdgeKind::FallThrough,
+                });
+            }
+            for exception in exception_table {
+                let range = u32::from(exception.start_pc)..u32::from(exception.end_pc);
+                let protected = range.contains(&block.start);
+                if let (true, Some(to)) = (protected, block_of(i64::from(exception.handler_pc))) {
+                    let kind = EdgeKind::Exception(exception.catch_type);
+                    edges.insert(Edge { from, to, kind });
+                }
+            }
+        }
+        let edges: Vec<Edge> = edges.into_iter().collect();
+
+        let mut successors = vec![vec![]; blocks.len()];
+        let mut predecessors = vec![vec![]; blocks.len()];
+        for edge in &edges {
+            if !successors[edge.from].contains(&edge.to) {
+                successors[edge.from].push(edge.to);
+                predecessors[edge.to].push(edge.from);
+            }
+        }
+
+        let mut graph = ControlFlowGraph {
+            code,
+            blocks,
+            edges,
+            successors,
+            predecessors,
+            dominators: vec![],
+            post_dominators: vec![],
+            reachable: vec![],
+            loops: vec![],
+        };
+        graph.analyse();
+        graph
+    }
+
+    /// Computes dominators, post-dominators and loops
+    fn analyse(&mut self) {
+        let count = self.blocks.len();
+        if count == 0 {
+            return;
+        }
+        let (dominators, reachable) =
+            immediate_dominators(count, 0, &self.successors, &self.predecessors);
+        self.dominators = dominators;
+        self.reachable = reachable;
+
+        // The reversed graph with the virtual exit as node `count`, reached from returns and throws
+        let mut successors = self.predecessors.clone();
+        let mut predecessors = self.successors.clone();
+        successors.push(vec![]);
+        predecessors.push(vec![]);
+        let exits: Vec<usize> = (0..count).filter(|block| self.exits(*block)).collect();
+        for block in exits {
+            successors[count].push(block);
+            predecessors[block].push(count);
+        }
+        let (post_dominators, _) =
+            immediate_dominators(count + 1, count, &successors, &predecessors);
+        self.post_dominators = post_dominators[..count]
+            .iter()
+            .map(|dominator| dominator.filter(|block| *block != count))
+            .collect();
+
+        self.loops = self.find_loops();
+    }
+
+    /// Whether the block leaves the method by returning or throwing, or runs off the end
+    fn exits(&self, block: usize) -> bool {
+        let (_, instruction) = &self.code[self.blocks[block].instructions.end - 1];
+        matches!(
+            instruction,
+            Instruction::IReturn
+                | Instruction::LReturn
+                | Instruction::FReturn
+                | Instruction::DReturn
+                | Instruction::AReturn
+                | Instruction::Return
+                | Instruction::AThrow
+        ) || self.successors[block].is_empty()
+    }
+
+    fn find_loops(&self) -> Vec<Loop> {
+        let mut latches: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for edge in &self.edges {
+            if self.dominates(edge.to, edge.from)
+                && !latches
+                    .get(&edge.to)
+                    .is_some_and(|latches| latches.contains(&edge.from))
+            {
+                latches.entry(edge.to).or_default().push(edge.from);
+            }
+        }
+        let mut loops: Vec<Loop> = latches
+            .into_iter()
+            .map(|(header, latches)| {
+                // Blocks reaching a latch backwards without passing the header
+                let mut blocks = BTreeSet::from([header]);
+                let mut stack = latches.clone();
+                while let Some(block) = stack.pop() {
+                    if blocks.insert(block) {
+                        stack.extend(&self.predecessors[block]);
+                    }
+                }
+                Loop {
+                    header,
+                    latches,
+                    blocks: blocks.into_iter().collect(),
+                    parent: None,
+                }
+            })
+            .collect();
+        // Outer loops have more blocks, and the innermost containing loop is the smallest one
+        loops.sort_by_key(|found| (std::cmp::Reverse(found.blocks.len()), found.header));
+        for i in 0..loops.len() {
+            let header = loops[i].header;
+            loops[i].parent = loops[..i]
+                .iter()
+                .rev()
+                .find(|outer| outer.blocks.binary_search(&header).is_ok())
+                .map(|outer| outer.header);
+        }
+        loops
+    }
+
+    pub fn code(&self) -> &'a [(u32, Instruction)] {
+        self.code
+    }
+
+    /// Blocks in the order of their offsets, the entry first
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    /// Instructions of the block with their offsets
+    pub fn instructions(&self, block: usize) -> &'a [(u32, Instruction)] {
+        &self.code[self.blocks[block].instructions.clone()]
+    }
+
+    /// Block containing the instruction at `offset`
+    pub fn block_at(&self, offset: u32) -> Option<usize> {
+        let block = self
+            .blocks
+            .partition_point(|block| block.start <= offset)
+            .checked_sub(1)?;
+        (offset < self.blocks[block].end).then_some(block)
+    }
+
+    /// All edges sorted by source, target and kind
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    /// Edges leaving the block
+    pub fn out_edges(&self, block: usize) -> impl Iterator<Item = &Edge> {
+        let start = self.edges.partition_point(|edge| edge.from < block);
+        self.edges[start..]
+            .iter()
+            .take_while(move |edge| edge.from == block)
+    }
+
+    /// Distinct blocks the block has edges to, in the order of the edges
+    pub fn successors(&self, block: usize) -> &[usize] {
+        &self.successors[block]
+    }
+
+    pub fn predecessors(&self, block: usize) -> &[usize] {
+        &self.predecessors[block]
+    }
+
+    pub fn is_reachable(&self, block: usize) -> bool {
+        self.reachable[block]
+    }
+
+    /// Immediate dominator, `None` for the entry and blocks that are not reachable
+    pub fn immediate_dominator(&self, block: usize) -> Option<usize> {
+        self.dominators[block]
+    }
+
+    /// Whether every path from the entry to `b` passes `a`. Blocks dominate themselves.
+    pub fn dominates(&self, a: usize, b: usize) -> bool {
+        if !self.reachable[b] {
+            return false;
+        }
+        let mut current = Some(b);
+        while let Some(block) = current {
+            if block == a {
+                return true;
+            }
+            current = self.dominators[block];
+        }
+        false
+    }
+
+    /// Immediate post-dominator, `None` if no block is passed on every path from the block
+    /// to the exit, or if the exit cannot be reached from it as in an endless loop
+    pub fn immediate_post_dominator(&self, block: usize) -> Option<usize> {
+        self.post_dominators[block]
+    }
+
+    /// Whether every path from `b` to the exit passes `a`. Blocks post-dominate themselves.
+    pub fn post_dominates(&self, a: usize, b: usize) -> bool {
+        let mut current = Some(b);
+        while let Some(block) = current {
+            if block == a {
+                return true;
+            }
+            current = self.post_dominators[block];
+        }
+        false
+    }
+
+    /// Blocks immediately dominated by the block, its children in the dominator tree
+    pub fn dominated(&self, block: usize) -> Vec<usize> {
+        (0..self.blocks.len())
+            .filter(|other| self.dominators[*other] == Some(block))
+            .collect()
+    }
+
+    /// Natural loops, outer loops before the loops nested in them. Loops with the
+    /// same header are merged. Cycles entered at several blocks are not natural loops.
+    pub fn loops(&self) -> &[Loop] {
+        &self.loops
+    }
+
+    /// Loops containing the block, innermost first
+    pub fn loops_of(&self, block: usize) -> Vec<&Loop> {
+        let mut loops: Vec<&Loop> = self
+            .loops
+            .iter()
+            .filter(|found| found.blocks.binary_search(&block).is_ok())
+            .collect();
+        loops.reverse();
+        loops
+    }
+
+    /// Writes the graph in the DOT language of Graphviz with the instructions of each block.
+    /// Exceptional edges are dashed and labelled with the caught class, back edges are bold.
+    pub fn write_dot<W: Write>(
+        &self,
+        out: &mut W,
+        name: &str,
+        class: &Class,
+    ) -> std::io::Result<()> {
+        // Branch targets start blocks, so they are shown as the names of the blocks
+        let labels = Labels::named("B", self.blocks.iter().map(|block| block.start).collect());
+        writeln!(out, "digraph {} {{", dot_string(name))?;
+        writeln!(out, "    node [shape=box, fontname=monospace];")?;
+        for index in 0..self.blocks.len() {
+            // Left-justified lines end with \l
+            let mut label = format!("B{index}\\l");
+            for (offset, instruction) in self.instructions(index) {
+                let text = match instruction.constant_index() {
+                    Some(constant) => format!(
+                        "{} {}",
+                        instruction.mnemonic(),
+                        crate::javap::constant_comment(class, constant)
+                    ),
+                    None => labels.instruction(*offset, instruction).to_string(),
+                };
+                label.push_str(&dot_escape(&format!("{offset}: {text}")));
+                label.push_str("\\l");
+            }
+            let style = match self.reachable[index] {
+                true => "",
+                false => ", style=dashed",
+            };
+            writeln!(out, "    B{index} [label=\"{label}\"{style}];")?;
+        }
+        for edge in &self.edges {
+            let mut attributes = vec![];
+            let mut styles = vec![];
+            if let EdgeKind::Exception(catch_type) = edge.kind {
+                let caught = match catch_type {
+                    0 => "any",
+                    index => class_name_at(&class.constant_pool, index).unwrap_or("?"),
+                };
+                attributes.push(format!("label={}", dot_string(caught)));
+                styles.push("dashed");
+            }
+            if self.dominates(edge.to, edge.from) {
+                styles.push("bold");
+            }
+            if !styles.is_empty() {
+                attributes.push(format!("style=\"{}\"", styles.join(",")));
+            }
+            let attributes = match attributes.is_empty() {
+                true => String::new(),
+                false => format!(" [{}]", attributes.join(", ")),
+            };
+            writeln!(out, "    B{} -> B{}{attributes};", edge.from, edge.to)?;
+        }
+        writeln!(out, "}}")
+    }
+}
+
+/// Whether execution can continue with the next instruction
+fn falls_through(instruction: &Instruction) -> bool {
+    !matches!(
+        instruction,
+        Instruction::Goto(_)
+            | Instruction::GotoW(_)
+            | Instruction::TableSwitch { .. }
+            | Instruction::LookupSwitch { .. }
+            | Instruction::Ret(_)
+            | Instruction::Wide(Wide::Ret(_))
+            | Instruction::IReturn
+            | Instruction::LReturn
+            | Instruction::FReturn
+            | Instruction::DReturn
+            | Instruction::AReturn
+            | Instruction::Return
+            | Instruction::AThrow
+    )
+}
+
+/// Immediate dominators of the nodes reachable from `entry`, by the iterative algorithm of
+/// Cooper, Harvey and Kennedy over the reverse postorder. Also returns which nodes are reachable.
+fn immediate_dominators(
+    count: usize,
+    entry: usize,
+    successors: &[Vec<usize>],
+    predecessors: &[Vec<usize>],
+) -> (Vec<Option<usize>>, Vec<bool>) {
+    // Postorder by an iterative depth first search
+    let mut order = vec![];
+    let mut visited = vec![false; count];
+    let mut stack = vec![(entry, 0)];
+    visited[entry] = true;
+    while let Some(top) = stack.last_mut() {
+        let (node, next) = *top;
+        top.1 += 1;
+        match successors[node].get(next) {
+            Some(successor) if !visited[*successor] => {
+                visited[*successor] = true;
+                stack.push((*successor, 0));
+            }
+            Some(_) => {}
+            None => {
+                order.push(node);
+                stack.pop();
+            }
+        }
+    }
+    let mut position = vec![usize::MAX; count];
+    for (i, node) in order.iter().enumerate() {
+        position[*node] = i;
+    }
+
+    let mut dominators: Vec<Option<usize>> = vec![None; count];
+    dominators[entry] = Some(entry);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for node in order.iter().rev().filter(|node| **node != entry) {
+            let mut processed = predecessors[*node]
+                .iter()
+                .filter(|predecessor| dominators[**predecessor].is_some());
+            let Some(first) = processed.next() else {
+                continue;
+            };
+            let mut dominator = *first;
+            for predecessor in processed {
+                // Walks both up the tree to their common dominator
+                let mut a = *predecessor;
+                while a != dominator {
+                    while position[a] < position[dominator] {
+                        a = dominators[a].expect("processed nodes have dominators");
+                    }
+                    while position[dominator] < position[a] {
+                        dominator = dominators[dominator].expect("processed nodes have dominators");
+                    }
+                }
+            }
+            if dominators[*node] != Some(dominator) {
+                dominators[*node] = Some(dominator);
+                changed = true;
+            }
+        }
+    }
+    dominators[entry] = None;
+    (dominators, visited)
+}
+
+/// Text escaped for a quoted DOT string
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,394 @@
+//! Class path: directories and archives searched in order for classes by their
+//! internal name, like the JVM searches its boot and application class paths.
+//! Classes are parsed once and shared, so that features following references
+//! from one class to another can ask for the same class many times.
+
+use crate::jar::JarFile;
+use crate::jimage::JImage;
+use crate::jmod::JmodFile;
+use crate::raw::class::Class;
+use crate::raw::*;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Directory or archive of the class path
+pub enum ClassPathEntry {
+    /// Classes in subdirectories by package, like `com/foo/Bar.class`
+    Directory(PathBuf),
+    Jar(JarFile),
+    Jmod(JmodFile),
+    /// Runtime image of a JDK, `lib/modules`
+    Image(JImage),
+}
+
+impl ClassPathEntry {
+    /// Opens the entry by the kind of the path: JAR, ZIP and JMOD files by their extension,
+    /// jimage files by their magic number and directories. The home directory of a JDK
+    /// stands for its runtime image, `lib/modules`, or before JDK 9 for its `rt.jar`.
+    pub fn open(path: impl AsRef<Path>) -> Result<ClassPathEntry> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            let image = path.join("lib").join("modules");
+            if image.is_file() {
+                return Ok(ClassPathEntry::Image(JImage::open(image)?));
+            }
+            return Ok(match rt_jar(path) {
+                Some(jar) => ClassPathEntry::Jar(JarFile::open(jar)?),
+                None => ClassPathEntry::Directory(path.to_path_buf()),
+            });
+        }
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        match extension {
+            Some("jar" | "zip") => Ok(ClassPathEntry::Jar(JarFile::open(path)?)),
+            Some("jmod") => Ok(ClassPathEntry::Jmod(JmodFile::open(path)?)),
+            _ => Ok(ClassPathEntry::Image(JImage::open(path)?)),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            ClassPathEntry::Directory(path) => path,
+            ClassPathEntry::Jar(jar) => jar.path(),
+            ClassPathEntry::Jmod(jmod) => jmod.path(),
+            ClassPathEntry::Image(image) => image.path(),
+        }
+    }
+
+    /// Where the class `name` is stored in this entry, for messages
+    pub fn location(&self, name: &str, release: Option<u16>) -> String {
+        match self {
+            ClassPathEntry::Directory(path) => {
+                path.join(format!("{name}.class")).display().to_string()
+            }
+            _ => format!(
+                "{}!/{}",
+                self.path().display(),
+                self.entry_name(name, release)
+            ),
+        }
+    }
+
+    pub fn contains(&self, name: &str, release: Option<u16>) -> bool {
+        match self {
+            ClassPathEntry::Directory(path) => path.join(format!("{name}.class")).is_file(),
+            ClassPathEntry::Jar(jar) => jar.contains(&self.entry_name(name, release)),
+            ClassPathEntry::Jmod(jmod) => jmod.contains(name),
+            ClassPathEntry::Image(image) => image.contains(name),
+        }
+    }
+
+    /// Bytes of the class with the internal name `name`
+    pub fn read_class(&self, name: &str, release: Option<u16>) -> Result<Vec<u8>> {
+        match self {
+            ClassPathEntry::Directory(path) => std::fs::read(path.join(format!("{name}.class"))),
+            ClassPathEntry::Jar(jar) => jar.read(&self.entry_name(name, release)),
+            ClassPathEntry::Jmod(jmod) => jmod.read_class(name),
+            ClassPathEntry::Image(image) => image.read_class(name),
+        }
+    }
+
+    /// Internal names of all classes in the entry, without `module-info`
+    pub fn class_names(&self, release: Option<u16>) -> Result<Vec<String>> {
+        let base_name = |entry: &str| {
+            let entry = crate::jar::split_versioned(entry).map_or(entry, |(_, name)| name);
+            Some(entry.strip_suffix(".class")?.to_string())
+        };
+        let mut names: Vec<String> = match self {
+            ClassPathEntry::Directory(path) => {
+                let mut names = vec![];
+                directory_class_names(path, "", &mut names)?;
+                names
+            }
+            ClassPathEntry::Jar(jar) => match release {
+                Some(release) => jar.release_class_entries(release),
+                None => jar.class_entries().collect(),
+            }
+            .into_iter()
+            .filter_map(base_name)
+            .collect(),
+            ClassPathEntry::Jmod(jmod) => jmod.class_names().map(str::to_string).collect(),
+            ClassPathEntry::Image(image) => image
+                .class_names()
+                .into_iter()
+                .map(|(_, name)| name)
+                .collect(),
+        };
+        names.retain(|name| !name.ends_with("module-info"));
+        Ok(names)
+    }
+
+    /// Entry name of the class in archives, with `release` selecting among the versions
+    /// of multi-release JARs. JMOD files and images name entries like JARs, as the
+    /// command line does.
+    fn entry_name(&self, name: &str, release: Option<u16>) -> String {
+        let entry = format!("{name}.class");
+        match (self, release) {
+            (ClassPathEntry::Jar(jar), Some(release)) => {
+                jar.versioned_entry(&entry, release).to_string()
+            }
+            _ => entry,
+        }
+    }
+}
+
+fn directory_class_names(directory: &Path, package: &str, names: &mut Vec<String>) -> Result<()> {
+    let mut entries: Vec<_> = std::fs::read_dir(directory)?.collect::<Result<_>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let file_name = entry.file_name();
+        let Some(file_name) = file_name.to_str() else {
+            continue;
+        };
+        let name = match package {
+            "" => file_name.to_string(),
+            _ => format!("{package}/{file_name}"),
+        };
+        if entry.file_type()?.is_dir() {
+            directory_class_names(&entry.path(), &name, names)?;
+        } else if let Some(name) = name.strip_suffix(".class") {
+            names.push(name.to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Class found in more than one entry of the class path, where only the first one is used
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shadowed {
+    pub name: String,
+    /// Locations of the class in the order of the class path, the first one is used
+    pub locations: Vec<String>,
+}
+
+/// Entries searched in order for classes, with the classes read so far
+#[derive(Default)]
+pub struct ClassPath {
+    entries: Vec<ClassPathEntry>,
+    /// Release that selects classes of multi-release JARs, the base versions if not set
+    release: Option<u16>,
+    /// Parsed classes by name, `None` for names that are not on the class path
+    cache: RefCell<HashMap<String, Option<Rc<Class>>>>,
+    /// Classes given directly, which are found before any entry
+    defined: RefCell<HashMap<String, Rc<Class>>>,
+}
+
+impl ClassPath {
+    pub fn new() -> ClassPath {
+        ClassPath::default()
+    }
+
+    /// Class path from a list like the `CLASSPATH` variable, separated by `:` (`;` on
+    /// Windows), where empty elements stand for the current directory
+    pub fn parse(paths: &str) -> Result<ClassPath> {
+        let mut class_path = ClassPath::new();
+        for path in std::env::split_paths(paths) {
+            match path.as_os_str().is_empty() {
+                true => class_path.add(".")?,
+                false => class_path.add(&path)?,
+            }
+        }
+        Ok(class_path)
+    }
+
+    pub fn set_release(&mut self, release: Option<u16>) {
+        self.release = release;
+        self.cache.get_mut().clear();
+    }
+
+    /// Opens the path and adds it to the end, see [`ClassPathEntry::open`]
+    pub fn add(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let entry = ClassPathEntry::open(path)
+            .map_err(|error| Error::new(error.kind(), format!("{}: {error}", path.display())))?;
+        self.push(entry);
+        Ok(())
+    }
+
+    pub fn push(&mut self, entry: ClassPathEntry) {
+        self.entries.push(entry);
+        // Classes that were not found may be in the new entry
+        self.cache.get_mut().retain(|_, class| class.is_some());
+    }
+
+    /// Adds `entry` in front, so that its classes shadow those of all other entries
+    pub fn push_front(&mut self, entry: ClassPathEntry) {
+        self.entries.insert(0, entry);
+        self.cache.get_mut().clear();
+    }
+
+    /// Makes `class` found by its name before the classes of all entries, like a class
+    /// defined by a class loader. Returns false if the class has no valid name.
+    pub fn define(&self, class: Rc<Class>) -> bool {
+        let Some(name) = class.name() else {
+            return false;
+        };
+        self.defined.borrow_mut().insert(name.to_string(), class);
+        true
+    }
+
+    pub fn entries(&self) -> &[ClassPathEntry] {
+        &self.entries
+    }
+
+    /// First entry that contains the class `name`, given by its internal name
+    pub fn locate(&self, name: &str) -> Option<&ClassPathEntry> {
+        if !is_valid_name(name) {
+            return None;
+        }
+        self.entries
+            .iter()
+            .find(|entry| entry.contains(name, self.release))
+    }
+
+    /// Module of the class `name` if it is read from a runtime image, like `java.base`
+    pub fn module(&self, name: &str) -> Option<&str> {
+        match self.locate(name)? {
+            ClassPathEntry::Image(image) => image.module_of_package(name.rsplit_once('/')?.0),
+            _ => None,
+        }
+    }
+
+    /// Where the class `name` is read from, for messages
+    pub fn location(&self, name: &str) -> Option<String> {
+        Some(self.locate(name)?.location(name, self.release))
+    }
+
+    /// Parses the class with the internal name `name`, like `java/io/PrintStream`, from the
+    /// first entry that contains it, unless it was defined. Classes are cached, as well as
+    /// names that are not found.
+    pub fn class(&self, name: &str) -> Result<Rc<Class>> {
+        if let Some(class) = self.defined.borrow().get(name) {
+            return Ok(class.clone());
+        }
+        if let Some(cached) = self.cache.borrow().get(name) {
+            return cached.clone().ok_or_else(|| not_found(name));
+        }
+        let Some(entry) = self.locate(name) else {
+            self.cache.borrow_mut().insert(name.to_string(), None);
+            return Err(not_found(name));
+        };
+
+        let location = || entry.location(name, self.release);
+        let in_location =
+            |error: Error| Error::new(error.kind(), format!("{}: {error}", location()));
+        let bytes = entry.read_class(name, self.release).map_err(in_location)?;
+        let mut class = Class::from(bytes.as_slice()).map_err(in_location)?;
+        class.resolve_attributes().map_err(in_location)?;
+        // Like the JVM, which throws NoClassDefFoundError for classes in the wrong file
+        if class.name() != Some(name) {
+            return Err(invalid_data(format!(
+                "{} contains {}, not {name}",
+                location(),
+                class.name().unwrap_or("an unnamed class")
+            )));
+        }
+
+        let class = Rc::new(class);
+        self.cache
+            .borrow_mut()
+            .insert(name.to_string(), Some(class.clone()));
+        Ok(class)
+    }
+
+    /// Classes that are in several entries, sorted by name. Reads the names of all classes.
+    pub fn shadowed(&self) -> Result<Vec<Shadowed>> {
+        let mut locations: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for entry in &self.entries {
+            for name in entry.class_names(self.release)? {
+                let location = entry.location(&name, self.release);
+                locations.entry(name).or_default().push(location);
+            }
+        }
+        Ok(locations
+            .into_iter()
+            .filter(|(_, locations)| locations.len() > 1)
+            .map(|(name, locations)| Shadowed { name, locations })
+            .collect())
+    }
+
+    /// Class referenced by the constant pool entry under `index` of `class`: the class of
+    /// Class entries, the owner of field and method references, or the owner of the member
+    /// a method handle refers to
+    pub fn referenced_class(&self, class: &Class, index: u16) -> Result<Rc<Class>> {
+        let pool = &class.constant_pool;
+        let class_index = match constant(pool, index) {
+            Some(ConstantPoolInfo::Class { .. }) => index,
+            Some(
+                ConstantPoolInfo::FieldRef { class_index, .. }
+                | ConstantPoolInfo::MethodRef { class_index, .. }
+                | ConstantPoolInfo::InterfaceMethodRef { class_index, .. },
+            ) => *class_index,
+            Some(ConstantPoolInfo::MethodHandle {
+                reference_index, ..
+            }) => return self.referenced_class(class, *reference_index),
+            Some(other) => {
+                return Err(invalid_data(format!(
+                    "#{index} is a {} and references no class",
+                    other.tag_name()
+                )))
+            }
+            None => {
+                return Err(invalid_data(format!(
+                    "#{index} is not in the constant pool"
+                )))
+            }
+        };
+        let name = class_name_at(pool, class_index)
+            .ok_or_else(|| invalid_data(format!("#{class_index} is not a valid class")))?;
+        if name.starts_with('[') {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{name} is an array class, which has no class file"),
+            ));
+        }
+        self.class(name)
+    }
+
+    /// Direct superclass of `class`, `None` for `java/lang/Object`
+    pub fn super_class(&self, class: &Class) -> Result<Option<Rc<Class>>> {
+        class.super_name().map(|name| self.class(name)).transpose()
+    }
+}
+
+fn not_found(name: &str) -> Error {
+    Error::new(
+        ErrorKind::NotFound,
+        format!("class {name} not found on the class path"),
+    )
+}
+
+/// Internal names that are safe to look up in directories: no empty, `.` or `..` parts
+fn is_valid_name(name: &str) -> bool {
+    name.split('/')
+        .all(|part| !part.is_empty() && part != "." && part != "..")
+        && !name.contains('\\')
+}
+
+/// Archive of the classes of a JDK before JDK 9 in its home directory, `jre/lib/rt.jar`,
+/// or `lib/rt.jar` for a JRE
+fn rt_jar(home: &Path) -> Option<PathBuf> {
+    [home.join("jre"), home.to_path_buf()]
+        .into_iter()
+        .map(|home| home.join("lib").join("rt.jar"))
+        .find(|jar| jar.is_file())
+}
+
+/// Home directory of the JDK that runs `java`: `JAVA_HOME`, or the installation the
+/// `java` executable on the `PATH` belongs to
+pub fn find_jdk() -> Option<PathBuf> {
+    let is_jdk = |home: &Path| home.join("lib").join("modules").is_file() || rt_jar(home).is_some();
+    if let Some(home) = std::env::var_os("JAVA_HOME").map(PathBuf::from) {
+        if is_jdk(&home) {
+            return Some(home);
+        }
+    }
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path).find_map(|directory| {
+        let java = std::fs::canonicalize(directory.join("java")).ok()?;
+        let home = java.parent()?.parent()?;
+        is_jdk(home).then(|| home.to_path_buf())
+    })
+}
@@ -0,0 +1,1660 @@
+//! Command line interface of the `hava` binary
+
+use crate::assembly;
+use crate::callgraph::{self, Analysis, CallGraph, MethodId};
+use crate::cfg::{ControlFlowGraph, EdgeKind};
+use crate::classpath::{find_jdk, ClassPath, ClassPathEntry};
+use crate::hierarchy::{Hierarchy, OBJECT};
+use crate::interpreter::Interpreter;
+use crate::jar::{split_versioned, JarFile};
+use crate::javap;
+use crate::jimage::JImage;
+use crate::jmod::JmodFile;
+use crate::raw::class::Class;
+use crate::raw::*;
+use crate::resolve::Resolver;
+use crate::verify;
+use std::collections::HashSet;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+pub const USAGE: &str = "\
+usage: hava [command] [options] <files...>
+
+commands:
+  dump        disassemble classes (default)
+  constants   print constant pool
+  methods     list methods with their access flags and descriptors
+  verify      check that classes are well formed and type check their code
+  strings     print string literals
+  diff        compare two classes
+  hexdump     dump bytes of the file labelled with structures they belong to
+  assemble    turn assembly listings (as printed by dump) into class files
+  versions    list classes of multi-release JARs with versioned overrides
+  find        show where classes, given by name, are loaded from
+  shadowed    list classes found more than once on the class path
+  hierarchy   print the class hierarchy of the classes, or of one of them
+  resolve     resolve field and method references to their declarations
+  callgraph   print the methods reachable from main methods and their calls
+  cfg         print basic blocks, dominators and loops of the methods
+  run         run the main method of a class, or the Main-Class of a JAR
+
+options:
+  --format text|javap|json   output format of dump (json requires the `json` feature)
+  --format dot               print the call graph or control-flow graphs as
+                             Graphviz graphs
+  --javap                    same as --format javap
+  --method <pattern>         only methods with matching name
+  --descriptor <pattern>     only methods with matching descriptor
+  --class <name>             class whose supertypes and subtypes hierarchy prints
+  --entry <method>           entry point of callgraph instead of the main methods,
+                             like com.foo.Bar.run or Bar.run(I)V, may be repeated
+  --rta                      only dispatch calls to classes instantiated by
+                             reachable code (rapid type analysis)
+  --callers <method>         print the calls of the method found by callgraph
+  --callees <method>         print the calls made by the method
+  --max-heap <size>          bytes the objects of run may take, like 512k, 64m
+                             or 1g (default: 256m)
+  --gc-stats                 print what the garbage collector of run did
+  --release <n>              read multi-release JARs as Java release n does
+                             (default for run: the release of the JDK)
+  -cp, --class-path <paths>  directories and archives searched by find,
+                             shadowed, resolve, callgraph, run and for
+                             supertypes by hierarchy and verify, separated by
+                             ':' (default: CLASSPATH or the current directory)
+  --jdk <dir>                JDK searched before the class path (default:
+                             JAVA_HOME or the one running java)
+  -o, --output <file>        class file written by assemble (default: input
+                             file with .class extension)
+  -h, --help                 print this help
+
+Files may be class files, directories (searched for class files recursively),
+glob patterns using *, ? and **, archives (all classes inside) and single
+archive entries like app.jar!/com/foo/Bar.class, where the entry may contain
+* and ? as well. Archives are JAR or ZIP files, JMOD files and the lib/modules
+image of a JDK, whose entries are named by class like java/lang/String.class.
+Like java -jar, run of a JAR searches the JAR and its manifest Class-Path
+instead of --class-path.
+
+exit status: 0 success, 1 verification failed, classes differ or the program run
+             threw an exception, 2 invalid usage, 3 some files could not be read
+             or parsed";
+
+pub const EXIT_SUCCESS: i32 = 0;
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_ERROR: i32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Dump,
+    Constants,
+    Methods,
+    Verify,
+    Strings,
+    Diff,
+    Hexdump,
+    Assemble,
+    Versions,
+    Find,
+    Shadowed,
+    Hierarchy,
+    Resolve,
+    CallGraph,
+    Cfg,
+    Run,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Javap,
+    Json,
+    Dot,
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub command: Command,
+    pub format: Format,
+    pub method: Option<String>,
+    pub descriptor: Option<String>,
+    pub class: Option<String>,
+    pub output: Option<String>,
+    pub release: Option<u16>,
+    pub class_path: Option<String>,
+    pub jdk: Option<String>,
+    pub entries: Vec<String>,
+    pub rta: bool,
+    pub callers: Option<String>,
+    pub callees: Option<String>,
+    pub max_heap: Option<usize>,
+    pub gc_stats: bool,
+    pub files: Vec<String>,
+}
+
+impl Options {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        let mut options = Options {
+            command: Command::Dump,
+            format: Format::Text,
+            method: None,
+            descriptor: None,
+            class: None,
+            output: None,
+            release: None,
+            class_path: None,
+            jdk: None,
+            entries: vec![],
+            rta: false,
+            callers: None,
+            callees: None,
+            max_heap: None,
+            gc_stats: false,
+            files: vec![],
+        };
+
+        let mut first = true;
+        while let Some(arg) = args.next() {
+            if std::mem::take(&mut first) {
+                let command = match arg.as_str() {
+                    "dump" => Some(Command::Dump),
+                    "constants" => Some(Command::Constants),
+                    "methods" => Some(Command::Methods),
+                    "verify" => Some(Command::Verify),
+                    "strings" => Some(Command::Strings),
+                    "diff" => Some(Command::Diff),
+                    "hexdump" => Some(Command::Hexdump),
+                    "assemble" => Some(Command::Assemble),
+                    "versions" => Some(Command::Versions),
+                    "find" => Some(Command::Find),
+                    "shadowed" => Some(Command::Shadowed),
+                    "hierarchy" => Some(Command::Hierarchy),
+                    "resolve" => Some(Command::Resolve),
+                    "callgraph" => Some(Command::CallGraph),
+                    "cfg" => Some(Command::Cfg),
+                    "run" => Some(Command::Run),
+                    _ => None,
+                };
+                if let Some(command) = command {
+                    options.command = command;
+                    continue;
+                }
+            }
+
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| format!("option {name} requires a value"))
+            };
+
+            match arg.as_str() {
+                "--javap" => options.format = Format::Javap,
+                "--format" => {
+                    options.format = match value("--format")?.as_str() {
+                        "text" => Format::Text,
+                        "javap" => Format::Javap,
+                        "json" => Format::Json,
+                        "dot" => Format::Dot,
+                        other => return Err(format!("unknown format {other:?}")),
+                    }
+                }
+                "--method" => options.method = Some(value("--method")?),
+                "--descriptor" => options.descriptor = Some(value("--descriptor")?),
+                "--class" => options.class = Some(value("--class")?.replace('.', "/")),
+                "-o" | "--output" => options.output = Some(value(&arg)?),
+                "--release" => {
+                    let release = value("--release")?;
+                    match release.parse() {
+                        Ok(release) => options.release = Some(release),
+                        Err(_) => return Err(format!("invalid release {release:?}")),
+                    }
+                }
+                "-cp" | "--class-path" => options.class_path = Some(value(&arg)?),
+                "--jdk" => options.jdk = Some(value("--jdk")?),
+                "--entry" => options.entries.push(value("--entry")?),
+                "--rta" => options.rta = true,
+                "--callers" => options.callers = Some(value("--callers")?),
+                "--callees" => options.callees = Some(value("--callees")?),
+                "--max-heap" => {
+                    let size = value("--max-heap")?;
+                    match parse_size(&size) {
+                        Some(size) => options.max_heap = Some(size),
+                        None => return Err(format!("invalid heap size {size:?}")),
+                    }
+                }
+                "--gc-stats" => options.gc_stats = true,
+                "-h" | "--help" => return Err(String::new()),
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    return Err(format!("unknown option {arg}"))
+                }
+                _ => options.files.push(arg),
+            }
+        }
+
+        if options.files.is_empty() && options.command != Command::Shadowed {
+            return Err("no input files".to_string());
+        }
+        if !options.files.is_empty() && options.command == Command::Shadowed {
+            return Err("shadowed takes no files, it lists the class path".to_string());
+        }
+        if options.command == Command::Diff && options.files.len() != 2 {
+            return Err("diff requires exactly two files".to_string());
+        }
+        if options.command == Command::Run && options.files.len() != 1 {
+            return Err("run requires exactly one class".to_string());
+        }
+        if options.command != Command::Run && (options.max_heap.is_some() || options.gc_stats) {
+            return Err("--max-heap and --gc-stats require run".to_string());
+        }
+        if options.output.is_some()
+            && (options.command != Command::Assemble || options.files.len() != 1)
+        {
+            return Err("--output requires assemble with a single file".to_string());
+        }
+        let call_graph = options.command == Command::CallGraph;
+        let graph = call_graph || options.command == Command::Cfg;
+        if (options.format == Format::Dot) != graph && options.format != Format::Text {
+            return Err(match graph {
+                true => "callgraph and cfg print text or dot".to_string(),
+                false => "dot output requires callgraph or cfg".to_string(),
+            });
+        }
+        let queries = options.callers.is_some() || options.callees.is_some();
+        if !call_graph && (queries || options.rta || !options.entries.is_empty()) {
+            return Err("--entry, --rta, --callers and --callees require callgraph".to_string());
+        }
+        if queries && options.format == Format::Dot {
+            return Err("--callers and --callees print text".to_string());
+        }
+        if options.format == Format::Json && cfg!(not(feature = "json")) {
+            return Err(
+                "JSON output requires hava to be built with the `json` feature".to_string(),
+            );
+        }
+        Ok(options)
+    }
+
+    fn matches(&self, class: &Class, method: &MethodInfo) -> bool {
+        let name = utf8_at(&class.constant_pool, method.name_index).unwrap_or_default();
+        let descriptor = utf8_at(&class.constant_pool, method.descriptor_index).unwrap_or_default();
+        self.method
+            .as_deref()
+            .is_none_or(|p| wildcard_match(p, name))
+            && self
+                .descriptor
+                .as_deref()
+                .is_none_or(|p| wildcard_match(p, descriptor))
+    }
+}
+
+/// Class file named on the command line, possibly stored in an archive
+enum Source {
+    File(PathBuf),
+    Entry { archive: Archive, name: String },
+}
+
+impl Source {
+    /// Path for messages, `app.jar!/com/foo/Bar.class` for archive entries
+    fn path(&self) -> PathBuf {
+        match self {
+            Source::File(path) => path.clone(),
+            Source::Entry { archive, name } => {
+                format!("{}!/{name}", archive.path().display()).into()
+            }
+        }
+    }
+
+    fn read(&self) -> std::io::Result<Vec<u8>> {
+        match self {
+            Source::File(path) => std::fs::read(path),
+            Source::Entry { archive, name } => archive.read(name),
+        }
+    }
+}
+
+/// Archive of classes, opened once and shared by the sources of its entries. Entries of
+/// JMOD files and JDK images are named like class files of a directory, `java/lang/String.class`.
+#[derive(Clone)]
+enum Archive {
+    Jar(Rc<JarFile>),
+    Jmod(Rc<JmodFile>),
+    Image(Rc<JImage>),
+}
+
+impl Archive {
+    /// Opens the archive if the path names one: a JAR or ZIP file, a JMOD file,
+    /// or a jimage file like `lib/modules` of a JDK
+    fn open(path: &Path) -> Option<std::io::Result<Archive>> {
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        let archive = match extension {
+            Some("jar" | "zip") => open_jar(path).map(Archive::Jar),
+            Some("jmod") => JmodFile::open(path).map(|jmod| Archive::Jmod(Rc::new(jmod))),
+            Some("jimage") => JImage::open(path).map(|image| Archive::Image(Rc::new(image))),
+            None if path.file_name().is_some_and(|name| name == "modules") => {
+                JImage::open(path).map(|image| Archive::Image(Rc::new(image)))
+            }
+            _ => return None,
+        };
+        Some(archive)
+    }
+
+    fn path(&self) -> &Path {
+        match self {
+            Archive::Jar(jar) => jar.path(),
+            Archive::Jmod(jmod) => jmod.path(),
+            Archive::Image(image) => image.path(),
+        }
+    }
+
+    /// Entry names of the classes, those Java `release` loads for multi-release JARs
+    fn class_entries(&self, release: Option<u16>) -> Vec<String> {
+        let entry = |name: &str| format!("{name}.class");
+        match self {
+            Archive::Jar(jar) => match release {
+                Some(release) => jar.release_class_entries(release),
+                None => jar.class_entries().collect(),
+            }
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+            Archive::Jmod(jmod) => jmod.class_names().map(entry).collect(),
+            Archive::Image(image) => {
+                let names = image.class_names();
+                names.iter().map(|(_, name)| entry(name)).collect()
+            }
+        }
+    }
+
+    /// Name of the entry read for `name`, which differs in multi-release JARs
+    fn entry<'a>(&'a self, name: &'a str, release: Option<u16>) -> &'a str {
+        match (self, release) {
+            (Archive::Jar(jar), Some(release)) => jar.versioned_entry(name, release),
+            _ => name,
+        }
+    }
+
+    fn read(&self, name: &str) -> std::io::Result<Vec<u8>> {
+        let class = || {
+            name.strip_suffix(".class").ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("no entry {name}, only classes can be read"),
+                )
+            })
+        };
+        match self {
+            Archive::Jar(jar) => jar.read(name),
+            Archive::Jmod(jmod) => jmod.read_class(class()?),
+            Archive::Image(image) => image.read_class(class()?),
+        }
+    }
+}
+
+/// Class together with the file it was read from
+struct Input {
+    path: PathBuf,
+    bytes: Vec<u8>,
+    class: Class,
+}
+
+pub fn run<I: Iterator<Item = String>>(args: I) -> i32 {
+    let options = match Options::parse(args) {
+        Ok(options) => options,
+        Err(message) if message.is_empty() => {
+            println!("{USAGE}");
+            return EXIT_SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("hava: {message}");
+            eprintln!("{USAGE}");
+            return EXIT_USAGE;
+        }
+    };
+
+    if options.command == Command::Assemble {
+        return assemble(&options);
+    }
+    // Commands that do not read class files given on the command line
+    let command: Option<fn(&Options) -> std::io::Result<i32>> = match options.command {
+        Command::Versions => Some(versions),
+        Command::Find => Some(find),
+        Command::Shadowed => Some(shadowed),
+        Command::Run if options.files[0].ends_with(".jar") => Some(run_jar),
+        _ => None,
+    };
+    if let Some(command) = command {
+        return match command(&options) {
+            Ok(status) => status,
+            Err(error) if error.kind() == std::io::ErrorKind::BrokenPipe => EXIT_SUCCESS,
+            Err(error) => {
+                eprintln!("hava: {error}");
+                EXIT_ERROR
+            }
+        };
+    }
+
+    let mut status = EXIT_SUCCESS;
+    let mut sources = vec![];
+    for pattern in &options.files {
+        if let Err(error) = sources_of(pattern, options.release, &mut sources) {
+            eprintln!("hava: {pattern}: {error}");
+            status = EXIT_ERROR;
+        }
+    }
+
+    if options.command == Command::Hexdump {
+        return match hexdump(&sources) {
+            Ok(hexdump_status) => hexdump_status.max(status),
+            Err(error) if error.kind() == std::io::ErrorKind::BrokenPipe => status,
+            Err(error) => {
+                eprintln!("hava: {error}");
+                EXIT_ERROR
+            }
+        };
+    }
+
+    let mut inputs = vec![];
+    for source in &sources {
+        match load(source) {
+            Ok(input) => inputs.push(input),
+            Err(error) => {
+                eprintln!("hava: {}: {}", source.path().display(), describe(&error));
+                status = EXIT_ERROR;
+            }
+        }
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    let result = match options.command {
+        Command::Diff => match inputs.as_slice() {
+            [old, new] => diff(&mut stdout, old, new).map(|same| {
+                if !same {
+                    status = status.max(EXIT_FAILURE);
+                }
+            }),
+            _ => Ok(()),
+        },
+        Command::Hierarchy => hierarchy(&mut stdout, &options, &inputs).map(|ok| {
+            if !ok {
+                status = status.max(EXIT_FAILURE);
+            }
+        }),
+        Command::Resolve => resolve(&mut stdout, &options, &inputs).map(|ok| {
+            if !ok {
+                status = status.max(EXIT_FAILURE);
+            }
+        }),
+        Command::CallGraph => call_graph(&mut stdout, &options, &inputs).map(|ok| {
+            if !ok {
+                status = status.max(EXIT_FAILURE);
+            }
+        }),
+        Command::Run => run_main(&mut stdout, &options, &inputs).map(|ok| {
+            if !ok {
+                status = status.max(EXIT_FAILURE);
+            }
+        }),
+        command => {
+            let class_path = match command {
+                Command::Verify => verify_class_path(&options, &inputs).map(Some),
+                _ => Ok(None),
+            };
+            let many = inputs.len() > 1;
+            // Strings are prefixed with the path instead and JSON documents and graphs are
+            // just concatenated
+            let headers = many
+                && command != Command::Strings
+                && !(command == Command::Dump && options.format == Format::Json)
+                && options.format != Format::Dot;
+            class_path.and_then(|class_path| {
+                inputs.iter_mut().enumerate().try_for_each(|(i, input)| {
+                    if headers {
+                        if i > 0 {
+                            writeln!(stdout)?;
+                        }
+                        writeln!(stdout, "{}:", input.path.display())?;
+                    }
+                    let ok = run_command(&mut stdout, &options, input, class_path.as_ref(), many)?;
+                    if !ok {
+                        status = status.max(EXIT_FAILURE);
+                    }
+                    Ok(())
+                })
+            })
+        }
+    };
+
+    match result {
+        Ok(()) => status,
+        // Output closed early, like when piped into `head`
+        Err(error) if error.kind() == std::io::ErrorKind::BrokenPipe => status,
+        Err(error) => {
+            eprintln!("hava: {error}");
+            EXIT_ERROR
+        }
+    }
+}
+
+/// Human readable cause of failed read or parse
+fn describe(error: &std::io::Error) -> String {
+    match error.kind() {
+        std::io::ErrorKind::UnexpectedEof => "unexpected end of file, class is truncated".into(),
+        _ => error.to_string(),
+    }
+}
+
+fn load(source: &Source) -> std::io::Result<Input> {
+    let bytes = source.read()?;
+    let mut class = Class::from(bytes.as_slice())?;
+    class.resolve_attributes()?;
+    Ok(Input {
+        path: source.path(),
+        bytes,
+        class,
+    })
+}
+
+/// Runs single file command, returns false if the check it performs failed
+fn run_command<W: Write>(
+    out: &mut W,
+    options: &Options,
+    input: &mut Input,
+    class_path: Option<&ClassPath>,
+    many: bool,
+) -> std::io::Result<bool> {
+    let Input { path, bytes, class } = input;
+
+    match options.command {
+        Command::Dump => {
+            if options.method.is_some() || options.descriptor.is_some() {
+                let methods = std::mem::take(&mut class.methods);
+                class.methods = methods
+                    .into_iter()
+                    .filter(|method| options.matches(class, method))
+                    .collect();
+            }
+            match options.format {
+                Format::Text => assembly::disassemble(out, class)?,
+                Format::Javap => {
+                    let file = javap::ClassFile {
+                        path,
+                        bytes,
+                        modified: std::fs::metadata(&path).and_then(|m| m.modified()).ok(),
+                    };
+                    javap::javap(out, class, Some(&file))?;
+                }
+                #[cfg(feature = "json")]
+                Format::Json => writeln!(out, "{:#}", crate::json::to_json(class))?,
+                #[cfg(not(feature = "json"))]
+                Format::Json => unreachable!(),
+                Format::Dot => unreachable!("dot output requires callgraph or cfg"),
+            }
+        }
+        Command::Constants => javap::constant_pool(out, class)?,
+        Command::Methods => {
+            let pool = &class.constant_pool;
+            for method in &class.methods {
+                if !options.matches(class, method) {
+                    continue;
+                }
+                let name = utf8_at(pool, method.name_index).unwrap_or("?");
+                let descriptor = utf8_at(pool, method.descriptor_index).unwrap_or("?");
+                writeln!(out, "{}{name}{descriptor}", method.access_flags)?;
+            }
+        }
+        Command::Cfg => {
+            let pool = &class.constant_pool;
+            for method in &class.methods {
+                let Some(code) = method.code().filter(|_| options.matches(class, method)) else {
+                    continue;
+                };
+                let name = utf8_at(pool, method.name_index).unwrap_or("?");
+                let descriptor = utf8_at(pool, method.descriptor_index).unwrap_or("?");
+                let graph = ControlFlowGraph::new(code.code, code.exception_table);
+                match options.format {
+                    Format::Dot => {
+                        let class_name = class.name().unwrap_or("?");
+                        let title = format!("{class_name}.{name}{descriptor}");
+                        graph.write_dot(out, &title, class)?
+                    }
+                    _ => {
+                        writeln!(out, "{name}{descriptor}")?;
+                        print_cfg(out, &graph, class)?;
+                    }
+                }
+            }
+        }
+        Command::Verify => {
+            let problems = verify::verify(class, class_path);
+            for problem in &problems {
+                writeln!(out, "{problem}")?;
+            }
+            if problems.is_empty() {
+                writeln!(out, "ok")?;
+            }
+            return Ok(problems.is_empty());
+        }
+        Command::Strings => {
+            let pool = &class.constant_pool;
+            for constant in pool {
+                let ConstantPoolInfo::String { string_index } = constant else {
+                    continue;
+                };
+                let value = utf16_at(pool, *string_index)
+                    .map_or_else(|| "?".to_string(), |units| String::from_utf16_lossy(&units));
+                if many {
+                    writeln!(out, "{}: {value:?}", path.display())?;
+                } else {
+                    writeln!(out, "{value:?}")?;
+                }
+            }
+        }
+        Command::Diff => unreachable!("diff operates on pair of files"),
+        Command::Hierarchy | Command::Resolve | Command::CallGraph | Command::Run => {
+            unreachable!("{:?} operates on all files together", options.command)
+        }
+        Command::Hexdump => unreachable!("hexdump does not need parsed class"),
+        Command::Assemble => unreachable!("assemble reads assembly, not classes"),
+        Command::Versions | Command::Find | Command::Shadowed => {
+            unreachable!("{:?} does not read class files", options.command)
+        }
+    }
+
+    Ok(true)
+}
+
+/// Assembles every file given as is (no globs, the sources are not class files)
+fn assemble(options: &Options) -> i32 {
+    let mut status = EXIT_SUCCESS;
+    for file in &options.files {
+        let source = Path::new(file);
+        let output = match &options.output {
+            Some(output) => PathBuf::from(output),
+            None => source.with_extension("class"),
+        };
+        let result = if output == source {
+            Err(std::io::Error::other(
+                "output would overwrite the input, choose another with -o",
+            ))
+        } else {
+            std::fs::read_to_string(source)
+                .and_then(|text| assembly::assemble(&text))
+                .and_then(|class| class.to_bytes())
+                .and_then(|bytes| std::fs::write(&output, bytes))
+        };
+        if let Err(error) = result {
+            eprintln!("hava: {file}: {}", describe(&error));
+            status = EXIT_ERROR;
+        }
+    }
+    status
+}
+
+/// Hex dump works on files that fail to parse too, so it only reads them
+fn hexdump(sources: &[Source]) -> std::io::Result<i32> {
+    let mut stdout = std::io::stdout().lock();
+    let color = stdout.is_terminal();
+    let mut status = EXIT_SUCCESS;
+    for (i, source) in sources.iter().enumerate() {
+        let path = source.path();
+        if sources.len() > 1 {
+            if i > 0 {
+                writeln!(stdout)?;
+            }
+            writeln!(stdout, "{}:", path.display())?;
+        }
+        let bytes = match source.read() {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                eprintln!("hava: {}: {}", path.display(), describe(&error));
+                status = EXIT_ERROR;
+                continue;
+            }
+        };
+        if !crate::hexdump::hexdump(&mut stdout, &bytes, color)? {
+            eprintln!(
+                "hava: {}: parsing failed, see the marked bytes",
+                path.display()
+            );
+            status = EXIT_ERROR;
+        }
+    }
+    Ok(status)
+}
+
+/// Prints differences between two classes, returns whether they are the same.
+/// Constant pool indexes are resolved, so reordered constant pools compare equal.
+fn diff<W: Write>(out: &mut W, old: &Input, new: &Input) -> std::io::Result<bool> {
+    let old_lines = describe_class(&old.class);
+    let new_lines = describe_class(&new.class);
+    if old_lines == new_lines {
+        return Ok(true);
+    }
+
+    writeln!(out, "--- {}", old.path.display())?;
+    writeln!(out, "+++ {}", new.path.display())?;
+    for (change, line) in line_diff(&old_lines, &new_lines) {
+        writeln!(out, "{change} {line}")?;
+    }
+    Ok(false)
+}
+
+/// Lines describing class structure in a way that does not depend on constant pool layout
+fn describe_class(class: &Class) -> Vec<String> {
+    let pool = &class.constant_pool;
+    let mut lines = vec![
+        format!("version {}.{}", class.major, class.minor),
+        format!(
+            "class {} flags {}",
+            class_name_at(pool, class.this_class).unwrap_or("?"),
+            class.access_info.javap_names(FlagsOwner::Class).join(", ")
+        ),
+        format!(
+            "extends {}",
+            class_name_at(pool, class.super_class).unwrap_or("-")
+        ),
+    ];
+    for interface in &class.interfaces {
+        lines.push(format!(
+            "implements {}",
+            class_name_at(pool, *interface).unwrap_or("?")
+        ));
+    }
+
+    for field in &class.fields {
+        lines.push(format!(
+            "field {}{} {}",
+            field.access_flags,
+            utf8_at(pool, field.name_index).unwrap_or("?"),
+            utf8_at(pool, field.descriptor_index).unwrap_or("?")
+        ));
+    }
+
+    for method in &class.methods {
+        let name = utf8_at(pool, method.name_index).unwrap_or("?");
+        let descriptor = utf8_at(pool, method.descriptor_index).unwrap_or("?");
+        lines.push(format!("method {}{name}{descriptor}", method.access_flags));
+        for attribute in &method.attributes {
+            let AttributeInfo::Code {
+                code,
+                exception_table,
+                ..
+            } = &attribute.info
+            else {
+                continue;
+            };
+            // Labels instead of offsets, so inserted code does not change every later branch
+            let labels = Labels::new(code, exception_table);
+            for (offset, instruction) in code {
+                if let Some(label) = labels.at(*offset) {
+                    lines.push(format!(" {label}:"));
+                }
+                let line = match instruction.constant_index() {
+                    Some(index) => format!(
+                        "  {} {}",
+                        instruction.mnemonic(),
+                        javap::constant_comment(class, index)
+                    ),
+                    None => format!("  {}", labels.instruction(*offset, instruction)),
+                };
+                lines.push(line);
+            }
+        }
+    }
+    lines
+}
+
+/// Longest common subsequence based diff, returns lines prefixed with ' ', '-' or '+'
+fn line_diff<'a>(old: &'a [String], new: &'a [String]) -> Vec<(char, &'a str)> {
+    let (n, m) = (old.len(), new.len());
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut result = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            result.push((' ', old[i].as_str()));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            result.push(('-', old[i].as_str()));
+            i += 1;
+        } else {
+            result.push(('+', new[j].as_str()));
+            j += 1;
+        }
+    }
+    result
+}
+
+/// Appends class files named by the command line argument `pattern`. Archives are opened
+/// here, so that all their entries are listed, and shared by the entries. With `release`,
+/// multi-release JARs give the entries that release would load.
+fn sources_of(
+    pattern: &str,
+    release: Option<u16>,
+    sources: &mut Vec<Source>,
+) -> std::io::Result<()> {
+    if let Some((path, entry)) = pattern.split_once("!/") {
+        let path = Path::new(path);
+        let archive = match Archive::open(path) {
+            Some(archive) => archive?,
+            None => open_jar(path).map(Archive::Jar)?,
+        };
+        if !entry.contains(['*', '?']) {
+            sources.push(Source::Entry {
+                name: archive.entry(entry, release).to_string(),
+                archive,
+            });
+            return Ok(());
+        }
+        // Versioned entries match by the name of the class they override
+        let names: Vec<String> = archive
+            .class_entries(release)
+            .into_iter()
+            .filter(|name| {
+                let base = split_versioned(name).map_or(name.as_str(), |(_, base)| base);
+                wildcard_match(entry, base)
+            })
+            .collect();
+        if names.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "no entries match the pattern",
+            ));
+        }
+        sources.extend(names.into_iter().map(|name| Source::Entry {
+            archive: archive.clone(),
+            name,
+        }));
+        return Ok(());
+    }
+
+    for path in expand(pattern)? {
+        let Some(archive) = Archive::open(&path) else {
+            sources.push(Source::File(path));
+            continue;
+        };
+        let archive = archive?;
+        sources.extend(
+            archive
+                .class_entries(release)
+                .into_iter()
+                .map(|name| Source::Entry {
+                    archive: archive.clone(),
+                    name,
+                }),
+        );
+    }
+    Ok(())
+}
+
+/// Opens the archive, warning about versioned classes compiled for another release
+fn open_jar(path: &Path) -> std::io::Result<Rc<JarFile>> {
+    let jar = JarFile::open(path)?;
+    if jar.is_multi_release() {
+        for mismatch in jar.version_mismatches() {
+            eprintln!("hava: warning: {}: {mismatch}", path.display());
+        }
+    }
+    Ok(Rc::new(jar))
+}
+
+/// Lists classes with versioned overrides, marking the variant `--release` selects with `*`
+fn versions(options: &Options) -> std::io::Result<i32> {
+    let mut stdout = std::io::stdout().lock();
+    let mut status = EXIT_SUCCESS;
+    let mut paths = vec![];
+    for pattern in &options.files {
+        match expand(pattern) {
+            Ok(expanded) => paths.extend(expanded),
+            Err(error) => {
+                eprintln!("hava: {pattern}: {error}");
+                status = EXIT_ERROR;
+            }
+        }
+    }
+
+    for (i, path) in paths.iter().enumerate() {
+        let jar = match open_jar(path) {
+            Ok(jar) => jar,
+            Err(error) => {
+                eprintln!("hava: {}: {}", path.display(), describe(&error));
+                status = EXIT_ERROR;
+                continue;
+            }
+        };
+        if paths.len() > 1 {
+            if i > 0 {
+                writeln!(stdout)?;
+            }
+            writeln!(stdout, "{}:", path.display())?;
+        }
+        if !jar.is_multi_release() && !jar.versions().is_empty() {
+            eprintln!(
+                "hava: warning: {}: has versioned entries, but the manifest does not \
+                 declare Multi-Release: true, so they are never loaded",
+                path.display()
+            );
+        }
+        for (name, versions) in jar.overrides() {
+            let selected = options
+                .release
+                .map(|release| jar.versioned_entry(name, release))
+                .and_then(split_versioned)
+                .map(|(version, _)| version);
+            let versions: Vec<String> = versions
+                .iter()
+                .map(|version| match selected == Some(*version) {
+                    true => format!("*{version}"),
+                    false => version.to_string(),
+                })
+                .collect();
+            writeln!(stdout, "{name}: {}", versions.join(" "))?;
+        }
+    }
+    Ok(status)
+}
+
+/// Class path of find and shadowed: the JDK first, like the boot class path of the JVM
+/// comes before the application class path, then `--class-path`, `CLASSPATH` or `.`
+fn class_path(options: &Options) -> std::io::Result<ClassPath> {
+    let paths = options
+        .class_path
+        .clone()
+        .or_else(|| std::env::var("CLASSPATH").ok())
+        .unwrap_or_else(|| ".".to_string());
+    with_jdk(options, ClassPath::parse(&paths)?)
+}
+
+/// The application class path with the JDK in front of it
+fn with_jdk(options: &Options, mut class_path: ClassPath) -> std::io::Result<ClassPath> {
+    match options.jdk.as_ref().map(PathBuf::from).or_else(find_jdk) {
+        Some(jdk) => class_path.push_front(ClassPathEntry::open(&jdk).map_err(|error| {
+            std::io::Error::new(error.kind(), format!("{}: {error}", jdk.display()))
+        })?),
+        None => eprintln!("hava: warning: no JDK found, set JAVA_HOME or use --jdk"),
+    }
+    // Programs run as on the JDK, which loads the classes of its release
+    let release = match options.command {
+        Command::Run => options.release.or_else(|| jdk_release(&class_path)),
+        _ => options.release,
+    };
+    class_path.set_release(release);
+    Ok(class_path)
+}
+
+/// Java release of the JDK on the class path, told by the version of its `Object` class
+fn jdk_release(class_path: &ClassPath) -> Option<u16> {
+    let object = class_path.class(OBJECT).ok()?;
+    // Version 53 is Java 9, the first release that reads multi-release JARs
+    object.major.checked_sub(44).filter(|release| *release >= 9)
+}
+
+/// Runs the main method of the class, with the classes it uses loaded from the class path.
+/// Returns false if it threw an exception.
+fn run_main<W: Write>(out: &mut W, options: &Options, inputs: &[Input]) -> std::io::Result<bool> {
+    let Some(input) = inputs.first() else {
+        return Ok(true);
+    };
+    let class_path = class_path(options)?;
+    let class = Rc::new(input.class.clone());
+    class_path.define(class.clone());
+    run_class(out, options, &class_path, class)
+}
+
+/// Runs the `Main-Class` of the JAR like `java -jar` does, with the JAR and the entries
+/// of its manifest `Class-Path`, relative to the directory of the JAR, as the class path.
+/// Entries that do not exist are left out, as by the JVM.
+fn run_jar(options: &Options) -> std::io::Result<i32> {
+    let path = Path::new(&options.files[0]);
+    let jar = JarFile::open(path)?;
+    let manifest = jar.manifest();
+    let main = manifest.and_then(|manifest| manifest.main_class());
+    let main = main
+        .ok_or_else(|| invalid_data(format!("no main manifest attribute, in {}", path.display())))?
+        .replace('.', "/");
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let dependencies: Vec<PathBuf> = manifest
+        .map(|manifest| manifest.class_path())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|dependency| directory.join(dependency))
+        .filter(|dependency| dependency.exists())
+        .collect();
+
+    let mut class_path = ClassPath::new();
+    class_path.push(ClassPathEntry::Jar(jar));
+    for dependency in dependencies {
+        class_path.add(dependency)?;
+    }
+    let class_path = with_jdk(options, class_path)?;
+    let class = class_path.class(&main)?;
+    let mut stdout = std::io::stdout().lock();
+    Ok(match run_class(&mut stdout, options, &class_path, class)? {
+        true => EXIT_SUCCESS,
+        false => EXIT_FAILURE,
+    })
+}
+
+/// Runs the main method of the class, with the classes it uses loaded from the class path.
+/// Returns false if it threw an exception.
+fn run_class<W: Write>(
+    out: &mut W,
+    options: &Options,
+    class_path: &ClassPath,
+    class: Rc<Class>,
+) -> std::io::Result<bool> {
+    let mut interpreter = Interpreter::new(class_path, out);
+    if let Some(max_heap) = options.max_heap {
+        interpreter.set_max_heap(max_heap);
+    }
+    let result = interpreter.run_main(class);
+    if options.gc_stats {
+        eprint!("{}", interpreter.gc_stats());
+    }
+    result
+}
+
+/// Number of bytes like 512k, 64m or 1g
+fn parse_size(size: &str) -> Option<usize> {
+    let (number, unit) = match size.find(|c: char| !c.is_ascii_digit()) {
+        Some(at) => size.split_at(at),
+        None => (size, ""),
+    };
+    let unit: usize = match unit.to_ascii_lowercase().as_str() {
+        "" => 1,
+        "k" => 1 << 10,
+        "m" => 1 << 20,
+        "g" => 1 << 30,
+        _ => return None,
+    };
+    number.parse::<usize>().ok()?.checked_mul(unit)
+}
+
+/// Class path for type checking, where the verified classes are found as well
+fn verify_class_path(options: &Options, inputs: &[Input]) -> std::io::Result<ClassPath> {
+    let class_path = class_path(options)?;
+    for input in inputs {
+        class_path.define(Rc::new(input.class.clone()));
+    }
+    Ok(class_path)
+}
+
+/// Prints the hierarchy of the classes with supertypes loaded from the class path, or the
+/// supertypes and subtypes of `--class`. Returns false for missing supertypes and cycles.
+fn hierarchy<W: Write>(out: &mut W, options: &Options, inputs: &[Input]) -> std::io::Result<bool> {
+    let mut hierarchy = Hierarchy::from_classes(inputs.iter().map(|input| &input.class));
+    hierarchy.complete(&class_path(options)?)?;
+
+    let mut ok = true;
+    for missing in hierarchy.missing() {
+        let referenced_by = missing.referenced_by.join(", ");
+        eprintln!(
+            "hava: supertype {} of {referenced_by} not found",
+            missing.name
+        );
+        ok = false;
+    }
+    for cycle in hierarchy.cycles() {
+        eprintln!(
+            "hava: cycle in class hierarchy: {} > {}",
+            cycle.join(" > "),
+            cycle[0]
+        );
+        ok = false;
+    }
+
+    let Some(name) = &options.class else {
+        // Trees of classes by superclass, then of interfaces by superinterface
+        let is_root =
+            |super_name: Option<&str>| super_name.is_none_or(|name| !hierarchy.contains(name));
+        for node in hierarchy.types() {
+            if !node.is_interface() && is_root(node.super_name.as_deref()) {
+                print_subtree(out, &hierarchy, &node.name, 0, false)?;
+            }
+        }
+        for node in hierarchy.types() {
+            if node.is_interface() && node.interfaces.iter().all(|name| is_root(Some(name))) {
+                print_subtree(out, &hierarchy, &node.name, 0, true)?;
+            }
+        }
+        return Ok(ok);
+    };
+
+    let Some(node) = hierarchy.get(name) else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("class {name} is not one of the files or their supertypes"),
+        ));
+    };
+    writeln!(out, "{}", describe_type(node))?;
+    let superclasses = hierarchy.superclass_chain(name)[1..].to_vec();
+    let interfaces: Vec<&str> = hierarchy
+        .supertypes(name)
+        .into_iter()
+        .filter(|name| hierarchy.get(name).is_none_or(|node| node.is_interface()))
+        .collect();
+    let mut subtypes = hierarchy.subtypes(name);
+    subtypes.sort_unstable();
+    let implementations = match node.is_interface() {
+        true => hierarchy.implementations(name),
+        false => vec![],
+    };
+    for (label, names, separator) in [
+        ("superclasses", superclasses, " > "),
+        ("interfaces", interfaces, ", "),
+        ("subtypes", subtypes, ", "),
+        ("implementations", implementations, ", "),
+    ] {
+        if !names.is_empty() {
+            writeln!(out, "{label}: {}", names.join(separator))?;
+        }
+    }
+    Ok(ok)
+}
+
+/// Prints every field and method reference of the classes with the class declaring the
+/// member, if that is not the class named by the reference. The classes are found before
+/// those of the class path. Returns false if some references cannot be resolved.
+fn resolve<W: Write>(out: &mut W, options: &Options, inputs: &[Input]) -> std::io::Result<bool> {
+    let class_path = class_path(options)?;
+    for input in inputs {
+        class_path.define(Rc::new(input.class.clone()));
+    }
+    let resolver = Resolver::new(&class_path);
+
+    let mut ok = true;
+    for (i, input) in inputs.iter().enumerate() {
+        if inputs.len() > 1 {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "{}:", input.path.display())?;
+        }
+        let pool = &input.class.constant_pool;
+        for (i, constant) in pool.iter().enumerate() {
+            let index = i as u16 + 1;
+            let Some((owner, name, descriptor)) = member_ref_at(pool, index) else {
+                continue;
+            };
+            let separator = match constant {
+                ConstantPoolInfo::FieldRef { .. } => " ",
+                _ => "",
+            };
+            let reference = format!(
+                "#{index} {} {owner}.{name}{separator}{descriptor}",
+                constant.tag_name()
+            );
+            match resolver.resolve(&input.class, index) {
+                Ok(member) if member.class_name() == owner => writeln!(out, "{reference}")?,
+                Ok(member) => writeln!(out, "{reference} -> {}", member.class_name())?,
+                Err(error) => {
+                    writeln!(out, "{reference}: {error}")?;
+                    ok = false;
+                }
+            }
+        }
+    }
+    Ok(ok)
+}
+
+/// Prints the blocks with their successors, immediate dominators and post-dominators,
+/// followed by the loops
+fn print_cfg<W: Write>(
+    out: &mut W,
+    graph: &ControlFlowGraph,
+    class: &Class,
+) -> std::io::Result<()> {
+    let block_name =
+        |block: Option<usize>| block.map_or("-".to_string(), |block| format!("B{block}"));
+    for (index, block) in graph.blocks().iter().enumerate() {
+        let successors: Vec<String> = graph
+            .out_edges(index)
+            .map(|edge| match edge.kind {
+                EdgeKind::Exception(0) => format!("B{} (catch any)", edge.to),
+                EdgeKind::Exception(catch_type) => {
+                    let caught = class_name_at(&class.constant_pool, catch_type).unwrap_or("?");
+                    format!("B{} (catch {caught})", edge.to)
+                }
+                EdgeKind::FallThrough | EdgeKind::Branch => format!("B{}", edge.to),
+            })
+            .collect();
+        write!(out, "  B{index} {}..{}", block.start, block.end)?;
+        if !successors.is_empty() {
+            write!(out, " -> {}", successors.join(", "))?;
+        }
+        match graph.is_reachable(index) {
+            true => writeln!(
+                out,
+                "; idom {}; ipdom {}",
+                block_name(graph.immediate_dominator(index)),
+                block_name(graph.immediate_post_dominator(index))
+            )?,
+            false => writeln!(out, "; unreachable")?,
+        }
+    }
+    for found in graph.loops() {
+        let blocks: Vec<String> = found
+            .blocks
+            .iter()
+            .map(|block| format!("B{block}"))
+            .collect();
+        let latches: Vec<String> = found
+            .latches
+            .iter()
+            .map(|block| format!("B{block}"))
+            .collect();
+        write!(
+            out,
+            "  loop B{}: {}; back edges from {}",
+            found.header,
+            blocks.join(" "),
+            latches.join(", ")
+        )?;
+        match found.parent {
+            Some(parent) => writeln!(out, "; inside loop B{parent}")?,
+            None => writeln!(out)?,
+        }
+    }
+    Ok(())
+}
+
+/// Prints the methods of the classes reachable from the entry points with the calls they make,
+/// or the graph in DOT. Calls into the class path end at the called method. Returns false
+/// if some references of reachable code cannot be resolved.
+fn call_graph<W: Write>(out: &mut W, options: &Options, inputs: &[Input]) -> std::io::Result<bool> {
+    let class_path = class_path(options)?;
+    for input in inputs {
+        class_path.define(Rc::new(input.class.clone()));
+    }
+    let mut hierarchy = Hierarchy::from_classes(inputs.iter().map(|input| &input.class));
+    hierarchy.complete(&class_path)?;
+    let classes: Vec<&Class> = inputs.iter().map(|input| &input.class).collect();
+
+    let entry_points = match options.entries.is_empty() {
+        true => {
+            let main = MethodSpec::parse("*.main([Ljava/lang/String;)V");
+            main.find(&classes, AccessFlags::Public | AccessFlags::Static)
+        }
+        false => {
+            let mut entry_points = vec![];
+            for entry in &options.entries {
+                let found = MethodSpec::parse(entry).find(&classes, AccessFlags::none());
+                if found.is_empty() {
+                    return Err(method_not_found(entry));
+                }
+                entry_points.extend(found);
+            }
+            entry_points
+        }
+    };
+    if entry_points.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no main methods found, give entry points with --entry",
+        ));
+    }
+
+    let analysis = match options.rta {
+        true => Analysis::Rta,
+        false => Analysis::Cha,
+    };
+    let application: HashSet<&str> = classes.iter().filter_map(|class| class.name()).collect();
+    let graph = callgraph::build(&class_path, &hierarchy, &entry_points, analysis, &|name| {
+        application.contains(name)
+    });
+    for unresolved in graph.unresolved() {
+        eprintln!(
+            "hava: {} at {}: {}",
+            unresolved.method, unresolved.offset, unresolved.error
+        );
+    }
+    let ok = graph.unresolved().is_empty();
+
+    if options.format == Format::Dot {
+        graph.write_dot(out)?;
+        return Ok(ok);
+    }
+    let queries = [(&options.callers, false), (&options.callees, true)];
+    if queries.iter().any(|(query, _)| query.is_some()) {
+        for (query, callees) in queries {
+            let Some(query) = query else {
+                continue;
+            };
+            let spec = MethodSpec::parse(query);
+            let methods: Vec<&MethodId> = graph
+                .reachable()
+                .iter()
+                .filter(|method| spec.matches(method))
+                .collect();
+            if methods.is_empty() {
+                return Err(method_not_found(query));
+            }
+            for method in methods {
+                writeln!(out, "{method}")?;
+                match callees {
+                    true => print_callees(out, &graph, method)?,
+                    false => {
+                        for edge in graph.callers(method) {
+                            let kind = edge.kind.name();
+                            writeln!(out, "    <- {kind} {} at {}", edge.caller, edge.offset)?;
+                        }
+                    }
+                }
+            }
+        }
+        return Ok(ok);
+    }
+    for method in graph.reachable() {
+        if !graph.is_library(method) {
+            writeln!(out, "{method}")?;
+            print_callees(out, &graph, method)?;
+        }
+    }
+    Ok(ok)
+}
+
+fn print_callees<W: Write>(
+    out: &mut W,
+    graph: &CallGraph,
+    method: &MethodId,
+) -> std::io::Result<()> {
+    for edge in graph.callees(method) {
+        let kind = edge.kind.name();
+        writeln!(out, "    {} -> {kind} {}", edge.offset, edge.callee)?;
+    }
+    Ok(())
+}
+
+fn method_not_found(spec: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        format!("method {spec} not found"),
+    )
+}
+
+/// Method given on the command line as class and name separated by the last dot, with
+/// an optional descriptor, like `com.foo.Bar.run` or `Bar.run(I)V`. The class may be `*`.
+struct MethodSpec {
+    class: String,
+    name: String,
+    descriptor: Option<String>,
+}
+
+impl MethodSpec {
+    fn parse(spec: &str) -> MethodSpec {
+        let (qualified, descriptor) = match spec.find('(') {
+            Some(start) => (&spec[..start], Some(spec[start..].to_string())),
+            None => (spec, None),
+        };
+        let (class, name) = qualified.rsplit_once('.').unwrap_or(("*", qualified));
+        MethodSpec {
+            class: class.replace('.', "/"),
+            name: name.to_string(),
+            descriptor,
+        }
+    }
+
+    fn matches(&self, method: &MethodId) -> bool {
+        (self.class == "*" || self.class == method.class)
+            && self.name == method.name
+            && self
+                .descriptor
+                .as_ref()
+                .is_none_or(|descriptor| *descriptor == method.descriptor)
+    }
+
+    /// Methods of the classes that match and have all of `access_flags`
+    fn find(&self, classes: &[&Class], access_flags: AccessFlags) -> Vec<MethodId> {
+        let mut found = vec![];
+        for class in classes {
+            let pool = &class.constant_pool;
+            for method in &class.methods {
+                let id = MethodId::new(
+                    class.name().unwrap_or("?"),
+                    utf8_at(pool, method.name_index).unwrap_or("?"),
+                    utf8_at(pool, method.descriptor_index).unwrap_or("?"),
+                );
+                if self.matches(&id) && method.access_flags.contains(access_flags) {
+                    found.push(id);
+                }
+            }
+        }
+        found
+    }
+}
+
+/// Prints `name` and below it, indented, the classes extending it. With `interfaces`,
+/// prints interfaces extending it instead.
+fn print_subtree<W: Write>(
+    out: &mut W,
+    hierarchy: &Hierarchy,
+    name: &str,
+    depth: usize,
+    interfaces: bool,
+) -> std::io::Result<()> {
+    let Some(node) = hierarchy.get(name) else {
+        return Ok(());
+    };
+    writeln!(
+        out,
+        "{:indent$}{}",
+        "",
+        describe_type(node),
+        indent = depth * 4
+    )?;
+    for subtype in hierarchy.direct_subtypes(name) {
+        let Some(sub) = hierarchy.get(subtype) else {
+            continue;
+        };
+        let extends = match interfaces {
+            true => sub.is_interface(),
+            false => !sub.is_interface() && sub.super_name.as_deref() == Some(name),
+        };
+        // Members of cycles are printed once, by the cycle report
+        if extends && !hierarchy.supertypes(subtype).contains(&subtype) {
+            print_subtree(out, hierarchy, subtype, depth + 1, interfaces)?;
+        }
+    }
+    Ok(())
+}
+
+/// Name of the type with `interface` or `abstract` in front and its interfaces
+fn describe_type(node: &crate::hierarchy::Type) -> String {
+    let mut text = String::new();
+    if node.is_interface() {
+        text.push_str("interface ");
+    } else if node.access_flags.contains(AccessFlags::Abstract) {
+        text.push_str("abstract ");
+    }
+    text.push_str(&node.name);
+    if !node.is_interface() && !node.interfaces.is_empty() {
+        text.push_str(" implements ");
+        text.push_str(&node.interfaces.join(", "));
+    }
+    text
+}
+
+/// Prints the location of each class, followed by the locations it shadows
+fn find(options: &Options) -> std::io::Result<i32> {
+    let class_path = class_path(options)?;
+    let mut stdout = std::io::stdout().lock();
+    let mut status = EXIT_SUCCESS;
+    for name in &options.files {
+        // Binary names and file names are accepted as well
+        let name = name
+            .strip_suffix(".class")
+            .unwrap_or(name)
+            .replace('.', "/");
+        if let Err(error) = class_path.class(&name) {
+            eprintln!("hava: {name}: {}", describe(&error));
+            status = EXIT_ERROR;
+            continue;
+        }
+        let mut locations = class_path
+            .entries()
+            .iter()
+            .filter(|entry| entry.contains(&name, options.release))
+            .map(|entry| entry.location(&name, options.release));
+        if let Some(location) = locations.next() {
+            writeln!(stdout, "{name}: {location}")?;
+        }
+        for location in locations {
+            writeln!(stdout, "    shadows {location}")?;
+        }
+    }
+    Ok(status)
+}
+
+/// Lists classes in more than one entry of the class path, with the location that is used first
+fn shadowed(options: &Options) -> std::io::Result<i32> {
+    let class_path = class_path(options)?;
+    let mut stdout = std::io::stdout().lock();
+    for shadowed in class_path.shadowed()? {
+        writeln!(stdout, "{}:", shadowed.name)?;
+        for (i, location) in shadowed.locations.iter().enumerate() {
+            let marker = if i == 0 { "used" } else { "shadowed" };
+            writeln!(stdout, "    {marker} {location}")?;
+        }
+    }
+    Ok(EXIT_SUCCESS)
+}
+
+/// Expands glob pattern or directory into list of class files.
+/// Plain paths are returned as they are, so that missing files are reported when read.
+fn expand(pattern: &str) -> std::io::Result<Vec<PathBuf>> {
+    let path = Path::new(pattern);
+    if !pattern.contains(['*', '?']) {
+        return if path.is_dir() {
+            let mut files = vec![];
+            find_classes(path, &mut files)?;
+            files.sort();
+            Ok(files)
+        } else {
+            Ok(vec![path.to_path_buf()])
+        };
+    }
+
+    let mut candidates = vec![PathBuf::new()];
+    for component in path.components() {
+        let component = component.as_os_str().to_string_lossy();
+        if !component.contains(['*', '?']) {
+            for candidate in &mut candidates {
+                candidate.push(component.as_ref());
+            }
+            continue;
+        }
+
+        let mut next = vec![];
+        for candidate in &candidates {
+            let directory = if candidate.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                candidate.as_path()
+            };
+            if component == "**" {
+                collect_directories(candidate, &mut next);
+                continue;
+            }
+            let Ok(entries) = std::fs::read_dir(directory) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                if name.starts_with('.') && !component.starts_with('.') {
+                    continue;
+                }
+                if wildcard_match(&component, &name) {
+                    next.push(candidate.join(name.as_ref()));
+                }
+            }
+        }
+        candidates = next;
+    }
+
+    candidates.retain(|candidate| candidate.is_file());
+    candidates.sort();
+    candidates.dedup();
+    if candidates.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no files match the pattern",
+        ));
+    }
+    Ok(candidates)
+}
+
+/// Pushes `directory` and all directories below it, used to expand `**`
+fn collect_directories(directory: &Path, result: &mut Vec<PathBuf>) {
+    result.push(directory.to_path_buf());
+    let path = if directory.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        directory
+    };
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            collect_directories(&directory.join(entry.file_name()), result);
+        }
+    }
+}
+
+fn find_classes(directory: &Path, result: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_classes(&path, result)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "class")
+        {
+            result.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Matches `text` against pattern where `*` matches any sequence and `?` any character
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
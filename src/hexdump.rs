@@ -0,0 +1,180 @@
+//! Hex dump of a class file with every byte range labelled by the structure it belongs to.
+//! Labels come from tracing the regular parser, so they always agree with [`Class::from`].
+
+use crate::raw::class::Class;
+use crate::raw::trace::{Span, Tracer};
+use crate::raw::*;
+use std::io::Write;
+
+const BYTES_PER_LINE: usize = 16;
+
+/// Place where parsing stopped
+struct Failure {
+    offset: usize,
+    message: String,
+}
+
+/// Prints the annotated dump, returns whether the whole file parsed successfully
+pub fn hexdump<W: Write>(out: &mut W, bytes: &[u8], color: bool) -> std::io::Result<bool> {
+    let mut failures = vec![];
+
+    let mut tracer = Tracer::new(bytes, 0, vec![]);
+    let class = Class::from(&mut tracer);
+    let end = tracer.offset();
+    let mut spans = tracer.spans;
+
+    match class {
+        Ok(class) => spans = refine_attributes(bytes, &class.constant_pool, spans, &mut failures),
+        Err(error) => failures.push(Failure {
+            offset: end,
+            message: describe(&error),
+        }),
+    }
+
+    if end < bytes.len() {
+        spans.push(Span {
+            offset: end,
+            length: bytes.len() - end,
+            label: if failures.is_empty() {
+                "(trailing bytes)".to_string()
+            } else {
+                "(not parsed)".to_string()
+            },
+        });
+    }
+
+    // Failure is shown next to the last read before it, or the bytes it failed to read
+    let marked: Vec<Option<usize>> = failures
+        .iter()
+        .map(|failure| {
+            spans
+                .iter()
+                .rposition(|span| span.offset + span.length == failure.offset)
+                .or_else(|| spans.iter().position(|span| span.offset == failure.offset))
+        })
+        .collect();
+
+    for (i, span) in spans.iter().enumerate() {
+        let failure = marked
+            .iter()
+            .position(|marked| *marked == Some(i))
+            .map(|failure| &failures[failure]);
+        print_span(out, bytes, span, failure, color)?;
+    }
+
+    for (failure, _) in failures.iter().zip(&marked).filter(|(_, m)| m.is_none()) {
+        let message = format!("<-- {}", failure.message);
+        writeln!(
+            out,
+            "{:08x}  {:width$}  {}",
+            failure.offset,
+            "",
+            highlight(&message, color),
+            width = BYTES_PER_LINE * 3 - 1
+        )?;
+    }
+
+    Ok(failures.is_empty())
+}
+
+/// Replaces raw `attributes[i].info` spans of known attributes with spans of their content
+fn refine_attributes(
+    bytes: &[u8],
+    constant_pool: &[ConstantPoolInfo],
+    spans: Vec<Span>,
+    failures: &mut Vec<Failure>,
+) -> Vec<Span> {
+    let mut result = vec![];
+    for span in spans {
+        let Some(path) = span.label.strip_suffix(".info") else {
+            result.push(span);
+            continue;
+        };
+
+        // attribute_name_index and attribute_length precede the info
+        let name = span
+            .offset
+            .checked_sub(6)
+            .map(|start| u16::from_be_bytes([bytes[start], bytes[start + 1]]))
+            .and_then(|index| utf8_at(constant_pool, index));
+        let Some(name) = name else {
+            result.push(span);
+            continue;
+        };
+
+        let info = &bytes[span.offset..span.offset + span.length];
+        let mut tracer = Tracer::new(info, span.offset, vec![path.to_string(), name.to_string()]);
+        match AttributeInfo::from(constant_pool, name, &mut tracer) {
+            Ok(None) => {
+                result.push(span);
+                continue;
+            }
+            Ok(Some(_)) => {}
+            Err(error) => failures.push(Failure {
+                offset: tracer.offset(),
+                message: describe(&error),
+            }),
+        }
+
+        let unused = tracer.remaining().len();
+        let offset = tracer.offset();
+        result.extend(refine_attributes(
+            bytes,
+            constant_pool,
+            tracer.spans,
+            failures,
+        ));
+        if unused > 0 {
+            result.push(Span {
+                offset,
+                length: unused,
+                label: format!("{path}.{name}.(unused)"),
+            });
+        }
+    }
+    result
+}
+
+fn print_span<W: Write>(
+    out: &mut W,
+    bytes: &[u8],
+    span: &Span,
+    failure: Option<&Failure>,
+    color: bool,
+) -> std::io::Result<()> {
+    let data = &bytes[span.offset..span.offset + span.length];
+    for (i, chunk) in data.chunks(BYTES_PER_LINE).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|byte| format!("{byte:02x}")).collect();
+        let hex = format!("{:width$}", hex.join(" "), width = BYTES_PER_LINE * 3 - 1);
+        let offset = span.offset + i * BYTES_PER_LINE;
+        if i > 0 {
+            writeln!(out, "{offset:08x}  {}", hex.trim_end())?;
+            continue;
+        }
+        match failure {
+            Some(failure) => writeln!(
+                out,
+                "{offset:08x}  {}  {}",
+                highlight(&hex, color),
+                highlight(&format!("{}  <-- {}", span.label, failure.message), color)
+            )?,
+            None => writeln!(out, "{offset:08x}  {hex}  {}", span.label)?,
+        }
+    }
+    Ok(())
+}
+
+fn describe(error: &std::io::Error) -> String {
+    match error.kind() {
+        std::io::ErrorKind::UnexpectedEof => "unexpected end of data".to_string(),
+        _ => error.to_string(),
+    }
+}
+
+fn highlight(text: &str, color: bool) -> String {
+    if color {
+        format!("\x1b[1;31m{text}\x1b[0m")
+    } else {
+        text.to_string()
+    }
+}
@@ -0,0 +1,365 @@
+//! Class hierarchy over a set of classes, indexed by internal name in both directions:
+//! from classes to their superclass and interfaces and from types to their subtypes.
+//! Supertypes outside of the set can be loaded from a [`ClassPath`], those that cannot
+//! be found are reported as missing, like cycles that the JVM would reject with
+//! `ClassCircularityError`.
+
+use crate::classpath::ClassPath;
+use crate::raw::class::Class;
+use crate::raw::AccessFlags;
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::io::{ErrorKind, Result};
+
+pub const OBJECT: &str = "java/lang/Object";
+
+/// Class or interface of the hierarchy with its direct supertypes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Type {
+    pub name: String,
+    /// `None` for `java/lang/Object`, and for modules
+    pub super_name: Option<String>,
+    pub interfaces: Vec<String>,
+    pub access_flags: AccessFlags,
+}
+
+impl Type {
+    pub fn from_class(class: &Class) -> Option<Type> {
+        Some(Type {
+            name: class.name()?.to_string(),
+            super_name: class.super_name().map(str::to_string),
+            interfaces: class.interface_names().map(str::to_string).collect(),
+            access_flags: class.access_info,
+        })
+    }
+
+    pub fn is_interface(&self) -> bool {
+        self.access_flags.contains(AccessFlags::Interface)
+    }
+
+    /// Superclass followed by the interfaces, in the order of the class file
+    pub fn direct_supertypes(&self) -> impl Iterator<Item = &str> {
+        self.super_name
+            .iter()
+            .chain(&self.interfaces)
+            .map(String::as_str)
+    }
+}
+
+/// Supertype that is referenced but not part of the hierarchy
+#[derive(Debug, Clone, PartialEq)]
+pub struct Missing {
+    pub name: String,
+    /// Types naming it as their superclass or interface, sorted
+    pub referenced_by: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Hierarchy {
+    types: BTreeMap<String, Type>,
+    /// Direct subtypes by the name of their superclass or interface, which may be missing
+    subtypes: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Hierarchy {
+    pub fn new() -> Hierarchy {
+        Hierarchy::default()
+    }
+
+    pub fn from_classes<'a>(classes: impl IntoIterator<Item = &'a Class>) -> Hierarchy {
+        let mut hierarchy = Hierarchy::new();
+        for class in classes {
+            hierarchy.add_class(class);
+        }
+        hierarchy
+    }
+
+    /// Adds the class, replacing a type of the same name. Classes without a valid name are skipped.
+    pub fn add_class(&mut self, class: &Class) {
+        if let Some(node) = Type::from_class(class) {
+            self.add(node);
+        }
+    }
+
+    pub fn add(&mut self, node: Type) {
+        if let Some(old) = self.types.remove(&node.name) {
+            for supertype in old.direct_supertypes() {
+                if let Some(subtypes) = self.subtypes.get_mut(supertype) {
+                    subtypes.remove(&old.name);
+                }
+            }
+        }
+        for supertype in node.direct_supertypes() {
+            self.subtypes
+                .entry(supertype.to_string())
+                .or_default()
+                .insert(node.name.clone());
+        }
+        self.types.insert(node.name.clone(), node);
+    }
+
+    /// Loads missing supertypes from the class path until every supertype is either
+    /// present or not found there. Errors other than classes that are not found are returned.
+    pub fn complete(&mut self, class_path: &ClassPath) -> Result<()> {
+        let mut not_found = HashSet::new();
+        loop {
+            let missing: Vec<String> = self
+                .missing()
+                .into_iter()
+                .map(|missing| missing.name)
+                .filter(|name| !not_found.contains(name))
+                .collect();
+            if missing.is_empty() {
+                return Ok(());
+            }
+            for name in missing {
+                match class_path.class(&name) {
+                    Ok(class) => self.add_class(&class),
+                    Err(error) if error.kind() == ErrorKind::NotFound => {
+                        not_found.insert(name);
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Type> {
+        self.types.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.types.contains_key(name)
+    }
+
+    /// All types sorted by name
+    pub fn types(&self) -> impl Iterator<Item = &Type> {
+        self.types.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
+    /// Types that extend or implement `name` directly, sorted by name
+    pub fn direct_subtypes(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.subtypes
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
+    /// All supertypes of `name` without itself, nearest first: breadth first with the
+    /// superclass before the interfaces. Supertypes that are missing are included.
+    pub fn supertypes(&self, name: &str) -> Vec<&str> {
+        self.reachable(name, |name| {
+            self.get(name)
+                .into_iter()
+                .flat_map(Type::direct_supertypes)
+                .collect()
+        })
+    }
+
+    /// All subtypes of `name` without itself, nearest first
+    pub fn subtypes(&self, name: &str) -> Vec<&str> {
+        self.reachable(name, |name| self.direct_subtypes(name).collect())
+    }
+
+    /// Classes that are not interfaces and implement `interface`, directly, through
+    /// their superclasses or through interfaces extending it, sorted by name
+    pub fn implementations(&self, interface: &str) -> Vec<&str> {
+        let mut implementations: Vec<&str> = self
+            .subtypes(interface)
+            .into_iter()
+            .filter(|name| self.get(name).is_some_and(|node| !node.is_interface()))
+            .collect();
+        implementations.sort_unstable();
+        implementations
+    }
+
+    /// `name` followed by its superclasses up to `java/lang/Object`, or up to the first
+    /// one that is missing, which is still included. Stops before repeating a class of a cycle.
+    pub fn superclass_chain<'a>(&'a self, name: &'a str) -> Vec<&'a str> {
+        let mut chain = vec![name];
+        let mut current = name;
+        while let Some(super_name) = self
+            .get(current)
+            .and_then(|node| node.super_name.as_deref())
+        {
+            if chain.contains(&super_name) {
+                break;
+            }
+            chain.push(super_name);
+            current = super_name;
+        }
+        chain
+    }
+
+    /// Whether a value of type `name` can be assigned to `supertype`. Types that are not
+    /// in the hierarchy are only assignable to themselves and `java/lang/Object`.
+    pub fn is_subtype(&self, name: &str, supertype: &str) -> bool {
+        if name == supertype || supertype == OBJECT {
+            return true;
+        }
+        match (array_component(name), array_component(supertype)) {
+            (Some(component), Some(super_component)) => {
+                let reference = |component: &str| component.starts_with(['L', '[']);
+                reference(component)
+                    && reference(super_component)
+                    && self.is_subtype(class_name(component), class_name(super_component))
+            }
+            // Arrays also implement Cloneable and Serializable
+            (Some(_), None) => {
+                matches!(supertype, "java/lang/Cloneable" | "java/io/Serializable")
+            }
+            (None, Some(_)) => false,
+            (None, None) => self.supertypes(name).contains(&supertype),
+        }
+    }
+
+    /// Nearest superclass shared by both types, as the verifier merges them: interfaces are
+    /// treated as `java/lang/Object`, and arrays of references merge by their components.
+    /// `None` if it cannot be told because superclasses of either type are missing.
+    pub fn common_superclass(&self, a: &str, b: &str) -> Option<String> {
+        if a == b {
+            return Some(a.to_string());
+        }
+        match (array_component(a), array_component(b)) {
+            (Some(a), Some(b)) => {
+                let reference = |component: &str| component.starts_with(['L', '[']);
+                if !reference(a) || !reference(b) {
+                    return Some(OBJECT.to_string());
+                }
+                let common = self.common_superclass(class_name(a), class_name(b))?;
+                return Some(match common.starts_with('[') {
+                    true => format!("[{common}"),
+                    false => format!("[L{common};"),
+                });
+            }
+            (Some(_), None) | (None, Some(_)) => return Some(OBJECT.to_string()),
+            (None, None) => {}
+        }
+        let is_interface = |name: &str| self.get(name).is_some_and(Type::is_interface);
+        if is_interface(a) || is_interface(b) {
+            return Some(OBJECT.to_string());
+        }
+        let chain_a = self.superclass_chain(a);
+        let chain_b = self.superclass_chain(b);
+        if let Some(common) = chain_a.iter().find(|name| chain_b.contains(name)) {
+            return Some(common.to_string());
+        }
+        // Chains end at a missing superclass, which might still be a common one
+        let complete = |chain: &[&str]| chain.last() == Some(&OBJECT);
+        (complete(&chain_a) && complete(&chain_b)).then(|| OBJECT.to_string())
+    }
+
+    /// Supertypes that are not in the hierarchy, sorted by name
+    pub fn missing(&self) -> Vec<Missing> {
+        let mut missing: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for node in self.types.values() {
+            for supertype in node.direct_supertypes() {
+                if !self.types.contains_key(supertype) {
+                    missing
+                        .entry(supertype)
+                        .or_default()
+                        .push(node.name.clone());
+                }
+            }
+        }
+        missing
+            .into_iter()
+            .map(|(name, referenced_by)| Missing {
+                name: name.to_string(),
+                referenced_by,
+            })
+            .collect()
+    }
+
+    /// Types that are their own supertypes, each cycle listed once starting from its
+    /// smallest name, in the order of extending, like `[A, B]` for `A extends B extends A`
+    pub fn cycles(&self) -> Vec<Vec<&str>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            Visiting,
+            Done,
+        }
+        let mut states: BTreeMap<&str, State> = BTreeMap::new();
+        let mut cycles: BTreeSet<Vec<&str>> = BTreeSet::new();
+        for start in self.types.keys() {
+            if states.contains_key(start.as_str()) {
+                continue;
+            }
+            // Depth first search with an explicit stack of types and their next supertype
+            let mut path: Vec<(&str, usize)> = vec![(start, 0)];
+            states.insert(start, State::Visiting);
+            while let Some(top) = path.last_mut() {
+                let (name, next) = *top;
+                top.1 += 1;
+                let supertype = self
+                    .get(name)
+                    .and_then(|node| node.direct_supertypes().nth(next));
+                let Some(supertype) = supertype else {
+                    states.insert(name, State::Done);
+                    path.pop();
+                    continue;
+                };
+                match states.get(supertype) {
+                    Some(State::Visiting) => {
+                        let start = path
+                            .iter()
+                            .position(|(name, _)| *name == supertype)
+                            .expect("types being visited are on the path");
+                        let mut cycle: Vec<&str> =
+                            path[start..].iter().map(|(name, _)| *name).collect();
+                        let smallest = (0..cycle.len())
+                            .min_by_key(|i| cycle[*i])
+                            .expect("cycles are not empty");
+                        cycle.rotate_left(smallest);
+                        cycles.insert(cycle);
+                    }
+                    Some(State::Done) => {}
+                    None if self.contains(supertype) => {
+                        states.insert(supertype, State::Visiting);
+                        path.push((supertype, 0));
+                    }
+                    None => {}
+                }
+            }
+        }
+        cycles.into_iter().collect()
+    }
+
+    /// Types reachable from `name` through `next`, breadth first without `name` itself
+    fn reachable<'a>(&'a self, name: &str, next: impl Fn(&str) -> Vec<&'a str>) -> Vec<&'a str> {
+        let mut seen: HashSet<&str> = HashSet::from([name]);
+        let mut queue: VecDeque<&str> = VecDeque::from([name]);
+        let mut result = vec![];
+        while let Some(current) = queue.pop_front() {
+            for found in next(current) {
+                if seen.insert(found) {
+                    result.push(found);
+                    queue.push_back(found);
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Component type of an array descriptor like `[Ljava/lang/String;`, `None` for classes
+fn array_component(name: &str) -> Option<&str> {
+    name.strip_prefix('[')
+}
+
+/// Internal name of a component type: `Ljava/lang/String;` becomes `java/lang/String`
+/// and array descriptors stay as they are
+fn class_name(component: &str) -> &str {
+    component
+        .strip_prefix('L')
+        .and_then(|name| name.strip_suffix(';'))
+        .unwrap_or(component)
+}
@@ -0,0 +1,158 @@
+//! Start of the JDK before the main method runs, as the JVM does it. When the class path
+//! has the classes of `java.base`, the interpreter runs them rather than its stubs: it
+//! initializes the core classes, creates the `system` and `main` thread groups and the
+//! main thread, and calls `System.initPhase1`, which reads the system properties and
+//! sets up `System.in`, `System.out` and `System.err` on the file descriptors of the
+//! process. Class libraries before JDK 9 are set up by `System.initializeSystemClass`
+//! instead. The native methods these classes need are those of [`jdk`], [`jdk8`] and
+//! [`misc`].
+//!
+//! [`jdk`]: crate::interpreter::jdk
+//! [`jdk8`]: crate::interpreter::jdk8
+//! [`misc`]: crate::interpreter::misc
+
+use crate::interpreter::builtins::{SYSTEM, THREAD};
+use crate::interpreter::exception::throw;
+use crate::interpreter::mirror::CLASS;
+use crate::interpreter::strings::STRING;
+use crate::interpreter::value::{Ref, Value};
+use crate::interpreter::Interpreter;
+use crate::raw::invalid_data;
+use crate::resolve::Resolver;
+use std::io::Result;
+
+const THREAD_GROUP: &str = "java/lang/ThreadGroup";
+
+/// `Thread.NORM_PRIORITY`, the priority of the main thread
+const NORM_PRIORITY: i32 = 5;
+
+impl Interpreter<'_> {
+    /// Whether the class path has the classes of a JDK to boot
+    pub fn has_jdk(&self) -> bool {
+        self.class_path.class(SYSTEM).is_ok()
+    }
+
+    /// Boots the JDK on the class path, after which its classes run rather than the stubs
+    pub fn boot(&mut self) -> Result<()> {
+        self.jdk = true;
+        for class in [STRING, SYSTEM, CLASS, THREAD_GROUP] {
+            let id = self.load_class(class)?;
+            self.initialize(id)?;
+        }
+
+        // The system thread group has a constructor of its own for the JVM
+        let system = self.construct(THREAD_GROUP, "()V", &[])?;
+        self.handles.push(system);
+        let name = self.intern("main");
+        let main = name.and_then(|name| {
+            let arguments = [Value::Reference(Some(system)), Value::Reference(Some(name))];
+            self.construct(
+                THREAD_GROUP,
+                "(Ljava/lang/ThreadGroup;Ljava/lang/String;)V",
+                &arguments,
+            )
+        });
+        self.handles.pop();
+        let main = main?;
+        self.handles.push(main);
+        let thread = self.create_main_thread(main);
+        self.handles.pop();
+        thread?;
+
+        // Class libraries before JDK 9, like `jre/lib/rt.jar`, have no phases
+        let system = self.class_path.class(SYSTEM)?;
+        match system.method("initPhase1", "()V") {
+            Some(_) => self.call_static(SYSTEM, "initPhase1", "()V", vec![])?,
+            None => self.call_static(SYSTEM, "initializeSystemClass", "()V", vec![])?,
+        };
+        Ok(())
+    }
+
+    /// Creates the main thread in the group. Its constructor asks for the current thread,
+    /// so the object is the current thread before the constructor runs.
+    fn create_main_thread(&mut self, group: Ref) -> Result<()> {
+        let id = self.load_class(THREAD)?;
+        self.initialize(id)?;
+        let thread = self.new_object(THREAD)?;
+        self.thread = Some(thread);
+        self.set_field(thread, THREAD, "priority", Value::Int(NORM_PRIORITY));
+        let name = self.intern("main")?;
+        let arguments = [Value::Reference(Some(group)), Value::Reference(Some(name))];
+        self.init(
+            thread,
+            THREAD,
+            "(Ljava/lang/ThreadGroup;Ljava/lang/String;)V",
+            &arguments,
+        )
+    }
+
+    /// New instance of the class, initialized by its constructor with the descriptor
+    pub fn construct(&mut self, class: &str, descriptor: &str, arguments: &[Value]) -> Result<Ref> {
+        let id = self.load_class(class)?;
+        self.initialize(id)?;
+        let object = self.new_object(class)?;
+        self.handles.push(object);
+        let result = self.init(object, class, descriptor, arguments);
+        self.handles.pop();
+        result.map(|()| object)
+    }
+
+    /// Runs the constructor of the class with the descriptor on the object
+    fn init(
+        &mut self,
+        object: Ref,
+        class: &str,
+        descriptor: &str,
+        arguments: &[Value],
+    ) -> Result<()> {
+        let id = self.load_class(class)?;
+        let loaded = self.classes[id.0].class.clone();
+        let (index, _) = loaded
+            .method("<init>", descriptor)
+            .ok_or_else(|| invalid_data(format!("{class} has no constructor {descriptor}")))?;
+        let mut all = vec![Value::Reference(Some(object))];
+        all.extend_from_slice(arguments);
+        self.invoke(loaded, index, all)?;
+        Ok(())
+    }
+
+    /// Calls the static method of the class, initializing the class first
+    pub fn call_static(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+        arguments: Vec<Value>,
+    ) -> Result<Option<Value>> {
+        let id = self.load_class(class)?;
+        self.initialize(id)?;
+        let loaded = self.classes[id.0].class.clone();
+        let (index, _) = loaded
+            .method(name, descriptor)
+            .ok_or_else(|| invalid_data(format!("{class} has no method {name}{descriptor}")))?;
+        self.invoke(loaded, index, arguments)
+    }
+
+    /// Calls the method that the class of the object selects, with the object as receiver
+    pub fn call_method(
+        &mut self,
+        object: Ref,
+        name: &str,
+        descriptor: &str,
+        arguments: Vec<Value>,
+    ) -> Result<Option<Value>> {
+        let class = self.class_name(object);
+        let selected = Resolver::new(self.class_path)
+            .select(class, name, descriptor)
+            .map_err(|error| throw(error.error, error.message))?;
+        let member = selected.ok_or_else(|| {
+            throw(
+                "java/lang/AbstractMethodError",
+                format!("{class}.{name}{descriptor}"),
+            )
+        })?;
+        let mut all = vec![Value::Reference(Some(object))];
+        all.extend(arguments);
+        self.call(&member, all)
+    }
+}
@@ -0,0 +1,379 @@
+//! Native methods of the JDK that the interpreter binds in the [`Registry`] of every
+//! interpreter: `System.arraycopy`, `identityHashCode`, `currentTimeMillis` and
+//! `nanoTime`, `Object.hashCode`, `Thread.currentThread`, and the functions of `Math` and
+//! `StrictMath` that map to those of Rust.
+
+use crate::hierarchy::OBJECT;
+use crate::interpreter::exception::{throw, throw_without_message};
+use crate::interpreter::heap::Object;
+use crate::interpreter::loader::reference_component;
+use crate::interpreter::natives;
+use crate::interpreter::registry::Registry;
+use crate::interpreter::value::{Ref, Value};
+use crate::interpreter::{null_receiver, Interpreter};
+use crate::raw::FieldType;
+use std::io::Result;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const SYSTEM: &str = "java/lang/System";
+pub const THREAD: &str = "java/lang/Thread";
+
+/// Binds the native methods of this module
+pub fn register(registry: &mut Registry) {
+    registry.register(
+        SYSTEM,
+        "arraycopy",
+        "(Ljava/lang/Object;ILjava/lang/Object;II)V",
+        array_copy,
+    );
+    registry.register(
+        SYSTEM,
+        "identityHashCode",
+        "(Ljava/lang/Object;)I",
+        identity_hash_code,
+    );
+    registry.register(SYSTEM, "currentTimeMillis", "()J", current_time_millis);
+    registry.register(SYSTEM, "nanoTime", "()J", nano_time);
+    registry.register(OBJECT, "hashCode", "()I", hash_code);
+    registry.register(
+        THREAD,
+        "currentThread",
+        "()Ljava/lang/Thread;",
+        current_thread,
+    );
+    for class in ["java/lang/Math", "java/lang/StrictMath"] {
+        register_math(registry, class);
+    }
+}
+
+/// Function of `Math` of one double
+type Unary = fn(f64) -> f64;
+
+/// Functions of `Math` or `StrictMath`
+fn register_math(registry: &mut Registry, class: &str) {
+    let unary: [(&str, Unary); 18] = [
+        ("sqrt", f64::sqrt),
+        ("cbrt", f64::cbrt),
+        ("sin", f64::sin),
+        ("cos", f64::cos),
+        ("tan", f64::tan),
+        ("asin", f64::asin),
+        ("acos", f64::acos),
+        ("atan", f64::atan),
+        ("sinh", f64::sinh),
+        ("cosh", f64::cosh),
+        ("tanh", f64::tanh),
+        ("exp", f64::exp),
+        ("expm1", f64::exp_m1),
+        ("log", f64::ln),
+        ("log10", f64::log10),
+        ("log1p", f64::ln_1p),
+        ("floor", f64::floor),
+        ("ceil", f64::ceil),
+    ];
+    for (name, function) in unary {
+        registry.register(class, name, "(D)D", move |_: &mut Interpreter, x: f64| {
+            Ok(function(x))
+        });
+    }
+    registry.register(class, "rint", "(D)D", |_: &mut Interpreter, x: f64| {
+        Ok(x.round_ties_even())
+    });
+    registry.register(
+        class,
+        "atan2",
+        "(DD)D",
+        |_: &mut Interpreter, y: f64, x: f64| Ok(y.atan2(x)),
+    );
+    registry.register(
+        class,
+        "hypot",
+        "(DD)D",
+        |_: &mut Interpreter, x: f64, y: f64| Ok(x.hypot(y)),
+    );
+    registry.register(
+        class,
+        "pow",
+        "(DD)D",
+        |_: &mut Interpreter, x: f64, y: f64| Ok(pow(x, y)),
+    );
+
+    registry.register(class, "abs", "(I)I", |_: &mut Interpreter, x: i32| {
+        Ok(x.wrapping_abs())
+    });
+    registry.register(class, "abs", "(J)J", |_: &mut Interpreter, x: i64| {
+        Ok(x.wrapping_abs())
+    });
+    registry.register(class, "abs", "(F)F", |_: &mut Interpreter, x: f32| {
+        Ok(x.abs())
+    });
+    registry.register(class, "abs", "(D)D", |_: &mut Interpreter, x: f64| {
+        Ok(x.abs())
+    });
+    registry.register(
+        class,
+        "max",
+        "(II)I",
+        |_: &mut Interpreter, a: i32, b: i32| Ok(a.max(b)),
+    );
+    registry.register(
+        class,
+        "max",
+        "(JJ)J",
+        |_: &mut Interpreter, a: i64, b: i64| Ok(a.max(b)),
+    );
+    registry.register(
+        class,
+        "max",
+        "(FF)F",
+        |_: &mut Interpreter, a: f32, b: f32| Ok(max(a.into(), b.into()) as f32),
+    );
+    registry.register(
+        class,
+        "max",
+        "(DD)D",
+        |_: &mut Interpreter, a: f64, b: f64| Ok(max(a, b)),
+    );
+    registry.register(
+        class,
+        "min",
+        "(II)I",
+        |_: &mut Interpreter, a: i32, b: i32| Ok(a.min(b)),
+    );
+    registry.register(
+        class,
+        "min",
+        "(JJ)J",
+        |_: &mut Interpreter, a: i64, b: i64| Ok(a.min(b)),
+    );
+    registry.register(
+        class,
+        "min",
+        "(FF)F",
+        |_: &mut Interpreter, a: f32, b: f32| Ok(min(a.into(), b.into()) as f32),
+    );
+    registry.register(
+        class,
+        "min",
+        "(DD)D",
+        |_: &mut Interpreter, a: f64, b: f64| Ok(min(a, b)),
+    );
+}
+
+/// `Math.pow`, which unlike C is NaN for a NaN exponent and for 1 or -1 to an infinite
+/// exponent
+fn pow(x: f64, y: f64) -> f64 {
+    if y.is_nan() || (x.abs() == 1.0 && y.is_infinite()) {
+        return f64::NAN;
+    }
+    x.powf(y)
+}
+
+/// `Math.max` of floating-point numbers: NaN if either is, and 0.0 rather than -0.0
+fn max(a: f64, b: f64) -> f64 {
+    match (a.is_nan(), b.is_nan()) {
+        (true, _) => a,
+        (_, true) => b,
+        _ if a == 0.0 && b == 0.0 && a.is_sign_negative() => b,
+        _ if a >= b => a,
+        _ => b,
+    }
+}
+
+/// `Math.min` of floating-point numbers: NaN if either is, and -0.0 rather than 0.0
+fn min(a: f64, b: f64) -> f64 {
+    match (a.is_nan(), b.is_nan()) {
+        (true, _) => a,
+        (_, true) => b,
+        _ if a == 0.0 && b == 0.0 && b.is_sign_negative() => b,
+        _ if a <= b => a,
+        _ => b,
+    }
+}
+
+fn current_time_millis(_: &mut Interpreter) -> Result<i64> {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Ok(elapsed.as_millis() as i64)
+}
+
+/// Nanoseconds since the interpreter was created
+fn nano_time(interpreter: &mut Interpreter) -> Result<i64> {
+    Ok(interpreter.started.elapsed().as_nanos() as i64)
+}
+
+/// Hash code of the object as `Object.hashCode` makes it. Objects are never moved, so
+/// the index of an object identifies it while it lives, like `Object.toString` shows it.
+fn identity_hash_code(_: &mut Interpreter, object: Option<Ref>) -> Result<i32> {
+    Ok(object.map_or(0, |object| object.0 as i32))
+}
+
+/// `Object.hashCode`, the identity hash code, or the hash of the text for the strings of
+/// the stubs
+fn hash_code(interpreter: &mut Interpreter, object: Option<Ref>) -> Result<i32> {
+    let object = object.ok_or_else(|| null_receiver(OBJECT, "hashCode", "()I"))?;
+    match interpreter.string(object) {
+        Some(text) => Ok(natives::string_hash(&text)),
+        None => identity_hash_code(interpreter, Some(object)),
+    }
+}
+
+/// The `Thread` of the main method, created without running its constructor on the first
+/// call, with its name and priority
+fn current_thread(interpreter: &mut Interpreter) -> Result<Option<Ref>> {
+    if let Some(thread) = interpreter.thread {
+        return Ok(Some(thread));
+    }
+    // Not initialized, as its static initializer registers natives of the JDK
+    let id = interpreter.load_class(THREAD)?;
+    let fields = interpreter.classes[id.0].default_fields();
+    let thread = interpreter.allocate(Object::Instance { class: id, fields })?;
+    interpreter.thread = Some(thread);
+    let name = interpreter.intern("main")?;
+    for (field, value) in [
+        ("name", Value::Reference(Some(name))),
+        ("priority", Value::Int(5)),
+    ] {
+        if let Some(slot) = interpreter.instance_slot(THREAD, field) {
+            interpreter.fields(thread)?[slot] = value;
+        }
+    }
+    Ok(Some(thread))
+}
+
+/// `System.arraycopy`, with the checks and exceptions of the JVM. Elements of arrays of
+/// references are checked one by one if the component types differ, and those before
+/// the first that does not fit the destination are copied.
+fn array_copy(
+    interpreter: &mut Interpreter,
+    source: Option<Ref>,
+    source_position: i32,
+    destination: Option<Ref>,
+    destination_position: i32,
+    length: i32,
+) -> Result<()> {
+    let (Some(source), Some(destination)) = (source, destination) else {
+        return Err(throw_without_message("java/lang/NullPointerException"));
+    };
+    let array_store = |message: String| {
+        throw(
+            "java/lang/ArrayStoreException",
+            format!("arraycopy: {message}"),
+        )
+    };
+    let out_of_bounds = |message: String| {
+        throw(
+            "java/lang/ArrayIndexOutOfBoundsException",
+            format!("arraycopy: {message}"),
+        )
+    };
+    let source_class = interpreter.class_name(source).to_string();
+    let destination_class = interpreter.class_name(destination).to_string();
+    let Some(source_component) = source_class.strip_prefix('[') else {
+        let name = external_name(&source_class);
+        return Err(array_store(format!("source type {name} is not an array")));
+    };
+    let Some(destination_component) = destination_class.strip_prefix('[') else {
+        let name = external_name(&destination_class);
+        return Err(array_store(format!(
+            "destination type {name} is not an array"
+        )));
+    };
+    let source_references = reference_component(source_component);
+    let destination_references = reference_component(destination_component);
+    match (source_references, destination_references) {
+        (None, _) | (_, None) if source_component != destination_component => {
+            let kind = |component: &str| match FieldType::parse(component) {
+                Some(field_type) if reference_component(component).is_none() => {
+                    field_type.java_name()
+                }
+                _ => "object array".to_string(),
+            };
+            return Err(array_store(format!(
+                "type mismatch: can not copy {}[] into {}[]",
+                kind(source_component),
+                kind(destination_component)
+            )));
+        }
+        _ => {}
+    }
+
+    let source_length = interpreter.elements(Some(source), String::new)?.len();
+    let destination_length = interpreter.elements(Some(destination), String::new)?.len();
+    let describe = |component: &str, length: usize| match FieldType::parse(component) {
+        Some(field_type) if reference_component(component).is_none() => {
+            format!("{}[{length}]", field_type.java_name())
+        }
+        _ => format!("object array[{length}]"),
+    };
+    let source_name = describe(source_component, source_length);
+    let destination_name = describe(destination_component, destination_length);
+    if source_position < 0 {
+        let message = format!("source index {source_position} out of bounds for {source_name}");
+        return Err(out_of_bounds(message));
+    }
+    if destination_position < 0 {
+        return Err(out_of_bounds(format!(
+            "destination index {destination_position} out of bounds for {destination_name}"
+        )));
+    }
+    if length < 0 {
+        return Err(out_of_bounds(format!("length {length} is negative")));
+    }
+    let (source_position, destination_position, length) = (
+        source_position as usize,
+        destination_position as usize,
+        length as usize,
+    );
+    if source_position + length > source_length {
+        let last = source_position + length;
+        let message = format!("last source index {last} out of bounds for {source_name}");
+        return Err(out_of_bounds(message));
+    }
+    if destination_position + length > destination_length {
+        let last = destination_position + length;
+        return Err(out_of_bounds(format!(
+            "last destination index {last} out of bounds for {destination_name}"
+        )));
+    }
+
+    let values =
+        interpreter.elements(Some(source), String::new)?[source_position..][..length].to_vec();
+    let mut copied = length;
+    let mut mismatch = None;
+    if let (Some(from), Some(to)) = (source_references, destination_references) {
+        if !interpreter.is_instance_of(from, to) {
+            copied = values
+                .iter()
+                .position(|value| match value {
+                    Value::Reference(Some(element)) => {
+                        !interpreter.is_instance_of(interpreter.class_name(*element), to)
+                    }
+                    _ => false,
+                })
+                .unwrap_or(length);
+            let widens = interpreter.is_instance_of(to, from);
+            let (from, to) = (external_name(from), external_name(to));
+            mismatch = Some(match widens {
+                true => format!(
+                    "element type mismatch: can not cast one of the elements of {from}[] to \
+                     the type of the destination array, {to}"
+                ),
+                false => format!("type mismatch: can not copy {from}[] into {to}[]"),
+            });
+        }
+    }
+    interpreter.elements(Some(destination), String::new)?[destination_position..][..copied]
+        .copy_from_slice(&values[..copied]);
+    match mismatch {
+        Some(message) if copied < length => Err(array_store(message)),
+        _ => Ok(()),
+    }
+}
+
+/// Name of a class or array type in messages of the JVM, like `java.lang.String` or
+/// `[Ljava.lang.String;`
+fn external_name(class: &str) -> String {
+    class.replace('/', ".")
+}
@@ -0,0 +1,345 @@
+//! Exceptions of the programs. A thrown exception travels up the Rust call stack as an
+//! [`std::io::Error`] carrying a [`Thrown`], and every frame it reaches looks for a
+//! handler in the exception table of its code (JVMS §2.10). Exceptions the interpreter
+//! raises itself, like `ArithmeticException` for a division by zero, become objects of
+//! their JDK class when a frame gets them, so they are caught like those the program
+//! throws. Without a JDK on the class path they cannot be created and end the program.
+//!
+//! The constructors and a few methods of `java/lang/Throwable` are provided natively,
+//! see [`natives`]. They keep the frames an exception was created in as an
+//! [`Object::Backtrace`] in the `backtrace` field, from which the stack trace of an
+//! uncaught exception is printed.
+
+use crate::interpreter::heap::Object;
+use crate::interpreter::value::{Ref, Value};
+use crate::interpreter::{natives, Interpreter};
+use crate::raw::class::Class;
+use crate::raw::{class_name_at, invalid_data, utf8_at, AccessFlags, AttributeInfo, Exception};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::Result;
+
+pub const THROWABLE: &str = "java/lang/Throwable";
+
+/// Frames kept in stack traces, the innermost ones, as many as the JVM keeps
+const MAX_TRACE_FRAMES: usize = 1024;
+
+/// Exception thrown by the program, as the payload of an [`std::io::Error`]
+#[derive(Debug)]
+pub enum Thrown {
+    /// Raised by the interpreter, to be created with the internal class name and message
+    Pending {
+        class: String,
+        message: Option<String>,
+    },
+    /// Instance of a subclass of `java/lang/Throwable`
+    Object(Ref),
+}
+
+impl Display for Thrown {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Thrown::Pending { class, message } => {
+                write!(f, "{}", class.replace('/', "."))?;
+                match message {
+                    Some(message) => write!(f, ": {message}"),
+                    None => Ok(()),
+                }
+            }
+            Thrown::Object(exception) => write!(f, "exception object {}", exception.0),
+        }
+    }
+}
+
+impl Error for Thrown {}
+
+/// Error raising an exception of the class, given by its internal name
+pub fn throw(class: &str, message: impl Into<String>) -> std::io::Error {
+    let message = Some(message.into());
+    let class = class.to_string();
+    std::io::Error::other(Thrown::Pending { class, message })
+}
+
+/// Error raising an exception of the class without a message, like those the JVM raises
+/// in native methods
+pub fn throw_without_message(class: &str) -> std::io::Error {
+    let class = class.to_string();
+    std::io::Error::other(Thrown::Pending {
+        class,
+        message: None,
+    })
+}
+
+/// Error throwing the exception object
+pub fn rethrow(exception: Ref) -> std::io::Error {
+    std::io::Error::other(Thrown::Object(exception))
+}
+
+/// Exception the error carries, if it is not an internal failure of the interpreter
+pub fn thrown(error: &std::io::Error) -> Option<&Thrown> {
+    error.get_ref()?.downcast_ref()
+}
+
+/// Method of a frame in a stack trace
+#[derive(Debug, Clone)]
+pub struct TraceElement {
+    /// Module of the class, for those of a runtime image like `java.base`
+    pub module: Option<String>,
+    /// Binary name of the class, like `java.lang.String`
+    pub class: String,
+    pub method: String,
+    pub file: Option<String>,
+    pub line: Option<u16>,
+    /// Whether the method is native, which has no source file and line
+    pub native: bool,
+}
+
+impl Display for TraceElement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(module) = &self.module {
+            write!(f, "{module}/")?;
+        }
+        write!(f, "{}.{}(", self.class, self.method)?;
+        if self.native {
+            return write!(f, "Native Method)");
+        }
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{file}:{line})"),
+            (Some(file), None) => write!(f, "{file})"),
+            (None, _) => write!(f, "Unknown Source)"),
+        }
+    }
+}
+
+impl TraceElement {
+    /// Element for the instruction at `offset` in the method of the class under `index`
+    fn new(class: &Class, module: Option<&str>, index: usize, offset: u32) -> TraceElement {
+        let method = &class.methods[index];
+        let file = class
+            .attributes
+            .iter()
+            .find_map(|attribute| match &attribute.info {
+                AttributeInfo::SourceFile(file) => Some(file.clone()),
+                _ => None,
+            });
+        // The line of the closest entry at or before the offset
+        let line = method.code().and_then(|code| {
+            let lines = code
+                .attributes
+                .iter()
+                .filter_map(|attribute| match &attribute.info {
+                    AttributeInfo::LineNumberTable(lines) => Some(lines),
+                    _ => None,
+                });
+            lines
+                .flatten()
+                .filter(|line| u32::from(line.start_pc) <= offset)
+                .max_by_key(|line| line.start_pc)
+                .map(|line| line.line_number)
+        });
+        TraceElement {
+            module: module.map(str::to_string),
+            class: class.name().unwrap_or("?").replace('/', "."),
+            method: utf8_at(&class.constant_pool, method.name_index)
+                .unwrap_or("?")
+                .to_string(),
+            file,
+            line,
+            native: method.access_flags.contains(AccessFlags::Native),
+        }
+    }
+}
+
+impl Interpreter<'_> {
+    /// Exception object the error throws, created now for exceptions raised by the
+    /// interpreter. Returns the error itself for internal failures, or when the class of
+    /// the exception cannot be loaded.
+    pub fn exception(&mut self, error: std::io::Error) -> Result<Ref> {
+        match thrown(&error) {
+            Some(Thrown::Object(exception)) => Ok(*exception),
+            Some(Thrown::Pending { class, message }) => {
+                if self.load_class(class).is_err() || self.load_class(THROWABLE).is_err() {
+                    return Err(error);
+                }
+                let (class, message) = (class.clone(), message.clone());
+                self.without_heap_limit(|interpreter| {
+                    interpreter.new_throwable(&class, message.as_deref(), None)
+                })
+            }
+            None => Err(error),
+        }
+    }
+
+    /// Creates an exception of the class with the message and cause, without running its
+    /// constructor, and fills in its stack trace
+    pub fn new_throwable(
+        &mut self,
+        class: &str,
+        message: Option<&str>,
+        cause: Option<Ref>,
+    ) -> Result<Ref> {
+        let id = self.load_class(class)?;
+        self.initialize(id)?;
+        let exception = self.new_object(class)?;
+        self.handles.push(exception);
+        let result = message
+            .map(|message| self.new_string(message))
+            .transpose()
+            .and_then(|message| {
+                let cause = cause.unwrap_or(exception);
+                self.init_throwable(exception, message, Some(cause))
+            });
+        self.handles.pop();
+        result.map(|()| exception)
+    }
+
+    /// Sets the fields of the exception like the constructors of `Throwable` do. A cause
+    /// that is the exception itself means that none was given, so `initCause` may set it.
+    pub fn init_throwable(
+        &mut self,
+        exception: Ref,
+        message: Option<Ref>,
+        cause: Option<Ref>,
+    ) -> Result<()> {
+        self.set_throwable_field(exception, "detailMessage", Value::Reference(message));
+        self.set_throwable_field(exception, "cause", Value::Reference(cause));
+        self.fill_in_stack_trace(exception)
+    }
+
+    /// Keeps the frames of the running methods in the exception, leaving out those of
+    /// its constructors
+    pub fn fill_in_stack_trace(&mut self, exception: Ref) -> Result<()> {
+        let class = self.class_name(exception).to_string();
+        let frames = self.frames.iter().rev().skip_while(|frame| {
+            let method = &frame.class.methods[frame.method];
+            let name = utf8_at(&frame.class.constant_pool, method.name_index);
+            let owner = frame.class.name().unwrap_or_default();
+            name == Some("<init>") && self.is_instance_of(&class, owner)
+        });
+        let trace = frames
+            .filter(|frame| !frame.class.name().is_some_and(|name| self.is_hidden(name)))
+            .take(MAX_TRACE_FRAMES)
+            .map(|frame| {
+                let module = frame
+                    .class
+                    .name()
+                    .and_then(|name| self.class_path.module(name));
+                TraceElement::new(&frame.class, module, frame.method, frame.offset)
+            })
+            .collect();
+        let backtrace = self.allocate(Object::Backtrace(trace))?;
+        self.set_throwable_field(exception, "backtrace", Value::Reference(Some(backtrace)));
+        Ok(())
+    }
+
+    /// Offset of the handler of the code for the exception thrown at `offset`
+    pub fn handler(
+        &mut self,
+        class: &Class,
+        exception_table: &[Exception],
+        offset: u32,
+        exception: Ref,
+    ) -> Result<Option<u16>> {
+        let thrown = self.class_name(exception).to_string();
+        for entry in exception_table {
+            if offset < entry.start_pc.into() || offset >= entry.end_pc.into() {
+                continue;
+            }
+            if entry.catch_type == 0 {
+                return Ok(Some(entry.handler_pc));
+            }
+            let catch_type = class_name_at(&class.constant_pool, entry.catch_type)
+                .ok_or_else(|| invalid_data(format!("#{} is not a class", entry.catch_type)))?;
+            if self.is_instance_of(&thrown, catch_type) {
+                return Ok(Some(entry.handler_pc));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Text of the exception like `Throwable.toString` makes it, the class name followed
+    /// by the message if there is one
+    pub fn throwable_to_string(&mut self, exception: Ref) -> String {
+        let class = self.class_name(exception).replace('/', ".");
+        let message = self.throwable_field(exception, "detailMessage");
+        match message.and_then(|message| self.string(message)) {
+            Some(message) => format!("{class}: {message}"),
+            None => class,
+        }
+    }
+
+    /// Text of the exception by the `toString` method of its class, which `printStackTrace`
+    /// prints. That of `Throwable` is used if the method throws.
+    pub fn describe_throwable(&mut self, exception: Ref) -> String {
+        let selected = natives::overriding(self, exception, "toString", "()Ljava/lang/String;");
+        let member = match selected {
+            Ok(Some(member)) if member.class_name() != THROWABLE => member,
+            _ => return self.throwable_to_string(exception),
+        };
+        self.handles.push(exception);
+        let text = self.call(&member, vec![Value::Reference(Some(exception))]);
+        self.handles.pop();
+        match text.and_then(|text| text.map_or(Ok(None), |text| text.as_reference())) {
+            Ok(Some(text)) => self.string(text).unwrap_or_default(),
+            Ok(None) => "null".to_string(),
+            Err(_) => self.throwable_to_string(exception),
+        }
+    }
+
+    /// Stack trace like `Throwable.printStackTrace` prints it, with the causes. Frames a
+    /// cause has in common with the exception it caused are left out.
+    pub fn stack_trace(&mut self, exception: Ref) -> String {
+        let mut text = String::new();
+        let mut enclosing: Vec<TraceElement> = vec![];
+        let mut seen = vec![];
+        let mut current = Some(exception);
+        while let Some(exception) = current.filter(|exception| !seen.contains(exception)) {
+            seen.push(exception);
+            if !text.is_empty() {
+                text.push_str("Caused by: ");
+            }
+            text.push_str(&self.describe_throwable(exception));
+            text.push('\n');
+            let trace = self.backtrace(exception);
+            let common = trace
+                .iter()
+                .rev()
+                .zip(enclosing.iter().rev())
+                .take_while(|(a, b)| a.to_string() == b.to_string())
+                .count();
+            for element in &trace[..trace.len() - common] {
+                text.push_str(&format!("\tat {element}\n"));
+            }
+            if common > 0 {
+                text.push_str(&format!("\t... {common} more\n"));
+            }
+            enclosing = trace;
+            current = self
+                .throwable_field(exception, "cause")
+                .filter(|cause| *cause != exception);
+        }
+        text
+    }
+
+    /// Frames the exception was created in
+    fn backtrace(&mut self, exception: Ref) -> Vec<TraceElement> {
+        match self.throwable_field(exception, "backtrace") {
+            Some(backtrace) => match self.heap.get(backtrace) {
+                Object::Backtrace(trace) => trace.clone(),
+                _ => vec![],
+            },
+            None => vec![],
+        }
+    }
+
+    /// Value of the reference field of `Throwable` with the name
+    fn throwable_field(&mut self, exception: Ref, name: &str) -> Option<Ref> {
+        self.field_value(exception, THROWABLE, name)?
+            .as_reference()
+            .ok()?
+    }
+
+    fn set_throwable_field(&mut self, exception: Ref, name: &str, value: Value) {
+        self.set_field(exception, THROWABLE, name, value);
+    }
+}
@@ -0,0 +1,169 @@
+//! Mark-sweep garbage collection of the heap. Objects reachable from the local variables
+//! and operand stacks of the frames, the static fields of the loaded classes and the
+//! objects the interpreter keeps, like `System.out`, the interned strings, the main thread,
+//! the mirrors of the classes, the lambdas kept for their call sites and its handles, are
+//! marked. All others are removed.
+//!
+//! A collection runs when an allocation would grow the heap beyond a threshold, which
+//! is set to twice the size of the objects that survive. If the heap is still too small
+//! for the new object, the allocation fails with `OutOfMemoryError`. Values that are
+//! not in any frame, like the arguments of a native method, must be kept on the operand
+//! stack or in the handles of the interpreter while something is allocated.
+
+use crate::interpreter::exception::throw;
+use crate::interpreter::heap::Object;
+use crate::interpreter::value::{Ref, Value};
+use crate::interpreter::Interpreter;
+use std::fmt::{Display, Formatter};
+use std::io::Result;
+use std::time::{Duration, Instant};
+
+/// Bytes the objects may take unless set otherwise
+pub const DEFAULT_MAX_HEAP: usize = 256 << 20;
+
+/// Heap size under which no collection runs
+pub const INITIAL_THRESHOLD: usize = 1 << 20;
+
+/// What the garbage collector did during a run
+#[derive(Debug, Default, Clone)]
+pub struct GcStats {
+    pub collections: usize,
+    /// Time spent collecting
+    pub time: Duration,
+    pub allocated_objects: usize,
+    pub allocated_bytes: usize,
+    pub freed_objects: usize,
+    pub freed_bytes: usize,
+    /// Largest size of the heap
+    pub peak: usize,
+    pub limit: usize,
+}
+
+impl Display for GcStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "gc: {} collections in {:.3} ms",
+            self.collections,
+            self.time.as_secs_f64() * 1000.0
+        )?;
+        writeln!(
+            f,
+            "gc: allocated {} objects, {}",
+            self.allocated_objects,
+            bytes(self.allocated_bytes)
+        )?;
+        writeln!(
+            f,
+            "gc: freed {} objects, {}",
+            self.freed_objects,
+            bytes(self.freed_bytes)
+        )?;
+        writeln!(
+            f,
+            "gc: heap peak {} of {}",
+            bytes(self.peak),
+            bytes(self.limit)
+        )
+    }
+}
+
+/// Size in bytes, KiB or MiB, whichever reads best
+fn bytes(size: usize) -> String {
+    match size {
+        0..1024 => format!("{size} B"),
+        1024..0x100000 => format!("{:.1} KiB", size as f64 / 1024.0),
+        _ => format!("{:.1} MiB", size as f64 / 1048576.0),
+    }
+}
+
+impl Interpreter<'_> {
+    /// Puts the object on the heap, collecting garbage first if the heap has grown
+    pub fn allocate(&mut self, object: Object) -> Result<Ref> {
+        let size = object.size();
+        if self.heap.used() + size > self.gc_threshold {
+            self.collect();
+            let limit = self.heap.limit();
+            self.gc_threshold = (2 * self.heap.used()).clamp(INITIAL_THRESHOLD.min(limit), limit);
+            if self.heap.used() + size > limit {
+                return Err(throw("java/lang/OutOfMemoryError", "Java heap space"));
+            }
+        }
+        self.gc_stats.allocated_objects += 1;
+        self.gc_stats.allocated_bytes += size;
+        let reference = self.heap.insert(object);
+        self.gc_stats.peak = self.gc_stats.peak.max(self.heap.used());
+        Ok(reference)
+    }
+
+    /// Allocates an array of the array type `class`, see [`Object::array`]
+    pub fn allocate_array(&mut self, class: &str, length: usize) -> Result<Ref> {
+        self.allocate(Object::array(class, length))
+    }
+
+    /// Removes the objects that cannot be reached from the roots
+    pub fn collect(&mut self) {
+        let start = Instant::now();
+        let mut marked = vec![false; self.heap.capacity()];
+        let mut pending = self.roots();
+        while let Some(reference) = pending.pop() {
+            if std::mem::replace(&mut marked[reference.0], true) {
+                continue;
+            }
+            pending.extend(self.heap.get(reference).references());
+        }
+        let (count, size) = self.heap.retain(|reference| marked[reference.0]);
+
+        self.gc_stats.collections += 1;
+        self.gc_stats.freed_objects += count;
+        self.gc_stats.freed_bytes += size;
+        self.gc_stats.time += start.elapsed();
+    }
+
+    /// Objects the program can use without following references
+    fn roots(&self) -> Vec<Ref> {
+        let frames = self
+            .frames
+            .iter()
+            .flat_map(|frame| frame.locals.iter().chain(&frame.stack));
+        let statics = self.classes.iter().flat_map(|class| &class.statics);
+        let mut roots: Vec<Ref> = frames
+            .chain(statics)
+            .filter_map(|value| match value {
+                Value::Reference(reference) => *reference,
+                _ => None,
+            })
+            .collect();
+        roots.extend(self.print_streams.iter().flatten());
+        roots.extend(self.strings.values());
+        roots.extend(self.thread);
+        roots.extend(self.mirrors.values());
+        roots.extend(self.lambda_instances());
+        roots.extend(&self.handles);
+        roots
+    }
+
+    /// Runs `f` with no limit on the size of the heap, like the JVM, which keeps memory to
+    /// create exceptions in when the heap is full
+    pub fn without_heap_limit<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let limit = self.heap.limit();
+        self.heap.set_limit(usize::MAX);
+        let result = f(self);
+        self.heap.set_limit(limit);
+        self.gc_threshold = self.gc_threshold.min(limit);
+        result
+    }
+
+    /// Sets the bytes the objects may take, see [`DEFAULT_MAX_HEAP`]
+    pub fn set_max_heap(&mut self, limit: usize) {
+        self.heap.set_limit(limit);
+        self.gc_threshold = INITIAL_THRESHOLD.min(limit);
+    }
+
+    pub fn gc_stats(&self) -> GcStats {
+        GcStats {
+            limit: self.heap.limit(),
+            ..self.gc_stats.clone()
+        }
+    }
+}
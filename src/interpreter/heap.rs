@@ -0,0 +1,160 @@
+use crate::interpreter::exception::TraceElement;
+use crate::interpreter::loader::ClassId;
+use crate::interpreter::value::{Ref, Value};
+use crate::raw::FieldType;
+
+/// Stream written by a `java/io/PrintStream` of the native stubs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stream {
+    Out,
+    Err,
+}
+
+#[derive(Debug, Clone)]
+pub enum Object {
+    /// `System.out` or `System.err`
+    PrintStream(Stream),
+    String(String),
+    /// Object of a loaded class, with the values of its fields by slot
+    Instance {
+        class: ClassId,
+        fields: Vec<Value>,
+    },
+    /// Array of the type, like `[I` or `[Ljava/lang/String;`
+    Array {
+        class: String,
+        elements: Vec<Value>,
+    },
+    /// Frames an exception was created in, kept by the exception
+    Backtrace(Vec<TraceElement>),
+}
+
+impl Object {
+    /// Array of the array type `class`, with `length` elements of the initial value
+    /// of the component type
+    pub fn array(class: &str, length: usize) -> Object {
+        let component = class.strip_prefix('[').and_then(FieldType::parse);
+        let initial = component.map_or(Value::NULL, |component| Value::default_for(&component));
+        Object::Array {
+            class: class.to_string(),
+            elements: vec![initial; length],
+        }
+    }
+
+    /// Bytes the object would take in the JVM, roughly: a header of 16 bytes, 8 bytes for
+    /// each field and references, and the size of the component type for array elements
+    pub fn size(&self) -> usize {
+        const HEADER: usize = 16;
+        HEADER
+            + match self {
+                Object::PrintStream(_) => 0,
+                Object::Backtrace(trace) => 8 * trace.len(),
+                Object::String(text) => text.len(),
+                Object::Instance { fields, .. } => 8 * fields.len(),
+                Object::Array { class, elements } => {
+                    let component = match class.as_bytes().get(1) {
+                        Some(b'Z' | b'B') => 1,
+                        Some(b'C' | b'S') => 2,
+                        Some(b'I' | b'F') => 4,
+                        _ => 8,
+                    };
+                    component * elements.len()
+                }
+            }
+    }
+
+    /// Objects the object refers to
+    pub fn references(&self) -> impl Iterator<Item = Ref> + '_ {
+        let values = match self {
+            Object::Instance { fields, .. } => fields.as_slice(),
+            Object::Array { elements, .. } => elements.as_slice(),
+            Object::PrintStream(_) | Object::String(_) | Object::Backtrace(_) => &[],
+        };
+        values.iter().filter_map(|value| match value {
+            Value::Reference(reference) => *reference,
+            _ => None,
+        })
+    }
+}
+
+/// Objects created by the program. Slots of collected objects are reused.
+pub struct Heap {
+    objects: Vec<Option<Object>>,
+    /// Slots of `objects` that are empty
+    free: Vec<usize>,
+    /// Bytes taken by the objects, see [`Object::size`]
+    used: usize,
+    /// Bytes the objects may take at most
+    limit: usize,
+}
+
+impl Heap {
+    pub fn new(limit: usize) -> Heap {
+        Heap {
+            objects: vec![],
+            free: vec![],
+            used: 0,
+            limit,
+        }
+    }
+
+    /// Adds the object, without checking the limit
+    pub fn insert(&mut self, object: Object) -> Ref {
+        self.used += object.size();
+        match self.free.pop() {
+            Some(index) => {
+                self.objects[index] = Some(object);
+                Ref(index)
+            }
+            None => {
+                self.objects.push(Some(object));
+                Ref(self.objects.len() - 1)
+            }
+        }
+    }
+
+    /// Removes the objects for which `keep` returns false, returning their number and size
+    pub fn retain(&mut self, keep: impl Fn(Ref) -> bool) -> (usize, usize) {
+        let (mut count, mut size) = (0, 0);
+        for (index, slot) in self.objects.iter_mut().enumerate() {
+            if slot.is_none() || keep(Ref(index)) {
+                continue;
+            }
+            let object = slot.take().expect("slot is not empty");
+            count += 1;
+            size += object.size();
+            self.free.push(index);
+        }
+        self.used -= size;
+        (count, size)
+    }
+
+    pub fn get(&self, reference: Ref) -> &Object {
+        self.objects[reference.0]
+            .as_ref()
+            .expect("reference to a collected object")
+    }
+
+    pub fn get_mut(&mut self, reference: Ref) -> &mut Object {
+        self.objects[reference.0]
+            .as_mut()
+            .expect("reference to a collected object")
+    }
+
+    /// Number of slots, the largest index of a reference plus one
+    pub fn capacity(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+    }
+}
@@ -0,0 +1,170 @@
+//! Call sites of `invokedynamic` (JVMS §6.5). The JVM links a call site by running its
+//! bootstrap method, which returns the method handle the instruction invokes from then
+//! on. The interpreter does not run `java.lang.invoke`: it knows the bootstrap methods
+//! compilers use and does what the call sites they link would do. Supported are the
+//! string concatenation of `StringConcatFactory`, which `javac` compiles `+` on strings
+//! to since Java 9, and the lambdas and method references of `LambdaMetafactory`, see
+//! [`lambda`].
+//!
+//! [`lambda`]: crate::interpreter::lambda
+
+use crate::interpreter::lambda::LAMBDA_METAFACTORY;
+use crate::interpreter::natives;
+use crate::interpreter::value::Value;
+use crate::interpreter::{unsupported, Interpreter};
+use crate::raw::class::Class;
+use crate::raw::*;
+use std::io::Result;
+
+const STRING_CONCAT_FACTORY: &str = "java/lang/invoke/StringConcatFactory";
+
+/// Recipe of `makeConcatWithConstants`: the next argument is inserted in place of this
+/// character, and the next constant in place of [`CONSTANT`]
+const ARGUMENT: char = '\u{1}';
+const CONSTANT: char = '\u{2}';
+
+/// Call site of an `invokedynamic` instruction, with its bootstrap method
+pub(super) struct CallSite<'a> {
+    pub(super) name: &'a str,
+    pub(super) descriptor: MethodDescriptor,
+    /// Class, name and descriptor of the bootstrap method
+    pub(super) bootstrap: (&'a str, &'a str, &'a str),
+    /// Constant pool indexes of the static arguments of the bootstrap method
+    pub(super) arguments: &'a [u16],
+}
+
+impl CallSite<'_> {
+    /// Call site referenced under `index` of the class
+    fn new(class: &Class, index: u16) -> Result<CallSite<'_>> {
+        let pool = &class.constant_pool;
+        let Some(ConstantPoolInfo::InvokeDynamic {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        }) = constant(pool, index)
+        else {
+            return Err(invalid_data(format!("#{index} is not an InvokeDynamic")));
+        };
+        let (name, descriptor) = name_and_type_at(pool, *name_and_type_index)
+            .ok_or_else(|| invalid_data(format!("#{index} has no valid name and type")))?;
+        let descriptor = MethodDescriptor::parse(descriptor)
+            .ok_or_else(|| invalid_data(format!("invalid method descriptor {descriptor:?}")))?;
+        let method = class
+            .bootstrap_methods()
+            .get(usize::from(*bootstrap_method_attr_index))
+            .ok_or_else(|| {
+                let attr_index = bootstrap_method_attr_index;
+                invalid_data(format!("no bootstrap method {attr_index} for #{index}"))
+            })?;
+        let handle = method.bootstrap_method_ref;
+        let bootstrap = match constant(pool, handle) {
+            Some(ConstantPoolInfo::MethodHandle {
+                reference_index, ..
+            }) => member_ref_at(pool, *reference_index),
+            _ => None,
+        };
+        let bootstrap = bootstrap
+            .ok_or_else(|| invalid_data(format!("#{handle} is not a valid MethodHandle")))?;
+        Ok(CallSite {
+            name,
+            descriptor,
+            bootstrap,
+            arguments: &method.bootstrap_arguments,
+        })
+    }
+}
+
+impl Interpreter<'_> {
+    /// `invokedynamic`: calls the call site referenced under `index` of the class with the
+    /// arguments on the operand stack, and pushes the value it returns
+    pub(super) fn invoke_dynamic(&mut self, class: &Class, index: u16) -> Result<()> {
+        let site = CallSite::new(class, index)?;
+        let parameters = &site.descriptor.parameters;
+        let (start, arguments) = self.arguments(parameters.len())?;
+        let result = match site.bootstrap {
+            (STRING_CONCAT_FACTORY, "makeConcatWithConstants", _) => {
+                let (recipe, constants) = site
+                    .arguments
+                    .split_first()
+                    .ok_or_else(|| invalid_data("makeConcatWithConstants without a recipe"))?;
+                let recipe = match constant(&class.constant_pool, *recipe) {
+                    Some(ConstantPoolInfo::String { string_index }) => {
+                        utf8_at(&class.constant_pool, *string_index)
+                    }
+                    _ => None,
+                };
+                let recipe = recipe.ok_or_else(|| invalid_data("recipe is not a String"))?;
+                self.concat(class, recipe, constants, parameters, &arguments)?
+            }
+            (STRING_CONCAT_FACTORY, "makeConcat", _) => {
+                let recipe = ARGUMENT.to_string().repeat(parameters.len());
+                self.concat(class, &recipe, &[], parameters, &arguments)?
+            }
+            (LAMBDA_METAFACTORY, name @ ("metafactory" | "altMetafactory"), _) => {
+                let alternative = name == "altMetafactory";
+                self.lambda(class, index, &site, &arguments, alternative)?
+            }
+            (owner, name, _) => {
+                return Err(unsupported(&format!("bootstrap method {owner}.{name}")));
+            }
+        };
+        self.frame().stack.truncate(start);
+        self.push(result);
+        Ok(())
+    }
+
+    /// New string of the recipe of `StringConcatFactory`, with the arguments of the types
+    /// and the constants under their indexes in the constant pool of the class inserted
+    fn concat(
+        &mut self,
+        class: &Class,
+        recipe: &str,
+        constants: &[u16],
+        types: &[FieldType],
+        arguments: &[Value],
+    ) -> Result<Value> {
+        let mut arguments = types.iter().zip(arguments);
+        let mut constants = constants.iter();
+        let mut text = String::new();
+        for c in recipe.chars() {
+            match c {
+                ARGUMENT => {
+                    let (field_type, value) = arguments
+                        .next()
+                        .ok_or_else(|| invalid_data("recipe has more arguments than the call"))?;
+                    text.push_str(&natives::to_string(self, field_type, *value)?);
+                }
+                CONSTANT => {
+                    let index = constants
+                        .next()
+                        .ok_or_else(|| invalid_data("recipe has more constants than given"))?;
+                    let pool = &class.constant_pool;
+                    let (field_type, value) = match constant(pool, *index) {
+                        Some(ConstantPoolInfo::String { string_index }) => {
+                            text.push_str(utf8_at(pool, *string_index).unwrap_or_default());
+                            continue;
+                        }
+                        Some(ConstantPoolInfo::Integer(value)) => {
+                            (FieldType::Int, Value::Int(*value))
+                        }
+                        Some(ConstantPoolInfo::Long(value)) => {
+                            (FieldType::Long, Value::Long(*value))
+                        }
+                        Some(ConstantPoolInfo::Float(value)) => {
+                            (FieldType::Float, Value::Float(*value))
+                        }
+                        Some(ConstantPoolInfo::Double(value)) => {
+                            (FieldType::Double, Value::Double(*value))
+                        }
+                        _ => {
+                            return Err(unsupported(&format!("concatenation of constant #{index}")))
+                        }
+                    };
+                    text.push_str(&natives::to_string(self, &field_type, value)?);
+                }
+                c => text.push(c),
+            }
+        }
+        let string = self.new_string(&text)?;
+        Ok(Value::Reference(Some(string)))
+    }
+}
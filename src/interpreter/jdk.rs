@@ -0,0 +1,1077 @@
+//! Native methods of `java.base` that the interpreter binds when it runs the classes of a
+//! JDK, see [`boot`](crate::interpreter::boot): those of `Object`, `Class` and its
+//! mirrors, `System`, `Runtime`, `Thread`, arrays, the bits of floating-point numbers,
+//! the system properties, the standard streams of the process, and of the security and
+//! reflection classes that ask for the caller. The program runs on a single thread:
+//! other threads are never started, monitors are always held, and `wait` returns at
+//! once, as a spurious wakeup.
+
+use crate::hierarchy::OBJECT;
+use crate::interpreter::builtins::{SYSTEM, THREAD};
+use crate::interpreter::exception::{throw, throw_without_message};
+use crate::interpreter::heap::Object;
+use crate::interpreter::loader::{reference_component, Slot};
+use crate::interpreter::mirror::{primitive_descriptor, CLASS};
+use crate::interpreter::registry::Registry;
+use crate::interpreter::strings::STRING;
+use crate::interpreter::value::{Ref, Value};
+use crate::interpreter::{null_receiver, Interpreter};
+use crate::raw::class::Class;
+use crate::raw::{
+    class_name_at, invalid_data, name_and_type_at, utf8_at, AccessFlags, AttributeInfo, InnerClass,
+};
+use std::io::{Read, Result, Write};
+use std::path::Path;
+use std::time::Duration;
+
+const RAW_PROPERTIES: &str = "jdk/internal/util/SystemProps$Raw";
+const FILE_DESCRIPTOR: &str = "java/io/FileDescriptor";
+pub const FILE_INPUT_STREAM: &str = "java/io/FileInputStream";
+const FILE_OUTPUT_STREAM: &str = "java/io/FileOutputStream";
+pub const ACCESS_CONTROLLER: &str = "java/security/AccessController";
+const REFERENCE: &str = "java/lang/ref/Reference";
+
+/// Modifiers of the mirrors of primitive types: `public`, `final` and `abstract`
+const PRIMITIVE_MODIFIERS: i32 = 0x411;
+
+/// Binds the native methods of this module
+pub fn register(registry: &mut Registry) {
+    register_object(registry);
+    register_class(registry);
+    register_system(registry);
+    register_thread(registry);
+    register_io(registry);
+
+    registry.register(
+        "java/lang/reflect/Array",
+        "getLength",
+        "(Ljava/lang/Object;)I",
+        |interpreter: &mut Interpreter, array: Option<Ref>| {
+            let array =
+                array.ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+            match interpreter.heap.get(array) {
+                Object::Array { elements, .. } => Ok(elements.len() as i32),
+                _ => Err(throw(
+                    "java/lang/IllegalArgumentException",
+                    "Argument is not an array",
+                )),
+            }
+        },
+    );
+    registry.register(
+        "java/lang/reflect/Array",
+        "newArray",
+        "(Ljava/lang/Class;I)Ljava/lang/Object;",
+        new_array,
+    );
+    registry.register(
+        "java/lang/Float",
+        "floatToRawIntBits",
+        "(F)I",
+        |_: &mut Interpreter, value: f32| Ok(value.to_bits() as i32),
+    );
+    registry.register(
+        "java/lang/Float",
+        "intBitsToFloat",
+        "(I)F",
+        |_: &mut Interpreter, bits: i32| Ok(f32::from_bits(bits as u32)),
+    );
+    registry.register(
+        "java/lang/Double",
+        "doubleToRawLongBits",
+        "(D)J",
+        |_: &mut Interpreter, value: f64| Ok(value.to_bits() as i64),
+    );
+    registry.register(
+        "java/lang/Double",
+        "longBitsToDouble",
+        "(J)D",
+        |_: &mut Interpreter, bits: i64| Ok(f64::from_bits(bits as u64)),
+    );
+    registry.register(
+        STRING,
+        "intern",
+        "()Ljava/lang/String;",
+        |interpreter: &mut Interpreter, string: Option<Ref>| {
+            let string = string.ok_or_else(|| null_receiver(STRING, "intern", "()V"))?;
+            Ok(Some(interpreter.intern_string(string)))
+        },
+    );
+    // Strings of UTF-16 keep the low byte first, see strings
+    registry.register(
+        "java/lang/StringUTF16",
+        "isBigEndian",
+        "()Z",
+        |_: &mut Interpreter| Ok(false),
+    );
+    registry.register(
+        "jdk/internal/reflect/Reflection",
+        "getCallerClass",
+        "()Ljava/lang/Class;",
+        caller_class,
+    );
+    registry.register(
+        "jdk/internal/reflect/Reflection",
+        "getClassAccessFlags",
+        "(Ljava/lang/Class;)I",
+        class_access_flags,
+    );
+    // The collector follows referents like other fields, so references are never cleared
+    // by it, as if memory never ran short
+    for class in [REFERENCE, "java/lang/ref/PhantomReference"] {
+        registry.register(
+            class,
+            "refersTo0",
+            "(Ljava/lang/Object;)Z",
+            |interpreter: &mut Interpreter, reference: Option<Ref>, object: Option<Ref>| {
+                let reference = reference
+                    .ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+                let referent = interpreter.field_value(reference, REFERENCE, "referent");
+                Ok(referent == Some(Value::Reference(object)))
+            },
+        );
+    }
+    registry.register(
+        REFERENCE,
+        "clear0",
+        "()V",
+        |interpreter: &mut Interpreter, reference: Option<Ref>| {
+            if let Some(reference) = reference {
+                interpreter.set_field(reference, REFERENCE, "referent", Value::NULL);
+            }
+            Ok(())
+        },
+    );
+    // No code runs with fewer permissions than any other
+    registry.register(
+        ACCESS_CONTROLLER,
+        "getStackAccessControlContext",
+        "()Ljava/security/AccessControlContext;",
+        |_: &mut Interpreter| Ok(None),
+    );
+    registry.register(
+        ACCESS_CONTROLLER,
+        "getInheritedAccessControlContext",
+        "()Ljava/security/AccessControlContext;",
+        |_: &mut Interpreter| Ok(None),
+    );
+    registry.register(
+        ACCESS_CONTROLLER,
+        "getProtectionDomain",
+        "(Ljava/lang/Class;)Ljava/security/ProtectionDomain;",
+        |_: &mut Interpreter, _: Option<Ref>| Ok(None),
+    );
+    registry.register(
+        ACCESS_CONTROLLER,
+        "ensureMaterializedForStackWalk",
+        "(Ljava/lang/Object;)V",
+        |_: &mut Interpreter, _: Option<Ref>| Ok(()),
+    );
+}
+
+fn register_object(registry: &mut Registry) {
+    registry.register(
+        OBJECT,
+        "getClass",
+        "()Ljava/lang/Class;",
+        |interpreter: &mut Interpreter, object: Option<Ref>| {
+            let object =
+                object.ok_or_else(|| null_receiver(OBJECT, "getClass", "()Ljava/lang/Class;"))?;
+            let class = interpreter.class_name(object).to_string();
+            Ok(Some(interpreter.mirror(&class)?))
+        },
+    );
+    registry.register(OBJECT, "clone", "()Ljava/lang/Object;", clone);
+    for name in ["notify", "notifyAll"] {
+        registry.register(
+            OBJECT,
+            name,
+            "()V",
+            |_: &mut Interpreter, _: Option<Ref>| Ok(()),
+        );
+    }
+    registry.register(
+        OBJECT,
+        "wait",
+        "(J)V",
+        |_: &mut Interpreter, _: Option<Ref>, timeout: i64| match timeout < 0 {
+            true => Err(throw(
+                "java/lang/IllegalArgumentException",
+                "timeout value is negative",
+            )),
+            false => Ok(()),
+        },
+    );
+}
+
+fn register_class(registry: &mut Registry) {
+    registry.register(
+        CLASS,
+        "registerNatives",
+        "()V",
+        |_: &mut Interpreter| Ok(()),
+    );
+    registry.register(
+        CLASS,
+        "getPrimitiveClass",
+        "(Ljava/lang/String;)Ljava/lang/Class;",
+        |interpreter: &mut Interpreter, name: Option<Ref>| {
+            let name = name.and_then(|name| interpreter.string(name));
+            let name =
+                name.ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+            Ok(Some(interpreter.mirror(&name)?))
+        },
+    );
+    registry.register(
+        CLASS,
+        "desiredAssertionStatus0",
+        "(Ljava/lang/Class;)Z",
+        |_: &mut Interpreter, _: Option<Ref>| Ok(false),
+    );
+    registry.register(
+        CLASS,
+        "forName0",
+        "(Ljava/lang/String;ZLjava/lang/ClassLoader;Ljava/lang/Class;)Ljava/lang/Class;",
+        for_name,
+    );
+    registry.register(
+        CLASS,
+        "initClassName",
+        "()Ljava/lang/String;",
+        |interpreter: &mut Interpreter, mirror: Option<Ref>| {
+            let name = mirrored(interpreter, mirror)?;
+            let name = interpreter.new_string(&name.replace('/', "."))?;
+            if let Some(mirror) = mirror {
+                interpreter.set_field(mirror, CLASS, "name", Value::Reference(Some(name)));
+            }
+            Ok(Some(name))
+        },
+    );
+    registry.register(
+        CLASS,
+        "isArray",
+        "()Z",
+        |interpreter: &mut Interpreter, mirror: Option<Ref>| {
+            Ok(mirrored(interpreter, mirror)?.starts_with('['))
+        },
+    );
+    registry.register(
+        CLASS,
+        "isPrimitive",
+        "()Z",
+        |interpreter: &mut Interpreter, mirror: Option<Ref>| {
+            Ok(primitive_descriptor(&mirrored(interpreter, mirror)?).is_some())
+        },
+    );
+    registry.register(
+        CLASS,
+        "isInterface",
+        "()Z",
+        |interpreter: &mut Interpreter, mirror: Option<Ref>| {
+            let name = mirrored(interpreter, mirror)?;
+            Ok(class_flags(interpreter, &name)?.contains(AccessFlags::Interface))
+        },
+    );
+    registry.register(
+        CLASS,
+        "isHidden",
+        "()Z",
+        |interpreter: &mut Interpreter, mirror: Option<Ref>| {
+            let name = mirrored(interpreter, mirror)?;
+            Ok(interpreter.is_hidden(&name))
+        },
+    );
+    registry.register(CLASS, "getModifiers", "()I", modifiers);
+    registry.register(
+        CLASS,
+        "getEnumConstantsShared",
+        "()[Ljava/lang/Object;",
+        enum_constants,
+    );
+    registry.register(
+        CLASS,
+        "getDeclaringClass0",
+        "()Ljava/lang/Class;",
+        |interpreter: &mut Interpreter, mirror: Option<Ref>| {
+            let outer = nesting(interpreter, mirror, |inner| inner.outer_class_info_index)?;
+            outer.map(|outer| interpreter.mirror(&outer)).transpose()
+        },
+    );
+    registry.register(
+        CLASS,
+        "getSimpleBinaryName0",
+        "()Ljava/lang/String;",
+        |interpreter: &mut Interpreter, mirror: Option<Ref>| {
+            let name = nesting(interpreter, mirror, |inner| inner.inner_name_index)?;
+            name.map(|name| interpreter.new_string(&name)).transpose()
+        },
+    );
+    registry.register(
+        CLASS,
+        "getEnclosingMethod0",
+        "()[Ljava/lang/Object;",
+        enclosing_method,
+    );
+    registry.register(
+        CLASS,
+        "getSuperclass",
+        "()Ljava/lang/Class;",
+        |interpreter: &mut Interpreter, mirror: Option<Ref>| {
+            let name = mirrored(interpreter, mirror)?;
+            if name.starts_with('[') {
+                return Ok(Some(interpreter.mirror(OBJECT)?));
+            }
+            if class_flags(interpreter, &name)?.contains(AccessFlags::Interface) {
+                return Ok(None);
+            }
+            let super_name = interpreter
+                .class_path
+                .class(&name)
+                .ok()
+                .and_then(|class| class.super_name().map(str::to_string));
+            super_name
+                .map(|super_name| interpreter.mirror(&super_name))
+                .transpose()
+        },
+    );
+    registry.register(
+        CLASS,
+        "getInterfaces0",
+        "()[Ljava/lang/Class;",
+        |interpreter: &mut Interpreter, mirror: Option<Ref>| {
+            let name = mirrored(interpreter, mirror)?;
+            let interfaces: Vec<String> = match name.starts_with('[') {
+                true => vec![
+                    "java/lang/Cloneable".to_string(),
+                    "java/io/Serializable".to_string(),
+                ],
+                false => match interpreter.class_path.class(&name) {
+                    Ok(class) => class.interface_names().map(str::to_string).collect(),
+                    Err(_) => vec![],
+                },
+            };
+            let array = interpreter.allocate_array("[Ljava/lang/Class;", interfaces.len())?;
+            for (index, interface) in interfaces.iter().enumerate() {
+                let mirror = interpreter.mirror(interface)?;
+                interpreter.elements(Some(array), String::new)?[index] =
+                    Value::Reference(Some(mirror));
+            }
+            Ok(Some(array))
+        },
+    );
+    registry.register(
+        CLASS,
+        "isInstance",
+        "(Ljava/lang/Object;)Z",
+        |interpreter: &mut Interpreter, mirror: Option<Ref>, object: Option<Ref>| {
+            let name = mirrored(interpreter, mirror)?;
+            Ok(object.is_some_and(|object| {
+                primitive_descriptor(&name).is_none()
+                    && interpreter.is_instance_of(interpreter.class_name(object), &name)
+            }))
+        },
+    );
+    registry.register(
+        CLASS,
+        "isAssignableFrom",
+        "(Ljava/lang/Class;)Z",
+        |interpreter: &mut Interpreter, mirror: Option<Ref>, other: Option<Ref>| {
+            let name = mirrored(interpreter, mirror)?;
+            let other = mirrored(interpreter, other)?;
+            Ok(
+                match primitive_descriptor(&name).or(primitive_descriptor(&other)) {
+                    Some(_) => name == other,
+                    None => interpreter.is_instance_of(&other, &name),
+                },
+            )
+        },
+    );
+}
+
+fn register_system(registry: &mut Registry) {
+    registry.register(SYSTEM, "registerNatives", "()V", |_: &mut Interpreter| {
+        Ok(())
+    });
+    for (name, field, descriptor) in [
+        ("setIn0", "in", "(Ljava/io/InputStream;)V"),
+        ("setOut0", "out", "(Ljava/io/PrintStream;)V"),
+        ("setErr0", "err", "(Ljava/io/PrintStream;)V"),
+    ] {
+        registry.register(
+            SYSTEM,
+            name,
+            descriptor,
+            move |interpreter: &mut Interpreter, stream: Option<Ref>| {
+                interpreter.set_static_field(SYSTEM, field, Value::Reference(stream))
+            },
+        );
+    }
+    registry.register(
+        SYSTEM,
+        "mapLibraryName",
+        "(Ljava/lang/String;)Ljava/lang/String;",
+        |interpreter: &mut Interpreter, name: Option<Ref>| {
+            let name = name.and_then(|name| interpreter.string(name));
+            let name =
+                name.ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+            Ok(Some(interpreter.new_string(&format!("lib{name}.so"))?))
+        },
+    );
+
+    const RUNTIME: &str = "java/lang/Runtime";
+    registry.register(
+        RUNTIME,
+        "availableProcessors",
+        "()I",
+        |_: &mut Interpreter, _: Option<Ref>| {
+            let count = std::thread::available_parallelism().map_or(1, |count| count.get());
+            Ok(count as i32)
+        },
+    );
+    registry.register(
+        RUNTIME,
+        "maxMemory",
+        "()J",
+        |interpreter: &mut Interpreter, _: Option<Ref>| Ok(interpreter.heap.limit() as i64),
+    );
+    registry.register(
+        RUNTIME,
+        "totalMemory",
+        "()J",
+        |interpreter: &mut Interpreter, _: Option<Ref>| Ok(interpreter.heap.limit() as i64),
+    );
+    registry.register(
+        RUNTIME,
+        "freeMemory",
+        "()J",
+        |interpreter: &mut Interpreter, _: Option<Ref>| {
+            Ok((interpreter.heap.limit() - interpreter.heap.used()) as i64)
+        },
+    );
+    registry.register(
+        RUNTIME,
+        "gc",
+        "()V",
+        |interpreter: &mut Interpreter, _: Option<Ref>| {
+            interpreter.collect();
+            Ok(())
+        },
+    );
+
+    registry.register(
+        RAW_PROPERTIES,
+        "vmProperties",
+        "()[Ljava/lang/String;",
+        vm_properties,
+    );
+    registry.register(
+        RAW_PROPERTIES,
+        "platformProperties",
+        "()[Ljava/lang/String;",
+        platform_properties,
+    );
+}
+
+fn register_thread(registry: &mut Registry) {
+    registry.register(THREAD, "registerNatives", "()V", |_: &mut Interpreter| {
+        Ok(())
+    });
+    // Threads other than the main thread never run
+    registry.register(
+        THREAD,
+        "start0",
+        "()V",
+        |_: &mut Interpreter, _: Option<Ref>| Ok(()),
+    );
+    registry.register(
+        THREAD,
+        "setPriority0",
+        "(I)V",
+        |_: &mut Interpreter, _: Option<Ref>, _: i32| Ok(()),
+    );
+    for name in ["interrupt0", "suspend0", "resume0"] {
+        registry.register(
+            THREAD,
+            name,
+            "()V",
+            |_: &mut Interpreter, _: Option<Ref>| Ok(()),
+        );
+    }
+    registry.register(
+        THREAD,
+        "setNativeName",
+        "(Ljava/lang/String;)V",
+        |_: &mut Interpreter, _: Option<Ref>, _: Option<Ref>| Ok(()),
+    );
+    registry.register(
+        THREAD,
+        "isAlive",
+        "()Z",
+        |interpreter: &mut Interpreter, thread: Option<Ref>| {
+            Ok(thread.is_some() && thread == interpreter.thread)
+        },
+    );
+    registry.register(
+        THREAD,
+        "holdsLock",
+        "(Ljava/lang/Object;)Z",
+        |_: &mut Interpreter, object: Option<Ref>| match object {
+            Some(_) => Ok(true),
+            None => Err(throw_without_message("java/lang/NullPointerException")),
+        },
+    );
+    registry.register(THREAD, "yield", "()V", |_: &mut Interpreter| Ok(()));
+    registry.register(
+        THREAD,
+        "sleep",
+        "(J)V",
+        |_: &mut Interpreter, millis: i64| {
+            let millis = u64::try_from(millis).map_err(|_| {
+                throw(
+                    "java/lang/IllegalArgumentException",
+                    "timeout value is negative",
+                )
+            })?;
+            std::thread::sleep(Duration::from_millis(millis));
+            Ok(())
+        },
+    );
+}
+
+fn register_io(registry: &mut Registry) {
+    for class in [FILE_DESCRIPTOR, FILE_INPUT_STREAM, FILE_OUTPUT_STREAM] {
+        registry.register(class, "initIDs", "()V", |_: &mut Interpreter| Ok(()));
+    }
+    // The standard streams of the process have no handles of Windows and do not append
+    registry.register(
+        FILE_DESCRIPTOR,
+        "getHandle",
+        "(I)J",
+        |_: &mut Interpreter, _: i32| Ok(-1i64),
+    );
+    registry.register(
+        FILE_DESCRIPTOR,
+        "getAppend",
+        "(I)Z",
+        |_: &mut Interpreter, _: i32| Ok(false),
+    );
+    registry.register(
+        FILE_DESCRIPTOR,
+        "close0",
+        "()V",
+        |interpreter: &mut Interpreter, descriptor: Option<Ref>| {
+            if let Some(descriptor) = descriptor {
+                interpreter.set_field(descriptor, FILE_DESCRIPTOR, "fd", Value::Int(-1));
+            }
+            Ok(())
+        },
+    );
+    registry.register(
+        FILE_OUTPUT_STREAM,
+        "writeBytes",
+        "([BIIZ)V",
+        |interpreter: &mut Interpreter,
+         stream: Option<Ref>,
+         bytes: Option<Ref>,
+         offset: i32,
+         length: i32,
+         _: bool| {
+            let bytes =
+                bytes.ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+            let elements = interpreter.elements(Some(bytes), String::new)?;
+            let range = usize::try_from(offset)
+                .ok()
+                .zip(usize::try_from(length).ok())
+                .map(|(offset, length)| offset..offset + length)
+                .filter(|range| range.end <= elements.len());
+            let range = range
+                .ok_or_else(|| throw_without_message("java/lang/IndexOutOfBoundsException"))?;
+            let bytes: Vec<u8> = elements[range]
+                .iter()
+                .map(|byte| byte.as_int().unwrap_or_default() as u8)
+                .collect();
+            write(interpreter, stream, &bytes)
+        },
+    );
+    registry.register(
+        FILE_OUTPUT_STREAM,
+        "write",
+        "(IZ)V",
+        |interpreter: &mut Interpreter, stream: Option<Ref>, byte: i32, _: bool| {
+            write(interpreter, stream, &[byte as u8])
+        },
+    );
+    registry.register(
+        FILE_INPUT_STREAM,
+        "readBytes",
+        "([BII)I",
+        |interpreter: &mut Interpreter,
+         stream: Option<Ref>,
+         bytes: Option<Ref>,
+         offset: i32,
+         length: i32| {
+            let bytes =
+                bytes.ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+            let size = interpreter.elements(Some(bytes), String::new)?.len();
+            let range = usize::try_from(offset)
+                .ok()
+                .zip(usize::try_from(length).ok())
+                .map(|(offset, length)| offset..offset + length)
+                .filter(|range| range.end <= size);
+            let range = range
+                .ok_or_else(|| throw_without_message("java/lang/IndexOutOfBoundsException"))?;
+            let mut buffer = vec![0; range.len()];
+            let count = read(interpreter, stream, &mut buffer)?;
+            if count == 0 && !buffer.is_empty() {
+                return Ok(-1);
+            }
+            let elements = interpreter.elements(Some(bytes), String::new)?;
+            for (element, byte) in elements[range].iter_mut().zip(&buffer[..count]) {
+                *element = Value::Int((*byte as i8).into());
+            }
+            Ok(count as i32)
+        },
+    );
+    registry.register(
+        FILE_INPUT_STREAM,
+        "read0",
+        "()I",
+        |interpreter: &mut Interpreter, stream: Option<Ref>| {
+            let mut byte = [0];
+            Ok(match read(interpreter, stream, &mut byte)? {
+                0 => -1,
+                _ => byte[0].into(),
+            })
+        },
+    );
+    registry.register(
+        FILE_INPUT_STREAM,
+        "available0",
+        "()I",
+        |_: &mut Interpreter, _: Option<Ref>| Ok(0),
+    );
+}
+
+/// Number of the file descriptor of a `FileInputStream` or `FileOutputStream`
+fn descriptor(interpreter: &mut Interpreter, stream: Option<Ref>, class: &str) -> Result<i32> {
+    let stream = stream.ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+    let descriptor = interpreter.field_value(stream, class, "fd");
+    let descriptor = descriptor.and_then(|descriptor| descriptor.as_reference().ok().flatten());
+    let number = descriptor.and_then(|descriptor| {
+        let number = interpreter.field_value(descriptor, FILE_DESCRIPTOR, "fd")?;
+        number.as_int().ok()
+    });
+    number.ok_or_else(|| throw("java/io/IOException", "Stream Closed"))
+}
+
+/// Writes the bytes to standard output or standard error, the only files the program
+/// can open
+fn write(interpreter: &mut Interpreter, stream: Option<Ref>, bytes: &[u8]) -> Result<()> {
+    match descriptor(interpreter, stream, FILE_OUTPUT_STREAM)? {
+        1 => interpreter.out.write_all(bytes),
+        2 => {
+            interpreter.out.flush()?;
+            std::io::stderr().write_all(bytes)
+        }
+        -1 => Err(throw("java/io/IOException", "Stream Closed")),
+        _ => Err(throw("java/io/IOException", "Bad file descriptor")),
+    }
+}
+
+/// Reads from standard input into the buffer, returning the count of bytes read
+fn read(interpreter: &mut Interpreter, stream: Option<Ref>, buffer: &mut [u8]) -> Result<usize> {
+    match descriptor(interpreter, stream, FILE_INPUT_STREAM)? {
+        0 => {
+            interpreter.out.flush()?;
+            std::io::stdin().read(buffer)
+        }
+        -1 => Err(throw("java/io/IOException", "Stream Closed")),
+        _ => Err(throw("java/io/IOException", "Bad file descriptor")),
+    }
+}
+
+/// Name of what the mirror stands for, `NullPointerException` for `null`
+pub fn mirrored(interpreter: &Interpreter, mirror: Option<Ref>) -> Result<String> {
+    let mirror = mirror.ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+    interpreter
+        .mirror_name(mirror)
+        .map(str::to_string)
+        .ok_or_else(|| invalid_data("object is not a mirror of a class"))
+}
+
+/// Access flags of the class, none for array and primitive types
+fn class_flags(interpreter: &Interpreter, name: &str) -> Result<AccessFlags> {
+    if name.starts_with('[') || primitive_descriptor(name).is_some() {
+        return Ok(AccessFlags::none());
+    }
+    let class = interpreter
+        .class_path
+        .class(name)
+        .map_err(|error| throw("java/lang/NoClassDefFoundError", format!("{name}: {error}")))?;
+    Ok(class.access_info)
+}
+
+/// `Class.getModifiers`. Nested classes have the modifiers of their InnerClasses entry.
+/// Arrays have the access of their element type, and are `final` and `abstract` like
+/// primitive types.
+fn modifiers(interpreter: &mut Interpreter, mirror: Option<Ref>) -> Result<i32> {
+    let name = mirrored(interpreter, mirror)?;
+    modifiers_of(interpreter, &name)
+}
+
+fn modifiers_of(interpreter: &Interpreter, name: &str) -> Result<i32> {
+    if primitive_descriptor(name).is_some() {
+        return Ok(PRIMITIVE_MODIFIERS);
+    }
+    let fixed = i32::from((AccessFlags::Final | AccessFlags::Abstract).bits());
+    if let Some(component) = name.strip_prefix('[') {
+        let access = AccessFlags::Public | AccessFlags::Private | AccessFlags::Protected;
+        let element = match reference_component(component) {
+            Some(class) => modifiers_of(interpreter, class)?,
+            None => i32::from(AccessFlags::Public.bits()),
+        };
+        return Ok(element & i32::from(access.bits()) | fixed);
+    }
+    let class = interpreter
+        .class_path
+        .class(name)
+        .map_err(|error| throw("java/lang/NoClassDefFoundError", format!("{name}: {error}")))?;
+    let flags = match inner_class(&class, name) {
+        Some(inner) => inner.inner_class_access_flags,
+        // ACC_SUPER is not a modifier
+        None => class.access_info & !AccessFlags::Super,
+    };
+    Ok(i32::from(flags.bits()))
+}
+
+/// Entry of the InnerClasses attribute of the class that describes the class itself
+/// `Class.getEnumConstantsShared`, which the JDK implements with reflection: the array
+/// `values()` returns, kept in the mirror, or null if the class is not an enum
+fn enum_constants(interpreter: &mut Interpreter, mirror: Option<Ref>) -> Result<Option<Ref>> {
+    let name = mirrored(interpreter, mirror)?;
+    let mirror = mirror.ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+    if let Some(Value::Reference(Some(constants))) =
+        interpreter.field_value(mirror, CLASS, "enumConstants")
+    {
+        return Ok(Some(constants));
+    }
+    if !class_flags(interpreter, &name)?.contains(AccessFlags::Enum) {
+        return Ok(None);
+    }
+    let id = interpreter.load_class(&name)?;
+    let class = interpreter.classes[id.0].class.clone();
+    let values = class.method("values", &format!("()[L{name};"));
+    let (Some("java/lang/Enum"), Some((index, _))) = (class.super_name(), values) else {
+        return Ok(None);
+    };
+    interpreter.initialize(id)?;
+    let constants = interpreter.invoke(class.clone(), index, vec![])?;
+    let constants = constants.map_or(Ok(None), |constants| constants.as_reference())?;
+    interpreter.set_field(mirror, CLASS, "enumConstants", Value::Reference(constants));
+    Ok(constants)
+}
+
+fn inner_class<'a>(class: &'a Class, name: &str) -> Option<&'a InnerClass> {
+    let pool = &class.constant_pool;
+    let mut entries = class
+        .attributes
+        .iter()
+        .flat_map(|attribute| match &attribute.info {
+            AttributeInfo::InnerClasses(classes) => classes.as_slice(),
+            _ => &[],
+        });
+    entries.find(|entry| class_name_at(pool, entry.inner_class_info_index) == Some(name))
+}
+
+/// Part of the InnerClasses entry of the class of the mirror that describes the class
+/// itself: the name of the class declaring it, or its simple name. `None` for top level
+/// and anonymous classes.
+fn nesting(
+    interpreter: &Interpreter,
+    mirror: Option<Ref>,
+    part: fn(&InnerClass) -> u16,
+) -> Result<Option<String>> {
+    let name = mirrored(interpreter, mirror)?;
+    let Ok(class) = interpreter.class_path.class(&name) else {
+        return Ok(None);
+    };
+    let pool = &class.constant_pool;
+    let Some(index) = inner_class(&class, &name)
+        .map(part)
+        .filter(|index| *index != 0)
+    else {
+        return Ok(None);
+    };
+    Ok(class_name_at(pool, index)
+        .or_else(|| utf8_at(pool, index))
+        .map(str::to_string))
+}
+
+/// `Class.getEnclosingMethod0`: the class, name and descriptor of the method that
+/// declares the local or anonymous class of the mirror, as its EnclosingMethod attribute
+/// gives them. The name and descriptor are `null` outside of a method.
+fn enclosing_method(interpreter: &mut Interpreter, mirror: Option<Ref>) -> Result<Option<Ref>> {
+    let name = mirrored(interpreter, mirror)?;
+    let Ok(class) = interpreter.class_path.class(&name) else {
+        return Ok(None);
+    };
+    let pool = &class.constant_pool;
+    let attribute = class
+        .attributes
+        .iter()
+        .find_map(|attribute| match attribute.info {
+            AttributeInfo::EnclosingMethod {
+                class_index,
+                method_index,
+            } => Some((class_index, method_index)),
+            _ => None,
+        });
+    let Some((class_index, method_index)) = attribute else {
+        return Ok(None);
+    };
+    let enclosing = class_name_at(pool, class_index)
+        .ok_or_else(|| invalid_data(format!("#{class_index} is not a class")))?;
+    let enclosing = interpreter.mirror(enclosing)?;
+    let method = match method_index {
+        0 => None,
+        _ => name_and_type_at(pool, method_index),
+    };
+    let array = interpreter.allocate_array("[Ljava/lang/Object;", 3)?;
+    interpreter.handles.push(array);
+    let result = (|| {
+        let mut values = vec![Value::Reference(Some(enclosing))];
+        for text in [method.map(|method| method.0), method.map(|method| method.1)] {
+            let string = text.map(|text| interpreter.new_string(text)).transpose()?;
+            values.push(Value::Reference(string));
+        }
+        interpreter
+            .elements(Some(array), String::new)?
+            .copy_from_slice(&values);
+        Ok(())
+    })();
+    interpreter.handles.pop();
+    result.map(|()| Some(array))
+}
+
+/// `Class.forName0`: the class with the binary name, like `java.lang.String` or
+/// `[Ljava.lang.String;`, initialized if asked for
+fn for_name(
+    interpreter: &mut Interpreter,
+    name: Option<Ref>,
+    initialize: bool,
+    _: Option<Ref>,
+    _: Option<Ref>,
+) -> Result<Option<Ref>> {
+    let name = name.and_then(|name| interpreter.string(name));
+    let name = name.ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+    let internal = name.replace('.', "/");
+    let not_found = || throw("java/lang/ClassNotFoundException", name.clone());
+    let class = match internal.strip_prefix('[') {
+        Some(component) => {
+            let component = component.trim_start_matches('[');
+            match reference_component(component) {
+                Some(class) => Some(class),
+                None if component.len() == 1 && component != "V" => None,
+                None => return Err(not_found()),
+            }
+        }
+        None => Some(internal.as_str()),
+    };
+    if let Some(class) = class {
+        if internal.contains(';') && !internal.starts_with('[') {
+            return Err(not_found());
+        }
+        let id = interpreter.load_class(class).map_err(|_| not_found())?;
+        if initialize && !internal.starts_with('[') {
+            interpreter.initialize(id)?;
+        }
+    }
+    Ok(Some(interpreter.mirror(&internal)?))
+}
+
+/// `Array.newArray`: an array of the length with elements of the type of the mirror
+fn new_array(
+    interpreter: &mut Interpreter,
+    component: Option<Ref>,
+    length: i32,
+) -> Result<Option<Ref>> {
+    let component = mirrored(interpreter, component)?;
+    let descriptor = match primitive_descriptor(&component) {
+        Some("V") => return Err(throw_without_message("java/lang/IllegalArgumentException")),
+        Some(primitive) => format!("[{primitive}"),
+        None if component.starts_with('[') => format!("[{component}"),
+        None => format!("[L{component};"),
+    };
+    let length = usize::try_from(length)
+        .map_err(|_| throw("java/lang/NegativeArraySizeException", length.to_string()))?;
+    Ok(Some(interpreter.allocate_array(&descriptor, length)?))
+}
+
+/// `Object.clone`: a copy of the array, or of the object if its class is `Cloneable`
+fn clone(interpreter: &mut Interpreter, object: Option<Ref>) -> Result<Option<Ref>> {
+    let object = object.ok_or_else(|| null_receiver(OBJECT, "clone", "()Ljava/lang/Object;"))?;
+    let copy = match interpreter.heap.get(object) {
+        Object::Array { class, elements } => Object::Array {
+            class: class.clone(),
+            elements: elements.clone(),
+        },
+        Object::Instance { class, fields } => {
+            let name = &interpreter.classes[class.0].name;
+            if !interpreter.is_instance_of(name, "java/lang/Cloneable") {
+                let name = name.replace('/', ".");
+                return Err(throw("java/lang/CloneNotSupportedException", name));
+            }
+            Object::Instance {
+                class: *class,
+                fields: fields.clone(),
+            }
+        }
+        Object::String(text) => Object::String(text.clone()),
+        Object::PrintStream(_) | Object::Backtrace(_) => {
+            let name = interpreter.class_name(object).replace('/', ".");
+            return Err(throw("java/lang/CloneNotSupportedException", name));
+        }
+    };
+    Ok(Some(interpreter.allocate(copy)?))
+}
+
+/// `Reflection.getCallerClass`: the class of the method that called the method asking
+pub fn caller_class(interpreter: &mut Interpreter) -> Result<Option<Ref>> {
+    let caller = interpreter.frames.iter().rev().nth(1);
+    let caller = caller.and_then(|frame| frame.class.name().map(str::to_string));
+    caller.map(|caller| interpreter.mirror(&caller)).transpose()
+}
+
+/// `Reflection.getClassAccessFlags`: the access flags of the class file of the mirror
+pub fn class_access_flags(interpreter: &mut Interpreter, mirror: Option<Ref>) -> Result<i32> {
+    let name = mirrored(interpreter, mirror)?;
+    let id = interpreter.load_class(&name)?;
+    Ok(i32::from(
+        interpreter.classes[id.0].class.access_info.bits(),
+    ))
+}
+
+/// `SystemProps$Raw.vmProperties`: the properties the JVM sets, as pairs of keys and values
+fn vm_properties(interpreter: &mut Interpreter) -> Result<Option<Ref>> {
+    let texts: Vec<Option<String>> = jvm_properties(interpreter)
+        .into_iter()
+        .flat_map(|(key, value)| [Some(key.to_string()), Some(value)])
+        .collect();
+    string_array(interpreter, &texts).map(Some)
+}
+
+/// Keys and values of the system properties the JVM sets itself
+pub fn jvm_properties(interpreter: &Interpreter) -> Vec<(&'static str, String)> {
+    let home = interpreter
+        .class_path
+        .locate(SYSTEM)
+        .and_then(|entry| java_home(entry.path()))
+        .unwrap_or_default();
+    let class_path: Vec<String> = interpreter
+        .class_path
+        .entries()
+        .iter()
+        .map(|entry| entry.path().display().to_string())
+        .filter(|path| !path.starts_with(&home) || home.is_empty())
+        .collect();
+    vec![
+        ("java.home", home.clone()),
+        ("java.class.path", class_path.join(":")),
+        ("java.library.path", String::new()),
+        ("sun.boot.library.path", format!("{home}/lib")),
+        (
+            "java.vm.specification.name",
+            "Java Virtual Machine Specification".to_string(),
+        ),
+        (
+            "java.vm.specification.vendor",
+            "Oracle Corporation".to_string(),
+        ),
+        ("java.vm.name", "hava".to_string()),
+        ("java.vm.vendor", "hava".to_string()),
+        ("java.vm.version", env!("CARGO_PKG_VERSION").to_string()),
+        ("java.vm.info", "interpreted mode".to_string()),
+        ("jdk.debug", "release".to_string()),
+    ]
+}
+
+/// Directory of the JDK whose classes are in the file, `lib/modules` or `jre/lib/rt.jar`
+fn java_home(path: &Path) -> Option<String> {
+    let home = path.parent()?.parent()?;
+    Some(home.display().to_string())
+}
+
+/// `SystemProps$Raw.platformProperties`: the properties of the platform, at the indexes
+/// the `_<key>_NDX` constants of the class give them
+fn platform_properties(interpreter: &mut Interpreter) -> Result<Option<Ref>> {
+    let id = interpreter.load_class(RAW_PROPERTIES)?;
+    interpreter.initialize(id)?;
+    let class = interpreter.classes[id.0].class.clone();
+    let constant = |name: &str| -> Option<usize> {
+        let (index, _) = class
+            .fields
+            .iter()
+            .enumerate()
+            .find(|(_, field)| utf8_at(&class.constant_pool, field.name_index) == Some(name))?;
+        let slot = match interpreter.classes[id.0].slots[index] {
+            Slot::Static(slot) => slot,
+            _ => return None,
+        };
+        let value = interpreter.classes[id.0].statics[slot].as_int().ok()?;
+        usize::try_from(value).ok()
+    };
+    let length = constant("FIXED_LENGTH")
+        .ok_or_else(|| invalid_data(format!("{RAW_PROPERTIES} has no FIXED_LENGTH")))?;
+    let mut texts = vec![None; length];
+    for (key, value) in os_properties() {
+        if let Some(index) = constant(&format!("_{}_NDX", key.replace('.', "_"))) {
+            if index < length {
+                texts[index] = Some(value);
+            }
+        }
+    }
+    string_array(interpreter, &texts).map(Some)
+}
+
+/// Keys and values of the system properties of the operating system and the user
+pub fn os_properties() -> Vec<(&'static str, String)> {
+    let home = std::env::var("HOME").unwrap_or_else(|_| "?".to_string());
+    let user = std::env::var("USER").unwrap_or_else(|_| "?".to_string());
+    let directory = std::env::current_dir()
+        .map(|directory| directory.display().to_string())
+        .unwrap_or_else(|_| "?".to_string());
+    vec![
+        ("display.language", "en".to_string()),
+        ("format.language", "en".to_string()),
+        ("file.encoding", "UTF-8".to_string()),
+        ("file.separator", "/".to_string()),
+        ("java.io.tmpdir", "/tmp".to_string()),
+        ("line.separator", "\n".to_string()),
+        ("os.arch", std::env::consts::ARCH.replace("x86_64", "amd64")),
+        ("os.name", "Linux".to_string()),
+        ("os.version", String::new()),
+        ("path.separator", ":".to_string()),
+        ("sun.arch.data.model", "64".to_string()),
+        ("sun.cpu.endian", "little".to_string()),
+        ("sun.io.unicode.encoding", "UnicodeLittle".to_string()),
+        ("sun.jnu.encoding", "UTF-8".to_string()),
+        ("user.dir", directory),
+        ("user.home", home),
+        ("user.name", user),
+    ]
+}
+
+/// New `String[]` with the texts, `null` for `None`
+fn string_array(interpreter: &mut Interpreter, texts: &[Option<String>]) -> Result<Ref> {
+    let array = interpreter.allocate_array("[Ljava/lang/String;", texts.len())?;
+    interpreter.handles.push(array);
+    let result = texts.iter().enumerate().try_for_each(|(index, text)| {
+        if let Some(text) = text {
+            let string = interpreter.new_string(text)?;
+            interpreter.elements(Some(array), String::new)?[index] = Value::Reference(Some(string));
+        }
+        Ok(())
+    });
+    interpreter.handles.pop();
+    result.map(|()| array)
+}
@@ -0,0 +1,791 @@
+//! Native methods of the class libraries before JDK 9, like `jre/lib/rt.jar`, which
+//! [`boot`](crate::interpreter::boot) starts with `System.initializeSystemClass`. The
+//! natives of [`jdk`] and [`misc`] that kept their names are shared, and this module binds
+//! the others: `sun.misc.Unsafe`, `VM` and `Signal`, the system properties that
+//! `System.initProperties` sets, the privileged actions of `AccessController`, and the
+//! reflection `Class.getDeclaredField` and `Class.newInstance` are built on, with which the
+//! atomic classes find their fields and the charsets are created. Constructors called by
+//! reflection always run natively, the accessors the JDK generates after some calls are
+//! not needed. Libraries of native methods are not loaded, and `Unsafe` allocates memory
+//! that only its methods for addresses read and write.
+//!
+//! [`jdk`]: crate::interpreter::jdk
+//! [`misc`]: crate::interpreter::misc
+
+use crate::hierarchy::OBJECT;
+use crate::interpreter::builtins::{SYSTEM, THREAD};
+use crate::interpreter::exception::{rethrow, throw, throw_without_message};
+use crate::interpreter::jdk::{
+    caller_class, class_access_flags, jvm_properties, mirrored, os_properties, ACCESS_CONTROLLER,
+    FILE_INPUT_STREAM,
+};
+use crate::interpreter::mirror::CLASS;
+use crate::interpreter::misc::{
+    allocate_instance, array_index_scale, compare_and_exchange, ensure_class_initialized,
+    find_signal, handle_signal, location, should_be_initialized, throw_exception, ADDRESS_SIZE,
+    ARRAY_BASE_OFFSET, PAGE_SIZE, TYPES,
+};
+use crate::interpreter::registry::Registry;
+use crate::interpreter::value::{Ref, Value};
+use crate::interpreter::Interpreter;
+use crate::raw::class::Class;
+use crate::raw::{
+    class_name_at, invalid_data, utf8_at, AccessFlags, Attribute, AttributeInfo, FieldType,
+    MethodDescriptor,
+};
+use std::io::Result;
+
+const UNSAFE: &str = "sun/misc/Unsafe";
+const SIGNAL: &str = "sun/misc/Signal";
+const REFLECTION: &str = "sun/reflect/Reflection";
+const CONSTRUCTOR_ACCESSOR: &str = "sun/reflect/NativeConstructorAccessorImpl";
+const FIELD: &str = "java/lang/reflect/Field";
+const CONSTRUCTOR: &str = "java/lang/reflect/Constructor";
+
+/// Alignment of the memory `Unsafe.allocateMemory` gives out, whose first address is not 0
+const MEMORY_ALIGNMENT: i64 = 8;
+
+/// Binds the native methods of this module
+pub fn register(registry: &mut Registry) {
+    register_unsafe(registry);
+    register_reflection(registry);
+
+    registry.register(OBJECT, "registerNatives", "()V", |_: &mut Interpreter| {
+        Ok(())
+    });
+    registry.register(
+        SYSTEM,
+        "initProperties",
+        "(Ljava/util/Properties;)Ljava/util/Properties;",
+        init_properties,
+    );
+    // The interpreter provides the native methods itself
+    registry.register(
+        SYSTEM,
+        "loadLibrary",
+        "(Ljava/lang/String;)V",
+        |_: &mut Interpreter, _: Option<Ref>| Ok(()),
+    );
+    registry.register(
+        THREAD,
+        "isInterrupted",
+        "(Z)Z",
+        |_: &mut Interpreter, _: Option<Ref>, _: bool| Ok(false),
+    );
+    registry.register(
+        FILE_INPUT_STREAM,
+        "available",
+        "()I",
+        |_: &mut Interpreter, _: Option<Ref>| Ok(0),
+    );
+    registry.register(
+        "java/util/concurrent/atomic/AtomicLong",
+        "VMSupportsCS8",
+        "()Z",
+        |_: &mut Interpreter| Ok(true),
+    );
+    registry.register("sun/misc/VM", "initialize", "()V", |_: &mut Interpreter| {
+        Ok(())
+    });
+    registry.register(SIGNAL, "findSignal", "(Ljava/lang/String;)I", find_signal);
+    registry.register(SIGNAL, "handle0", "(IJ)J", handle_signal);
+
+    // No code runs with fewer permissions than any other, so the context does not matter
+    registry.register(
+        ACCESS_CONTROLLER,
+        "doPrivileged",
+        "(Ljava/security/PrivilegedAction;)Ljava/lang/Object;",
+        do_privileged,
+    );
+    registry.register(
+        ACCESS_CONTROLLER,
+        "doPrivileged",
+        "(Ljava/security/PrivilegedAction;Ljava/security/AccessControlContext;)Ljava/lang/Object;",
+        |interpreter: &mut Interpreter, action: Option<Ref>, _: Option<Ref>| {
+            do_privileged(interpreter, action)
+        },
+    );
+    registry.register(
+        ACCESS_CONTROLLER,
+        "doPrivileged",
+        "(Ljava/security/PrivilegedExceptionAction;)Ljava/lang/Object;",
+        do_privileged_exception,
+    );
+    registry.register(
+        ACCESS_CONTROLLER,
+        "doPrivileged",
+        "(Ljava/security/PrivilegedExceptionAction;Ljava/security/AccessControlContext;)\
+         Ljava/lang/Object;",
+        |interpreter: &mut Interpreter, action: Option<Ref>, _: Option<Ref>| {
+            do_privileged_exception(interpreter, action)
+        },
+    );
+}
+
+fn register_unsafe(registry: &mut Registry) {
+    registry.register(UNSAFE, "registerNatives", "()V", |_: &mut Interpreter| {
+        Ok(())
+    });
+    registry.register(
+        UNSAFE,
+        "arrayBaseOffset",
+        "(Ljava/lang/Class;)I",
+        |_: &mut Interpreter, _: Option<Ref>, _: Option<Ref>| Ok(ARRAY_BASE_OFFSET),
+    );
+    registry.register(
+        UNSAFE,
+        "arrayIndexScale",
+        "(Ljava/lang/Class;)I",
+        array_index_scale,
+    );
+    registry.register(
+        UNSAFE,
+        "addressSize",
+        "()I",
+        |_: &mut Interpreter, _: Option<Ref>| Ok(ADDRESS_SIZE),
+    );
+    registry.register(
+        UNSAFE,
+        "pageSize",
+        "()I",
+        |_: &mut Interpreter, _: Option<Ref>| Ok(PAGE_SIZE),
+    );
+    registry.register(
+        UNSAFE,
+        "objectFieldOffset",
+        "(Ljava/lang/reflect/Field;)J",
+        object_field_offset,
+    );
+    registry.register(
+        UNSAFE,
+        "shouldBeInitialized",
+        "(Ljava/lang/Class;)Z",
+        should_be_initialized,
+    );
+    registry.register(
+        UNSAFE,
+        "ensureClassInitialized",
+        "(Ljava/lang/Class;)V",
+        ensure_class_initialized,
+    );
+    registry.register(
+        UNSAFE,
+        "allocateInstance",
+        "(Ljava/lang/Class;)Ljava/lang/Object;",
+        allocate_instance,
+    );
+    registry.register(
+        UNSAFE,
+        "throwException",
+        "(Ljava/lang/Throwable;)V",
+        throw_exception,
+    );
+    for name in ["loadFence", "storeFence", "fullFence"] {
+        registry.register(
+            UNSAFE,
+            name,
+            "()V",
+            |_: &mut Interpreter, _: Option<Ref>| Ok(()),
+        );
+    }
+
+    // References are objects in the names of the methods
+    let types = TYPES.map(|(name, descriptor)| match name {
+        "Reference" => ("Object", descriptor),
+        _ => (name, descriptor),
+    });
+    for (name, descriptor) in types {
+        for suffix in ["", "Volatile"] {
+            registry.register(
+                UNSAFE,
+                &format!("get{name}{suffix}"),
+                &format!("(Ljava/lang/Object;J){descriptor}"),
+                |interpreter: &mut Interpreter,
+                 _: Option<Ref>,
+                 object: Option<Ref>,
+                 offset: i64| { Ok(*location(interpreter, object, offset)?) },
+            );
+            registry.register(
+                UNSAFE,
+                &format!("put{name}{suffix}"),
+                &format!("(Ljava/lang/Object;J{descriptor})V"),
+                |interpreter: &mut Interpreter,
+                 _: Option<Ref>,
+                 object: Option<Ref>,
+                 offset: i64,
+                 value: Value| {
+                    *location(interpreter, object, offset)? = value;
+                    Ok(())
+                },
+            );
+        }
+    }
+    for (name, descriptor) in [types[0], types[1], types[6]] {
+        registry.register(
+            UNSAFE,
+            &format!("putOrdered{name}"),
+            &format!("(Ljava/lang/Object;J{descriptor})V"),
+            |interpreter: &mut Interpreter,
+             _: Option<Ref>,
+             object: Option<Ref>,
+             offset: i64,
+             value: Value| {
+                *location(interpreter, object, offset)? = value;
+                Ok(())
+            },
+        );
+        registry.register(
+            UNSAFE,
+            &format!("compareAndSwap{name}"),
+            &format!("(Ljava/lang/Object;J{descriptor}{descriptor})Z"),
+            |interpreter: &mut Interpreter,
+             _: Option<Ref>,
+             object: Option<Ref>,
+             offset: i64,
+             expected: Value,
+             value: Value| {
+                let witness = compare_and_exchange(interpreter, object, offset, expected, value)?;
+                Ok(witness == expected)
+            },
+        );
+    }
+
+    registry.register(
+        UNSAFE,
+        "allocateMemory",
+        "(J)J",
+        |interpreter: &mut Interpreter, _: Option<Ref>, size: i64| {
+            let size = usize::try_from(size)
+                .map_err(|_| throw_without_message("java/lang/IllegalArgumentException"))?;
+            let address =
+                interpreter
+                    .memory
+                    .last_key_value()
+                    .map_or(MEMORY_ALIGNMENT, |(address, block)| {
+                        (address + block.len() as i64 + MEMORY_ALIGNMENT) & !(MEMORY_ALIGNMENT - 1)
+                    });
+            interpreter.memory.insert(address, vec![0; size]);
+            Ok(address)
+        },
+    );
+    registry.register(
+        UNSAFE,
+        "freeMemory",
+        "(J)V",
+        |interpreter: &mut Interpreter, _: Option<Ref>, address: i64| {
+            interpreter.memory.remove(&address);
+            Ok(())
+        },
+    );
+    registry.register(
+        UNSAFE,
+        "getByte",
+        "(J)B",
+        |interpreter: &mut Interpreter, _: Option<Ref>, address: i64| {
+            let bytes = memory(interpreter, address, 1)?;
+            Ok(i32::from(bytes[0] as i8))
+        },
+    );
+    registry.register(
+        UNSAFE,
+        "putByte",
+        "(JB)V",
+        |interpreter: &mut Interpreter, _: Option<Ref>, address: i64, value: i32| {
+            memory(interpreter, address, 1)?[0] = value as u8;
+            Ok(())
+        },
+    );
+    // In the byte order of the platform, whose sun.cpu.endian is little
+    registry.register(
+        UNSAFE,
+        "getLong",
+        "(J)J",
+        |interpreter: &mut Interpreter, _: Option<Ref>, address: i64| {
+            let bytes = memory(interpreter, address, 8)?;
+            Ok(i64::from_le_bytes(bytes.try_into().expect("8 bytes")))
+        },
+    );
+    registry.register(
+        UNSAFE,
+        "putLong",
+        "(JJ)V",
+        |interpreter: &mut Interpreter, _: Option<Ref>, address: i64, value: i64| {
+            memory(interpreter, address, 8)?.copy_from_slice(&value.to_le_bytes());
+            Ok(())
+        },
+    );
+}
+
+fn register_reflection(registry: &mut Registry) {
+    registry.register(
+        REFLECTION,
+        "getCallerClass",
+        "()Ljava/lang/Class;",
+        caller_class,
+    );
+    registry.register(
+        REFLECTION,
+        "getClassAccessFlags",
+        "(Ljava/lang/Class;)I",
+        class_access_flags,
+    );
+    registry.register(
+        CLASS,
+        "getName0",
+        "()Ljava/lang/String;",
+        |interpreter: &mut Interpreter, mirror: Option<Ref>| {
+            let name = mirrored(interpreter, mirror)?;
+            Ok(Some(interpreter.intern(&name.replace('/', "."))?))
+        },
+    );
+    // Classes are all loaded by the bootstrap class loader
+    registry.register(
+        CLASS,
+        "getClassLoader0",
+        "()Ljava/lang/ClassLoader;",
+        |_: &mut Interpreter, _: Option<Ref>| Ok(None),
+    );
+    registry.register(
+        CLASS,
+        "getComponentType",
+        "()Ljava/lang/Class;",
+        |interpreter: &mut Interpreter, mirror: Option<Ref>| {
+            let name = mirrored(interpreter, mirror)?;
+            name.strip_prefix('[')
+                .map(|component| interpreter.descriptor_mirror(component))
+                .transpose()
+        },
+    );
+    registry.register(
+        CLASS,
+        "getDeclaredFields0",
+        "(Z)[Ljava/lang/reflect/Field;",
+        declared_fields,
+    );
+    registry.register(
+        CLASS,
+        "getDeclaredConstructors0",
+        "(Z)[Ljava/lang/reflect/Constructor;",
+        declared_constructors,
+    );
+    registry.register(
+        CONSTRUCTOR_ACCESSOR,
+        "newInstance0",
+        "(Ljava/lang/reflect/Constructor;[Ljava/lang/Object;)Ljava/lang/Object;",
+        new_instance,
+    );
+    // Replaces the method that counts the calls, to never generate an accessor
+    registry.register(
+        CONSTRUCTOR_ACCESSOR,
+        "newInstance",
+        "([Ljava/lang/Object;)Ljava/lang/Object;",
+        |interpreter: &mut Interpreter, accessor: Option<Ref>, arguments: Option<Ref>| {
+            let accessor =
+                accessor.ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+            let constructor = reference_field(interpreter, accessor, CONSTRUCTOR_ACCESSOR, "c");
+            new_instance(interpreter, constructor, arguments)
+        },
+    );
+}
+
+/// `System.initProperties`: sets the properties of the JVM, the operating system and the
+/// release of the class library on the `Properties`, and returns them
+fn init_properties(interpreter: &mut Interpreter, properties: Option<Ref>) -> Result<Option<Ref>> {
+    let properties =
+        properties.ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+    let major = interpreter.class_path.class(OBJECT)?.major;
+    let specification = format!("1.{}", major.saturating_sub(44));
+    let release = [
+        (
+            "java.specification.name",
+            "Java Platform API Specification".to_string(),
+        ),
+        (
+            "java.specification.vendor",
+            "Oracle Corporation".to_string(),
+        ),
+        ("java.specification.version", specification.clone()),
+        ("java.vm.specification.version", specification),
+        ("java.class.version", format!("{major}.0")),
+        ("java.vendor", "Oracle Corporation".to_string()),
+        ("file.encoding.pkg", "sun.io".to_string()),
+        ("user.language", "en".to_string()),
+    ];
+    let all = jvm_properties(interpreter)
+        .into_iter()
+        .chain(os_properties())
+        .chain(release);
+    for (key, value) in all {
+        let key = interpreter.new_string(key)?;
+        interpreter.handles.push(key);
+        let result = interpreter.new_string(&value).and_then(|value| {
+            interpreter.call_method(
+                properties,
+                "setProperty",
+                "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/Object;",
+                vec![Value::Reference(Some(key)), Value::Reference(Some(value))],
+            )
+        });
+        interpreter.handles.pop();
+        result?;
+    }
+    Ok(Some(properties))
+}
+
+/// `AccessController.doPrivileged` of a `PrivilegedAction`: what its `run` method returns
+fn do_privileged(interpreter: &mut Interpreter, action: Option<Ref>) -> Result<Option<Ref>> {
+    let action = action.ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+    let result = interpreter.call_method(action, "run", "()Ljava/lang/Object;", vec![])?;
+    result.map_or(Ok(None), |result| result.as_reference())
+}
+
+/// `AccessController.doPrivileged` of a `PrivilegedExceptionAction`, whose checked
+/// exceptions are thrown in a `PrivilegedActionException`
+fn do_privileged_exception(
+    interpreter: &mut Interpreter,
+    action: Option<Ref>,
+) -> Result<Option<Ref>> {
+    let error = match do_privileged(interpreter, action) {
+        Ok(result) => return Ok(result),
+        Err(error) => error,
+    };
+    let exception = interpreter.exception(error)?;
+    let class = interpreter.class_name(exception);
+    let checked = interpreter.is_instance_of(class, "java/lang/Exception")
+        && !interpreter.is_instance_of(class, "java/lang/RuntimeException");
+    Err(match checked {
+        true => wrap(
+            interpreter,
+            "java/security/PrivilegedActionException",
+            "(Ljava/lang/Exception;)V",
+            exception,
+        ),
+        false => rethrow(exception),
+    })
+}
+
+/// Error throwing a new exception of the class, created by its constructor with the
+/// descriptor from the exception it wraps
+fn wrap(
+    interpreter: &mut Interpreter,
+    class: &str,
+    descriptor: &str,
+    exception: Ref,
+) -> std::io::Error {
+    interpreter.handles.push(exception);
+    let wrapper = interpreter.construct(class, descriptor, &[Value::Reference(Some(exception))]);
+    interpreter.handles.pop();
+    match wrapper {
+        Ok(wrapper) => rethrow(wrapper),
+        Err(error) => error,
+    }
+}
+
+/// `Unsafe.objectFieldOffset`: the slot of the field, see [`misc`](crate::interpreter::misc)
+fn object_field_offset(
+    interpreter: &mut Interpreter,
+    _: Option<Ref>,
+    field: Option<Ref>,
+) -> Result<i64> {
+    let field = field.ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+    let class = reference_field(interpreter, field, FIELD, "clazz");
+    let class = class.and_then(|class| interpreter.mirror_name(class).map(str::to_string));
+    let name = reference_field(interpreter, field, FIELD, "name");
+    let name = name.and_then(|name| interpreter.string(name));
+    let (Some(class), Some(name)) = (class, name) else {
+        return Err(invalid_data("Field without a class or name"));
+    };
+    match interpreter.instance_slot(&class, &name) {
+        Some(slot) => Ok(slot as i64),
+        None => Err(throw("java/lang/IllegalArgumentException", name)),
+    }
+}
+
+/// `Class.getDeclaredFields0`: new `Field` objects for the fields the class of the mirror
+/// declares, or its public ones, with the index of each field as its slot
+fn declared_fields(
+    interpreter: &mut Interpreter,
+    mirror: Option<Ref>,
+    public_only: bool,
+) -> Result<Option<Ref>> {
+    let class = declared_class(interpreter, mirror)?;
+    let fields: Vec<_> = class
+        .iter()
+        .flat_map(|class| class.fields.iter().enumerate())
+        .filter(|(_, field)| !public_only || field.access_flags.contains(AccessFlags::Public))
+        .map(|(index, field)| {
+            let pool = &class.as_ref().expect("class of the field").constant_pool;
+            let name = utf8_at(pool, field.name_index).unwrap_or_default();
+            let descriptor = utf8_at(pool, field.descriptor_index).unwrap_or_default();
+            (
+                index,
+                name.to_string(),
+                descriptor.to_string(),
+                field.access_flags,
+                signature(&field.attributes),
+            )
+        })
+        .collect();
+    let array = interpreter.allocate_array("[Ljava/lang/reflect/Field;", fields.len())?;
+    interpreter.handles.push(array);
+    let result = fields.into_iter().enumerate().try_for_each(
+        |(position, (index, name, descriptor, flags, signature))| {
+            let name = interpreter.intern(&name)?;
+            let field_type = interpreter.descriptor_mirror(&descriptor)?;
+            let signature = signature
+                .map(|text| interpreter.intern(&text))
+                .transpose()?;
+            let arguments = [
+                Value::Reference(mirror),
+                Value::Reference(Some(name)),
+                Value::Reference(Some(field_type)),
+                Value::Int(i32::from(flags.bits())),
+                Value::Int(index as i32),
+                Value::Reference(signature),
+                Value::NULL,
+            ];
+            let field = interpreter.construct(
+                FIELD,
+                "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;IILjava/lang/String;[B)V",
+                &arguments,
+            )?;
+            interpreter.elements(Some(array), String::new)?[position] =
+                Value::Reference(Some(field));
+            Ok(())
+        },
+    );
+    interpreter.handles.pop();
+    result.map(|()| Some(array))
+}
+
+/// `Class.getDeclaredConstructors0`: new `Constructor` objects for the constructors the
+/// class of the mirror declares, or its public ones, with the index of each method as its
+/// slot
+fn declared_constructors(
+    interpreter: &mut Interpreter,
+    mirror: Option<Ref>,
+    public_only: bool,
+) -> Result<Option<Ref>> {
+    let class = declared_class(interpreter, mirror)?;
+    let constructors: Vec<_> = class
+        .iter()
+        .flat_map(|class| class.methods.iter().enumerate())
+        .filter(|(_, method)| !public_only || method.access_flags.contains(AccessFlags::Public))
+        .filter_map(|(index, method)| {
+            let pool = &class.as_ref().expect("class of the method").constant_pool;
+            if utf8_at(pool, method.name_index) != Some("<init>") {
+                return None;
+            }
+            let descriptor = utf8_at(pool, method.descriptor_index)?;
+            let parameters: Vec<String> = MethodDescriptor::parse(descriptor)?
+                .parameters
+                .iter()
+                .map(FieldType::to_string)
+                .collect();
+            let exceptions: Vec<String> = method
+                .attributes
+                .iter()
+                .flat_map(|attribute| match &attribute.info {
+                    AttributeInfo::Exceptions(indexes) => indexes.as_slice(),
+                    _ => &[],
+                })
+                .filter_map(|index| class_name_at(pool, *index))
+                .map(|name| format!("L{name};"))
+                .collect();
+            Some((
+                index,
+                parameters,
+                exceptions,
+                method.access_flags,
+                signature(&method.attributes),
+            ))
+        })
+        .collect();
+    let array =
+        interpreter.allocate_array("[Ljava/lang/reflect/Constructor;", constructors.len())?;
+    interpreter.handles.push(array);
+    let result = constructors.into_iter().enumerate().try_for_each(
+        |(position, (index, parameters, exceptions, flags, signature))| {
+            let parameters = class_array(interpreter, &parameters)?;
+            interpreter.handles.push(parameters);
+            let constructor = class_array(interpreter, &exceptions).and_then(|exceptions| {
+                interpreter.handles.push(exceptions);
+                let signature = signature.map(|text| interpreter.intern(&text)).transpose();
+                let constructor = signature.and_then(|signature| {
+                    let arguments = [
+                        Value::Reference(mirror),
+                        Value::Reference(Some(parameters)),
+                        Value::Reference(Some(exceptions)),
+                        Value::Int(i32::from(flags.bits())),
+                        Value::Int(index as i32),
+                        Value::Reference(signature),
+                        Value::NULL,
+                        Value::NULL,
+                    ];
+                    interpreter.construct(
+                        CONSTRUCTOR,
+                        "(Ljava/lang/Class;[Ljava/lang/Class;[Ljava/lang/Class;IILjava/lang/String;[B[B)V",
+                        &arguments,
+                    )
+                });
+                interpreter.handles.pop();
+                constructor
+            });
+            interpreter.handles.pop();
+            interpreter.elements(Some(array), String::new)?[position] =
+                Value::Reference(Some(constructor?));
+            Ok(())
+        },
+    );
+    interpreter.handles.pop();
+    result.map(|()| Some(array))
+}
+
+/// Class of the mirror, loaded, `None` for array and primitive types, which declare no
+/// members
+fn declared_class(
+    interpreter: &mut Interpreter,
+    mirror: Option<Ref>,
+) -> Result<Option<std::rc::Rc<Class>>> {
+    let name = mirrored(interpreter, mirror)?;
+    if name.starts_with('[') || crate::interpreter::mirror::primitive_descriptor(&name).is_some() {
+        return Ok(None);
+    }
+    let id = interpreter.load_class(&name)?;
+    Ok(Some(interpreter.classes[id.0].class.clone()))
+}
+
+/// Text of the Signature attribute among the attributes
+fn signature(attributes: &[Attribute]) -> Option<String> {
+    attributes
+        .iter()
+        .find_map(|attribute| match &attribute.info {
+            AttributeInfo::Signature(signature) => Some(signature.clone()),
+            _ => None,
+        })
+}
+
+/// New `Class[]` with the mirrors of the types with the descriptors
+fn class_array(interpreter: &mut Interpreter, descriptors: &[String]) -> Result<Ref> {
+    let array = interpreter.allocate_array("[Ljava/lang/Class;", descriptors.len())?;
+    interpreter.handles.push(array);
+    let result = descriptors
+        .iter()
+        .enumerate()
+        .try_for_each(|(index, descriptor)| {
+            let mirror = interpreter.descriptor_mirror(descriptor)?;
+            interpreter.elements(Some(array), String::new)?[index] = Value::Reference(Some(mirror));
+            Ok(())
+        });
+    interpreter.handles.pop();
+    result.map(|()| array)
+}
+
+/// `NativeConstructorAccessorImpl.newInstance0`: a new object of the class declaring the
+/// constructor, initialized by it with the arguments, which are unboxed for parameters of
+/// primitive types. Exceptions of the constructor are thrown in an
+/// `InvocationTargetException`.
+fn new_instance(
+    interpreter: &mut Interpreter,
+    constructor: Option<Ref>,
+    arguments: Option<Ref>,
+) -> Result<Option<Ref>> {
+    let constructor =
+        constructor.ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+    let class = reference_field(interpreter, constructor, CONSTRUCTOR, "clazz");
+    let class = class.and_then(|class| interpreter.mirror_name(class).map(str::to_string));
+    let slot = interpreter.field_value(constructor, CONSTRUCTOR, "slot");
+    let slot = slot.and_then(|slot| usize::try_from(slot.as_int().ok()?).ok());
+    let (Some(class), Some(slot)) = (class, slot) else {
+        return Err(invalid_data("Constructor without a class or slot"));
+    };
+    let id = interpreter.load_class(&class)?;
+    if interpreter.classes[id.0].is_abstract() {
+        return Err(throw(
+            "java/lang/InstantiationException",
+            class.replace('/', "."),
+        ));
+    }
+    interpreter.initialize(id)?;
+    let loaded = interpreter.classes[id.0].class.clone();
+    let descriptor = loaded
+        .methods
+        .get(slot)
+        .and_then(|method| utf8_at(&loaded.constant_pool, method.descriptor_index));
+    let parameters = descriptor
+        .and_then(MethodDescriptor::parse)
+        .ok_or_else(|| invalid_data(format!("{class} has no constructor at slot {slot}")))?
+        .parameters;
+    let values = match arguments {
+        Some(_) => interpreter.elements(arguments, String::new)?.clone(),
+        None => vec![],
+    };
+    if values.len() != parameters.len() {
+        return Err(throw(
+            "java/lang/IllegalArgumentException",
+            "wrong number of arguments",
+        ));
+    }
+    let object = interpreter.new_object(&class)?;
+    let mut all = vec![Value::Reference(Some(object))];
+    for (parameter, value) in parameters.iter().zip(values) {
+        all.push(match parameter.is_reference() {
+            true => value,
+            false => unbox(interpreter, value)?,
+        });
+    }
+    interpreter.handles.push(object);
+    let result = interpreter.invoke(loaded, slot, all);
+    interpreter.handles.pop();
+    match result {
+        Ok(_) => Ok(Some(object)),
+        Err(error) => {
+            let exception = interpreter.exception(error)?;
+            Err(wrap(
+                interpreter,
+                "java/lang/reflect/InvocationTargetException",
+                "(Ljava/lang/Throwable;)V",
+                exception,
+            ))
+        }
+    }
+}
+
+/// Primitive value of a wrapper object like `Integer`, passed for a primitive parameter
+fn unbox(interpreter: &mut Interpreter, value: Value) -> Result<Value> {
+    let wrapper = value
+        .as_reference()?
+        .ok_or_else(|| throw_without_message("java/lang/IllegalArgumentException"))?;
+    let class = interpreter.class_name(wrapper).to_string();
+    interpreter
+        .field_value(wrapper, &class, "value")
+        .ok_or_else(|| {
+            throw(
+                "java/lang/IllegalArgumentException",
+                "argument type mismatch",
+            )
+        })
+}
+
+/// Value of the reference field of the object with the name that the class declares
+fn reference_field(
+    interpreter: &mut Interpreter,
+    object: Ref,
+    class: &str,
+    name: &str,
+) -> Option<Ref> {
+    interpreter
+        .field_value(object, class, name)?
+        .as_reference()
+        .ok()?
+}
+
+/// The `size` bytes from the address of the memory `Unsafe.allocateMemory` gave out
+fn memory<'m>(interpreter: &'m mut Interpreter, address: i64, size: usize) -> Result<&'m mut [u8]> {
+    let block = interpreter.memory.range_mut(..=address).next_back();
+    let bytes = block.and_then(|(start, block)| {
+        let offset = usize::try_from(address - start).ok()?;
+        block.get_mut(offset..offset.checked_add(size)?)
+    });
+    bytes.ok_or_else(|| invalid_data(format!("Unsafe access to unallocated memory at {address}")))
+}
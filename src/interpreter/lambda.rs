@@ -0,0 +1,497 @@
+//! Lambdas and method references, which `javac` compiles to `invokedynamic` call sites
+//! bootstrapped by `LambdaMetafactory`. As the JDK does, the interpreter spins a class for
+//! each call site that implements the functional interface. Its fields hold the captured
+//! arguments, and its implementation of the single abstract method loads them and its
+//! parameters, converts them to the types of the method handle given to the bootstrap
+//! method, invokes it and converts the result back. Static, virtual, interface and special
+//! handles are invoked with the instruction of their kind, and constructors with `new`.
+//!
+//! The class is written in the language of [`assembly`] and defined on the class path, so
+//! it is loaded, instantiated and selected from like any other class. It is hidden, as on
+//! the JVM: `Class.isHidden` is true for it and its frames are left out of stack traces.
+//! A lambda that captures nothing is created once for its call site. `altMetafactory`
+//! adds marker interfaces and bridge methods, and makes serializable lambdas implement
+//! `Serializable`, though without the `writeReplace` method that serializes them.
+//!
+//! [`assembly`]: crate::assembly
+
+use crate::assembly::assemble;
+use crate::hierarchy::OBJECT;
+use crate::interpreter::invokedynamic::CallSite;
+use crate::interpreter::value::{Ref, Value};
+use crate::interpreter::Interpreter;
+use crate::raw::class::Class;
+use crate::raw::*;
+use std::io::Result;
+use std::rc::Rc;
+
+pub const LAMBDA_METAFACTORY: &str = "java/lang/invoke/LambdaMetafactory";
+
+const SERIALIZABLE: &str = "java/io/Serializable";
+
+/// Flags of `altMetafactory`
+const FLAG_SERIALIZABLE: i32 = 1;
+const FLAG_MARKERS: i32 = 2;
+const FLAG_BRIDGES: i32 = 4;
+
+/// Kinds of the method handles a lambda can invoke, see [`REFERENCE_KINDS`]
+const REF_INVOKE_VIRTUAL: u8 = 5;
+const REF_INVOKE_STATIC: u8 = 6;
+const REF_INVOKE_SPECIAL: u8 = 7;
+const REF_NEW_INVOKE_SPECIAL: u8 = 8;
+const REF_INVOKE_INTERFACE: u8 = 9;
+
+/// Primitive types with the classes their values are boxed in
+const WRAPPERS: [(FieldType, &str); 8] = [
+    (FieldType::Boolean, "java/lang/Boolean"),
+    (FieldType::Byte, "java/lang/Byte"),
+    (FieldType::Char, "java/lang/Character"),
+    (FieldType::Short, "java/lang/Short"),
+    (FieldType::Int, "java/lang/Integer"),
+    (FieldType::Long, "java/lang/Long"),
+    (FieldType::Float, "java/lang/Float"),
+    (FieldType::Double, "java/lang/Double"),
+];
+
+/// Class spun for a call site, with its only instance if the lambda captures nothing
+pub struct Lambda {
+    class: String,
+    instance: Option<Ref>,
+}
+
+/// Method a lambda invokes, given by a method handle
+struct Implementation<'a> {
+    kind: u8,
+    owner: &'a str,
+    name: &'a str,
+    descriptor: &'a str,
+    /// Parameter and return types of the descriptor
+    types: MethodDescriptor,
+    /// Whether the handle references an interface method
+    interface: bool,
+}
+
+/// Static arguments of `metafactory` and `altMetafactory`
+struct Metafactory<'a> {
+    /// Descriptor of the method of the interface, with type variables erased
+    erased: &'a str,
+    implementation: Implementation<'a>,
+    /// Descriptor of the method with the types it is used with, like
+    /// `(Ljava/lang/String;)I` for a `ToIntFunction<String>`
+    instantiated: MethodDescriptor,
+    flags: i32,
+    /// Interfaces the lambda implements besides the functional interface
+    markers: Vec<&'a str>,
+    /// Descriptors of further methods of the interface the lambda implements alike
+    bridges: Vec<&'a str>,
+}
+
+impl Metafactory<'_> {
+    /// Static arguments under the indexes of the class, with the flags, marker interfaces
+    /// and bridges that follow for `altMetafactory`
+    fn new<'a>(class: &'a Class, arguments: &[u16], alternative: bool) -> Result<Metafactory<'a>> {
+        let pool = &class.constant_pool;
+        let [erased, handle, instantiated, rest @ ..] = arguments else {
+            return Err(invalid_data(
+                "LambdaMetafactory needs three static arguments",
+            ));
+        };
+        let instantiated = method_type(pool, *instantiated)?;
+        let mut factory = Metafactory {
+            erased: method_type(pool, *erased)?,
+            implementation: implementation(pool, *handle)?,
+            instantiated: parse_descriptor(instantiated)?,
+            flags: 0,
+            markers: vec![],
+            bridges: vec![],
+        };
+        if !alternative {
+            return Ok(factory);
+        }
+
+        let mut rest = rest.iter();
+        let mut next = || {
+            rest.next()
+                .copied()
+                .ok_or_else(|| invalid_data("altMetafactory has too few static arguments"))
+        };
+        factory.flags = integer(pool, next()?)?;
+        if factory.flags & FLAG_MARKERS != 0 {
+            for _ in 0..integer(pool, next()?)? {
+                let index = next()?;
+                let marker = class_name_at(pool, index)
+                    .ok_or_else(|| invalid_data(format!("#{index} is not a class")))?;
+                factory.markers.push(marker);
+            }
+        }
+        if factory.flags & FLAG_BRIDGES != 0 {
+            for _ in 0..integer(pool, next()?)? {
+                factory.bridges.push(method_type(pool, next()?)?);
+            }
+        }
+        Ok(factory)
+    }
+}
+
+impl Interpreter<'_> {
+    /// Lambda of the call site under `index` of the class, which captures the arguments.
+    /// The class of the lambda is spun when the call site runs first.
+    pub(super) fn lambda(
+        &mut self,
+        class: &Class,
+        index: u16,
+        site: &CallSite,
+        arguments: &[Value],
+        alternative: bool,
+    ) -> Result<Value> {
+        let caller = class.name().unwrap_or_default().to_string();
+        let key = (caller, index);
+        let name = match self.lambdas.get(&key) {
+            Some(Lambda {
+                instance: Some(instance),
+                ..
+            }) => return Ok(Value::Reference(Some(*instance))),
+            Some(lambda) => lambda.class.clone(),
+            None => {
+                let factory = Metafactory::new(class, site.arguments, alternative)?;
+                // Named as the JDK names them, counting lambdas of all classes
+                let name = format!("{}$$Lambda${}", key.0, self.lambdas.len() + 1);
+                let spun = assemble(&spin(&name, site, &factory)?)?;
+                self.class_path.define(Rc::new(spun));
+                self.hidden.insert(name.clone());
+                let id = self.load_class(&name)?;
+                self.initialize(id)?;
+                self.lambdas.insert(
+                    key.clone(),
+                    Lambda {
+                        class: name.clone(),
+                        instance: None,
+                    },
+                );
+                name
+            }
+        };
+
+        let lambda = self.new_object(&name)?;
+        for (i, argument) in arguments.iter().enumerate() {
+            self.set_field(lambda, &name, &format!("arg${}", i + 1), *argument);
+        }
+        if arguments.is_empty() {
+            if let Some(linked) = self.lambdas.get_mut(&key) {
+                linked.instance = Some(lambda);
+            }
+        }
+        Ok(Value::Reference(Some(lambda)))
+    }
+
+    /// Whether the class is hidden, like the classes of lambdas
+    pub fn is_hidden(&self, class: &str) -> bool {
+        self.hidden.contains(class)
+    }
+
+    /// Lambdas that capture nothing, which are kept for their call sites
+    pub(super) fn lambda_instances(&self) -> impl Iterator<Item = Ref> + '_ {
+        self.lambdas.values().filter_map(|lambda| lambda.instance)
+    }
+}
+
+/// Source of the class named `name` for the lambda of the call site, in the language of
+/// [`assembly`](crate::assembly)
+fn spin(name: &str, site: &CallSite, factory: &Metafactory) -> Result<String> {
+    let Some(FieldType::Object(interface)) = &site.descriptor.return_type else {
+        return Err(invalid_data(
+            "LambdaMetafactory call site does not return an interface",
+        ));
+    };
+    let mut lines = vec![
+        ".version 52 0".to_string(),
+        format!(".class final super synthetic {name}"),
+        format!(".super {OBJECT}"),
+        format!(".implements {interface}"),
+    ];
+    let serializable = factory.flags & FLAG_SERIALIZABLE != 0;
+    let mut interfaces = vec![interface.as_str()];
+    for marker in factory
+        .markers
+        .iter()
+        .copied()
+        .chain(serializable.then_some(SERIALIZABLE))
+    {
+        if !interfaces.contains(&marker) {
+            interfaces.push(marker);
+            lines.push(format!(".implements {marker}"));
+        }
+    }
+    for (i, captured) in site.descriptor.parameters.iter().enumerate() {
+        lines.push(format!(".field private final arg${} {captured}", i + 1));
+    }
+    let mut descriptors = vec![factory.erased];
+    for bridge in &factory.bridges {
+        if !descriptors.contains(bridge) {
+            descriptors.push(bridge);
+        }
+    }
+    for descriptor in descriptors {
+        method(&mut lines, name, site, factory, descriptor)?;
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Appends the method of the interface with the descriptor, which invokes the
+/// implementation with the captured arguments followed by its own
+fn method(
+    lines: &mut Vec<String>,
+    class: &str,
+    site: &CallSite,
+    factory: &Metafactory,
+    descriptor: &str,
+) -> Result<()> {
+    let method = parse_descriptor(descriptor)?;
+    let implementation = &factory.implementation;
+    let Implementation {
+        kind,
+        owner,
+        name,
+        interface,
+        ..
+    } = *implementation;
+    let mut code = vec![];
+    let mut targets = vec![];
+    match kind {
+        REF_INVOKE_VIRTUAL | REF_INVOKE_SPECIAL | REF_INVOKE_INTERFACE => {
+            targets.push(FieldType::Object(owner.to_string()));
+        }
+        REF_NEW_INVOKE_SPECIAL => code.extend([format!("new {owner}"), "dup".to_string()]),
+        _ => {}
+    }
+    targets.extend(implementation.types.parameters.iter().cloned());
+    let captured = &site.descriptor.parameters;
+    if targets.len() != captured.len() + method.parameters.len() {
+        return Err(invalid_data(format!(
+            "{owner}.{name} does not take the arguments of the lambda {descriptor}"
+        )));
+    }
+
+    let (captured_targets, targets) = targets.split_at(captured.len());
+    for (i, (from, to)) in captured.iter().zip(captured_targets).enumerate() {
+        code.push("aload_0".to_string());
+        code.push(format!("getfield {class}.arg${} {from}", i + 1));
+        convert(&mut code, from, None, to)?;
+    }
+    let mut slot = 1;
+    for (i, (from, to)) in method.parameters.iter().zip(targets).enumerate() {
+        code.push(format!("{}load {slot}", prefix(from)));
+        slot += from.slots();
+        convert(&mut code, from, factory.instantiated.parameters.get(i), to)?;
+    }
+
+    let reference = format!("{owner}.{name}{}", implementation.descriptor);
+    let interface = if interface { "interface " } else { "" };
+    code.push(match kind {
+        REF_INVOKE_VIRTUAL => format!("invokevirtual {reference}"),
+        REF_INVOKE_STATIC => format!("invokestatic {interface}{reference}"),
+        REF_INVOKE_SPECIAL | REF_NEW_INVOKE_SPECIAL => {
+            format!("invokespecial {interface}{reference}")
+        }
+        REF_INVOKE_INTERFACE => {
+            let count = 1 + implementation.types.parameter_slots();
+            format!("invokeinterface {reference} {count}")
+        }
+        _ => unreachable!("kinds are checked by `implementation`"),
+    });
+
+    let result = match kind {
+        REF_NEW_INVOKE_SPECIAL => Some(FieldType::Object(owner.to_string())),
+        _ => implementation.types.return_type.clone(),
+    };
+    match (&result, &method.return_type) {
+        (result, None) => {
+            match result.as_ref().map(FieldType::slots) {
+                Some(1) => code.push("pop".to_string()),
+                Some(_) => code.push("pop2".to_string()),
+                None => {}
+            }
+            code.push("return".to_string());
+        }
+        (Some(from), Some(to)) => {
+            convert(
+                &mut code,
+                from,
+                factory.instantiated.return_type.as_ref(),
+                to,
+            )?;
+            code.push(format!("{}return", prefix(to)));
+        }
+        (None, Some(_)) => {
+            return Err(invalid_data(format!(
+                "{owner}.{name} returns nothing for the lambda {descriptor}"
+            )));
+        }
+    }
+
+    // Operands are loaded one after another and converted in place, which takes at most
+    // two more slots, past the object that is constructed
+    let stack = 4 + captured
+        .iter()
+        .chain(targets)
+        .map(FieldType::slots)
+        .sum::<usize>();
+    lines.push(format!(".method public {}{descriptor}", site.name));
+    lines.push(format!("    .code stack {stack} locals {slot}"));
+    lines.extend(code.into_iter().map(|line| format!("        {line}")));
+    lines.push("    .end code".to_string());
+    lines.push(".end method".to_string());
+    Ok(())
+}
+
+/// Appends the instructions that convert the value of type `from` on the operand stack
+/// to `to`, as `LambdaMetafactory` does: primitives are widened, boxed and unboxed, and
+/// references are cast. The type the value is `instantiated` with tells which wrapper
+/// an erased reference is unboxed from.
+fn convert(
+    code: &mut Vec<String>,
+    from: &FieldType,
+    instantiated: Option<&FieldType>,
+    to: &FieldType,
+) -> Result<()> {
+    match (from.is_reference(), to.is_reference()) {
+        (false, false) => widen(code, from, to),
+        (false, true) => {
+            let boxed = match to {
+                FieldType::Object(name) => unboxed(name),
+                _ => None,
+            };
+            let boxed = boxed.unwrap_or(from);
+            widen(code, from, boxed)?;
+            let wrapper = wrapper(boxed);
+            code.push(format!(
+                "invokestatic {wrapper}.valueOf({boxed})L{wrapper};"
+            ));
+            Ok(())
+        }
+        (true, false) => {
+            let source = match instantiated.filter(|t| t.is_reference()).unwrap_or(from) {
+                FieldType::Object(name) => unboxed(name),
+                _ => None,
+            };
+            let primitive = source.unwrap_or(to);
+            let wrapper = wrapper(primitive);
+            if *from != FieldType::Object(wrapper.to_string()) {
+                code.push(format!("checkcast {wrapper}"));
+            }
+            let value = primitive.java_name();
+            code.push(format!("invokevirtual {wrapper}.{value}Value(){primitive}"));
+            widen(code, primitive, to)
+        }
+        (true, true) => {
+            match to {
+                _ if from == to => {}
+                FieldType::Object(name) if name == OBJECT => {}
+                FieldType::Object(name) => code.push(format!("checkcast {name}")),
+                array => code.push(format!("checkcast {array}")),
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Appends the instruction that widens the primitive value of type `from` to `to`
+fn widen(code: &mut Vec<String>, from: &FieldType, to: &FieldType) -> Result<()> {
+    let int = |field_type: &FieldType| prefix(field_type) == 'i';
+    let instruction = match (from, to) {
+        _ if from == to || int(from) && int(to) => return Ok(()),
+        (from, FieldType::Long) if int(from) => "i2l",
+        (from, FieldType::Float) if int(from) => "i2f",
+        (from, FieldType::Double) if int(from) => "i2d",
+        (FieldType::Long, FieldType::Float) => "l2f",
+        (FieldType::Long, FieldType::Double) => "l2d",
+        (FieldType::Float, FieldType::Double) => "f2d",
+        _ => {
+            return Err(invalid_data(format!(
+                "lambda cannot convert {from} to {to}"
+            )))
+        }
+    };
+    code.push(instruction.to_string());
+    Ok(())
+}
+
+/// First letter of the instructions that load, store and return values of the type
+fn prefix(field_type: &FieldType) -> char {
+    match field_type {
+        FieldType::Long => 'l',
+        FieldType::Float => 'f',
+        FieldType::Double => 'd',
+        FieldType::Object(_) | FieldType::Array(_) => 'a',
+        _ => 'i',
+    }
+}
+
+/// Class the values of the primitive type are boxed in
+fn wrapper(primitive: &FieldType) -> &'static str {
+    WRAPPERS
+        .iter()
+        .find(|(field_type, _)| field_type == primitive)
+        .map_or(OBJECT, |(_, wrapper)| wrapper)
+}
+
+/// Primitive type boxed in the class, if it is a wrapper
+fn unboxed(class: &str) -> Option<&'static FieldType> {
+    WRAPPERS
+        .iter()
+        .find(|(_, wrapper)| *wrapper == class)
+        .map(|(field_type, _)| field_type)
+}
+
+/// Method a lambda invokes through the method handle under `index`
+fn implementation(pool: &[ConstantPoolInfo], index: u16) -> Result<Implementation<'_>> {
+    let Some(ConstantPoolInfo::MethodHandle {
+        reference_kind,
+        reference_index,
+    }) = constant(pool, index)
+    else {
+        return Err(invalid_data(format!("#{index} is not a MethodHandle")));
+    };
+    if !(REF_INVOKE_VIRTUAL..=REF_INVOKE_INTERFACE).contains(reference_kind) {
+        let kind = REFERENCE_KINDS
+            .get(usize::from(*reference_kind))
+            .unwrap_or(&"");
+        return Err(invalid_data(format!(
+            "lambda cannot invoke a handle of kind {kind}"
+        )));
+    }
+    let (owner, name, descriptor) = member_ref_at(pool, *reference_index)
+        .ok_or_else(|| invalid_data(format!("#{index} is not a valid MethodHandle")))?;
+    Ok(Implementation {
+        kind: *reference_kind,
+        owner,
+        name,
+        descriptor,
+        types: parse_descriptor(descriptor)?,
+        interface: matches!(
+            constant(pool, *reference_index),
+            Some(ConstantPoolInfo::InterfaceMethodRef { .. })
+        ),
+    })
+}
+
+/// Descriptor of the MethodType under `index`
+fn method_type(pool: &[ConstantPoolInfo], index: u16) -> Result<&str> {
+    let descriptor = match constant(pool, index) {
+        Some(ConstantPoolInfo::MethodType { descriptor_index }) => utf8_at(pool, *descriptor_index),
+        _ => None,
+    };
+    descriptor.ok_or_else(|| invalid_data(format!("#{index} is not a valid MethodType")))
+}
+
+fn integer(pool: &[ConstantPoolInfo], index: u16) -> Result<i32> {
+    match constant(pool, index) {
+        Some(ConstantPoolInfo::Integer(value)) => Ok(*value),
+        _ => Err(invalid_data(format!("#{index} is not an Integer"))),
+    }
+}
+
+fn parse_descriptor(descriptor: &str) -> Result<MethodDescriptor> {
+    MethodDescriptor::parse(descriptor)
+        .ok_or_else(|| invalid_data(format!("invalid method descriptor {descriptor:?}")))
+}
@@ -0,0 +1,413 @@
+//! Life cycle of the classes of the interpreter (JVMS §5.3 to §5.5). A class is loaded
+//! from the class path when it is first used, together with its superclass and
+//! superinterfaces. Linking checks that these can be extended and prepares the static
+//! fields with their initial values. Before the first instance is created, a static
+//! field is accessed or a static method is called, the class is initialized: its final
+//! static fields get their ConstantValue, its superclass is initialized, and its `<clinit>`
+//! method runs, once. A class whose initialization failed cannot be used anymore.
+
+use crate::hierarchy::OBJECT;
+use crate::interpreter::exception::{rethrow, throw};
+use crate::interpreter::heap::Object;
+use crate::interpreter::natives;
+use crate::interpreter::value::{Ref, Value};
+use crate::interpreter::Interpreter;
+use crate::raw::class::Class;
+use crate::raw::*;
+use std::io::Result;
+use std::rc::Rc;
+
+/// Class loaded by the interpreter, as its index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClassId(pub usize);
+
+/// Where the value of a field is kept
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Slot {
+    /// Index in the fields of the instances
+    Instance(usize),
+    /// Index in the static fields of the class
+    Static(usize),
+}
+
+/// How far the initialization of a class got
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    /// Loaded and linked, but not initialized
+    Linked,
+    /// Being initialized, requests to initialize it again return at once
+    Initializing,
+    Initialized,
+    /// Initialization failed
+    Erroneous,
+}
+
+/// Parsed class with the layout of its instances and the values of its static fields
+pub struct LoadedClass {
+    pub class: Rc<Class>,
+    pub name: String,
+    pub super_class: Option<ClassId>,
+    pub interfaces: Vec<ClassId>,
+    pub state: State,
+    /// Slot of each field of the class, by index of the field. Instances of subclasses keep
+    /// the fields of their superclasses in the same slots, followed by their own fields.
+    pub slots: Vec<Slot>,
+    /// Types of the instance fields, those of the superclasses included, by slot
+    pub instance_fields: Vec<FieldType>,
+    pub statics: Vec<Value>,
+}
+
+impl LoadedClass {
+    /// Instance fields with their initial values, zero, false or null by type
+    pub fn default_fields(&self) -> Vec<Value> {
+        self.instance_fields
+            .iter()
+            .map(Value::default_for)
+            .collect()
+    }
+
+    pub fn is_abstract(&self) -> bool {
+        self.class
+            .access_info
+            .intersects(AccessFlags::Abstract | AccessFlags::Interface)
+    }
+
+    pub fn is_interface(&self) -> bool {
+        self.class.access_info.contains(AccessFlags::Interface)
+    }
+
+    /// Whether the interface has methods with a body, other than static ones, which makes
+    /// classes implementing it initialize it
+    fn declares_default_methods(&self) -> bool {
+        self.class.methods.iter().any(|method| {
+            let flags = method.access_flags;
+            !flags.intersects(AccessFlags::Abstract | AccessFlags::Static)
+        })
+    }
+}
+
+impl Interpreter<'_> {
+    /// Loads and links the class with the internal name, with its superclasses and
+    /// superinterfaces. Classes are loaded once.
+    pub fn load_class(&mut self, name: &str) -> Result<ClassId> {
+        if let Some(id) = self.class_ids.get(name) {
+            return Ok(*id);
+        }
+        if self.loading.iter().any(|loading| loading == name) {
+            return Err(throw("java/lang/ClassCircularityError", name));
+        }
+        let class = self
+            .class_path
+            .class(name)
+            .map_err(|error| throw("java/lang/NoClassDefFoundError", format!("{name}: {error}")))?;
+        self.loading.push(name.to_string());
+        let supertypes = self.load_supertypes(name, &class);
+        self.loading.pop();
+        let (super_class, interfaces) = supertypes?;
+        let mut instance_fields = match super_class {
+            Some(id) => self.classes[id.0].instance_fields.clone(),
+            None => vec![],
+        };
+
+        let pool = &class.constant_pool;
+        let mut slots = vec![];
+        let mut statics = vec![];
+        for field in &class.fields {
+            let descriptor = utf8_at(pool, field.descriptor_index).unwrap_or_default();
+            let field_type = FieldType::parse(descriptor).ok_or_else(|| {
+                invalid_data(format!("{name} has a field of invalid type {descriptor:?}"))
+            })?;
+            match field.access_flags.contains(AccessFlags::Static) {
+                true => {
+                    slots.push(Slot::Static(statics.len()));
+                    statics.push(Value::default_for(&field_type));
+                }
+                false => {
+                    slots.push(Slot::Instance(instance_fields.len()));
+                    instance_fields.push(field_type);
+                }
+            }
+        }
+
+        let id = ClassId(self.classes.len());
+        self.classes.push(LoadedClass {
+            name: name.to_string(),
+            class,
+            super_class,
+            interfaces,
+            state: State::Linked,
+            slots,
+            instance_fields,
+            statics,
+        });
+        self.class_ids.insert(name.to_string(), id);
+        Ok(id)
+    }
+
+    /// Loads the superclass and superinterfaces of the class and checks that the class
+    /// may extend them
+    fn load_supertypes(
+        &mut self,
+        name: &str,
+        class: &Class,
+    ) -> Result<(Option<ClassId>, Vec<ClassId>)> {
+        let super_class = match class.super_name() {
+            // Without a JDK on the class path, java/lang/Object is taken to have no fields
+            Some(OBJECT) if self.class_path.class(OBJECT).is_err() => None,
+            Some(super_name) => {
+                let id = self.load_class(super_name)?;
+                let super_class = &self.classes[id.0];
+                if super_class.is_interface() {
+                    return Err(throw(
+                        "java/lang/IncompatibleClassChangeError",
+                        format!("class {name} has interface {super_name} as super class"),
+                    ));
+                }
+                if super_class.class.access_info.contains(AccessFlags::Final) {
+                    return Err(throw(
+                        "java/lang/VerifyError",
+                        format!("class {name} cannot inherit from final class {super_name}"),
+                    ));
+                }
+                Some(id)
+            }
+            None => None,
+        };
+        let mut interfaces = vec![];
+        for interface in class.interface_names() {
+            let id = self.load_class(interface)?;
+            if !self.classes[id.0].is_interface() {
+                return Err(throw(
+                    "java/lang/IncompatibleClassChangeError",
+                    format!("class {name} cannot implement {interface}, which is not an interface"),
+                ));
+            }
+            interfaces.push(id);
+        }
+        Ok((super_class, interfaces))
+    }
+
+    /// Initializes the class unless it was (JVMS §5.5). Fails with `NoClassDefFoundError`
+    /// for classes whose initialization failed before.
+    pub fn initialize(&mut self, id: ClassId) -> Result<()> {
+        let loaded = &mut self.classes[id.0];
+        match loaded.state {
+            State::Initialized | State::Initializing => return Ok(()),
+            State::Erroneous => {
+                let name = loaded.name.replace('/', ".");
+                let message = format!("Could not initialize class {name}");
+                return Err(throw("java/lang/NoClassDefFoundError", message));
+            }
+            State::Linked => loaded.state = State::Initializing,
+        }
+        let result = self.run_initializers(id);
+        self.classes[id.0].state = match result {
+            Ok(()) => State::Initialized,
+            Err(_) => State::Erroneous,
+        };
+        result
+    }
+
+    fn run_initializers(&mut self, id: ClassId) -> Result<()> {
+        self.set_constant_values(id)?;
+        let loaded = &self.classes[id.0];
+        if !loaded.is_interface() {
+            let super_class = loaded.super_class;
+            let interfaces = loaded.interfaces.clone();
+            if let Some(super_class) = super_class {
+                self.initialize(super_class)?;
+            }
+            for interface in self.default_method_interfaces(&interfaces) {
+                self.initialize(interface)?;
+            }
+        }
+
+        let loaded = &self.classes[id.0];
+        let class = loaded.class.clone();
+        let initializer = class.method("<clinit>", "()V");
+        let Some((index, _)) = initializer.filter(|_| !natives::replaces_initializer(&loaded.name))
+        else {
+            return Ok(());
+        };
+        let Err(error) = self.invoke(class.clone(), index, vec![]) else {
+            return Ok(());
+        };
+        // Errors pass, exceptions are wrapped
+        let exception = self.exception(error)?;
+        if self.is_instance_of(self.class_name(exception), "java/lang/Error") {
+            return Err(rethrow(exception));
+        }
+        self.handles.push(exception);
+        let wrapped = self.new_throwable(
+            "java/lang/ExceptionInInitializerError",
+            None,
+            Some(exception),
+        );
+        self.handles.pop();
+        Err(rethrow(wrapped?))
+    }
+
+    /// Superinterfaces to initialize with a class, in the order of a depth-first walk
+    fn default_method_interfaces(&self, interfaces: &[ClassId]) -> Vec<ClassId> {
+        let mut found = vec![];
+        for interface in interfaces {
+            let loaded = &self.classes[interface.0];
+            if loaded.declares_default_methods() && !found.contains(interface) {
+                found.push(*interface);
+            }
+            for inherited in self.default_method_interfaces(&loaded.interfaces) {
+                if !found.contains(&inherited) {
+                    found.push(inherited);
+                }
+            }
+        }
+        found
+    }
+
+    /// Sets the static fields that have a ConstantValue attribute to that value
+    fn set_constant_values(&mut self, id: ClassId) -> Result<()> {
+        let class = self.classes[id.0].class.clone();
+        let pool = &class.constant_pool;
+        for (field, slot) in class.fields.iter().zip(self.classes[id.0].slots.clone()) {
+            let Slot::Static(slot) = slot else {
+                continue;
+            };
+            let constant_value =
+                field
+                    .attributes
+                    .iter()
+                    .find_map(|attribute| match attribute.info {
+                        AttributeInfo::ConstantValue(index) => Some(index),
+                        _ => None,
+                    });
+            let Some(index) = constant_value else {
+                continue;
+            };
+            let value = match constant(pool, index) {
+                Some(ConstantPoolInfo::Integer(value)) => Value::Int(*value),
+                Some(ConstantPoolInfo::Long(value)) => Value::Long(*value),
+                Some(ConstantPoolInfo::Float(value)) => Value::Float(*value),
+                Some(ConstantPoolInfo::Double(value)) => Value::Double(*value),
+                Some(ConstantPoolInfo::String { string_index }) => {
+                    let units = utf16_at(pool, *string_index).unwrap_or_default();
+                    Value::Reference(Some(self.intern_utf16(&units)?))
+                }
+                _ => {
+                    let name = &self.classes[id.0].name;
+                    return Err(invalid_data(format!(
+                        "{name} has an invalid ConstantValue #{index}"
+                    )));
+                }
+            };
+            self.classes[id.0].statics[slot] = value;
+        }
+        Ok(())
+    }
+
+    /// Slot of the instance field with the name that the class declares, if it can be
+    /// loaded
+    pub fn instance_slot(&mut self, class: &str, name: &str) -> Option<usize> {
+        let id = self.load_class(class).ok()?;
+        self.declared_slot(id, name)
+    }
+
+    /// Slot of the instance field with the name that the loaded class declares
+    pub fn declared_slot(&self, id: ClassId, name: &str) -> Option<usize> {
+        let loaded = &self.classes[id.0];
+        let pool = &loaded.class.constant_pool;
+        let index = loaded
+            .class
+            .fields
+            .iter()
+            .position(|field| utf8_at(pool, field.name_index) == Some(name))?;
+        match loaded.slots[index] {
+            Slot::Instance(slot) => Some(slot),
+            Slot::Static(_) => None,
+        }
+    }
+
+    /// New instance of the class, with the initial values of its fields, created without
+    /// running a constructor like the JVM does for the objects it makes itself
+    pub fn new_object(&mut self, class: &str) -> Result<Ref> {
+        let id = self.load_class(class)?;
+        let fields = self.classes[id.0].default_fields();
+        self.allocate(Object::Instance { class: id, fields })
+    }
+
+    /// Value of the instance field with the name that the class declares, if the object has it
+    pub fn field_value(&mut self, object: Ref, class: &str, name: &str) -> Option<Value> {
+        let slot = self.instance_slot(class, name)?;
+        match self.heap.get(object) {
+            Object::Instance { fields, .. } => fields.get(slot).copied(),
+            _ => None,
+        }
+    }
+
+    /// Sets the instance field with the name that the class declares, if the object has it
+    pub fn set_field(&mut self, object: Ref, class: &str, name: &str, value: Value) {
+        let Some(slot) = self.instance_slot(class, name) else {
+            return;
+        };
+        if let Object::Instance { fields, .. } = self.heap.get_mut(object) {
+            if let Some(field) = fields.get_mut(slot) {
+                *field = value;
+            }
+        }
+    }
+
+    /// Sets the static field with the name that the class declares
+    pub fn set_static_field(&mut self, class: &str, name: &str, value: Value) -> Result<()> {
+        let id = self.load_class(class)?;
+        let loaded = &mut self.classes[id.0];
+        let pool = &loaded.class.constant_pool;
+        let index = loaded
+            .class
+            .fields
+            .iter()
+            .position(|field| utf8_at(pool, field.name_index) == Some(name));
+        match index.map(|index| loaded.slots[index]) {
+            Some(Slot::Static(slot)) => {
+                loaded.statics[slot] = value;
+                Ok(())
+            }
+            _ => Err(invalid_data(format!("{class} has no static field {name}"))),
+        }
+    }
+
+    /// Whether objects of the class or array type `from` are instances of `to`, as
+    /// `checkcast` and `instanceof` decide it (JVMS §6.5)
+    pub fn is_instance_of(&self, from: &str, to: &str) -> bool {
+        if from == to || to == OBJECT {
+            return true;
+        }
+        match (from.strip_prefix('['), to.strip_prefix('[')) {
+            (Some(from), Some(to)) => match (reference_component(from), reference_component(to)) {
+                (Some(from), Some(to)) => self.is_instance_of(from, to),
+                _ => from == to,
+            },
+            (Some(_), None) => matches!(to, "java/lang/Cloneable" | "java/io/Serializable"),
+            (None, Some(_)) => false,
+            (None, None) => {
+                let Ok(class) = self.class_path.class(from) else {
+                    return false;
+                };
+                let supertypes = class
+                    .super_name()
+                    .into_iter()
+                    .chain(class.interface_names());
+                supertypes
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .any(|supertype| self.is_instance_of(supertype, to))
+            }
+        }
+    }
+}
+
+/// Class name of an array component like `Ljava/lang/String;` or `[I`, `None` for primitives
+pub fn reference_component(component: &str) -> Option<&str> {
+    match component.strip_prefix('L') {
+        Some(name) => name.strip_suffix(';'),
+        None => component.starts_with('[').then_some(component),
+    }
+}
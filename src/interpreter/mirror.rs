@@ -0,0 +1,84 @@
+//! Objects of `java/lang/Class`, which the JVM calls mirrors. Every class, array type
+//! and primitive type has one mirror, created when it is first asked for, by `ldc`,
+//! `Object.getClass` or the natives of `Class`. Mirrors are never collected. Like the
+//! JVM, the interpreter sets the `componentType` of the mirrors of array types, and
+//! keeps what each mirror stands for itself.
+
+use crate::interpreter::loader::reference_component;
+use crate::interpreter::value::{Ref, Value};
+use crate::interpreter::Interpreter;
+use crate::raw::{invalid_data, FieldType};
+use std::io::Result;
+
+pub const CLASS: &str = "java/lang/Class";
+
+impl Interpreter<'_> {
+    /// The mirror of the class, array type or primitive type, given by its internal name,
+    /// like `java/lang/String`, its descriptor, like `[I`, or its keyword, like `int`
+    pub fn mirror(&mut self, name: &str) -> Result<Ref> {
+        if let Some(mirror) = self.mirrors.get(name) {
+            return Ok(*mirror);
+        }
+        let component = match name.strip_prefix('[') {
+            Some(component) => Some(self.descriptor_mirror(component)?),
+            None => {
+                if primitive_descriptor(name).is_none() {
+                    self.load_class(name)?;
+                }
+                None
+            }
+        };
+        // Mirrors are kept from collection as soon as they are in the table
+        let mirror = self.new_object(CLASS)?;
+        self.mirrors.insert(name.to_string(), mirror);
+        self.mirror_names.insert(mirror, name.to_string());
+        if let Some(component) = component {
+            self.set_field(
+                mirror,
+                CLASS,
+                "componentType",
+                Value::Reference(Some(component)),
+            );
+        }
+        Ok(mirror)
+    }
+
+    /// The mirror of the type with the descriptor, like `I`, `Ljava/lang/String;` or `[I`
+    pub fn descriptor_mirror(&mut self, descriptor: &str) -> Result<Ref> {
+        match reference_component(descriptor) {
+            Some(class) => self.mirror(class),
+            None => self.mirror(&primitive_name(descriptor)?),
+        }
+    }
+
+    /// What the mirror stands for, as [`mirror`](Interpreter::mirror) takes it
+    pub fn mirror_name(&self, mirror: Ref) -> Option<&str> {
+        self.mirror_names.get(&mirror).map(String::as_str)
+    }
+}
+
+/// Keyword of the primitive type of the descriptor, like `int` for `I`
+fn primitive_name(descriptor: &str) -> Result<String> {
+    match descriptor {
+        "V" => Ok("void".to_string()),
+        _ => FieldType::parse(descriptor)
+            .map(|field_type| field_type.java_name())
+            .ok_or_else(|| invalid_data(format!("invalid type {descriptor:?}"))),
+    }
+}
+
+/// Descriptor of the primitive type with the keyword, like `I` for `int`
+pub fn primitive_descriptor(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "boolean" => "Z",
+        "byte" => "B",
+        "char" => "C",
+        "short" => "S",
+        "int" => "I",
+        "long" => "J",
+        "float" => "F",
+        "double" => "D",
+        "void" => "V",
+        _ => return None,
+    })
+}
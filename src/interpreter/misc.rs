@@ -0,0 +1,368 @@
+//! Native methods of `jdk.internal.misc`, which the classes of `java.base` use below the
+//! public API: `Unsafe`, on which atomic operations and the concurrent collections are
+//! built, `VM`, `CDS` and `Signal`. The interpreter has no memory addresses, so `Unsafe`
+//! works on fields and array elements only. The offset of an instance field is its slot,
+//! and that of an array element is its index scaled by the size of its type, past
+//! [`ARRAY_BASE_OFFSET`], as on the JVM. No archive of classes is shared or dumped.
+
+use crate::interpreter::exception::{rethrow, throw, throw_without_message};
+use crate::interpreter::heap::Object;
+use crate::interpreter::loader::State;
+use crate::interpreter::mirror::primitive_descriptor;
+use crate::interpreter::registry::Registry;
+use crate::interpreter::value::{Ref, Value};
+use crate::interpreter::{unsupported, Interpreter};
+use crate::raw::invalid_data;
+use std::io::Result;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const UNSAFE: &str = "jdk/internal/misc/Unsafe";
+const VM: &str = "jdk/internal/misc/VM";
+const CDS: &str = "jdk/internal/misc/CDS";
+const SIGNAL: &str = "jdk/internal/misc/Signal";
+
+/// Offset of the first element of arrays
+pub const ARRAY_BASE_OFFSET: i32 = 16;
+
+/// Bytes of a memory address
+pub const ADDRESS_SIZE: i32 = 8;
+
+pub const PAGE_SIZE: i32 = 4096;
+
+/// Types `Unsafe` gets and puts values of, by the name of its methods
+pub const TYPES: [(&str, &str); 9] = [
+    ("Int", "I"),
+    ("Reference", "Ljava/lang/Object;"),
+    ("Boolean", "Z"),
+    ("Byte", "B"),
+    ("Short", "S"),
+    ("Char", "C"),
+    ("Long", "J"),
+    ("Float", "F"),
+    ("Double", "D"),
+];
+
+/// Binds the native methods of this module
+pub fn register(registry: &mut Registry) {
+    register_unsafe(registry);
+
+    registry.register(VM, "initialize", "()V", |_: &mut Interpreter| Ok(()));
+    registry.register(
+        VM,
+        "getNanoTimeAdjustment",
+        "(J)J",
+        |_: &mut Interpreter, offset: i64| {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            let seconds = now.as_secs() as i64 - offset;
+            // Out of range of the long of nanoseconds
+            if seconds.unsigned_abs() > (i64::MAX / 1_000_000_000) as u64 {
+                return Ok(-1);
+            }
+            Ok(seconds * 1_000_000_000 + i64::from(now.subsec_nanos()))
+        },
+    );
+    registry.register(
+        VM,
+        "latestUserDefinedLoader0",
+        "()Ljava/lang/ClassLoader;",
+        |_: &mut Interpreter| Ok(None),
+    );
+
+    for name in [
+        "isDumpingClassList0",
+        "isDumpingArchive0",
+        "isSharingEnabled0",
+    ] {
+        registry.register(CDS, name, "()Z", |_: &mut Interpreter| Ok(false));
+    }
+    registry.register(
+        CDS,
+        "getRandomSeedForDumping",
+        "()J",
+        |_: &mut Interpreter| Ok(0i64),
+    );
+    registry.register(
+        CDS,
+        "initializeFromArchive",
+        "(Ljava/lang/Class;)V",
+        |_: &mut Interpreter, _: Option<Ref>| Ok(()),
+    );
+
+    registry.register(SIGNAL, "findSignal0", "(Ljava/lang/String;)I", find_signal);
+    registry.register(SIGNAL, "handle0", "(IJ)J", handle_signal);
+    registry.register(
+        "jdk/internal/misc/ScopedMemoryAccess",
+        "registerNatives",
+        "()V",
+        |_: &mut Interpreter| Ok(()),
+    );
+}
+
+fn register_unsafe(registry: &mut Registry) {
+    registry.register(UNSAFE, "registerNatives", "()V", |_: &mut Interpreter| {
+        Ok(())
+    });
+    registry.register(
+        UNSAFE,
+        "arrayBaseOffset0",
+        "(Ljava/lang/Class;)I",
+        |_: &mut Interpreter, _: Option<Ref>, _: Option<Ref>| Ok(ARRAY_BASE_OFFSET),
+    );
+    registry.register(
+        UNSAFE,
+        "arrayIndexScale0",
+        "(Ljava/lang/Class;)I",
+        array_index_scale,
+    );
+    registry.register(
+        UNSAFE,
+        "addressSize0",
+        "()I",
+        |_: &mut Interpreter, _: Option<Ref>| Ok(ADDRESS_SIZE),
+    );
+    registry.register(
+        UNSAFE,
+        "pageSize",
+        "()I",
+        |_: &mut Interpreter, _: Option<Ref>| Ok(PAGE_SIZE),
+    );
+    registry.register(
+        UNSAFE,
+        "objectFieldOffset1",
+        "(Ljava/lang/Class;Ljava/lang/String;)J",
+        |interpreter: &mut Interpreter, _: Option<Ref>, class: Option<Ref>, name: Option<Ref>| {
+            let class = class.and_then(|class| interpreter.mirror_name(class).map(str::to_string));
+            let name = name.and_then(|name| interpreter.string(name));
+            let (Some(class), Some(name)) = (class, name) else {
+                return Err(throw_without_message("java/lang/NullPointerException"));
+            };
+            match interpreter.instance_slot(&class, &name) {
+                Some(slot) => Ok(slot as i64),
+                None => Err(throw("java/lang/InternalError", name)),
+            }
+        },
+    );
+    registry.register(
+        UNSAFE,
+        "shouldBeInitialized0",
+        "(Ljava/lang/Class;)Z",
+        should_be_initialized,
+    );
+    registry.register(
+        UNSAFE,
+        "ensureClassInitialized0",
+        "(Ljava/lang/Class;)V",
+        ensure_class_initialized,
+    );
+    registry.register(
+        UNSAFE,
+        "allocateInstance",
+        "(Ljava/lang/Class;)Ljava/lang/Object;",
+        allocate_instance,
+    );
+    registry.register(
+        UNSAFE,
+        "throwException",
+        "(Ljava/lang/Throwable;)V",
+        throw_exception,
+    );
+    for name in ["loadFence", "storeFence", "fullFence"] {
+        registry.register(
+            UNSAFE,
+            name,
+            "()V",
+            |_: &mut Interpreter, _: Option<Ref>| Ok(()),
+        );
+    }
+
+    for (name, descriptor) in TYPES {
+        for suffix in ["", "Volatile"] {
+            registry.register(
+                UNSAFE,
+                &format!("get{name}{suffix}"),
+                &format!("(Ljava/lang/Object;J){descriptor}"),
+                |interpreter: &mut Interpreter,
+                 _: Option<Ref>,
+                 object: Option<Ref>,
+                 offset: i64| { Ok(*location(interpreter, object, offset)?) },
+            );
+            registry.register(
+                UNSAFE,
+                &format!("put{name}{suffix}"),
+                &format!("(Ljava/lang/Object;J{descriptor})V"),
+                |interpreter: &mut Interpreter,
+                 _: Option<Ref>,
+                 object: Option<Ref>,
+                 offset: i64,
+                 value: Value| {
+                    *location(interpreter, object, offset)? = value;
+                    Ok(())
+                },
+            );
+        }
+    }
+    for (name, descriptor) in [TYPES[0], TYPES[1], TYPES[6]] {
+        registry.register(
+            UNSAFE,
+            &format!("compareAndSet{name}"),
+            &format!("(Ljava/lang/Object;J{descriptor}{descriptor})Z"),
+            |interpreter: &mut Interpreter,
+             _: Option<Ref>,
+             object: Option<Ref>,
+             offset: i64,
+             expected: Value,
+             value: Value| {
+                let witness = compare_and_exchange(interpreter, object, offset, expected, value)?;
+                Ok(witness == expected)
+            },
+        );
+        registry.register(
+            UNSAFE,
+            &format!("compareAndExchange{name}"),
+            &format!("(Ljava/lang/Object;J{descriptor}{descriptor}){descriptor}"),
+            |interpreter: &mut Interpreter,
+             _: Option<Ref>,
+             object: Option<Ref>,
+             offset: i64,
+             expected: Value,
+             value: Value| {
+                compare_and_exchange(interpreter, object, offset, expected, value)
+            },
+        );
+    }
+}
+
+/// `Signal.findSignal0`: the number of the signal with the name, -1 for unknown ones
+pub fn find_signal(interpreter: &mut Interpreter, name: Option<Ref>) -> Result<i32> {
+    let name = name.and_then(|name| interpreter.string(name));
+    Ok(match name.as_deref() {
+        Some("HUP") => 1,
+        Some("INT") => 2,
+        Some("TERM") => 15,
+        _ => -1,
+    })
+}
+
+/// `Signal.handle0`. Handlers are never called, the previous handler was the default one.
+pub fn handle_signal(_: &mut Interpreter, _: i32, _: i64) -> Result<i64> {
+    Ok(0)
+}
+
+/// `Unsafe.arrayIndexScale0`: the bytes of an element of arrays of the mirror
+pub fn array_index_scale(
+    interpreter: &mut Interpreter,
+    _: Option<Ref>,
+    class: Option<Ref>,
+) -> Result<i32> {
+    let class = class.ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+    let name = interpreter.mirror_name(class).unwrap_or_default();
+    let component = name
+        .strip_prefix('[')
+        .ok_or_else(|| throw_without_message("java/lang/IllegalArgumentException"))?;
+    Ok(scale(component))
+}
+
+/// `Unsafe.shouldBeInitialized0`: whether the class of the mirror was loaded but not
+/// initialized yet
+pub fn should_be_initialized(
+    interpreter: &mut Interpreter,
+    _: Option<Ref>,
+    class: Option<Ref>,
+) -> Result<bool> {
+    let class = class.and_then(|class| interpreter.mirror_name(class));
+    let id = class.and_then(|class| interpreter.class_ids.get(class));
+    Ok(id.is_some_and(|id| interpreter.classes[id.0].state != State::Initialized))
+}
+
+/// `Unsafe.ensureClassInitialized0`: initializes the class of the mirror
+pub fn ensure_class_initialized(
+    interpreter: &mut Interpreter,
+    _: Option<Ref>,
+    class: Option<Ref>,
+) -> Result<()> {
+    let class = class.and_then(|class| interpreter.mirror_name(class).map(str::to_string));
+    let class = class.ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+    if class.starts_with('[') || primitive_descriptor(&class).is_some() {
+        return Ok(());
+    }
+    let id = interpreter.load_class(&class)?;
+    interpreter.initialize(id)
+}
+
+/// `Unsafe.allocateInstance`: a new object of the class of the mirror, whose constructor
+/// does not run
+pub fn allocate_instance(
+    interpreter: &mut Interpreter,
+    _: Option<Ref>,
+    class: Option<Ref>,
+) -> Result<Option<Ref>> {
+    let class = class.and_then(|class| interpreter.mirror_name(class).map(str::to_string));
+    let class = class.ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+    let id = interpreter.load_class(&class)?;
+    if interpreter.classes[id.0].is_abstract() {
+        return Err(throw(
+            "java/lang/InstantiationException",
+            class.replace('/', "."),
+        ));
+    }
+    interpreter.initialize(id)?;
+    Ok(Some(interpreter.new_object(&class)?))
+}
+
+/// `Unsafe.throwException`, which throws checked exceptions without declaring them
+pub fn throw_exception(_: &mut Interpreter, _: Option<Ref>, exception: Option<Ref>) -> Result<()> {
+    let exception =
+        exception.ok_or_else(|| throw_without_message("java/lang/NullPointerException"))?;
+    Err(rethrow(exception))
+}
+
+/// Bytes of an element of arrays of the component type
+fn scale(component: &str) -> i32 {
+    match component {
+        "Z" | "B" => 1,
+        "C" | "S" => 2,
+        "J" | "D" => 8,
+        // References are compressed to 4 bytes, like ints and floats
+        _ => 4,
+    }
+}
+
+/// Field of the object or element of the array at the offset
+pub fn location<'a>(
+    interpreter: &'a mut Interpreter,
+    object: Option<Ref>,
+    offset: i64,
+) -> Result<&'a mut Value> {
+    let object = object.ok_or_else(|| unsupported("Unsafe access to a memory address"))?;
+    let (values, index) = match interpreter.heap.get_mut(object) {
+        Object::Instance { fields, .. } => (fields, usize::try_from(offset).ok()),
+        Object::Array { class, elements } => {
+            let scale = i64::from(scale(&class[1..]));
+            let index = (offset - i64::from(ARRAY_BASE_OFFSET)) / scale;
+            (elements, usize::try_from(index).ok())
+        }
+        _ => return Err(invalid_data("Unsafe access to an object without fields")),
+    };
+    index
+        .and_then(|index| values.get_mut(index))
+        .ok_or_else(|| invalid_data(format!("Unsafe access at invalid offset {offset}")))
+}
+
+/// Sets the field or element at the offset to `value` if it is `expected`, and returns the
+/// value it had. The program runs on a single thread, so nothing else changes it between.
+pub fn compare_and_exchange(
+    interpreter: &mut Interpreter,
+    object: Option<Ref>,
+    offset: i64,
+    expected: Value,
+    value: Value,
+) -> Result<Value> {
+    let location = location(interpreter, object, offset)?;
+    let witness = *location;
+    if witness == expected {
+        *location = value;
+    }
+    Ok(witness)
+}
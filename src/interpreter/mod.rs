@@ -0,0 +1,1215 @@
+//! Interpreter that runs the `main` method of a class. Every method invocation gets a
+//! [`Frame`] with its local variables and operand stack, and the instructions of its code
+//! are executed one after another until it returns. Calls nested deeper than
+//! [`MAX_FRAMES`] throw `StackOverflowError`. Classes are read from a [`ClassPath`] and
+//! method references are resolved and selected as the JVM does, see [`Resolver`].
+//!
+//! With a JDK on the class path, the interpreter runs its classes: it starts them as the
+//! JVM does, see [`boot`], so that `System.out.println` and the collections are those of
+//! `java.base`. Native methods run Rust functions bound to them in a [`registry`], like
+//! those of [`builtins`], [`jdk`], [`jdk8`] and [`misc`], and classes have mirrors, see [`mirror`].
+//! Without a JDK, the methods and fields programs need most are stubs of [`natives`].
+//! Supported are arithmetic and conversions of all primitive types, branches, static and
+//! virtual calls, objects with their fields and arrays, strings, whose constants are
+//! interned, and the string concatenation and [`lambda`]s of [`invokedynamic`]. Classes
+//! are loaded as they are used, see [`loader`], objects no longer used are removed by
+//! [`gc`], and exceptions are thrown and caught as described in [`exception`].
+
+pub mod boot;
+pub mod builtins;
+pub mod exception;
+pub mod gc;
+pub mod heap;
+pub mod invokedynamic;
+pub mod jdk;
+pub mod jdk8;
+pub mod lambda;
+pub mod loader;
+pub mod mirror;
+pub mod misc;
+pub mod natives;
+pub mod registry;
+pub mod strings;
+pub mod value;
+
+#[cfg(test)]
+mod tests;
+
+use crate::classpath::ClassPath;
+use crate::hierarchy::OBJECT;
+use crate::interpreter::exception::{rethrow, throw, throw_without_message, thrown, Thrown};
+use crate::interpreter::gc::{GcStats, DEFAULT_MAX_HEAP, INITIAL_THRESHOLD};
+use crate::interpreter::heap::{Heap, Object, Stream};
+use crate::interpreter::lambda::Lambda;
+use crate::interpreter::loader::{ClassId, LoadedClass, Slot};
+use crate::interpreter::registry::Registry;
+use crate::interpreter::value::{Ref, Value};
+use crate::raw::class::Class;
+use crate::raw::*;
+use crate::resolve::{Member, Resolver};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{Result, Write};
+use std::rc::Rc;
+use std::time::Instant;
+
+/// Frames the methods running at once may have, beyond which a call throws
+/// `StackOverflowError`, about as deep as the JVM lets simple recursions go
+pub const MAX_FRAMES: usize = 16_384;
+
+/// Stack the thread running the interpreter needs for [`MAX_FRAMES`] frames, as each
+/// frame takes a few nested calls of the interpreter, which take around 16 KiB in debug
+/// builds. Only the pages in use are allocated.
+pub const STACK_SIZE: usize = 1 << 30;
+
+/// Invocation of a method
+struct Frame {
+    class: Rc<Class>,
+    /// Index of the method in the class
+    method: usize,
+    locals: Vec<Value>,
+    stack: Vec<Value>,
+    /// Offset of the instruction being executed
+    offset: u32,
+}
+
+/// What happens after an instruction
+enum Flow {
+    Next,
+    /// Continue at the offset
+    Jump(i64),
+    Return(Option<Value>),
+}
+
+/// Kind of invoke instruction
+#[derive(Clone, Copy, PartialEq)]
+enum Invoke {
+    Static,
+    Special,
+    Virtual,
+    Interface,
+}
+
+pub struct Interpreter<'a> {
+    class_path: &'a ClassPath,
+    /// Whether the classes of a JDK run, booted by [`boot`], rather than the stubs of
+    /// [`natives`]
+    jdk: bool,
+    classes: Vec<LoadedClass>,
+    class_ids: HashMap<String, ClassId>,
+    /// Classes whose supertypes are being loaded, to detect circular hierarchies
+    loading: Vec<String>,
+    heap: Heap,
+    /// Size of the heap at which the next collection runs
+    gc_threshold: usize,
+    gc_stats: GcStats,
+    frames: Vec<Frame>,
+    /// `System.out` and `System.err` once they are used
+    print_streams: [Option<Ref>; 2],
+    /// Interned strings by their UTF-16 code units, those of string constants among them
+    strings: HashMap<Vec<u16>, Ref>,
+    /// `Thread` of the main method, once it is used
+    thread: Option<Ref>,
+    /// Objects of `java/lang/Class` by the name of what they stand for, see [`mirror`]
+    mirrors: HashMap<String, Ref>,
+    mirror_names: HashMap<Ref, String>,
+    /// Classes spun for the call sites of lambdas, by the class and constant pool index of
+    /// the call site, see [`lambda`]
+    lambdas: HashMap<(String, u16), Lambda>,
+    /// Names of hidden classes, which are left out of stack traces
+    hidden: HashSet<String>,
+    /// Native methods bound to Rust functions
+    registry: Registry,
+    /// When the interpreter was created, from when `System.nanoTime` counts
+    started: Instant,
+    /// Objects kept from collection while the interpreter works with them
+    handles: Vec<Ref>,
+    /// Blocks of memory `Unsafe.allocateMemory` gave out, by their address
+    memory: BTreeMap<i64, Vec<u8>>,
+    /// Where `System.out` prints to
+    out: &'a mut dyn Write,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(class_path: &'a ClassPath, out: &'a mut dyn Write) -> Interpreter<'a> {
+        let mut registry = Registry::default();
+        builtins::register(&mut registry);
+        jdk::register(&mut registry);
+        jdk8::register(&mut registry);
+        misc::register(&mut registry);
+        Interpreter {
+            class_path,
+            jdk: false,
+            classes: vec![],
+            class_ids: HashMap::new(),
+            loading: vec![],
+            heap: Heap::new(DEFAULT_MAX_HEAP),
+            gc_threshold: INITIAL_THRESHOLD,
+            gc_stats: GcStats::default(),
+            frames: vec![],
+            print_streams: [None, None],
+            strings: HashMap::new(),
+            thread: None,
+            mirrors: HashMap::new(),
+            mirror_names: HashMap::new(),
+            lambdas: HashMap::new(),
+            hidden: HashSet::new(),
+            registry,
+            started: Instant::now(),
+            handles: vec![],
+            memory: BTreeMap::new(),
+            out,
+        }
+    }
+
+    /// Initializes the class and runs its static `main(String[])` or `main()` method.
+    /// Returns false if an exception ended it, after printing its stack trace.
+    pub fn run_main(&mut self, class: Rc<Class>) -> Result<bool> {
+        let name = class.name().unwrap_or("?").to_string();
+        let id = self.load_class(&name)?;
+        let class = self.classes[id.0].class.clone();
+        let main = [("([Ljava/lang/String;)V", 1), ("()V", 0)]
+            .into_iter()
+            .find_map(|(descriptor, parameters)| {
+                let (index, method) = class.method("main", descriptor)?;
+                let is_static = method.access_flags.contains(AccessFlags::Static);
+                is_static.then_some((index, parameters))
+            });
+        let Some((index, parameters)) = main else {
+            return Err(invalid_data(format!("{name} has no static main method")));
+        };
+        let arguments = match parameters {
+            0 => vec![],
+            _ => {
+                let arguments = self.allocate_array("[Ljava/lang/String;", 0)?;
+                vec![Value::Reference(Some(arguments))]
+            }
+        };
+        let result = match self.has_jdk() {
+            true => self.boot(),
+            false => Ok(()),
+        };
+        let result = result
+            .and_then(|()| self.initialize(id))
+            .and_then(|()| self.invoke(class, index, arguments));
+        let uncaught = match result {
+            Ok(_) => None,
+            Err(error) => Some(self.exception(error)?),
+        };
+        self.out.flush()?;
+        if let Some(exception) = uncaught {
+            let trace = self.stack_trace(exception);
+            let message = format!("Exception in thread \"main\" {trace}");
+            std::io::stderr().write_all(message.as_bytes())?;
+        }
+        Ok(uncaught.is_none())
+    }
+
+    /// Invokes the method of the class under `index` with the receiver, for instance
+    /// methods, and the arguments. Returns the value it returns.
+    pub fn invoke(
+        &mut self,
+        class: Rc<Class>,
+        index: usize,
+        arguments: Vec<Value>,
+    ) -> Result<Option<Value>> {
+        let method = &class.methods[index];
+        let pool = &class.constant_pool;
+        let name = utf8_at(pool, method.name_index).unwrap_or("?");
+        let descriptor = utf8_at(pool, method.descriptor_index).unwrap_or("?");
+        let class_name = class.name().unwrap_or("?");
+        let Some(code) = method.code() else {
+            let class_name = class_name.replace('/', ".");
+            // Thrown by the call, like on the JVM
+            if !method.access_flags.contains(AccessFlags::Native) {
+                return Err(throw(
+                    "java/lang/AbstractMethodError",
+                    format!("{class_name}.{name}{descriptor}"),
+                ));
+            }
+            // The native method has a frame of its own in the stack trace
+            let error = throw(
+                "java/lang/UnsatisfiedLinkError",
+                format!("'{}'", signature(&class_name, name, descriptor)),
+            );
+            self.frames.push(Frame {
+                class: class.clone(),
+                method: index,
+                locals: vec![],
+                stack: vec![],
+                offset: 0,
+            });
+            let exception = self.exception(error);
+            self.frames.pop();
+            return Err(exception.map_or_else(|error| error, rethrow));
+        };
+
+        let mut locals = Vec::with_capacity(code.max_locals.into());
+        for argument in arguments {
+            locals.push(argument);
+            if argument.size() == 2 {
+                locals.push(Value::Int(0));
+            }
+        }
+        locals.resize(code.max_locals.into(), Value::Int(0));
+        if self.frames.len() >= MAX_FRAMES {
+            return Err(throw_without_message("java/lang/StackOverflowError"));
+        }
+        self.frames.push(Frame {
+            class: class.clone(),
+            method: index,
+            locals,
+            stack: Vec::with_capacity(code.max_stack.into()),
+            offset: 0,
+        });
+        let result = self.execute(&class, code);
+        let frame = self.frames.pop().expect("frame of the method");
+        result.map_err(|error| {
+            // Exception objects have their stack trace, other failures get the frames added
+            if let Some(Thrown::Object(_)) = thrown(&error) {
+                return error;
+            }
+            let offset = frame.offset;
+            let at = format!("at {class_name}.{name}{descriptor} offset {offset}");
+            std::io::Error::new(error.kind(), format!("{error}\n    {at}"))
+        })
+    }
+
+    /// Runs the code of the frame on top until it returns, or throws an exception it has
+    /// no handler for
+    fn execute(&mut self, class: &Class, code: MethodCode) -> Result<Option<Value>> {
+        let mut index = 0;
+        loop {
+            let Some((offset, instruction)) = code.code.get(index) else {
+                return Err(invalid_data("execution falls off the end of the code"));
+            };
+            self.frame().offset = *offset;
+            let flow = match self.step(class, *offset, instruction) {
+                Ok(flow) => flow,
+                Err(error) => {
+                    let exception = self.exception(error)?;
+                    let handler = self.handler(class, code.exception_table, *offset, exception)?;
+                    let Some(handler) = handler else {
+                        return Err(rethrow(exception));
+                    };
+                    let stack = &mut self.frame().stack;
+                    stack.clear();
+                    stack.push(Value::Reference(Some(exception)));
+                    Flow::Jump(handler.into())
+                }
+            };
+            match flow {
+                Flow::Next => index += 1,
+                Flow::Jump(target) => {
+                    let found = code
+                        .code
+                        .binary_search_by_key(&target, |(offset, _)| i64::from(*offset));
+                    index = found.map_err(|_| {
+                        invalid_data(format!("branch target {target} is not an instruction"))
+                    })?;
+                }
+                Flow::Return(value) => return Ok(value),
+            }
+        }
+    }
+
+    fn step(&mut self, class: &Class, offset: u32, instruction: &Instruction) -> Result<Flow> {
+        use Instruction as I;
+        let branch = |condition: bool, relative: i16| match condition {
+            true => Flow::Jump(i64::from(offset) + i64::from(relative)),
+            false => Flow::Next,
+        };
+        match instruction {
+            I::Nop => {}
+            I::AConstNull => self.push(Value::NULL),
+            I::IConstM1 => self.push(Value::Int(-1)),
+            I::IConst0 => self.push(Value::Int(0)),
+            I::IConst1 => self.push(Value::Int(1)),
+            I::IConst2 => self.push(Value::Int(2)),
+            I::IConst3 => self.push(Value::Int(3)),
+            I::IConst4 => self.push(Value::Int(4)),
+            I::IConst5 => self.push(Value::Int(5)),
+            I::FConst0 => self.push(Value::Float(0.0)),
+            I::FConst1 => self.push(Value::Float(1.0)),
+            I::FConst2 => self.push(Value::Float(2.0)),
+            I::LConst0 => self.push(Value::Long(0)),
+            I::LConst1 => self.push(Value::Long(1)),
+            I::DConst0 => self.push(Value::Double(0.0)),
+            I::DConst1 => self.push(Value::Double(1.0)),
+            I::BiPush(value) => self.push(Value::Int((*value).into())),
+            I::SiPush(value) => self.push(Value::Int((*value).into())),
+            I::Ldc(index) => self.load_constant(class, (*index).into())?,
+            I::LdcW(index) => self.load_constant(class, *index)?,
+            I::Ldc2W(index) => self.load_constant(class, *index)?,
+
+            I::ILoad(index)
+            | I::LLoad(index)
+            | I::FLoad(index)
+            | I::DLoad(index)
+            | I::ALoad(index) => self.load((*index).into())?,
+            I::ILoad0 | I::LLoad0 | I::FLoad0 | I::DLoad0 | I::ALoad0 => self.load(0)?,
+            I::ILoad1 | I::LLoad1 | I::FLoad1 | I::DLoad1 | I::ALoad1 => self.load(1)?,
+            I::ILoad2 | I::LLoad2 | I::FLoad2 | I::DLoad2 | I::ALoad2 => self.load(2)?,
+            I::ILoad3 | I::LLoad3 | I::FLoad3 | I::DLoad3 | I::ALoad3 => self.load(3)?,
+            I::IStore(index)
+            | I::LStore(index)
+            | I::FStore(index)
+            | I::DStore(index)
+            | I::AStore(index) => self.store((*index).into())?,
+            I::IStore0 | I::LStore0 | I::FStore0 | I::DStore0 | I::AStore0 => self.store(0)?,
+            I::IStore1 | I::LStore1 | I::FStore1 | I::DStore1 | I::AStore1 => self.store(1)?,
+            I::IStore2 | I::LStore2 | I::FStore2 | I::DStore2 | I::AStore2 => self.store(2)?,
+            I::IStore3 | I::LStore3 | I::FStore3 | I::DStore3 | I::AStore3 => self.store(3)?,
+            I::IInc(index, increment) => self.increment((*index).into(), (*increment).into())?,
+            I::Wide(wide) => match wide {
+                Wide::ILoad(index)
+                | Wide::LLoad(index)
+                | Wide::FLoad(index)
+                | Wide::DLoad(index)
+                | Wide::ALoad(index) => self.load((*index).into())?,
+                Wide::IStore(index)
+                | Wide::LStore(index)
+                | Wide::FStore(index)
+                | Wide::DStore(index)
+                | Wide::AStore(index) => self.store((*index).into())?,
+                Wide::IInc(index, increment) => {
+                    self.increment((*index).into(), (*increment).into())?
+                }
+                other => return Err(unsupported(other.mnemonic())),
+            },
+
+            I::Pop => {
+                self.pop_slots(1)?;
+            }
+            I::Pop2 => {
+                self.pop_slots(2)?;
+            }
+            I::Dup | I::DupX1 | I::DupX2 | I::Dup2 | I::Dup2X1 | I::Dup2X2 => {
+                // Slots of the values duplicated and of those they are inserted below
+                let (top, below) = match instruction {
+                    I::Dup => (1, 0),
+                    I::DupX1 => (1, 1),
+                    I::DupX2 => (1, 2),
+                    I::Dup2 => (2, 0),
+                    I::Dup2X1 => (2, 1),
+                    _ => (2, 2),
+                };
+                let duplicated = self.pop_slots(top)?;
+                let below = self.pop_slots(below)?;
+                let stack = &mut self.frame().stack;
+                stack.extend(&duplicated);
+                stack.extend(below);
+                stack.extend(duplicated);
+            }
+            I::Swap => {
+                let a = self.pop()?;
+                let b = self.pop()?;
+                self.push(a);
+                self.push(b);
+            }
+
+            I::IAdd => self.int_operation(|a, b| Ok(a.wrapping_add(b)))?,
+            I::ISub => self.int_operation(|a, b| Ok(a.wrapping_sub(b)))?,
+            I::IMul => self.int_operation(|a, b| Ok(a.wrapping_mul(b)))?,
+            I::IDiv => self.int_operation(|a, b| match b {
+                0 => Err(throw("java/lang/ArithmeticException", "/ by zero")),
+                _ => Ok(a.wrapping_div(b)),
+            })?,
+            I::IRem => self.int_operation(|a, b| match b {
+                0 => Err(throw("java/lang/ArithmeticException", "/ by zero")),
+                _ => Ok(a.wrapping_rem(b)),
+            })?,
+            I::IAnd => self.int_operation(|a, b| Ok(a & b))?,
+            I::IOr => self.int_operation(|a, b| Ok(a | b))?,
+            I::IXor => self.int_operation(|a, b| Ok(a ^ b))?,
+            I::IShl => self.int_operation(|a, b| Ok(a.wrapping_shl(b as u32)))?,
+            I::IShr => self.int_operation(|a, b| Ok(a.wrapping_shr(b as u32)))?,
+            I::IUShr => self.int_operation(|a, b| Ok((a as u32).wrapping_shr(b as u32) as i32))?,
+            I::INeg => {
+                let value = self.pop()?.as_int()?;
+                self.push(Value::Int(value.wrapping_neg()));
+            }
+            I::LAdd => self.long_operation(|a, b| Ok(a.wrapping_add(b)))?,
+            I::LSub => self.long_operation(|a, b| Ok(a.wrapping_sub(b)))?,
+            I::LMul => self.long_operation(|a, b| Ok(a.wrapping_mul(b)))?,
+            I::LDiv => self.long_operation(|a, b| match b {
+                0 => Err(throw("java/lang/ArithmeticException", "/ by zero")),
+                _ => Ok(a.wrapping_div(b)),
+            })?,
+            I::LRem => self.long_operation(|a, b| match b {
+                0 => Err(throw("java/lang/ArithmeticException", "/ by zero")),
+                _ => Ok(a.wrapping_rem(b)),
+            })?,
+            I::LAnd => self.long_operation(|a, b| Ok(a & b))?,
+            I::LOr => self.long_operation(|a, b| Ok(a | b))?,
+            I::LXor => self.long_operation(|a, b| Ok(a ^ b))?,
+            // The shift distance is an int
+            I::LShl | I::LShr | I::LUShr => {
+                let distance = self.pop()?.as_int()? as u32;
+                let value = self.pop()?.as_long()?;
+                let result = match instruction {
+                    I::LShl => value.wrapping_shl(distance),
+                    I::LShr => value.wrapping_shr(distance),
+                    _ => (value as u64).wrapping_shr(distance) as i64,
+                };
+                self.push(Value::Long(result));
+            }
+            I::LNeg => {
+                let value = self.pop()?.as_long()?;
+                self.push(Value::Long(value.wrapping_neg()));
+            }
+            I::FAdd => self.float_operation(|a, b| a + b)?,
+            I::FSub => self.float_operation(|a, b| a - b)?,
+            I::FMul => self.float_operation(|a, b| a * b)?,
+            I::FDiv => self.float_operation(|a, b| a / b)?,
+            I::FRem => self.float_operation(|a, b| a % b)?,
+            I::FNeg => {
+                let value = self.pop()?.as_float()?;
+                self.push(Value::Float(-value));
+            }
+            I::DAdd => self.double_operation(|a, b| a + b)?,
+            I::DSub => self.double_operation(|a, b| a - b)?,
+            I::DMul => self.double_operation(|a, b| a * b)?,
+            I::DDiv => self.double_operation(|a, b| a / b)?,
+            I::DRem => self.double_operation(|a, b| a % b)?,
+            I::DNeg => {
+                let value = self.pop()?.as_double()?;
+                self.push(Value::Double(-value));
+            }
+            I::LCmp => {
+                let b = self.pop()?.as_long()?;
+                let a = self.pop()?.as_long()?;
+                self.push(Value::Int(a.cmp(&b) as i32));
+            }
+            // Comparisons with NaN give 1 for the g variants and -1 for the l variants
+            I::FCmpL | I::FCmpG => {
+                let b = self.pop()?.as_float()?;
+                let a = self.pop()?.as_float()?;
+                let result = match a.partial_cmp(&b) {
+                    Some(ordering) => ordering as i32,
+                    None if matches!(instruction, I::FCmpG) => 1,
+                    None => -1,
+                };
+                self.push(Value::Int(result));
+            }
+            I::DCmpL | I::DCmpG => {
+                let b = self.pop()?.as_double()?;
+                let a = self.pop()?.as_double()?;
+                let result = match a.partial_cmp(&b) {
+                    Some(ordering) => ordering as i32,
+                    None if matches!(instruction, I::DCmpG) => 1,
+                    None => -1,
+                };
+                self.push(Value::Int(result));
+            }
+
+            // Conversions from floating point to integers round towards zero and saturate,
+            // with NaN giving 0, which is what `as` does
+            I::I2L => self.convert(|value| Ok(Value::Long(value.as_int()?.into())))?,
+            I::I2F => self.convert(|value| Ok(Value::Float(value.as_int()? as f32)))?,
+            I::I2D => self.convert(|value| Ok(Value::Double(value.as_int()?.into())))?,
+            I::L2I => self.convert(|value| Ok(Value::Int(value.as_long()? as i32)))?,
+            I::L2F => self.convert(|value| Ok(Value::Float(value.as_long()? as f32)))?,
+            I::L2D => self.convert(|value| Ok(Value::Double(value.as_long()? as f64)))?,
+            I::F2I => self.convert(|value| Ok(Value::Int(value.as_float()? as i32)))?,
+            I::F2L => self.convert(|value| Ok(Value::Long(value.as_float()? as i64)))?,
+            I::F2D => self.convert(|value| Ok(Value::Double(value.as_float()?.into())))?,
+            I::D2I => self.convert(|value| Ok(Value::Int(value.as_double()? as i32)))?,
+            I::D2L => self.convert(|value| Ok(Value::Long(value.as_double()? as i64)))?,
+            I::D2F => self.convert(|value| Ok(Value::Float(value.as_double()? as f32)))?,
+            I::I2B => self.convert(|value| Ok(Value::Int(value.as_int()? as i8 as i32)))?,
+            I::I2C => self.convert(|value| Ok(Value::Int(value.as_int()? as u16 as i32)))?,
+            I::I2S => self.convert(|value| Ok(Value::Int(value.as_int()? as i16 as i32)))?,
+
+            I::IfEq(relative) => return Ok(branch(self.pop()?.as_int()? == 0, *relative)),
+            I::IfNe(relative) => return Ok(branch(self.pop()?.as_int()? != 0, *relative)),
+            I::IfLt(relative) => return Ok(branch(self.pop()?.as_int()? < 0, *relative)),
+            I::IfGe(relative) => return Ok(branch(self.pop()?.as_int()? >= 0, *relative)),
+            I::IfGt(relative) => return Ok(branch(self.pop()?.as_int()? > 0, *relative)),
+            I::IfLe(relative) => return Ok(branch(self.pop()?.as_int()? <= 0, *relative)),
+            I::IfICmpEq(relative)
+            | I::IfICmpNe(relative)
+            | I::IfICmpLt(relative)
+            | I::IfICmpGe(relative)
+            | I::IfICmpGt(relative)
+            | I::IfICmpLe(relative) => {
+                let b = self.pop()?.as_int()?;
+                let a = self.pop()?.as_int()?;
+                let condition = match instruction {
+                    I::IfICmpEq(_) => a == b,
+                    I::IfICmpNe(_) => a != b,
+                    I::IfICmpLt(_) => a < b,
+                    I::IfICmpGe(_) => a >= b,
+                    I::IfICmpGt(_) => a > b,
+                    _ => a <= b,
+                };
+                return Ok(branch(condition, *relative));
+            }
+            I::IfACmpEq(relative) | I::IfACmpNe(relative) => {
+                let b = self.pop()?.as_reference()?;
+                let a = self.pop()?.as_reference()?;
+                let equal = matches!(instruction, I::IfACmpEq(_));
+                return Ok(branch((a == b) == equal, *relative));
+            }
+            I::IfNull(relative) => {
+                return Ok(branch(self.pop()?.as_reference()?.is_none(), *relative))
+            }
+            I::IfNonNull(relative) => {
+                return Ok(branch(self.pop()?.as_reference()?.is_some(), *relative))
+            }
+            I::Goto(relative) => return Ok(branch(true, *relative)),
+            I::GotoW(relative) => return Ok(Flow::Jump(i64::from(offset) + i64::from(*relative))),
+            I::TableSwitch {
+                default,
+                low,
+                high,
+                offsets,
+            } => {
+                let key = self.pop()?.as_int()?;
+                let relative = match key >= *low && key <= *high {
+                    true => offsets[(i64::from(key) - i64::from(*low)) as usize],
+                    false => *default,
+                };
+                return Ok(Flow::Jump(i64::from(offset) + i64::from(relative)));
+            }
+            I::LookupSwitch { default, pairs } => {
+                let key = self.pop()?.as_int()?;
+                let relative = pairs
+                    .iter()
+                    .find(|(matched, _)| *matched == key)
+                    .map_or(*default, |(_, relative)| *relative);
+                return Ok(Flow::Jump(i64::from(offset) + i64::from(relative)));
+            }
+
+            I::IReturn | I::LReturn | I::FReturn | I::DReturn | I::AReturn => {
+                return Ok(Flow::Return(Some(self.pop()?)))
+            }
+            I::Return => return Ok(Flow::Return(None)),
+
+            I::GetStatic(index) => {
+                let pool = &class.constant_pool;
+                let (owner, name, _) = member_ref_at(pool, *index)
+                    .ok_or_else(|| invalid_data(format!("#{index} is not a field reference")))?;
+                let value = match natives::static_field(self, owner, name) {
+                    Some(value) => value?,
+                    None => {
+                        let (id, slot) = self.static_field(class, *index)?;
+                        self.classes[id.0].statics[slot]
+                    }
+                };
+                self.push(value);
+            }
+            I::PutStatic(index) => {
+                let (id, slot) = self.static_field(class, *index)?;
+                self.classes[id.0].statics[slot] = self.pop()?;
+            }
+            I::GetField(index) => {
+                let (id, slot) = self.instance_field(class, *index)?;
+                let object = self.pop()?.as_reference()?;
+                let object = object.ok_or_else(|| null_field(class, *index, "read"))?;
+                let value = self.fields_of(object, id)?[slot];
+                self.push(value);
+            }
+            I::PutField(index) => {
+                let (id, slot) = self.instance_field(class, *index)?;
+                let value = self.pop()?;
+                let object = self.pop()?.as_reference()?;
+                let object = object.ok_or_else(|| null_field(class, *index, "assign"))?;
+                self.fields_of(object, id)?[slot] = value;
+            }
+            I::CheckCast(index) | I::InstanceOf(index) => {
+                let name = class_name_at(&class.constant_pool, *index)
+                    .ok_or_else(|| invalid_data(format!("#{index} is not a class")))?;
+                let object = self.pop()?.as_reference()?;
+                let is_instance =
+                    object.is_some_and(|object| self.is_instance_of(self.class_name(object), name));
+                match instruction {
+                    I::InstanceOf(_) => self.push(Value::Int(is_instance.into())),
+                    // Null passes any cast
+                    _ if is_instance || object.is_none() => self.push(Value::Reference(object)),
+                    _ => {
+                        let object = self.class_name(object.expect("not null"));
+                        let message = format!(
+                            "class {} cannot be cast to class {}",
+                            object.replace('/', "."),
+                            name.replace('/', ".")
+                        );
+                        return Err(throw("java/lang/ClassCastException", message));
+                    }
+                }
+            }
+            I::New(index) => {
+                let name = class_name_at(&class.constant_pool, *index)
+                    .ok_or_else(|| invalid_data(format!("#{index} is not a class")))?;
+                let id = self.load_class(name)?;
+                let loaded = &self.classes[id.0];
+                if loaded.is_abstract() {
+                    let name = name.replace('/', ".");
+                    return Err(throw("java/lang/InstantiationError", name));
+                }
+                let fields = loaded.default_fields();
+                self.initialize(id)?;
+                let object = self.allocate(Object::Instance { class: id, fields })?;
+                self.push(Value::Reference(Some(object)));
+            }
+            I::InvokeStatic(index) => self.invoke_instruction(class, *index, Invoke::Static)?,
+            I::InvokeSpecial(index) => self.invoke_instruction(class, *index, Invoke::Special)?,
+            I::InvokeVirtual(index) => self.invoke_instruction(class, *index, Invoke::Virtual)?,
+            I::InvokeInterface(index, _) => {
+                self.invoke_instruction(class, *index, Invoke::Interface)?
+            }
+            I::InvokeDynamic(index) => self.invoke_dynamic(class, *index)?,
+
+            I::NewArray(atype) => {
+                let descriptor = match atype {
+                    4 => "[Z",
+                    5 => "[C",
+                    6 => "[F",
+                    7 => "[D",
+                    8 => "[B",
+                    9 => "[S",
+                    10 => "[I",
+                    11 => "[J",
+                    _ => return Err(invalid_data(format!("invalid array type {atype}"))),
+                };
+                self.new_array(descriptor)?;
+            }
+            I::ANewArray(index) => {
+                let name = class_name_at(&class.constant_pool, *index)
+                    .ok_or_else(|| invalid_data(format!("#{index} is not a class")))?;
+                let descriptor = match name.starts_with('[') {
+                    true => format!("[{name}"),
+                    false => format!("[L{name};"),
+                };
+                self.new_array(&descriptor)?;
+            }
+            I::MultiANewArray(index, dimensions) => {
+                let name = class_name_at(&class.constant_pool, *index)
+                    .ok_or_else(|| invalid_data(format!("#{index} is not a class")))?;
+                let mut lengths = vec![];
+                for count in self.pop_slots((*dimensions).into())? {
+                    let count = count.as_int()?;
+                    let length = usize::try_from(count).map_err(|_| {
+                        throw("java/lang/NegativeArraySizeException", count.to_string())
+                    })?;
+                    lengths.push(length);
+                }
+                let array = self.multi_array(name, &lengths)?;
+                self.push(Value::Reference(Some(array)));
+            }
+            I::ArrayLength => {
+                let array = self.pop()?.as_reference()?;
+                let length = self.elements(array, || "read the array length".to_string())?;
+                let length = length.len();
+                self.push(Value::Int(length as i32));
+            }
+            I::IALoad
+            | I::LALoad
+            | I::FALoad
+            | I::DALoad
+            | I::AALoad
+            | I::BALoad
+            | I::CALoad
+            | I::SALoad => {
+                let index = self.pop()?.as_int()?;
+                let array = self.pop()?.as_reference()?;
+                let elements = self.elements(array, || {
+                    format!("load from {} array", element_kind(instruction))
+                })?;
+                let value = elements[element_index(elements.len(), index)?];
+                self.push(value);
+            }
+            I::IAStore
+            | I::LAStore
+            | I::FAStore
+            | I::DAStore
+            | I::AAStore
+            | I::BAStore
+            | I::CAStore
+            | I::SAStore => {
+                let value = self.pop()?;
+                let index = self.pop()?.as_int()?;
+                let array = self.pop()?.as_reference()?;
+                let what = || format!("store to {} array", element_kind(instruction));
+                let length = self.elements(array, what)?.len();
+                let index = element_index(length, index)?;
+                // Elements of arrays of small types are truncated to them
+                let value = match (instruction, value, array) {
+                    (I::AAStore, Value::Reference(Some(object)), Some(array)) => {
+                        self.check_array_store(array, object)?;
+                        value
+                    }
+                    (I::BAStore, Value::Int(value), Some(array)) => match self.class_name(array) {
+                        "[Z" => Value::Int(value & 1),
+                        _ => Value::Int(value as i8 as i32),
+                    },
+                    (I::CAStore, Value::Int(value), _) => Value::Int(value as u16 as i32),
+                    (I::SAStore, Value::Int(value), _) => Value::Int(value as i16 as i32),
+                    _ => value,
+                };
+                self.elements(array, what)?[index] = value;
+            }
+            I::AThrow => {
+                let exception = self.pop()?.as_reference()?;
+                let exception = exception.ok_or_else(|| {
+                    throw("java/lang/NullPointerException", "Cannot throw exception")
+                })?;
+                return Err(rethrow(exception));
+            }
+            // With a single thread, monitors are never contended
+            I::MonitorEnter | I::MonitorExit => {
+                let object = self.pop()?.as_reference()?;
+                if object.is_none() {
+                    let action = match instruction {
+                        I::MonitorEnter => "enter",
+                        _ => "exit",
+                    };
+                    let message = format!("Cannot {action} synchronized block");
+                    return Err(throw("java/lang/NullPointerException", message));
+                }
+            }
+            other => return Err(unsupported(other.mnemonic())),
+        }
+        Ok(Flow::Next)
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("a method is running")
+    }
+
+    fn push(&mut self, value: Value) {
+        self.frame().stack.push(value);
+    }
+
+    fn pop(&mut self) -> Result<Value> {
+        self.frame()
+            .stack
+            .pop()
+            .ok_or_else(|| invalid_data("operand stack underflow"))
+    }
+
+    /// Pops the values taking `slots` slots, in the order they were pushed
+    fn pop_slots(&mut self, slots: usize) -> Result<Vec<Value>> {
+        let mut values = vec![];
+        let mut popped = 0;
+        while popped < slots {
+            let value = self.pop()?;
+            popped += value.size();
+            values.push(value);
+        }
+        if popped > slots {
+            return Err(invalid_data("instruction splits a long or double"));
+        }
+        values.reverse();
+        Ok(values)
+    }
+
+    fn load(&mut self, index: usize) -> Result<()> {
+        let value = self.frame().locals.get(index).copied();
+        let value = value.ok_or_else(|| invalid_data(format!("no local variable {index}")))?;
+        self.push(value);
+        Ok(())
+    }
+
+    fn store(&mut self, index: usize) -> Result<()> {
+        let value = self.pop()?;
+        let local = self.frame().locals.get_mut(index);
+        *local.ok_or_else(|| invalid_data(format!("no local variable {index}")))? = value;
+        Ok(())
+    }
+
+    fn increment(&mut self, index: usize, increment: i32) -> Result<()> {
+        let local = self.frame().locals.get_mut(index);
+        let local = local.ok_or_else(|| invalid_data(format!("no local variable {index}")))?;
+        *local = Value::Int(local.as_int()?.wrapping_add(increment));
+        Ok(())
+    }
+
+    fn int_operation(&mut self, operation: impl Fn(i32, i32) -> Result<i32>) -> Result<()> {
+        let b = self.pop()?.as_int()?;
+        let a = self.pop()?.as_int()?;
+        self.push(Value::Int(operation(a, b)?));
+        Ok(())
+    }
+
+    fn long_operation(&mut self, operation: impl Fn(i64, i64) -> Result<i64>) -> Result<()> {
+        let b = self.pop()?.as_long()?;
+        let a = self.pop()?.as_long()?;
+        self.push(Value::Long(operation(a, b)?));
+        Ok(())
+    }
+
+    fn float_operation(&mut self, operation: impl Fn(f32, f32) -> f32) -> Result<()> {
+        let b = self.pop()?.as_float()?;
+        let a = self.pop()?.as_float()?;
+        self.push(Value::Float(operation(a, b)));
+        Ok(())
+    }
+
+    fn double_operation(&mut self, operation: impl Fn(f64, f64) -> f64) -> Result<()> {
+        let b = self.pop()?.as_double()?;
+        let a = self.pop()?.as_double()?;
+        self.push(Value::Double(operation(a, b)));
+        Ok(())
+    }
+
+    /// Replaces the value on top of the stack by its conversion
+    fn convert(&mut self, conversion: impl Fn(Value) -> Result<Value>) -> Result<()> {
+        let value = conversion(self.pop()?)?;
+        self.push(value);
+        Ok(())
+    }
+
+    /// `ldc`, `ldc_w` and `ldc2_w`
+    fn load_constant(&mut self, class: &Class, index: u16) -> Result<()> {
+        let pool = &class.constant_pool;
+        let value = match constant(pool, index) {
+            Some(ConstantPoolInfo::Integer(value)) => Value::Int(*value),
+            Some(ConstantPoolInfo::Float(value)) => Value::Float(*value),
+            Some(ConstantPoolInfo::Long(value)) => Value::Long(*value),
+            Some(ConstantPoolInfo::Double(value)) => Value::Double(*value),
+            Some(ConstantPoolInfo::String { string_index }) => {
+                let units = utf16_at(pool, *string_index)
+                    .ok_or_else(|| invalid_data(format!("#{index} is not a valid String")))?;
+                Value::Reference(Some(self.intern_utf16(&units)?))
+            }
+            Some(ConstantPoolInfo::Class { name_index }) => {
+                let name = utf8_at(pool, *name_index)
+                    .ok_or_else(|| invalid_data(format!("#{index} is not a valid Class")))?;
+                Value::Reference(Some(self.mirror(name)?))
+            }
+            _ => return Err(unsupported(&format!("ldc of constant #{index}"))),
+        };
+        self.push(value);
+        Ok(())
+    }
+
+    /// Calls the method referenced by the invoke instruction with the arguments on the
+    /// operand stack, and pushes the value it returns
+    fn invoke_instruction(&mut self, class: &Class, index: u16, kind: Invoke) -> Result<()> {
+        let pool = &class.constant_pool;
+        let (owner, name, descriptor) = member_ref_at(pool, index)
+            .ok_or_else(|| invalid_data(format!("#{index} is not a method reference")))?;
+        let parsed = MethodDescriptor::parse(descriptor)
+            .ok_or_else(|| invalid_data(format!("invalid method descriptor {descriptor:?}")))?;
+        let count = parsed.parameters.len() + usize::from(kind != Invoke::Static);
+        let (start, arguments) = self.arguments(count)?;
+        // invokestatic and invokespecial may name methods of interfaces too
+        let interface = matches!(
+            constant(pool, index),
+            Some(ConstantPoolInfo::InterfaceMethodRef { .. })
+        );
+
+        let result = match self.select(owner, name, descriptor, kind, interface, &arguments) {
+            Ok(member) => {
+                if kind == Invoke::Static {
+                    let id = self.load_class(member.class_name())?;
+                    self.initialize(id)?;
+                }
+                self.call(&member, arguments)?
+            }
+            // Without a JDK, the classes the stubs stand for are not on the class path
+            Err(error) => match natives::call(self, owner, name, descriptor, &arguments) {
+                Some(result) => result?,
+                None => return Err(error),
+            },
+        };
+        self.frame().stack.truncate(start);
+        if let Some(value) = result {
+            self.push(value);
+        }
+        Ok(())
+    }
+
+    /// The `count` values on top of the stack, in the order they were pushed, with the
+    /// height of the stack below them. They stay on the stack during a call, where the
+    /// collector finds them, and are removed by truncating it to that height.
+    fn arguments(&mut self, count: usize) -> Result<(usize, Vec<Value>)> {
+        let stack = &mut self.frame().stack;
+        let start = stack
+            .len()
+            .checked_sub(count)
+            .ok_or_else(|| invalid_data("operand stack underflow"))?;
+        Ok((start, stack[start..].to_vec()))
+    }
+
+    /// Calls the method, which is native if the interpreter provides it for the class
+    /// declaring it, like the methods of `Throwable` called on a subclass
+    pub fn call(&mut self, member: &Member, arguments: Vec<Value>) -> Result<Option<Value>> {
+        let method = &member.class.methods[member.index];
+        let pool = &member.class.constant_pool;
+        let name = utf8_at(pool, method.name_index).unwrap_or_default();
+        let descriptor = utf8_at(pool, method.descriptor_index).unwrap_or_default();
+        match natives::call(self, member.class_name(), name, descriptor, &arguments) {
+            Some(result) => result,
+            None => self.invoke(member.class.clone(), member.index, arguments),
+        }
+    }
+
+    /// Method that an invocation calls: the one resolved, as a method of an interface if
+    /// `interface`, or for virtual calls the one the class of the receiver selects
+    fn select(
+        &self,
+        owner: &str,
+        name: &str,
+        descriptor: &str,
+        kind: Invoke,
+        interface: bool,
+        arguments: &[Value],
+    ) -> Result<Member> {
+        let resolver = Resolver::new(self.class_path);
+        let resolved = match interface {
+            true => resolver.resolve_interface_method(owner, name, descriptor),
+            false => resolver.resolve_method(owner, name, descriptor),
+        };
+        let resolved = match resolved {
+            Ok(resolved) => resolved,
+            // Without a JDK, classes may still override the methods of Object
+            Err(error) if !self.jdk && owner == OBJECT && kind == Invoke::Virtual => {
+                let receiver = arguments[0].as_reference()?;
+                let selected = receiver.and_then(|receiver| {
+                    let receiver = self.class_name(receiver);
+                    resolver.select(receiver, name, descriptor).ok().flatten()
+                });
+                return selected.ok_or_else(|| throw(error.error, error.message));
+            }
+            Err(error) => return Err(throw(error.error, error.message)),
+        };
+        if kind == Invoke::Static {
+            return Ok(resolved);
+        }
+        let receiver = arguments[0].as_reference()?;
+        let receiver = receiver.ok_or_else(|| null_receiver(owner, name, descriptor))?;
+        // Private methods are not overridden, even when called with invokevirtual as
+        // between nestmates
+        let private = resolved
+            .method()
+            .access_flags
+            .contains(AccessFlags::Private);
+        if kind == Invoke::Special || private {
+            return Ok(resolved);
+        }
+        let receiver = self.class_name(receiver);
+        let selected = resolver
+            .select(receiver, name, descriptor)
+            .map_err(|error| throw(error.error, error.message))?;
+        Ok(selected.unwrap_or(resolved))
+    }
+
+    /// Class and slot of the static field referenced under `index` of `class`, with the
+    /// class declaring the field initialized
+    fn static_field(&mut self, class: &Class, index: u16) -> Result<(ClassId, usize)> {
+        let (id, slot) = self.field(class, index)?;
+        match slot {
+            Slot::Static(slot) => {
+                self.initialize(id)?;
+                Ok((id, slot))
+            }
+            Slot::Instance(_) => Err(throw(
+                "java/lang/IncompatibleClassChangeError",
+                format!("#{index} is not a static field"),
+            )),
+        }
+    }
+
+    /// Class declaring the instance field referenced under `index` of `class`, and its slot
+    fn instance_field(&mut self, class: &Class, index: u16) -> Result<(ClassId, usize)> {
+        match self.field(class, index)? {
+            (id, Slot::Instance(slot)) => Ok((id, slot)),
+            (_, Slot::Static(_)) => Err(throw(
+                "java/lang/IncompatibleClassChangeError",
+                format!("#{index} is a static field"),
+            )),
+        }
+    }
+
+    /// Resolves the field reference, with the class declaring the field loaded
+    fn field(&mut self, class: &Class, index: u16) -> Result<(ClassId, Slot)> {
+        let pool = &class.constant_pool;
+        let (owner, name, descriptor) = member_ref_at(pool, index)
+            .ok_or_else(|| invalid_data(format!("#{index} is not a field reference")))?;
+        let member = Resolver::new(self.class_path)
+            .resolve_field(owner, name, descriptor)
+            .map_err(|error| throw(error.error, error.message))?;
+        let id = self.load_class(member.class_name())?;
+        Ok((id, self.classes[id.0].slots[member.index]))
+    }
+
+    /// Fields of the instance for accessing a field the class `id` declares. Objects of
+    /// other classes, which only code that is not verified passes, throw `VerifyError`.
+    fn fields_of(&mut self, object: Ref, id: ClassId) -> Result<&mut Vec<Value>> {
+        let owner = &self.classes[id.0].name;
+        let class = self.class_name(object);
+        if !self.is_instance_of(class, owner) {
+            let message = format!("field of {owner} accessed on an object of {class}");
+            return Err(throw("java/lang/VerifyError", message));
+        }
+        self.fields(object)
+    }
+
+    /// Fields of the instance
+    fn fields(&mut self, object: Ref) -> Result<&mut Vec<Value>> {
+        match self.heap.get_mut(object) {
+            Object::Instance { fields, .. } => Ok(fields),
+            _ => Err(invalid_data("object has no fields")),
+        }
+    }
+
+    /// Pops the length and pushes a new array of the type with that length
+    fn new_array(&mut self, descriptor: &str) -> Result<()> {
+        let length = self.pop()?.as_int()?;
+        let length = usize::try_from(length)
+            .map_err(|_| throw("java/lang/NegativeArraySizeException", length.to_string()))?;
+        let array = self.allocate_array(descriptor, length)?;
+        self.push(Value::Reference(Some(array)));
+        Ok(())
+    }
+
+    /// Elements of the array, for an instruction that does `what` with them
+    fn elements(
+        &mut self,
+        array: Option<Ref>,
+        what: impl Fn() -> String,
+    ) -> Result<&mut Vec<Value>> {
+        let array = array.ok_or_else(|| {
+            throw(
+                "java/lang/NullPointerException",
+                format!("Cannot {}", what()),
+            )
+        })?;
+        match self.heap.get_mut(array) {
+            Object::Array { elements, .. } => Ok(elements),
+            _ => Err(invalid_data("object is not an array")),
+        }
+    }
+
+    /// Array of the array type with the lengths of its dimensions, the first of the array
+    /// itself, the next of its elements and so on. Elements of the last dimension, or of
+    /// arrays of length zero, have their initial value.
+    fn multi_array(&mut self, class: &str, lengths: &[usize]) -> Result<Ref> {
+        let array = self.allocate_array(class, lengths[0])?;
+        if lengths.len() == 1 {
+            return Ok(array);
+        }
+        self.handles.push(array);
+        let result = (0..lengths[0]).try_for_each(|index| {
+            let element = self.multi_array(&class[1..], &lengths[1..])?;
+            self.elements(Some(array), String::new)?[index] = Value::Reference(Some(element));
+            Ok(())
+        });
+        self.handles.pop();
+        result.map(|()| array)
+    }
+
+    /// Fails with `ArrayStoreException` unless the object can be an element of the array
+    fn check_array_store(&self, array: Ref, object: Ref) -> Result<()> {
+        let Object::Array { class, .. } = self.heap.get(array) else {
+            return Err(invalid_data("object is not an array"));
+        };
+        let component = &class[1..];
+        let component = component
+            .strip_prefix('L')
+            .and_then(|name| name.strip_suffix(';'))
+            .unwrap_or(component);
+        let object = self.class_name(object);
+        match self.is_instance_of(object, component) {
+            true => Ok(()),
+            false => Err(throw(
+                "java/lang/ArrayStoreException",
+                object.replace('/', "."),
+            )),
+        }
+    }
+
+    /// Internal name of the class of the object, or the descriptor of arrays
+    pub fn class_name(&self, object: Ref) -> &str {
+        match self.heap.get(object) {
+            Object::PrintStream(_) => "java/io/PrintStream",
+            Object::String(_) => "java/lang/String",
+            Object::Instance { class, .. } => &self.classes[class.0].name,
+            Object::Array { class, .. } => class,
+            Object::Backtrace(_) => "[Ljava/lang/Object;",
+        }
+    }
+
+    /// The object of `System.out` or `System.err`
+    fn print_stream(&mut self, stream: Stream) -> Result<Ref> {
+        let index = stream as usize;
+        if let Some(reference) = self.print_streams[index] {
+            return Ok(reference);
+        }
+        let reference = self.allocate(Object::PrintStream(stream))?;
+        self.print_streams[index] = Some(reference);
+        Ok(reference)
+    }
+}
+
+/// Type of the elements an array instruction works with, as `NullPointerException` names it
+fn element_kind(instruction: &Instruction) -> &'static str {
+    match instruction {
+        Instruction::IALoad | Instruction::IAStore => "int",
+        Instruction::LALoad | Instruction::LAStore => "long",
+        Instruction::FALoad | Instruction::FAStore => "float",
+        Instruction::DALoad | Instruction::DAStore => "double",
+        Instruction::BALoad | Instruction::BAStore => "byte/boolean",
+        Instruction::CALoad | Instruction::CAStore => "char",
+        Instruction::SALoad | Instruction::SAStore => "short",
+        _ => "object",
+    }
+}
+
+/// Index of an array element, or `ArrayIndexOutOfBoundsException`
+fn element_index(length: usize, index: i32) -> Result<usize> {
+    match usize::try_from(index) {
+        Ok(index) if index < length => Ok(index),
+        _ => Err(throw(
+            "java/lang/ArrayIndexOutOfBoundsException",
+            format!("Index {index} out of bounds for length {length}"),
+        )),
+    }
+}
+
+/// Method like the JVM names it in messages, like `void Main.run(int, java.lang.String)`
+fn signature(class: &str, name: &str, descriptor: &str) -> String {
+    let Some(parsed) = MethodDescriptor::parse(descriptor) else {
+        return format!("{class}.{name}{descriptor}");
+    };
+    let return_type = parsed
+        .return_type
+        .map_or("void".to_string(), |return_type| return_type.java_name());
+    let parameters: Vec<String> = parsed.parameters.iter().map(FieldType::java_name).collect();
+    format!("{return_type} {class}.{name}({})", parameters.join(", "))
+}
+
+/// `NullPointerException` of a call of the method on `null`
+fn null_receiver(owner: &str, name: &str, descriptor: &str) -> std::io::Error {
+    // Named like the JVM does, without the package of java.lang classes
+    let short = |name: String| match name.strip_prefix("java.lang.") {
+        Some(simple) if !simple.contains('.') => simple.to_string(),
+        _ => name,
+    };
+    let parameters: Vec<String> = MethodDescriptor::parse(descriptor)
+        .map(|parsed| {
+            let names = parsed.parameters.iter().map(FieldType::java_name);
+            names.map(short).collect()
+        })
+        .unwrap_or_default();
+    let owner = short(owner.replace('/', "."));
+    let method = format!("{owner}.{name}({})", parameters.join(", "));
+    throw(
+        "java/lang/NullPointerException",
+        format!("Cannot invoke \"{method}\""),
+    )
+}
+
+/// `NullPointerException` of `getfield` or `putfield`, which `action` the field
+fn null_field(class: &Class, index: u16, action: &str) -> std::io::Error {
+    let name = member_ref_at(&class.constant_pool, index).map_or("?", |(_, name, _)| name);
+    throw(
+        "java/lang/NullPointerException",
+        format!("Cannot {action} field \"{name}\""),
+    )
+}
+
+fn unsupported(what: &str) -> std::io::Error {
+    invalid_data(format!("{what} is not supported"))
+}
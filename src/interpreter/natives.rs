@@ -0,0 +1,349 @@
+//! Methods and fields of the JDK that the interpreter provides itself. Without a JDK,
+//! these stubs stand for its classes: `System.out` and `System.err` with the `print` and
+//! `println` methods of `PrintStream`, and the core methods of `String`, whose objects
+//! keep their text, with `String.valueOf(Object)`. With or without a JDK, the constructor
+//! of `Object` does nothing, and the constructors of `Throwable` and the methods that use
+//! its stack trace are replaced. Other native methods are looked up in the
+//! [`Registry`](crate::interpreter::registry).
+
+use crate::hierarchy::OBJECT;
+use crate::interpreter::exception::{throw, THROWABLE};
+use crate::interpreter::heap::{Object, Stream};
+use crate::interpreter::strings::STRING;
+use crate::interpreter::value::{Ref, Value};
+use crate::interpreter::{null_receiver, Interpreter};
+use crate::javap::{java_double, java_float};
+use crate::raw::{invalid_data, FieldType, MethodDescriptor};
+use crate::resolve::{Member, Resolver};
+use std::io::{Result, Write};
+
+const PRINT_STREAM: &str = "java/io/PrintStream";
+
+/// Value of the static field, if it is provided by the interpreter
+pub fn static_field(
+    interpreter: &mut Interpreter,
+    owner: &str,
+    name: &str,
+) -> Option<Result<Value>> {
+    let stream = match (owner, name) {
+        _ if interpreter.jdk => return None,
+        ("java/lang/System", "out") => Stream::Out,
+        ("java/lang/System", "err") => Stream::Err,
+        _ => return None,
+    };
+    Some(
+        interpreter
+            .print_stream(stream)
+            .map(|stream| Value::Reference(Some(stream))),
+    )
+}
+
+/// Calls the method if it is provided by the interpreter, with the receiver as the first
+/// of `arguments` for instance methods. Returns `None` for other methods.
+pub fn call(
+    interpreter: &mut Interpreter,
+    owner: &str,
+    name: &str,
+    descriptor: &str,
+    arguments: &[Value],
+) -> Option<Result<Option<Value>>> {
+    let stubs = !interpreter.jdk;
+    match (owner, name) {
+        ("java/lang/Object", "<init>") => Some(Ok(None)),
+        (PRINT_STREAM, "print" | "println") if stubs => {
+            Some(print(interpreter, descriptor, arguments, name == "println"))
+        }
+        (THROWABLE, _) if is_throwable_native(name, descriptor) => {
+            Some(throwable(interpreter, name, descriptor, arguments))
+        }
+        (STRING, _) if stubs && is_string_native(name, descriptor) => {
+            Some(string(interpreter, name, descriptor, arguments))
+        }
+        (STRING, "valueOf") if stubs && descriptor == "(Ljava/lang/Object;)Ljava/lang/String;" => {
+            Some(value_of(interpreter, arguments))
+        }
+        _ => {
+            let method = interpreter.registry.get(owner, name, descriptor)?;
+            Some(method(interpreter, arguments))
+        }
+    }
+}
+
+/// Whether the static initializer of the class does not run. That of `Throwable` sets up
+/// the stack trace the natives replace, and that of `ExceptionInInitializerError` only
+/// serialization.
+pub fn replaces_initializer(class: &str) -> bool {
+    matches!(class, THROWABLE | "java/lang/ExceptionInInitializerError")
+}
+
+/// Whether the method of `Throwable` is provided by [`throwable`]
+fn is_throwable_native(name: &str, descriptor: &str) -> bool {
+    matches!(
+        (name, descriptor),
+        ("<init>", _)
+            | ("fillInStackTrace", "()Ljava/lang/Throwable;")
+            | ("toString", "()Ljava/lang/String;")
+            | ("printStackTrace", "()V")
+    )
+}
+
+/// Constructors of `Throwable`, `fillInStackTrace`, `toString` and `printStackTrace()`
+fn throwable(
+    interpreter: &mut Interpreter,
+    name: &str,
+    descriptor: &str,
+    arguments: &[Value],
+) -> Result<Option<Value>> {
+    let references = arguments
+        .iter()
+        .map(|argument| argument.as_reference())
+        .collect::<Result<Vec<_>>>()?;
+    let Some(Some(receiver)) = references.first().copied() else {
+        return Err(invalid_data("Throwable method called without a receiver"));
+    };
+    match (name, descriptor) {
+        ("<init>", _) => {
+            // Without a cause, the cause is the exception itself
+            let (message, cause) = match (descriptor, &references[1..]) {
+                ("()V", []) => (None, Some(receiver)),
+                ("(Ljava/lang/String;)V", [message]) => (*message, Some(receiver)),
+                (
+                    "(Ljava/lang/String;Ljava/lang/Throwable;)V"
+                    | "(Ljava/lang/String;Ljava/lang/Throwable;ZZ)V",
+                    [message, cause, ..],
+                ) => (*message, *cause),
+                // The message is the text of the cause
+                ("(Ljava/lang/Throwable;)V", [Some(cause)]) => {
+                    let text = interpreter.describe_throwable(*cause);
+                    let message = interpreter.new_string(&text)?;
+                    (Some(message), Some(*cause))
+                }
+                ("(Ljava/lang/Throwable;)V", [None]) => (None, None),
+                _ => return Err(invalid_data(format!("no Throwable.<init>{descriptor}"))),
+            };
+            interpreter.init_throwable(receiver, message, cause)?;
+            Ok(None)
+        }
+        ("fillInStackTrace", "()Ljava/lang/Throwable;") => {
+            interpreter.fill_in_stack_trace(receiver)?;
+            Ok(Some(Value::Reference(Some(receiver))))
+        }
+        ("toString", "()Ljava/lang/String;") => {
+            let text = interpreter.throwable_to_string(receiver);
+            let string = interpreter.new_string(&text)?;
+            Ok(Some(Value::Reference(Some(string))))
+        }
+        ("printStackTrace", "()V") => {
+            let trace = interpreter.stack_trace(receiver);
+            interpreter.out.flush()?;
+            std::io::stderr().write_all(trace.as_bytes())?;
+            Ok(None)
+        }
+        _ => Err(invalid_data(format!(
+            "no native Throwable.{name}{descriptor}"
+        ))),
+    }
+}
+
+/// Whether the method of `String` is provided by [`string`]
+fn is_string_native(name: &str, descriptor: &str) -> bool {
+    matches!(
+        (name, descriptor),
+        ("length", "()I")
+            | ("charAt", "(I)C")
+            | ("equals", "(Ljava/lang/Object;)Z")
+            | ("hashCode", "()I")
+            | ("concat", "(Ljava/lang/String;)Ljava/lang/String;")
+    )
+}
+
+/// `length`, `charAt`, `equals`, `hashCode` and `concat` of `String`. Indexes
+/// and lengths count UTF-16 code units, like Java does.
+fn string(
+    interpreter: &mut Interpreter,
+    name: &str,
+    descriptor: &str,
+    arguments: &[Value],
+) -> Result<Option<Value>> {
+    let receiver = match arguments.first() {
+        Some(receiver) => receiver.as_reference()?,
+        None => return Err(invalid_data("String method called without a receiver")),
+    };
+    let receiver = receiver.ok_or_else(|| null_receiver(STRING, name, descriptor))?;
+    let text = interpreter.string(receiver).ok_or_else(|| {
+        invalid_data(format!(
+            "{} is not a String",
+            interpreter.class_name(receiver)
+        ))
+    })?;
+    let argument = arguments.get(1).copied();
+    let value = match name {
+        "length" => Value::Int(text.encode_utf16().count() as i32),
+        "charAt" => {
+            let index = argument.unwrap_or(Value::Int(0)).as_int()?;
+            let unit = usize::try_from(index)
+                .ok()
+                .and_then(|index| text.encode_utf16().nth(index));
+            let unit = unit.ok_or_else(|| {
+                throw(
+                    "java/lang/StringIndexOutOfBoundsException",
+                    format!("String index out of range: {index}"),
+                )
+            })?;
+            Value::Int(unit.into())
+        }
+        "equals" => {
+            let other = argument.map_or(Ok(None), |argument| argument.as_reference())?;
+            let other = other.and_then(|other| interpreter.string(other));
+            Value::Int((other.as_ref() == Some(&text)).into())
+        }
+        "hashCode" => Value::Int(string_hash(&text)),
+        "concat" => {
+            let other = argument.map_or(Ok(None), |argument| argument.as_reference())?;
+            // Like the JDK, which calls isEmpty on the argument
+            let other = other.ok_or_else(|| null_receiver(STRING, "isEmpty", "()Z"))?;
+            let other_text = interpreter.string(other).unwrap_or_default();
+            let string = match other_text.is_empty() {
+                true => receiver,
+                false => interpreter.new_string(&(text + &other_text))?,
+            };
+            Value::Reference(Some(string))
+        }
+        _ => return Err(invalid_data(format!("no native String.{name}{descriptor}"))),
+    };
+    Ok(Some(value))
+}
+
+/// `String.valueOf(Object)`, which `javac` calls for objects that are concatenated.
+/// Strings are returned themselves.
+fn value_of(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Option<Value>> {
+    let [value] = arguments else {
+        return Err(invalid_data("String.valueOf called without its argument"));
+    };
+    if let Some(string) = value.as_reference()? {
+        if interpreter.string(string).is_some() {
+            return Ok(Some(*value));
+        }
+    }
+    let text = to_string(interpreter, &FieldType::Object(OBJECT.to_string()), *value)?;
+    let string = interpreter.new_string(&text)?;
+    Ok(Some(Value::Reference(Some(string))))
+}
+
+/// `String.hashCode` of the text
+pub fn string_hash(text: &str) -> i32 {
+    text.encode_utf16().fold(0, |hash: i32, unit| {
+        hash.wrapping_mul(31).wrapping_add(unit.into())
+    })
+}
+
+/// `PrintStream.print` and `println` of any of the overloads
+fn print(
+    interpreter: &mut Interpreter,
+    descriptor: &str,
+    arguments: &[Value],
+    newline: bool,
+) -> Result<Option<Value>> {
+    let parsed = MethodDescriptor::parse(descriptor)
+        .ok_or_else(|| invalid_data(format!("invalid descriptor {descriptor:?}")))?;
+    let (Some(receiver), [] | [_]) = (arguments.first(), parsed.parameters.as_slice()) else {
+        return Err(invalid_data(format!("no PrintStream method {descriptor}")));
+    };
+    let mut text = match (parsed.parameters.first(), arguments.get(1)) {
+        (Some(parameter), Some(value)) => to_string(interpreter, parameter, *value)?,
+        _ => String::new(),
+    };
+    if newline {
+        text.push('\n');
+    }
+    let stream = match receiver.as_reference()? {
+        Some(reference) => match interpreter.heap.get(reference) {
+            Object::PrintStream(stream) => *stream,
+            _ => {
+                let class = interpreter.class_name(reference);
+                return Err(invalid_data(format!("{class} is not a PrintStream")));
+            }
+        },
+        None => {
+            let method = format!(
+                "java.io.PrintStream.{}()",
+                if newline { "println" } else { "print" }
+            );
+            return Err(throw(
+                "java/lang/NullPointerException",
+                format!("Cannot invoke \"{method}\""),
+            ));
+        }
+    };
+    match stream {
+        Stream::Out => interpreter.out.write_all(text.as_bytes())?,
+        Stream::Err => {
+            interpreter.out.flush()?;
+            std::io::stderr().write_all(text.as_bytes())?
+        }
+    }
+    Ok(None)
+}
+
+/// Text of the value like `String.valueOf` makes it, calling `toString` on objects of
+/// classes that override it
+pub fn to_string(
+    interpreter: &mut Interpreter,
+    field_type: &FieldType,
+    value: Value,
+) -> Result<String> {
+    Ok(match field_type {
+        FieldType::Boolean => (value.as_int()? != 0).to_string(),
+        FieldType::Char => char::decode_utf16([value.as_int()? as u16])
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect(),
+        FieldType::Byte | FieldType::Short | FieldType::Int => value.as_int()?.to_string(),
+        FieldType::Long => value.as_long()?.to_string(),
+        // Releases of the JDK write some numbers differently, like 1.0E23
+        FieldType::Float | FieldType::Double if interpreter.jdk => {
+            let (class, descriptor) = match field_type {
+                FieldType::Float => ("java/lang/Float", "(F)Ljava/lang/String;"),
+                _ => ("java/lang/Double", "(D)Ljava/lang/String;"),
+            };
+            let text = interpreter.call_static(class, "toString", descriptor, vec![value])?;
+            let text = text.map_or(Ok(None), |text| text.as_reference())?;
+            text.and_then(|text| interpreter.string(text))
+                .ok_or_else(|| invalid_data(format!("{class}.toString returned no string")))?
+        }
+        FieldType::Float => java_float(value.as_float()?),
+        FieldType::Double => java_double(value.as_double()?),
+        FieldType::Object(_) | FieldType::Array(_) => match value.as_reference()? {
+            None => "null".to_string(),
+            Some(reference) => match interpreter.string(reference) {
+                Some(text) => text,
+                None => {
+                    let class = interpreter.class_name(reference).to_string();
+                    let selected =
+                        overriding(interpreter, reference, "toString", "()Ljava/lang/String;")?;
+                    match selected {
+                        Some(member) => {
+                            let text = interpreter.call(&member, vec![value])?;
+                            let text = text.map_or(Ok(None), |text| text.as_reference())?;
+                            to_string(interpreter, field_type, Value::Reference(text))?
+                        }
+                        None => format!("{}@{:x}", class.replace('/', "."), reference.0),
+                    }
+                }
+            },
+        },
+    })
+}
+
+/// Method of the class of the object that overrides the method of `Object`
+pub fn overriding(
+    interpreter: &Interpreter,
+    object: Ref,
+    name: &str,
+    descriptor: &str,
+) -> Result<Option<Member>> {
+    let class = interpreter.class_name(object);
+    let selected = Resolver::new(interpreter.class_path)
+        .select(class, name, descriptor)
+        .map_err(|error| throw(error.error, error.message))?;
+    Ok(selected.filter(|member| member.class_name() != OBJECT))
+}
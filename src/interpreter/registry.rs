@@ -0,0 +1,206 @@
+//! Native methods bound to Rust functions. A function is registered for the class, name
+//! and descriptor of a method, and takes the interpreter followed by the receiver, for
+//! instance methods, and the arguments, as Rust types: `i32`, `i64`, `f32`, `f64`,
+//! `bool`, `u16` for `char`, and `Option<Ref>` for references, or [`Value`] for any of
+//! them. It returns one of these, or `()` for `void`, in a [`Result`] whose errors are
+//! thrown like those of the interpreter. Methods are looked up by the class declaring the
+//! method that the invocation selects, or by the class named in the invocation when that
+//! class is not on the class path, as without a JDK.
+
+use crate::interpreter::value::{Ref, Value};
+use crate::interpreter::Interpreter;
+use crate::raw::invalid_data;
+use std::collections::HashMap;
+use std::io::Result;
+use std::rc::Rc;
+
+/// Native method as it is called, with the receiver as the first of the arguments for
+/// instance methods. Returns the value of the method, if it is not `void`.
+pub type NativeMethod = Rc<dyn Fn(&mut Interpreter, &[Value]) -> Result<Option<Value>>>;
+
+/// Native methods by class, with their names and descriptors
+#[derive(Default)]
+pub struct Registry {
+    classes: HashMap<String, Vec<(String, String, NativeMethod)>>,
+}
+
+impl Registry {
+    /// Binds the method `owner.name(descriptor)` to the function, replacing the function
+    /// bound to it before
+    pub fn register<Parameters>(
+        &mut self,
+        owner: &str,
+        name: &str,
+        descriptor: &str,
+        function: impl NativeFunction<Parameters> + 'static,
+    ) {
+        let method: NativeMethod =
+            Rc::new(move |interpreter, arguments| function.call(interpreter, arguments));
+        let methods = self.classes.entry(owner.to_string()).or_default();
+        methods
+            .retain(|(other, other_descriptor, _)| other != name || other_descriptor != descriptor);
+        methods.push((name.to_string(), descriptor.to_string(), method));
+    }
+
+    /// Function bound to the method, if there is one
+    pub fn get(&self, owner: &str, name: &str, descriptor: &str) -> Option<NativeMethod> {
+        self.classes
+            .get(owner)?
+            .iter()
+            .find(|(other, other_descriptor, _)| other == name && other_descriptor == descriptor)
+            .map(|(_, _, method)| method.clone())
+    }
+}
+
+/// Rust type of a parameter of a native method
+pub trait FromValue: Sized {
+    fn from_value(value: Value) -> Result<Self>;
+}
+
+impl FromValue for i32 {
+    fn from_value(value: Value) -> Result<i32> {
+        value.as_int()
+    }
+}
+
+impl FromValue for i64 {
+    fn from_value(value: Value) -> Result<i64> {
+        value.as_long()
+    }
+}
+
+impl FromValue for f32 {
+    fn from_value(value: Value) -> Result<f32> {
+        value.as_float()
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: Value) -> Result<f64> {
+        value.as_double()
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: Value) -> Result<bool> {
+        Ok(value.as_int()? != 0)
+    }
+}
+
+/// `char`, a UTF-16 code unit
+impl FromValue for u16 {
+    fn from_value(value: Value) -> Result<u16> {
+        Ok(value.as_int()? as u16)
+    }
+}
+
+impl FromValue for Option<Ref> {
+    fn from_value(value: Value) -> Result<Option<Ref>> {
+        value.as_reference()
+    }
+}
+
+/// Value of any type, for methods that take several
+impl FromValue for Value {
+    fn from_value(value: Value) -> Result<Value> {
+        Ok(value)
+    }
+}
+
+/// Rust type a native method returns
+pub trait IntoValue {
+    /// The value, `None` for `void`
+    fn into_value(self) -> Option<Value>;
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Option<Value> {
+        None
+    }
+}
+
+impl IntoValue for i32 {
+    fn into_value(self) -> Option<Value> {
+        Some(Value::Int(self))
+    }
+}
+
+impl IntoValue for i64 {
+    fn into_value(self) -> Option<Value> {
+        Some(Value::Long(self))
+    }
+}
+
+impl IntoValue for f32 {
+    fn into_value(self) -> Option<Value> {
+        Some(Value::Float(self))
+    }
+}
+
+impl IntoValue for f64 {
+    fn into_value(self) -> Option<Value> {
+        Some(Value::Double(self))
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> Option<Value> {
+        Some(Value::Int(self.into()))
+    }
+}
+
+impl IntoValue for u16 {
+    fn into_value(self) -> Option<Value> {
+        Some(Value::Int(self.into()))
+    }
+}
+
+impl IntoValue for Option<Ref> {
+    fn into_value(self) -> Option<Value> {
+        Some(Value::Reference(self))
+    }
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Option<Value> {
+        Some(self)
+    }
+}
+
+/// Rust function that can be bound to a native method with the parameters of the types
+/// of the tuple `Parameters`
+pub trait NativeFunction<Parameters> {
+    /// Calls the function with the arguments converted to the types of its parameters
+    fn call(&self, interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Option<Value>>;
+}
+
+/// Implements [`NativeFunction`] for functions with parameters of the types
+macro_rules! native_function {
+    ($($parameter:ident),*) => {
+        impl<F, R, $($parameter),*> NativeFunction<($($parameter,)*)> for F
+        where
+            F: Fn(&mut Interpreter, $($parameter),*) -> Result<R>,
+            R: IntoValue,
+            $($parameter: FromValue,)*
+        {
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn call(&self, interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Option<Value>> {
+                let mut arguments = arguments.iter();
+                $(
+                    let argument = arguments
+                        .next()
+                        .ok_or_else(|| invalid_data("native method called with too few arguments"))?;
+                    let $parameter = $parameter::from_value(*argument)?;
+                )*
+                Ok(self(interpreter, $($parameter),*)?.into_value())
+            }
+        }
+    };
+}
+
+native_function!();
+native_function!(A);
+native_function!(A, B);
+native_function!(A, B, C);
+native_function!(A, B, C, D);
+native_function!(A, B, C, D, E);
@@ -0,0 +1,150 @@
+//! Strings of the programs. With a JDK, a string is an instance of `java/lang/String`
+//! whose `value` holds its characters as bytes, one per character if they all fit, in
+//! Latin-1, or two per character otherwise, in UTF-16 with the low byte first, as
+//! `coder` tells. Before JDK 9, which has no `coder`, `value` is an array of the chars
+//! themselves. Without a JDK, a string is an [`Object::String`] that keeps its text,
+//! for the methods [`natives`](crate::interpreter::natives) provide. String constants
+//! are interned: all constants with the same text are the same object. Texts are kept
+//! as UTF-16 code units, as constants may have unpaired surrogates, which the strings of
+//! the stubs show as U+FFFD.
+
+use crate::interpreter::heap::Object;
+use crate::interpreter::value::{Ref, Value};
+use crate::interpreter::Interpreter;
+use std::io::Result;
+
+pub const STRING: &str = "java/lang/String";
+
+/// Values of `String.coder`
+const LATIN1: i32 = 0;
+const UTF16: i32 = 1;
+
+impl Interpreter<'_> {
+    /// New `java/lang/String` with the text
+    pub fn new_string(&mut self, text: &str) -> Result<Ref> {
+        if !self.jdk {
+            return self.allocate(Object::String(text.to_string()));
+        }
+        self.new_utf16_string(&text.encode_utf16().collect::<Vec<_>>())
+    }
+
+    /// New `java/lang/String` with the UTF-16 code units
+    pub fn new_utf16_string(&mut self, units: &[u16]) -> Result<Ref> {
+        if !self.jdk {
+            let text = String::from_utf16_lossy(units);
+            return self.allocate(Object::String(text));
+        }
+        if !self.compact_strings() {
+            let value = self.allocate(Object::Array {
+                class: "[C".to_string(),
+                elements: units
+                    .iter()
+                    .map(|unit| Value::Int((*unit).into()))
+                    .collect(),
+            })?;
+            self.handles.push(value);
+            let string = self.new_object(STRING);
+            self.handles.pop();
+            let string = string?;
+            self.set_field(string, STRING, "value", Value::Reference(Some(value)));
+            return Ok(string);
+        }
+        let (coder, bytes): (_, Vec<u8>) = match units.iter().all(|unit| *unit <= 0xff) {
+            true => (LATIN1, units.iter().map(|unit| *unit as u8).collect()),
+            false => (
+                UTF16,
+                units.iter().flat_map(|unit| unit.to_le_bytes()).collect(),
+            ),
+        };
+        let value = self.allocate(Object::Array {
+            class: "[B".to_string(),
+            elements: bytes
+                .into_iter()
+                .map(|byte| Value::Int(byte as i8 as i32))
+                .collect(),
+        })?;
+        self.handles.push(value);
+        let string = self.new_object(STRING);
+        self.handles.pop();
+        let string = string?;
+        self.set_field(string, STRING, "value", Value::Reference(Some(value)));
+        self.set_field(string, STRING, "coder", Value::Int(coder));
+        Ok(string)
+    }
+
+    /// Text of a `java/lang/String` object, `None` for other objects
+    pub fn string(&self, object: Ref) -> Option<String> {
+        match self.heap.get(object) {
+            Object::String(text) => Some(text.clone()),
+            _ => Some(String::from_utf16_lossy(&self.utf16(object)?)),
+        }
+    }
+
+    /// UTF-16 code units of a `java/lang/String` object, `None` for other objects
+    pub fn utf16(&self, object: Ref) -> Option<Vec<u16>> {
+        let fields = match self.heap.get(object) {
+            Object::String(text) => return Some(text.encode_utf16().collect()),
+            Object::Instance { class, fields } if self.classes[class.0].name == STRING => fields,
+            _ => return None,
+        };
+        let id = self.class_ids.get(STRING)?;
+        let value = fields[self.declared_slot(*id, "value")?]
+            .as_reference()
+            .ok()??;
+        let Object::Array { elements, .. } = self.heap.get(value) else {
+            return None;
+        };
+        let Some(coder) = self.declared_slot(*id, "coder") else {
+            let units = elements.iter().map(|unit| match unit {
+                Value::Int(unit) => *unit as u16,
+                _ => 0,
+            });
+            return Some(units.collect());
+        };
+        let coder = fields[coder].as_int().ok()?;
+        let bytes = elements.iter().map(|byte| match byte {
+            Value::Int(byte) => *byte as u8,
+            _ => 0,
+        });
+        Some(match coder {
+            LATIN1 => bytes.map(u16::from).collect(),
+            _ => {
+                let bytes: Vec<u8> = bytes.collect();
+                bytes
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                    .collect()
+            }
+        })
+    }
+
+    /// Whether the strings of the JDK keep their characters as bytes with a `coder`, as
+    /// since JDK 9, rather than as chars
+    fn compact_strings(&mut self) -> bool {
+        self.instance_slot(STRING, "coder").is_some()
+    }
+
+    /// The `java/lang/String` with the text, the same object for equal texts
+    pub fn intern(&mut self, text: &str) -> Result<Ref> {
+        self.intern_utf16(&text.encode_utf16().collect::<Vec<_>>())
+    }
+
+    /// The `java/lang/String` with the UTF-16 code units, the same object for equal units
+    pub fn intern_utf16(&mut self, units: &[u16]) -> Result<Ref> {
+        if let Some(string) = self.strings.get(units) {
+            return Ok(*string);
+        }
+        let string = self.new_utf16_string(units)?;
+        self.strings.insert(units.to_vec(), string);
+        Ok(string)
+    }
+
+    /// `String.intern`: the string with the text of `string` that was interned before, or
+    /// `string` itself, which is interned now
+    pub fn intern_string(&mut self, string: Ref) -> Ref {
+        match self.utf16(string) {
+            Some(text) => *self.strings.entry(text).or_insert(string),
+            None => string,
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use crate::assembly::assemble;
+use crate::classpath::ClassPath;
+use crate::interpreter::value::Value;
+use crate::interpreter::Interpreter;
+use std::io::Result;
+use std::rc::Rc;
+
+/// Result of `instruction` applied to the arguments, as the only instruction of a static
+/// method with the descriptor that loads its parameters and returns the result
+fn run(instruction: &str, descriptor: &str, arguments: &[Value]) -> Value {
+    let (parameters, result) = descriptor[1..].split_once(')').unwrap();
+    let mut code = String::new();
+    let mut slot = 0;
+    for parameter in parameters.chars() {
+        let (load, size) = match parameter {
+            'J' => ("lload", 2),
+            'F' => ("fload", 1),
+            'D' => ("dload", 2),
+            _ => ("iload", 1),
+        };
+        code.push_str(&format!("        {load} {slot}\n"));
+        slot += size;
+    }
+    let result = match result {
+        "J" => "lreturn",
+        "F" => "freturn",
+        "D" => "dreturn",
+        _ => "ireturn",
+    };
+    let source = format!(
+        ".version 49 0\n\
+         .class super T\n\
+         .super java/lang/Object\n\
+         .method static test{descriptor}\n    \
+         .code stack 4 locals {slot}\n\
+         {code}        {instruction}\n        {result}\n    \
+         .end code\n\
+         .end method\n"
+    );
+    call(&[&source], descriptor, arguments).unwrap().unwrap()
+}
+
+/// Calls the static method `test` of the class `T` with the arguments, the classes being
+/// assembled from the listings
+fn call(listings: &[&str], descriptor: &str, arguments: &[Value]) -> Result<Option<Value>> {
+    let class_path = ClassPath::new();
+    for listing in listings {
+        class_path.define(Rc::new(assemble(listing).unwrap()));
+    }
+    let mut out = vec![];
+    let mut interpreter = Interpreter::new(&class_path, &mut out);
+    let id = interpreter.load_class("T").unwrap();
+    let class = interpreter.classes[id.0].class.clone();
+    let (index, _) = class.method("test", descriptor).unwrap();
+    interpreter.invoke(class, index, arguments.to_vec())
+}
+
+fn int(instruction: &str, descriptor: &str, arguments: &[Value]) -> i32 {
+    run(instruction, descriptor, arguments).as_int().unwrap()
+}
+
+fn long(instruction: &str, descriptor: &str, arguments: &[Value]) -> i64 {
+    match run(instruction, descriptor, arguments) {
+        Value::Long(value) => value,
+        value => panic!("{instruction} returned {value:?}"),
+    }
+}
+
+#[test]
+fn float_comparisons() {
+    use Value::Float as F;
+    assert_eq!(int("fcmpl", "(FF)I", &[F(1.0), F(2.0)]), -1);
+    assert_eq!(int("fcmpg", "(FF)I", &[F(2.0), F(1.0)]), 1);
+    assert_eq!(int("fcmpl", "(FF)I", &[F(0.0), F(-0.0)]), 0);
+    assert_eq!(int("fcmpl", "(FF)I", &[F(f32::NAN), F(1.0)]), -1);
+    assert_eq!(int("fcmpg", "(FF)I", &[F(f32::NAN), F(1.0)]), 1);
+    assert_eq!(int("fcmpl", "(FF)I", &[F(1.0), F(f32::NAN)]), -1);
+    assert_eq!(int("fcmpg", "(FF)I", &[F(f32::NAN), F(f32::NAN)]), 1);
+}
+
+#[test]
+fn double_comparisons() {
+    use Value::Double as D;
+    assert_eq!(int("dcmpl", "(DD)I", &[D(-1.0), D(1.0)]), -1);
+    assert_eq!(int("dcmpg", "(DD)I", &[D(1.0), D(1.0)]), 0);
+    assert_eq!(int("dcmpl", "(DD)I", &[D(f64::NAN), D(1.0)]), -1);
+    assert_eq!(int("dcmpg", "(DD)I", &[D(f64::NAN), D(1.0)]), 1);
+    assert_eq!(int("dcmpl", "(DD)I", &[D(1.0), D(f64::NAN)]), -1);
+    assert_eq!(int("dcmpg", "(DD)I", &[D(1.0), D(f64::NAN)]), 1);
+}
+
+#[test]
+fn long_comparisons() {
+    use Value::Long as L;
+    assert_eq!(int("lcmp", "(JJ)I", &[L(1), L(2)]), -1);
+    assert_eq!(int("lcmp", "(JJ)I", &[L(2), L(2)]), 0);
+    assert_eq!(int("lcmp", "(JJ)I", &[L(i64::MAX), L(i64::MIN)]), 1);
+    assert_eq!(int("lcmp", "(JJ)I", &[L(i64::MIN), L(i64::MAX)]), -1);
+}
+
+#[test]
+fn floating_point_to_integer_conversions() {
+    use Value::{Double as D, Float as F};
+    assert_eq!(int("d2i", "(D)I", &[D(f64::NAN)]), 0);
+    assert_eq!(int("d2i", "(D)I", &[D(1e300)]), i32::MAX);
+    assert_eq!(int("d2i", "(D)I", &[D(-1e300)]), i32::MIN);
+    assert_eq!(int("d2i", "(D)I", &[D(-1.9)]), -1);
+    assert_eq!(int("f2i", "(F)I", &[F(f32::NAN)]), 0);
+    assert_eq!(int("f2i", "(F)I", &[F(f32::INFINITY)]), i32::MAX);
+    assert_eq!(long("f2l", "(F)J", &[F(f32::NAN)]), 0);
+    assert_eq!(long("f2l", "(F)J", &[F(1e30)]), i64::MAX);
+    assert_eq!(long("f2l", "(F)J", &[F(f32::NEG_INFINITY)]), i64::MIN);
+    assert_eq!(long("f2l", "(F)J", &[F(2.5)]), 2);
+    assert_eq!(long("d2l", "(D)J", &[D(f64::NAN)]), 0);
+    assert_eq!(long("d2l", "(D)J", &[D(-1e300)]), i64::MIN);
+}
+
+#[test]
+fn division_overflow() {
+    use Value::{Int as I, Long as L};
+    assert_eq!(int("idiv", "(II)I", &[I(i32::MIN), I(-1)]), i32::MIN);
+    assert_eq!(int("irem", "(II)I", &[I(i32::MIN), I(-1)]), 0);
+    assert_eq!(long("ldiv", "(JJ)J", &[L(i64::MIN), L(-1)]), i64::MIN);
+    assert_eq!(long("lrem", "(JJ)J", &[L(i64::MIN), L(-1)]), 0);
+    assert_eq!(int("idiv", "(II)I", &[I(-7), I(2)]), -3);
+    assert_eq!(int("irem", "(II)I", &[I(-7), I(2)]), -1);
+}
+
+#[test]
+fn shift_distances_are_masked() {
+    use Value::{Int as I, Long as L};
+    assert_eq!(int("ishl", "(II)I", &[I(1), I(33)]), 2);
+    assert_eq!(int("ishl", "(II)I", &[I(1), I(-1)]), i32::MIN);
+    assert_eq!(int("ishr", "(II)I", &[I(-8), I(33)]), -4);
+    assert_eq!(int("iushr", "(II)I", &[I(-1), I(33)]), i32::MAX);
+    assert_eq!(long("lshl", "(JI)J", &[L(1), I(65)]), 2);
+    assert_eq!(long("lshl", "(JI)J", &[L(1), I(-1)]), i64::MIN);
+    assert_eq!(long("lshr", "(JI)J", &[L(-8), I(65)]), -4);
+    assert_eq!(long("lushr", "(JI)J", &[L(-1), I(65)]), i64::MAX);
+}
+
+#[test]
+fn narrowing_int_conversions() {
+    use Value::Int as I;
+    assert_eq!(int("i2b", "(I)B", &[I(200)]), -56);
+    assert_eq!(int("i2b", "(I)B", &[I(0x17f)]), 127);
+    assert_eq!(int("i2c", "(I)C", &[I(-1)]), 0xffff);
+    assert_eq!(int("i2c", "(I)C", &[I(0x1_0041)]), 0x41);
+    assert_eq!(int("i2s", "(I)S", &[I(0x1_8000)]), -0x8000);
+    assert_eq!(int("i2s", "(I)S", &[I(0xffff)]), -1);
+}
+
+#[test]
+fn field_of_another_class() {
+    let point = ".class super P\n.field x I\n";
+    let test = ".class super T\n\
+                .method static test()I\n\
+                .code stack 1 locals 0\n\
+                new T\n\
+                getfield P.x I\n\
+                ireturn\n\
+                .end code\n\
+                .end method\n";
+    let error = call(&[point, test], "()I", &[]).unwrap_err();
+    // Without a JDK, the exception cannot be created and ends the program
+    let message = error.to_string();
+    assert!(
+        message.starts_with("java.lang.VerifyError: field of P"),
+        "{message}"
+    );
+}
+
+#[test]
+fn strings_before_jdk_9_are_arrays_of_chars() {
+    // Without `coder`, as in the `String` of JDK 8
+    let string = ".class public final super java/lang/String\n\
+                  .field private final value [C\n\
+                  .field private hash I\n";
+    let test = ".class super T\n\
+                .method static test()I\n\
+                .code stack 2 locals 0\n\
+                ldc \"h\\u00e9\\u20ac\"\n\
+                getfield java/lang/String.value [C\n\
+                iconst_2\n\
+                caload\n\
+                ireturn\n\
+                .end code\n\
+                .end method\n";
+    let class_path = ClassPath::new();
+    for listing in [string, test] {
+        class_path.define(Rc::new(assemble(listing).unwrap()));
+    }
+    let mut out = vec![];
+    let mut interpreter = Interpreter::new(&class_path, &mut out);
+    interpreter.jdk = true;
+    let id = interpreter.load_class("T").unwrap();
+    let class = interpreter.classes[id.0].class.clone();
+    let (index, _) = class.method("test", "()I").unwrap();
+    let result = interpreter.invoke(class, index, vec![]).unwrap();
+    assert_eq!(result.unwrap().as_int().unwrap(), 0x20ac);
+}
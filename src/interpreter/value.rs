@@ -0,0 +1,85 @@
+use crate::raw::invalid_data;
+use crate::raw::FieldType;
+use std::io::Result;
+
+/// Object on the heap, as its index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Ref(pub usize);
+
+/// Value on the operand stack or in a local variable. Long and double values are one value
+/// on the stack, while in local variables they leave the following variable unused.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    /// Also boolean, byte, char and short
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    /// `None` is null
+    Reference(Option<Ref>),
+    /// Address pushed by `jsr`
+    ReturnAddress(u32),
+}
+
+impl Value {
+    pub const NULL: Value = Value::Reference(None);
+
+    /// Initial value of fields and array elements of the type
+    pub fn default_for(field_type: &FieldType) -> Value {
+        match field_type {
+            FieldType::Long => Value::Long(0),
+            FieldType::Float => Value::Float(0.0),
+            FieldType::Double => Value::Double(0.0),
+            FieldType::Object(_) | FieldType::Array(_) => Value::NULL,
+            _ => Value::Int(0),
+        }
+    }
+
+    /// Slots taken in local variables, and by the operand stack instructions like `pop2`
+    pub fn size(&self) -> usize {
+        match self {
+            Value::Long(_) | Value::Double(_) => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn as_int(self) -> Result<i32> {
+        match self {
+            Value::Int(value) => Ok(value),
+            other => Err(mismatch("int", other)),
+        }
+    }
+
+    pub fn as_long(self) -> Result<i64> {
+        match self {
+            Value::Long(value) => Ok(value),
+            other => Err(mismatch("long", other)),
+        }
+    }
+
+    pub fn as_float(self) -> Result<f32> {
+        match self {
+            Value::Float(value) => Ok(value),
+            other => Err(mismatch("float", other)),
+        }
+    }
+
+    pub fn as_double(self) -> Result<f64> {
+        match self {
+            Value::Double(value) => Ok(value),
+            other => Err(mismatch("double", other)),
+        }
+    }
+
+    pub fn as_reference(self) -> Result<Option<Ref>> {
+        match self {
+            Value::Reference(reference) => Ok(reference),
+            other => Err(mismatch("reference", other)),
+        }
+    }
+}
+
+/// Values of the wrong type are only met in code that does not verify
+fn mismatch(expected: &str, found: Value) -> std::io::Error {
+    invalid_data(format!("expected {expected}, found {found:?}"))
+}
@@ -0,0 +1,294 @@
+//! JAR files: ZIP archives of classes described by `META-INF/MANIFEST.MF`
+
+use crate::raw::class::Class;
+use crate::zip::{ZipArchive, ZipEntry};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::io::Result;
+use std::path::{Path, PathBuf};
+
+pub const MANIFEST: &str = "META-INF/MANIFEST.MF";
+
+/// Directory of multi-release JARs holding classes for release N under `N/`
+pub const VERSIONS: &str = "META-INF/versions/";
+
+/// Versioned directories are only used from Java 9, which introduced them
+const FIRST_VERSIONED_RELEASE: u16 = 9;
+
+pub struct JarFile {
+    path: PathBuf,
+    archive: ZipArchive,
+    manifest: Option<Manifest>,
+}
+
+impl JarFile {
+    pub fn open(path: impl AsRef<Path>) -> Result<JarFile> {
+        let path = path.as_ref();
+        let archive = ZipArchive::open(path)?;
+        let manifest = match archive.entry(MANIFEST) {
+            Some(entry) => Some(Manifest::parse(&String::from_utf8_lossy(
+                &archive.read(entry)?,
+            ))),
+            None => None,
+        };
+        Ok(JarFile {
+            path: path.to_path_buf(),
+            archive,
+            manifest,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn manifest(&self) -> Option<&Manifest> {
+        self.manifest.as_ref()
+    }
+
+    /// All entries including directories, in the order of the archive
+    pub fn entries(&self) -> &[ZipEntry] {
+        self.archive.entries()
+    }
+
+    /// Names of the class file entries, like `com/foo/Bar.class`
+    pub fn class_entries(&self) -> impl Iterator<Item = &str> {
+        self.entries()
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .filter(|name| name.ends_with(".class"))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.archive.entry(name).is_some()
+    }
+
+    /// Content of the entry called `name`
+    pub fn read(&self, name: &str) -> Result<Vec<u8>> {
+        self.archive.read_entry(name)
+    }
+
+    /// Parses the class stored under `name`, given either as an entry name
+    /// (`com/foo/Bar.class`) or as an internal class name (`com/foo/Bar`)
+    pub fn class(&self, name: &str) -> Result<Class> {
+        let bytes = match name.ends_with(".class") {
+            true => self.read(name)?,
+            false => self.read(&format!("{name}.class"))?,
+        };
+        let mut class = Class::from(bytes.as_slice())?;
+        class.resolve_attributes()?;
+        Ok(class)
+    }
+
+    /// Every class of the archive with its entry name, each parsed only when the iterator
+    /// reaches it
+    pub fn classes(&self) -> impl Iterator<Item = (&str, Result<Class>)> {
+        self.class_entries().map(|name| (name, self.class(name)))
+    }
+
+    /// Whether versioned entries are used, which the manifest has to enable
+    pub fn is_multi_release(&self) -> bool {
+        self.manifest().is_some_and(Manifest::multi_release)
+    }
+
+    /// Entry that Java `release` reads for `name`: in multi-release JARs the one in the highest
+    /// `META-INF/versions/N/` with N up to `release`, otherwise `name` itself
+    pub fn versioned_entry<'a>(&'a self, name: &'a str, release: u16) -> &'a str {
+        if !self.is_multi_release() {
+            return name;
+        }
+        (FIRST_VERSIONED_RELEASE..=release)
+            .rev()
+            .find_map(|version| {
+                let entry = self.archive.entry(&format!("{VERSIONS}{version}/{name}"))?;
+                Some(entry.name.as_str())
+            })
+            .unwrap_or(name)
+    }
+
+    /// Class entries that Java `release` sees: base classes replaced by their overrides,
+    /// together with classes that only exist in versioned directories, sorted by class name
+    pub fn release_class_entries(&self, release: u16) -> Vec<&str> {
+        let multi_release = self.is_multi_release();
+        // Chosen version and entry by the name of the class in the base directory
+        let mut chosen: BTreeMap<&str, (u16, &str)> = BTreeMap::new();
+        for entry in self.class_entries() {
+            let (version, name) = match split_versioned(entry) {
+                Some((version, name)) if multi_release && version <= release => (version, name),
+                Some(_) => continue,
+                None => (0, entry),
+            };
+            let current = chosen.entry(name).or_insert((version, entry));
+            if version > current.0 {
+                *current = (version, entry);
+            }
+        }
+        chosen.into_values().map(|(_, entry)| entry).collect()
+    }
+
+    /// Releases that have a versioned directory, in ascending order
+    pub fn versions(&self) -> Vec<u16> {
+        let mut versions: Vec<u16> = self
+            .class_entries()
+            .filter_map(|entry| Some(split_versioned(entry)?.0))
+            .collect();
+        versions.sort_unstable();
+        versions.dedup();
+        versions
+    }
+
+    /// Base entry names of classes with versioned overrides, each with the releases overriding it
+    pub fn overrides(&self) -> Vec<(&str, Vec<u16>)> {
+        let mut overrides: BTreeMap<&str, Vec<u16>> = BTreeMap::new();
+        for entry in self.class_entries() {
+            if let Some((version, name)) = split_versioned(entry) {
+                overrides.entry(name).or_default().push(version);
+            }
+        }
+        overrides
+            .into_iter()
+            .map(|(name, mut versions)| {
+                versions.sort_unstable();
+                (name, versions)
+            })
+            .collect()
+    }
+
+    /// Versioned classes whose major version is not the one of their directory's release.
+    /// Entries that cannot be read are reported too, with `major` set to `None`.
+    pub fn version_mismatches(&self) -> Vec<VersionMismatch> {
+        self.class_entries()
+            .filter_map(|entry| {
+                let (version, _) = split_versioned(entry)?;
+                let major = self
+                    .read(entry)
+                    .ok()
+                    .filter(|bytes| bytes.starts_with(&[0xca, 0xfe, 0xba, 0xbe]))
+                    .and_then(|bytes| Some(u16::from_be_bytes([*bytes.get(6)?, *bytes.get(7)?])));
+                (major != Some(version + 44)).then(|| VersionMismatch {
+                    entry: entry.to_string(),
+                    version,
+                    major,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Release and base entry name of an entry under `META-INF/versions/N/`
+pub fn split_versioned(entry: &str) -> Option<(u16, &str)> {
+    let (version, name) = entry.strip_prefix(VERSIONS)?.split_once('/')?;
+    let version: u16 = version.parse().ok()?;
+    (version >= FIRST_VERSIONED_RELEASE && !name.is_empty()).then_some((version, name))
+}
+
+/// Versioned class compiled for another release than its directory says
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionMismatch {
+    pub entry: String,
+    /// Release of the versioned directory
+    pub version: u16,
+    /// Major version of the class file, `None` if it could not be read
+    pub major: Option<u16>,
+}
+
+impl Display for VersionMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            entry,
+            version,
+            major,
+        } = self;
+        let expected = version + 44;
+        match major {
+            Some(major) if *major > expected => write!(
+                f,
+                "{entry} has major version {major}, newer than {expected} of Java {version}, \
+                 which cannot load it"
+            ),
+            Some(major) => write!(
+                f,
+                "{entry} has major version {major}, expected {expected} for Java {version}"
+            ),
+            None => write!(f, "{entry} is not a readable class file"),
+        }
+    }
+}
+
+/// Parsed manifest. Header names are compared ignoring case, as the JAR specification requires.
+#[derive(Debug, Clone, Default)]
+pub struct Manifest {
+    /// Headers of the main section in the order they appear
+    pub main: Vec<(String, String)>,
+    /// Per-entry sections, named by their `Name` header
+    pub entries: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl Manifest {
+    /// Parses the manifest leniently: lines without `:` are skipped and
+    /// lines starting with a space continue the value of the previous header
+    pub fn parse(text: &str) -> Manifest {
+        let mut sections: Vec<Vec<(String, String)>> = vec![vec![]];
+        let mut previous_blank = false;
+        for line in text.split("\r\n").flat_map(|line| line.split(['\n', '\r'])) {
+            let section = sections.last_mut().expect("there is always a section");
+            if line.is_empty() {
+                previous_blank = true;
+                continue;
+            }
+            if let Some(continuation) = line.strip_prefix(' ') {
+                if let Some((_, value)) = section.last_mut() {
+                    value.push_str(continuation);
+                }
+                continue;
+            }
+            if std::mem::take(&mut previous_blank) && !section.is_empty() {
+                sections.push(vec![]);
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                let value = value.strip_prefix(' ').unwrap_or(value);
+                let section = sections.last_mut().expect("there is always a section");
+                section.push((name.to_string(), value.to_string()));
+            }
+        }
+
+        let mut sections = sections.into_iter();
+        let main = sections.next().unwrap_or_default();
+        let entries = sections
+            .filter_map(|section| {
+                let name = header(&section, "Name")?.to_string();
+                Some((name, section))
+            })
+            .collect();
+        Manifest { main, entries }
+    }
+
+    /// Value of the main section header `name`
+    pub fn get(&self, name: &str) -> Option<&str> {
+        header(&self.main, name)
+    }
+
+    /// Class launched by `java -jar`, in binary form like `com.foo.Main`
+    pub fn main_class(&self) -> Option<&str> {
+        self.get("Main-Class").map(str::trim)
+    }
+
+    /// Relative URLs of the JARs and directories this one depends on
+    pub fn class_path(&self) -> Vec<&str> {
+        self.get("Class-Path")
+            .map(|value| value.split_whitespace().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn multi_release(&self) -> bool {
+        self.get("Multi-Release")
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"))
+    }
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
@@ -0,0 +1,1515 @@
+//! Disassembly in the layout of `javap -c -v -p`, close enough to diff against it
+
+use crate::raw::class::Class;
+use crate::raw::*;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Class file that [`Class`] was read from, `javap` prints its path, size, modification date
+/// and checksum in the header
+pub struct ClassFile<'a> {
+    pub path: &'a Path,
+    pub bytes: &'a [u8],
+    pub modified: Option<SystemTime>,
+}
+
+pub fn javap<W: std::io::Write>(
+    out: &mut W,
+    class: &Class,
+    file: Option<&ClassFile>,
+) -> std::io::Result<()> {
+    let mut javap = Javap::new(class);
+
+    if let Some(file) = file {
+        javap.file_header(file);
+    }
+    javap.class_header();
+    javap.constant_pool();
+
+    javap.out.println("{");
+    javap.out.indent(1);
+    // javap ends every field with an empty line, but only separates the methods
+    for field in &class.fields {
+        javap.field(field);
+        javap.out.newline();
+    }
+    for (i, method) in class.methods.iter().enumerate() {
+        if i > 0 {
+            javap.out.newline();
+        }
+        javap.method(method);
+    }
+    javap.out.indent(-1);
+    javap.out.println("}");
+
+    for attribute in &class.attributes {
+        javap.attribute(attribute, None);
+    }
+
+    out.write_all(javap.out.output.as_bytes())
+}
+
+/// Only the "Constant pool:" section of the `javap -v` output
+pub fn constant_pool<W: std::io::Write>(out: &mut W, class: &Class) -> std::io::Result<()> {
+    let mut javap = Javap::new(class);
+    javap.constant_pool();
+    out.write_all(javap.out.output.as_bytes())
+}
+
+/// Constant as shown in the code comments, like `Method java/io/PrintStream.print:(I)V`
+pub fn constant_comment(class: &Class, index: u16) -> String {
+    Javap::new(class).tagged_constant(index)
+}
+
+/// Line writer behaving like the one inside `javap`: spaces are delayed until something
+/// is printed after them, so lines never end with spaces, and `tab` aligns comments
+#[derive(Default)]
+struct Printer {
+    output: String,
+    buffer: String,
+    pending_spaces: usize,
+    indent: usize,
+}
+
+impl Printer {
+    const INDENT_WIDTH: usize = 2;
+    const TAB_COLUMN: usize = 40;
+
+    fn print(&mut self, text: &str) {
+        for c in text.chars() {
+            match c {
+                ' ' => self.pending_spaces += 1,
+                '\n' => self.newline(),
+                _ => {
+                    if self.buffer.is_empty() {
+                        self.pending_spaces += self.indent * Self::INDENT_WIDTH;
+                    }
+                    self.buffer
+                        .extend(std::iter::repeat_n(' ', self.pending_spaces));
+                    self.pending_spaces = 0;
+                    self.buffer.push(c);
+                }
+            }
+        }
+    }
+
+    fn println(&mut self, text: &str) {
+        self.print(text);
+        self.newline();
+    }
+
+    fn newline(&mut self) {
+        self.pending_spaces = 0;
+        self.output.push_str(&self.buffer);
+        self.output.push('\n');
+        self.buffer.clear();
+    }
+
+    fn tab(&mut self) {
+        let column = self.indent * Self::INDENT_WIDTH + Self::TAB_COLUMN;
+        let length = self.buffer.chars().count();
+        self.pending_spaces += if column <= length { 1 } else { column - length };
+    }
+
+    fn indent(&mut self, delta: isize) {
+        self.indent = self.indent.saturating_add_signed(delta);
+    }
+}
+
+struct Javap<'a> {
+    class: &'a Class,
+    pool: &'a [ConstantPoolInfo],
+    out: Printer,
+}
+
+impl<'a> Javap<'a> {
+    fn new(class: &'a Class) -> Self {
+        Javap {
+            class,
+            pool: &class.constant_pool,
+            out: Printer::default(),
+        }
+    }
+
+    fn file_header(&mut self, file: &ClassFile) {
+        let path = std::fs::canonicalize(file.path).unwrap_or_else(|_| file.path.to_path_buf());
+        self.out.println(&format!("Classfile {}", path.display()));
+        self.out.indent(1);
+        let size = file.bytes.len();
+        match file.modified {
+            Some(modified) => self.out.println(&format!(
+                "Last modified {}; size {size} bytes",
+                format_date(modified)
+            )),
+            None => self.out.println(&format!("size {size} bytes")),
+        }
+        let checksum: String = sha256(file.bytes)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        self.out.println(&format!("SHA-256 checksum {checksum}"));
+        for attribute in &self.class.attributes {
+            if let AttributeInfo::SourceFile(source) = &attribute.info {
+                self.out.println(&format!("Compiled from \"{source}\""));
+            }
+        }
+        self.out.indent(-1);
+    }
+
+    fn class_header(&mut self) {
+        let class = self.class;
+        let flags = class.access_info;
+        let is_interface = flags.contains(AccessFlags::Interface);
+
+        let mut modifiers = vec![];
+        if flags.contains(AccessFlags::Public) {
+            modifiers.push("public");
+        }
+        if flags.contains(AccessFlags::Final) {
+            modifiers.push("final");
+        }
+        if flags.contains(AccessFlags::Abstract) && !is_interface {
+            modifiers.push("abstract");
+        }
+        self.modifiers(&modifiers);
+
+        if flags.contains(AccessFlags::Module) {
+            self.out.print("module ");
+        } else if is_interface {
+            self.out.print("interface ");
+        } else {
+            self.out.print("class ");
+        }
+        let this_class = class_name_at(self.pool, class.this_class).unwrap_or("?");
+        self.out.print(&this_class.replace('/', "."));
+
+        let signature = class.attributes.iter().find_map(|a| match &a.info {
+            AttributeInfo::Signature(signature) => Some(signature.as_str()),
+            _ => None,
+        });
+        match signature.and_then(|s| Signature::new(s).class_signature()) {
+            Some((type_parameters, superclass, interfaces)) => {
+                self.out.print(&type_parameters);
+                if !is_interface {
+                    self.out.print(&format!(" extends {superclass}"));
+                }
+                if !interfaces.is_empty() {
+                    let keyword = if is_interface {
+                        "extends"
+                    } else {
+                        "implements"
+                    };
+                    self.out
+                        .print(&format!(" {keyword} {}", interfaces.join(", ")));
+                }
+            }
+            None => {
+                if let Some(superclass) = class_name_at(self.pool, class.super_class) {
+                    if !is_interface && superclass != "java/lang/Object" {
+                        self.out
+                            .print(&format!(" extends {}", superclass.replace('/', ".")));
+                    }
+                }
+                for (i, interface) in class.interfaces.iter().enumerate() {
+                    let keyword = match (i, is_interface) {
+                        (0, true) => " extends ",
+                        (0, false) => " implements ",
+                        _ => ",",
+                    };
+                    let name = class_name_at(self.pool, *interface).unwrap_or("?");
+                    self.out
+                        .print(&format!("{keyword}{}", name.replace('/', ".")));
+                }
+            }
+        }
+        self.out.newline();
+
+        self.out.indent(1);
+        self.out.println(&format!("minor version: {}", class.minor));
+        self.out.println(&format!("major version: {}", class.major));
+        self.flags(flags, FlagsOwner::Class);
+        self.out
+            .print(&format!("this_class: #{}", class.this_class));
+        self.out.tab();
+        self.out
+            .println(&format!("// {}", self.constant_value(class.this_class)));
+        self.out
+            .print(&format!("super_class: #{}", class.super_class));
+        if class.super_class != 0 {
+            self.out.tab();
+            self.out
+                .print(&format!("// {}", self.constant_value(class.super_class)));
+        }
+        self.out.newline();
+        self.out.println(&format!(
+            "interfaces: {}, fields: {}, methods: {}, attributes: {}",
+            class.interfaces.len(),
+            class.fields.len(),
+            class.methods.len(),
+            class.attributes.len()
+        ));
+        self.out.indent(-1);
+    }
+
+    fn constant_pool(&mut self) {
+        self.out.println("Constant pool:");
+        self.out.indent(1);
+        let width = (self.pool.len() + 1).to_string().len() + 1;
+        for (i, constant) in self.pool.iter().enumerate() {
+            if let ConstantPoolInfo::Unusable = constant {
+                continue;
+            }
+            let index = format!("#{}", i + 1);
+            self.out
+                .print(&format!("{index:>width$} = {:<18} ", constant.tag_name()));
+
+            match constant {
+                ConstantPoolInfo::Class { name_index }
+                | ConstantPoolInfo::Module { name_index }
+                | ConstantPoolInfo::Package { name_index } => {
+                    self.out.print(&format!("#{name_index}"));
+                    self.comment((i + 1) as u16);
+                }
+                ConstantPoolInfo::String { string_index } => {
+                    self.out.print(&format!("#{string_index}"));
+                    self.comment((i + 1) as u16);
+                }
+                ConstantPoolInfo::MethodType { descriptor_index } => {
+                    self.out.print(&format!("#{descriptor_index}"));
+                    self.out.tab();
+                    self.out
+                        .print(&format!("//  {}", self.constant_value((i + 1) as u16)));
+                }
+                ConstantPoolInfo::FieldRef {
+                    class_index,
+                    name_and_type_index,
+                }
+                | ConstantPoolInfo::MethodRef {
+                    class_index,
+                    name_and_type_index,
+                }
+                | ConstantPoolInfo::InterfaceMethodRef {
+                    class_index,
+                    name_and_type_index,
+                } => {
+                    self.out
+                        .print(&format!("#{class_index}.#{name_and_type_index}"));
+                    self.comment((i + 1) as u16);
+                }
+                ConstantPoolInfo::NameAndType {
+                    name_index,
+                    descriptor_index,
+                } => {
+                    self.out
+                        .print(&format!("#{name_index}:#{descriptor_index}"));
+                    self.comment((i + 1) as u16);
+                }
+                ConstantPoolInfo::Dynamic {
+                    bootstrap_method_attr_index,
+                    name_and_type_index,
+                }
+                | ConstantPoolInfo::InvokeDynamic {
+                    bootstrap_method_attr_index,
+                    name_and_type_index,
+                } => {
+                    self.out.print(&format!(
+                        "#{bootstrap_method_attr_index}:#{name_and_type_index}"
+                    ));
+                    self.comment((i + 1) as u16);
+                }
+                ConstantPoolInfo::MethodHandle {
+                    reference_kind,
+                    reference_index,
+                } => {
+                    self.out
+                        .print(&format!("{reference_kind}:#{reference_index}"));
+                    self.comment((i + 1) as u16);
+                }
+                _ => self.out.print(&self.constant_value((i + 1) as u16)),
+            }
+            self.out.newline();
+        }
+        self.out.indent(-1);
+    }
+
+    fn comment(&mut self, index: u16) {
+        self.out.tab();
+        self.out
+            .print(&format!("// {}", self.constant_value(index)));
+    }
+
+    fn field(&mut self, field: &FieldInfo) {
+        let flags = field.access_flags;
+        let mut modifiers = member_modifiers(flags);
+        if flags.contains(AccessFlags::Volatile) {
+            modifiers.push("volatile");
+        }
+        if flags.contains(AccessFlags::Transient) {
+            modifiers.push("transient");
+        }
+        self.modifiers(&modifiers);
+
+        let descriptor = utf8_at(self.pool, field.descriptor_index).unwrap_or("?");
+        let signature = signature(&field.attributes);
+        let field_type = signature
+            .and_then(|s| Signature::new(s).complete(Signature::type_signature))
+            .or_else(|| FieldType::parse(descriptor).map(|t| t.java_name()))
+            .unwrap_or_else(|| descriptor.to_string());
+        let name = utf8_at(self.pool, field.name_index).unwrap_or("?");
+        self.out.println(&format!("{field_type} {name};"));
+
+        self.out.indent(1);
+        self.out.println(&format!("descriptor: {descriptor}"));
+        self.flags(flags, FlagsOwner::Field);
+        for attribute in &field.attributes {
+            self.attribute(attribute, None);
+        }
+        self.out.indent(-1);
+    }
+
+    fn method(&mut self, method: &MethodInfo) {
+        let flags = method.access_flags;
+        let name = utf8_at(self.pool, method.name_index).unwrap_or("?");
+        let descriptor = utf8_at(self.pool, method.descriptor_index).unwrap_or("?");
+
+        let mut modifiers = member_modifiers(flags);
+        for (flag, modifier) in [
+            (AccessFlags::Synchronized, "synchronized"),
+            (AccessFlags::Native, "native"),
+            (AccessFlags::Abstract, "abstract"),
+            (AccessFlags::Strict, "strictfp"),
+        ] {
+            if flags.contains(flag) {
+                modifiers.push(modifier);
+            }
+        }
+        if self.class.access_info.contains(AccessFlags::Interface)
+            && !flags.intersects(AccessFlags::Abstract | AccessFlags::Static | AccessFlags::Private)
+            && name != "<clinit>"
+            && self.class.major >= 52
+        {
+            modifiers.push("default");
+        }
+        self.modifiers(&modifiers);
+
+        let parsed = signature(&method.attributes)
+            .and_then(|s| Signature::new(s).method_signature())
+            .or_else(|| {
+                let descriptor = MethodDescriptor::parse(descriptor)?;
+                let parameters = descriptor
+                    .parameters
+                    .iter()
+                    .map(FieldType::java_name)
+                    .collect();
+                let return_type = descriptor
+                    .return_type
+                    .map(|t| t.java_name())
+                    .unwrap_or_else(|| "void".to_string());
+                Some((String::new(), parameters, return_type))
+            });
+
+        let Some((type_parameters, mut parameters, return_type)) = parsed else {
+            self.out.println(&format!("{name}{descriptor};"));
+            return;
+        };
+        if flags.contains(AccessFlags::Varargs) {
+            if let Some(last) = parameters.last_mut() {
+                if let Some(element) = last.strip_suffix("[]") {
+                    *last = format!("{element}...");
+                }
+            }
+        }
+        if !type_parameters.is_empty() {
+            self.out.print(&format!("{type_parameters} "));
+        }
+        let parameters = parameters.join(", ");
+        match name {
+            "<init>" => {
+                let class_name = class_name_at(self.pool, self.class.this_class).unwrap_or("?");
+                self.out
+                    .print(&format!("{}({parameters})", class_name.replace('/', ".")));
+            }
+            "<clinit>" => self.out.print("{}"),
+            _ => self
+                .out
+                .print(&format!("{return_type} {name}({parameters})")),
+        }
+        for attribute in &method.attributes {
+            if let AttributeInfo::Exceptions(exceptions) = &attribute.info {
+                let names: Vec<String> = exceptions
+                    .iter()
+                    .map(|e| {
+                        class_name_at(self.pool, *e)
+                            .unwrap_or("?")
+                            .replace('/', ".")
+                    })
+                    .collect();
+                self.out.print(&format!(" throws {}", names.join(", ")));
+            }
+        }
+        self.out.println(";");
+
+        self.out.indent(1);
+        self.out.println(&format!("descriptor: {descriptor}"));
+        self.flags(flags, FlagsOwner::Method);
+        for attribute in &method.attributes {
+            self.attribute(attribute, Some(method));
+        }
+        self.out.indent(-1);
+    }
+
+    fn modifiers(&mut self, modifiers: &[&str]) {
+        for modifier in modifiers {
+            self.out.print(&format!("{modifier} "));
+        }
+    }
+
+    fn flags(&mut self, flags: AccessFlags, owner: FlagsOwner) {
+        let names = flags.javap_names(owner).join(", ");
+        self.out
+            .println(&format!("flags: ({:#06x}) {names}", flags.bits()));
+    }
+
+    fn attribute(&mut self, attribute: &Attribute, method: Option<&MethodInfo>) {
+        let name = utf8_at(self.pool, attribute.name_index).unwrap_or("?");
+        match &attribute.info {
+            AttributeInfo::Code {
+                max_stack,
+                max_locals,
+                code,
+                exception_table,
+                attributes,
+            } => {
+                self.out.println("Code:");
+                self.out.indent(1);
+                let args_size = method
+                    .map(|method| {
+                        // javap counts parameters rather than slots they occupy
+                        let descriptor = utf8_at(self.pool, method.descriptor_index)
+                            .and_then(MethodDescriptor::parse)
+                            .map(|d| d.parameters.len())
+                            .unwrap_or(0);
+                        let this = !method.access_flags.contains(AccessFlags::Static);
+                        descriptor + this as usize
+                    })
+                    .unwrap_or(0);
+                self.out.println(&format!(
+                    "stack={max_stack}, locals={max_locals}, args_size={args_size}"
+                ));
+                for (offset, instruction) in code {
+                    self.instruction(*offset, instruction);
+                }
+                if !exception_table.is_empty() {
+                    self.out.println("Exception table:");
+                    self.out.indent(1);
+                    self.out.println(" from    to  target type");
+                    for exception in exception_table {
+                        self.out.print(&format!(
+                            " {:5} {:5} {:5}   ",
+                            exception.start_pc, exception.end_pc, exception.handler_pc
+                        ));
+                        match exception.catch_type {
+                            0 => self.out.println("any"),
+                            catch_type => {
+                                let name = self.constant_value(catch_type);
+                                self.out.println(&format!("Class {name}"));
+                            }
+                        }
+                    }
+                    self.out.indent(-1);
+                }
+                for attribute in attributes {
+                    self.attribute(attribute, None);
+                }
+                self.out.indent(-1);
+            }
+            AttributeInfo::LineNumberTable(lines) => {
+                self.out.println("LineNumberTable:");
+                self.out.indent(1);
+                for line in lines {
+                    self.out
+                        .println(&format!("line {}: {}", line.line_number, line.start_pc));
+                }
+                self.out.indent(-1);
+            }
+            AttributeInfo::LocalVariableTable(variables)
+            | AttributeInfo::LocalVariableTypeTable(variables) => {
+                self.out.println(&format!("{name}:"));
+                self.out.indent(1);
+                self.out.println("Start  Length  Slot  Name   Signature");
+                for variable in variables {
+                    self.out.println(&format!(
+                        "{:5} {:7} {:5} {:>5}   {}",
+                        variable.start_pc,
+                        variable.length,
+                        variable.index,
+                        utf8_at(self.pool, variable.name_index).unwrap_or("?"),
+                        utf8_at(self.pool, variable.descriptor_index).unwrap_or("?"),
+                    ));
+                }
+                self.out.indent(-1);
+            }
+            AttributeInfo::StackMapTable(frames) => {
+                self.out.println(&format!(
+                    "StackMapTable: number_of_entries = {}",
+                    frames.len()
+                ));
+                self.out.indent(1);
+                for frame in frames {
+                    self.stack_map_frame(frame);
+                }
+                self.out.indent(-1);
+            }
+            AttributeInfo::ConstantValue(index) => {
+                let value = self.tagged_constant(*index);
+                self.out.println(&format!("ConstantValue: {value}"));
+            }
+            AttributeInfo::Exceptions(exceptions) => {
+                self.out.println("Exceptions:");
+                self.out.indent(1);
+                let names: Vec<String> = exceptions
+                    .iter()
+                    .map(|e| {
+                        class_name_at(self.pool, *e)
+                            .unwrap_or("?")
+                            .replace('/', ".")
+                    })
+                    .collect();
+                self.out.println(&format!("throws {}", names.join(", ")));
+                self.out.indent(-1);
+            }
+            AttributeInfo::Signature(signature) => {
+                let index = self
+                    .pool
+                    .iter()
+                    .position(|c| matches!(c, ConstantPoolInfo::Utf8(s) if s == signature))
+                    .map(|i| i + 1)
+                    .unwrap_or(0);
+                self.out.print(&format!("Signature: #{index}"));
+                self.out.tab();
+                self.out.println(&format!("// {signature}"));
+            }
+            AttributeInfo::SourceFile(source) => {
+                self.out.println(&format!("SourceFile: \"{source}\""));
+            }
+            AttributeInfo::NestHost(index) => {
+                let value = self.tagged_constant(*index);
+                self.out.println(&format!("NestHost: {value}"));
+            }
+            AttributeInfo::NestMembers(classes) | AttributeInfo::PermittedSubclasses(classes) => {
+                self.out.println(&format!("{name}:"));
+                self.out.indent(1);
+                for class in classes {
+                    let value = self.constant_value(*class);
+                    self.out.println(&value);
+                }
+                self.out.indent(-1);
+            }
+            AttributeInfo::BootstrapMethods(methods) => {
+                self.out.println("BootstrapMethods:");
+                self.out.indent(1);
+                for (i, method) in methods.iter().enumerate() {
+                    let handle = self.constant_value(method.bootstrap_method_ref);
+                    self.out
+                        .println(&format!("{i}: #{} {handle}", method.bootstrap_method_ref));
+                    self.out.indent(1);
+                    self.out.println("Method arguments:");
+                    self.out.indent(1);
+                    for argument in &method.bootstrap_arguments {
+                        let value = self.constant_value(*argument);
+                        self.out.println(&format!("#{argument} {value}"));
+                    }
+                    self.out.indent(-2);
+                }
+                self.out.indent(-1);
+            }
+            AttributeInfo::InnerClasses(classes) => {
+                self.out.println("InnerClasses:");
+                self.out.indent(1);
+                for inner in classes {
+                    let flags = inner.inner_class_access_flags;
+                    let mut modifiers = member_modifiers(flags);
+                    if flags.contains(AccessFlags::Abstract)
+                        && !flags.contains(AccessFlags::Interface)
+                    {
+                        modifiers.push("abstract");
+                    }
+                    self.modifiers(&modifiers);
+                    if inner.inner_name_index != 0 {
+                        self.out.print(&format!("#{}= ", inner.inner_name_index));
+                    }
+                    self.out
+                        .print(&format!("#{}", inner.inner_class_info_index));
+                    if inner.outer_class_info_index != 0 {
+                        self.out
+                            .print(&format!(" of #{}", inner.outer_class_info_index));
+                    }
+                    self.out.print(";");
+                    self.out.tab();
+                    self.out.print("// ");
+                    if inner.inner_name_index != 0 {
+                        let name = utf8_at(self.pool, inner.inner_name_index).unwrap_or("?");
+                        self.out.print(&format!("{name}="));
+                    }
+                    let inner_class = self.tagged_constant(inner.inner_class_info_index);
+                    self.out.print(&inner_class);
+                    if inner.outer_class_info_index != 0 {
+                        let outer_class = self.tagged_constant(inner.outer_class_info_index);
+                        self.out.print(&format!(" of {outer_class}"));
+                    }
+                    self.out.newline();
+                }
+                self.out.indent(-1);
+            }
+            AttributeInfo::EnclosingMethod {
+                class_index,
+                method_index,
+            } => {
+                self.out
+                    .print(&format!("EnclosingMethod: #{class_index}.#{method_index}"));
+                self.out.tab();
+                let class_name = class_name_at(self.pool, *class_index).unwrap_or("?");
+                self.out
+                    .print(&format!("// {}", class_name.replace('/', ".")));
+                if let Some((name, _)) = name_and_type_at(self.pool, *method_index) {
+                    self.out.print(&format!(".{}", check_name(name)));
+                }
+                self.out.newline();
+            }
+            AttributeInfo::RuntimeVisibleAnnotations(annotations)
+            | AttributeInfo::RuntimeInvisibleAnnotations(annotations) => {
+                self.out.println(&format!("{name}:"));
+                self.out.indent(1);
+                for (i, annotation) in annotations.iter().enumerate() {
+                    self.annotation(i, annotation);
+                }
+                self.out.indent(-1);
+            }
+            AttributeInfo::RuntimeVisibleParameterAnnotations(parameters)
+            | AttributeInfo::RuntimeInvisibleParameterAnnotations(parameters) => {
+                self.out.println(&format!("{name}:"));
+                self.out.indent(1);
+                for (parameter, annotations) in parameters.iter().enumerate() {
+                    self.out.println(&format!("parameter {parameter}:"));
+                    self.out.indent(1);
+                    for (i, annotation) in annotations.iter().enumerate() {
+                        self.annotation(i, annotation);
+                    }
+                    self.out.indent(-1);
+                }
+                self.out.indent(-1);
+            }
+            AttributeInfo::AnnotationDefault(value) => {
+                self.out.println("AnnotationDefault:");
+                self.out.indent(1);
+                self.out
+                    .println(&format!("default_value: {}", element_value_indexes(value)));
+                self.out.indent(1);
+                let value = self.element_value(value);
+                self.out.println(&value);
+                self.out.indent(-2);
+            }
+            AttributeInfo::Deprecated => self.out.println("Deprecated: true"),
+            AttributeInfo::Synthetic => self.out.println("Synthetic: true"),
+            AttributeInfo::Uncrecognized(bytes) => {
+                self.out.println(&format!(
+                    "{name}: length = {:#x} (unknown attribute)",
+                    bytes.len()
+                ));
+                for chunk in bytes.chunks(16) {
+                    let hex: Vec<String> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+                    self.out.println(&format!("   {}", hex.join(" ")));
+                }
+            }
+        }
+    }
+
+    fn annotation(&mut self, i: usize, annotation: &Annotation) {
+        self.out
+            .println(&format!("{i}: {}", annotation_indexes(annotation)));
+        self.out.indent(1);
+        let type_name = self.descriptor_java_name(annotation.type_index);
+        if annotation.element_value_pairs.is_empty() {
+            self.out.println(&type_name);
+        } else {
+            self.out.println(&format!("{type_name}("));
+            self.out.indent(1);
+            for (name_index, value) in &annotation.element_value_pairs {
+                let name = utf8_at(self.pool, *name_index).unwrap_or("?");
+                let value = self.element_value(value);
+                self.out.println(&format!("{name}={value}"));
+            }
+            self.out.indent(-1);
+            self.out.println(")");
+        }
+        self.out.indent(-1);
+    }
+
+    /// Java name of the type described by the Utf8 descriptor under `index`
+    fn descriptor_java_name(&self, index: u16) -> String {
+        let descriptor = utf8_at(self.pool, index).unwrap_or("?");
+        FieldType::parse(descriptor)
+            .map(|t| t.java_name())
+            .unwrap_or_else(|| descriptor.to_string())
+    }
+
+    fn element_value(&self, value: &ElementValue) -> String {
+        match value {
+            ElementValue::Const {
+                tag,
+                const_value_index,
+            } => {
+                let constant = constant(self.pool, *const_value_index);
+                match (tag, constant) {
+                    (b's', Some(ConstantPoolInfo::Utf8(text))) => format!("\"{}\"", escape(text)),
+                    (b'Z', Some(ConstantPoolInfo::Integer(value))) => (*value != 0).to_string(),
+                    (b'C', Some(ConstantPoolInfo::Integer(value))) => {
+                        let c = char::from_u32(*value as u32).unwrap_or('?');
+                        format!("'{}'", escape(&c.to_string()))
+                    }
+                    _ => self.constant_value(*const_value_index),
+                }
+            }
+            ElementValue::Enum {
+                type_name_index,
+                const_name_index,
+            } => format!(
+                "{}.{}",
+                utf8_at(self.pool, *type_name_index).unwrap_or("?"),
+                utf8_at(self.pool, *const_name_index).unwrap_or("?")
+            ),
+            ElementValue::Class(index) => {
+                format!("class {}", utf8_at(self.pool, *index).unwrap_or("?"))
+            }
+            ElementValue::Annotation(annotation) => {
+                let type_name = self.descriptor_java_name(annotation.type_index);
+                if annotation.element_value_pairs.is_empty() {
+                    format!("@{type_name}")
+                } else {
+                    let pairs: Vec<String> = annotation
+                        .element_value_pairs
+                        .iter()
+                        .map(|(name, value)| {
+                            let name = utf8_at(self.pool, *name).unwrap_or("?");
+                            format!("{name}={}", self.element_value(value))
+                        })
+                        .collect();
+                    format!("@{type_name}({})", pairs.join(","))
+                }
+            }
+            ElementValue::Array(values) => {
+                let values: Vec<String> = values.iter().map(|v| self.element_value(v)).collect();
+                format!("[{}]", values.join(","))
+            }
+        }
+    }
+
+    fn stack_map_frame(&mut self, frame: &StackMapFrame) {
+        let kind = match frame.frame_type {
+            0..=63 => "same",
+            64..=127 => "same_locals_1_stack_item",
+            247 => "same_locals_1_stack_item_frame_extended",
+            248..=250 => "chop",
+            251 => "same_frame_extended",
+            252..=254 => "append",
+            _ => "full_frame",
+        };
+        self.out
+            .println(&format!("frame_type = {} /* {kind} */", frame.frame_type));
+        self.out.indent(1);
+        if frame.frame_type >= 247 {
+            self.out
+                .println(&format!("offset_delta = {}", frame.offset_delta));
+        }
+        if frame.frame_type >= 252 {
+            self.verification_types("locals", &frame.locals);
+        }
+        if (64..=127).contains(&frame.frame_type)
+            || frame.frame_type == 247
+            || frame.frame_type == 255
+        {
+            self.verification_types("stack", &frame.stack);
+        }
+        self.out.indent(-1);
+    }
+
+    fn verification_types(&mut self, name: &str, types: &[VerificationType]) {
+        let types: Vec<String> = types
+            .iter()
+            .map(|t| match t {
+                VerificationType::Top => "top".to_string(),
+                VerificationType::Integer => "int".to_string(),
+                VerificationType::Float => "float".to_string(),
+                VerificationType::Double => "double".to_string(),
+                VerificationType::Long => "long".to_string(),
+                VerificationType::Null => "null".to_string(),
+                VerificationType::UninitializedThis => "this".to_string(),
+                VerificationType::Object(index) => self.tagged_constant(*index),
+                VerificationType::Uninitialized(offset) => format!("uninitialized {offset}"),
+            })
+            .collect();
+        if types.is_empty() {
+            self.out.println(&format!("{name} = []"));
+        } else {
+            self.out
+                .println(&format!("{name} = [ {} ]", types.join(", ")));
+        }
+    }
+
+    fn instruction(&mut self, pc: u32, instruction: &Instruction) {
+        let mnemonic = match instruction {
+            Instruction::Wide(wide) => format!("{}_w", wide.mnemonic()),
+            _ => instruction.mnemonic().to_string(),
+        };
+        self.out.print(&format!("{pc:4}: {mnemonic:<13} "));
+
+        let target = |offset: i32| pc as i64 + offset as i64;
+        match instruction {
+            Instruction::BiPush(value) => self.out.print(&value.to_string()),
+            Instruction::SiPush(value) => self.out.print(&value.to_string()),
+            Instruction::ILoad(index)
+            | Instruction::LLoad(index)
+            | Instruction::FLoad(index)
+            | Instruction::DLoad(index)
+            | Instruction::ALoad(index)
+            | Instruction::IStore(index)
+            | Instruction::LStore(index)
+            | Instruction::FStore(index)
+            | Instruction::DStore(index)
+            | Instruction::AStore(index)
+            | Instruction::Ret(index) => self.out.print(&index.to_string()),
+            Instruction::IInc(index, value) => self.out.print(&format!("{index}, {value}")),
+            Instruction::Wide(Wide::IInc(index, value)) => {
+                self.out.print(&format!("{index}, {value}"))
+            }
+            Instruction::Wide(wide) => self.out.print(&wide.index().to_string()),
+            Instruction::NewArray(atype) => {
+                let name = array_type_name(*atype).unwrap_or("?");
+                self.out.print(&format!(" {name}"));
+            }
+            Instruction::IfEq(offset)
+            | Instruction::IfNe(offset)
+            | Instruction::IfLt(offset)
+            | Instruction::IfGe(offset)
+            | Instruction::IfGt(offset)
+            | Instruction::IfLe(offset)
+            | Instruction::IfICmpEq(offset)
+            | Instruction::IfICmpNe(offset)
+            | Instruction::IfICmpLt(offset)
+            | Instruction::IfICmpGe(offset)
+            | Instruction::IfICmpGt(offset)
+            | Instruction::IfICmpLe(offset)
+            | Instruction::IfACmpEq(offset)
+            | Instruction::IfACmpNe(offset)
+            | Instruction::IfNull(offset)
+            | Instruction::IfNonNull(offset)
+            | Instruction::Goto(offset)
+            | Instruction::Jsr(offset) => self.out.print(&target(*offset as i32).to_string()),
+            Instruction::GotoW(offset) | Instruction::JsrW(offset) => {
+                self.out.print(&target(*offset).to_string())
+            }
+            Instruction::TableSwitch {
+                default,
+                low,
+                high,
+                offsets,
+            } => {
+                self.out.print(&format!("{{ // {low} to {high}"));
+                self.out.indent(3);
+                for (i, offset) in offsets.iter().enumerate() {
+                    self.out.newline();
+                    let key = *low as i64 + i as i64;
+                    self.out.print(&format!("{key:12}: {}", target(*offset)));
+                }
+                self.out.newline();
+                self.out
+                    .print(&format!("{:>12}: {}", "default", target(*default)));
+                self.out.newline();
+                self.out.print("}");
+                self.out.indent(-3);
+            }
+            Instruction::LookupSwitch { default, pairs } => {
+                self.out.print(&format!("{{ // {}", pairs.len()));
+                self.out.indent(3);
+                for (key, offset) in pairs {
+                    self.out.newline();
+                    self.out.print(&format!("{key:12}: {}", target(*offset)));
+                }
+                self.out.newline();
+                self.out
+                    .print(&format!("{:>12}: {}", "default", target(*default)));
+                self.out.newline();
+                self.out.print("}");
+                self.out.indent(-3);
+            }
+            Instruction::InvokeInterface(index, count) => {
+                self.out.print(&format!("#{index},  {count}"));
+                self.code_comment(*index);
+            }
+            Instruction::InvokeDynamic(index) => {
+                self.out.print(&format!("#{index},  0"));
+                self.code_comment(*index);
+            }
+            Instruction::MultiANewArray(index, dimensions) => {
+                self.out.print(&format!("#{index},  {dimensions}"));
+                self.code_comment(*index);
+            }
+            _ => {
+                if let Some(index) = instruction.constant_index() {
+                    self.out.print(&format!("#{index}"));
+                    self.code_comment(index);
+                }
+            }
+        }
+        self.out.newline();
+    }
+
+    fn code_comment(&mut self, index: u16) {
+        self.out.tab();
+        let value = self.tagged_constant(index);
+        self.out.print(&format!("// {value}"));
+    }
+
+    /// Constant prefixed with its kind, like `Method java/lang/Object."<init>":()V`.
+    /// Members of this class are printed without the class name.
+    fn tagged_constant(&self, index: u16) -> String {
+        let Some(constant) = constant(self.pool, index) else {
+            return format!("#{index}");
+        };
+        let tag = match constant {
+            ConstantPoolInfo::Class { .. } => "class",
+            ConstantPoolInfo::Double(_) => "double",
+            ConstantPoolInfo::FieldRef { .. } => "Field",
+            ConstantPoolInfo::Float(_) => "float",
+            ConstantPoolInfo::Integer(_) => "int",
+            ConstantPoolInfo::InterfaceMethodRef { .. } => "InterfaceMethod",
+            ConstantPoolInfo::Long(_) => "long",
+            ConstantPoolInfo::MethodRef { .. } => "Method",
+            other => other.tag_name(),
+        };
+        let value = match constant {
+            ConstantPoolInfo::FieldRef {
+                class_index,
+                name_and_type_index,
+            }
+            | ConstantPoolInfo::MethodRef {
+                class_index,
+                name_and_type_index,
+            }
+            | ConstantPoolInfo::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            } if *class_index == self.class.this_class => self.constant_value(*name_and_type_index),
+            _ => self.constant_value(index),
+        };
+        format!("{tag} {value}")
+    }
+
+    /// Text of the constant as printed by `javap` in comments
+    fn constant_value(&self, index: u16) -> String {
+        let Some(constant) = constant(self.pool, index) else {
+            return format!("#{index}");
+        };
+        match constant {
+            ConstantPoolInfo::Class { name_index }
+            | ConstantPoolInfo::Module { name_index }
+            | ConstantPoolInfo::Package { name_index } => {
+                check_name(utf8_at(self.pool, *name_index).unwrap_or("?"))
+            }
+            ConstantPoolInfo::Double(value) => format!("{}d", java_double(*value)),
+            ConstantPoolInfo::Float(value) => format!("{}f", java_float(*value)),
+            ConstantPoolInfo::Integer(value) => value.to_string(),
+            ConstantPoolInfo::Long(value) => format!("{value}l"),
+            ConstantPoolInfo::String { string_index } => self.constant_value(*string_index),
+            ConstantPoolInfo::Utf8(value) => escape(value),
+            // `javap` prints what its output encoding cannot represent as `?`
+            ConstantPoolInfo::Utf16(units) => escape(
+                &char::decode_utf16(units.iter().copied())
+                    .map(|c| c.unwrap_or('?'))
+                    .collect::<String>(),
+            ),
+            ConstantPoolInfo::FieldRef {
+                class_index,
+                name_and_type_index,
+            }
+            | ConstantPoolInfo::MethodRef {
+                class_index,
+                name_and_type_index,
+            }
+            | ConstantPoolInfo::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            } => format!(
+                "{}.{}",
+                self.constant_value(*class_index),
+                self.constant_value(*name_and_type_index)
+            ),
+            ConstantPoolInfo::NameAndType {
+                name_index,
+                descriptor_index,
+            } => format!(
+                "{}:{}",
+                check_name(utf8_at(self.pool, *name_index).unwrap_or("?")),
+                utf8_at(self.pool, *descriptor_index).unwrap_or("?")
+            ),
+            ConstantPoolInfo::Dynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            }
+            | ConstantPoolInfo::InvokeDynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => format!(
+                "#{bootstrap_method_attr_index}:{}",
+                self.constant_value(*name_and_type_index)
+            ),
+            ConstantPoolInfo::MethodHandle {
+                reference_kind,
+                reference_index,
+            } => format!(
+                "{} {}",
+                REFERENCE_KINDS
+                    .get(*reference_kind as usize)
+                    .unwrap_or(&"REF_???"),
+                self.constant_value(*reference_index)
+            ),
+            ConstantPoolInfo::MethodType { descriptor_index } => {
+                utf8_at(self.pool, *descriptor_index)
+                    .unwrap_or("?")
+                    .to_string()
+            }
+            ConstantPoolInfo::Unusable => String::new(),
+        }
+    }
+}
+
+/// Annotation with constant pool indexes, like `#20(#25=s#26,#27=[I#28])`
+fn annotation_indexes(annotation: &Annotation) -> String {
+    let pairs: Vec<String> = annotation
+        .element_value_pairs
+        .iter()
+        .map(|(name, value)| format!("#{name}={}", element_value_indexes(value)))
+        .collect();
+    format!("#{}({})", annotation.type_index, pairs.join(","))
+}
+
+fn element_value_indexes(value: &ElementValue) -> String {
+    match value {
+        ElementValue::Const {
+            tag,
+            const_value_index,
+        } => format!("{}#{const_value_index}", *tag as char),
+        ElementValue::Enum {
+            type_name_index,
+            const_name_index,
+        } => format!("e#{type_name_index}.#{const_name_index}"),
+        ElementValue::Class(index) => format!("c#{index}"),
+        ElementValue::Annotation(annotation) => format!("@{}", annotation_indexes(annotation)),
+        ElementValue::Array(values) => {
+            let values: Vec<String> = values.iter().map(element_value_indexes).collect();
+            format!("[{}]", values.join(","))
+        }
+    }
+}
+
+fn member_modifiers(flags: AccessFlags) -> Vec<&'static str> {
+    let mut modifiers = vec![];
+    for (flag, modifier) in [
+        (AccessFlags::Public, "public"),
+        (AccessFlags::Private, "private"),
+        (AccessFlags::Protected, "protected"),
+        (AccessFlags::Static, "static"),
+        (AccessFlags::Final, "final"),
+    ] {
+        if flags.contains(flag) {
+            modifiers.push(modifier);
+        }
+    }
+    modifiers
+}
+
+fn signature(attributes: &[Attribute]) -> Option<&str> {
+    attributes.iter().find_map(|a| match &a.info {
+        AttributeInfo::Signature(signature) => Some(signature.as_str()),
+        _ => None,
+    })
+}
+
+/// Quotes names that are not valid Java identifiers (allowing `/` as package separator)
+fn check_name(name: &str) -> String {
+    if name.is_empty() {
+        return "\"\"".to_string();
+    }
+    let mut previous = '/';
+    for c in name.chars() {
+        let valid = if previous == '/' {
+            c == '_' || c == '$' || c.is_alphabetic()
+        } else {
+            c == '/' || c == '_' || c == '$' || c.is_alphanumeric()
+        };
+        if !valid {
+            let escaped = name
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+                .replace('\t', "\\t");
+            return format!("\"{escaped}\"");
+        }
+        previous = c;
+    }
+    name.to_string()
+}
+
+/// Escapes string constant the way `javap` does
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\t' => result.push_str("\\t"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\u{8}' => result.push_str("\\b"),
+            '\u{c}' => result.push_str("\\f"),
+            '"' => result.push_str("\\\""),
+            '\'' => result.push_str("\\'"),
+            '\\' => result.push_str("\\\\"),
+            c if c.is_control() => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Formats number like `Double.toString` does: plain notation between 10^-3 and 10^7,
+/// computerized scientific notation otherwise, always with a fractional part
+pub fn java_double(value: f64) -> String {
+    java_decimal(
+        value.is_nan(),
+        value.is_infinite(),
+        value.is_sign_negative(),
+        &format!("{:e}", value.abs()),
+        &format!("{:.1e}", value.abs()),
+    )
+}
+
+/// [`java_double`] for `Float.toString`
+pub fn java_float(value: f32) -> String {
+    java_decimal(
+        value.is_nan(),
+        value.is_infinite(),
+        value.is_sign_negative(),
+        &format!("{:e}", value.abs()),
+        &format!("{:.1e}", value.abs()),
+    )
+}
+
+/// Decimal text of the number given as the shortest scientific notation that reads back
+/// as it, and as the closest one with two digits
+fn java_decimal(
+    nan: bool,
+    infinite: bool,
+    negative: bool,
+    scientific: &str,
+    two_digits: &str,
+) -> String {
+    if nan {
+        return "NaN".to_string();
+    }
+    let sign = if negative { "-" } else { "" };
+    if infinite {
+        return format!("{sign}Infinity");
+    }
+
+    let (mantissa, _) = scientific.split_once('e').unwrap_or((scientific, "0"));
+    if mantissa == "0" {
+        return format!("{sign}0.0");
+    }
+    // Java writes at least two digits, which differ from the single digit that reads back
+    // as the number at the bottom of subnormals: 4.9E-324, not 5.0E-324
+    let scientific = match mantissa.contains('.') {
+        true => scientific,
+        false => two_digits,
+    };
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((scientific, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let digits = match digits.trim_end_matches('0') {
+        "" => "0",
+        digits => digits,
+    };
+
+    if (-3..7).contains(&exponent) {
+        let (integer, fraction) = if exponent < 0 {
+            let zeros = "0".repeat((-exponent - 1) as usize);
+            ("0".to_string(), format!("{zeros}{digits}"))
+        } else {
+            let point = exponent as usize + 1;
+            let padded = format!("{digits:0<point$}");
+            let (integer, fraction) = padded.split_at(point);
+            (integer.to_string(), fraction.to_string())
+        };
+        let fraction = if fraction.is_empty() {
+            "0".to_string()
+        } else {
+            fraction
+        };
+        format!("{sign}{integer}.{fraction}")
+    } else {
+        let (first, rest) = digits.split_at(1);
+        let rest = if rest.is_empty() { "0" } else { rest };
+        format!("{sign}{first}.{rest}E{exponent}")
+    }
+}
+
+/// Parser of generic signatures (JVMS 4.7.9.1) producing Java source syntax
+struct Signature<'s> {
+    text: &'s str,
+}
+
+impl<'s> Signature<'s> {
+    fn new(text: &'s str) -> Self {
+        Signature { text }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text.chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> Option<()> {
+        self.text = self.text.strip_prefix(c)?;
+        Some(())
+    }
+
+    fn identifier(&mut self) -> Option<&'s str> {
+        let end = self
+            .text
+            .find([';', '<', '>', '.', ':', '/'])
+            .unwrap_or(self.text.len());
+        let (identifier, rest) = self.text.split_at(end);
+        self.text = rest;
+        (!identifier.is_empty()).then_some(identifier)
+    }
+
+    /// Runs `parse` and requires it to consume the entire signature
+    fn complete(mut self, parse: fn(&mut Self) -> Option<String>) -> Option<String> {
+        let result = parse(&mut self)?;
+        self.text.is_empty().then_some(result)
+    }
+
+    /// Type parameters with the surrounding `<>`, or empty string if there are none
+    fn type_parameters(&mut self) -> Option<String> {
+        if self.eat('<').is_none() {
+            return Some(String::new());
+        }
+        let mut parameters = vec![];
+        while self.eat('>').is_none() {
+            let mut parameter = self.identifier()?.to_string();
+            let mut separator = " extends ";
+            self.eat(':')?;
+            if !matches!(self.peek(), Some(':') | Some('>')) {
+                parameter.push_str(separator);
+                parameter.push_str(&self.reference_type()?);
+                separator = " & ";
+            }
+            while self.eat(':').is_some() {
+                parameter.push_str(separator);
+                parameter.push_str(&self.reference_type()?);
+                separator = " & ";
+            }
+            parameters.push(parameter);
+        }
+        Some(format!("<{}>", parameters.join(", ")))
+    }
+
+    fn type_signature(&mut self) -> Option<String> {
+        let base = match self.peek()? {
+            'B' => "byte",
+            'C' => "char",
+            'D' => "double",
+            'F' => "float",
+            'I' => "int",
+            'J' => "long",
+            'S' => "short",
+            'Z' => "boolean",
+            'V' => "void",
+            _ => return self.reference_type(),
+        };
+        self.text = &self.text[1..];
+        Some(base.to_string())
+    }
+
+    fn reference_type(&mut self) -> Option<String> {
+        match self.peek()? {
+            'L' => {
+                self.eat('L')?;
+                let mut name = String::new();
+                loop {
+                    name.push_str(self.identifier()?);
+                    if self.eat('<').is_some() {
+                        let mut arguments = vec![];
+                        while self.eat('>').is_none() {
+                            arguments.push(match self.peek()? {
+                                '*' => {
+                                    self.eat('*')?;
+                                    "?".to_string()
+                                }
+                                '+' => {
+                                    self.eat('+')?;
+                                    format!("? extends {}", self.reference_type()?)
+                                }
+                                '-' => {
+                                    self.eat('-')?;
+                                    format!("? super {}", self.reference_type()?)
+                                }
+                                _ => self.reference_type()?,
+                            });
+                        }
+                        name.push_str(&format!("<{}>", arguments.join(", ")));
+                    }
+                    match self.peek()? {
+                        '/' | '.' => {
+                            self.text = &self.text[1..];
+                            name.push('.');
+                        }
+                        ';' => {
+                            self.eat(';')?;
+                            return Some(name);
+                        }
+                        _ => return None,
+                    }
+                }
+            }
+            'T' => {
+                self.eat('T')?;
+                let name = self.identifier()?;
+                self.eat(';')?;
+                Some(name.to_string())
+            }
+            '[' => {
+                self.eat('[')?;
+                Some(format!("{}[]", self.type_signature()?))
+            }
+            _ => None,
+        }
+    }
+
+    /// Type parameters, superclass and interfaces of the class signature
+    fn class_signature(mut self) -> Option<(String, String, Vec<String>)> {
+        let type_parameters = self.type_parameters()?;
+        let superclass = self.reference_type()?;
+        let mut interfaces = vec![];
+        while !self.text.is_empty() {
+            interfaces.push(self.reference_type()?);
+        }
+        Some((type_parameters, superclass, interfaces))
+    }
+
+    /// Type parameters, parameters and return type of the method signature
+    fn method_signature(mut self) -> Option<(String, Vec<String>, String)> {
+        let type_parameters = self.type_parameters()?;
+        self.eat('(')?;
+        let mut parameters = vec![];
+        while self.eat(')').is_none() {
+            parameters.push(self.type_signature()?);
+        }
+        let return_type = self.type_signature()?;
+        Some((type_parameters, parameters, return_type))
+    }
+}
+
+/// Date in the `MMM d, yyyy` format used by `javap`, in UTC
+fn format_date(time: SystemTime) -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    // Civil from days algorithm by Howard Hinnant
+    let days = seconds.div_euclid(86400) + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!("{} {day}, {year}", MONTHS[month as usize - 1])
+}
+
+fn sha256(bytes: &[u8]) -> [u8; 32] {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
+        0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
+        0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
+        0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+        0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
+        0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+        0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
+        0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+        0xc67178f2,
+    ];
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+
+    let mut message = bytes.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((bytes.len() as u64) * 8).to_be_bytes());
+
+    for chunk in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (i, word) in h.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
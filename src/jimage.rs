@@ -0,0 +1,697 @@
+//! Reader of jimage files, the format of `lib/modules` holding the classes of a JDK
+//! since Java 9. Resources are named `/<module>/<path>`, like
+//! `/java.base/java/lang/String.class`, and found through a perfect hash table.
+//! The image also contains `/packages/<package>` entries listing the modules of
+//! each package, which is how classes are found by their internal name alone.
+
+use crate::raw::class::Class;
+use crate::raw::invalid_data;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use std::io::Result;
+use std::path::{Path, PathBuf};
+
+const MAGIC: u32 = 0xcafe_dada;
+const MAJOR_VERSION: u16 = 1;
+const HEADER_SIZE: usize = 28;
+/// Seed of the hash function and its multiplier, as in `ImageStringsReader`
+const HASH_SEED: u32 = 0x0100_0193;
+
+const COMPRESSED_MAGIC: u32 = 0xcafe_fafa;
+const COMPRESSED_HEADER_SIZE: usize = 29;
+
+/// Constant pool tags of the `compact-cp` compressor, which replace Utf8 entries
+const EXTERNALIZED_STRING: u8 = 23;
+const EXTERNALIZED_DESCRIPTOR: u8 = 25;
+const CONSTANT_UTF8: u8 = 1;
+/// Sizes of the other constant pool entries without their tag, by tag
+const CONSTANT_SIZES: [usize; 21] = [
+    0, 0, 0, 4, 4, 8, 8, 2, 2, 4, 4, 4, 4, 0, 0, 3, 2, 4, 4, 2, 2,
+];
+
+/// Kinds of location attributes
+const ATTRIBUTE_MODULE: usize = 1;
+const ATTRIBUTE_PARENT: usize = 2;
+const ATTRIBUTE_BASE: usize = 3;
+const ATTRIBUTE_EXTENSION: usize = 4;
+const ATTRIBUTE_OFFSET: usize = 5;
+const ATTRIBUTE_COMPRESSED: usize = 6;
+const ATTRIBUTE_UNCOMPRESSED: usize = 7;
+const ATTRIBUTE_COUNT: usize = 8;
+
+/// Resource of the image, with names given as offsets into the strings table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    attributes: [u64; ATTRIBUTE_COUNT],
+}
+
+impl Location {
+    /// Size of the resource after decompression
+    pub fn size(&self) -> u64 {
+        self.attributes[ATTRIBUTE_UNCOMPRESSED]
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.attributes[ATTRIBUTE_COMPRESSED] != 0
+    }
+}
+
+/// Image read into memory with its header checked
+pub struct JImage {
+    path: PathBuf,
+    bytes: Vec<u8>,
+    /// Images are written in the byte order of the platform that created them
+    big_endian: bool,
+    table_length: usize,
+    redirect: usize,
+    offsets: usize,
+    locations: usize,
+    strings: usize,
+    /// Start of the resources, which follow the index
+    resources: usize,
+}
+
+impl JImage {
+    pub fn open(path: impl AsRef<Path>) -> Result<JImage> {
+        let path = path.as_ref();
+        let mut image = JImage::from_bytes(std::fs::read(path)?)?;
+        image.path = path.to_path_buf();
+        Ok(image)
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<JImage> {
+        let header = bytes
+            .get(..HEADER_SIZE)
+            .ok_or_else(|| invalid_data("not a jimage file, file is too short"))?;
+        let big_endian = match LittleEndian::read_u32(header) {
+            MAGIC => false,
+            _ if BigEndian::read_u32(header) == MAGIC => true,
+            _ => {
+                return Err(invalid_data(
+                    "not a jimage file, magic number does not match",
+                ))
+            }
+        };
+        let field = |index: usize| match big_endian {
+            true => BigEndian::read_u32(&header[index * 4..]),
+            false => LittleEndian::read_u32(&header[index * 4..]),
+        };
+        let major = (field(1) >> 16) as u16;
+        if major != MAJOR_VERSION {
+            return Err(invalid_data(format!("unsupported jimage version {major}")));
+        }
+        let table_length = field(4) as usize;
+        let locations_size = field(5) as usize;
+        let strings_size = field(6) as usize;
+
+        let redirect = HEADER_SIZE;
+        let offsets = redirect + table_length * 4;
+        let locations = offsets + table_length * 4;
+        let strings = locations + locations_size;
+        let resources = strings + strings_size;
+        if resources > bytes.len() {
+            return Err(invalid_data("jimage file is truncated"));
+        }
+        Ok(JImage {
+            path: PathBuf::new(),
+            bytes,
+            big_endian,
+            table_length,
+            redirect,
+            offsets,
+            locations,
+            strings,
+            resources,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Resource called `name`, like `/java.base/java/lang/String.class`
+    pub fn find(&self, name: &str) -> Option<Location> {
+        if self.table_length == 0 {
+            return None;
+        }
+        let slot = hash(name, HASH_SEED) as usize % self.table_length;
+        let redirect = self.u32_at(self.redirect + slot * 4)? as i32;
+        let index = match redirect {
+            0 => return None,
+            // Negative values are the index itself, positive ones the seed of a second hash
+            ..0 => (-1 - redirect) as usize,
+            _ => hash(name, redirect as u32) as usize % self.table_length,
+        };
+        // Names that are not in the table still hash to some entry
+        let location = self.location(index)?;
+        (self.name(&location)? == name).then_some(location)
+    }
+
+    /// Every resource of the image, in the order of the hash table
+    pub fn locations(&self) -> impl Iterator<Item = Location> + '_ {
+        (0..self.table_length).filter_map(|index| self.location(index))
+    }
+
+    /// Full name of the resource, `/<module>/<parent>/<base>.<extension>`
+    pub fn name(&self, location: &Location) -> Option<String> {
+        let mut name = String::new();
+        if let Some(module) = self.attribute_string(location, ATTRIBUTE_MODULE)? {
+            name.push('/');
+            name.push_str(module);
+            name.push('/');
+        }
+        if let Some(parent) = self.attribute_string(location, ATTRIBUTE_PARENT)? {
+            name.push_str(parent);
+            name.push('/');
+        }
+        name.push_str(
+            self.attribute_string(location, ATTRIBUTE_BASE)?
+                .unwrap_or(""),
+        );
+        if let Some(extension) = self.attribute_string(location, ATTRIBUTE_EXTENSION)? {
+            name.push('.');
+            name.push_str(extension);
+        }
+        Some(name)
+    }
+
+    /// Module and class name of class resources, like `java.base` and `java/lang/String`
+    pub fn class_name(&self, location: &Location) -> Option<(&str, String)> {
+        if self.attribute_string(location, ATTRIBUTE_EXTENSION)? != Some("class") {
+            return None;
+        }
+        let module = self.attribute_string(location, ATTRIBUTE_MODULE)??;
+        let base = self.attribute_string(location, ATTRIBUTE_BASE)??;
+        if matches!(module, "modules" | "packages") || base == "module-info" {
+            return None;
+        }
+        let name = match self.attribute_string(location, ATTRIBUTE_PARENT)? {
+            Some(parent) => format!("{parent}/{base}"),
+            None => base.to_string(),
+        };
+        Some((module, name))
+    }
+
+    /// Internal names of all classes with their modules, sorted by name
+    pub fn class_names(&self) -> Vec<(&str, String)> {
+        let mut names: Vec<(&str, String)> = self
+            .locations()
+            .filter_map(|location| self.class_name(&location))
+            .collect();
+        names.sort_unstable_by(|(_, a), (_, b)| a.cmp(b));
+        names
+    }
+
+    /// Names of the modules in the image, sorted
+    pub fn modules(&self) -> Vec<&str> {
+        let mut modules: Vec<&str> = self
+            .locations()
+            .filter_map(|location| self.attribute_string(&location, ATTRIBUTE_MODULE)?)
+            .filter(|module| !matches!(*module, "modules" | "packages"))
+            .collect();
+        modules.sort_unstable();
+        modules.dedup();
+        modules
+    }
+
+    /// Module containing classes of the package, given with slashes like `java/lang`
+    pub fn module_of_package(&self, package: &str) -> Option<&str> {
+        let location = self.find(&format!("/packages/{}", package.replace('/', ".")))?;
+        let content = self.read(&location).ok()?;
+        // Pairs of a flag set for modules that only have the package empty and the module name
+        content.chunks_exact(8).find_map(|pair| {
+            let (empty, module) = match self.big_endian {
+                true => (BigEndian::read_u32(pair), BigEndian::read_u32(&pair[4..])),
+                false => (
+                    LittleEndian::read_u32(pair),
+                    LittleEndian::read_u32(&pair[4..]),
+                ),
+            };
+            match empty {
+                0 => self.string(module as usize),
+                _ => None,
+            }
+        })
+    }
+
+    /// Content of the resource, decompressed if it was stored compressed
+    pub fn read(&self, location: &Location) -> Result<Vec<u8>> {
+        let start = self.resources as u64 + location.attributes[ATTRIBUTE_OFFSET];
+        let stored = match location.is_compressed() {
+            true => location.attributes[ATTRIBUTE_COMPRESSED],
+            false => location.size(),
+        };
+        let mut content = usize::try_from(start)
+            .ok()
+            .zip(usize::try_from(stored).ok())
+            .and_then(|(start, stored)| self.bytes.get(start..start.checked_add(stored)?))
+            .ok_or_else(|| invalid_data("resource lies outside of the jimage file"))?
+            .to_vec();
+        if location.is_compressed() {
+            // Compressors may be stacked, each adding a header in front of its output
+            while content.len() >= COMPRESSED_HEADER_SIZE
+                && self.read_u32(&content) == COMPRESSED_MAGIC
+            {
+                content = self.decompress(&content)?;
+            }
+        }
+        if content.len() as u64 != location.size() {
+            return Err(invalid_data(
+                "resource has a size other than its location says",
+            ));
+        }
+        Ok(content)
+    }
+
+    /// Content of the resource called `name`
+    pub fn read_resource(&self, name: &str) -> Result<Vec<u8>> {
+        match self.find(name) {
+            Some(location) => self.read(&location),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no resource {name}"),
+            )),
+        }
+    }
+
+    /// Location of the class with the internal name `name`, looked up in the module of its package
+    pub fn find_class(&self, name: &str) -> Option<Location> {
+        let (package, _) = name.rsplit_once('/')?;
+        let module = self.module_of_package(package)?;
+        self.find(&format!("/{module}/{name}.class"))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.find_class(name).is_some()
+    }
+
+    /// Bytes of the class with the internal name `name`
+    pub fn read_class(&self, name: &str) -> Result<Vec<u8>> {
+        match self.find_class(name) {
+            Some(location) => self.read(&location),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no class {name}"),
+            )),
+        }
+    }
+
+    /// Parses the class with the internal name `name`, like `java/lang/String`
+    pub fn class(&self, name: &str) -> Result<Class> {
+        let bytes = self.read_class(name)?;
+        let mut class = Class::from(bytes.as_slice())?;
+        class.resolve_attributes()?;
+        Ok(class)
+    }
+
+    fn decompress(&self, content: &[u8]) -> Result<Vec<u8>> {
+        let compressed_size = self.read_u64(&content[4..]);
+        let size = self.read_u64(&content[12..]);
+        let decompressor = self.read_u32(&content[20..]) as usize;
+        let decompressor = self
+            .string(decompressor)
+            .ok_or_else(|| invalid_data("resource names no decompressor"))?;
+        let data = usize::try_from(compressed_size)
+            .ok()
+            .and_then(|length| content.get(COMPRESSED_HEADER_SIZE..)?.get(..length))
+            .ok_or_else(|| invalid_data("compressed resource is truncated"))?;
+        match decompressor {
+            "zip" => miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, size as usize)
+                .map_err(|error| invalid_data(format!("compressed resource is corrupt: {error}"))),
+            "compact-cp" => self
+                .unshare_strings(data)
+                .ok_or_else(|| invalid_data("compressed resource is truncated")),
+            _ => Err(invalid_data(format!(
+                "resource is compressed with unsupported {decompressor}"
+            ))),
+        }
+    }
+
+    /// Restores a class file whose constant pool strings were moved to the strings table,
+    /// either whole or, for descriptors, split into the packages and names of their classes
+    fn unshare_strings(&self, mut data: &[u8]) -> Option<Vec<u8>> {
+        let mut class = Vec::with_capacity(data.len() * 2);
+        // Magic, version and constant pool count are kept
+        class.extend_from_slice(data.get(..10)?);
+        let count = BigEndian::read_u16(&data[8..]);
+        data = &data[10..];
+        let mut index = 1;
+        while index < count {
+            let (&tag, rest) = data.split_first()?;
+            data = rest;
+            let put_utf8 = |class: &mut Vec<u8>, bytes: &[u8]| {
+                class.push(CONSTANT_UTF8);
+                class.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+                class.extend_from_slice(bytes);
+            };
+            match tag {
+                EXTERNALIZED_STRING => {
+                    let string = self.string_bytes(read_compressed_index(&mut data)?)?;
+                    put_utf8(&mut class, string);
+                }
+                EXTERNALIZED_DESCRIPTOR => {
+                    let descriptor = self.string_bytes(read_compressed_index(&mut data)?)?;
+                    let length = read_compressed_index(&mut data)?;
+                    let mut indexes = data.get(..length)?;
+                    data = &data[length..];
+                    // Every `L` is followed by the package and the simple name of the class
+                    let mut string = vec![];
+                    for &byte in descriptor {
+                        string.push(byte);
+                        if byte == b'L' {
+                            let package =
+                                self.string_bytes(read_compressed_index(&mut indexes)?)?;
+                            if !package.is_empty() {
+                                string.extend_from_slice(package);
+                                string.push(b'/');
+                            }
+                            let name = self.string_bytes(read_compressed_index(&mut indexes)?)?;
+                            string.extend_from_slice(name);
+                        }
+                    }
+                    put_utf8(&mut class, &string);
+                }
+                _ => {
+                    let size = match tag {
+                        CONSTANT_UTF8 => 2 + BigEndian::read_u16(data.get(..2)?) as usize,
+                        _ => *CONSTANT_SIZES.get(tag as usize)?,
+                    };
+                    if size == 0 {
+                        return None;
+                    }
+                    class.push(tag);
+                    class.extend_from_slice(data.get(..size)?);
+                    data = &data[size..];
+                }
+            }
+            // Long and Double constants take two entries
+            index += match tag {
+                5 | 6 => 2,
+                _ => 1,
+            };
+        }
+        class.extend_from_slice(data);
+        Some(class)
+    }
+
+    /// Location under `index` of the hash table, decoded from its attribute stream
+    fn location(&self, index: usize) -> Option<Location> {
+        let offset = self.u32_at(self.offsets + index * 4)? as usize;
+        let mut bytes = self.bytes.get(self.locations + offset..self.strings)?;
+        let mut attributes = [0; ATTRIBUTE_COUNT];
+        // Each attribute starts with its kind and length in one byte, kind 0 ends the stream
+        while let Some((&head, rest)) = bytes.split_first() {
+            let kind = (head >> 3) as usize;
+            if kind == 0 {
+                break;
+            }
+            let length = (head & 0x7) as usize + 1;
+            let value = rest.get(..length)?;
+            *attributes.get_mut(kind)? = value
+                .iter()
+                .fold(0, |value, byte| value << 8 | *byte as u64);
+            bytes = &rest[length..];
+        }
+        Some(Location { attributes })
+    }
+
+    /// Name attribute `kind` of the location, `Some(None)` if the name is empty
+    fn attribute_string(&self, location: &Location, kind: usize) -> Option<Option<&str>> {
+        match location.attributes[kind] {
+            0 => Some(None),
+            offset => self.string(offset as usize).map(Some),
+        }
+    }
+
+    /// String of the strings table, stored as modified UTF-8 ended by a zero byte
+    fn string(&self, offset: usize) -> Option<&str> {
+        std::str::from_utf8(self.string_bytes(offset)?).ok()
+    }
+
+    fn string_bytes(&self, offset: usize) -> Option<&[u8]> {
+        let bytes = self.bytes.get(self.strings + offset..self.resources)?;
+        let end = bytes.iter().position(|byte| *byte == 0)?;
+        Some(&bytes[..end])
+    }
+
+    fn u32_at(&self, position: usize) -> Option<u32> {
+        Some(self.read_u32(self.bytes.get(position..position + 4)?))
+    }
+
+    fn read_u32(&self, bytes: &[u8]) -> u32 {
+        match self.big_endian {
+            true => BigEndian::read_u32(bytes),
+            false => LittleEndian::read_u32(bytes),
+        }
+    }
+
+    fn read_u64(&self, bytes: &[u8]) -> u64 {
+        match self.big_endian {
+            true => BigEndian::read_u64(bytes),
+            false => LittleEndian::read_u64(bytes),
+        }
+    }
+}
+
+/// Reads an index of the `compact-cp` compressor: with the high bit set, bits 5 and 6
+/// of the first byte give the length and its low five bits start the value,
+/// otherwise the value takes four bytes
+fn read_compressed_index(data: &mut &[u8]) -> Option<usize> {
+    let (&first, _) = data.split_first()?;
+    let (length, value) = match first & 0x80 {
+        0 => (4, first as usize),
+        _ => (((first >> 5) & 0x3) as usize, (first & 0x1f) as usize),
+    };
+    let bytes = data.get(1..length.max(1))?;
+    *data = &data[length.max(1)..];
+    Some(
+        bytes
+            .iter()
+            .fold(value, |value, byte| value << 8 | *byte as usize),
+    )
+}
+
+/// Hash of the image's lookup table, masked to a positive `int` like in Java
+fn hash(name: &str, seed: u32) -> u32 {
+    let hash = name.bytes().fold(seed, |hash, byte| {
+        hash.wrapping_mul(HASH_SEED) ^ byte as u32
+    });
+    hash & 0x7fff_ffff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Image of the resources, given by name with their content and whether it is stored
+    /// compressed with `zip`, and of the package of each class, in the byte order
+    fn image(resources: &[(&str, &[u8], bool)], big_endian: bool) -> Vec<u8> {
+        let u32_bytes = |value: u32| match big_endian {
+            true => value.to_be_bytes(),
+            false => value.to_le_bytes(),
+        };
+        let u64_bytes = |value: u64| match big_endian {
+            true => value.to_be_bytes(),
+            false => value.to_le_bytes(),
+        };
+        // Offset 0 is the empty string
+        let mut strings = vec![0];
+        let mut string = |text: &str| -> u64 {
+            if text.is_empty() {
+                return 0;
+            }
+            let offset = strings.len() as u64;
+            strings.extend_from_slice(text.as_bytes());
+            strings.push(0);
+            offset
+        };
+
+        let mut packages = vec![];
+        for (name, _, _) in resources {
+            let (module, path) = name[1..].split_once('/').unwrap();
+            if let Some((package, _)) = path.strip_suffix(".class").and_then(|p| p.rsplit_once('/'))
+            {
+                let mut content = u32_bytes(0).to_vec();
+                content.extend(u32_bytes(string(module) as u32));
+                packages.push((format!("/packages/{}", package.replace('/', ".")), content));
+            }
+        }
+        let resources: Vec<(&str, Vec<u8>, bool)> = resources
+            .iter()
+            .map(|(name, content, compressed)| (*name, content.to_vec(), *compressed))
+            .chain(
+                packages
+                    .iter()
+                    .map(|(name, content)| (name.as_str(), content.clone(), false)),
+            )
+            .collect();
+
+        // Perfect hash like `PerfectHashBuilder`: names sharing a slot of the first hash
+        // get a seed for a second hash that gives each of them a free index
+        let length = resources.len();
+        let mut buckets = vec![vec![]; length];
+        for (position, (name, _, _)) in resources.iter().enumerate() {
+            buckets[hash(name, HASH_SEED) as usize % length].push(position);
+        }
+        let mut order: Vec<usize> = (0..length).collect();
+        order.sort_by_key(|slot| std::cmp::Reverse(buckets[*slot].len()));
+        let mut redirect = vec![0i32; length];
+        let mut indexes = vec![usize::MAX; length];
+        let mut used = vec![false; length];
+        for slot in order {
+            let bucket = &buckets[slot];
+            match bucket.len() {
+                0 => {}
+                1 => {
+                    let index = used.iter().position(|used| !used).unwrap();
+                    used[index] = true;
+                    indexes[bucket[0]] = index;
+                    redirect[slot] = -1 - index as i32;
+                }
+                _ => {
+                    let place = |seed: u32| {
+                        let chosen: Vec<usize> = bucket
+                            .iter()
+                            .map(|&position| hash(resources[position].0, seed) as usize % length)
+                            .collect();
+                        let mut unique = chosen.clone();
+                        unique.sort_unstable();
+                        unique.dedup();
+                        let free = chosen.iter().all(|index| !used[*index]);
+                        (free && unique.len() == chosen.len()).then_some(chosen)
+                    };
+                    let (seed, chosen) = (1..).find_map(|seed| Some((seed, place(seed)?))).unwrap();
+                    for (&position, index) in bucket.iter().zip(chosen) {
+                        used[index] = true;
+                        indexes[position] = index;
+                    }
+                    redirect[slot] = seed as i32;
+                }
+            }
+        }
+
+        let mut offsets = vec![0; length];
+        let mut locations = vec![];
+        let mut data = vec![];
+        for ((name, content, compressed), index) in resources.iter().zip(indexes) {
+            offsets[index] = locations.len() as u32;
+
+            let (module, path) = name[1..].split_once('/').unwrap();
+            let (parent, file) = path.rsplit_once('/').unwrap_or(("", path));
+            let (base, extension) = file.rsplit_once('.').unwrap_or((file, ""));
+            let stored = match compressed {
+                true => {
+                    let deflated = miniz_oxide::deflate::compress_to_vec_zlib(content, 6);
+                    let mut stored = u32_bytes(COMPRESSED_MAGIC).to_vec();
+                    stored.extend(u64_bytes(deflated.len() as u64));
+                    stored.extend(u64_bytes(content.len() as u64));
+                    stored.extend(u32_bytes(string("zip") as u32));
+                    stored.extend(u32_bytes(0));
+                    stored.push(1);
+                    stored.extend(deflated);
+                    stored
+                }
+                false => content.clone(),
+            };
+            let attributes = [
+                (ATTRIBUTE_MODULE, string(module)),
+                (ATTRIBUTE_PARENT, string(parent)),
+                (ATTRIBUTE_BASE, string(base)),
+                (ATTRIBUTE_EXTENSION, string(extension)),
+                (ATTRIBUTE_OFFSET, data.len() as u64),
+                (
+                    ATTRIBUTE_COMPRESSED,
+                    if *compressed { stored.len() as u64 } else { 0 },
+                ),
+                (ATTRIBUTE_UNCOMPRESSED, content.len() as u64),
+            ];
+            for (kind, value) in attributes {
+                let bytes = value.to_be_bytes();
+                let start = bytes.iter().position(|byte| *byte != 0).unwrap_or(7);
+                locations.push((kind << 3) as u8 | (7 - start) as u8);
+                locations.extend_from_slice(&bytes[start..]);
+            }
+            locations.push(0);
+            data.extend(stored);
+        }
+
+        let mut bytes = vec![];
+        let header = [
+            MAGIC,
+            (MAJOR_VERSION as u32) << 16,
+            0,
+            length as u32,
+            length as u32,
+            locations.len() as u32,
+            strings.len() as u32,
+        ];
+        for field in header {
+            bytes.extend(u32_bytes(field));
+        }
+        for value in redirect {
+            bytes.extend(u32_bytes(value as u32));
+        }
+        for offset in offsets {
+            bytes.extend(u32_bytes(offset));
+        }
+        bytes.extend(locations);
+        bytes.extend(strings);
+        bytes.extend(data);
+        bytes
+    }
+
+    fn check(big_endian: bool) {
+        let class = b"\xca\xfe\xba\xbe class bytes";
+        let text = b"resource ".repeat(20);
+        let image = JImage::from_bytes(image(
+            &[
+                ("/java.base/java/lang/Object.class", class, false),
+                ("/java.base/java/lang/Text.txt", &text, true),
+                ("/jdk.tool/jdk/tool/Main.class", class, true),
+            ],
+            big_endian,
+        ))
+        .unwrap();
+
+        let location = image.find("/java.base/java/lang/Text.txt").unwrap();
+        assert!(location.is_compressed());
+        assert_eq!(image.read(&location).unwrap(), text);
+        assert_eq!(
+            image
+                .read_resource("/java.base/java/lang/Object.class")
+                .unwrap(),
+            class
+        );
+        assert!(image.find("/java.base/java/lang/String.class").is_none());
+
+        assert_eq!(image.module_of_package("jdk/tool"), Some("jdk.tool"));
+        assert_eq!(image.read_class("jdk/tool/Main").unwrap(), class);
+        assert!(image.contains("java/lang/Object"));
+        assert!(!image.contains("java/lang/Text"));
+        assert_eq!(
+            image.class_names(),
+            [
+                ("java.base", "java/lang/Object".to_string()),
+                ("jdk.tool", "jdk/tool/Main".to_string())
+            ]
+        );
+        assert_eq!(image.modules(), ["java.base", "jdk.tool"]);
+    }
+
+    #[test]
+    fn lookup_little_endian() {
+        check(false);
+    }
+
+    #[test]
+    fn lookup_big_endian() {
+        check(true);
+    }
+
+    #[test]
+    fn other_version() {
+        let mut bytes = image(&[], false);
+        bytes[6] = 2;
+        let error = JImage::from_bytes(bytes).err().unwrap();
+        assert_eq!(error.to_string(), "unsupported jimage version 2");
+    }
+}
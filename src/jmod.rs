@@ -0,0 +1,107 @@
+//! JMOD files: the modules of a JDK before they are linked into a runtime image.
+//! They are ZIP archives behind a four byte header, with classes under `classes/`.
+
+use crate::raw::class::Class;
+use crate::raw::invalid_data;
+use crate::zip::ZipArchive;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8] = b"JM\x01\x00";
+
+/// Directory of the class files, other sections are `bin/`, `conf/`, `lib/` and so on
+pub const CLASSES: &str = "classes/";
+
+pub struct JmodFile {
+    path: PathBuf,
+    archive: ZipArchive,
+}
+
+impl JmodFile {
+    pub fn open(path: impl AsRef<Path>) -> Result<JmodFile> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        if !bytes.starts_with(MAGIC) {
+            return Err(invalid_data("not a JMOD file, magic number does not match"));
+        }
+        // The archive reader finds the archive behind the header by itself
+        Ok(JmodFile {
+            path: path.to_path_buf(),
+            archive: ZipArchive::from_bytes(bytes)?,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn archive(&self) -> &ZipArchive {
+        &self.archive
+    }
+
+    /// Internal names of the classes, like `java/lang/String`, in the order of the archive
+    pub fn class_names(&self) -> impl Iterator<Item = &str> {
+        self.archive.entries().iter().filter_map(|entry| {
+            let name = entry.name.strip_prefix(CLASSES)?.strip_suffix(".class")?;
+            (name != "module-info").then_some(name)
+        })
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.archive
+            .entry(&format!("{CLASSES}{name}.class"))
+            .is_some()
+    }
+
+    /// Bytes of the class with the internal name `name`
+    pub fn read_class(&self, name: &str) -> Result<Vec<u8>> {
+        self.archive.read_entry(&format!("{CLASSES}{name}.class"))
+    }
+
+    /// Parses the class with the internal name `name`, like `java/lang/String`
+    pub fn class(&self, name: &str) -> Result<Class> {
+        let bytes = self.read_class(name)?;
+        let mut class = Class::from(bytes.as_slice())?;
+        class.resolve_attributes()?;
+        Ok(class)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembly::assemble;
+    use crate::zip::tests::archive;
+    use crate::zip::{DEFLATED, STORED};
+
+    #[test]
+    fn classes_behind_the_header() {
+        let class = assemble(".class super p/A\n").unwrap().to_bytes().unwrap();
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(archive(&[
+            ("classes/module-info.class", STORED, 0, b"module"),
+            ("classes/p/A.class", DEFLATED, 0, &class),
+            ("bin/tool", STORED, 0, b"#!/bin/sh"),
+        ]));
+        let path = std::env::temp_dir().join(format!("hava-{}.jmod", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        let jmod = JmodFile::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        let jmod = jmod.unwrap();
+
+        assert_eq!(jmod.class_names().collect::<Vec<_>>(), ["p/A"]);
+        assert!(jmod.contains("p/A"));
+        assert!(!jmod.contains("bin/tool"));
+        assert_eq!(jmod.read_class("p/A").unwrap(), class);
+        assert_eq!(jmod.class("p/A").unwrap().name(), Some("p/A"));
+    }
+
+    #[test]
+    fn zip_without_header() {
+        let path = std::env::temp_dir().join(format!("hava-{}.zip.jmod", std::process::id()));
+        std::fs::write(&path, archive(&[("classes/p/A.class", STORED, 0, b"")])).unwrap();
+        let jmod = JmodFile::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(jmod.is_err());
+    }
+}
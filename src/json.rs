@@ -0,0 +1,540 @@
+//! JSON representation of a parsed class, enabled with the `json` cargo feature.
+//!
+//! The schema is versioned by the top level `schema` field and only grows in
+//! backwards compatible ways (new keys) while the version stays the same.
+//! Constant pool indexes are always kept next to the values they resolve to,
+//! so consumers can use whichever they prefer.
+//!
+//! ```text
+//! Class {
+//!   schema: 1,
+//!   version: { major, minor },
+//!   access_flags: Flags,
+//!   this_class: string,
+//!   super_class: string | null,          // null only for java/lang/Object and modules
+//!   interfaces: [string],
+//!   constant_pool: [Constant],           // ordered by index, starting with #1
+//!   fields: [Member],
+//!   methods: [Member],
+//!   attributes: [Attribute],
+//! }
+//!
+//! Flags { value: number, names: [string] }    // names like "ACC_PUBLIC"
+//!
+//! Member { name, descriptor, access_flags: Flags, attributes: [Attribute] }
+//!
+//! Constant { index, tag, ...tag specific keys }, tag is one of:
+//!   Utf8               { value }
+//!   Integer, Long      { value: number }
+//!   Float, Double      { value: number | "NaN" | "Infinity" | "-Infinity" }
+//!   Class              { name_index, name }
+//!   String             { string_index, value }
+//!   Fieldref, Methodref, InterfaceMethodref
+//!                      { class_index, name_and_type_index, class, name, descriptor }
+//!   NameAndType        { name_index, descriptor_index, name, descriptor }
+//!   MethodHandle       { reference_kind, reference_kind_name, reference_index, reference: Constant }
+//!   MethodType         { descriptor_index, descriptor }
+//!   Dynamic, InvokeDynamic
+//!                      { bootstrap_method_attr_index, name_and_type_index, name, descriptor }
+//!   Module, Package    { name_index, name }
+//!   Unusable           {}                  // second slot of Long and Double
+//!
+//! Attribute { name, ...attribute specific keys }, known attributes:
+//!   Code               { max_stack, max_locals, code: [Instruction],
+//!                        exception_table: [{ start_pc, end_pc, handler_pc, catch_type: string | null }],
+//!                        attributes: [Attribute] }
+//!   ConstantValue      { value: Constant }
+//!   SourceFile, Signature
+//!                      { value: string }
+//!   Exceptions         { exceptions: [string] }
+//!   LineNumberTable    { line_numbers: [{ start_pc, line_number }] }
+//!   LocalVariableTable, LocalVariableTypeTable
+//!                      { local_variables: [{ start_pc, length, index, name, descriptor }] }
+//!   StackMapTable      { frames: [{ frame_type, offset_delta, locals: [VerificationType], stack: [VerificationType] }] }
+//!   BootstrapMethods   { methods: [{ method: Constant, arguments: [Constant] }] }
+//!   InnerClasses       { classes: [{ inner_class, outer_class: string | null, inner_name: string | null, access_flags: Flags }] }
+//!   EnclosingMethod    { class, method: { name, descriptor } | null }
+//!   NestHost           { host: string }
+//!   NestMembers, PermittedSubclasses
+//!                      { classes: [string] }
+//!   RuntimeVisibleAnnotations, RuntimeInvisibleAnnotations
+//!                      { annotations: [Annotation] }
+//!   RuntimeVisibleParameterAnnotations, RuntimeInvisibleParameterAnnotations
+//!                      { parameters: [[Annotation]] }
+//!   AnnotationDefault  { value: ElementValue }
+//!   Deprecated, Synthetic
+//!                      {}
+//! Attributes that hava does not understand are { name, data: hex string }.
+//!
+//! Instruction { offset, opcode, mnemonic, operands: [number], ...optional keys }
+//!   constant: Constant                 // instructions referencing the constant pool
+//!   target: number                     // absolute offset of branch instructions
+//!   default, targets: [number]         // tableswitch, with low and high in operands
+//!   default, pairs: [{ key, target }]  // lookupswitch
+//!   wide: string                       // mnemonic of the instruction modified by wide
+//!
+//! VerificationType { type: "top" | "int" | "float" | "double" | "long" | "null"
+//!                          | "uninitialized_this" | "object" | "uninitialized",
+//!                    class: string,     // only for object
+//!                    offset: number }   // only for uninitialized, offset of the `new`
+//!
+//! Annotation { type: string, elements: [{ name, value: ElementValue }] }
+//!
+//! ElementValue { tag, ...tag specific keys }, tag is one of:
+//!   B C D F I J S Z s  { value: Constant }
+//!   e                  { type, name }
+//!   c                  { class }         // return descriptor, like "Ljava/lang/String;"
+//!   @                  { annotation: Annotation }
+//!   [                  { values: [ElementValue] }
+//! ```
+
+use crate::raw::class::Class;
+use crate::raw::*;
+use serde_json::{json, Map, Value};
+
+/// Version of the schema described in the module documentation
+pub const SCHEMA_VERSION: u32 = 1;
+
+pub fn to_json(class: &Class) -> Value {
+    let pool = class.constant_pool.as_slice();
+
+    let constant_pool: Vec<Value> = (1..=pool.len())
+        .map(|index| constant_json(pool, index as u16))
+        .collect();
+
+    json!({
+        "schema": SCHEMA_VERSION,
+        "version": { "major": class.major, "minor": class.minor },
+        "access_flags": flags_json(class.access_info, FlagsOwner::Class),
+        "this_class": class_name_at(pool, class.this_class),
+        "super_class": class_name_at(pool, class.super_class),
+        "interfaces": class.interfaces.iter().map(|index| class_name_at(pool, *index)).collect::<Vec<_>>(),
+        "constant_pool": constant_pool,
+        "fields": class.fields.iter().map(|field| member_json(
+            pool,
+            field.name_index,
+            field.descriptor_index,
+            flags_json(field.access_flags, FlagsOwner::Field),
+            &field.attributes,
+        )).collect::<Vec<_>>(),
+        "methods": class.methods.iter().map(|method| member_json(
+            pool,
+            method.name_index,
+            method.descriptor_index,
+            flags_json(method.access_flags, FlagsOwner::Method),
+            &method.attributes,
+        )).collect::<Vec<_>>(),
+        "attributes": attributes_json(pool, &class.attributes),
+    })
+}
+
+fn flags_json(flags: AccessFlags, owner: FlagsOwner) -> Value {
+    json!({
+        "value": flags.bits(),
+        "names": flags.javap_names(owner),
+    })
+}
+
+fn member_json(
+    pool: &[ConstantPoolInfo],
+    name_index: u16,
+    descriptor_index: u16,
+    access_flags: Value,
+    attributes: &[Attribute],
+) -> Value {
+    json!({
+        "name": utf8_at(pool, name_index),
+        "descriptor": utf8_at(pool, descriptor_index),
+        "access_flags": access_flags,
+        "attributes": attributes_json(pool, attributes),
+    })
+}
+
+/// Java style float formatting, since JSON has no representation of NaN or infinities
+fn float_json(value: f64) -> Value {
+    if value.is_nan() {
+        json!("NaN")
+    } else if value.is_infinite() {
+        json!(if value > 0.0 { "Infinity" } else { "-Infinity" })
+    } else {
+        json!(value)
+    }
+}
+
+fn constant_json(pool: &[ConstantPoolInfo], index: u16) -> Value {
+    let Some(constant) = constant(pool, index) else {
+        return Value::Null;
+    };
+
+    let mut object = Map::new();
+    object.insert("index".into(), json!(index));
+    object.insert("tag".into(), json!(constant.tag_name()));
+
+    let details = match constant {
+        ConstantPoolInfo::Utf8(value) => json!({ "value": value }),
+        ConstantPoolInfo::Utf16(units) => json!({ "value": String::from_utf16_lossy(units) }),
+        ConstantPoolInfo::Integer(value) => json!({ "value": value }),
+        ConstantPoolInfo::Long(value) => json!({ "value": value }),
+        ConstantPoolInfo::Float(value) => json!({ "value": float_json(*value as f64) }),
+        ConstantPoolInfo::Double(value) => json!({ "value": float_json(*value) }),
+        ConstantPoolInfo::Class { name_index } => json!({
+            "name_index": name_index,
+            "name": utf8_at(pool, *name_index),
+        }),
+        ConstantPoolInfo::String { string_index } => json!({
+            "string_index": string_index,
+            "value": utf8_at(pool, *string_index),
+        }),
+        ConstantPoolInfo::FieldRef {
+            class_index,
+            name_and_type_index,
+        }
+        | ConstantPoolInfo::MethodRef {
+            class_index,
+            name_and_type_index,
+        }
+        | ConstantPoolInfo::InterfaceMethodRef {
+            class_index,
+            name_and_type_index,
+        } => {
+            let (name, descriptor) = name_and_type_at(pool, *name_and_type_index).unzip();
+            json!({
+                "class_index": class_index,
+                "name_and_type_index": name_and_type_index,
+                "class": class_name_at(pool, *class_index),
+                "name": name,
+                "descriptor": descriptor,
+            })
+        }
+        ConstantPoolInfo::NameAndType {
+            name_index,
+            descriptor_index,
+        } => json!({
+            "name_index": name_index,
+            "descriptor_index": descriptor_index,
+            "name": utf8_at(pool, *name_index),
+            "descriptor": utf8_at(pool, *descriptor_index),
+        }),
+        ConstantPoolInfo::MethodHandle {
+            reference_kind,
+            reference_index,
+        } => json!({
+            "reference_kind": reference_kind,
+            "reference_kind_name": REFERENCE_KINDS.get(*reference_kind as usize),
+            "reference_index": reference_index,
+            "reference": constant_json(pool, *reference_index),
+        }),
+        ConstantPoolInfo::MethodType { descriptor_index } => json!({
+            "descriptor_index": descriptor_index,
+            "descriptor": utf8_at(pool, *descriptor_index),
+        }),
+        ConstantPoolInfo::Dynamic {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        }
+        | ConstantPoolInfo::InvokeDynamic {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        } => {
+            let (name, descriptor) = name_and_type_at(pool, *name_and_type_index).unzip();
+            json!({
+                "bootstrap_method_attr_index": bootstrap_method_attr_index,
+                "name_and_type_index": name_and_type_index,
+                "name": name,
+                "descriptor": descriptor,
+            })
+        }
+        ConstantPoolInfo::Module { name_index } | ConstantPoolInfo::Package { name_index } => {
+            json!({
+                "name_index": name_index,
+                "name": utf8_at(pool, *name_index),
+            })
+        }
+        ConstantPoolInfo::Unusable => json!({}),
+    };
+
+    if let Value::Object(details) = details {
+        object.extend(details);
+    }
+    Value::Object(object)
+}
+
+fn attributes_json(pool: &[ConstantPoolInfo], attributes: &[Attribute]) -> Vec<Value> {
+    attributes
+        .iter()
+        .map(|attribute| attribute_json(pool, attribute))
+        .collect()
+}
+
+fn attribute_json(pool: &[ConstantPoolInfo], attribute: &Attribute) -> Value {
+    let class_names = |indexes: &[u16]| -> Vec<Option<&str>> {
+        indexes.iter().map(|i| class_name_at(pool, *i)).collect()
+    };
+
+    let details = match &attribute.info {
+        AttributeInfo::Code {
+            max_stack,
+            max_locals,
+            code,
+            exception_table,
+            attributes,
+        } => json!({
+            "max_stack": max_stack,
+            "max_locals": max_locals,
+            "code": code
+                .iter()
+                .map(|(offset, instruction)| instruction_json(pool, *offset, instruction))
+                .collect::<Vec<_>>(),
+            "exception_table": exception_table.iter().map(|exception| json!({
+                "start_pc": exception.start_pc,
+                "end_pc": exception.end_pc,
+                "handler_pc": exception.handler_pc,
+                "catch_type": class_name_at(pool, exception.catch_type),
+            })).collect::<Vec<_>>(),
+            "attributes": attributes_json(pool, attributes),
+        }),
+        AttributeInfo::ConstantValue(index) => json!({ "value": constant_json(pool, *index) }),
+        AttributeInfo::SourceFile(value) | AttributeInfo::Signature(value) => {
+            json!({ "value": value })
+        }
+        AttributeInfo::Exceptions(exceptions) => json!({ "exceptions": class_names(exceptions) }),
+        AttributeInfo::LineNumberTable(line_numbers) => json!({
+            "line_numbers": line_numbers.iter().map(|line| json!({
+                "start_pc": line.start_pc,
+                "line_number": line.line_number,
+            })).collect::<Vec<_>>(),
+        }),
+        AttributeInfo::LocalVariableTable(variables)
+        | AttributeInfo::LocalVariableTypeTable(variables) => json!({
+            "local_variables": variables.iter().map(|variable| json!({
+                "start_pc": variable.start_pc,
+                "length": variable.length,
+                "index": variable.index,
+                "name": utf8_at(pool, variable.name_index),
+                "descriptor": utf8_at(pool, variable.descriptor_index),
+            })).collect::<Vec<_>>(),
+        }),
+        AttributeInfo::StackMapTable(frames) => json!({
+            "frames": frames.iter().map(|frame| json!({
+                "frame_type": frame.frame_type,
+                "offset_delta": frame.offset_delta,
+                "locals": frame.locals.iter().map(|t| verification_type_json(pool, t)).collect::<Vec<_>>(),
+                "stack": frame.stack.iter().map(|t| verification_type_json(pool, t)).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+        }),
+        AttributeInfo::BootstrapMethods(methods) => json!({
+            "methods": methods.iter().map(|method| json!({
+                "method": constant_json(pool, method.bootstrap_method_ref),
+                "arguments": method
+                    .bootstrap_arguments
+                    .iter()
+                    .map(|index| constant_json(pool, *index))
+                    .collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+        }),
+        AttributeInfo::InnerClasses(classes) => json!({
+            "classes": classes.iter().map(|class| json!({
+                "inner_class": class_name_at(pool, class.inner_class_info_index),
+                "outer_class": class_name_at(pool, class.outer_class_info_index),
+                "inner_name": utf8_at(pool, class.inner_name_index),
+                "access_flags": flags_json(class.inner_class_access_flags, FlagsOwner::InnerClass),
+            })).collect::<Vec<_>>(),
+        }),
+        AttributeInfo::EnclosingMethod {
+            class_index,
+            method_index,
+        } => json!({
+            "class": class_name_at(pool, *class_index),
+            "method": name_and_type_at(pool, *method_index)
+                .map(|(name, descriptor)| json!({ "name": name, "descriptor": descriptor })),
+        }),
+        AttributeInfo::NestHost(index) => json!({ "host": class_name_at(pool, *index) }),
+        AttributeInfo::NestMembers(classes) | AttributeInfo::PermittedSubclasses(classes) => {
+            json!({ "classes": class_names(classes) })
+        }
+        AttributeInfo::RuntimeVisibleAnnotations(annotations)
+        | AttributeInfo::RuntimeInvisibleAnnotations(annotations) => json!({
+            "annotations": annotations.iter().map(|a| annotation_json(pool, a)).collect::<Vec<_>>(),
+        }),
+        AttributeInfo::RuntimeVisibleParameterAnnotations(parameters)
+        | AttributeInfo::RuntimeInvisibleParameterAnnotations(parameters) => json!({
+            "parameters": parameters
+                .iter()
+                .map(|annotations| annotations.iter().map(|a| annotation_json(pool, a)).collect::<Vec<_>>())
+                .collect::<Vec<_>>(),
+        }),
+        AttributeInfo::AnnotationDefault(value) => {
+            json!({ "value": element_value_json(pool, value) })
+        }
+        AttributeInfo::Deprecated | AttributeInfo::Synthetic => json!({}),
+        AttributeInfo::Uncrecognized(data) => {
+            let hex: String = data.iter().map(|byte| format!("{byte:02x}")).collect();
+            json!({ "data": hex })
+        }
+    };
+
+    let mut object = Map::new();
+    object.insert("name".into(), json!(utf8_at(pool, attribute.name_index)));
+    if let Value::Object(details) = details {
+        object.extend(details);
+    }
+    Value::Object(object)
+}
+
+fn verification_type_json(
+    pool: &[ConstantPoolInfo],
+    verification_type: &VerificationType,
+) -> Value {
+    match verification_type {
+        VerificationType::Top => json!({ "type": "top" }),
+        VerificationType::Integer => json!({ "type": "int" }),
+        VerificationType::Float => json!({ "type": "float" }),
+        VerificationType::Double => json!({ "type": "double" }),
+        VerificationType::Long => json!({ "type": "long" }),
+        VerificationType::Null => json!({ "type": "null" }),
+        VerificationType::UninitializedThis => json!({ "type": "uninitialized_this" }),
+        VerificationType::Object(index) => json!({
+            "type": "object",
+            "class": class_name_at(pool, *index),
+        }),
+        VerificationType::Uninitialized(offset) => json!({
+            "type": "uninitialized",
+            "offset": offset,
+        }),
+    }
+}
+
+fn annotation_json(pool: &[ConstantPoolInfo], annotation: &Annotation) -> Value {
+    json!({
+        "type": utf8_at(pool, annotation.type_index),
+        "elements": annotation.element_value_pairs.iter().map(|(name, value)| json!({
+            "name": utf8_at(pool, *name),
+            "value": element_value_json(pool, value),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+fn element_value_json(pool: &[ConstantPoolInfo], value: &ElementValue) -> Value {
+    match value {
+        ElementValue::Const {
+            tag,
+            const_value_index,
+        } => json!({
+            "tag": (*tag as char).to_string(),
+            "value": constant_json(pool, *const_value_index),
+        }),
+        ElementValue::Enum {
+            type_name_index,
+            const_name_index,
+        } => json!({
+            "tag": "e",
+            "type": utf8_at(pool, *type_name_index),
+            "name": utf8_at(pool, *const_name_index),
+        }),
+        ElementValue::Class(index) => json!({ "tag": "c", "class": utf8_at(pool, *index) }),
+        ElementValue::Annotation(annotation) => json!({
+            "tag": "@",
+            "annotation": annotation_json(pool, annotation),
+        }),
+        ElementValue::Array(values) => json!({
+            "tag": "[",
+            "values": values.iter().map(|v| element_value_json(pool, v)).collect::<Vec<_>>(),
+        }),
+    }
+}
+
+fn instruction_json(pool: &[ConstantPoolInfo], offset: u32, instruction: &Instruction) -> Value {
+    let mut object = Map::new();
+    object.insert("offset".into(), json!(offset));
+    object.insert("opcode".into(), json!(instruction.opcode()));
+    object.insert("mnemonic".into(), json!(instruction.mnemonic()));
+
+    let target = |relative: i64| json!(offset as i64 + relative);
+
+    let operands: Vec<i64> = match instruction {
+        Instruction::BiPush(value) => vec![*value as i64],
+        Instruction::SiPush(value) => vec![*value as i64],
+        Instruction::ILoad(index)
+        | Instruction::LLoad(index)
+        | Instruction::FLoad(index)
+        | Instruction::DLoad(index)
+        | Instruction::ALoad(index)
+        | Instruction::IStore(index)
+        | Instruction::LStore(index)
+        | Instruction::FStore(index)
+        | Instruction::DStore(index)
+        | Instruction::AStore(index)
+        | Instruction::Ret(index)
+        | Instruction::NewArray(index) => vec![*index as i64],
+        Instruction::IInc(index, value) => vec![*index as i64, *value as i64],
+        Instruction::IfEq(relative)
+        | Instruction::IfNe(relative)
+        | Instruction::IfLt(relative)
+        | Instruction::IfGe(relative)
+        | Instruction::IfGt(relative)
+        | Instruction::IfLe(relative)
+        | Instruction::IfICmpEq(relative)
+        | Instruction::IfICmpNe(relative)
+        | Instruction::IfICmpLt(relative)
+        | Instruction::IfICmpGe(relative)
+        | Instruction::IfICmpGt(relative)
+        | Instruction::IfICmpLe(relative)
+        | Instruction::IfACmpEq(relative)
+        | Instruction::IfACmpNe(relative)
+        | Instruction::IfNull(relative)
+        | Instruction::IfNonNull(relative)
+        | Instruction::Goto(relative)
+        | Instruction::Jsr(relative) => {
+            object.insert("target".into(), target(*relative as i64));
+            vec![*relative as i64]
+        }
+        Instruction::GotoW(relative) | Instruction::JsrW(relative) => {
+            object.insert("target".into(), target(*relative as i64));
+            vec![*relative as i64]
+        }
+        Instruction::InvokeInterface(index, count) => vec![*index as i64, *count as i64],
+        Instruction::MultiANewArray(index, dimensions) => vec![*index as i64, *dimensions as i64],
+        Instruction::TableSwitch {
+            default,
+            low,
+            high,
+            offsets,
+        } => {
+            object.insert("default".into(), target(*default as i64));
+            object.insert(
+                "targets".into(),
+                offsets.iter().map(|o| target(*o as i64)).collect(),
+            );
+            vec![*low as i64, *high as i64]
+        }
+        Instruction::LookupSwitch { default, pairs } => {
+            object.insert("default".into(), target(*default as i64));
+            object.insert(
+                "pairs".into(),
+                pairs
+                    .iter()
+                    .map(|(key, o)| json!({ "key": key, "target": target(*o as i64) }))
+                    .collect(),
+            );
+            vec![]
+        }
+        Instruction::Wide(wide) => {
+            object.insert("wide".into(), json!(wide.mnemonic()));
+            match wide {
+                Wide::IInc(index, value) => vec![*index as i64, *value as i64],
+                _ => vec![wide.index() as i64],
+            }
+        }
+        _ => instruction
+            .constant_index()
+            .map(|index| vec![index as i64])
+            .unwrap_or_default(),
+    };
+    object.insert("operands".into(), json!(operands));
+
+    if let Some(index) = instruction.constant_index() {
+        object.insert("constant".into(), constant_json(pool, index));
+    }
+
+    Value::Object(object)
+}
@@ -1,25 +1,32 @@
+pub mod assembly;
+pub mod callgraph;
+pub mod cfg;
+pub mod classpath;
+pub mod cli;
+pub mod hexdump;
+pub mod hierarchy;
+pub mod interpreter;
+pub mod jar;
+pub mod javap;
+pub mod jimage;
+pub mod jmod;
+#[cfg(feature = "json")]
+pub mod json;
 pub mod raw;
+pub mod resolve;
+pub mod typecheck;
+pub mod verify;
+pub mod zip;
 
-use crate::raw::class::Class;
-use std::fs::File;
-
-fn main() -> std::io::Result<()> {
-    let mut args = std::env::args();
-    let _ = args.next();
-
-    let Some(filename) = args.next() else {
-        eprintln!("please provide class file as a program argument");
-        std::process::exit(2)
-    };
-
-    let Ok(file) = File::open(filename) else {
-        eprintln!("Couldn't open Hello.class file");
-        std::process::exit(1)
-    };
-    let mut class = Class::from(file)?;
-    class.resolve_attributes()?;
-
-    class.disassemble();
-
-    Ok(())
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // Deep recursion of interpreted methods needs more than the stack of the main thread
+    let thread = std::thread::Builder::new()
+        .stack_size(interpreter::STACK_SIZE)
+        .spawn(|| cli::run(args.into_iter()))
+        .expect("cannot start the main thread");
+    match thread.join() {
+        Ok(status) => std::process::exit(status),
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
 }
@@ -11,12 +11,91 @@ pub enum AccessFlags {
     Static = 0x0008,
     Final = 0x0010,
     Synchronized = 0x0020,
+    Super = 0x0020,
     Bridge = 0x0040,
+    Volatile = 0x0040,
     Varargs = 0x0080,
+    Transient = 0x0080,
     Native = 0x0100,
+    Interface = 0x0200,
     Abstract = 0x0400,
     Strict = 0x0800,
     Synthetic = 0x1000,
+    Annotation = 0x2000,
+    Enum = 0x4000,
+    Module = 0x8000,
+}
+
+/// Meaning of some bits depends on what structure they are attached to
+/// (0x0020 is ACC_SUPER for classes and ACC_SYNCHRONIZED for methods).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagsOwner {
+    Class,
+    Field,
+    Method,
+    InnerClass,
+}
+
+impl AccessFlags {
+    /// Names of flags as printed by `javap -v`, for example `ACC_PUBLIC`
+    pub fn javap_names(&self, owner: FlagsOwner) -> Vec<&'static str> {
+        let table: &[(u16, &str)] = match owner {
+            FlagsOwner::Class => &[
+                (0x0001, "ACC_PUBLIC"),
+                (0x0010, "ACC_FINAL"),
+                (0x0020, "ACC_SUPER"),
+                (0x0200, "ACC_INTERFACE"),
+                (0x0400, "ACC_ABSTRACT"),
+                (0x1000, "ACC_SYNTHETIC"),
+                (0x2000, "ACC_ANNOTATION"),
+                (0x4000, "ACC_ENUM"),
+                (0x8000, "ACC_MODULE"),
+            ],
+            FlagsOwner::InnerClass => &[
+                (0x0001, "ACC_PUBLIC"),
+                (0x0002, "ACC_PRIVATE"),
+                (0x0004, "ACC_PROTECTED"),
+                (0x0008, "ACC_STATIC"),
+                (0x0010, "ACC_FINAL"),
+                (0x0200, "ACC_INTERFACE"),
+                (0x0400, "ACC_ABSTRACT"),
+                (0x1000, "ACC_SYNTHETIC"),
+                (0x2000, "ACC_ANNOTATION"),
+                (0x4000, "ACC_ENUM"),
+            ],
+            FlagsOwner::Field => &[
+                (0x0001, "ACC_PUBLIC"),
+                (0x0002, "ACC_PRIVATE"),
+                (0x0004, "ACC_PROTECTED"),
+                (0x0008, "ACC_STATIC"),
+                (0x0010, "ACC_FINAL"),
+                (0x0040, "ACC_VOLATILE"),
+                (0x0080, "ACC_TRANSIENT"),
+                (0x1000, "ACC_SYNTHETIC"),
+                (0x4000, "ACC_ENUM"),
+            ],
+            FlagsOwner::Method => &[
+                (0x0001, "ACC_PUBLIC"),
+                (0x0002, "ACC_PRIVATE"),
+                (0x0004, "ACC_PROTECTED"),
+                (0x0008, "ACC_STATIC"),
+                (0x0010, "ACC_FINAL"),
+                (0x0020, "ACC_SYNCHRONIZED"),
+                (0x0040, "ACC_BRIDGE"),
+                (0x0080, "ACC_VARARGS"),
+                (0x0100, "ACC_NATIVE"),
+                (0x0400, "ACC_ABSTRACT"),
+                (0x0800, "ACC_STRICT"),
+                (0x1000, "ACC_SYNTHETIC"),
+            ],
+        };
+
+        table
+            .iter()
+            .filter(|(bit, _)| self.bits() & bit != 0)
+            .map(|(_, name)| *name)
+            .collect()
+    }
 }
 
 impl std::fmt::Display for AccessFlags {
@@ -1,34 +1,136 @@
 use crate::raw::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Exception {
     pub start_pc: u16,
     pub end_pc: u16,
     pub handler_pc: u16,
+    /// Index of the caught Class, 0 for handlers catching everything (`finally`)
     pub catch_type: u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LineNumber {
     pub start_pc: u16,
     pub line_number: u16,
 }
 
-#[derive(Debug)]
+/// Entry of LocalVariableTable or LocalVariableTypeTable, the latter storing
+/// generic signature instead of descriptor under `descriptor_index`
+#[derive(Debug, Clone)]
+pub struct LocalVariable {
+    pub start_pc: u16,
+    pub length: u16,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub index: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationType {
+    Top,
+    Integer,
+    Float,
+    Double,
+    Long,
+    Null,
+    UninitializedThis,
+    Object(u16),
+    /// Offset of the `new` instruction that created the object
+    Uninitialized(u16),
+}
+
+/// Entry of StackMapTable. Frame type determines which of the fields are meaningful:
+///
+/// - 0..=63 same frame, `offset_delta` equal to frame type
+/// - 64..=127 same locals with one stack item, `offset_delta` is frame type - 64
+/// - 247 same locals with one stack item, extended
+/// - 248..=250 chop last `251 - frame_type` locals
+/// - 251 same frame, extended
+/// - 252..=254 append `frame_type - 251` locals
+/// - 255 full frame
+#[derive(Debug, Clone)]
+pub struct StackMapFrame {
+    pub frame_type: u8,
+    pub offset_delta: u16,
+    pub locals: Vec<VerificationType>,
+    pub stack: Vec<VerificationType>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BootstrapMethod {
+    pub bootstrap_method_ref: u16,
+    pub bootstrap_arguments: Vec<u16>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InnerClass {
+    pub inner_class_info_index: u16,
+    pub outer_class_info_index: u16,
+    pub inner_name_index: u16,
+    pub inner_class_access_flags: AccessFlags,
+}
+
+#[derive(Debug, Clone)]
+pub struct Annotation {
+    pub type_index: u16,
+    pub element_value_pairs: Vec<(u16, ElementValue)>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ElementValue {
+    /// Constant of a primitive type or String, tag is one of `BCDFIJSZs`
+    Const {
+        tag: u8,
+        const_value_index: u16,
+    },
+    Enum {
+        type_name_index: u16,
+        const_name_index: u16,
+    },
+    Class(u16),
+    Annotation(Annotation),
+    Array(Vec<ElementValue>),
+}
+
+#[derive(Debug, Clone)]
 pub enum AttributeInfo {
+    AnnotationDefault(ElementValue),
+    BootstrapMethods(Vec<BootstrapMethod>),
     Code {
         max_stack: u16,
         max_locals: u16,
-        code: Vec<Instruction>,
+        /// Instructions paired with their offset from the start of the code
+        code: Vec<(u32, Instruction)>,
         exception_table: Vec<Exception>,
         attributes: Vec<Attribute>,
     },
+    ConstantValue(u16),
+    Deprecated,
+    EnclosingMethod {
+        class_index: u16,
+        method_index: u16,
+    },
+    Exceptions(Vec<u16>),
+    InnerClasses(Vec<InnerClass>),
     LineNumberTable(Vec<LineNumber>),
+    LocalVariableTable(Vec<LocalVariable>),
+    LocalVariableTypeTable(Vec<LocalVariable>),
+    NestHost(u16),
+    NestMembers(Vec<u16>),
+    PermittedSubclasses(Vec<u16>),
+    RuntimeInvisibleAnnotations(Vec<Annotation>),
+    RuntimeInvisibleParameterAnnotations(Vec<Vec<Annotation>>),
+    RuntimeVisibleAnnotations(Vec<Annotation>),
+    RuntimeVisibleParameterAnnotations(Vec<Vec<Annotation>>),
+    Signature(String),
     SourceFile(String),
+    StackMapTable(Vec<StackMapFrame>),
+    Synthetic,
     Uncrecognized(Vec<u8>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Attribute {
     pub name_index: u16,
     pub info: AttributeInfo,
@@ -36,82 +138,680 @@ pub struct Attribute {
 
 impl Attribute {
     pub fn from<F: ByteUtils>(file: &mut F) -> std::io::Result<Attribute> {
-        let name_index = file.read_u2()?;
-        let attribute_length = file.read_u4()?;
+        let name_index = file.field("attribute_name_index").read_u2()?;
+        let attribute_length = file.field("attribute_length").read_u4()?;
         let mut info = vec![0u8; attribute_length as usize];
-        file.read_exact(info.as_mut_slice())?;
+        file.field("info").read_exact(info.as_mut_slice())?;
         Ok(Attribute {
             name_index,
             info: AttributeInfo::Uncrecognized(info),
         })
     }
+
+    /// Encodes the attribute, taking the content from `info`
+    pub fn write(
+        &self,
+        constant_pool: &[ConstantPoolInfo],
+        out: &mut Vec<u8>,
+    ) -> std::io::Result<()> {
+        let mut info = vec![];
+        self.info.write(constant_pool, &mut info)?;
+        out.put_u2(self.name_index);
+        out.put_u4(info.len() as u32);
+        out.extend_from_slice(&info);
+        Ok(())
+    }
+}
+
+impl Exception {
+    pub fn from<F: ByteUtils>(file: &mut F) -> std::io::Result<Exception> {
+        Ok(Exception {
+            start_pc: file.field("start_pc").read_u2()?,
+            end_pc: file.field("end_pc").read_u2()?,
+            handler_pc: file.field("handler_pc").read_u2()?,
+            catch_type: file.field("catch_type").read_u2()?,
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.put_u2(self.start_pc);
+        out.put_u2(self.end_pc);
+        out.put_u2(self.handler_pc);
+        out.put_u2(self.catch_type);
+    }
 }
 
 impl LineNumber {
     pub fn from<F: ByteUtils>(file: &mut F) -> std::io::Result<LineNumber> {
         Ok(LineNumber {
-            start_pc: file.read_u2()?,
-            line_number: file.read_u2()?,
+            start_pc: file.field("start_pc").read_u2()?,
+            line_number: file.field("line_number").read_u2()?,
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.put_u2(self.start_pc);
+        out.put_u2(self.line_number);
+    }
+}
+
+impl LocalVariable {
+    pub fn from<F: ByteUtils>(file: &mut F) -> std::io::Result<LocalVariable> {
+        Ok(LocalVariable {
+            start_pc: file.field("start_pc").read_u2()?,
+            length: file.field("length").read_u2()?,
+            name_index: file.field("name_index").read_u2()?,
+            descriptor_index: file.field("descriptor_index").read_u2()?,
+            index: file.field("index").read_u2()?,
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.put_u2(self.start_pc);
+        out.put_u2(self.length);
+        out.put_u2(self.name_index);
+        out.put_u2(self.descriptor_index);
+        out.put_u2(self.index);
+    }
+}
+
+impl VerificationType {
+    pub fn from<F: ByteUtils>(file: &mut F) -> std::io::Result<VerificationType> {
+        let tag = file.field("tag").read_u1()?;
+        Ok(match tag {
+            0 => VerificationType::Top,
+            1 => VerificationType::Integer,
+            2 => VerificationType::Float,
+            3 => VerificationType::Double,
+            4 => VerificationType::Long,
+            5 => VerificationType::Null,
+            6 => VerificationType::UninitializedThis,
+            7 => VerificationType::Object(file.field("cpool_index").read_u2()?),
+            8 => VerificationType::Uninitialized(file.field("offset").read_u2()?),
+            _ => return Err(invalid_data(format!("unknown verification type tag {tag}"))),
         })
     }
+
+    pub fn tag(&self) -> u8 {
+        match self {
+            VerificationType::Top => 0,
+            VerificationType::Integer => 1,
+            VerificationType::Float => 2,
+            VerificationType::Double => 3,
+            VerificationType::Long => 4,
+            VerificationType::Null => 5,
+            VerificationType::UninitializedThis => 6,
+            VerificationType::Object(_) => 7,
+            VerificationType::Uninitialized(_) => 8,
+        }
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.put_u1(self.tag());
+        match self {
+            VerificationType::Object(index) | VerificationType::Uninitialized(index) => {
+                out.put_u2(*index)
+            }
+            _ => {}
+        }
+    }
+}
+
+impl StackMapFrame {
+    pub fn from<F: ByteUtils>(file: &mut F) -> std::io::Result<StackMapFrame> {
+        let frame_type = file.field("frame_type").read_u1()?;
+        let mut frame = StackMapFrame {
+            frame_type,
+            offset_delta: 0,
+            locals: vec![],
+            stack: vec![],
+        };
+
+        match frame_type {
+            0..=63 => frame.offset_delta = frame_type.into(),
+            64..=127 => {
+                frame.offset_delta = (frame_type - 64).into();
+                frame.stack = file.read_table("stack", 1, VerificationType::from)?;
+            }
+            247 => {
+                frame.offset_delta = file.field("offset_delta").read_u2()?;
+                frame.stack = file.read_table("stack", 1, VerificationType::from)?;
+            }
+            248..=251 => frame.offset_delta = file.field("offset_delta").read_u2()?,
+            252..=254 => {
+                frame.offset_delta = file.field("offset_delta").read_u2()?;
+                frame.locals =
+                    file.read_table("locals", (frame_type - 251).into(), VerificationType::from)?;
+            }
+            255 => {
+                frame.offset_delta = file.field("offset_delta").read_u2()?;
+                let number_of_locals = file.field("number_of_locals").read_u2()?;
+                frame.locals =
+                    file.read_table("locals", number_of_locals.into(), VerificationType::from)?;
+                let number_of_stack_items = file.field("number_of_stack_items").read_u2()?;
+                frame.stack = file.read_table(
+                    "stack",
+                    number_of_stack_items.into(),
+                    VerificationType::from,
+                )?;
+            }
+            _ => {
+                return Err(invalid_data(format!(
+                    "reserved stack map frame type {frame_type}"
+                )))
+            }
+        }
+
+        Ok(frame)
+    }
+
+    /// Encodes the frame in the layout selected by `frame_type`
+    pub fn write(&self, out: &mut Vec<u8>) -> std::io::Result<()> {
+        out.put_u1(self.frame_type);
+        match self.frame_type {
+            0..=63 => {}
+            64..=127 => self.write_types("stack", &self.stack, 1, out)?,
+            247 => {
+                out.put_u2(self.offset_delta);
+                self.write_types("stack", &self.stack, 1, out)?;
+            }
+            248..=251 => out.put_u2(self.offset_delta),
+            252..=254 => {
+                out.put_u2(self.offset_delta);
+                let count = (self.frame_type - 251).into();
+                self.write_types("locals", &self.locals, count, out)?;
+            }
+            255 => {
+                out.put_u2(self.offset_delta);
+                put_count(out, self.locals.len(), "full frame locals")?;
+                self.locals.iter().for_each(|local| local.write(out));
+                put_count(out, self.stack.len(), "full frame stack")?;
+                self.stack.iter().for_each(|item| item.write(out));
+            }
+            frame_type => {
+                return Err(invalid_data(format!(
+                    "reserved stack map frame type {frame_type}"
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes types whose count is implied by the frame type
+    fn write_types(
+        &self,
+        name: &str,
+        types: &[VerificationType],
+        count: usize,
+        out: &mut Vec<u8>,
+    ) -> std::io::Result<()> {
+        if types.len() != count {
+            return Err(invalid_data(format!(
+                "stack map frame of type {} needs {count} {name}, has {}",
+                self.frame_type,
+                types.len()
+            )));
+        }
+        types.iter().for_each(|item| item.write(out));
+        Ok(())
+    }
+}
+
+impl BootstrapMethod {
+    pub fn from<F: ByteUtils>(file: &mut F) -> std::io::Result<BootstrapMethod> {
+        let bootstrap_method_ref = file.field("bootstrap_method_ref").read_u2()?;
+        let num_bootstrap_arguments = file.field("num_bootstrap_arguments").read_u2()?;
+        Ok(BootstrapMethod {
+            bootstrap_method_ref,
+            bootstrap_arguments: file.read_table(
+                "bootstrap_arguments",
+                num_bootstrap_arguments.into(),
+                |f| f.read_u2(),
+            )?,
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) -> std::io::Result<()> {
+        out.put_u2(self.bootstrap_method_ref);
+        write_indexes(out, &self.bootstrap_arguments, "bootstrap arguments")
+    }
+}
+
+impl InnerClass {
+    pub fn from<F: ByteUtils>(file: &mut F) -> std::io::Result<InnerClass> {
+        Ok(InnerClass {
+            inner_class_info_index: file.field("inner_class_info_index").read_u2()?,
+            outer_class_info_index: file.field("outer_class_info_index").read_u2()?,
+            inner_name_index: file.field("inner_name_index").read_u2()?,
+            inner_class_access_flags: AccessFlags::from(
+                file.field("inner_class_access_flags").read_u2()?,
+            ),
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.put_u2(self.inner_class_info_index);
+        out.put_u2(self.outer_class_info_index);
+        out.put_u2(self.inner_name_index);
+        out.put_u2(self.inner_class_access_flags.bits());
+    }
+}
+
+impl Annotation {
+    pub fn from<F: ByteUtils>(file: &mut F) -> std::io::Result<Annotation> {
+        let type_index = file.field("type_index").read_u2()?;
+        let num_element_value_pairs = file.field("num_element_value_pairs").read_u2()?;
+        let element_value_pairs = file.read_table(
+            "element_value_pairs",
+            num_element_value_pairs.into(),
+            |file| {
+                let element_name_index = file.field("element_name_index").read_u2()?;
+                file.enter(|| "value".to_string());
+                let value = ElementValue::from(file)?;
+                file.leave();
+                Ok((element_name_index, value))
+            },
+        )?;
+        Ok(Annotation {
+            type_index,
+            element_value_pairs,
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) -> std::io::Result<()> {
+        out.put_u2(self.type_index);
+        put_count(out, self.element_value_pairs.len(), "element value pairs")?;
+        for (name_index, value) in &self.element_value_pairs {
+            out.put_u2(*name_index);
+            value.write(out)?;
+        }
+        Ok(())
+    }
+}
+
+impl ElementValue {
+    pub fn from<F: ByteUtils>(file: &mut F) -> std::io::Result<ElementValue> {
+        let tag = file.field("tag").read_u1()?;
+        Ok(match tag {
+            b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b's' => ElementValue::Const {
+                tag,
+                const_value_index: file.field("const_value_index").read_u2()?,
+            },
+            b'e' => ElementValue::Enum {
+                type_name_index: file.field("type_name_index").read_u2()?,
+                const_name_index: file.field("const_name_index").read_u2()?,
+            },
+            b'c' => ElementValue::Class(file.field("class_info_index").read_u2()?),
+            b'@' => {
+                file.enter(|| "annotation_value".to_string());
+                let annotation = Annotation::from(file)?;
+                file.leave();
+                ElementValue::Annotation(annotation)
+            }
+            b'[' => {
+                let num_values = file.field("num_values").read_u2()?;
+                ElementValue::Array(file.read_table(
+                    "values",
+                    num_values.into(),
+                    ElementValue::from,
+                )?)
+            }
+            _ => return Err(invalid_data(format!("unknown element value tag {tag}"))),
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) -> std::io::Result<()> {
+        match self {
+            ElementValue::Const {
+                tag,
+                const_value_index,
+            } => {
+                out.put_u1(*tag);
+                out.put_u2(*const_value_index);
+            }
+            ElementValue::Enum {
+                type_name_index,
+                const_name_index,
+            } => {
+                out.put_u1(b'e');
+                out.put_u2(*type_name_index);
+                out.put_u2(*const_name_index);
+            }
+            ElementValue::Class(index) => {
+                out.put_u1(b'c');
+                out.put_u2(*index);
+            }
+            ElementValue::Annotation(annotation) => {
+                out.put_u1(b'@');
+                annotation.write(out)?;
+            }
+            ElementValue::Array(values) => {
+                out.put_u1(b'[');
+                put_count(out, values.len(), "element value array")?;
+                for value in values {
+                    value.write(out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads `u2` count followed by that many `u2` constant pool indexes
+fn read_indexes<F: ByteUtils>(
+    bytes: &mut F,
+    count_name: &'static str,
+    name: &str,
+) -> std::io::Result<Vec<u16>> {
+    let count = bytes.field(count_name).read_u2()?;
+    bytes.read_table(name, count.into(), |f| f.read_u2())
+}
+
+/// Writes `u2` count followed by the `u2` indexes, counterpart of [`read_indexes`]
+fn write_indexes(out: &mut Vec<u8>, indexes: &[u16], name: &str) -> std::io::Result<()> {
+    put_count(out, indexes.len(), name)?;
+    indexes.iter().for_each(|index| out.put_u2(*index));
+    Ok(())
+}
+
+/// Writes `u2` count followed by the annotations
+fn write_annotations(out: &mut Vec<u8>, annotations: &[Annotation]) -> std::io::Result<()> {
+    put_count(out, annotations.len(), "annotations")?;
+    annotations
+        .iter()
+        .try_for_each(|annotation| annotation.write(out))
+}
+
+/// Index of the first Utf8 constant equal to `value`, attributes that keep
+/// their strings resolved need it to be written back
+fn utf8_index(constant_pool: &[ConstantPoolInfo], value: &str) -> std::io::Result<u16> {
+    constant_pool
+        .iter()
+        .position(|constant| matches!(constant, ConstantPoolInfo::Utf8(utf8) if utf8 == value))
+        .map(|index| index as u16 + 1)
+        .ok_or_else(|| invalid_data(format!("constant pool has no Utf8 {value:?}")))
 }
 
 impl AttributeInfo {
-    pub fn from(
+    /// Parses content of the attribute called `name`, returns `None` for unknown attributes
+    pub fn from<F: ByteUtils>(
         constant_pool: &[ConstantPoolInfo],
         name: &str,
-        mut bytes: &[u8],
+        mut bytes: F,
     ) -> std::io::Result<Option<AttributeInfo>> {
         Ok(match name {
             "Code" => {
-                let max_stack = bytes.read_u2()?;
-                let max_locals = bytes.read_u2()?;
-
-                let code_length = bytes.read_u4()? as usize;
-                let mut code: Vec<Instruction> = vec![];
-                let mut code_bytes = &bytes[..code_length];
-                while !code_bytes.is_empty() {
-                    code.push(Instruction::from(&mut code_bytes)?);
+                let max_stack = bytes.field("max_stack").read_u2()?;
+                let max_locals = bytes.field("max_locals").read_u2()?;
+
+                let code_length = bytes.field("code_length").read_u4()?;
+                let mut code: Vec<(u32, Instruction)> = vec![];
+                let mut offset = 0;
+                while offset < code_length {
+                    bytes.enter(|| format!("code[{offset}]"));
+                    let instruction = Instruction::from(&mut bytes, offset)?;
+                    bytes.leave();
+                    let length = instruction.length(offset);
+                    code.push((offset, instruction));
+                    offset += length;
+                }
+                if offset != code_length {
+                    return Err(invalid_data(format!(
+                        "last instruction crosses the end of code of length {code_length}"
+                    )));
                 }
-                bytes = &bytes[code_length..];
 
-                let exception_table_length = bytes.read_u2()?;
-                assert!(exception_table_length == 0);
+                let exception_table_length = bytes.field("exception_table_length").read_u2()?;
+                let exception_table = bytes.read_table(
+                    "exception_table",
+                    exception_table_length.into(),
+                    Exception::from,
+                )?;
 
-                let attributes_count = bytes.read_u2()?;
-                let mut attributes = bytes.read_array(attributes_count.into(), Attribute::from)?;
+                let attributes_count = bytes.field("attributes_count").read_u2()?;
+                let mut attributes =
+                    bytes.read_table("attributes", attributes_count.into(), Attribute::from)?;
                 resolve_attributes(constant_pool, attributes.iter_mut())?;
 
                 Some(AttributeInfo::Code {
                     max_stack,
                     max_locals,
                     code,
-                    exception_table: vec![],
+                    exception_table,
                     attributes,
                 })
             }
             "SourceFile" => {
-                if let ConstantPoolInfo::Utf8(source) =
-                    &constant_pool[bytes.read_u2()? as usize - 1]
-                {
-                    Some(AttributeInfo::SourceFile(source.to_string()))
-                } else {
-                    panic!();
-                }
+                let index = bytes.field("sourcefile_index").read_u2()?;
+                let Some(source) = utf8_at(constant_pool, index) else {
+                    return Err(invalid_data(format!(
+                        "SourceFile points to #{index} which is not Utf8"
+                    )));
+                };
+                Some(AttributeInfo::SourceFile(source.to_string()))
+            }
+            "Signature" => {
+                let index = bytes.field("signature_index").read_u2()?;
+                let Some(signature) = utf8_at(constant_pool, index) else {
+                    return Err(invalid_data(format!(
+                        "Signature points to #{index} which is not Utf8"
+                    )));
+                };
+                Some(AttributeInfo::Signature(signature.to_string()))
             }
             "LineNumberTable" => {
-                let line_number_table_length = bytes.read_u2()?;
-                Some(AttributeInfo::LineNumberTable(bytes.read_array(
+                let line_number_table_length = bytes.field("line_number_table_length").read_u2()?;
+                Some(AttributeInfo::LineNumberTable(bytes.read_table(
+                    "line_number_table",
                     line_number_table_length.into(),
                     LineNumber::from,
                 )?))
             }
-            _ => {
-                println!("Unrecognized attribute name: {name}");
-                None
+            "LocalVariableTable" | "LocalVariableTypeTable" => {
+                let local_variable_table_length = bytes.field("table_length").read_u2()?;
+                let table = bytes.read_table(
+                    "table",
+                    local_variable_table_length.into(),
+                    LocalVariable::from,
+                )?;
+                Some(if name == "LocalVariableTable" {
+                    AttributeInfo::LocalVariableTable(table)
+                } else {
+                    AttributeInfo::LocalVariableTypeTable(table)
+                })
+            }
+            "StackMapTable" => {
+                let number_of_entries = bytes.field("number_of_entries").read_u2()?;
+                Some(AttributeInfo::StackMapTable(bytes.read_table(
+                    "entries",
+                    number_of_entries.into(),
+                    StackMapFrame::from,
+                )?))
+            }
+            "BootstrapMethods" => {
+                let num_bootstrap_methods = bytes.field("num_bootstrap_methods").read_u2()?;
+                Some(AttributeInfo::BootstrapMethods(bytes.read_table(
+                    "bootstrap_methods",
+                    num_bootstrap_methods.into(),
+                    BootstrapMethod::from,
+                )?))
+            }
+            "InnerClasses" => {
+                let number_of_classes = bytes.field("number_of_classes").read_u2()?;
+                Some(AttributeInfo::InnerClasses(bytes.read_table(
+                    "classes",
+                    number_of_classes.into(),
+                    InnerClass::from,
+                )?))
             }
+            "ConstantValue" => Some(AttributeInfo::ConstantValue(
+                bytes.field("constantvalue_index").read_u2()?,
+            )),
+            "Exceptions" => Some(AttributeInfo::Exceptions(read_indexes(
+                &mut bytes,
+                "number_of_exceptions",
+                "exception_index_table",
+            )?)),
+            "NestHost" => Some(AttributeInfo::NestHost(
+                bytes.field("host_class_index").read_u2()?,
+            )),
+            "NestMembers" => Some(AttributeInfo::NestMembers(read_indexes(
+                &mut bytes,
+                "number_of_classes",
+                "classes",
+            )?)),
+            "PermittedSubclasses" => Some(AttributeInfo::PermittedSubclasses(read_indexes(
+                &mut bytes,
+                "number_of_classes",
+                "classes",
+            )?)),
+            "EnclosingMethod" => Some(AttributeInfo::EnclosingMethod {
+                class_index: bytes.field("class_index").read_u2()?,
+                method_index: bytes.field("method_index").read_u2()?,
+            }),
+            "RuntimeVisibleAnnotations" | "RuntimeInvisibleAnnotations" => {
+                let num_annotations = bytes.field("num_annotations").read_u2()?;
+                let annotations =
+                    bytes.read_table("annotations", num_annotations.into(), Annotation::from)?;
+                Some(if name == "RuntimeVisibleAnnotations" {
+                    AttributeInfo::RuntimeVisibleAnnotations(annotations)
+                } else {
+                    AttributeInfo::RuntimeInvisibleAnnotations(annotations)
+                })
+            }
+            "RuntimeVisibleParameterAnnotations" | "RuntimeInvisibleParameterAnnotations" => {
+                let num_parameters = bytes.field("num_parameters").read_u1()?;
+                let parameters =
+                    bytes.read_table("parameter_annotations", num_parameters.into(), |bytes| {
+                        let num_annotations = bytes.field("num_annotations").read_u2()?;
+                        bytes.read_table("annotations", num_annotations.into(), Annotation::from)
+                    })?;
+                Some(if name == "RuntimeVisibleParameterAnnotations" {
+                    AttributeInfo::RuntimeVisibleParameterAnnotations(parameters)
+                } else {
+                    AttributeInfo::RuntimeInvisibleParameterAnnotations(parameters)
+                })
+            }
+            "AnnotationDefault" => {
+                bytes.enter(|| "default_value".to_string());
+                let value = ElementValue::from(&mut bytes)?;
+                bytes.leave();
+                Some(AttributeInfo::AnnotationDefault(value))
+            }
+            "Deprecated" => Some(AttributeInfo::Deprecated),
+            "Synthetic" => Some(AttributeInfo::Synthetic),
+            _ => None,
         })
     }
+
+    /// Encodes content of the attribute, counterpart of [`AttributeInfo::from`]
+    pub fn write(
+        &self,
+        constant_pool: &[ConstantPoolInfo],
+        out: &mut Vec<u8>,
+    ) -> std::io::Result<()> {
+        match self {
+            AttributeInfo::Code {
+                max_stack,
+                max_locals,
+                code,
+                exception_table,
+                attributes,
+            } => {
+                out.put_u2(*max_stack);
+                out.put_u2(*max_locals);
+                let mut bytes = vec![];
+                for (offset, instruction) in code {
+                    if *offset as usize != bytes.len() {
+                        return Err(invalid_data(format!(
+                            "instruction at offset {offset} would be encoded at {}",
+                            bytes.len()
+                        )));
+                    }
+                    instruction.write(&mut bytes, *offset);
+                }
+                out.put_u4(bytes.len() as u32);
+                out.extend_from_slice(&bytes);
+                put_count(out, exception_table.len(), "exception table")?;
+                exception_table
+                    .iter()
+                    .for_each(|exception| exception.write(out));
+                write_attributes(out, constant_pool, attributes)?;
+            }
+            AttributeInfo::SourceFile(value) | AttributeInfo::Signature(value) => {
+                out.put_u2(utf8_index(constant_pool, value)?)
+            }
+            AttributeInfo::LineNumberTable(table) => {
+                put_count(out, table.len(), "line number table")?;
+                table.iter().for_each(|line| line.write(out));
+            }
+            AttributeInfo::LocalVariableTable(table)
+            | AttributeInfo::LocalVariableTypeTable(table) => {
+                put_count(out, table.len(), "local variable table")?;
+                table.iter().for_each(|variable| variable.write(out));
+            }
+            AttributeInfo::StackMapTable(frames) => {
+                put_count(out, frames.len(), "stack map table")?;
+                frames.iter().try_for_each(|frame| frame.write(out))?;
+            }
+            AttributeInfo::BootstrapMethods(methods) => {
+                put_count(out, methods.len(), "bootstrap methods")?;
+                methods.iter().try_for_each(|method| method.write(out))?;
+            }
+            AttributeInfo::InnerClasses(classes) => {
+                put_count(out, classes.len(), "inner classes")?;
+                classes.iter().for_each(|class| class.write(out));
+            }
+            AttributeInfo::ConstantValue(index) | AttributeInfo::NestHost(index) => {
+                out.put_u2(*index)
+            }
+            AttributeInfo::Exceptions(indexes) => write_indexes(out, indexes, "exceptions")?,
+            AttributeInfo::NestMembers(indexes) => write_indexes(out, indexes, "nest members")?,
+            AttributeInfo::PermittedSubclasses(indexes) => {
+                write_indexes(out, indexes, "permitted subclasses")?
+            }
+            AttributeInfo::EnclosingMethod {
+                class_index,
+                method_index,
+            } => {
+                out.put_u2(*class_index);
+                out.put_u2(*method_index);
+            }
+            AttributeInfo::RuntimeVisibleAnnotations(annotations)
+            | AttributeInfo::RuntimeInvisibleAnnotations(annotations) => {
+                write_annotations(out, annotations)?
+            }
+            AttributeInfo::RuntimeVisibleParameterAnnotations(parameters)
+            | AttributeInfo::RuntimeInvisibleParameterAnnotations(parameters) => {
+                let Ok(count) = u8::try_from(parameters.len()) else {
+                    return Err(invalid_data(format!(
+                        "{} parameters have annotations, at most 255 fit in a class file",
+                        parameters.len()
+                    )));
+                };
+                out.put_u1(count);
+                for annotations in parameters {
+                    write_annotations(out, annotations)?;
+                }
+            }
+            AttributeInfo::AnnotationDefault(value) => value.write(out)?,
+            AttributeInfo::Deprecated | AttributeInfo::Synthetic => {}
+            AttributeInfo::Uncrecognized(bytes) => out.extend_from_slice(bytes),
+        }
+        Ok(())
+    }
+}
+
+/// Writes `attributes_count` followed by the attributes
+pub fn write_attributes(
+    out: &mut Vec<u8>,
+    constant_pool: &[ConstantPoolInfo],
+    attributes: &[Attribute],
+) -> std::io::Result<()> {
+    put_count(out, attributes.len(), "attributes")?;
+    attributes
+        .iter()
+        .try_for_each(|attribute| attribute.write(constant_pool, out))
 }
 
 pub fn resolve_attributes<'a, It>(
@@ -124,12 +824,11 @@ where
     for attribute in attributes {
         let name_index = attribute.name_index;
 
-        let name =
-            if let ConstantPoolInfo::Utf8(actual_name) = &constant_pool[name_index as usize - 1] {
-                actual_name
-            } else {
-                panic!()
-            };
+        let Some(name) = utf8_at(constant_pool, name_index) else {
+            return Err(invalid_data(format!(
+                "attribute name points to #{name_index} which is not Utf8"
+            )));
+        };
 
         if let AttributeInfo::Uncrecognized(data) = &attribute.info {
             if let Some(info) = AttributeInfo::from(constant_pool, name, data.as_slice())? {
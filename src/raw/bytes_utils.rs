@@ -1,20 +1,9 @@
 use byteorder::{BigEndian, ReadBytesExt};
 
+/// Reader of class file primitives. Parsers also describe what they read with
+/// [`ByteUtils::enter`] and [`ByteUtils::field`], which is ignored by everything
+/// except [`crate::raw::trace::Tracer`] used for annotated hex dumps.
 pub trait ByteUtils: ReadBytesExt {
-    fn read_u1(&mut self) -> std::io::Result<u8>;
-    fn read_u2(&mut self) -> std::io::Result<u16>;
-    fn read_u4(&mut self) -> std::io::Result<u32>;
-
-    /// Read array in the shape of length items...
-    fn read_array<B, F>(&mut self, count: usize, f: F) -> std::io::Result<Vec<B>>
-    where
-        F: FnMut(&mut Self) -> std::io::Result<B>;
-}
-
-impl<Bytes> ByteUtils for Bytes
-where
-    Bytes: ReadBytesExt,
-{
     fn read_u1(&mut self) -> std::io::Result<u8> {
         self.read_u8()
     }
@@ -27,15 +16,94 @@ where
         self.read_u32::<BigEndian>()
     }
 
-    fn read_array<B, F>(&mut self, count: usize, mut f: F) -> std::io::Result<Vec<B>>
+    /// Read array in the shape of length items...
+    fn read_vec<B, F>(&mut self, count: usize, mut f: F) -> std::io::Result<Vec<B>>
     where
         F: FnMut(&mut Self) -> std::io::Result<B>,
     {
-        let mut result: Vec<B> = vec![];
-        result.reserve(count);
+        let mut result: Vec<B> = Vec::with_capacity(count);
         for _ in 0..count {
             result.push(f(self)?);
         }
         Ok(result)
     }
+
+    /// Same as [`ByteUtils::read_vec`], with items named `name[i]`
+    fn read_table<B, F>(&mut self, name: &str, count: usize, mut f: F) -> std::io::Result<Vec<B>>
+    where
+        F: FnMut(&mut Self) -> std::io::Result<B>,
+    {
+        let mut result: Vec<B> = Vec::with_capacity(count);
+        for i in 0..count {
+            self.enter(|| format!("{name}[{i}]"));
+            result.push(f(self)?);
+            self.leave();
+        }
+        Ok(result)
+    }
+
+    /// Following reads belong to the structure named `name`, until matching [`ByteUtils::leave`].
+    /// Parsers do not leave on error, so the structure that failed to parse stays entered.
+    fn enter(&mut self, _name: impl FnOnce() -> String) {}
+
+    fn leave(&mut self) {}
+
+    /// Names the field read by the next read
+    fn field(&mut self, _name: &'static str) -> &mut Self {
+        self
+    }
+}
+
+impl ByteUtils for &[u8] {}
+
+impl<T: ByteUtils + ?Sized> ByteUtils for &mut T {
+    fn enter(&mut self, name: impl FnOnce() -> String) {
+        (**self).enter(name)
+    }
+
+    fn leave(&mut self) {
+        (**self).leave()
+    }
+
+    fn field(&mut self, name: &'static str) -> &mut Self {
+        (**self).field(name);
+        self
+    }
+}
+
+/// Error reported when bytes do not form a valid class file structure
+pub fn invalid_data(message: impl Into<String>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.into())
+}
+
+/// Writer of class file primitives, counterpart of [`ByteUtils`]
+pub trait PutBytes {
+    fn put_u1(&mut self, value: u8);
+    fn put_u2(&mut self, value: u16);
+    fn put_u4(&mut self, value: u32);
+}
+
+impl PutBytes for Vec<u8> {
+    fn put_u1(&mut self, value: u8) {
+        self.push(value);
+    }
+
+    fn put_u2(&mut self, value: u16) {
+        self.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn put_u4(&mut self, value: u32) {
+        self.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// Writes `u2` length of the table called `name`, failing when it has too many entries
+pub fn put_count(out: &mut Vec<u8>, count: usize, name: &str) -> std::io::Result<()> {
+    let Ok(count) = u16::try_from(count) else {
+        return Err(invalid_data(format!(
+            "{name} has {count} entries, at most 65535 fit in a class file"
+        )));
+    };
+    out.put_u2(count);
+    Ok(())
 }
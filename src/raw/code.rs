@@ -1,71 +1,1249 @@
 use crate::raw::*;
 
-#[derive(Debug)]
+/// Decoded JVM instruction, operands are kept exactly as encoded in the class file.
+/// Branch operands are offsets relative to the address of the instruction itself.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Instruction {
+    AALoad,
+    AAStore,
+    AConstNull,
+    ALoad(u8),
     ALoad0,
     ALoad1,
     ALoad2,
     ALoad3,
-    BiPush(u8),
+    ANewArray(u16),
+    AReturn,
+    AStore(u8),
+    AStore0,
+    AStore1,
+    AStore2,
+    AStore3,
+    AThrow,
+    ArrayLength,
+    BALoad,
+    BAStore,
+    BiPush(i8),
+    CALoad,
+    CAStore,
+    CheckCast(u16),
+    D2F,
+    D2I,
+    D2L,
+    DALoad,
+    DAStore,
+    DAdd,
+    DCmpG,
+    DCmpL,
+    DConst0,
+    DConst1,
+    DDiv,
+    DLoad(u8),
+    DLoad0,
+    DLoad1,
+    DLoad2,
+    DLoad3,
+    DMul,
+    DNeg,
+    DRem,
+    DReturn,
+    DStore(u8),
+    DStore0,
+    DStore1,
+    DStore2,
+    DStore3,
+    DSub,
+    Dup,
+    Dup2,
+    Dup2X1,
+    Dup2X2,
+    DupX1,
+    DupX2,
+    F2D,
+    F2I,
+    F2L,
+    FALoad,
+    FAStore,
+    FAdd,
+    FCmpG,
+    FCmpL,
+    FConst0,
+    FConst1,
+    FConst2,
+    FDiv,
+    FLoad(u8),
+    FLoad0,
+    FLoad1,
+    FLoad2,
+    FLoad3,
+    FMul,
+    FNeg,
+    FRem,
+    FReturn,
+    FStore(u8),
+    FStore0,
+    FStore1,
+    FStore2,
+    FStore3,
+    FSub,
+    GetField(u16),
     GetStatic(u16),
+    Goto(i16),
+    GotoW(i32),
+    I2B,
+    I2C,
+    I2D,
+    I2F,
+    I2L,
+    I2S,
+    IALoad,
+    IAStore,
     IAdd,
+    IAnd,
+    IConst0,
+    IConst1,
+    IConst2,
+    IConst3,
+    IConst4,
+    IConst5,
+    IConstM1,
+    IDiv,
+    IInc(u8, i8),
+    ILoad(u8),
     ILoad0,
     ILoad1,
     ILoad2,
     ILoad3,
+    IMul,
+    INeg,
+    IOr,
+    IRem,
+    IReturn,
+    IShl,
+    IShr,
+    IStore(u8),
     IStore0,
     IStore1,
     IStore2,
     IStore3,
+    ISub,
+    IUShr,
+    IXor,
+    IfACmpEq(i16),
+    IfACmpNe(i16),
+    IfEq(i16),
+    IfGe(i16),
+    IfGt(i16),
+    IfICmpEq(i16),
+    IfICmpGe(i16),
+    IfICmpGt(i16),
+    IfICmpLe(i16),
+    IfICmpLt(i16),
+    IfICmpNe(i16),
+    IfLe(i16),
+    IfLt(i16),
+    IfNe(i16),
+    IfNonNull(i16),
+    IfNull(i16),
+    InstanceOf(u16),
+    InvokeDynamic(u16),
+    InvokeInterface(u16, u8),
     InvokeSpecial(u16),
+    InvokeStatic(u16),
     InvokeVirtual(u16),
+    Jsr(i16),
+    JsrW(i32),
+    L2D,
+    L2F,
+    L2I,
+    LALoad,
+    LAStore,
+    LAdd,
+    LAnd,
+    LCmp,
+    LConst0,
+    LConst1,
+    LDiv,
+    LLoad(u8),
+    LLoad0,
+    LLoad1,
+    LLoad2,
+    LLoad3,
+    LMul,
+    LNeg,
+    LOr,
+    LRem,
+    LReturn,
+    LShl,
+    LShr,
+    LStore(u8),
+    LStore0,
+    LStore1,
+    LStore2,
+    LStore3,
+    LSub,
+    LUShr,
+    LXor,
     Ldc(u8),
+    Ldc2W(u16),
+    LdcW(u16),
+    LookupSwitch {
+        default: i32,
+        pairs: Vec<(i32, i32)>,
+    },
+    MonitorEnter,
+    MonitorExit,
+    MultiANewArray(u16, u8),
+    New(u16),
+    NewArray(u8),
     Nop,
+    Pop,
+    Pop2,
+    PutField(u16),
+    PutStatic(u16),
+    Ret(u8),
     Return,
+    SALoad,
+    SAStore,
+    SiPush(i16),
+    Swap,
+    TableSwitch {
+        default: i32,
+        low: i32,
+        high: i32,
+        offsets: Vec<i32>,
+    },
+    Wide(Wide),
+}
+
+/// Instructions that can be modified by the `wide` prefix, with widened operands
+#[derive(Debug, Clone, PartialEq)]
+pub enum Wide {
+    ALoad(u16),
+    AStore(u16),
+    DLoad(u16),
+    DStore(u16),
+    FLoad(u16),
+    FStore(u16),
+    IInc(u16, i16),
+    ILoad(u16),
+    IStore(u16),
+    LLoad(u16),
+    LStore(u16),
+    Ret(u16),
+}
+
+impl Wide {
+    pub fn from<F: ByteUtils>(file: &mut F) -> std::io::Result<Wide> {
+        let opcode = file.read_u1()?;
+        let index = file.read_u2()?;
+        Ok(match opcode {
+            0x15 => Wide::ILoad(index),
+            0x16 => Wide::LLoad(index),
+            0x17 => Wide::FLoad(index),
+            0x18 => Wide::DLoad(index),
+            0x19 => Wide::ALoad(index),
+            0x36 => Wide::IStore(index),
+            0x37 => Wide::LStore(index),
+            0x38 => Wide::FStore(index),
+            0x39 => Wide::DStore(index),
+            0x3a => Wide::AStore(index),
+            0x84 => Wide::IInc(index, file.read_u2()? as i16),
+            0xa9 => Wide::Ret(index),
+            _ => {
+                return Err(invalid_data(format!(
+                    "opcode {opcode:#x} cannot follow wide"
+                )))
+            }
+        })
+    }
+
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Wide::ILoad(_) => 0x15,
+            Wide::LLoad(_) => 0x16,
+            Wide::FLoad(_) => 0x17,
+            Wide::DLoad(_) => 0x18,
+            Wide::ALoad(_) => 0x19,
+            Wide::IStore(_) => 0x36,
+            Wide::LStore(_) => 0x37,
+            Wide::FStore(_) => 0x38,
+            Wide::DStore(_) => 0x39,
+            Wide::AStore(_) => 0x3a,
+            Wide::IInc(..) => 0x84,
+            Wide::Ret(_) => 0xa9,
+        }
+    }
+
+    /// Mnemonic of the modified instruction
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Wide::ILoad(_) => "iload",
+            Wide::LLoad(_) => "lload",
+            Wide::FLoad(_) => "fload",
+            Wide::DLoad(_) => "dload",
+            Wide::ALoad(_) => "aload",
+            Wide::IStore(_) => "istore",
+            Wide::LStore(_) => "lstore",
+            Wide::FStore(_) => "fstore",
+            Wide::DStore(_) => "dstore",
+            Wide::AStore(_) => "astore",
+            Wide::IInc(..) => "iinc",
+            Wide::Ret(_) => "ret",
+        }
+    }
+
+    /// Local variable slot that instruction operates on
+    pub fn index(&self) -> u16 {
+        match self {
+            Wide::ILoad(index)
+            | Wide::LLoad(index)
+            | Wide::FLoad(index)
+            | Wide::DLoad(index)
+            | Wide::ALoad(index)
+            | Wide::IStore(index)
+            | Wide::LStore(index)
+            | Wide::FStore(index)
+            | Wide::DStore(index)
+            | Wide::AStore(index)
+            | Wide::IInc(index, _)
+            | Wide::Ret(index) => *index,
+        }
+    }
 }
 
 impl Instruction {
-    pub fn from<F: ByteUtils>(file: &mut F) -> std::io::Result<Instruction> {
+    /// Decodes single instruction. `offset` is the address of the instruction inside
+    /// the method's code, required to skip alignment padding of switch instructions.
+    pub fn from<F: ByteUtils>(file: &mut F, offset: u32) -> std::io::Result<Instruction> {
         let opcode = file.read_u1()?;
         Ok(match opcode {
-            0x0 => Instruction::Nop,
-            0xb1 => Instruction::Return,
+            0x00 => Instruction::Nop,
+            0x01 => Instruction::AConstNull,
+            0x02 => Instruction::IConstM1,
+            0x03 => Instruction::IConst0,
+            0x04 => Instruction::IConst1,
+            0x05 => Instruction::IConst2,
+            0x06 => Instruction::IConst3,
+            0x07 => Instruction::IConst4,
+            0x08 => Instruction::IConst5,
+            0x09 => Instruction::LConst0,
+            0x0a => Instruction::LConst1,
+            0x0b => Instruction::FConst0,
+            0x0c => Instruction::FConst1,
+            0x0d => Instruction::FConst2,
+            0x0e => Instruction::DConst0,
+            0x0f => Instruction::DConst1,
+            0x10 => Instruction::BiPush(file.read_u1()? as i8),
+            0x11 => Instruction::SiPush(file.read_u2()? as i16),
+            0x12 => Instruction::Ldc(file.read_u1()?),
+            0x13 => Instruction::LdcW(file.read_u2()?),
+            0x14 => Instruction::Ldc2W(file.read_u2()?),
+            0x15 => Instruction::ILoad(file.read_u1()?),
+            0x16 => Instruction::LLoad(file.read_u1()?),
+            0x17 => Instruction::FLoad(file.read_u1()?),
+            0x18 => Instruction::DLoad(file.read_u1()?),
+            0x19 => Instruction::ALoad(file.read_u1()?),
+            0x1a => Instruction::ILoad0,
+            0x1b => Instruction::ILoad1,
+            0x1c => Instruction::ILoad2,
+            0x1d => Instruction::ILoad3,
+            0x1e => Instruction::LLoad0,
+            0x1f => Instruction::LLoad1,
+            0x20 => Instruction::LLoad2,
+            0x21 => Instruction::LLoad3,
+            0x22 => Instruction::FLoad0,
+            0x23 => Instruction::FLoad1,
+            0x24 => Instruction::FLoad2,
+            0x25 => Instruction::FLoad3,
+            0x26 => Instruction::DLoad0,
+            0x27 => Instruction::DLoad1,
+            0x28 => Instruction::DLoad2,
+            0x29 => Instruction::DLoad3,
             0x2a => Instruction::ALoad0,
             0x2b => Instruction::ALoad1,
             0x2c => Instruction::ALoad2,
             0x2d => Instruction::ALoad3,
+            0x2e => Instruction::IALoad,
+            0x2f => Instruction::LALoad,
+            0x30 => Instruction::FALoad,
+            0x31 => Instruction::DALoad,
+            0x32 => Instruction::AALoad,
+            0x33 => Instruction::BALoad,
+            0x34 => Instruction::CALoad,
+            0x35 => Instruction::SALoad,
+            0x36 => Instruction::IStore(file.read_u1()?),
+            0x37 => Instruction::LStore(file.read_u1()?),
+            0x38 => Instruction::FStore(file.read_u1()?),
+            0x39 => Instruction::DStore(file.read_u1()?),
+            0x3a => Instruction::AStore(file.read_u1()?),
             0x3b => Instruction::IStore0,
             0x3c => Instruction::IStore1,
             0x3d => Instruction::IStore2,
             0x3e => Instruction::IStore3,
-            0x1a => Instruction::ILoad0,
-            0x1b => Instruction::ILoad1,
-            0x1c => Instruction::ILoad2,
-            0x1d => Instruction::ILoad3,
+            0x3f => Instruction::LStore0,
+            0x40 => Instruction::LStore1,
+            0x41 => Instruction::LStore2,
+            0x42 => Instruction::LStore3,
+            0x43 => Instruction::FStore0,
+            0x44 => Instruction::FStore1,
+            0x45 => Instruction::FStore2,
+            0x46 => Instruction::FStore3,
+            0x47 => Instruction::DStore0,
+            0x48 => Instruction::DStore1,
+            0x49 => Instruction::DStore2,
+            0x4a => Instruction::DStore3,
+            0x4b => Instruction::AStore0,
+            0x4c => Instruction::AStore1,
+            0x4d => Instruction::AStore2,
+            0x4e => Instruction::AStore3,
+            0x4f => Instruction::IAStore,
+            0x50 => Instruction::LAStore,
+            0x51 => Instruction::FAStore,
+            0x52 => Instruction::DAStore,
+            0x53 => Instruction::AAStore,
+            0x54 => Instruction::BAStore,
+            0x55 => Instruction::CAStore,
+            0x56 => Instruction::SAStore,
+            0x57 => Instruction::Pop,
+            0x58 => Instruction::Pop2,
+            0x59 => Instruction::Dup,
+            0x5a => Instruction::DupX1,
+            0x5b => Instruction::DupX2,
+            0x5c => Instruction::Dup2,
+            0x5d => Instruction::Dup2X1,
+            0x5e => Instruction::Dup2X2,
+            0x5f => Instruction::Swap,
             0x60 => Instruction::IAdd,
-            0x10 => Instruction::BiPush(file.read_u1()?),
-            0x12 => Instruction::Ldc(file.read_u1()?),
-            0xb2 => {
-                let indexbyte1 = file.read_u1()? as u16;
-                let indexbyte2 = file.read_u1()? as u16;
-                let index = (indexbyte1 << 8) | indexbyte2;
-                Instruction::GetStatic(index)
-            }
-            0xb6 => {
-                let indexbyte1 = file.read_u1()? as u16;
-                let indexbyte2 = file.read_u1()? as u16;
-                let index = (indexbyte1 << 8) | indexbyte2;
-                Instruction::InvokeVirtual(index)
-            }
-            0xb7 => {
-                let indexbyte1 = file.read_u1()? as u16;
-                let indexbyte2 = file.read_u1()? as u16;
-                let index = (indexbyte1 << 8) | indexbyte2;
-                Instruction::InvokeSpecial(index)
+            0x61 => Instruction::LAdd,
+            0x62 => Instruction::FAdd,
+            0x63 => Instruction::DAdd,
+            0x64 => Instruction::ISub,
+            0x65 => Instruction::LSub,
+            0x66 => Instruction::FSub,
+            0x67 => Instruction::DSub,
+            0x68 => Instruction::IMul,
+            0x69 => Instruction::LMul,
+            0x6a => Instruction::FMul,
+            0x6b => Instruction::DMul,
+            0x6c => Instruction::IDiv,
+            0x6d => Instruction::LDiv,
+            0x6e => Instruction::FDiv,
+            0x6f => Instruction::DDiv,
+            0x70 => Instruction::IRem,
+            0x71 => Instruction::LRem,
+            0x72 => Instruction::FRem,
+            0x73 => Instruction::DRem,
+            0x74 => Instruction::INeg,
+            0x75 => Instruction::LNeg,
+            0x76 => Instruction::FNeg,
+            0x77 => Instruction::DNeg,
+            0x78 => Instruction::IShl,
+            0x79 => Instruction::LShl,
+            0x7a => Instruction::IShr,
+            0x7b => Instruction::LShr,
+            0x7c => Instruction::IUShr,
+            0x7d => Instruction::LUShr,
+            0x7e => Instruction::IAnd,
+            0x7f => Instruction::LAnd,
+            0x80 => Instruction::IOr,
+            0x81 => Instruction::LOr,
+            0x82 => Instruction::IXor,
+            0x83 => Instruction::LXor,
+            0x84 => Instruction::IInc(file.read_u1()?, file.read_u1()? as i8),
+            0x85 => Instruction::I2L,
+            0x86 => Instruction::I2F,
+            0x87 => Instruction::I2D,
+            0x88 => Instruction::L2I,
+            0x89 => Instruction::L2F,
+            0x8a => Instruction::L2D,
+            0x8b => Instruction::F2I,
+            0x8c => Instruction::F2L,
+            0x8d => Instruction::F2D,
+            0x8e => Instruction::D2I,
+            0x8f => Instruction::D2L,
+            0x90 => Instruction::D2F,
+            0x91 => Instruction::I2B,
+            0x92 => Instruction::I2C,
+            0x93 => Instruction::I2S,
+            0x94 => Instruction::LCmp,
+            0x95 => Instruction::FCmpL,
+            0x96 => Instruction::FCmpG,
+            0x97 => Instruction::DCmpL,
+            0x98 => Instruction::DCmpG,
+            0x99 => Instruction::IfEq(file.read_u2()? as i16),
+            0x9a => Instruction::IfNe(file.read_u2()? as i16),
+            0x9b => Instruction::IfLt(file.read_u2()? as i16),
+            0x9c => Instruction::IfGe(file.read_u2()? as i16),
+            0x9d => Instruction::IfGt(file.read_u2()? as i16),
+            0x9e => Instruction::IfLe(file.read_u2()? as i16),
+            0x9f => Instruction::IfICmpEq(file.read_u2()? as i16),
+            0xa0 => Instruction::IfICmpNe(file.read_u2()? as i16),
+            0xa1 => Instruction::IfICmpLt(file.read_u2()? as i16),
+            0xa2 => Instruction::IfICmpGe(file.read_u2()? as i16),
+            0xa3 => Instruction::IfICmpGt(file.read_u2()? as i16),
+            0xa4 => Instruction::IfICmpLe(file.read_u2()? as i16),
+            0xa5 => Instruction::IfACmpEq(file.read_u2()? as i16),
+            0xa6 => Instruction::IfACmpNe(file.read_u2()? as i16),
+            0xa7 => Instruction::Goto(file.read_u2()? as i16),
+            0xa8 => Instruction::Jsr(file.read_u2()? as i16),
+            0xa9 => Instruction::Ret(file.read_u1()?),
+            0xaa => {
+                skip_switch_padding(file, offset)?;
+                let default = file.read_u4()? as i32;
+                let low = file.read_u4()? as i32;
+                let high = file.read_u4()? as i32;
+                if high < low {
+                    return Err(invalid_data(format!(
+                        "tableswitch with low={low} > high={high}"
+                    )));
+                }
+                let count = (high as i64 - low as i64 + 1) as usize;
+                let offsets = file.read_vec(count, |file| Ok(file.read_u4()? as i32))?;
+                Instruction::TableSwitch {
+                    default,
+                    low,
+                    high,
+                    offsets,
+                }
+            }
+            0xab => {
+                skip_switch_padding(file, offset)?;
+                let default = file.read_u4()? as i32;
+                let npairs = file.read_u4()? as i32;
+                if npairs < 0 {
+                    return Err(invalid_data(format!("lookupswitch with npairs={npairs}")));
+                }
+                let pairs = file.read_vec(npairs as usize, |file| {
+                    Ok((file.read_u4()? as i32, file.read_u4()? as i32))
+                })?;
+                Instruction::LookupSwitch { default, pairs }
             }
+            0xac => Instruction::IReturn,
+            0xad => Instruction::LReturn,
+            0xae => Instruction::FReturn,
+            0xaf => Instruction::DReturn,
+            0xb0 => Instruction::AReturn,
+            0xb1 => Instruction::Return,
+            0xb2 => Instruction::GetStatic(file.read_u2()?),
+            0xb3 => Instruction::PutStatic(file.read_u2()?),
+            0xb4 => Instruction::GetField(file.read_u2()?),
+            0xb5 => Instruction::PutField(file.read_u2()?),
+            0xb6 => Instruction::InvokeVirtual(file.read_u2()?),
+            0xb7 => Instruction::InvokeSpecial(file.read_u2()?),
+            0xb8 => Instruction::InvokeStatic(file.read_u2()?),
+            0xb9 => {
+                let index = file.read_u2()?;
+                let count = file.read_u1()?;
+                let _zero = file.read_u1()?;
+                Instruction::InvokeInterface(index, count)
+            }
+            0xba => {
+                let index = file.read_u2()?;
+                let _zero = file.read_u2()?;
+                Instruction::InvokeDynamic(index)
+            }
+            0xbb => Instruction::New(file.read_u2()?),
+            0xbc => Instruction::NewArray(file.read_u1()?),
+            0xbd => Instruction::ANewArray(file.read_u2()?),
+            0xbe => Instruction::ArrayLength,
+            0xbf => Instruction::AThrow,
+            0xc0 => Instruction::CheckCast(file.read_u2()?),
+            0xc1 => Instruction::InstanceOf(file.read_u2()?),
+            0xc2 => Instruction::MonitorEnter,
+            0xc3 => Instruction::MonitorExit,
+            0xc4 => Instruction::Wide(Wide::from(file)?),
+            0xc5 => Instruction::MultiANewArray(file.read_u2()?, file.read_u1()?),
+            0xc6 => Instruction::IfNull(file.read_u2()? as i16),
+            0xc7 => Instruction::IfNonNull(file.read_u2()? as i16),
+            0xc8 => Instruction::GotoW(file.read_u4()? as i32),
+            0xc9 => Instruction::JsrW(file.read_u4()? as i32),
             _ => {
-                panic!("Unrecognized opcode: {opcode:#x}");
+                return Err(invalid_data(format!("Unrecognized opcode: {opcode:#x}")));
             }
         })
     }
+
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Instruction::Nop => 0x00,
+            Instruction::AConstNull => 0x01,
+            Instruction::IConstM1 => 0x02,
+            Instruction::IConst0 => 0x03,
+            Instruction::IConst1 => 0x04,
+            Instruction::IConst2 => 0x05,
+            Instruction::IConst3 => 0x06,
+            Instruction::IConst4 => 0x07,
+            Instruction::IConst5 => 0x08,
+            Instruction::LConst0 => 0x09,
+            Instruction::LConst1 => 0x0a,
+            Instruction::FConst0 => 0x0b,
+            Instruction::FConst1 => 0x0c,
+            Instruction::FConst2 => 0x0d,
+            Instruction::DConst0 => 0x0e,
+            Instruction::DConst1 => 0x0f,
+            Instruction::BiPush(_) => 0x10,
+            Instruction::SiPush(_) => 0x11,
+            Instruction::Ldc(_) => 0x12,
+            Instruction::LdcW(_) => 0x13,
+            Instruction::Ldc2W(_) => 0x14,
+            Instruction::ILoad(_) => 0x15,
+            Instruction::LLoad(_) => 0x16,
+            Instruction::FLoad(_) => 0x17,
+            Instruction::DLoad(_) => 0x18,
+            Instruction::ALoad(_) => 0x19,
+            Instruction::ILoad0 => 0x1a,
+            Instruction::ILoad1 => 0x1b,
+            Instruction::ILoad2 => 0x1c,
+            Instruction::ILoad3 => 0x1d,
+            Instruction::LLoad0 => 0x1e,
+            Instruction::LLoad1 => 0x1f,
+            Instruction::LLoad2 => 0x20,
+            Instruction::LLoad3 => 0x21,
+            Instruction::FLoad0 => 0x22,
+            Instruction::FLoad1 => 0x23,
+            Instruction::FLoad2 => 0x24,
+            Instruction::FLoad3 => 0x25,
+            Instruction::DLoad0 => 0x26,
+            Instruction::DLoad1 => 0x27,
+            Instruction::DLoad2 => 0x28,
+            Instruction::DLoad3 => 0x29,
+            Instruction::ALoad0 => 0x2a,
+            Instruction::ALoad1 => 0x2b,
+            Instruction::ALoad2 => 0x2c,
+            Instruction::ALoad3 => 0x2d,
+            Instruction::IALoad => 0x2e,
+            Instruction::LALoad => 0x2f,
+            Instruction::FALoad => 0x30,
+            Instruction::DALoad => 0x31,
+            Instruction::AALoad => 0x32,
+            Instruction::BALoad => 0x33,
+            Instruction::CALoad => 0x34,
+            Instruction::SALoad => 0x35,
+            Instruction::IStore(_) => 0x36,
+            Instruction::LStore(_) => 0x37,
+            Instruction::FStore(_) => 0x38,
+            Instruction::DStore(_) => 0x39,
+            Instruction::AStore(_) => 0x3a,
+            Instruction::IStore0 => 0x3b,
+            Instruction::IStore1 => 0x3c,
+            Instruction::IStore2 => 0x3d,
+            Instruction::IStore3 => 0x3e,
+            Instruction::LStore0 => 0x3f,
+            Instruction::LStore1 => 0x40,
+            Instruction::LStore2 => 0x41,
+            Instruction::LStore3 => 0x42,
+            Instruction::FStore0 => 0x43,
+            Instruction::FStore1 => 0x44,
+            Instruction::FStore2 => 0x45,
+            Instruction::FStore3 => 0x46,
+            Instruction::DStore0 => 0x47,
+            Instruction::DStore1 => 0x48,
+            Instruction::DStore2 => 0x49,
+            Instruction::DStore3 => 0x4a,
+            Instruction::AStore0 => 0x4b,
+            Instruction::AStore1 => 0x4c,
+            Instruction::AStore2 => 0x4d,
+            Instruction::AStore3 => 0x4e,
+            Instruction::IAStore => 0x4f,
+            Instruction::LAStore => 0x50,
+            Instruction::FAStore => 0x51,
+            Instruction::DAStore => 0x52,
+            Instruction::AAStore => 0x53,
+            Instruction::BAStore => 0x54,
+            Instruction::CAStore => 0x55,
+            Instruction::SAStore => 0x56,
+            Instruction::Pop => 0x57,
+            Instruction::Pop2 => 0x58,
+            Instruction::Dup => 0x59,
+            Instruction::DupX1 => 0x5a,
+            Instruction::DupX2 => 0x5b,
+            Instruction::Dup2 => 0x5c,
+            Instruction::Dup2X1 => 0x5d,
+            Instruction::Dup2X2 => 0x5e,
+            Instruction::Swap => 0x5f,
+            Instruction::IAdd => 0x60,
+            Instruction::LAdd => 0x61,
+            Instruction::FAdd => 0x62,
+            Instruction::DAdd => 0x63,
+            Instruction::ISub => 0x64,
+            Instruction::LSub => 0x65,
+            Instruction::FSub => 0x66,
+            Instruction::DSub => 0x67,
+            Instruction::IMul => 0x68,
+            Instruction::LMul => 0x69,
+            Instruction::FMul => 0x6a,
+            Instruction::DMul => 0x6b,
+            Instruction::IDiv => 0x6c,
+            Instruction::LDiv => 0x6d,
+            Instruction::FDiv => 0x6e,
+            Instruction::DDiv => 0x6f,
+            Instruction::IRem => 0x70,
+            Instruction::LRem => 0x71,
+            Instruction::FRem => 0x72,
+            Instruction::DRem => 0x73,
+            Instruction::INeg => 0x74,
+            Instruction::LNeg => 0x75,
+            Instruction::FNeg => 0x76,
+            Instruction::DNeg => 0x77,
+            Instruction::IShl => 0x78,
+            Instruction::LShl => 0x79,
+            Instruction::IShr => 0x7a,
+            Instruction::LShr => 0x7b,
+            Instruction::IUShr => 0x7c,
+            Instruction::LUShr => 0x7d,
+            Instruction::IAnd => 0x7e,
+            Instruction::LAnd => 0x7f,
+            Instruction::IOr => 0x80,
+            Instruction::LOr => 0x81,
+            Instruction::IXor => 0x82,
+            Instruction::LXor => 0x83,
+            Instruction::IInc(..) => 0x84,
+            Instruction::I2L => 0x85,
+            Instruction::I2F => 0x86,
+            Instruction::I2D => 0x87,
+            Instruction::L2I => 0x88,
+            Instruction::L2F => 0x89,
+            Instruction::L2D => 0x8a,
+            Instruction::F2I => 0x8b,
+            Instruction::F2L => 0x8c,
+            Instruction::F2D => 0x8d,
+            Instruction::D2I => 0x8e,
+            Instruction::D2L => 0x8f,
+            Instruction::D2F => 0x90,
+            Instruction::I2B => 0x91,
+            Instruction::I2C => 0x92,
+            Instruction::I2S => 0x93,
+            Instruction::LCmp => 0x94,
+            Instruction::FCmpL => 0x95,
+            Instruction::FCmpG => 0x96,
+            Instruction::DCmpL => 0x97,
+            Instruction::DCmpG => 0x98,
+            Instruction::IfEq(_) => 0x99,
+            Instruction::IfNe(_) => 0x9a,
+            Instruction::IfLt(_) => 0x9b,
+            Instruction::IfGe(_) => 0x9c,
+            Instruction::IfGt(_) => 0x9d,
+            Instruction::IfLe(_) => 0x9e,
+            Instruction::IfICmpEq(_) => 0x9f,
+            Instruction::IfICmpNe(_) => 0xa0,
+            Instruction::IfICmpLt(_) => 0xa1,
+            Instruction::IfICmpGe(_) => 0xa2,
+            Instruction::IfICmpGt(_) => 0xa3,
+            Instruction::IfICmpLe(_) => 0xa4,
+            Instruction::IfACmpEq(_) => 0xa5,
+            Instruction::IfACmpNe(_) => 0xa6,
+            Instruction::Goto(_) => 0xa7,
+            Instruction::Jsr(_) => 0xa8,
+            Instruction::Ret(_) => 0xa9,
+            Instruction::TableSwitch { .. } => 0xaa,
+            Instruction::LookupSwitch { .. } => 0xab,
+            Instruction::IReturn => 0xac,
+            Instruction::LReturn => 0xad,
+            Instruction::FReturn => 0xae,
+            Instruction::DReturn => 0xaf,
+            Instruction::AReturn => 0xb0,
+            Instruction::Return => 0xb1,
+            Instruction::GetStatic(_) => 0xb2,
+            Instruction::PutStatic(_) => 0xb3,
+            Instruction::GetField(_) => 0xb4,
+            Instruction::PutField(_) => 0xb5,
+            Instruction::InvokeVirtual(_) => 0xb6,
+            Instruction::InvokeSpecial(_) => 0xb7,
+            Instruction::InvokeStatic(_) => 0xb8,
+            Instruction::InvokeInterface(..) => 0xb9,
+            Instruction::InvokeDynamic(_) => 0xba,
+            Instruction::New(_) => 0xbb,
+            Instruction::NewArray(_) => 0xbc,
+            Instruction::ANewArray(_) => 0xbd,
+            Instruction::ArrayLength => 0xbe,
+            Instruction::AThrow => 0xbf,
+            Instruction::CheckCast(_) => 0xc0,
+            Instruction::InstanceOf(_) => 0xc1,
+            Instruction::MonitorEnter => 0xc2,
+            Instruction::MonitorExit => 0xc3,
+            Instruction::Wide(_) => 0xc4,
+            Instruction::MultiANewArray(..) => 0xc5,
+            Instruction::IfNull(_) => 0xc6,
+            Instruction::IfNonNull(_) => 0xc7,
+            Instruction::GotoW(_) => 0xc8,
+            Instruction::JsrW(_) => 0xc9,
+        }
+    }
+
+    /// Name of the instruction as used by the JVM specification and `javap`
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Nop => "nop",
+            Instruction::AConstNull => "aconst_null",
+            Instruction::IConstM1 => "iconst_m1",
+            Instruction::IConst0 => "iconst_0",
+            Instruction::IConst1 => "iconst_1",
+            Instruction::IConst2 => "iconst_2",
+            Instruction::IConst3 => "iconst_3",
+            Instruction::IConst4 => "iconst_4",
+            Instruction::IConst5 => "iconst_5",
+            Instruction::LConst0 => "lconst_0",
+            Instruction::LConst1 => "lconst_1",
+            Instruction::FConst0 => "fconst_0",
+            Instruction::FConst1 => "fconst_1",
+            Instruction::FConst2 => "fconst_2",
+            Instruction::DConst0 => "dconst_0",
+            Instruction::DConst1 => "dconst_1",
+            Instruction::BiPush(_) => "bipush",
+            Instruction::SiPush(_) => "sipush",
+            Instruction::Ldc(_) => "ldc",
+            Instruction::LdcW(_) => "ldc_w",
+            Instruction::Ldc2W(_) => "ldc2_w",
+            Instruction::ILoad(_) => "iload",
+            Instruction::LLoad(_) => "lload",
+            Instruction::FLoad(_) => "fload",
+            Instruction::DLoad(_) => "dload",
+            Instruction::ALoad(_) => "aload",
+            Instruction::ILoad0 => "iload_0",
+            Instruction::ILoad1 => "iload_1",
+            Instruction::ILoad2 => "iload_2",
+            Instruction::ILoad3 => "iload_3",
+            Instruction::LLoad0 => "lload_0",
+            Instruction::LLoad1 => "lload_1",
+            Instruction::LLoad2 => "lload_2",
+            Instruction::LLoad3 => "lload_3",
+            Instruction::FLoad0 => "fload_0",
+            Instruction::FLoad1 => "fload_1",
+            Instruction::FLoad2 => "fload_2",
+            Instruction::FLoad3 => "fload_3",
+            Instruction::DLoad0 => "dload_0",
+            Instruction::DLoad1 => "dload_1",
+            Instruction::DLoad2 => "dload_2",
+            Instruction::DLoad3 => "dload_3",
+            Instruction::ALoad0 => "aload_0",
+            Instruction::ALoad1 => "aload_1",
+            Instruction::ALoad2 => "aload_2",
+            Instruction::ALoad3 => "aload_3",
+            Instruction::IALoad => "iaload",
+            Instruction::LALoad => "laload",
+            Instruction::FALoad => "faload",
+            Instruction::DALoad => "daload",
+            Instruction::AALoad => "aaload",
+            Instruction::BALoad => "baload",
+            Instruction::CALoad => "caload",
+            Instruction::SALoad => "saload",
+            Instruction::IStore(_) => "istore",
+            Instruction::LStore(_) => "lstore",
+            Instruction::FStore(_) => "fstore",
+            Instruction::DStore(_) => "dstore",
+            Instruction::AStore(_) => "astore",
+            Instruction::IStore0 => "istore_0",
+            Instruction::IStore1 => "istore_1",
+            Instruction::IStore2 => "istore_2",
+            Instruction::IStore3 => "istore_3",
+            Instruction::LStore0 => "lstore_0",
+            Instruction::LStore1 => "lstore_1",
+            Instruction::LStore2 => "lstore_2",
+            Instruction::LStore3 => "lstore_3",
+            Instruction::FStore0 => "fstore_0",
+            Instruction::FStore1 => "fstore_1",
+            Instruction::FStore2 => "fstore_2",
+            Instruction::FStore3 => "fstore_3",
+            Instruction::DStore0 => "dstore_0",
+            Instruction::DStore1 => "dstore_1",
+            Instruction::DStore2 => "dstore_2",
+            Instruction::DStore3 => "dstore_3",
+            Instruction::AStore0 => "astore_0",
+            Instruction::AStore1 => "astore_1",
+            Instruction::AStore2 => "astore_2",
+            Instruction::AStore3 => "astore_3",
+            Instruction::IAStore => "iastore",
+            Instruction::LAStore => "lastore",
+            Instruction::FAStore => "fastore",
+            Instruction::DAStore => "dastore",
+            Instruction::AAStore => "aastore",
+            Instruction::BAStore => "bastore",
+            Instruction::CAStore => "castore",
+            Instruction::SAStore => "sastore",
+            Instruction::Pop => "pop",
+            Instruction::Pop2 => "pop2",
+            Instruction::Dup => "dup",
+            Instruction::DupX1 => "dup_x1",
+            Instruction::DupX2 => "dup_x2",
+            Instruction::Dup2 => "dup2",
+            Instruction::Dup2X1 => "dup2_x1",
+            Instruction::Dup2X2 => "dup2_x2",
+            Instruction::Swap => "swap",
+            Instruction::IAdd => "iadd",
+            Instruction::LAdd => "ladd",
+            Instruction::FAdd => "fadd",
+            Instruction::DAdd => "dadd",
+            Instruction::ISub => "isub",
+            Instruction::LSub => "lsub",
+            Instruction::FSub => "fsub",
+            Instruction::DSub => "dsub",
+            Instruction::IMul => "imul",
+            Instruction::LMul => "lmul",
+            Instruction::FMul => "fmul",
+            Instruction::DMul => "dmul",
+            Instruction::IDiv => "idiv",
+            Instruction::LDiv => "ldiv",
+            Instruction::FDiv => "fdiv",
+            Instruction::DDiv => "ddiv",
+            Instruction::IRem => "irem",
+            Instruction::LRem => "lrem",
+            Instruction::FRem => "frem",
+            Instruction::DRem => "drem",
+            Instruction::INeg => "ineg",
+            Instruction::LNeg => "lneg",
+            Instruction::FNeg => "fneg",
+            Instruction::DNeg => "dneg",
+            Instruction::IShl => "ishl",
+            Instruction::LShl => "lshl",
+            Instruction::IShr => "ishr",
+            Instruction::LShr => "lshr",
+            Instruction::IUShr => "iushr",
+            Instruction::LUShr => "lushr",
+            Instruction::IAnd => "iand",
+            Instruction::LAnd => "land",
+            Instruction::IOr => "ior",
+            Instruction::LOr => "lor",
+            Instruction::IXor => "ixor",
+            Instruction::LXor => "lxor",
+            Instruction::IInc(..) => "iinc",
+            Instruction::I2L => "i2l",
+            Instruction::I2F => "i2f",
+            Instruction::I2D => "i2d",
+            Instruction::L2I => "l2i",
+            Instruction::L2F => "l2f",
+            Instruction::L2D => "l2d",
+            Instruction::F2I => "f2i",
+            Instruction::F2L => "f2l",
+            Instruction::F2D => "f2d",
+            Instruction::D2I => "d2i",
+            Instruction::D2L => "d2l",
+            Instruction::D2F => "d2f",
+            Instruction::I2B => "i2b",
+            Instruction::I2C => "i2c",
+            Instruction::I2S => "i2s",
+            Instruction::LCmp => "lcmp",
+            Instruction::FCmpL => "fcmpl",
+            Instruction::FCmpG => "fcmpg",
+            Instruction::DCmpL => "dcmpl",
+            Instruction::DCmpG => "dcmpg",
+            Instruction::IfEq(_) => "ifeq",
+            Instruction::IfNe(_) => "ifne",
+            Instruction::IfLt(_) => "iflt",
+            Instruction::IfGe(_) => "ifge",
+            Instruction::IfGt(_) => "ifgt",
+            Instruction::IfLe(_) => "ifle",
+            Instruction::IfICmpEq(_) => "if_icmpeq",
+            Instruction::IfICmpNe(_) => "if_icmpne",
+            Instruction::IfICmpLt(_) => "if_icmplt",
+            Instruction::IfICmpGe(_) => "if_icmpge",
+            Instruction::IfICmpGt(_) => "if_icmpgt",
+            Instruction::IfICmpLe(_) => "if_icmple",
+            Instruction::IfACmpEq(_) => "if_acmpeq",
+            Instruction::IfACmpNe(_) => "if_acmpne",
+            Instruction::Goto(_) => "goto",
+            Instruction::Jsr(_) => "jsr",
+            Instruction::Ret(_) => "ret",
+            Instruction::TableSwitch { .. } => "tableswitch",
+            Instruction::LookupSwitch { .. } => "lookupswitch",
+            Instruction::IReturn => "ireturn",
+            Instruction::LReturn => "lreturn",
+            Instruction::FReturn => "freturn",
+            Instruction::DReturn => "dreturn",
+            Instruction::AReturn => "areturn",
+            Instruction::Return => "return",
+            Instruction::GetStatic(_) => "getstatic",
+            Instruction::PutStatic(_) => "putstatic",
+            Instruction::GetField(_) => "getfield",
+            Instruction::PutField(_) => "putfield",
+            Instruction::InvokeVirtual(_) => "invokevirtual",
+            Instruction::InvokeSpecial(_) => "invokespecial",
+            Instruction::InvokeStatic(_) => "invokestatic",
+            Instruction::InvokeInterface(..) => "invokeinterface",
+            Instruction::InvokeDynamic(_) => "invokedynamic",
+            Instruction::New(_) => "new",
+            Instruction::NewArray(_) => "newarray",
+            Instruction::ANewArray(_) => "anewarray",
+            Instruction::ArrayLength => "arraylength",
+            Instruction::AThrow => "athrow",
+            Instruction::CheckCast(_) => "checkcast",
+            Instruction::InstanceOf(_) => "instanceof",
+            Instruction::MonitorEnter => "monitorenter",
+            Instruction::MonitorExit => "monitorexit",
+            Instruction::Wide(_) => "wide",
+            Instruction::MultiANewArray(..) => "multianewarray",
+            Instruction::IfNull(_) => "ifnull",
+            Instruction::IfNonNull(_) => "ifnonnull",
+            Instruction::GotoW(_) => "goto_w",
+            Instruction::JsrW(_) => "jsr_w",
+        }
+    }
+
+    /// Constant pool index referenced by the instruction, if any
+    pub fn constant_index(&self) -> Option<u16> {
+        match self {
+            Instruction::Ldc(index) => Some(*index as u16),
+            Instruction::LdcW(index)
+            | Instruction::Ldc2W(index)
+            | Instruction::GetStatic(index)
+            | Instruction::PutStatic(index)
+            | Instruction::GetField(index)
+            | Instruction::PutField(index)
+            | Instruction::InvokeVirtual(index)
+            | Instruction::InvokeSpecial(index)
+            | Instruction::InvokeStatic(index)
+            | Instruction::InvokeInterface(index, _)
+            | Instruction::InvokeDynamic(index)
+            | Instruction::New(index)
+            | Instruction::ANewArray(index)
+            | Instruction::CheckCast(index)
+            | Instruction::InstanceOf(index)
+            | Instruction::MultiANewArray(index, _) => Some(*index),
+            _ => None,
+        }
+    }
+
+    /// Number of bytes the instruction takes when encoded at `offset`
+    pub fn length(&self, offset: u32) -> u32 {
+        let padding = 3 - offset % 4;
+        match self {
+            Instruction::TableSwitch { offsets, .. } => 1 + padding + 12 + 4 * offsets.len() as u32,
+            Instruction::LookupSwitch { pairs, .. } => 1 + padding + 8 + 8 * pairs.len() as u32,
+            Instruction::Wide(Wide::IInc(..)) => 6,
+            Instruction::Wide(_) => 4,
+            Instruction::BiPush(_)
+            | Instruction::Ldc(_)
+            | Instruction::ILoad(_)
+            | Instruction::LLoad(_)
+            | Instruction::FLoad(_)
+            | Instruction::DLoad(_)
+            | Instruction::ALoad(_)
+            | Instruction::IStore(_)
+            | Instruction::LStore(_)
+            | Instruction::FStore(_)
+            | Instruction::DStore(_)
+            | Instruction::AStore(_)
+            | Instruction::Ret(_)
+            | Instruction::NewArray(_) => 2,
+            Instruction::MultiANewArray(..) => 4,
+            Instruction::InvokeInterface(..)
+            | Instruction::InvokeDynamic(_)
+            | Instruction::GotoW(_)
+            | Instruction::JsrW(_) => 5,
+            Instruction::SiPush(_) | Instruction::IInc(..) => 3,
+            _ if self.constant_index().is_some() || !self.branch_targets(0).is_empty() => 3,
+            _ => 1,
+        }
+    }
+
+    /// Encodes the instruction located at `offset`, counterpart of [`Instruction::from`]
+    pub fn write(&self, out: &mut Vec<u8>, offset: u32) {
+        out.put_u1(self.opcode());
+        match self {
+            Instruction::BiPush(value) => out.put_u1(*value as u8),
+            Instruction::SiPush(value) => out.put_u2(*value as u16),
+            Instruction::Ldc(index)
+            | Instruction::ILoad(index)
+            | Instruction::LLoad(index)
+            | Instruction::FLoad(index)
+            | Instruction::DLoad(index)
+            | Instruction::ALoad(index)
+            | Instruction::IStore(index)
+            | Instruction::LStore(index)
+            | Instruction::FStore(index)
+            | Instruction::DStore(index)
+            | Instruction::AStore(index)
+            | Instruction::Ret(index)
+            | Instruction::NewArray(index) => out.put_u1(*index),
+            Instruction::IInc(index, value) => {
+                out.put_u1(*index);
+                out.put_u1(*value as u8);
+            }
+            Instruction::InvokeInterface(index, count) => {
+                out.put_u2(*index);
+                out.put_u1(*count);
+                out.put_u1(0);
+            }
+            Instruction::InvokeDynamic(index) => {
+                out.put_u2(*index);
+                out.put_u2(0);
+            }
+            Instruction::MultiANewArray(index, dimensions) => {
+                out.put_u2(*index);
+                out.put_u1(*dimensions);
+            }
+            Instruction::GotoW(relative) | Instruction::JsrW(relative) => {
+                out.put_u4(*relative as u32)
+            }
+            Instruction::TableSwitch {
+                default,
+                low,
+                high,
+                offsets,
+            } => {
+                out.extend(std::iter::repeat_n(0, (3 - offset % 4) as usize));
+                out.put_u4(*default as u32);
+                out.put_u4(*low as u32);
+                out.put_u4(*high as u32);
+                for relative in offsets {
+                    out.put_u4(*relative as u32);
+                }
+            }
+            Instruction::LookupSwitch { default, pairs } => {
+                out.extend(std::iter::repeat_n(0, (3 - offset % 4) as usize));
+                out.put_u4(*default as u32);
+                out.put_u4(pairs.len() as u32);
+                for (key, relative) in pairs {
+                    out.put_u4(*key as u32);
+                    out.put_u4(*relative as u32);
+                }
+            }
+            Instruction::Wide(wide) => {
+                out.put_u1(wide.opcode());
+                out.put_u2(wide.index());
+                if let Wide::IInc(_, value) = wide {
+                    out.put_u2(*value as u16);
+                }
+            }
+            _ => {
+                if let Some(index) = self.constant_index() {
+                    out.put_u2(index);
+                } else if let [target] = self.branch_targets(offset).as_slice() {
+                    out.put_u2((target - offset as i64) as u16);
+                }
+            }
+        }
+    }
+
+    /// Absolute offsets that the instruction at `offset` may jump to, including the switch
+    /// default. Targets are not validated, so they may be negative or past the end of code.
+    pub fn branch_targets(&self, offset: u32) -> Vec<i64> {
+        let target = |relative: i64| offset as i64 + relative;
+        match self {
+            Instruction::IfEq(relative)
+            | Instruction::IfNe(relative)
+            | Instruction::IfLt(relative)
+            | Instruction::IfGe(relative)
+            | Instruction::IfGt(relative)
+            | Instruction::IfLe(relative)
+            | Instruction::IfICmpEq(relative)
+            | Instruction::IfICmpNe(relative)
+            | Instruction::IfICmpLt(relative)
+            | Instruction::IfICmpGe(relative)
+            | Instruction::IfICmpGt(relative)
+            | Instruction::IfICmpLe(relative)
+            | Instruction::IfACmpEq(relative)
+            | Instruction::IfACmpNe(relative)
+            | Instruction::IfNull(relative)
+            | Instruction::IfNonNull(relative)
+            | Instruction::Goto(relative)
+            | Instruction::Jsr(relative) => vec![target(*relative as i64)],
+            Instruction::GotoW(relative) | Instruction::JsrW(relative) => {
+                vec![target(*relative as i64)]
+            }
+            Instruction::TableSwitch {
+                default, offsets, ..
+            } => std::iter::once(default)
+                .chain(offsets)
+                .map(|relative| target(*relative as i64))
+                .collect(),
+            Instruction::LookupSwitch { default, pairs } => std::iter::once(default)
+                .chain(pairs.iter().map(|(_, relative)| relative))
+                .map(|relative| target(*relative as i64))
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+/// Switch operands start at the address that is a multiple of 4 from the start of the code
+fn skip_switch_padding<F: ByteUtils>(file: &mut F, offset: u32) -> std::io::Result<()> {
+    for _ in 0..(3 - offset % 4) {
+        file.read_u1()?;
+    }
+    Ok(())
+}
+
+/// Names of `newarray` element types, indexed by `atype`
+pub fn array_type_name(atype: u8) -> Option<&'static str> {
+    Some(match atype {
+        4 => "boolean",
+        5 => "char",
+        6 => "float",
+        7 => "double",
+        8 => "byte",
+        9 => "short",
+        10 => "int",
+        11 => "long",
+        _ => return None,
+    })
+}
+
+impl std::fmt::Display for Instruction {
+    /// Mnemonic followed by operands exactly as encoded, for example `iinc 1 -1` or `goto -12`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.mnemonic())?;
+        match self {
+            Instruction::BiPush(value) => write!(f, " {value}"),
+            Instruction::SiPush(value) => write!(f, " {value}"),
+            Instruction::ILoad(index)
+            | Instruction::LLoad(index)
+            | Instruction::FLoad(index)
+            | Instruction::DLoad(index)
+            | Instruction::ALoad(index)
+            | Instruction::IStore(index)
+            | Instruction::LStore(index)
+            | Instruction::FStore(index)
+            | Instruction::DStore(index)
+            | Instruction::AStore(index)
+            | Instruction::Ret(index)
+            | Instruction::NewArray(index) => write!(f, " {index}"),
+            Instruction::IInc(index, value) => write!(f, " {index} {value}"),
+            Instruction::IfEq(offset)
+            | Instruction::IfNe(offset)
+            | Instruction::IfLt(offset)
+            | Instruction::IfGe(offset)
+            | Instruction::IfGt(offset)
+            | Instruction::IfLe(offset)
+            | Instruction::IfICmpEq(offset)
+            | Instruction::IfICmpNe(offset)
+            | Instruction::IfICmpLt(offset)
+            | Instruction::IfICmpGe(offset)
+            | Instruction::IfICmpGt(offset)
+            | Instruction::IfICmpLe(offset)
+            | Instruction::IfACmpEq(offset)
+            | Instruction::IfACmpNe(offset)
+            | Instruction::IfNull(offset)
+            | Instruction::IfNonNull(offset)
+            | Instruction::Goto(offset)
+            | Instruction::Jsr(offset) => write!(f, " {offset}"),
+            Instruction::GotoW(offset) | Instruction::JsrW(offset) => write!(f, " {offset}"),
+            Instruction::InvokeInterface(index, count) => write!(f, " {index} {count}"),
+            Instruction::MultiANewArray(index, dimensions) => write!(f, " {index} {dimensions}"),
+            Instruction::TableSwitch {
+                default,
+                low,
+                high,
+                offsets,
+            } => {
+                write!(f, " {low} {high}")?;
+                for offset in offsets {
+                    write!(f, " {offset}")?;
+                }
+                write!(f, " default {default}")
+            }
+            Instruction::LookupSwitch { default, pairs } => {
+                for (key, offset) in pairs {
+                    write!(f, " {key}:{offset}")?;
+                }
+                write!(f, " default {default}")
+            }
+            Instruction::Wide(Wide::IInc(index, value)) => write!(f, " iinc {index} {value}"),
+            Instruction::Wide(wide) => write!(f, " {} {}", wide.mnemonic(), wide.index()),
+            _ => match self.constant_index() {
+                Some(index) => write!(f, " {index}"),
+                None => Ok(()),
+            },
+        }
+    }
 }
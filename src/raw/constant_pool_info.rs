@@ -1,78 +1,342 @@
 use crate::raw::*;
 use num_enum::TryFromPrimitive;
-use std::fs::File;
-use std::io::Read;
 
 #[derive(TryFromPrimitive)]
 #[repr(u8)]
 enum ConstantPoolInfoTag {
     Class = 7,
+    Double = 6,
+    Dynamic = 17,
     FieldRef = 9,
+    Float = 4,
+    Integer = 3,
+    InterfaceMethodRef = 11,
+    InvokeDynamic = 18,
+    Long = 5,
+    MethodHandle = 15,
     MethodRef = 10,
+    MethodType = 16,
+    Module = 19,
     NameAndType = 12,
+    Package = 20,
     String = 8,
     Utf8 = 1,
 }
 
+impl ConstantPoolInfoTag {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Class => "Class",
+            Self::Double => "Double",
+            Self::Dynamic => "Dynamic",
+            Self::FieldRef => "Fieldref",
+            Self::Float => "Float",
+            Self::Integer => "Integer",
+            Self::InterfaceMethodRef => "InterfaceMethodref",
+            Self::InvokeDynamic => "InvokeDynamic",
+            Self::Long => "Long",
+            Self::MethodHandle => "MethodHandle",
+            Self::MethodRef => "Methodref",
+            Self::MethodType => "MethodType",
+            Self::Module => "Module",
+            Self::NameAndType => "NameAndType",
+            Self::Package => "Package",
+            Self::String => "String",
+            Self::Utf8 => "Utf8",
+        }
+    }
+}
+
 // aka cp_info
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ConstantPoolInfo {
     Class {
         name_index: u16,
     },
+    Double(f64),
+    Dynamic {
+        bootstrap_method_attr_index: u16,
+        name_and_type_index: u16,
+    },
     FieldRef {
         class_index: u16,
         name_and_type_index: u16,
     },
+    Float(f32),
+    Integer(i32),
+    InterfaceMethodRef {
+        class_index: u16,
+        name_and_type_index: u16,
+    },
+    InvokeDynamic {
+        bootstrap_method_attr_index: u16,
+        name_and_type_index: u16,
+    },
+    Long(i64),
+    MethodHandle {
+        reference_kind: u8,
+        reference_index: u16,
+    },
     MethodRef {
         class_index: u16,
         name_and_type_index: u16,
     },
+    MethodType {
+        descriptor_index: u16,
+    },
+    Module {
+        name_index: u16,
+    },
     NameAndType {
         name_index: u16,
         descriptor_index: u16,
     },
+    Package {
+        name_index: u16,
+    },
     String {
         string_index: u16,
     },
     Utf8(String),
+    /// Utf8 entry whose text has unpaired surrogates, which a `String` cannot hold, as its
+    /// UTF-16 code units
+    Utf16(Vec<u16>),
+    /// Slot following Long or Double, which take two entries in the constant pool
+    Unusable,
 }
 
+/// Names of `MethodHandle.reference_kind` values, indexed by kind
+pub const REFERENCE_KINDS: [&str; 10] = [
+    "",
+    "REF_getField",
+    "REF_getStatic",
+    "REF_putField",
+    "REF_putStatic",
+    "REF_invokeVirtual",
+    "REF_invokeStatic",
+    "REF_invokeSpecial",
+    "REF_newInvokeSpecial",
+    "REF_invokeInterface",
+];
+
 impl ConstantPoolInfo {
-    pub fn from(file: &mut File) -> std::io::Result<ConstantPoolInfo> {
-        let tag = file.read_u1()?;
-        if let Ok(tag) = ConstantPoolInfoTag::try_from(tag) {
-            Ok(match tag {
-                ConstantPoolInfoTag::Class => ConstantPoolInfo::Class {
-                    name_index: file.read_u2()?,
-                },
-                ConstantPoolInfoTag::MethodRef => ConstantPoolInfo::MethodRef {
-                    class_index: file.read_u2()?,
-                    name_and_type_index: file.read_u2()?,
-                },
-                ConstantPoolInfoTag::FieldRef => ConstantPoolInfo::FieldRef {
-                    class_index: file.read_u2()?,
-                    name_and_type_index: file.read_u2()?,
-                },
-                ConstantPoolInfoTag::NameAndType => ConstantPoolInfo::NameAndType {
-                    name_index: file.read_u2()?,
-                    descriptor_index: file.read_u2()?,
-                },
-                ConstantPoolInfoTag::String => ConstantPoolInfo::String {
-                    string_index: file.read_u2()?,
-                },
-                ConstantPoolInfoTag::Utf8 => {
-                    let length = file.read_u2()?;
-                    let mut buf = vec![0u8; length.into()];
-                    file.read_exact(buf.as_mut_slice())?;
-                    // SAFETY: We belive that Java compilers know how to generate valid utf8
-                    // strings
-                    ConstantPoolInfo::Utf8(unsafe { String::from_utf8_unchecked(buf) })
+    pub fn from<F: ByteUtils>(file: &mut F) -> std::io::Result<ConstantPoolInfo> {
+        let tag = file.field("tag").read_u1()?;
+        let Ok(tag) = ConstantPoolInfoTag::try_from(tag) else {
+            return Err(invalid_data(format!("unknown constant pool tag {tag}")));
+        };
+
+        file.enter(|| tag.name().to_string());
+        let constant = match tag {
+            ConstantPoolInfoTag::Class => ConstantPoolInfo::Class {
+                name_index: file.field("name_index").read_u2()?,
+            },
+            ConstantPoolInfoTag::Double => {
+                ConstantPoolInfo::Double(f64::from_bits(read_high_low(file)?))
+            }
+            ConstantPoolInfoTag::Dynamic => ConstantPoolInfo::Dynamic {
+                bootstrap_method_attr_index: file.field("bootstrap_method_attr_index").read_u2()?,
+                name_and_type_index: file.field("name_and_type_index").read_u2()?,
+            },
+            ConstantPoolInfoTag::Float => {
+                ConstantPoolInfo::Float(f32::from_bits(file.field("bytes").read_u4()?))
+            }
+            ConstantPoolInfoTag::Integer => {
+                ConstantPoolInfo::Integer(file.field("bytes").read_u4()? as i32)
+            }
+            ConstantPoolInfoTag::InterfaceMethodRef => ConstantPoolInfo::InterfaceMethodRef {
+                class_index: file.field("class_index").read_u2()?,
+                name_and_type_index: file.field("name_and_type_index").read_u2()?,
+            },
+            ConstantPoolInfoTag::InvokeDynamic => ConstantPoolInfo::InvokeDynamic {
+                bootstrap_method_attr_index: file.field("bootstrap_method_attr_index").read_u2()?,
+                name_and_type_index: file.field("name_and_type_index").read_u2()?,
+            },
+            ConstantPoolInfoTag::Long => ConstantPoolInfo::Long(read_high_low(file)? as i64),
+            ConstantPoolInfoTag::MethodHandle => ConstantPoolInfo::MethodHandle {
+                reference_kind: file.field("reference_kind").read_u1()?,
+                reference_index: file.field("reference_index").read_u2()?,
+            },
+            ConstantPoolInfoTag::MethodRef => ConstantPoolInfo::MethodRef {
+                class_index: file.field("class_index").read_u2()?,
+                name_and_type_index: file.field("name_and_type_index").read_u2()?,
+            },
+            ConstantPoolInfoTag::MethodType => ConstantPoolInfo::MethodType {
+                descriptor_index: file.field("descriptor_index").read_u2()?,
+            },
+            ConstantPoolInfoTag::Module => ConstantPoolInfo::Module {
+                name_index: file.field("name_index").read_u2()?,
+            },
+            ConstantPoolInfoTag::FieldRef => ConstantPoolInfo::FieldRef {
+                class_index: file.field("class_index").read_u2()?,
+                name_and_type_index: file.field("name_and_type_index").read_u2()?,
+            },
+            ConstantPoolInfoTag::NameAndType => ConstantPoolInfo::NameAndType {
+                name_index: file.field("name_index").read_u2()?,
+                descriptor_index: file.field("descriptor_index").read_u2()?,
+            },
+            ConstantPoolInfoTag::Package => ConstantPoolInfo::Package {
+                name_index: file.field("name_index").read_u2()?,
+            },
+            ConstantPoolInfoTag::String => ConstantPoolInfo::String {
+                string_index: file.field("string_index").read_u2()?,
+            },
+            ConstantPoolInfoTag::Utf8 => {
+                let length = file.field("length").read_u2()?;
+                let mut buf = vec![0u8; length.into()];
+                file.field("bytes").read_exact(buf.as_mut_slice())?;
+                let units = decode_modified_utf8(&buf);
+                match String::from_utf16(&units) {
+                    Ok(text) => ConstantPoolInfo::Utf8(text),
+                    Err(_) => ConstantPoolInfo::Utf16(units),
                 }
-            })
-        } else {
-            println!("Skipping tag {tag} due to not beeing implemented yet");
-            panic!();
+            }
+        };
+        file.leave();
+        Ok(constant)
+    }
+
+    /// Encodes the entry, [`ConstantPoolInfo::Unusable`] takes no bytes
+    pub fn write(&self, out: &mut Vec<u8>) -> std::io::Result<()> {
+        match self {
+            ConstantPoolInfo::Utf8(_) | ConstantPoolInfo::Utf16(_) => {
+                let bytes = encode_modified_utf8(&self.utf16().unwrap_or_default());
+                let Ok(length) = u16::try_from(bytes.len()) else {
+                    return Err(invalid_data(format!(
+                        "Utf8 constant of {} bytes is longer than 65535 bytes",
+                        bytes.len()
+                    )));
+                };
+                out.put_u1(ConstantPoolInfoTag::Utf8 as u8);
+                out.put_u2(length);
+                out.extend_from_slice(&bytes);
+            }
+            ConstantPoolInfo::Integer(value) => {
+                out.put_u1(ConstantPoolInfoTag::Integer as u8);
+                out.put_u4(*value as u32);
+            }
+            ConstantPoolInfo::Float(value) => {
+                out.put_u1(ConstantPoolInfoTag::Float as u8);
+                out.put_u4(value.to_bits());
+            }
+            ConstantPoolInfo::Long(value) => {
+                out.put_u1(ConstantPoolInfoTag::Long as u8);
+                out.extend_from_slice(&value.to_be_bytes());
+            }
+            ConstantPoolInfo::Double(value) => {
+                out.put_u1(ConstantPoolInfoTag::Double as u8);
+                out.extend_from_slice(&value.to_bits().to_be_bytes());
+            }
+            ConstantPoolInfo::Class { name_index } => {
+                out.put_u1(ConstantPoolInfoTag::Class as u8);
+                out.put_u2(*name_index);
+            }
+            ConstantPoolInfo::String { string_index } => {
+                out.put_u1(ConstantPoolInfoTag::String as u8);
+                out.put_u2(*string_index);
+            }
+            ConstantPoolInfo::FieldRef {
+                class_index,
+                name_and_type_index,
+            } => {
+                out.put_u1(ConstantPoolInfoTag::FieldRef as u8);
+                out.put_u2(*class_index);
+                out.put_u2(*name_and_type_index);
+            }
+            ConstantPoolInfo::MethodRef {
+                class_index,
+                name_and_type_index,
+            } => {
+                out.put_u1(ConstantPoolInfoTag::MethodRef as u8);
+                out.put_u2(*class_index);
+                out.put_u2(*name_and_type_index);
+            }
+            ConstantPoolInfo::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            } => {
+                out.put_u1(ConstantPoolInfoTag::InterfaceMethodRef as u8);
+                out.put_u2(*class_index);
+                out.put_u2(*name_and_type_index);
+            }
+            ConstantPoolInfo::NameAndType {
+                name_index,
+                descriptor_index,
+            } => {
+                out.put_u1(ConstantPoolInfoTag::NameAndType as u8);
+                out.put_u2(*name_index);
+                out.put_u2(*descriptor_index);
+            }
+            ConstantPoolInfo::MethodHandle {
+                reference_kind,
+                reference_index,
+            } => {
+                out.put_u1(ConstantPoolInfoTag::MethodHandle as u8);
+                out.put_u1(*reference_kind);
+                out.put_u2(*reference_index);
+            }
+            ConstantPoolInfo::MethodType { descriptor_index } => {
+                out.put_u1(ConstantPoolInfoTag::MethodType as u8);
+                out.put_u2(*descriptor_index);
+            }
+            ConstantPoolInfo::Dynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => {
+                out.put_u1(ConstantPoolInfoTag::Dynamic as u8);
+                out.put_u2(*bootstrap_method_attr_index);
+                out.put_u2(*name_and_type_index);
+            }
+            ConstantPoolInfo::InvokeDynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => {
+                out.put_u1(ConstantPoolInfoTag::InvokeDynamic as u8);
+                out.put_u2(*bootstrap_method_attr_index);
+                out.put_u2(*name_and_type_index);
+            }
+            ConstantPoolInfo::Module { name_index } => {
+                out.put_u1(ConstantPoolInfoTag::Module as u8);
+                out.put_u2(*name_index);
+            }
+            ConstantPoolInfo::Package { name_index } => {
+                out.put_u1(ConstantPoolInfoTag::Package as u8);
+                out.put_u2(*name_index);
+            }
+            ConstantPoolInfo::Unusable => {}
+        }
+        Ok(())
+    }
+
+    /// Number of constant pool slots that this entry occupies
+    pub fn slots(&self) -> usize {
+        match self {
+            Self::Long(_) | Self::Double(_) => 2,
+            _ => 1,
+        }
+    }
+
+    /// Name of the entry's tag as used by the JVM specification (`Methodref`, `Utf8`, ...)
+    pub fn tag_name(&self) -> &'static str {
+        match self {
+            Self::Class { .. } => "Class",
+            Self::Double(_) => "Double",
+            Self::Dynamic { .. } => "Dynamic",
+            Self::FieldRef { .. } => "Fieldref",
+            Self::Float(_) => "Float",
+            Self::Integer(_) => "Integer",
+            Self::InterfaceMethodRef { .. } => "InterfaceMethodref",
+            Self::InvokeDynamic { .. } => "InvokeDynamic",
+            Self::Long(_) => "Long",
+            Self::MethodHandle { .. } => "MethodHandle",
+            Self::MethodRef { .. } => "Methodref",
+            Self::MethodType { .. } => "MethodType",
+            Self::Module { .. } => "Module",
+            Self::NameAndType { .. } => "NameAndType",
+            Self::Package { .. } => "Package",
+            Self::String { .. } => "String",
+            Self::Utf8(_) | Self::Utf16(_) => "Utf8",
+            Self::Unusable => "Unusable",
         }
     }
 
@@ -96,4 +360,164 @@ impl ConstantPoolInfo {
             None
         }
     }
+
+    /// UTF-16 code units of a Utf8 entry, with its unpaired surrogates
+    pub fn utf16(&self) -> Option<Vec<u16>> {
+        match self {
+            ConstantPoolInfo::Utf8(text) => Some(text.encode_utf16().collect()),
+            ConstantPoolInfo::Utf16(units) => Some(units.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Reads `high_bytes` and `low_bytes` pair used by Long and Double
+fn read_high_low<F: ByteUtils>(file: &mut F) -> std::io::Result<u64> {
+    let high = file.field("high_bytes").read_u4()? as u64;
+    let low = file.field("low_bytes").read_u4()? as u64;
+    Ok((high << 32) | low)
+}
+
+/// Reads `count - 1` entries of the constant pool, inserting [`ConstantPoolInfo::Unusable`]
+/// after every Long and Double so that indexing with `index - 1` stays valid
+pub fn read_constant_pool<F: ByteUtils>(
+    file: &mut F,
+    count: u16,
+) -> std::io::Result<Vec<ConstantPoolInfo>> {
+    let count = (count as usize).saturating_sub(1);
+    let mut constant_pool = Vec::with_capacity(count);
+    while constant_pool.len() < count {
+        let index = constant_pool.len() + 1;
+        file.enter(|| format!("constant_pool[{index}]"));
+        let constant = ConstantPoolInfo::from(file)?;
+        file.leave();
+        let slots = constant.slots();
+        constant_pool.push(constant);
+        if slots == 2 {
+            constant_pool.push(ConstantPoolInfo::Unusable);
+        }
+    }
+    Ok(constant_pool)
+}
+
+/// Writes `constant_pool_count` followed by the entries, counterpart of [`read_constant_pool`]
+pub fn write_constant_pool(
+    out: &mut Vec<u8>,
+    constant_pool: &[ConstantPoolInfo],
+) -> std::io::Result<()> {
+    put_count(out, constant_pool.len() + 1, "constant pool")?;
+    for constant in constant_pool {
+        constant.write(out)?;
+    }
+    Ok(())
+}
+
+/// Constant pool entry under 1-based `index`, if it exists
+pub fn constant(constant_pool: &[ConstantPoolInfo], index: u16) -> Option<&ConstantPoolInfo> {
+    constant_pool.get((index as usize).checked_sub(1)?)
+}
+
+/// UTF-16 code units of the Utf8 entry under `index`, which may be unpaired surrogates
+pub fn utf16_at(constant_pool: &[ConstantPoolInfo], index: u16) -> Option<Vec<u16>> {
+    constant(constant_pool, index)?.utf16()
+}
+
+/// Content of the Utf8 entry under `index`
+pub fn utf8_at(constant_pool: &[ConstantPoolInfo], index: u16) -> Option<&str> {
+    match constant(constant_pool, index)? {
+        ConstantPoolInfo::Utf8(value) => Some(value),
+        _ => None,
+    }
+}
+
+/// Internal name of the Class entry under `index`, like `java/lang/Object`
+pub fn class_name_at(constant_pool: &[ConstantPoolInfo], index: u16) -> Option<&str> {
+    match constant(constant_pool, index)? {
+        ConstantPoolInfo::Class { name_index } => utf8_at(constant_pool, *name_index),
+        _ => None,
+    }
+}
+
+/// Name and descriptor of the NameAndType entry under `index`
+pub fn name_and_type_at(constant_pool: &[ConstantPoolInfo], index: u16) -> Option<(&str, &str)> {
+    match constant(constant_pool, index)? {
+        ConstantPoolInfo::NameAndType {
+            name_index,
+            descriptor_index,
+        } => Some((
+            utf8_at(constant_pool, *name_index)?,
+            utf8_at(constant_pool, *descriptor_index)?,
+        )),
+        _ => None,
+    }
+}
+
+/// Owner class, name and descriptor of Fieldref, Methodref or InterfaceMethodref under `index`
+pub fn member_ref_at(constant_pool: &[ConstantPoolInfo], index: u16) -> Option<(&str, &str, &str)> {
+    match constant(constant_pool, index)? {
+        ConstantPoolInfo::FieldRef {
+            class_index,
+            name_and_type_index,
+        }
+        | ConstantPoolInfo::MethodRef {
+            class_index,
+            name_and_type_index,
+        }
+        | ConstantPoolInfo::InterfaceMethodRef {
+            class_index,
+            name_and_type_index,
+        } => {
+            let class = class_name_at(constant_pool, *class_index)?;
+            let (name, descriptor) = name_and_type_at(constant_pool, *name_and_type_index)?;
+            Some((class, name, descriptor))
+        }
+        _ => None,
+    }
+}
+
+/// Decodes the "modified UTF-8" used by class files into UTF-16 code units: NUL is
+/// encoded on two bytes and supplementary characters as surrogate pairs of three bytes each
+pub fn decode_modified_utf8(bytes: &[u8]) -> Vec<u16> {
+    let mut units: Vec<u16> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let a = bytes[i] as u16;
+        if a & 0x80 == 0 {
+            units.push(a);
+            i += 1;
+        } else if a & 0xe0 == 0xc0 && i + 1 < bytes.len() {
+            let b = bytes[i + 1] as u16;
+            units.push(((a & 0x1f) << 6) | (b & 0x3f));
+            i += 2;
+        } else if a & 0xf0 == 0xe0 && i + 2 < bytes.len() {
+            let b = bytes[i + 1] as u16;
+            let c = bytes[i + 2] as u16;
+            units.push(((a & 0x0f) << 12) | ((b & 0x3f) << 6) | (c & 0x3f));
+            i += 3;
+        } else {
+            units.push(char::REPLACEMENT_CHARACTER as u16);
+            i += 1;
+        }
+    }
+    units
+}
+
+/// Encodes UTF-16 code units as "modified UTF-8", counterpart of [`decode_modified_utf8`]
+pub fn encode_modified_utf8(units: &[u16]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(units.len());
+    for &unit in units {
+        match unit {
+            0x0001..=0x007f => bytes.push(unit as u8),
+            0x0000 | 0x0080..=0x07ff => {
+                bytes.push(0xc0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+            _ => {
+                bytes.push(0xe0 | (unit >> 12) as u8);
+                bytes.push(0x80 | ((unit >> 6) & 0x3f) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+        }
+    }
+    bytes
 }
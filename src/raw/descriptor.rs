@@ -0,0 +1,136 @@
+/// Type of a field, parameter or return value as encoded in descriptors, like `I` or
+/// `[Ljava/lang/String;`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    Byte,
+    Char,
+    Double,
+    Float,
+    Int,
+    Long,
+    Short,
+    Boolean,
+    /// Internal name of the class, like `java/lang/String`
+    Object(String),
+    Array(Box<FieldType>),
+}
+
+/// Parsed method descriptor like `(IJ)V`, `return_type` is `None` for `void`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MethodDescriptor {
+    pub parameters: Vec<FieldType>,
+    pub return_type: Option<FieldType>,
+}
+
+impl FieldType {
+    pub fn parse(descriptor: &str) -> Option<FieldType> {
+        match FieldType::parse_prefix(descriptor)? {
+            (field_type, "") => Some(field_type),
+            _ => None,
+        }
+    }
+
+    /// Parses single type from the start of `descriptor`, returning the remaining part
+    pub fn parse_prefix(descriptor: &str) -> Option<(FieldType, &str)> {
+        let mut chars = descriptor.chars();
+        let field_type = match chars.next()? {
+            'B' => FieldType::Byte,
+            'C' => FieldType::Char,
+            'D' => FieldType::Double,
+            'F' => FieldType::Float,
+            'I' => FieldType::Int,
+            'J' => FieldType::Long,
+            'S' => FieldType::Short,
+            'Z' => FieldType::Boolean,
+            'L' => {
+                let end = descriptor.find(';')?;
+                if end == 1 {
+                    return None;
+                }
+                return Some((
+                    FieldType::Object(descriptor[1..end].to_string()),
+                    &descriptor[end + 1..],
+                ));
+            }
+            '[' => {
+                let (element, rest) = FieldType::parse_prefix(&descriptor[1..])?;
+                return Some((FieldType::Array(Box::new(element)), rest));
+            }
+            _ => return None,
+        };
+        Some((field_type, chars.as_str()))
+    }
+
+    /// Number of local variable or operand stack slots the value occupies
+    pub fn slots(&self) -> usize {
+        match self {
+            FieldType::Long | FieldType::Double => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(self, FieldType::Object(_) | FieldType::Array(_))
+    }
+
+    /// Name as written in Java source, like `int[]` or `java.lang.String`
+    pub fn java_name(&self) -> String {
+        match self {
+            FieldType::Byte => "byte".to_string(),
+            FieldType::Char => "char".to_string(),
+            FieldType::Double => "double".to_string(),
+            FieldType::Float => "float".to_string(),
+            FieldType::Int => "int".to_string(),
+            FieldType::Long => "long".to_string(),
+            FieldType::Short => "short".to_string(),
+            FieldType::Boolean => "boolean".to_string(),
+            FieldType::Object(name) => name.replace('/', "."),
+            FieldType::Array(element) => format!("{}[]", element.java_name()),
+        }
+    }
+}
+
+impl std::fmt::Display for FieldType {
+    /// Formats back into the descriptor form
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldType::Byte => write!(f, "B"),
+            FieldType::Char => write!(f, "C"),
+            FieldType::Double => write!(f, "D"),
+            FieldType::Float => write!(f, "F"),
+            FieldType::Int => write!(f, "I"),
+            FieldType::Long => write!(f, "J"),
+            FieldType::Short => write!(f, "S"),
+            FieldType::Boolean => write!(f, "Z"),
+            FieldType::Object(name) => write!(f, "L{name};"),
+            FieldType::Array(element) => write!(f, "[{element}"),
+        }
+    }
+}
+
+impl MethodDescriptor {
+    pub fn parse(descriptor: &str) -> Option<MethodDescriptor> {
+        let mut rest = descriptor.strip_prefix('(')?;
+        let mut parameters = vec![];
+        while !rest.starts_with(')') {
+            let (parameter, remaining) = FieldType::parse_prefix(rest)?;
+            parameters.push(parameter);
+            rest = remaining;
+        }
+
+        let return_type = match &rest[1..] {
+            "V" => None,
+            other => Some(FieldType::parse(other)?),
+        };
+
+        Some(MethodDescriptor {
+            parameters,
+            return_type,
+        })
+    }
+
+    /// Number of local variable slots taken by parameters, without `this`
+    pub fn parameter_slots(&self) -> usize {
+        self.parameters.iter().map(FieldType::slots).sum()
+    }
+}
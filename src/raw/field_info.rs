@@ -0,0 +1,37 @@
+use crate::raw::*;
+
+#[derive(Debug, Default, Clone)]
+pub struct FieldInfo {
+    pub access_flags: AccessFlags,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub attributes: Vec<Attribute>,
+}
+
+impl FieldInfo {
+    pub fn from<F: ByteUtils>(file: &mut F) -> std::io::Result<FieldInfo> {
+        let access_flags = AccessFlags::from(file.field("access_flags").read_u2()?);
+        let name_index = file.field("name_index").read_u2()?;
+        let descriptor_index = file.field("descriptor_index").read_u2()?;
+        let attributes_count = file.field("attributes_count").read_u2()?;
+        let attributes = file.read_table("attributes", attributes_count.into(), Attribute::from)?;
+
+        Ok(FieldInfo {
+            access_flags,
+            name_index,
+            descriptor_index,
+            attributes,
+        })
+    }
+
+    pub fn write(
+        &self,
+        constant_pool: &[ConstantPoolInfo],
+        out: &mut Vec<u8>,
+    ) -> std::io::Result<()> {
+        out.put_u2(self.access_flags.bits());
+        out.put_u2(self.name_index);
+        out.put_u2(self.descriptor_index);
+        write_attributes(out, constant_pool, &self.attributes)
+    }
+}
@@ -0,0 +1,140 @@
+use crate::raw::*;
+use std::fmt::{Display, Formatter};
+
+/// Symbolic names `L0`, `L1`, ... of code offsets that are branch targets or delimit
+/// exception handler ranges. Labels are numbered in the order of their offsets.
+#[derive(Debug, Clone)]
+pub struct Labels {
+    /// Sorted offsets, label number is the position in this list
+    offsets: Vec<u32>,
+    /// Name of the labels before their number
+    prefix: &'static str,
+}
+
+impl Default for Labels {
+    fn default() -> Self {
+        Labels::named("L", vec![])
+    }
+}
+
+impl Labels {
+    /// Collects labels for the given code. Offsets that do not start an instruction
+    /// (and are not the end of the code) do not get a label, since it could not be printed.
+    pub fn new(code: &[(u32, Instruction)], exception_table: &[Exception]) -> Labels {
+        Labels::with_offsets(code, exception_table, [])
+    }
+
+    /// Like [`Labels::new`], also labelling `extra` offsets such as local variable ranges
+    pub fn with_offsets(
+        code: &[(u32, Instruction)],
+        exception_table: &[Exception],
+        extra: impl IntoIterator<Item = u32>,
+    ) -> Labels {
+        let end = code
+            .last()
+            .map(|(offset, instruction)| offset + instruction.length(*offset))
+            .unwrap_or(0);
+
+        let branches = code
+            .iter()
+            .flat_map(|(offset, instruction)| instruction.branch_targets(*offset));
+        let ranges = exception_table.iter().flat_map(|exception| {
+            [exception.start_pc, exception.end_pc, exception.handler_pc].map(i64::from)
+        });
+
+        let mut offsets: Vec<u32> = branches
+            .chain(ranges)
+            .chain(extra.into_iter().map(i64::from))
+            .filter_map(|target| u32::try_from(target).ok())
+            .filter(|target| {
+                *target == end
+                    || code
+                        .binary_search_by_key(target, |(offset, _)| *offset)
+                        .is_ok()
+            })
+            .collect();
+        offsets.sort_unstable();
+        offsets.dedup();
+        Labels::named("L", offsets)
+    }
+
+    /// Names the sorted `offsets` `{prefix}0`, `{prefix}1`, ..., for example `B0`, `B1`, ...
+    /// for the starts of basic blocks
+    pub fn named(prefix: &'static str, offsets: Vec<u32>) -> Labels {
+        Labels { offsets, prefix }
+    }
+
+    /// Labelled offsets, label `{prefix}{n}` is at the n-th of them
+    pub fn offsets(&self) -> &[u32] {
+        &self.offsets
+    }
+
+    /// Label at the given offset, if any
+    pub fn at(&self, offset: u32) -> Option<String> {
+        self.offsets
+            .binary_search(&offset)
+            .ok()
+            .map(|index| format!("{}{index}", self.prefix))
+    }
+
+    /// Label of the branch target, or the offset exactly as encoded when the target has no label
+    fn target(&self, offset: u32, relative: i64) -> String {
+        u32::try_from(offset as i64 + relative)
+            .ok()
+            .and_then(|target| self.at(target))
+            .unwrap_or_else(|| relative.to_string())
+    }
+
+    /// Displays the instruction at `offset` with branch targets replaced by labels,
+    /// for example `goto L3` or `lookupswitch 1:L0 5:L1 default L2`
+    pub fn instruction<'a>(&'a self, offset: u32, instruction: &'a Instruction) -> Labelled<'a> {
+        Labelled {
+            labels: self,
+            offset,
+            instruction,
+        }
+    }
+}
+
+/// Instruction displayed with [`Labels`], created by [`Labels::instruction`]
+pub struct Labelled<'a> {
+    labels: &'a Labels,
+    offset: u32,
+    instruction: &'a Instruction,
+}
+
+impl Display for Labelled<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let target = |relative: i64| self.labels.target(self.offset, relative);
+        match self.instruction {
+            Instruction::TableSwitch {
+                default,
+                low,
+                high,
+                offsets,
+            } => {
+                write!(f, "tableswitch {low} {high}")?;
+                for offset in offsets {
+                    write!(f, " {}", target(*offset as i64))?;
+                }
+                write!(f, " default {}", target(*default as i64))
+            }
+            Instruction::LookupSwitch { default, pairs } => {
+                write!(f, "lookupswitch")?;
+                for (key, offset) in pairs {
+                    write!(f, " {key}:{}", target(*offset as i64))?;
+                }
+                write!(f, " default {}", target(*default as i64))
+            }
+            instruction => match instruction.branch_targets(self.offset).as_slice() {
+                [absolute] => write!(
+                    f,
+                    "{} {}",
+                    instruction.mnemonic(),
+                    target(absolute - self.offset as i64)
+                ),
+                _ => write!(f, "{instruction}"),
+            },
+        }
+    }
+}
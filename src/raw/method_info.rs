@@ -1,7 +1,6 @@
 use crate::raw::*;
-use std::fs::File;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct MethodInfo {
     pub access_flags: AccessFlags,
     pub name_index: u16,
@@ -10,12 +9,12 @@ pub struct MethodInfo {
 }
 
 impl MethodInfo {
-    pub fn from(file: &mut File) -> std::io::Result<MethodInfo> {
-        let access_flags = AccessFlags::from(file.read_u2()?);
-        let name_index = file.read_u2()?;
-        let descriptor_index = file.read_u2()?;
-        let attributes_count = file.read_u2()?;
-        let attributes = file.read_array(attributes_count.into(), Attribute::from)?;
+    pub fn from<F: ByteUtils>(file: &mut F) -> std::io::Result<MethodInfo> {
+        let access_flags = AccessFlags::from(file.field("access_flags").read_u2()?);
+        let name_index = file.field("name_index").read_u2()?;
+        let descriptor_index = file.field("descriptor_index").read_u2()?;
+        let attributes_count = file.field("attributes_count").read_u2()?;
+        let attributes = file.read_table("attributes", attributes_count.into(), Attribute::from)?;
 
         Ok(MethodInfo {
             access_flags,
@@ -24,4 +23,48 @@ impl MethodInfo {
             attributes,
         })
     }
+
+    pub fn write(
+        &self,
+        constant_pool: &[ConstantPoolInfo],
+        out: &mut Vec<u8>,
+    ) -> std::io::Result<()> {
+        out.put_u2(self.access_flags.bits());
+        out.put_u2(self.name_index);
+        out.put_u2(self.descriptor_index);
+        write_attributes(out, constant_pool, &self.attributes)
+    }
+
+    /// Code attribute of the method, `None` for abstract and native methods
+    pub fn code(&self) -> Option<MethodCode<'_>> {
+        self.attributes
+            .iter()
+            .find_map(|attribute| match &attribute.info {
+                AttributeInfo::Code {
+                    max_stack,
+                    max_locals,
+                    code,
+                    exception_table,
+                    attributes,
+                } => Some(MethodCode {
+                    max_stack: *max_stack,
+                    max_locals: *max_locals,
+                    code,
+                    exception_table,
+                    attributes,
+                }),
+                _ => None,
+            })
+    }
+}
+
+/// Borrowed content of a Code attribute
+#[derive(Debug, Clone, Copy)]
+pub struct MethodCode<'a> {
+    pub max_stack: u16,
+    pub max_locals: u16,
+    /// Instructions paired with their offset from the start of the code
+    pub code: &'a [(u32, Instruction)],
+    pub exception_table: &'a [Exception],
+    pub attributes: &'a [Attribute],
 }
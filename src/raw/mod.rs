@@ -3,7 +3,11 @@ pub mod attribute;
 pub mod class;
 pub mod code;
 pub mod constant_pool_info;
+pub mod descriptor;
+pub mod field_info;
+pub mod labels;
 pub mod method_info;
+pub mod trace;
 
 mod bytes_utils;
 
@@ -12,4 +16,7 @@ pub use crate::raw::attribute::*;
 pub use crate::raw::bytes_utils::*;
 pub use crate::raw::code::*;
 pub use crate::raw::constant_pool_info::*;
+pub use crate::raw::descriptor::*;
+pub use crate::raw::field_info::*;
+pub use crate::raw::labels::*;
 pub use crate::raw::method_info::*;
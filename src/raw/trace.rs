@@ -0,0 +1,95 @@
+use crate::raw::*;
+use std::io::Read;
+
+/// Byte range of the class file together with the structure it was parsed as
+#[derive(Debug, Clone)]
+pub struct Span {
+    pub offset: usize,
+    pub length: usize,
+    /// Path to the structure, like `constant_pool[12].Utf8.length`
+    pub label: String,
+}
+
+/// Reader that records which structure every byte was parsed as
+pub struct Tracer<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    /// Offset of `bytes` within the whole file
+    base: usize,
+    path: Vec<String>,
+    field: Option<&'static str>,
+    pub spans: Vec<Span>,
+}
+
+impl<'a> Tracer<'a> {
+    /// Traces reads from `bytes` located at `base` in the file, with labels starting with `path`
+    pub fn new(bytes: &'a [u8], base: usize, path: Vec<String>) -> Self {
+        Tracer {
+            bytes,
+            position: 0,
+            base,
+            path,
+            field: None,
+            spans: vec![],
+        }
+    }
+
+    /// Offset in the file of the next byte to read
+    pub fn offset(&self) -> usize {
+        self.base + self.position
+    }
+
+    /// Bytes that were not read yet
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.bytes[self.position..]
+    }
+}
+
+impl Read for Tracer<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let length = buf.len().min(self.bytes.len() - self.position);
+        if length == 0 {
+            return Ok(0);
+        }
+        buf[..length].copy_from_slice(&self.bytes[self.position..self.position + length]);
+
+        let mut label = self.path.join(".");
+        if let Some(field) = self.field.take() {
+            if !label.is_empty() {
+                label.push('.');
+            }
+            label.push_str(field);
+        }
+
+        let offset = self.offset();
+        match self.spans.last_mut() {
+            // Multi-byte structures like instructions are read piece by piece
+            Some(last) if last.label == label && last.offset + last.length == offset => {
+                last.length += length
+            }
+            _ => self.spans.push(Span {
+                offset,
+                length,
+                label,
+            }),
+        }
+
+        self.position += length;
+        Ok(length)
+    }
+}
+
+impl ByteUtils for Tracer<'_> {
+    fn enter(&mut self, name: impl FnOnce() -> String) {
+        self.path.push(name());
+    }
+
+    fn leave(&mut self) {
+        self.path.pop();
+    }
+
+    fn field(&mut self, name: &'static str) -> &mut Self {
+        self.field = Some(name);
+        self
+    }
+}
@@ -0,0 +1,521 @@
+//! Resolution of field and method references as the JVM does it (JVMS §5.4.3.2 to
+//! §5.4.3.4). A reference names the class it was compiled against, while the member may
+//! be declared by a superclass or a superinterface, or be a default method chosen among
+//! the maximally-specific superinterface methods. Access control (§5.4.4) is not checked.
+
+use crate::classpath::ClassPath;
+use crate::hierarchy::OBJECT;
+use crate::raw::class::Class;
+use crate::raw::*;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::io::ErrorKind;
+use std::rc::Rc;
+
+/// Reference that cannot be resolved, with the error the JVM throws for it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolutionError {
+    /// Internal name of the `LinkageError` subclass, like `java/lang/NoSuchMethodError`
+    pub error: &'static str,
+    pub message: String,
+}
+
+impl ResolutionError {
+    fn new(error: &'static str, message: impl Into<String>) -> ResolutionError {
+        ResolutionError {
+            error,
+            message: message.into(),
+        }
+    }
+
+    /// Simple name of the error class, like `NoSuchMethodError`
+    pub fn name(&self) -> &'static str {
+        self.error.rsplit('/').next().unwrap_or(self.error)
+    }
+}
+
+impl Display for ResolutionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.name(), self.message)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ResolutionError>;
+
+/// Field or method found by resolution, as its index in the declaring class
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub class: Rc<Class>,
+    pub index: usize,
+}
+
+impl Member {
+    /// Internal name of the declaring class
+    pub fn class_name(&self) -> &str {
+        self.class.name().unwrap_or("?")
+    }
+
+    /// Field declared under the index, if the member is a field
+    pub fn field(&self) -> &FieldInfo {
+        &self.class.fields[self.index]
+    }
+
+    /// Method declared under the index, if the member is a method
+    pub fn method(&self) -> &MethodInfo {
+        &self.class.methods[self.index]
+    }
+}
+
+/// Kind of constant pool reference, which decides the lookup
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reference {
+    Field,
+    Method,
+    InterfaceMethod,
+}
+
+/// Resolves references against the classes of a class path
+pub struct Resolver<'a> {
+    class_path: &'a ClassPath,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(class_path: &'a ClassPath) -> Resolver<'a> {
+        Resolver { class_path }
+    }
+
+    /// Resolves the Fieldref, Methodref or InterfaceMethodref under `index` of `class`
+    pub fn resolve(&self, class: &Class, index: u16) -> Result<Member> {
+        let pool = &class.constant_pool;
+        let reference = match constant(pool, index) {
+            Some(ConstantPoolInfo::FieldRef { .. }) => Reference::Field,
+            Some(ConstantPoolInfo::MethodRef { .. }) => Reference::Method,
+            Some(ConstantPoolInfo::InterfaceMethodRef { .. }) => Reference::InterfaceMethod,
+            _ => {
+                return Err(ResolutionError::new(
+                    "java/lang/ClassFormatError",
+                    format!("#{index} is not a field or method reference"),
+                ))
+            }
+        };
+        let (owner, name, descriptor) = member_ref_at(pool, index).ok_or_else(|| {
+            ResolutionError::new(
+                "java/lang/ClassFormatError",
+                format!("#{index} is not a valid reference"),
+            )
+        })?;
+        self.resolve_reference(reference, owner, name, descriptor)
+    }
+
+    pub fn resolve_reference(
+        &self,
+        reference: Reference,
+        owner: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<Member> {
+        match reference {
+            Reference::Field => self.resolve_field(owner, name, descriptor),
+            Reference::Method => self.resolve_method(owner, name, descriptor),
+            Reference::InterfaceMethod => self.resolve_interface_method(owner, name, descriptor),
+        }
+    }
+
+    /// Field resolution (§5.4.3.2): the class itself, then its superinterfaces,
+    /// then its superclass, each searched the same way
+    pub fn resolve_field(&self, owner: &str, name: &str, descriptor: &str) -> Result<Member> {
+        let class = self.class(owner)?;
+        self.field_lookup(&class, name, descriptor)?.ok_or_else(|| {
+            ResolutionError::new(
+                "java/lang/NoSuchFieldError",
+                format!("{owner}.{name} {descriptor}"),
+            )
+        })
+    }
+
+    /// Method resolution (§5.4.3.3): the class and its superclasses, then the
+    /// maximally-specific superinterface methods, then any superinterface method
+    pub fn resolve_method(&self, owner: &str, name: &str, descriptor: &str) -> Result<Member> {
+        // Array types have the methods of Object, with clone made public
+        let owner = if owner.starts_with('[') {
+            OBJECT
+        } else {
+            owner
+        };
+        let class = self.class(owner)?;
+        if class.access_info.contains(AccessFlags::Interface) {
+            return Err(ResolutionError::new(
+                "java/lang/IncompatibleClassChangeError",
+                format!("found interface {owner}, but class was expected"),
+            ));
+        }
+
+        let mut current = Some(class.clone());
+        while let Some(class) = current {
+            if let Some(index) = signature_polymorphic(&class, name) {
+                return Ok(Member { class, index });
+            }
+            if let Some(index) = declared_method(&class, name, descriptor) {
+                return Ok(Member { class, index });
+            }
+            current = self.super_class(&class)?;
+        }
+
+        self.superinterface_method(&class, name, descriptor)?
+            .ok_or_else(|| no_such_method(owner, name, descriptor))
+    }
+
+    /// Interface method resolution (§5.4.3.4): the interface itself, then public instance
+    /// methods of `java/lang/Object`, then superinterface methods like for classes
+    pub fn resolve_interface_method(
+        &self,
+        owner: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<Member> {
+        let class = self.class(owner)?;
+        if !class.access_info.contains(AccessFlags::Interface) {
+            return Err(ResolutionError::new(
+                "java/lang/IncompatibleClassChangeError",
+                format!("found class {owner}, but interface was expected"),
+            ));
+        }
+        if let Some(index) = declared_method(&class, name, descriptor) {
+            return Ok(Member { class, index });
+        }
+
+        let object = self.class(OBJECT)?;
+        if let Some(index) = declared_method(&object, name, descriptor) {
+            let flags = object.methods[index].access_flags;
+            if flags.contains(AccessFlags::Public) && !flags.contains(AccessFlags::Static) {
+                return Ok(Member {
+                    class: object,
+                    index,
+                });
+            }
+        }
+
+        self.superinterface_method(&class, name, descriptor)?
+            .ok_or_else(|| no_such_method(owner, name, descriptor))
+    }
+
+    /// Method selection (§5.4.6) of an instance method for a receiver of class `receiver`:
+    /// the first declaration in the class or its superclasses that is neither static nor
+    /// private, otherwise the only maximally-specific superinterface method that is not
+    /// abstract. `None` means the invocation throws `AbstractMethodError`, or
+    /// `IncompatibleClassChangeError` when several default methods conflict.
+    /// Package-private overriding is not distinguished.
+    pub fn select(&self, receiver: &str, name: &str, descriptor: &str) -> Result<Option<Member>> {
+        // Arrays inherit the methods of Object
+        let receiver = if receiver.starts_with('[') {
+            OBJECT
+        } else {
+            receiver
+        };
+        let class = self.class(receiver)?;
+        let mut current = Some(class.clone());
+        while let Some(class) = current {
+            if let Some(index) = declared_method(&class, name, descriptor) {
+                let flags = class.methods[index].access_flags;
+                // Static and private methods do not override
+                if !flags.contains(AccessFlags::Static) && !flags.contains(AccessFlags::Private) {
+                    return Ok(
+                        (!flags.contains(AccessFlags::Abstract)).then_some(Member { class, index })
+                    );
+                }
+            }
+            current = self.super_class(&class)?;
+        }
+        let maximally_specific = self.maximally_specific(&class, name, descriptor)?;
+        let mut concrete = maximally_specific
+            .into_iter()
+            .filter(|member| !member.method().access_flags.contains(AccessFlags::Abstract));
+        Ok(match (concrete.next(), concrete.next()) {
+            (Some(member), None) => Some(member),
+            _ => None,
+        })
+    }
+
+    /// Maximally-specific superinterface methods of `class` (§5.4.3.3): methods declared by
+    /// its superinterfaces, direct or not, that are neither private nor static and are not
+    /// overridden by such a method of a subinterface that is a superinterface of `class` too
+    pub fn maximally_specific(
+        &self,
+        class: &Class,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<Vec<Member>> {
+        let candidates = self.interface_methods(class, name, descriptor)?;
+        let mut result = vec![];
+        for candidate in &candidates {
+            let mut overridden = false;
+            for other in &candidates {
+                if Rc::ptr_eq(&other.class, &candidate.class) {
+                    continue;
+                }
+                let superinterfaces = self.superinterfaces(&other.class)?;
+                if superinterfaces
+                    .iter()
+                    .any(|interface| interface.name() == candidate.class.name())
+                {
+                    overridden = true;
+                    break;
+                }
+            }
+            if !overridden {
+                result.push(candidate.clone());
+            }
+        }
+        Ok(result)
+    }
+
+    /// All superinterfaces of `class`, of itself and of its superclasses, each once,
+    /// in the order they are declared with the nearest first
+    pub fn superinterfaces(&self, class: &Class) -> Result<Vec<Rc<Class>>> {
+        let mut superclasses = vec![];
+        let mut current = self.super_class(class)?;
+        while let Some(superclass) = current {
+            current = self.super_class(&superclass)?;
+            superclasses.push(superclass);
+        }
+
+        let mut seen = HashSet::new();
+        let mut result: Vec<Rc<Class>> = vec![];
+        for class in std::iter::once(class).chain(superclasses.iter().map(|class| &**class)) {
+            // Breadth first over the interfaces of this class
+            let mut pending: Vec<String> = class.interface_names().map(str::to_string).collect();
+            let mut next = 0;
+            while let Some(name) = pending.get(next).cloned() {
+                next += 1;
+                if !seen.insert(name.clone()) {
+                    continue;
+                }
+                let interface = self.class(&name)?;
+                pending.extend(interface.interface_names().map(str::to_string));
+                result.push(interface);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Step of method resolution shared with interface method resolution: the only
+    /// maximally-specific method that is not abstract, or otherwise any candidate
+    fn superinterface_method(
+        &self,
+        class: &Class,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<Option<Member>> {
+        let maximally_specific = self.maximally_specific(class, name, descriptor)?;
+        let concrete: Vec<&Member> = maximally_specific
+            .iter()
+            .filter(|member| !member.method().access_flags.contains(AccessFlags::Abstract))
+            .collect();
+        if let [member] = concrete.as_slice() {
+            return Ok(Some((*member).clone()));
+        }
+        // The JVMS lets the JVM choose any of them, the first is as good as another
+        Ok(self
+            .interface_methods(class, name, descriptor)?
+            .into_iter()
+            .next())
+    }
+
+    /// Methods of all superinterfaces with the name and descriptor that are neither private
+    /// nor static
+    fn interface_methods(
+        &self,
+        class: &Class,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<Vec<Member>> {
+        Ok(self
+            .superinterfaces(class)?
+            .into_iter()
+            .filter_map(|interface| {
+                let index = declared_method(&interface, name, descriptor)?;
+                let flags = interface.methods[index].access_flags;
+                let excluded = flags.intersects(AccessFlags::Private | AccessFlags::Static);
+                (!excluded).then_some(Member {
+                    class: interface,
+                    index,
+                })
+            })
+            .collect())
+    }
+
+    fn field_lookup(
+        &self,
+        class: &Rc<Class>,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<Option<Member>> {
+        let pool = &class.constant_pool;
+        let declared = class.fields.iter().position(|field| {
+            utf8_at(pool, field.name_index) == Some(name)
+                && utf8_at(pool, field.descriptor_index) == Some(descriptor)
+        });
+        if let Some(index) = declared {
+            return Ok(Some(Member {
+                class: class.clone(),
+                index,
+            }));
+        }
+        for interface in class.interface_names() {
+            let interface = self.class(interface)?;
+            if let Some(member) = self.field_lookup(&interface, name, descriptor)? {
+                return Ok(Some(member));
+            }
+        }
+        match self.super_class(class)? {
+            Some(super_class) => self.field_lookup(&super_class, name, descriptor),
+            None => Ok(None),
+        }
+    }
+
+    fn class(&self, name: &str) -> Result<Rc<Class>> {
+        self.class_path
+            .class(name)
+            .map_err(|error| match error.kind() {
+                ErrorKind::NotFound => ResolutionError::new("java/lang/NoClassDefFoundError", name),
+                _ => ResolutionError::new("java/lang/ClassFormatError", error.to_string()),
+            })
+    }
+
+    fn super_class(&self, class: &Class) -> Result<Option<Rc<Class>>> {
+        class.super_name().map(|name| self.class(name)).transpose()
+    }
+}
+
+fn no_such_method(owner: &str, name: &str, descriptor: &str) -> ResolutionError {
+    ResolutionError::new(
+        "java/lang/NoSuchMethodError",
+        format!("{owner}.{name}{descriptor}"),
+    )
+}
+
+/// Index of the method of `class` with the name and descriptor
+fn declared_method(class: &Class, name: &str, descriptor: &str) -> Option<usize> {
+    class.method(name, descriptor).map(|(index, _)| index)
+}
+
+/// Index of the signature polymorphic method `name` (JVMS §2.9.3) of `MethodHandle` or
+/// `VarHandle`, which matches references of any descriptor
+fn signature_polymorphic(class: &Class, name: &str) -> Option<usize> {
+    if !matches!(
+        class.name(),
+        Some("java/lang/invoke/MethodHandle" | "java/lang/invoke/VarHandle")
+    ) {
+        return None;
+    }
+    let pool = &class.constant_pool;
+    let mut named = class
+        .methods
+        .iter()
+        .enumerate()
+        .filter(|(_, method)| utf8_at(pool, method.name_index) == Some(name));
+    let (index, method) = named.next()?;
+    let polymorphic = named.next().is_none()
+        && method
+            .access_flags
+            .contains(AccessFlags::Varargs | AccessFlags::Native)
+        && utf8_at(pool, method.descriptor_index)
+            .is_some_and(|descriptor| descriptor.starts_with("([Ljava/lang/Object;)"));
+    polymorphic.then_some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembly::assemble;
+
+    /// Class path of the classes, each given as its header and the flags of its methods
+    /// `m()V`, followed by `code` for those that have a body
+    fn class_path(classes: &[(&str, &[&str])]) -> ClassPath {
+        let class_path = ClassPath::new();
+        let object = ".version 52 0\n.class public super java/lang/Object\n.super #0\n";
+        class_path.define(Rc::new(assemble(object).unwrap()));
+        for (header, methods) in classes {
+            let mut listing = format!(".version 52 0\n{header}\n");
+            for flags in *methods {
+                match flags.strip_suffix(" code") {
+                    Some(flags) => listing.push_str(&format!(
+                        ".method {flags} m()V\n.code stack 0 locals 1\nreturn\n.end code\n.end method\n"
+                    )),
+                    None => listing.push_str(&format!(".method {flags} m()V\n.end method\n")),
+                }
+            }
+            class_path.define(Rc::new(assemble(&listing).unwrap()));
+        }
+        class_path
+    }
+
+    /// Declaring class of the method `m()V` selected for a receiver of class `C`
+    fn selected(class_path: &ClassPath) -> Option<String> {
+        let selected = Resolver::new(class_path).select("C", "m", "()V").unwrap();
+        selected.map(|member| member.class_name().to_string())
+    }
+
+    #[test]
+    fn more_specific_default_wins() {
+        let class_path = class_path(&[
+            (".class public abstract interface I", &["public code"]),
+            (
+                ".class public abstract interface J\n.implements I",
+                &["public code"],
+            ),
+            (".class public super C\n.implements I\n.implements J", &[]),
+        ]);
+        assert_eq!(selected(&class_path).as_deref(), Some("J"));
+        let resolved = Resolver::new(&class_path).resolve_method("C", "m", "()V");
+        assert_eq!(resolved.unwrap().class_name(), "J");
+    }
+
+    #[test]
+    fn abstract_redeclaration_hides_default() {
+        let by_interface = class_path(&[
+            (".class public abstract interface I", &["public code"]),
+            (
+                ".class public abstract interface J\n.implements I",
+                &["public abstract"],
+            ),
+            (".class public super C\n.implements J", &[]),
+        ]);
+        assert_eq!(selected(&by_interface), None);
+
+        let by_superclass = class_path(&[
+            (".class public abstract interface I", &["public code"]),
+            (
+                ".class public abstract super A\n.implements I",
+                &["public abstract"],
+            ),
+            (".class public super C\n.super A", &[]),
+        ]);
+        assert_eq!(selected(&by_superclass), None);
+    }
+
+    #[test]
+    fn conflicting_defaults() {
+        let class_path = class_path(&[
+            (".class public abstract interface I", &["public code"]),
+            (".class public abstract interface K", &["public code"]),
+            (".class public super C\n.implements I\n.implements K", &[]),
+        ]);
+        assert_eq!(selected(&class_path), None);
+        // Resolution succeeds, the invocation fails when it selects the method
+        assert!(Resolver::new(&class_path)
+            .resolve_method("C", "m", "()V")
+            .is_ok());
+    }
+
+    #[test]
+    fn private_method_does_not_override() {
+        let class_path = class_path(&[
+            (".class public super A", &["public code"]),
+            (".class public super C\n.super A", &["private code"]),
+        ]);
+        assert_eq!(selected(&class_path).as_deref(), Some("A"));
+        let resolved = Resolver::new(&class_path).resolve_method("C", "m", "()V");
+        assert_eq!(resolved.unwrap().class_name(), "C");
+    }
+}
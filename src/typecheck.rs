@@ -0,0 +1,1622 @@
+//! Type checking of method bodies like the verifier of the JVM does it (JVMS §4.10). The
+//! operand stack and local variables are simulated with verification types along every
+//! path through the code. Where the StackMapTable declares a frame, the inferred types must
+//! be assignable to it and execution continues from the declared frame. Elsewhere, paths
+//! joining at an instruction are merged, which is the type inference of class files older
+//! than version 50 that have no stack maps.
+//!
+//! Whether one class is assignable to another is told by loading superclasses from a
+//! [`ClassPath`]. Classes that cannot be loaded are assumed to be assignable, so that
+//! a class is checked without its dependencies by its own code only. Interfaces accept
+//! any reference, as for the JVM verifier. Subroutines (`jsr` and `ret`) continue after
+//! the `jsr` with the locals from before the call.
+
+use crate::classpath::ClassPath;
+use crate::hierarchy::OBJECT;
+use crate::interpreter::exception::THROWABLE;
+use crate::raw::class::Class;
+use crate::raw::*;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+
+/// Verification type of a value on the operand stack or in a local variable
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    /// Unusable value, like the second slot of a long or a variable never assigned
+    Top,
+    /// Also boolean, byte, char and short
+    Int,
+    Float,
+    Long,
+    Double,
+    Null,
+    /// `this` in a constructor before the superclass constructor is called
+    UninitializedThis,
+    /// Object created by the `new` instruction at the offset, before its constructor is called
+    Uninitialized(u32),
+    /// Internal name of a class or descriptor of an array type, like `[I`
+    Reference(String),
+    /// Address pushed by `jsr`
+    ReturnAddress,
+}
+
+impl Type {
+    pub fn from_field_type(field_type: &FieldType) -> Type {
+        match field_type {
+            FieldType::Boolean
+            | FieldType::Byte
+            | FieldType::Char
+            | FieldType::Short
+            | FieldType::Int => Type::Int,
+            FieldType::Float => Type::Float,
+            FieldType::Long => Type::Long,
+            FieldType::Double => Type::Double,
+            FieldType::Object(name) => Type::Reference(name.clone()),
+            FieldType::Array(_) => Type::Reference(field_type.to_string()),
+        }
+    }
+
+    /// Slots taken in local variables and on the operand stack
+    pub fn size(&self) -> usize {
+        match self {
+            Type::Long | Type::Double => 2,
+            _ => 1,
+        }
+    }
+
+    fn is_initialized_reference(&self) -> bool {
+        matches!(self, Type::Null | Type::Reference(_))
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Top => write!(f, "top"),
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::Long => write!(f, "long"),
+            Type::Double => write!(f, "double"),
+            Type::Null => write!(f, "null"),
+            Type::UninitializedThis => write!(f, "uninitializedThis"),
+            Type::Uninitialized(offset) => write!(f, "uninitialized({offset})"),
+            Type::Reference(name) => write!(f, "{name}"),
+            Type::ReturnAddress => write!(f, "returnAddress"),
+        }
+    }
+}
+
+/// Types before an instruction. Long and double values take one entry of `stack`,
+/// but two of `locals`, where the second one is `Top`.
+#[derive(Debug, Clone, PartialEq)]
+struct Frame {
+    locals: Vec<Type>,
+    stack: Vec<Type>,
+    /// In a constructor, whether the superclass or another constructor has not been called yet
+    this_uninitialized: bool,
+}
+
+impl Frame {
+    fn depth(&self) -> usize {
+        self.stack.iter().map(Type::size).sum()
+    }
+}
+
+/// Type checks the code of every method with a body, returning the problems as
+/// `method name descriptor: instruction offset: message`, sorted by method and offset
+pub fn check(class: &Class, class_path: Option<&ClassPath>) -> Vec<String> {
+    let classes = Classes {
+        class,
+        class_path,
+        cache: RefCell::new(HashMap::new()),
+    };
+    class
+        .methods
+        .iter()
+        .flat_map(|method| check_method(&classes, method))
+        .collect()
+}
+
+fn check_method(classes: &Classes, method: &MethodInfo) -> Vec<String> {
+    let class = classes.class;
+    let pool = &class.constant_pool;
+    let name = utf8_at(pool, method.name_index).unwrap_or("?");
+    let descriptor = utf8_at(pool, method.descriptor_index).unwrap_or("?");
+    let (Some(code), Some(parsed)) = (method.code(), MethodDescriptor::parse(descriptor)) else {
+        return vec![];
+    };
+    if code.code.is_empty() {
+        return vec![];
+    }
+    let mut checker = Checker {
+        classes,
+        pool,
+        descriptor: parsed,
+        is_constructor: name == "<init>",
+        code,
+        stack_maps: class.major >= 51,
+        problems: BTreeSet::new(),
+    };
+    checker.run(method.access_flags.contains(AccessFlags::Static));
+    checker
+        .problems
+        .into_iter()
+        .map(|(offset, message)| match offset {
+            Some(offset) => format!("method {name}{descriptor}: instruction {offset}: {message}"),
+            None => format!("method {name}{descriptor}: {message}"),
+        })
+        .collect()
+}
+
+/// Superclass of a class and whether it is an interface
+type ClassInfo = (Option<String>, bool);
+
+/// Superclasses of the classes referenced by the code, loaded on demand
+struct Classes<'a> {
+    class: &'a Class,
+    class_path: Option<&'a ClassPath>,
+    /// `None` for classes that cannot be loaded
+    cache: RefCell<HashMap<String, Option<ClassInfo>>>,
+}
+
+impl Classes<'_> {
+    fn info(&self, name: &str) -> Option<ClassInfo> {
+        let describe = |class: &Class| {
+            let super_name = class.super_name().map(str::to_string);
+            (
+                super_name,
+                class.access_info.contains(AccessFlags::Interface),
+            )
+        };
+        if self.class.name() == Some(name) {
+            return Some(describe(self.class));
+        }
+        if let Some(info) = self.cache.borrow().get(name) {
+            return info.clone();
+        }
+        let info = self
+            .class_path
+            .and_then(|class_path| class_path.class(name).ok())
+            .map(|class| describe(&class));
+        self.cache
+            .borrow_mut()
+            .insert(name.to_string(), info.clone());
+        info
+    }
+
+    /// `name` and its superclasses, and whether the chain reaches `java/lang/Object`
+    fn superclass_chain(&self, name: &str) -> (Vec<String>, bool) {
+        let mut chain = vec![name.to_string()];
+        loop {
+            let current = chain.last().expect("chain starts with the class");
+            if current == OBJECT {
+                return (chain, true);
+            }
+            match self.info(current) {
+                Some((Some(super_name), _)) if !chain.contains(&super_name) => {
+                    chain.push(super_name)
+                }
+                _ => return (chain, false),
+            }
+        }
+    }
+
+    fn is_assignable(&self, from: &Type, to: &Type) -> bool {
+        match (from, to) {
+            _ if from == to => true,
+            (_, Type::Top) => true,
+            (Type::Null, Type::Reference(_)) => true,
+            (Type::Reference(from), Type::Reference(to)) => self.is_reference_assignable(from, to),
+            _ => false,
+        }
+    }
+
+    fn is_reference_assignable(&self, from: &str, to: &str) -> bool {
+        if from == to || to == OBJECT {
+            return true;
+        }
+        match (from.strip_prefix('['), to.strip_prefix('[')) {
+            (Some(from), Some(to)) => match (reference_component(from), reference_component(to)) {
+                (Some(from), Some(to)) => self.is_reference_assignable(from, to),
+                _ => from == to,
+            },
+            (Some(_), None) => matches!(to, "java/lang/Cloneable" | "java/io/Serializable"),
+            (None, Some(_)) => false,
+            (None, None) => match self.info(to) {
+                // Unknown classes are given the benefit of the doubt
+                None | Some((_, true)) => true,
+                Some((_, false)) => {
+                    let (chain, complete) = self.superclass_chain(from);
+                    chain.iter().any(|name| name == to) || !complete
+                }
+            },
+        }
+    }
+
+    /// Type of values coming from both `a` and `b`, `Top` if they have nothing in common
+    fn merge(&self, a: &Type, b: &Type) -> Type {
+        match (a, b) {
+            _ if a == b => a.clone(),
+            (Type::Null, Type::Reference(_)) => b.clone(),
+            (Type::Reference(_), Type::Null) => a.clone(),
+            (Type::Reference(a), Type::Reference(b)) => {
+                Type::Reference(self.common_superclass(a, b))
+            }
+            _ => Type::Top,
+        }
+    }
+
+    fn common_superclass(&self, a: &str, b: &str) -> String {
+        if a == b {
+            return a.to_string();
+        }
+        match (a.strip_prefix('['), b.strip_prefix('[')) {
+            (Some(a), Some(b)) => match (reference_component(a), reference_component(b)) {
+                (Some(a), Some(b)) => {
+                    let common = self.common_superclass(a, b);
+                    match common.starts_with('[') {
+                        true => format!("[{common}"),
+                        false => format!("[L{common};"),
+                    }
+                }
+                _ => OBJECT.to_string(),
+            },
+            (None, None) => {
+                let is_interface = |name: &str| !matches!(self.info(name), Some((_, false)));
+                if is_interface(a) || is_interface(b) {
+                    return OBJECT.to_string();
+                }
+                let (chain_a, _) = self.superclass_chain(a);
+                let (chain_b, _) = self.superclass_chain(b);
+                chain_a
+                    .into_iter()
+                    .find(|name| chain_b.contains(name))
+                    .unwrap_or_else(|| OBJECT.to_string())
+            }
+            _ => OBJECT.to_string(),
+        }
+    }
+}
+
+/// Class name of an array component like `Ljava/lang/String;` or `[I`, `None` for primitives
+fn reference_component(component: &str) -> Option<&str> {
+    match component.strip_prefix('L') {
+        Some(name) => name.strip_suffix(';'),
+        None => component.starts_with('[').then_some(component),
+    }
+}
+
+/// Result of an instruction, the message says what is wrong
+type Step<T = ()> = Result<T, String>;
+
+struct Checker<'a> {
+    classes: &'a Classes<'a>,
+    pool: &'a [ConstantPoolInfo],
+    descriptor: MethodDescriptor,
+    is_constructor: bool,
+    code: MethodCode<'a>,
+    /// Version 51 and later requires frames at branch targets and handlers
+    stack_maps: bool,
+    /// Offset, if the problem is at an instruction, and the message
+    problems: BTreeSet<(Option<u32>, String)>,
+}
+
+impl Checker<'_> {
+    fn problem(&mut self, offset: Option<u32>, message: String) {
+        self.problems.insert((offset, message));
+    }
+
+    fn run(&mut self, is_static: bool) {
+        let Some(initial) = self.initial_frame(is_static) else {
+            return;
+        };
+        let declared = self.declared_frames(&initial);
+        let code = self.code.code;
+        let index_of = |offset: i64| {
+            code.binary_search_by_key(&offset, |(offset, _)| i64::from(*offset))
+                .ok()
+        };
+
+        let mut states: Vec<Option<Frame>> = vec![None; code.len()];
+        let mut pending = BTreeSet::new();
+        // Instructions that failed to check, the code after them is not looked at
+        let mut failed = vec![false; code.len()];
+        self.flow(&mut states, &declared, &mut pending, 0, initial, None);
+        // Instructions with a declared frame are checked even when they cannot be reached
+        for (index, frame) in declared.iter().enumerate() {
+            if let (Some(frame), None) = (frame, &states[index]) {
+                states[index] = Some(frame.clone());
+                pending.insert(index);
+            }
+        }
+
+        while let Some(index) = pending.pop_first() {
+            let (offset, instruction) = &code[index];
+            let frame = states[index]
+                .clone()
+                .expect("pending instructions have a state");
+
+            for exception in self.code.exception_table {
+                let range = u32::from(exception.start_pc)..u32::from(exception.end_pc);
+                if !range.contains(offset) {
+                    continue;
+                }
+                let Some(handler) = index_of(exception.handler_pc.into()) else {
+                    continue;
+                };
+                let caught = match exception.catch_type {
+                    0 => THROWABLE,
+                    index => class_name_at(self.pool, index).unwrap_or(THROWABLE),
+                };
+                let caught = Type::Reference(caught.to_string());
+                if !self
+                    .classes
+                    .is_assignable(&caught, &Type::Reference(THROWABLE.to_string()))
+                {
+                    let message = format!("exception handler catches {caught}, not a Throwable");
+                    self.problem(Some(exception.handler_pc.into()), message);
+                }
+                let handler_frame = Frame {
+                    locals: frame.locals.clone(),
+                    stack: vec![caught],
+                    this_uninitialized: frame.this_uninitialized,
+                };
+                self.flow(
+                    &mut states,
+                    &declared,
+                    &mut pending,
+                    handler,
+                    handler_frame,
+                    Some(*offset),
+                );
+            }
+
+            let mut next = frame;
+            if let Err(message) = self.execute(&mut next, *offset, instruction) {
+                self.problem(Some(*offset), message);
+                failed[index] = true;
+                continue;
+            }
+
+            for target in instruction.branch_targets(*offset) {
+                match index_of(target) {
+                    // Continues after the jsr, see the module documentation
+                    Some(_)
+                        if matches!(instruction, Instruction::Jsr(_) | Instruction::JsrW(_)) => {}
+                    Some(target) => self.flow(
+                        &mut states,
+                        &declared,
+                        &mut pending,
+                        target,
+                        next.clone(),
+                        Some(*offset),
+                    ),
+                    None => self.problem(
+                        Some(*offset),
+                        format!("branch target {target} is not an instruction"),
+                    ),
+                }
+            }
+            if matches!(instruction, Instruction::Jsr(_) | Instruction::JsrW(_)) {
+                next.stack.pop();
+            } else if !falls_through(instruction) {
+                continue;
+            }
+            match index + 1 < code.len() {
+                true => self.flow(&mut states, &declared, &mut pending, index + 1, next, None),
+                false => self.problem(
+                    Some(*offset),
+                    "execution falls off the end of the code".to_string(),
+                ),
+            }
+        }
+
+        if self.stack_maps {
+            // Code that cannot be reached is only checked from a declared frame
+            for index in 0..code.len() {
+                let previous_reached =
+                    index == 0 || (states[index - 1].is_some() && !failed[index - 1]);
+                if states[index].is_none() && previous_reached {
+                    let message = "unreachable code has no stack map frame".to_string();
+                    self.problem(Some(code[index].0), message);
+                }
+            }
+        }
+    }
+
+    /// Continues at the instruction under `index` with `frame`, coming from the instruction
+    /// at `branch` for jumps and exception handlers, or falling through
+    fn flow(
+        &mut self,
+        states: &mut [Option<Frame>],
+        declared: &[Option<Frame>],
+        pending: &mut BTreeSet<usize>,
+        index: usize,
+        frame: Frame,
+        branch: Option<u32>,
+    ) {
+        let offset = self.code.code[index].0;
+        if let Some(expected) = &declared[index] {
+            if let Err(message) = self.frame_assignable(&frame, expected) {
+                let at = branch.unwrap_or(offset);
+                self.problem(
+                    Some(at),
+                    format!("stack map frame at {offset} does not match: {message}"),
+                );
+            }
+            if states[index].is_none() {
+                states[index] = Some(expected.clone());
+                pending.insert(index);
+            }
+            return;
+        }
+        if let (Some(branch), true) = (branch, self.stack_maps) {
+            self.problem(Some(branch), format!("missing stack map frame at {offset}"));
+        }
+        let merged = match &states[index] {
+            None => frame,
+            Some(old) => {
+                if old.stack.len() != frame.stack.len() || old.depth() != frame.depth() {
+                    let message = format!(
+                        "inconsistent stack height at {offset}: {} and {}",
+                        old.depth(),
+                        frame.depth()
+                    );
+                    self.problem(Some(branch.unwrap_or(offset)), message);
+                    return;
+                }
+                let mut stack = vec![];
+                for (a, b) in old.stack.iter().zip(&frame.stack) {
+                    let merged = self.classes.merge(a, b);
+                    if merged == Type::Top {
+                        let message = format!("inconsistent stack at {offset}: {a} and {b}");
+                        self.problem(Some(branch.unwrap_or(offset)), message);
+                        return;
+                    }
+                    stack.push(merged);
+                }
+                Frame {
+                    locals: old
+                        .locals
+                        .iter()
+                        .zip(&frame.locals)
+                        .map(|(a, b)| self.classes.merge(a, b))
+                        .collect(),
+                    stack,
+                    this_uninitialized: old.this_uninitialized || frame.this_uninitialized,
+                }
+            }
+        };
+        if states[index].as_ref() != Some(&merged) {
+            states[index] = Some(merged);
+            pending.insert(index);
+        }
+    }
+
+    fn frame_assignable(&self, frame: &Frame, expected: &Frame) -> Step {
+        for (i, (actual, expected)) in frame.locals.iter().zip(&expected.locals).enumerate() {
+            if !self.classes.is_assignable(actual, expected) {
+                return Err(format!("local {i} is {actual}, frame has {expected}"));
+            }
+        }
+        if frame.stack.len() != expected.stack.len() {
+            return Err(format!(
+                "stack has {} values, frame has {}",
+                frame.stack.len(),
+                expected.stack.len()
+            ));
+        }
+        for (i, (actual, expected)) in frame.stack.iter().zip(&expected.stack).enumerate() {
+            if !self.classes.is_assignable(actual, expected) {
+                return Err(format!("stack entry {i} is {actual}, frame has {expected}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Locals holding `this` and the parameters, `None` if they do not fit `max_locals`
+    fn initial_frame(&mut self, is_static: bool) -> Option<Frame> {
+        let mut locals = vec![];
+        let class_name = self.classes.class.name().unwrap_or(OBJECT);
+        let this_uninitialized = self.is_constructor && class_name != OBJECT && !is_static;
+        if !is_static {
+            locals.push(match this_uninitialized {
+                true => Type::UninitializedThis,
+                false => Type::Reference(class_name.to_string()),
+            });
+        }
+        for parameter in &self.descriptor.parameters {
+            push_local(&mut locals, Type::from_field_type(parameter));
+        }
+        let max_locals = usize::from(self.code.max_locals);
+        if locals.len() > max_locals {
+            let message = format!(
+                "parameters take {} local variables, max_locals is {max_locals}",
+                locals.len()
+            );
+            self.problem(None, message);
+            return None;
+        }
+        locals.resize(max_locals, Type::Top);
+        Some(Frame {
+            locals,
+            stack: vec![],
+            this_uninitialized,
+        })
+    }
+
+    /// Frames of the StackMapTable by instruction index
+    fn declared_frames(&mut self, initial: &Frame) -> Vec<Option<Frame>> {
+        let code = self.code.code;
+        let mut declared = vec![None; code.len()];
+        let Some(frames) =
+            self.code
+                .attributes
+                .iter()
+                .find_map(|attribute| match &attribute.info {
+                    AttributeInfo::StackMapTable(frames) => Some(frames),
+                    _ => None,
+                })
+        else {
+            return declared;
+        };
+
+        // Locals as listed by frames, with long and double once
+        let mut locals: Vec<Type> = vec![];
+        let mut slots = initial.locals.iter();
+        while let Some(local) = slots.next() {
+            if local.size() == 2 {
+                slots.next();
+            }
+            locals.push(local.clone());
+        }
+        while locals.last() == Some(&Type::Top) {
+            locals.pop();
+        }
+
+        let mut offset: Option<u32> = None;
+        for frame in frames {
+            let at = match offset {
+                None => u32::from(frame.offset_delta),
+                Some(previous) => previous + u32::from(frame.offset_delta) + 1,
+            };
+            offset = Some(at);
+            let mut convert = |types: &[VerificationType]| -> Option<Vec<Type>> {
+                types
+                    .iter()
+                    .map(|t| self.verification_type(at, t))
+                    .collect()
+            };
+            let (Some(frame_locals), Some(stack)) = (convert(&frame.locals), convert(&frame.stack))
+            else {
+                continue;
+            };
+            match frame.frame_type {
+                0..=127 | 247 | 251 => {}
+                248..=250 => {
+                    let chopped = usize::from(251 - frame.frame_type);
+                    if chopped > locals.len() {
+                        let message = format!(
+                            "stack map frame at {at} chops {chopped} locals of {}",
+                            locals.len()
+                        );
+                        self.problem(None, message);
+                        return declared;
+                    }
+                    locals.truncate(locals.len() - chopped);
+                }
+                252..=254 => locals.extend(frame_locals),
+                _ => locals = frame_locals,
+            }
+            let mut expanded = vec![];
+            for local in &locals {
+                push_local(&mut expanded, local.clone());
+            }
+            let max_locals = usize::from(self.code.max_locals);
+            if expanded.len() > max_locals {
+                let message = format!(
+                    "stack map frame at {at} has {} locals, max_locals is {max_locals}",
+                    expanded.len()
+                );
+                self.problem(None, message);
+                continue;
+            }
+            expanded.resize(max_locals, Type::Top);
+            let this_uninitialized = expanded.contains(&Type::UninitializedThis)
+                || stack.contains(&Type::UninitializedThis);
+            let frame = Frame {
+                locals: expanded,
+                stack,
+                this_uninitialized,
+            };
+            if frame.depth() > usize::from(self.code.max_stack) {
+                let message = format!(
+                    "stack map frame at {at} exceeds max_stack {}",
+                    self.code.max_stack
+                );
+                self.problem(None, message);
+            }
+            match code.binary_search_by_key(&at, |(offset, _)| *offset) {
+                Ok(index) => declared[index] = Some(frame),
+                Err(_) => self.problem(
+                    None,
+                    format!("stack map frame at {at} is not at an instruction"),
+                ),
+            }
+        }
+        declared
+    }
+
+    fn verification_type(&mut self, at: u32, verification_type: &VerificationType) -> Option<Type> {
+        Some(match verification_type {
+            VerificationType::Top => Type::Top,
+            VerificationType::Integer => Type::Int,
+            VerificationType::Float => Type::Float,
+            VerificationType::Double => Type::Double,
+            VerificationType::Long => Type::Long,
+            VerificationType::Null => Type::Null,
+            VerificationType::UninitializedThis => Type::UninitializedThis,
+            VerificationType::Object(index) => match class_name_at(self.pool, *index) {
+                Some(name) => Type::Reference(name.to_string()),
+                None => {
+                    let message =
+                        format!("stack map frame at {at} refers to #{index}, which is not a class");
+                    self.problem(None, message);
+                    return None;
+                }
+            },
+            VerificationType::Uninitialized(offset) => {
+                let created = self
+                    .code
+                    .code
+                    .binary_search_by_key(&u32::from(*offset), |(offset, _)| *offset)
+                    .is_ok_and(|index| matches!(self.code.code[index].1, Instruction::New(_)));
+                if !created {
+                    let message = format!("stack map frame at {at} has uninitialized({offset}), which is not a new instruction");
+                    self.problem(None, message);
+                    return None;
+                }
+                Type::Uninitialized(u32::from(*offset))
+            }
+        })
+    }
+
+    /// Applies the instruction at `offset` to the frame
+    fn execute(&self, frame: &mut Frame, offset: u32, instruction: &Instruction) -> Step {
+        use Instruction as I;
+        let mut state = State {
+            checker: self,
+            frame,
+        };
+        let s = &mut state;
+        match instruction {
+            I::Nop => {}
+            I::AConstNull => s.push(Type::Null)?,
+            I::IConstM1
+            | I::IConst0
+            | I::IConst1
+            | I::IConst2
+            | I::IConst3
+            | I::IConst4
+            | I::IConst5
+            | I::BiPush(_)
+            | I::SiPush(_) => s.push(Type::Int)?,
+            I::LConst0 | I::LConst1 => s.push(Type::Long)?,
+            I::FConst0 | I::FConst1 | I::FConst2 => s.push(Type::Float)?,
+            I::DConst0 | I::DConst1 => s.push(Type::Double)?,
+            I::Ldc(index) => s.push(self.constant_type(u16::from(*index), false)?)?,
+            I::LdcW(index) => s.push(self.constant_type(*index, false)?)?,
+            I::Ldc2W(index) => s.push(self.constant_type(*index, true)?)?,
+
+            I::ILoad(index) => s.load(usize::from(*index), Type::Int)?,
+            I::ILoad0 | I::ILoad1 | I::ILoad2 | I::ILoad3 => {
+                s.load(short_index(instruction), Type::Int)?
+            }
+            I::LLoad(index) => s.load(usize::from(*index), Type::Long)?,
+            I::LLoad0 | I::LLoad1 | I::LLoad2 | I::LLoad3 => {
+                s.load(short_index(instruction), Type::Long)?
+            }
+            I::FLoad(index) => s.load(usize::from(*index), Type::Float)?,
+            I::FLoad0 | I::FLoad1 | I::FLoad2 | I::FLoad3 => {
+                s.load(short_index(instruction), Type::Float)?
+            }
+            I::DLoad(index) => s.load(usize::from(*index), Type::Double)?,
+            I::DLoad0 | I::DLoad1 | I::DLoad2 | I::DLoad3 => {
+                s.load(short_index(instruction), Type::Double)?
+            }
+            I::ALoad(index) => s.load_reference(usize::from(*index))?,
+            I::ALoad0 | I::ALoad1 | I::ALoad2 | I::ALoad3 => {
+                s.load_reference(short_index(instruction))?
+            }
+
+            I::IStore(index) => s.store(usize::from(*index), Type::Int)?,
+            I::IStore0 | I::IStore1 | I::IStore2 | I::IStore3 => {
+                s.store(short_index(instruction), Type::Int)?
+            }
+            I::LStore(index) => s.store(usize::from(*index), Type::Long)?,
+            I::LStore0 | I::LStore1 | I::LStore2 | I::LStore3 => {
+                s.store(short_index(instruction), Type::Long)?
+            }
+            I::FStore(index) => s.store(usize::from(*index), Type::Float)?,
+            I::FStore0 | I::FStore1 | I::FStore2 | I::FStore3 => {
+                s.store(short_index(instruction), Type::Float)?
+            }
+            I::DStore(index) => s.store(usize::from(*index), Type::Double)?,
+            I::DStore0 | I::DStore1 | I::DStore2 | I::DStore3 => {
+                s.store(short_index(instruction), Type::Double)?
+            }
+            I::AStore(index) => s.store_reference(usize::from(*index))?,
+            I::AStore0 | I::AStore1 | I::AStore2 | I::AStore3 => {
+                s.store_reference(short_index(instruction))?
+            }
+            I::IInc(index, _) => s.increment(usize::from(*index))?,
+            I::Wide(wide) => match wide {
+                Wide::ILoad(index) => s.load(usize::from(*index), Type::Int)?,
+                Wide::LLoad(index) => s.load(usize::from(*index), Type::Long)?,
+                Wide::FLoad(index) => s.load(usize::from(*index), Type::Float)?,
+                Wide::DLoad(index) => s.load(usize::from(*index), Type::Double)?,
+                Wide::ALoad(index) => s.load_reference(usize::from(*index))?,
+                Wide::IStore(index) => s.store(usize::from(*index), Type::Int)?,
+                Wide::LStore(index) => s.store(usize::from(*index), Type::Long)?,
+                Wide::FStore(index) => s.store(usize::from(*index), Type::Float)?,
+                Wide::DStore(index) => s.store(usize::from(*index), Type::Double)?,
+                Wide::AStore(index) => s.store_reference(usize::from(*index))?,
+                Wide::IInc(index, _) => s.increment(usize::from(*index))?,
+                Wide::Ret(index) => s.ret(usize::from(*index))?,
+            },
+
+            I::IALoad => s.array_load("[I", Type::Int)?,
+            I::LALoad => s.array_load("[J", Type::Long)?,
+            I::FALoad => s.array_load("[F", Type::Float)?,
+            I::DALoad => s.array_load("[D", Type::Double)?,
+            I::BALoad => s.array_load("[B", Type::Int)?,
+            I::CALoad => s.array_load("[C", Type::Int)?,
+            I::SALoad => s.array_load("[S", Type::Int)?,
+            I::AALoad => {
+                s.pop(&Type::Int)?;
+                let component = match s.pop_array()? {
+                    Type::Reference(array) => match array_component(&array) {
+                        Some(component)
+                            if component.size() == 1
+                                && component != Type::Int
+                                && component != Type::Float =>
+                        {
+                            component
+                        }
+                        _ => {
+                            return Err(format!(
+                                "aaload needs an array of references, found {array}"
+                            ))
+                        }
+                    },
+                    _ => Type::Null,
+                };
+                s.push(component)?;
+            }
+            I::IAStore => s.array_store("[I", Type::Int)?,
+            I::LAStore => s.array_store("[J", Type::Long)?,
+            I::FAStore => s.array_store("[F", Type::Float)?,
+            I::DAStore => s.array_store("[D", Type::Double)?,
+            I::BAStore => s.array_store("[B", Type::Int)?,
+            I::CAStore => s.array_store("[C", Type::Int)?,
+            I::SAStore => s.array_store("[S", Type::Int)?,
+            I::AAStore => {
+                s.pop_reference()?;
+                s.pop(&Type::Int)?;
+                if let Type::Reference(array) = s.pop_array()? {
+                    if !matches!(array.as_bytes().get(1), Some(b'L' | b'[')) {
+                        return Err(format!(
+                            "aastore needs an array of references, found {array}"
+                        ));
+                    }
+                }
+            }
+
+            I::Pop => {
+                s.pop_slots(1)?;
+            }
+            I::Pop2 => {
+                s.pop_slots(2)?;
+            }
+            I::Dup => {
+                let a = s.pop_slots(1)?;
+                s.push_all(&a)?;
+                s.push_all(&a)?;
+            }
+            I::DupX1 | I::DupX2 | I::Dup2X1 | I::Dup2X2 => {
+                let (top, below) = match instruction {
+                    I::DupX1 => (1, 1),
+                    I::DupX2 => (1, 2),
+                    I::Dup2X1 => (2, 1),
+                    _ => (2, 2),
+                };
+                let a = s.pop_slots(top)?;
+                let b = s.pop_slots(below)?;
+                s.push_all(&a)?;
+                s.push_all(&b)?;
+                s.push_all(&a)?;
+            }
+            I::Dup2 => {
+                let a = s.pop_slots(2)?;
+                s.push_all(&a)?;
+                s.push_all(&a)?;
+            }
+            I::Swap => {
+                let a = s.pop_slots(1)?;
+                let b = s.pop_slots(1)?;
+                s.push_all(&a)?;
+                s.push_all(&b)?;
+            }
+
+            I::IAdd
+            | I::ISub
+            | I::IMul
+            | I::IDiv
+            | I::IRem
+            | I::IAnd
+            | I::IOr
+            | I::IXor
+            | I::IShl
+            | I::IShr
+            | I::IUShr => s.binary(Type::Int, Type::Int, Type::Int)?,
+            I::LAdd | I::LSub | I::LMul | I::LDiv | I::LRem | I::LAnd | I::LOr | I::LXor => {
+                s.binary(Type::Long, Type::Long, Type::Long)?
+            }
+            I::LShl | I::LShr | I::LUShr => s.binary(Type::Long, Type::Int, Type::Long)?,
+            I::FAdd | I::FSub | I::FMul | I::FDiv | I::FRem => {
+                s.binary(Type::Float, Type::Float, Type::Float)?
+            }
+            I::DAdd | I::DSub | I::DMul | I::DDiv | I::DRem => {
+                s.binary(Type::Double, Type::Double, Type::Double)?
+            }
+            I::LCmp => s.binary(Type::Long, Type::Long, Type::Int)?,
+            I::FCmpL | I::FCmpG => s.binary(Type::Float, Type::Float, Type::Int)?,
+            I::DCmpL | I::DCmpG => s.binary(Type::Double, Type::Double, Type::Int)?,
+            I::INeg | I::I2B | I::I2C | I::I2S => s.unary(Type::Int, Type::Int)?,
+            I::LNeg => s.unary(Type::Long, Type::Long)?,
+            I::FNeg => s.unary(Type::Float, Type::Float)?,
+            I::DNeg => s.unary(Type::Double, Type::Double)?,
+            I::I2L => s.unary(Type::Int, Type::Long)?,
+            I::I2F => s.unary(Type::Int, Type::Float)?,
+            I::I2D => s.unary(Type::Int, Type::Double)?,
+            I::L2I => s.unary(Type::Long, Type::Int)?,
+            I::L2F => s.unary(Type::Long, Type::Float)?,
+            I::L2D => s.unary(Type::Long, Type::Double)?,
+            I::F2I => s.unary(Type::Float, Type::Int)?,
+            I::F2L => s.unary(Type::Float, Type::Long)?,
+            I::F2D => s.unary(Type::Float, Type::Double)?,
+            I::D2I => s.unary(Type::Double, Type::Int)?,
+            I::D2L => s.unary(Type::Double, Type::Long)?,
+            I::D2F => s.unary(Type::Double, Type::Float)?,
+
+            I::IfEq(_)
+            | I::IfNe(_)
+            | I::IfLt(_)
+            | I::IfGe(_)
+            | I::IfGt(_)
+            | I::IfLe(_)
+            | I::TableSwitch { .. }
+            | I::LookupSwitch { .. } => {
+                s.pop(&Type::Int)?;
+            }
+            I::IfICmpEq(_)
+            | I::IfICmpNe(_)
+            | I::IfICmpLt(_)
+            | I::IfICmpGe(_)
+            | I::IfICmpGt(_)
+            | I::IfICmpLe(_) => {
+                s.pop(&Type::Int)?;
+                s.pop(&Type::Int)?;
+            }
+            I::IfNull(_) | I::IfNonNull(_) => {
+                s.pop_reference()?;
+            }
+            I::IfACmpEq(_) | I::IfACmpNe(_) => {
+                s.pop_reference()?;
+                s.pop_reference()?;
+            }
+            I::Goto(_) | I::GotoW(_) => {}
+            I::Jsr(_) | I::JsrW(_) => {
+                if self.stack_maps {
+                    return Err(
+                        "jsr is not allowed in class files of version 51 and later".to_string()
+                    );
+                }
+                s.push(Type::ReturnAddress)?;
+            }
+            I::Ret(index) => s.ret(usize::from(*index))?,
+
+            I::IReturn => s.return_value(Type::Int)?,
+            I::LReturn => s.return_value(Type::Long)?,
+            I::FReturn => s.return_value(Type::Float)?,
+            I::DReturn => s.return_value(Type::Double)?,
+            I::AReturn => {
+                let Some(FieldType::Object(_) | FieldType::Array(_)) = &self.descriptor.return_type
+                else {
+                    return Err("areturn in a method not returning a reference".to_string());
+                };
+                let expected = Type::from_field_type(
+                    self.descriptor.return_type.as_ref().expect("checked above"),
+                );
+                s.pop(&expected)?;
+            }
+            I::Return => {
+                if self.descriptor.return_type.is_some() {
+                    return Err("return without a value in a method returning one".to_string());
+                }
+                if s.frame.this_uninitialized {
+                    return Err("constructor returns before calling super() or this()".to_string());
+                }
+            }
+            I::AThrow => {
+                s.pop(&Type::Reference(THROWABLE.to_string()))?;
+            }
+
+            I::GetStatic(index) => {
+                let field = self.field_type(*index)?;
+                s.push(field)?;
+            }
+            I::PutStatic(index) => {
+                let field = self.field_type(*index)?;
+                s.pop(&field)?;
+            }
+            I::GetField(index) => {
+                let field = self.field_type(*index)?;
+                let owner = self.owner(*index)?;
+                s.pop(&owner)?;
+                s.push(field)?;
+            }
+            I::PutField(index) => {
+                let field = self.field_type(*index)?;
+                s.pop(&field)?;
+                // Constructors may assign fields of their class before calling super()
+                let owner = self.owner(*index)?;
+                let own_field = owner == Type::Reference(self.class_name().to_string());
+                match s.top()? {
+                    Type::UninitializedThis if own_field => {
+                        s.pop_any()?;
+                    }
+                    _ => {
+                        s.pop(&owner)?;
+                    }
+                }
+            }
+
+            I::InvokeVirtual(index)
+            | I::InvokeSpecial(index)
+            | I::InvokeStatic(index)
+            | I::InvokeInterface(index, _) => self.invoke(s, instruction, *index)?,
+            I::InvokeDynamic(index) => {
+                let descriptor = match constant(self.pool, *index) {
+                    Some(ConstantPoolInfo::InvokeDynamic {
+                        name_and_type_index,
+                        ..
+                    }) => name_and_type_at(self.pool, *name_and_type_index)
+                        .map(|(_, descriptor)| descriptor),
+                    _ => None,
+                };
+                let descriptor = descriptor
+                    .and_then(MethodDescriptor::parse)
+                    .ok_or_else(|| format!("#{index} is not a valid InvokeDynamic constant"))?;
+                s.pop_arguments(&descriptor)?;
+                if let Some(return_type) = &descriptor.return_type {
+                    s.push(Type::from_field_type(return_type))?;
+                }
+            }
+
+            I::New(index) => {
+                let name = self.class_at(*index)?;
+                if name.starts_with('[') {
+                    return Err(format!("new cannot create array {name}"));
+                }
+                s.push(Type::Uninitialized(offset))?;
+            }
+            I::NewArray(atype) => {
+                let descriptor = match atype {
+                    4 => "[Z",
+                    5 => "[C",
+                    6 => "[F",
+                    7 => "[D",
+                    8 => "[B",
+                    9 => "[S",
+                    10 => "[I",
+                    11 => "[J",
+                    _ => return Err(format!("invalid array type {atype}")),
+                };
+                s.pop(&Type::Int)?;
+                s.push(Type::Reference(descriptor.to_string()))?;
+            }
+            I::ANewArray(index) => {
+                let name = self.class_at(*index)?;
+                s.pop(&Type::Int)?;
+                let array = match name.starts_with('[') {
+                    true => format!("[{name}"),
+                    false => format!("[L{name};"),
+                };
+                s.push(Type::Reference(array))?;
+            }
+            I::MultiANewArray(index, dimensions) => {
+                let name = self.class_at(*index)?;
+                let depth = name.bytes().take_while(|byte| *byte == b'[').count();
+                if *dimensions == 0 || usize::from(*dimensions) > depth {
+                    return Err(format!(
+                        "multianewarray of {dimensions} dimensions for {name}"
+                    ));
+                }
+                for _ in 0..*dimensions {
+                    s.pop(&Type::Int)?;
+                }
+                s.push(Type::Reference(name.to_string()))?;
+            }
+            I::ArrayLength => {
+                s.pop_array()?;
+                s.push(Type::Int)?;
+            }
+            I::CheckCast(index) => {
+                let name = self.class_at(*index)?;
+                s.pop_reference()?;
+                s.push(Type::Reference(name.to_string()))?;
+            }
+            I::InstanceOf(index) => {
+                self.class_at(*index)?;
+                s.pop_reference()?;
+                s.push(Type::Int)?;
+            }
+            I::MonitorEnter | I::MonitorExit => {
+                s.pop_reference()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn invoke(&self, s: &mut State, instruction: &Instruction, index: u16) -> Step {
+        let (owner, name, descriptor) = member_ref_at(self.pool, index)
+            .ok_or_else(|| format!("#{index} is not a method reference"))?;
+        let parsed = MethodDescriptor::parse(descriptor)
+            .ok_or_else(|| format!("invalid method descriptor {descriptor:?}"))?;
+        let mnemonic = instruction.mnemonic();
+        if name == "<clinit>"
+            || (name == "<init>" && !matches!(instruction, Instruction::InvokeSpecial(_)))
+        {
+            return Err(format!("{mnemonic} cannot call {name}"));
+        }
+        s.pop_arguments(&parsed)?;
+        let owner_type = Type::Reference(owner.to_string());
+        match instruction {
+            Instruction::InvokeStatic(_) => {}
+            Instruction::InvokeSpecial(_) if name == "<init>" => {
+                if parsed.return_type.is_some() {
+                    return Err(format!(
+                        "constructor {owner}.<init>{descriptor} returns a value"
+                    ));
+                }
+                let receiver = s.pop_any()?;
+                let initialized = match &receiver {
+                    Type::UninitializedThis => {
+                        let class_name = self.class_name();
+                        let super_name = self.classes.class.super_name();
+                        if owner != class_name && Some(owner) != super_name {
+                            return Err(format!("constructor of {class_name} calls {owner}.<init>, which is not of this class or its superclass"));
+                        }
+                        s.frame.this_uninitialized = false;
+                        Type::Reference(class_name.to_string())
+                    }
+                    Type::Uninitialized(created) => {
+                        let class = match self
+                            .code
+                            .code
+                            .binary_search_by_key(created, |(offset, _)| *offset)
+                        {
+                            Ok(index) => match &self.code.code[index].1 {
+                                Instruction::New(class) => self.class_at(*class)?,
+                                _ => {
+                                    return Err(format!(
+                                        "uninitialized({created}) was not created by new"
+                                    ))
+                                }
+                            },
+                            Err(_) => {
+                                return Err(format!(
+                                    "uninitialized({created}) was not created by new"
+                                ))
+                            }
+                        };
+                        if class != owner {
+                            return Err(format!("calls {owner}.<init> on an object of {class}"));
+                        }
+                        Type::Reference(class.to_string())
+                    }
+                    other => {
+                        return Err(format!(
+                            "calls {owner}.<init> on {other}, which is not uninitialized"
+                        ))
+                    }
+                };
+                // Every copy of the object is initialized
+                for value in s.frame.locals.iter_mut().chain(s.frame.stack.iter_mut()) {
+                    if *value == receiver {
+                        *value = initialized.clone();
+                    }
+                }
+                return Ok(());
+            }
+            Instruction::InvokeSpecial(_) => {
+                let current = Type::Reference(self.class_name().to_string());
+                let receiver = s.pop_any()?;
+                if !self.classes.is_assignable(&receiver, &current)
+                    || !self.classes.is_assignable(&receiver, &owner_type)
+                {
+                    return Err(format!(
+                        "invokespecial of {owner}.{name} on {receiver}, expected {}",
+                        self.class_name()
+                    ));
+                }
+            }
+            _ => {
+                s.pop(&owner_type)?;
+            }
+        }
+        if let Some(return_type) = &parsed.return_type {
+            s.push(Type::from_field_type(return_type))?;
+        }
+        Ok(())
+    }
+
+    fn class_name(&self) -> &str {
+        self.classes.class.name().unwrap_or("?")
+    }
+
+    fn class_at(&self, index: u16) -> Step<&str> {
+        class_name_at(self.pool, index).ok_or_else(|| format!("#{index} is not a class"))
+    }
+
+    fn field_type(&self, index: u16) -> Step<Type> {
+        member_ref_at(self.pool, index)
+            .and_then(|(_, _, descriptor)| FieldType::parse(descriptor))
+            .map(|field_type| Type::from_field_type(&field_type))
+            .ok_or_else(|| format!("#{index} is not a valid field reference"))
+    }
+
+    /// Class of the member reference, as the type of the object it is accessed on
+    fn owner(&self, index: u16) -> Step<Type> {
+        member_ref_at(self.pool, index)
+            .map(|(owner, _, _)| Type::Reference(owner.to_string()))
+            .ok_or_else(|| format!("#{index} is not a valid member reference"))
+    }
+
+    /// Type of the value `ldc` (or `ldc2_w` with `wide`) pushes
+    fn constant_type(&self, index: u16, wide: bool) -> Step<Type> {
+        let reference = |name: &str| Type::Reference(name.to_string());
+        let loaded = match constant(self.pool, index) {
+            Some(ConstantPoolInfo::Integer(_)) => Type::Int,
+            Some(ConstantPoolInfo::Float(_)) => Type::Float,
+            Some(ConstantPoolInfo::Long(_)) => Type::Long,
+            Some(ConstantPoolInfo::Double(_)) => Type::Double,
+            Some(ConstantPoolInfo::String { .. }) => reference("java/lang/String"),
+            Some(ConstantPoolInfo::Class { .. }) => reference("java/lang/Class"),
+            Some(ConstantPoolInfo::MethodType { .. }) => reference("java/lang/invoke/MethodType"),
+            Some(ConstantPoolInfo::MethodHandle { .. }) => {
+                reference("java/lang/invoke/MethodHandle")
+            }
+            Some(ConstantPoolInfo::Dynamic {
+                name_and_type_index,
+                ..
+            }) => name_and_type_at(self.pool, *name_and_type_index)
+                .and_then(|(_, descriptor)| FieldType::parse(descriptor))
+                .map(|field_type| Type::from_field_type(&field_type))
+                .ok_or_else(|| format!("#{index} is not a valid Dynamic constant"))?,
+            _ => return Err(format!("#{index} cannot be loaded by ldc")),
+        };
+        if (loaded.size() == 2) != wide {
+            let mnemonic = if wide { "ldc2_w" } else { "ldc" };
+            return Err(format!("{mnemonic} cannot load {loaded} constant #{index}"));
+        }
+        Ok(loaded)
+    }
+}
+
+/// Frame being changed by an instruction
+struct State<'a, 'b> {
+    checker: &'a Checker<'a>,
+    frame: &'b mut Frame,
+}
+
+impl State<'_, '_> {
+    fn push(&mut self, value: Type) -> Step {
+        let max_stack = usize::from(self.checker.code.max_stack);
+        if self.frame.depth() + value.size() > max_stack {
+            return Err(format!("operand stack overflows max_stack {max_stack}"));
+        }
+        self.frame.stack.push(value);
+        Ok(())
+    }
+
+    fn push_all(&mut self, values: &[Type]) -> Step {
+        values.iter().try_for_each(|value| self.push(value.clone()))
+    }
+
+    fn top(&self) -> Step<&Type> {
+        self.frame
+            .stack
+            .last()
+            .ok_or_else(|| "operand stack underflow".to_string())
+    }
+
+    fn pop_any(&mut self) -> Step<Type> {
+        self.frame
+            .stack
+            .pop()
+            .ok_or_else(|| "operand stack underflow".to_string())
+    }
+
+    /// Pops a value assignable to `expected`
+    fn pop(&mut self, expected: &Type) -> Step<Type> {
+        let value = self.pop_any()?;
+        match self.checker.classes.is_assignable(&value, expected) {
+            true => Ok(value),
+            false => Err(format!(
+                "expected {expected} on the operand stack, found {value}"
+            )),
+        }
+    }
+
+    /// Pops an initialized reference or null
+    fn pop_reference(&mut self) -> Step<Type> {
+        let value = self.pop_any()?;
+        match value.is_initialized_reference() {
+            true => Ok(value),
+            false => Err(format!(
+                "expected a reference on the operand stack, found {value}"
+            )),
+        }
+    }
+
+    /// Pops an array or null
+    fn pop_array(&mut self) -> Step<Type> {
+        let value = self.pop_reference()?;
+        match &value {
+            Type::Reference(name) if !name.starts_with('[') => Err(format!(
+                "expected an array on the operand stack, found {value}"
+            )),
+            _ => Ok(value),
+        }
+    }
+
+    /// Pops values taking `slots` slots, without splitting a long or double.
+    /// Returns them in the order they were on the stack.
+    fn pop_slots(&mut self, slots: usize) -> Step<Vec<Type>> {
+        let mut values = vec![];
+        let mut taken = 0;
+        while taken < slots {
+            let value = self.pop_any()?;
+            taken += value.size();
+            values.push(value);
+        }
+        if taken > slots {
+            return Err(format!(
+                "instruction would split the {} on the operand stack",
+                values.last().expect("values were popped")
+            ));
+        }
+        values.reverse();
+        Ok(values)
+    }
+
+    fn pop_arguments(&mut self, descriptor: &MethodDescriptor) -> Step {
+        for parameter in descriptor.parameters.iter().rev() {
+            self.pop(&Type::from_field_type(parameter))?;
+        }
+        Ok(())
+    }
+
+    fn unary(&mut self, operand: Type, result: Type) -> Step {
+        self.pop(&operand)?;
+        self.push(result)
+    }
+
+    /// Pops `right` then `left` and pushes the result
+    fn binary(&mut self, left: Type, right: Type, result: Type) -> Step {
+        self.pop(&right)?;
+        self.pop(&left)?;
+        self.push(result)
+    }
+
+    fn array_load(&mut self, array: &str, element: Type) -> Step {
+        self.pop(&Type::Int)?;
+        self.check_array(array)?;
+        self.push(element)
+    }
+
+    fn array_store(&mut self, array: &str, element: Type) -> Step {
+        self.pop(&element)?;
+        self.pop(&Type::Int)?;
+        self.check_array(array)
+    }
+
+    /// Pops an array of the primitive type, where byte instructions also accept booleans
+    fn check_array(&mut self, array: &str) -> Step {
+        match self.pop_array()? {
+            Type::Reference(name) if name != array && !(array == "[B" && name == "[Z") => Err(
+                format!("expected {array} on the operand stack, found {name}"),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    fn local(&self, index: usize, size: usize) -> Step<&Type> {
+        let max_locals = self.frame.locals.len();
+        if index + size > max_locals {
+            return Err(format!(
+                "local variable {index} is outside of max_locals {max_locals}"
+            ));
+        }
+        Ok(&self.frame.locals[index])
+    }
+
+    fn load(&mut self, index: usize, expected: Type) -> Step {
+        let value = self.local(index, expected.size())?;
+        if *value != expected {
+            return Err(format!(
+                "local variable {index} holds {value}, expected {expected}"
+            ));
+        }
+        self.push(expected)
+    }
+
+    /// Loads a reference, which may also be uninitialized
+    fn load_reference(&mut self, index: usize) -> Step {
+        let value = self.local(index, 1)?.clone();
+        match value {
+            Type::Null | Type::Reference(_) | Type::UninitializedThis | Type::Uninitialized(_) => {
+                self.push(value)
+            }
+            _ => Err(format!(
+                "local variable {index} holds {value}, expected a reference"
+            )),
+        }
+    }
+
+    fn store(&mut self, index: usize, expected: Type) -> Step {
+        self.pop(&expected)?;
+        self.set_local(index, expected)
+    }
+
+    /// Stores a reference, which may also be uninitialized, or a return address
+    fn store_reference(&mut self, index: usize) -> Step {
+        let value = self.pop_any()?;
+        match value {
+            Type::Null
+            | Type::Reference(_)
+            | Type::UninitializedThis
+            | Type::Uninitialized(_)
+            | Type::ReturnAddress => self.set_local(index, value),
+            _ => Err(format!("astore of {value}, expected a reference")),
+        }
+    }
+
+    fn set_local(&mut self, index: usize, value: Type) -> Step {
+        let size = value.size();
+        self.local(index, size)?;
+        // A long or double in the previous variable loses its second half
+        if index > 0 && self.frame.locals[index - 1].size() == 2 {
+            self.frame.locals[index - 1] = Type::Top;
+        }
+        self.frame.locals[index] = value;
+        if size == 2 {
+            self.frame.locals[index + 1] = Type::Top;
+        }
+        Ok(())
+    }
+
+    fn increment(&mut self, index: usize) -> Step {
+        let value = self.local(index, 1)?;
+        match value {
+            Type::Int => Ok(()),
+            _ => Err(format!("iinc of local variable {index} holding {value}")),
+        }
+    }
+
+    fn ret(&mut self, index: usize) -> Step {
+        if self.checker.stack_maps {
+            return Err("ret is not allowed in class files of version 51 and later".to_string());
+        }
+        let value = self.local(index, 1)?;
+        match value {
+            Type::ReturnAddress => Ok(()),
+            _ => Err(format!("ret with local variable {index} holding {value}")),
+        }
+    }
+
+    /// Pops the returned value, checking it against the return type of the method
+    fn return_value(&mut self, expected: Type) -> Step {
+        let declared = self
+            .checker
+            .descriptor
+            .return_type
+            .as_ref()
+            .map(Type::from_field_type);
+        if declared.as_ref() != Some(&expected) {
+            let declared = declared.map_or("void".to_string(), |declared| declared.to_string());
+            return Err(format!(
+                "returns {expected} from a method returning {declared}"
+            ));
+        }
+        self.pop(&expected)?;
+        Ok(())
+    }
+}
+
+/// Local variable of `iload_0` and the like
+fn short_index(instruction: &Instruction) -> usize {
+    use Instruction as I;
+    match instruction {
+        I::ILoad0
+        | I::LLoad0
+        | I::FLoad0
+        | I::DLoad0
+        | I::ALoad0
+        | I::IStore0
+        | I::LStore0
+        | I::FStore0
+        | I::DStore0
+        | I::AStore0 => 0,
+        I::ILoad1
+        | I::LLoad1
+        | I::FLoad1
+        | I::DLoad1
+        | I::ALoad1
+        | I::IStore1
+        | I::LStore1
+        | I::FStore1
+        | I::DStore1
+        | I::AStore1 => 1,
+        I::ILoad2
+        | I::LLoad2
+        | I::FLoad2
+        | I::DLoad2
+        | I::ALoad2
+        | I::IStore2
+        | I::LStore2
+        | I::FStore2
+        | I::DStore2
+        | I::AStore2 => 2,
+        _ => 3,
+    }
+}
+
+/// Type of the elements of an array type like `[I`
+fn array_component(array: &str) -> Option<Type> {
+    FieldType::parse(array.strip_prefix('[')?).map(|component| Type::from_field_type(&component))
+}
+
+fn push_local(locals: &mut Vec<Type>, value: Type) {
+    let size = value.size();
+    locals.push(value);
+    if size == 2 {
+        locals.push(Type::Top);
+    }
+}
+
+/// Whether execution can continue with the next instruction
+fn falls_through(instruction: &Instruction) -> bool {
+    !matches!(
+        instruction,
+        Instruction::Goto(_)
+            | Instruction::GotoW(_)
+            | Instruction::TableSwitch { .. }
+            | Instruction::LookupSwitch { .. }
+            | Instruction::Ret(_)
+            | Instruction::Wide(Wide::Ret(_))
+            | Instruction::IReturn
+            | Instruction::LReturn
+            | Instruction::FReturn
+            | Instruction::DReturn
+            | Instruction::AReturn
+            | Instruction::Return
+            | Instruction::AThrow
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check;
+    use crate::assembly::assemble;
+
+    /// Problems of the static method `m()V` of a class of the version with the code
+    fn problems(version: u16, stack: u16, locals: u16, code: &str) -> Vec<String> {
+        let source = format!(
+            ".version {version} 0\n\
+             .class super T\n\
+             .method static m()V\n\
+             .code stack {stack} locals {locals}\n\
+             {code}\n\
+             .end code\n\
+             .end method\n"
+        );
+        check(&assemble(&source).unwrap(), None)
+    }
+
+    #[test]
+    fn stack_underflow() {
+        assert_eq!(
+            problems(49, 1, 0, "pop\nreturn"),
+            ["method m()V: instruction 0: operand stack underflow"]
+        );
+    }
+
+    #[test]
+    fn max_stack_overflow() {
+        assert_eq!(
+            problems(49, 1, 0, "iconst_0\niconst_1\npop2\nreturn"),
+            ["method m()V: instruction 1: operand stack overflows max_stack 1"]
+        );
+    }
+
+    #[test]
+    fn local_outside_max_locals() {
+        assert_eq!(
+            problems(49, 1, 1, "iconst_0\nistore_1\nreturn"),
+            ["method m()V: instruction 1: local variable 1 is outside of max_locals 1"]
+        );
+    }
+
+    #[test]
+    fn int_where_float_expected() {
+        assert_eq!(
+            problems(49, 2, 0, "iconst_0\nfconst_1\nfadd\npop\nreturn"),
+            ["method m()V: instruction 2: expected float on the operand stack, found int"]
+        );
+    }
+
+    #[test]
+    fn uninitialized_object_used() {
+        let code = "new java/lang/Object\n\
+                    invokevirtual java/lang/Object.hashCode()I\n\
+                    pop\n\
+                    return";
+        assert_eq!(
+            problems(49, 2, 0, code),
+            [
+                "method m()V: instruction 3: expected java/lang/Object on the operand stack, \
+              found uninitialized(0)"
+            ]
+        );
+    }
+
+    #[test]
+    fn falling_off_the_end() {
+        assert_eq!(
+            problems(49, 1, 0, "iconst_0\npop"),
+            ["method m()V: instruction 1: execution falls off the end of the code"]
+        );
+    }
+
+    #[test]
+    fn local_merged_to_top() {
+        // The int and float stored on the two paths merge to top at L1
+        let code = "iconst_0\nifeq L0\niconst_1\nistore_0\ngoto L1\n\
+                    L0:\nfconst_0\nfstore_0\n\
+                    L1:\niload_0\npop\nreturn";
+        assert_eq!(
+            problems(49, 1, 1, code),
+            ["method m()V: instruction 11: local variable 0 holds top, expected int"]
+        );
+    }
+
+    #[test]
+    fn stack_map_frame_mismatch() {
+        // Both the branch and falling through reach the frame
+        let code = "iconst_0\nistore_0\niconst_0\nifeq L0\n\
+                    L0:\n.stack full locals Float stack\nreturn";
+        assert_eq!(
+            problems(52, 1, 1, code),
+            [
+                "method m()V: instruction 3: stack map frame at 6 does not match: \
+                 local 0 is int, frame has float",
+                "method m()V: instruction 6: stack map frame at 6 does not match: \
+                 local 0 is int, frame has float",
+            ]
+        );
+    }
+}
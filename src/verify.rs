@@ -0,0 +1,405 @@
+//! Structural checks of a parsed class, roughly the format checking of JVMS §4.8
+//! together with the static constraints on code from §4.9.1. Methods of well formed classes
+//! are type checked then, see [`typecheck`](crate::typecheck).
+
+use crate::classpath::ClassPath;
+use crate::raw::class::Class;
+use crate::raw::*;
+use crate::typecheck;
+
+/// Returns the list of problems found, empty when the class looks well formed and its code
+/// type checks. Supertypes of classes used by the code are loaded from `class_path`.
+pub fn verify(class: &Class, class_path: Option<&ClassPath>) -> Vec<String> {
+    let mut verifier = Verifier {
+        class,
+        pool: &class.constant_pool,
+        problems: vec![],
+    };
+    verifier.constant_pool();
+    verifier.class_header();
+    for field in &class.fields {
+        verifier.field(field);
+    }
+    for method in &class.methods {
+        verifier.method(method);
+    }
+    if verifier.problems.is_empty() {
+        verifier.problems = typecheck::check(class, class_path);
+    }
+    verifier.problems
+}
+
+/// Kind of constant that an index is expected to point to
+#[derive(Clone, Copy)]
+enum Expected {
+    Utf8,
+    Class,
+    NameAndType,
+    FieldRef,
+    MethodRef,
+    /// Methodref or InterfaceMethodref
+    AnyMethodRef,
+    InterfaceMethodRef,
+    InvokeDynamic,
+    /// Anything `ldc` and `ldc_w` can load
+    Loadable,
+    /// Long or Double for `ldc2_w`
+    Wide,
+    /// Reference of MethodHandle, depends on the reference kind
+    Member,
+}
+
+impl Expected {
+    fn matches(self, constant: &ConstantPoolInfo) -> bool {
+        use ConstantPoolInfo as C;
+        match self {
+            Expected::Utf8 => matches!(constant, C::Utf8(_) | C::Utf16(_)),
+            Expected::Class => matches!(constant, C::Class { .. }),
+            Expected::NameAndType => matches!(constant, C::NameAndType { .. }),
+            Expected::FieldRef => matches!(constant, C::FieldRef { .. }),
+            Expected::MethodRef => matches!(constant, C::MethodRef { .. }),
+            Expected::AnyMethodRef => {
+                matches!(constant, C::MethodRef { .. } | C::InterfaceMethodRef { .. })
+            }
+            Expected::InterfaceMethodRef => matches!(constant, C::InterfaceMethodRef { .. }),
+            Expected::InvokeDynamic => matches!(constant, C::InvokeDynamic { .. }),
+            Expected::Loadable => matches!(
+                constant,
+                C::Integer(_)
+                    | C::Float(_)
+                    | C::String { .. }
+                    | C::Class { .. }
+                    | C::MethodType { .. }
+                    | C::MethodHandle { .. }
+                    | C::Dynamic { .. }
+            ),
+            Expected::Wide => matches!(constant, C::Long(_) | C::Double(_) | C::Dynamic { .. }),
+            Expected::Member => matches!(
+                constant,
+                C::FieldRef { .. } | C::MethodRef { .. } | C::InterfaceMethodRef { .. }
+            ),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Expected::Utf8 => "Utf8",
+            Expected::Class => "Class",
+            Expected::NameAndType => "NameAndType",
+            Expected::FieldRef => "Fieldref",
+            Expected::MethodRef => "Methodref",
+            Expected::AnyMethodRef => "Methodref or InterfaceMethodref",
+            Expected::InterfaceMethodRef => "InterfaceMethodref",
+            Expected::InvokeDynamic => "InvokeDynamic",
+            Expected::Loadable => "loadable constant",
+            Expected::Wide => "Long or Double",
+            Expected::Member => "Fieldref, Methodref or InterfaceMethodref",
+        }
+    }
+}
+
+struct Verifier<'a> {
+    class: &'a Class,
+    pool: &'a [ConstantPoolInfo],
+    problems: Vec<String>,
+}
+
+impl<'a> Verifier<'a> {
+    fn problem(&mut self, context: &str, message: String) {
+        self.problems.push(format!("{context}: {message}"));
+    }
+
+    /// Checks that `index` points to the `expected` kind of constant, returns if it does
+    fn reference(&mut self, context: &str, index: u16, expected: Expected) -> bool {
+        match constant(self.pool, index) {
+            Some(constant) if expected.matches(constant) => true,
+            Some(constant) => {
+                self.problem(
+                    context,
+                    format!(
+                        "#{index} should be {}, found {}",
+                        expected.name(),
+                        constant.tag_name()
+                    ),
+                );
+                false
+            }
+            None => {
+                self.problem(
+                    context,
+                    format!(
+                        "#{index} is outside of the constant pool of size {}",
+                        self.pool.len() + 1
+                    ),
+                );
+                false
+            }
+        }
+    }
+
+    fn bootstrap_methods_count(&self) -> usize {
+        self.class
+            .attributes
+            .iter()
+            .find_map(|attribute| match &attribute.info {
+                AttributeInfo::BootstrapMethods(methods) => Some(methods.len()),
+                _ => None,
+            })
+            .unwrap_or(0)
+    }
+
+    fn constant_pool(&mut self) {
+        for (i, constant) in self.pool.iter().enumerate() {
+            let context = format!("constant #{}", i + 1);
+            let context = context.as_str();
+            match constant {
+                ConstantPoolInfo::Class { name_index }
+                | ConstantPoolInfo::Module { name_index }
+                | ConstantPoolInfo::Package { name_index } => {
+                    self.reference(context, *name_index, Expected::Utf8);
+                }
+                ConstantPoolInfo::String { string_index } => {
+                    self.reference(context, *string_index, Expected::Utf8);
+                }
+                ConstantPoolInfo::FieldRef {
+                    class_index,
+                    name_and_type_index,
+                }
+                | ConstantPoolInfo::MethodRef {
+                    class_index,
+                    name_and_type_index,
+                }
+                | ConstantPoolInfo::InterfaceMethodRef {
+                    class_index,
+                    name_and_type_index,
+                } => {
+                    self.reference(context, *class_index, Expected::Class);
+                    if self.reference(context, *name_and_type_index, Expected::NameAndType) {
+                        let Some((_, descriptor)) =
+                            name_and_type_at(self.pool, *name_and_type_index)
+                        else {
+                            continue;
+                        };
+                        let valid = match constant {
+                            ConstantPoolInfo::FieldRef { .. } => {
+                                FieldType::parse(descriptor).is_some()
+                            }
+                            _ => MethodDescriptor::parse(descriptor).is_some(),
+                        };
+                        if !valid {
+                            self.problem(context, format!("invalid descriptor {descriptor:?}"));
+                        }
+                    }
+                }
+                ConstantPoolInfo::NameAndType {
+                    name_index,
+                    descriptor_index,
+                } => {
+                    self.reference(context, *name_index, Expected::Utf8);
+                    self.reference(context, *descriptor_index, Expected::Utf8);
+                }
+                ConstantPoolInfo::MethodHandle {
+                    reference_kind,
+                    reference_index,
+                } => {
+                    if !(1..=9).contains(reference_kind) {
+                        self.problem(context, format!("invalid reference kind {reference_kind}"));
+                    }
+                    let expected = match reference_kind {
+                        1..=4 => Expected::FieldRef,
+                        5 | 8 => Expected::MethodRef,
+                        6 | 7 => Expected::AnyMethodRef,
+                        9 => Expected::InterfaceMethodRef,
+                        _ => Expected::Member,
+                    };
+                    self.reference(context, *reference_index, expected);
+                }
+                ConstantPoolInfo::MethodType { descriptor_index } => {
+                    if self.reference(context, *descriptor_index, Expected::Utf8) {
+                        let descriptor = utf8_at(self.pool, *descriptor_index).unwrap_or_default();
+                        if MethodDescriptor::parse(descriptor).is_none() {
+                            self.problem(
+                                context,
+                                format!("invalid method descriptor {descriptor:?}"),
+                            );
+                        }
+                    }
+                }
+                ConstantPoolInfo::Dynamic {
+                    bootstrap_method_attr_index,
+                    name_and_type_index,
+                }
+                | ConstantPoolInfo::InvokeDynamic {
+                    bootstrap_method_attr_index,
+                    name_and_type_index,
+                } => {
+                    self.reference(context, *name_and_type_index, Expected::NameAndType);
+                    let count = self.bootstrap_methods_count();
+                    if *bootstrap_method_attr_index as usize >= count {
+                        self.problem(
+                            context,
+                            format!(
+                                "bootstrap method {bootstrap_method_attr_index} does not exist, \
+                                 class has {count}"
+                            ),
+                        );
+                    }
+                }
+                ConstantPoolInfo::Utf8(_)
+                | ConstantPoolInfo::Utf16(_)
+                | ConstantPoolInfo::Integer(_)
+                | ConstantPoolInfo::Float(_)
+                | ConstantPoolInfo::Long(_)
+                | ConstantPoolInfo::Double(_)
+                | ConstantPoolInfo::Unusable => {}
+            }
+        }
+    }
+
+    fn class_header(&mut self) {
+        self.reference("this_class", self.class.this_class, Expected::Class);
+
+        let is_module = self.class.access_info.contains(AccessFlags::Module);
+        if self.class.super_class == 0 {
+            let name = class_name_at(self.pool, self.class.this_class);
+            if name != Some("java/lang/Object") && !is_module {
+                self.problem(
+                    "super_class",
+                    "only java/lang/Object can have no superclass".to_string(),
+                );
+            }
+        } else {
+            self.reference("super_class", self.class.super_class, Expected::Class);
+        }
+
+        for index in &self.class.interfaces {
+            self.reference("interfaces", *index, Expected::Class);
+        }
+    }
+
+    fn field(&mut self, field: &FieldInfo) {
+        let name = utf8_at(self.pool, field.name_index).unwrap_or("?");
+        let context = format!("field {name}");
+        self.reference(&context, field.name_index, Expected::Utf8);
+        if self.reference(&context, field.descriptor_index, Expected::Utf8) {
+            let descriptor = utf8_at(self.pool, field.descriptor_index).unwrap_or_default();
+            if FieldType::parse(descriptor).is_none() {
+                self.problem(&context, format!("invalid descriptor {descriptor:?}"));
+            }
+        }
+    }
+
+    fn method(&mut self, method: &MethodInfo) {
+        let name = utf8_at(self.pool, method.name_index).unwrap_or("?");
+        let descriptor = utf8_at(self.pool, method.descriptor_index).unwrap_or("?");
+        let context = format!("method {name}{descriptor}");
+        self.reference(&context, method.name_index, Expected::Utf8);
+        if self.reference(&context, method.descriptor_index, Expected::Utf8)
+            && MethodDescriptor::parse(descriptor).is_none()
+        {
+            self.problem(&context, format!("invalid descriptor {descriptor:?}"));
+        }
+
+        let code = method
+            .attributes
+            .iter()
+            .find_map(|attribute| match &attribute.info {
+                AttributeInfo::Code {
+                    code,
+                    exception_table,
+                    ..
+                } => Some((code, exception_table)),
+                _ => None,
+            });
+
+        let has_body = !method
+            .access_flags
+            .intersects(AccessFlags::Abstract | AccessFlags::Native);
+        match (code, has_body) {
+            (Some((code, exception_table)), true) => self.code(&context, code, exception_table),
+            (None, true) => self.problem(&context, "missing Code attribute".to_string()),
+            (Some(_), false) => self.problem(
+                &context,
+                "abstract and native methods cannot have Code attribute".to_string(),
+            ),
+            (None, false) => {}
+        }
+    }
+
+    fn code(&mut self, context: &str, code: &[(u32, Instruction)], exception_table: &[Exception]) {
+        if code.is_empty() {
+            self.problem(context, "code is empty".to_string());
+            return;
+        }
+
+        let code_length = match code.last() {
+            Some((offset, instruction)) => (offset + instruction.length(*offset)) as i64,
+            None => 0,
+        };
+        let is_instruction_start = |target: i64| {
+            code.binary_search_by_key(&target, |(offset, _)| *offset as i64)
+                .is_ok()
+        };
+
+        for (offset, instruction) in code {
+            let context = format!("{context}: instruction {offset}");
+
+            for target in instruction.branch_targets(*offset) {
+                if !is_instruction_start(target) {
+                    self.problem(
+                        &context,
+                        format!("branch target {target} is not the start of an instruction"),
+                    );
+                }
+            }
+
+            let expected = match instruction {
+                Instruction::Ldc(_) | Instruction::LdcW(_) => Expected::Loadable,
+                Instruction::Ldc2W(_) => Expected::Wide,
+                Instruction::GetStatic(_)
+                | Instruction::PutStatic(_)
+                | Instruction::GetField(_)
+                | Instruction::PutField(_) => Expected::FieldRef,
+                Instruction::InvokeVirtual(_) => Expected::MethodRef,
+                Instruction::InvokeSpecial(_) | Instruction::InvokeStatic(_) => {
+                    Expected::AnyMethodRef
+                }
+                Instruction::InvokeInterface(..) => Expected::InterfaceMethodRef,
+                Instruction::InvokeDynamic(_) => Expected::InvokeDynamic,
+                _ => Expected::Class,
+            };
+            if let Some(index) = instruction.constant_index() {
+                self.reference(&context, index, expected);
+            }
+        }
+
+        for (i, exception) in exception_table.iter().enumerate() {
+            let context = format!("{context}: exception table entry {i}");
+            let Exception {
+                start_pc,
+                end_pc,
+                handler_pc,
+                catch_type,
+            } = exception;
+
+            if start_pc >= end_pc {
+                self.problem(&context, format!("empty range {start_pc}..{end_pc}"));
+            }
+            if !is_instruction_start(*start_pc as i64) {
+                self.problem(&context, format!("start {start_pc} is not an instruction"));
+            }
+            if !is_instruction_start(*end_pc as i64) && *end_pc as i64 != code_length {
+                self.problem(&context, format!("end {end_pc} is not an instruction"));
+            }
+            if !is_instruction_start(*handler_pc as i64) {
+                self.problem(
+                    &context,
+                    format!("handler {handler_pc} is not an instruction"),
+                );
+            }
+            if *catch_type != 0 {
+                self.reference(&context, *catch_type, Expected::Class);
+            }
+        }
+    }
+}
@@ -0,0 +1,385 @@
+//! Reader of ZIP archives, the container of JAR and JMOD files. Supports what class
+//! files are stored with: stored and deflated entries, ZIP64 and data prepended to
+//! the archive (like the header of JMOD files).
+
+use crate::raw::invalid_data;
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::HashMap;
+use std::io::Result;
+use std::path::Path;
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR: u32 = 0x0706_4b50;
+const ZIP64_EXTRA: u16 = 0x0001;
+
+/// Compression methods of entries
+pub const STORED: u16 = 0;
+pub const DEFLATED: u16 = 8;
+
+#[derive(Debug, Clone)]
+pub struct ZipEntry {
+    /// Path inside the archive, directories end with `/`
+    pub name: String,
+    /// Compression method, 0 for stored and 8 for deflated entries
+    pub method: u16,
+    pub crc32: u32,
+    pub compressed_size: u64,
+    pub size: u64,
+    flags: u16,
+    /// Offset of the local header from the start of the archive
+    header_offset: u64,
+}
+
+impl ZipEntry {
+    pub fn is_directory(&self) -> bool {
+        self.name.ends_with('/')
+    }
+}
+
+/// Archive read into memory with its central directory parsed. Entries are
+/// decompressed only when read.
+pub struct ZipArchive {
+    bytes: Vec<u8>,
+    /// Position of the archive in `bytes`, past any prepended data
+    base: u64,
+    entries: Vec<ZipEntry>,
+    /// Positions in `entries` by name
+    names: HashMap<String, usize>,
+}
+
+impl ZipArchive {
+    pub fn open(path: &Path) -> Result<ZipArchive> {
+        ZipArchive::from_bytes(std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<ZipArchive> {
+        let end = find_end_of_central_directory(&bytes)?;
+        let mut count = u16_at(&bytes, end + 10)? as u64;
+        let mut directory_size = u32_at(&bytes, end + 12)? as u64;
+        let mut directory_offset = u32_at(&bytes, end + 16)? as u64;
+        // The central directory is followed by the ZIP64 record if there is one
+        let mut directory_end = end;
+
+        let zip64 =
+            count == 0xffff || directory_size == 0xffff_ffff || directory_offset == 0xffff_ffff;
+        if zip64 && end >= 20 && u32_at(&bytes, end - 20)? == ZIP64_LOCATOR {
+            let record = u64_at(&bytes, end - 20 + 8)?;
+            let record =
+                usize::try_from(record).map_err(|_| invalid_data("invalid ZIP64 record"))?;
+            if u32_at(&bytes, record)? != ZIP64_END_OF_CENTRAL_DIRECTORY {
+                return Err(invalid_data("ZIP64 end of central directory not found"));
+            }
+            count = u64_at(&bytes, record + 32)?;
+            directory_size = u64_at(&bytes, record + 40)?;
+            directory_offset = u64_at(&bytes, record + 48)?;
+            directory_end = record;
+        }
+
+        let base = (directory_end as u64)
+            .checked_sub(directory_offset + directory_size)
+            .ok_or_else(|| invalid_data("central directory lies outside of the file"))?;
+        let mut position = (base + directory_offset) as usize;
+        let mut entries = Vec::with_capacity(count.min(bytes.len() as u64 / 46) as usize);
+        for _ in 0..count {
+            let (entry, length) = central_header(&bytes, position)?;
+            entries.push(entry);
+            position += length;
+        }
+
+        let mut names = HashMap::with_capacity(entries.len());
+        for (i, entry) in entries.iter().enumerate() {
+            // Like the JDK, the first of duplicate entries wins
+            names.entry(entry.name.clone()).or_insert(i);
+        }
+        Ok(ZipArchive {
+            bytes,
+            base,
+            entries,
+            names,
+        })
+    }
+
+    /// Entries in the order of the central directory
+    pub fn entries(&self) -> &[ZipEntry] {
+        &self.entries
+    }
+
+    pub fn entry(&self, name: &str) -> Option<&ZipEntry> {
+        self.names.get(name).map(|&i| &self.entries[i])
+    }
+
+    /// Decompressed content of the entry, checked against its CRC-32
+    pub fn read(&self, entry: &ZipEntry) -> Result<Vec<u8>> {
+        let name = &entry.name;
+        if entry.flags & 1 != 0 {
+            return Err(invalid_data(format!("{name} is encrypted")));
+        }
+        let header = usize::try_from(self.base + entry.header_offset)
+            .map_err(|_| invalid_data(format!("{name} lies outside of the file")))?;
+        if u32_at(&self.bytes, header)? != LOCAL_HEADER {
+            return Err(invalid_data(format!("{name} has no local header")));
+        }
+        let name_length = u16_at(&self.bytes, header + 26)? as usize;
+        let extra_length = u16_at(&self.bytes, header + 28)? as usize;
+        let data = slice(
+            &self.bytes,
+            header + 30 + name_length + extra_length,
+            entry.compressed_size,
+        )?;
+
+        let content = match entry.method {
+            STORED => data.to_vec(),
+            DEFLATED => {
+                miniz_oxide::inflate::decompress_to_vec_with_limit(data, entry.size as usize)
+                    .map_err(|error| invalid_data(format!("{name} is corrupt: {error}")))?
+            }
+            method => {
+                return Err(invalid_data(format!(
+                    "{name} uses unsupported compression method {method}"
+                )))
+            }
+        };
+        if content.len() as u64 != entry.size || crc32(&content) != entry.crc32 {
+            return Err(invalid_data(format!(
+                "{name} is corrupt, CRC-32 does not match"
+            )));
+        }
+        Ok(content)
+    }
+
+    /// Decompressed content of the entry called `name`
+    pub fn read_entry(&self, name: &str) -> Result<Vec<u8>> {
+        match self.entry(name) {
+            Some(entry) => self.read(entry),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no entry {name}"),
+            )),
+        }
+    }
+}
+
+/// Position of the end of central directory record, searched from the end
+/// since it may be followed by a comment of up to 65535 bytes
+fn find_end_of_central_directory(bytes: &[u8]) -> Result<usize> {
+    let last = bytes
+        .len()
+        .checked_sub(22)
+        .ok_or_else(|| invalid_data("not a ZIP archive, file is too short"))?;
+    let first = last.saturating_sub(u16::MAX as usize);
+    (first..=last)
+        .rev()
+        .find(|&position| LittleEndian::read_u32(&bytes[position..]) == END_OF_CENTRAL_DIRECTORY)
+        .ok_or_else(|| invalid_data("not a ZIP archive, end of central directory not found"))
+}
+
+/// Entry of the central directory at `position` and the length of its record
+fn central_header(bytes: &[u8], position: usize) -> Result<(ZipEntry, usize)> {
+    if u32_at(bytes, position)? != CENTRAL_HEADER {
+        return Err(invalid_data(format!(
+            "central directory entry expected at {position}"
+        )));
+    }
+    let name_length = u16_at(bytes, position + 28)? as usize;
+    let extra_length = u16_at(bytes, position + 30)? as usize;
+    let comment_length = u16_at(bytes, position + 32)? as usize;
+    let name = slice(bytes, position + 46, name_length as u64)?;
+    let mut entry = ZipEntry {
+        name: String::from_utf8_lossy(name).into_owned(),
+        method: u16_at(bytes, position + 10)?,
+        crc32: u32_at(bytes, position + 16)?,
+        compressed_size: u32_at(bytes, position + 20)? as u64,
+        size: u32_at(bytes, position + 24)? as u64,
+        flags: u16_at(bytes, position + 8)?,
+        header_offset: u32_at(bytes, position + 42)? as u64,
+    };
+
+    // ZIP64 extra field holds the values that did not fit, in this order
+    let mut extra = slice(bytes, position + 46 + name_length, extra_length as u64)?;
+    while extra.len() >= 4 {
+        let id = LittleEndian::read_u16(extra);
+        let length = (LittleEndian::read_u16(&extra[2..]) as usize).min(extra.len() - 4);
+        if id == ZIP64_EXTRA {
+            let mut values = extra[4..4 + length]
+                .chunks_exact(8)
+                .map(LittleEndian::read_u64);
+            for field in [
+                &mut entry.size,
+                &mut entry.compressed_size,
+                &mut entry.header_offset,
+            ] {
+                if *field == 0xffff_ffff {
+                    *field = values.next().ok_or_else(|| {
+                        invalid_data(format!("{} has incomplete ZIP64 extra field", entry.name))
+                    })?;
+                }
+            }
+        }
+        extra = &extra[4 + length..];
+    }
+
+    Ok((entry, 46 + name_length + extra_length + comment_length))
+}
+
+fn slice(bytes: &[u8], start: usize, length: u64) -> Result<&[u8]> {
+    usize::try_from(length)
+        .ok()
+        .and_then(|length| bytes.get(start..start.checked_add(length)?))
+        .ok_or_else(|| invalid_data("ZIP archive is truncated"))
+}
+
+fn u16_at(bytes: &[u8], position: usize) -> Result<u16> {
+    slice(bytes, position, 2).map(LittleEndian::read_u16)
+}
+
+fn u32_at(bytes: &[u8], position: usize) -> Result<u32> {
+    slice(bytes, position, 4).map(LittleEndian::read_u32)
+}
+
+fn u64_at(bytes: &[u8], position: usize) -> Result<u64> {
+    slice(bytes, position, 8).map(LittleEndian::read_u64)
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, byte| {
+        CRC32_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Bit of the flags telling that sizes and CRC-32 follow the data
+    const DATA_DESCRIPTOR: u16 = 0x08;
+
+    /// Archive of the entries, each given with its compression method and flags
+    pub(crate) fn archive(entries: &[(&str, u16, u16, &[u8])]) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut directory = vec![];
+        for &(name, method, flags, content) in entries {
+            let data = match method {
+                DEFLATED => miniz_oxide::deflate::compress_to_vec(content, 6),
+                _ => content.to_vec(),
+            };
+            let crc = crc32(content);
+            let sizes = [crc, data.len() as u32, content.len() as u32];
+            let offset = bytes.len() as u32;
+
+            bytes.extend_from_slice(&LOCAL_HEADER.to_le_bytes());
+            for value in [20, flags, method, 0, 0] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            for value in match flags & DATA_DESCRIPTOR {
+                0 => sizes,
+                _ => [0; 3],
+            } {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            bytes.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(&0u16.to_le_bytes());
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.extend_from_slice(&data);
+            if flags & DATA_DESCRIPTOR != 0 {
+                bytes.extend_from_slice(&0x0807_4b50u32.to_le_bytes());
+                for value in sizes {
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+
+            directory.extend_from_slice(&CENTRAL_HEADER.to_le_bytes());
+            for value in [20, 20, flags, method, 0, 0] {
+                directory.extend_from_slice(&value.to_le_bytes());
+            }
+            for value in sizes {
+                directory.extend_from_slice(&value.to_le_bytes());
+            }
+            for value in [name.len() as u16, 0, 0, 0, 0] {
+                directory.extend_from_slice(&value.to_le_bytes());
+            }
+            directory.extend_from_slice(&0u32.to_le_bytes());
+            directory.extend_from_slice(&offset.to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+        }
+
+        let offset = bytes.len() as u32;
+        bytes.extend_from_slice(&directory);
+        bytes.extend_from_slice(&END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        let count = entries.len() as u16;
+        for value in [0, 0, count, count] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&offset.to_le_bytes());
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn stored_and_deflated_entries() {
+        let text = "class files compress well, class files compress well".repeat(10);
+        let archive = ZipArchive::from_bytes(archive(&[
+            ("META-INF/", STORED, 0, b""),
+            ("stored.txt", STORED, 0, b"stored as is"),
+            ("deflated.txt", DEFLATED, 0, text.as_bytes()),
+            (
+                "streamed.txt",
+                DEFLATED,
+                DATA_DESCRIPTOR,
+                b"sizes come last",
+            ),
+        ]))
+        .unwrap();
+
+        let names: Vec<&str> = archive.entries().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["META-INF/", "stored.txt", "deflated.txt", "streamed.txt"]
+        );
+        assert!(archive.entry("META-INF/").unwrap().is_directory());
+        assert_eq!(archive.read_entry("stored.txt").unwrap(), b"stored as is");
+        let deflated = archive.entry("deflated.txt").unwrap();
+        assert!(deflated.compressed_size < deflated.size);
+        assert_eq!(archive.read(deflated).unwrap(), text.as_bytes());
+        assert_eq!(
+            archive.read_entry("streamed.txt").unwrap(),
+            b"sizes come last"
+        );
+        assert!(archive.read_entry("missing.txt").is_err());
+    }
+
+    #[test]
+    fn corrupt_entry() {
+        let mut bytes = archive(&[("a.txt", STORED, 0, b"content")]);
+        let position = bytes.windows(7).position(|w| w == b"content").unwrap();
+        bytes[position] = b'C';
+        let error = ZipArchive::from_bytes(bytes)
+            .unwrap()
+            .read_entry("a.txt")
+            .unwrap_err();
+        assert!(error.to_string().contains("CRC-32"), "{error}");
+    }
+}